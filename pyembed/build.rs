@@ -38,4 +38,11 @@ fn main() {
         "cargo:rustc-env=PYTHON_INTERPRETER_PATH={}",
         python_interpreter
     );
+
+    // Expose the Python version as `Py_3_X` cfgs, like PyO3 does. Subinterpreters
+    // use APIs which differ between Python versions.
+    pyo3_build_config::use_pyo3_cfgs();
+    println!("cargo:rustc-check-cfg=cfg(Py_3_9)");
+    println!("cargo:rustc-check-cfg=cfg(Py_3_11)");
+    println!("cargo:rustc-check-cfg=cfg(Py_3_12)");
 }
@@ -0,0 +1,138 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+#ifndef PYEMBED_H
+#define PYEMBED_H
+
+/* Generated by cbindgen from src/capi.rs. Do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// An interpreter config.
+//
+// Wraps an `OxidizedPythonInterpreterConfig`.
+typedef struct PyembedConfig PyembedConfig;
+
+// An initialized interpreter.
+//
+// Wraps a `MainPythonInterpreter`.
+typedef struct PyembedInterpreter PyembedInterpreter;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Describe the last error on the current thread.
+//
+// Returns `NULL` if no error occurred. The string is owned by pyembed and
+// remains valid until the next failing call on the current thread.
+const char *pyembed_last_error(void);
+
+// Create an interpreter config with default values.
+//
+// The config must be released with `pyembed_config_free()` or passed to
+// `pyembed_interpreter_new()`.
+struct PyembedConfig *pyembed_config_new(void);
+
+// Create an interpreter config from JSON.
+//
+// The JSON object has the fields of `OxidizedPythonInterpreterConfig`.
+// Missing fields have their default values. Returns `NULL` on error.
+//
+// # Safety
+//
+// `json` must be a NUL-terminated string.
+struct PyembedConfig *pyembed_config_from_json(const char *json);
+
+// Release an interpreter config.
+//
+// # Safety
+//
+// `config` must be `NULL` or a config not yet released or passed to
+// `pyembed_interpreter_new()`.
+void pyembed_config_free(struct PyembedConfig *config);
+
+// Add packed resources data in memory to an interpreter config.
+//
+// Returns 0 on success and -1 on error.
+//
+// # Safety
+//
+// `config` must be a valid config. `data` must point to `length` bytes
+// which remain valid and unmodified until the interpreter is finalized.
+int pyembed_config_add_packed_resources(struct PyembedConfig *config,
+                                        const uint8_t *data,
+                                        size_t length);
+
+// Add packed resources in a file to an interpreter config.
+//
+// The file is memory mapped when the interpreter is initialized.
+//
+// Returns 0 on success and -1 on error.
+//
+// # Safety
+//
+// `config` must be a valid config. `path` must be a NUL-terminated string.
+int pyembed_config_add_packed_resources_path(struct PyembedConfig *config, const char *path);
+
+// Set the arguments of the interpreter, as passed to `main()`.
+//
+// If not set, the arguments of the current process are used.
+//
+// Returns 0 on success and -1 on error.
+//
+// # Safety
+//
+// `config` must be a valid config. `argv` must point to `argc`
+// NUL-terminated strings.
+int pyembed_config_set_argv(struct PyembedConfig *config, int argc, const char *const *argv);
+
+// Initialize an interpreter from a config.
+//
+// The config is consumed, even on error. Returns `NULL` on error.
+//
+// Only one interpreter can exist in a process at a time. The interpreter
+// is finalized by `pyembed_interpreter_run()` or `pyembed_interpreter_free()`.
+//
+// # Safety
+//
+// `config` must be a config not yet released or passed to this function.
+struct PyembedInterpreter *pyembed_interpreter_new(struct PyembedConfig *config);
+
+// Execute Python source code in the `__main__` module of an interpreter.
+//
+// Returns 0 on success. If the code raises an exception, the exception is
+// printed to `sys.stderr` and -1 is returned.
+//
+// # Safety
+//
+// `interpreter` must be a valid interpreter. `code` must be a
+// NUL-terminated string.
+int pyembed_interpreter_exec(struct PyembedInterpreter *interpreter, const char *code);
+
+// Run an interpreter and finalize it.
+//
+// This behaves like `MainPythonInterpreter::run()`: it runs the configured
+// command, file or module, or the REPL. Returns the exit code.
+//
+// The interpreter is consumed.
+//
+// # Safety
+//
+// `interpreter` must be an interpreter not yet released.
+int pyembed_interpreter_run(struct PyembedInterpreter *interpreter);
+
+// Finalize an interpreter without running it.
+//
+// # Safety
+//
+// `interpreter` must be `NULL` or an interpreter not yet released.
+void pyembed_interpreter_free(struct PyembedInterpreter *interpreter);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* PYEMBED_H */
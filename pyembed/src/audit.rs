@@ -0,0 +1,142 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Forwarding of Python audit events to Rust.
+
+Python raises audit events (PEP 578) for security-relevant operations such
+as imports, opening files and spawning processes. An audit hook registered
+with `PySys_AddAuditHook()` passes them to an [AuditCallback].
+*/
+
+use {
+    crate::config::AuditCallback,
+    pyo3::ffi as pyffi,
+    std::{
+        cell::Cell,
+        ffi::{c_void, CStr},
+        os::raw::{c_char, c_int},
+        panic::{catch_unwind, AssertUnwindSafe},
+    },
+};
+
+type AuditHookFunction =
+    unsafe extern "C" fn(*const c_char, *mut pyffi::PyObject, *mut c_void) -> c_int;
+
+extern "C" {
+    fn PySys_AddAuditHook(hook: AuditHookFunction, user_data: *mut c_void) -> c_int;
+}
+
+thread_local! {
+    /// Whether the current thread is running the audit hook.
+    ///
+    /// Rendering arguments can run Python code raising further events,
+    /// which are ignored.
+    static IN_AUDIT_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// State of an installed audit hook.
+pub(crate) struct AuditHook {
+    callback: AuditCallback,
+    events: Vec<String>,
+}
+
+impl AuditHook {
+    pub fn new(callback: AuditCallback, events: Vec<String>) -> Self {
+        Self { callback, events }
+    }
+
+    /// Whether an event should be passed to the callback.
+    ///
+    /// An empty filter passes all events. Otherwise an event passes if it
+    /// equals a filter entry or starts with the entry followed by `.`.
+    fn wants(&self, event: &str) -> bool {
+        self.events.is_empty()
+            || self.events.iter().any(|prefix| {
+                matches!(
+                    event.strip_prefix(prefix.as_str()),
+                    Some(rest) if rest.is_empty() || rest.starts_with('.')
+                )
+            })
+    }
+
+    /// Register the hook with Python.
+    ///
+    /// Hooks can't be removed. `self` must outlive the Python runtime, which
+    /// clears hooks during finalization.
+    pub unsafe fn install(&self) -> Result<(), &'static str> {
+        if PySys_AddAuditHook(audit_hook, self as *const Self as *mut c_void) == 0 {
+            Ok(())
+        } else {
+            pyffi::PyErr_Clear();
+            Err("unable to add audit hook")
+        }
+    }
+}
+
+/// Render the arguments of an audit event via `repr()`.
+unsafe fn render_args(args: *mut pyffi::PyObject) -> String {
+    let repr = pyffi::PyObject_Repr(args);
+    if repr.is_null() {
+        pyffi::PyErr_Clear();
+        return "<unrepresentable>".to_string();
+    }
+
+    let mut size = 0;
+    let data = pyffi::PyUnicode_AsUTF8AndSize(repr, &mut size);
+    let rendered = if data.is_null() {
+        pyffi::PyErr_Clear();
+        "<unrepresentable>".to_string()
+    } else {
+        String::from_utf8_lossy(std::slice::from_raw_parts(data as *const u8, size as usize))
+            .to_string()
+    };
+
+    pyffi::Py_DECREF(repr);
+
+    rendered
+}
+
+/// The function registered with `PySys_AddAuditHook()`.
+///
+/// Never fails, so it can't abort the audited operation.
+unsafe extern "C" fn audit_hook(
+    event: *const c_char,
+    args: *mut pyffi::PyObject,
+    user_data: *mut c_void,
+) -> c_int {
+    if IN_AUDIT_HOOK.with(|cell| cell.replace(true)) {
+        return 0;
+    }
+
+    let hook = &*(user_data as *const AuditHook);
+    let event = CStr::from_ptr(event).to_string_lossy();
+
+    if hook.wants(&event) {
+        let rendered = render_args(args);
+
+        // Unwinding into C is undefined behavior.
+        let _ = catch_unwind(AssertUnwindSafe(|| (hook.callback)(&event, &rendered)));
+    }
+
+    IN_AUDIT_HOOK.with(|cell| cell.set(false));
+
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wants() {
+        let hook = AuditHook::new(|_, _| {}, vec![]);
+        assert!(hook.wants("open"));
+
+        let hook = AuditHook::new(|_, _| {}, vec!["import".into(), "subprocess".into()]);
+        assert!(hook.wants("import"));
+        assert!(hook.wants("subprocess.Popen"));
+        assert!(!hook.wants("open"));
+        assert!(!hook.wants("importlib.import_module"));
+    }
+}
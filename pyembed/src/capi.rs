@@ -0,0 +1,411 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! A C API for embedding an interpreter.
+
+This module exposes `extern "C"` functions for constructing an interpreter
+config, initializing an interpreter from it, running it and finalizing it.
+`include/pyembed.h` declares these functions. It is generated with `cbindgen`
+by `cargo run --bin release -- synchronize-generated-files`.
+
+Configs and interpreters are opaque pointers owned by the caller. Functions
+signal failure by returning `NULL` or a negative value, after which
+[pyembed_last_error()] describes the error.
+*/
+
+use {
+    crate::{MainPythonInterpreter, OxidizedPythonInterpreterConfig, PackedResourcesSource},
+    std::{
+        cell::RefCell,
+        ffi::{CStr, CString, OsString},
+        os::raw::{c_char, c_int},
+        panic::{catch_unwind, AssertUnwindSafe},
+        path::PathBuf,
+    },
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+fn set_last_error(message: impl std::fmt::Display) {
+    // Interior NULs would truncate the message. So replace them.
+    let message = message.to_string().replace('\0', "\\0");
+
+    LAST_ERROR.with(|e| {
+        *e.borrow_mut() = Some(CString::new(message).expect("NULs are replaced"));
+    });
+}
+
+/// Run a function, recording its error and returning `failure` if it fails.
+///
+/// Panics are treated as errors, as unwinding into C is undefined behavior.
+/// Objects a panic may leave in an inconsistent state aren't reachable from
+/// Rust afterwards. So the function is asserted to be unwind safe.
+fn guard<T>(failure: T, f: impl FnOnce() -> Result<T, String>) -> T {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(message)) => {
+            set_last_error(message);
+            failure
+        }
+        Err(_) => {
+            set_last_error("panic in pyembed");
+            failure
+        }
+    }
+}
+
+/// Convert a C string to a Rust string.
+unsafe fn c_str<'a>(value: *const c_char, name: &str) -> Result<&'a str, String> {
+    if value.is_null() {
+        return Err(format!("{} is NULL", name));
+    }
+
+    CStr::from_ptr(value)
+        .to_str()
+        .map_err(|e| format!("{} is not valid UTF-8: {}", name, e))
+}
+
+/// Convert a C string to an [OsString].
+///
+/// On POSIX platforms, bytes are used as-is. Elsewhere, they must be UTF-8.
+unsafe fn c_os_string(value: *const c_char, name: &str) -> Result<OsString, String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+
+        if value.is_null() {
+            return Err(format!("{} is NULL", name));
+        }
+
+        Ok(OsString::from_vec(
+            CStr::from_ptr(value).to_bytes().to_vec(),
+        ))
+    }
+
+    #[cfg(not(unix))]
+    {
+        Ok(OsString::from(c_str(value, name)?))
+    }
+}
+
+/// An interpreter config.
+///
+/// Wraps an `OxidizedPythonInterpreterConfig`.
+pub struct PyembedConfig(OxidizedPythonInterpreterConfig<'static>);
+
+/// An initialized interpreter.
+///
+/// Wraps a `MainPythonInterpreter`.
+pub struct PyembedInterpreter(MainPythonInterpreter<'static, 'static>);
+
+/// Describe the last error on the current thread.
+///
+/// Returns `NULL` if no error occurred. The string is owned by pyembed and
+/// remains valid until the next failing call on the current thread.
+#[no_mangle]
+pub extern "C" fn pyembed_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| {
+        e.borrow()
+            .as_ref()
+            .map(|message| message.as_ptr())
+            .unwrap_or(std::ptr::null())
+    })
+}
+
+/// Create an interpreter config with default values.
+///
+/// The config must be released with `pyembed_config_free()` or passed to
+/// `pyembed_interpreter_new()`.
+#[no_mangle]
+pub extern "C" fn pyembed_config_new() -> *mut PyembedConfig {
+    Box::into_raw(Box::new(PyembedConfig(
+        OxidizedPythonInterpreterConfig::default(),
+    )))
+}
+
+/// Create an interpreter config from JSON.
+///
+/// The JSON object has the fields of `OxidizedPythonInterpreterConfig`.
+/// Missing fields have their default values. Returns `NULL` on error.
+///
+/// # Safety
+///
+/// `json` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_from_json(json: *const c_char) -> *mut PyembedConfig {
+    guard(std::ptr::null_mut(), || {
+        let json = c_str(json, "json")?;
+
+        let config = serde_json::from_str::<OxidizedPythonInterpreterConfig>(json)
+            .map_err(|e| format!("error parsing config: {}", e))?;
+
+        Ok(Box::into_raw(Box::new(PyembedConfig(config))))
+    })
+}
+
+/// Release an interpreter config.
+///
+/// # Safety
+///
+/// `config` must be `NULL` or a config not yet released or passed to
+/// `pyembed_interpreter_new()`.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_free(config: *mut PyembedConfig) {
+    if !config.is_null() {
+        drop(Box::from_raw(config));
+    }
+}
+
+/// Add packed resources data in memory to an interpreter config.
+///
+/// Returns 0 on success and -1 on error.
+///
+/// # Safety
+///
+/// `config` must be a valid config. `data` must point to `length` bytes
+/// which remain valid and unmodified until the interpreter is finalized.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_add_packed_resources(
+    config: *mut PyembedConfig,
+    data: *const u8,
+    length: usize,
+) -> c_int {
+    guard(-1, || {
+        let config = config.as_mut().ok_or("config is NULL")?;
+
+        if data.is_null() {
+            return Err("data is NULL".to_string());
+        }
+
+        config
+            .0
+            .packed_resources
+            .push(PackedResourcesSource::Memory(std::slice::from_raw_parts(
+                data, length,
+            )));
+
+        Ok(0)
+    })
+}
+
+/// Add packed resources in a file to an interpreter config.
+///
+/// The file is memory mapped when the interpreter is initialized.
+///
+/// Returns 0 on success and -1 on error.
+///
+/// # Safety
+///
+/// `config` must be a valid config. `path` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_add_packed_resources_path(
+    config: *mut PyembedConfig,
+    path: *const c_char,
+) -> c_int {
+    guard(-1, || {
+        let config = config.as_mut().ok_or("config is NULL")?;
+        let path = PathBuf::from(c_os_string(path, "path")?);
+
+        config
+            .0
+            .packed_resources
+            .push(PackedResourcesSource::MemoryMappedPath(path));
+
+        Ok(0)
+    })
+}
+
+/// Set the arguments of the interpreter, as passed to `main()`.
+///
+/// If not set, the arguments of the current process are used.
+///
+/// Returns 0 on success and -1 on error.
+///
+/// # Safety
+///
+/// `config` must be a valid config. `argv` must point to `argc`
+/// NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_config_set_argv(
+    config: *mut PyembedConfig,
+    argc: c_int,
+    argv: *const *const c_char,
+) -> c_int {
+    guard(-1, || {
+        let config = config.as_mut().ok_or("config is NULL")?;
+        let argc = usize::try_from(argc).map_err(|_| "argc is negative")?;
+
+        if argv.is_null() && argc > 0 {
+            return Err("argv is NULL".to_string());
+        }
+
+        config.0.argv = Some(
+            (0..argc)
+                .map(|i| c_os_string(*argv.add(i), "argv entry"))
+                .collect::<Result<Vec<_>, _>>()?,
+        );
+
+        Ok(0)
+    })
+}
+
+/// Initialize an interpreter from a config.
+///
+/// The config is consumed, even on error. Returns `NULL` on error.
+///
+/// Only one interpreter can exist in a process at a time. The interpreter
+/// is finalized by `pyembed_interpreter_run()` or `pyembed_interpreter_free()`.
+///
+/// # Safety
+///
+/// `config` must be a config not yet released or passed to this function.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_interpreter_new(
+    config: *mut PyembedConfig,
+) -> *mut PyembedInterpreter {
+    if config.is_null() {
+        set_last_error("config is NULL");
+        return std::ptr::null_mut();
+    }
+
+    let config = Box::from_raw(config);
+
+    guard(std::ptr::null_mut(), move || {
+        let interpreter = MainPythonInterpreter::new(config.0)
+            .map_err(|e| format!("error initializing interpreter: {}", e))?;
+
+        Ok(Box::into_raw(Box::new(PyembedInterpreter(interpreter))))
+    })
+}
+
+/// Execute Python source code in the `__main__` module of an interpreter.
+///
+/// Returns 0 on success. If the code raises an exception, the exception is
+/// printed to `sys.stderr` and -1 is returned.
+///
+/// # Safety
+///
+/// `interpreter` must be a valid interpreter. `code` must be a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_interpreter_exec(
+    interpreter: *mut PyembedInterpreter,
+    code: *const c_char,
+) -> c_int {
+    let interpreter = match interpreter.as_ref() {
+        Some(interpreter) => interpreter,
+        None => {
+            set_last_error("interpreter is NULL");
+            return -1;
+        }
+    };
+
+    guard(-1, || {
+        let code = c_str(code, "code")?;
+
+        interpreter.0.with_gil(|py| {
+            let main = py.import("__main__").map_err(|e| e.to_string())?;
+
+            py.run(code, Some(main.dict()), None).map_err(|e| {
+                let message = e.to_string();
+                e.print(py);
+                message
+            })?;
+
+            Ok(0)
+        })
+    })
+}
+
+/// Run an interpreter and finalize it.
+///
+/// This behaves like `MainPythonInterpreter::run()`: it runs the configured
+/// command, file or module, or the REPL. Returns the exit code.
+///
+/// The interpreter is consumed.
+///
+/// # Safety
+///
+/// `interpreter` must be an interpreter not yet released.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_interpreter_run(interpreter: *mut PyembedInterpreter) -> c_int {
+    if interpreter.is_null() {
+        set_last_error("interpreter is NULL");
+        return -1;
+    }
+
+    let interpreter = Box::from_raw(interpreter);
+
+    guard(-1, move || Ok(interpreter.0.run()))
+}
+
+/// Finalize an interpreter without running it.
+///
+/// # Safety
+///
+/// `interpreter` must be `NULL` or an interpreter not yet released.
+#[no_mangle]
+pub unsafe extern "C" fn pyembed_interpreter_free(interpreter: *mut PyembedInterpreter) {
+    if !interpreter.is_null() {
+        drop(Box::from_raw(interpreter));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(pyembed_last_error()) }
+            .to_string_lossy()
+            .to_string()
+    }
+
+    #[test]
+    fn test_config_from_json() {
+        unsafe {
+            let config =
+                pyembed_config_from_json(b"{\"filesystem_importer\": true}\0".as_ptr().cast());
+            assert!(!config.is_null());
+            assert!((*config).0.filesystem_importer);
+            pyembed_config_free(config);
+
+            assert!(pyembed_config_from_json(b"{\"unknown\"\0".as_ptr().cast()).is_null());
+            assert!(last_error().starts_with("error parsing config"));
+
+            assert!(pyembed_config_from_json(std::ptr::null()).is_null());
+            assert_eq!(last_error(), "json is NULL");
+        }
+    }
+
+    #[test]
+    fn test_config_set_argv() {
+        unsafe {
+            let config = pyembed_config_new();
+            let argv = [
+                b"prog\0".as_ptr().cast::<c_char>(),
+                b"--flag\0".as_ptr().cast(),
+            ];
+
+            assert_eq!(pyembed_config_set_argv(config, 2, argv.as_ptr()), 0);
+            assert_eq!(
+                (*config).0.argv,
+                Some(vec![OsString::from("prog"), OsString::from("--flag")])
+            );
+
+            assert_eq!(pyembed_config_set_argv(config, -1, argv.as_ptr()), -1);
+            assert_eq!(last_error(), "argc is negative");
+
+            assert_eq!(
+                pyembed_config_add_packed_resources(config, std::ptr::null(), 0),
+                -1
+            );
+            assert_eq!(last_error(), "data is NULL");
+
+            pyembed_config_free(config);
+        }
+    }
+}
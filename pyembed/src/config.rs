@@ -5,17 +5,20 @@
 //! Data structures for configuring a Python interpreter.
 
 use {
-    crate::NewInterpreterError,
+    crate::{config_overrides, NewInterpreterError},
     oxidized_importer::{PackedResourcesSource, PythonResourcesState},
-    pyo3::ffi as pyffi,
+    pyo3::{ffi as pyffi, PyResult, Python},
     python_packaging::interpreter::{
-        MemoryAllocatorBackend, MultiprocessingStartMethod, PythonInterpreterConfig,
-        PythonInterpreterProfile, TerminfoResolution,
+        BuildInfo, HostRequirements, LogDestination, MemoryAllocatorBackend, Migration,
+        MultiprocessingStartMethod, PackedResourcesVerification, ProgramPath,
+        PythonInterpreterConfig, PythonInterpreterProfile, SandboxPolicy, TerminfoResolution,
     },
+    python_packed_resources::Resource,
     std::{
-        ffi::{CString, OsString},
+        env,
+        ffi::{CString, OsStr, OsString},
         ops::Deref,
-        path::PathBuf,
+        path::{Path, PathBuf},
     },
 };
 
@@ -35,6 +38,60 @@ pub struct ExtensionModule {
     pub init_func: unsafe extern "C" fn() -> *mut pyffi::PyObject,
 }
 
+impl ExtensionModule {
+    /// Construct an instance from a module name and its initialization function.
+    ///
+    /// With PyO3, the initialization function of a `#[pymodule] fn foo` is `foo::init`.
+    ///
+    /// Panics if `name` contains a NUL byte.
+    pub fn new(name: &str, init_func: unsafe extern "C" fn() -> *mut pyffi::PyObject) -> Self {
+        Self {
+            name: CString::new(name).expect("extension module name should not contain NUL"),
+            init_func,
+        }
+    }
+}
+
+/// A function called when an interpreter is initialized.
+///
+/// See [OxidizedPythonInterpreterConfig::startup_hooks].
+pub type StartupHook = fn(Python) -> PyResult<()>;
+
+/// A function receiving data written to a standard stream.
+///
+/// See [OxidizedPythonInterpreterConfig::stdout_callback].
+pub type StdioCallback = fn(&[u8]);
+
+/// A function receiving Python audit events.
+///
+/// Receives the event name and the `repr()` of the event arguments.
+///
+/// See [OxidizedPythonInterpreterConfig::audit_callback].
+pub type AuditCallback = fn(&str, &str);
+
+/// Defines the key used to decrypt encrypted packed resources data.
+#[derive(Clone, Debug)]
+pub enum PackedResourcesKey {
+    /// A 32 byte AES-256 key.
+    Key([u8; 32]),
+
+    /// A function called to obtain the key.
+    ///
+    /// Use this to derive the key at run-time instead of storing it. The function
+    /// is called when the interpreter is initialized.
+    Callback(fn() -> Result<[u8; 32], String>),
+}
+
+impl PackedResourcesKey {
+    /// Obtain the key.
+    pub fn resolve(&self) -> Result<[u8; 32], String> {
+        match self {
+            Self::Key(key) => Ok(*key),
+            Self::Callback(f) => f(),
+        }
+    }
+}
+
 /// Configuration for a Python interpreter.
 ///
 /// This type is used to create a [crate::MainPythonInterpreter], which manages
@@ -189,6 +246,19 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// (see other `allocator_*` fields).
     pub allocator_debug: bool,
 
+    /// Whether to collect statistics of allocations by the custom allocator.
+    ///
+    /// Default value: [false]
+    ///
+    /// Interpreter initialization behavior: if [Self::allocator_backend]
+    /// resolves to a custom allocator, it counts allocations and the bytes they
+    /// hold from the time it is installed. Statistics can be queried with
+    /// [crate::allocator_stats()] and from Python via
+    /// `oxidized_importer.allocator.stats()`.
+    ///
+    /// Collecting statistics adds a small cost to every allocation.
+    pub allocator_stats: bool,
+
     /// Whether to automatically set missing "path configuration" fields.
     ///
     /// If `true`, various path configuration
@@ -268,6 +338,58 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// [`sys.path_hooks`]: https://docs.python.org/3/library/sys.html#sys.path_hooks
     pub filesystem_importer: bool,
 
+    /// Whether to retain importing from zip files when the path-based finder is disabled.
+    ///
+    /// When [Self::filesystem_importer] is [false], the standard library
+    /// [`zipimport`] importer is removed along with the rest of the path-based
+    /// finder. Setting this to [true] keeps it, so `OxidizedFinder` services
+    /// imports first and zip files on `sys.path` are used as a fallback.
+    ///
+    /// Default value: [false]
+    ///
+    /// Interpreter initialization behavior: If [true] and
+    /// [Self::filesystem_importer] is [false], `PathFinder` remains on
+    /// `sys.meta_path` after `OxidizedFinder`, `zipimport.zipimporter` remains
+    /// on `sys.path_hooks`, and `sys.path_importer_cache` is cleared. Has no
+    /// effect if [Self::filesystem_importer] is [true], as `zipimport` is
+    /// already installed.
+    ///
+    /// [`zipimport`]: https://docs.python.org/3/library/zipimport.html
+    pub zipimport_importer: bool,
+
+    /// Directories to append to `sys.path`.
+    ///
+    /// This provides a controlled location for plugins, such as
+    /// `$ORIGIN/plugins`, without honoring `PYTHONPATH` or the rest of the
+    /// environment.
+    ///
+    /// Default value: `vec![]`
+    ///
+    /// [Self::resolve()] behavior: the token `$ORIGIN` is expanded to the
+    /// resolved value of [Self::origin].
+    ///
+    /// Interpreter initialization behavior: the directories, followed by those
+    /// from [Self::extra_sys_paths_env], are appended to `sys.path` after the
+    /// interpreter is initialized. If [Self::filesystem_importer] is [false],
+    /// `PathFinder` remains on `sys.meta_path` after `OxidizedFinder` and a path
+    /// hook only accepting these directories and their subdirectories is added to
+    /// `sys.path_hooks`. So modules can be imported from the filesystem in these
+    /// directories and nowhere else.
+    pub extra_sys_paths: Vec<PathBuf>,
+
+    /// Environment variable holding directories to append to `sys.path`.
+    ///
+    /// If this value is set and the environment variable it refers to is set,
+    /// its value is split into directories using the platform's path separator
+    /// (`;` on Windows, `:` elsewhere). These are handled like
+    /// [Self::extra_sys_paths]. Empty entries are ignored.
+    ///
+    /// Use an application-specific name, such as `MYAPP_PLUGINS_PATH`. Unlike
+    /// `PYTHONPATH`, it won't affect other Python applications.
+    ///
+    /// Default value: [None]
+    pub extra_sys_paths_env: Option<String>,
+
     /// References to packed resources data.
     ///
     /// The format of the data is defined by the ``python-packed-resources``
@@ -282,7 +404,8 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     ///
     /// Default value: `vec![]`
     ///
-    /// [Self::resolve()] behavior: [PackedResourcesSource::MemoryMappedPath] members
+    /// [Self::resolve()] behavior: [PackedResourcesSource::MemoryMappedPath] and
+    /// [PackedResourcesSource::MemoryMappedPathVerified] members
     /// have the special string `$ORIGIN` expanded to the string value that
     /// [Self::origin] resolves to.
     ///
@@ -290,6 +413,32 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     #[cfg_attr(feature = "serialization", serde(skip))]
     pub packed_resources: Vec<PackedResourcesSource<'a>>,
 
+    /// Key used to decrypt encrypted packed resources data.
+    ///
+    /// Encrypted entries in [Self::packed_resources] can only be loaded if this
+    /// is set.
+    ///
+    /// Default value: [None]
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub packed_resources_key: Option<PackedResourcesKey>,
+
+    /// When content digests of packed resources are verified.
+    ///
+    /// Verification detects corrupted or modified resources data. Only
+    /// version 4 packed resources data has content digests.
+    ///
+    /// Default value: [PackedResourcesVerification::None]
+    ///
+    /// Interpreter initialization behavior: with
+    /// [PackedResourcesVerification::Startup], interpreter initialization fails if
+    /// the digest of any resource in [Self::packed_resources] doesn't match or is
+    /// missing. With [PackedResourcesVerification::OnLoad], a resource is verified
+    /// the first time it is imported or its data is read and the import or read
+    /// fails on mismatch.
+    pub packed_resources_verification: PackedResourcesVerification,
+
     /// Extra extension modules to make available to the interpreter.
     ///
     /// The values will effectively be passed to ``PyImport_ExtendInitTab()``.
@@ -304,6 +453,132 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     #[cfg_attr(feature = "serialization", serde(skip))]
     pub extra_extension_modules: Option<Vec<ExtensionModule>>,
 
+    /// Functions to call once the interpreter is initialized.
+    ///
+    /// Hooks allow the embedding application to prepare the interpreter before
+    /// any Python code it runs. e.g. to import a module registered via
+    /// [Self::extra_extension_modules] and store state in it.
+    ///
+    /// Default value: `vec![]`
+    ///
+    /// Interpreter initialization behavior: hooks are called in order with the
+    /// GIL held after the interpreter is fully initialized and before
+    /// [PythonInterpreterConfig::run_command] or similar code is run. If a hook
+    /// returns an error, interpreter initialization fails.
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub startup_hooks: Vec<StartupHook>,
+
+    /// Function receiving data written to standard output.
+    ///
+    /// This allows applications without a console, such as Windows GUI
+    /// applications, to capture output of Python code.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if set, `sys.stdout` is replaced
+    /// by a line buffered text stream passing written data to the callback,
+    /// unless [Self::stdio_callback_fds] is set. The encoding and error
+    /// handler of the original stream are preserved. If `sys.stdout` is
+    /// `None`, UTF-8 is used. The callback is called without the GIL held.
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub stdout_callback: Option<StdioCallback>,
+
+    /// Function receiving data written to standard error.
+    ///
+    /// Like [Self::stdout_callback] but for `sys.stderr`. If `sys.stderr` is
+    /// `None`, UTF-8 with the `backslashreplace` error handler is used.
+    ///
+    /// Default value: [None]
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub stderr_callback: Option<StdioCallback>,
+
+    /// Whether to redirect the stdout and stderr file descriptors to callbacks.
+    ///
+    /// This also captures output of C code, Rust code, and child processes
+    /// inheriting the file descriptors.
+    ///
+    /// Default value: `false`
+    ///
+    /// Interpreter initialization behavior: if `true`, file descriptors 1
+    /// and 2 are replaced by pipes before the interpreter is initialized for
+    /// each of [Self::stdout_callback] and [Self::stderr_callback] that is set.
+    /// Background threads pass data read from the pipes to the callbacks. The
+    /// original file descriptors are restored when the interpreter is
+    /// finalized. Python's standard streams are not replaced and write to
+    /// the pipes.
+    pub stdio_callback_fds: bool,
+
+    /// Whether to replace missing standard streams by null streams.
+    ///
+    /// Processes without a console, such as Windows GUI applications, have
+    /// no standard streams and Python sets `sys.stdin`, `sys.stdout` and
+    /// `sys.stderr` to `None`. Code writing to these streams then fails, even
+    /// though output is typically just meant to be discarded.
+    ///
+    /// Default value: `false`
+    ///
+    /// Interpreter initialization behavior: if `true`, each of `sys.stdin`,
+    /// `sys.stdout` and `sys.stderr` that is `None` after streams of
+    /// [Self::stdout_callback] and [Self::stderr_callback] are installed is
+    /// replaced by a UTF-8 text stream of the null device. Reading returns no
+    /// data and written data is discarded. The corresponding `sys.__stdin__`,
+    /// `sys.__stdout__` and `sys.__stderr__` attributes are replaced as well if
+    /// they are `None`.
+    pub replace_missing_stdio: bool,
+
+    /// Function receiving Python audit events.
+    ///
+    /// See <https://peps.python.org/pep-0578/> and
+    /// <https://docs.python.org/3/library/audit_events.html> for the events
+    /// Python raises. e.g. `import`, `open` and `subprocess.Popen`.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if set, an audit hook passing
+    /// events allowed by [Self::audit_events] to the callback is added
+    /// before the interpreter is initialized, so events raised during
+    /// initialization are seen too. The hook stays installed until the
+    /// interpreter is finalized and also sees events of subinterpreters.
+    /// The callback is called with the GIL held and should return quickly.
+    /// It can't abort the audited operation. Events raised while rendering
+    /// event arguments are not passed to the callback.
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub audit_callback: Option<AuditCallback>,
+
+    /// Names of audit events to pass to [Self::audit_callback].
+    ///
+    /// An entry matches the event of the same name and events below it,
+    /// separated by `.`. e.g. `subprocess` matches `subprocess.Popen`. If
+    /// empty, all events are passed.
+    ///
+    /// Default value: `vec![]`
+    pub audit_events: Vec<String>,
+
+    /// Signals whose handling stays with the Rust host.
+    ///
+    /// Python installs its own handlers for some signals, notably `SIGINT`,
+    /// which it turns into `KeyboardInterrupt`. Hosts handling these signals
+    /// themselves can list them here. Use [crate::interrupt_python] to raise
+    /// `KeyboardInterrupt` in Python when desired.
+    ///
+    /// Default value: `vec![]`
+    ///
+    /// Interpreter initialization behavior: the handlers of the listed signals
+    /// are reset to the default while the interpreter is initialized and
+    /// reinstalled afterwards. So Python records its own handlers as if the
+    /// Rust host hadn't installed any, but signals reach the handlers of the
+    /// host. `signal.getsignal()` reports the handlers of Python. Python code
+    /// calling `signal.signal()` still replaces the handlers of the host.
+    pub host_signals: Vec<i32>,
+
     /// Command line arguments to initialize `sys.argv` with.
     ///
     /// Default value: [None]
@@ -336,16 +611,54 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// of elements.
     pub argvb: bool,
 
+    /// Whether to run an interactive console instead of the configured code.
+    ///
+    /// The console reads input with a line editor providing history and tab
+    /// completion of names and importable modules, including those provided
+    /// by `OxidizedFinder`. Unlike the interactive interpreter of Python, it
+    /// doesn't need the `readline` module, which standalone Python
+    /// distributions typically lack. Line editing is only available on Unix
+    /// terminals. The Windows console provides editing of its own.
+    ///
+    /// Default value: [false]
+    ///
+    /// [crate::MainPythonInterpreter::run()] behavior: if [true], the console
+    /// is run instead of `Py_RunMain()`. So the `run_*` fields of
+    /// [PythonInterpreterConfig] are ignored.
+    pub console: bool,
+
+    /// Command line argument enabling [Self::console].
+    ///
+    /// This allows applications to offer a console for diagnostics, e.g. via
+    /// `myapp --console`.
+    ///
+    /// Default value: [None]
+    ///
+    /// [Self::resolve()] behavior: if the first argument after the executable
+    /// in the resolved `sys.argv` equals this value, the argument is removed
+    /// and [Self::console] is set to [true].
+    pub console_argument: Option<String>,
+
     /// Automatically detect and run in `multiprocessing` mode.
     ///
     /// If set, [crate::MainPythonInterpreter::run()] will detect when the invoked
     /// interpreter looks like it is supposed to be a `multiprocessing` worker and
     /// will automatically call into the `multiprocessing` module instead of running
-    /// the configured code.
+    /// the configured code. Likewise, the forkserver and resource tracker
+    /// processes of `multiprocessing`, which are started via `-c <code>`, run
+    /// that code. Unless `sys.frozen` is set, workers of the `spawn` start method
+    /// are started this way too.
     ///
     /// Enabling this has the same effect as calling `multiprocessing.freeze_support()`
     /// in your application code's `__main__` and replaces the need to do so.
     ///
+    /// Interpreter initialization behavior: if [true] and [Self::oxidized_importer]
+    /// is [true], the `OxidizedImporter` will be taught to call
+    /// `multiprocessing.set_executable()` with the path of the current executable
+    /// when `multiprocessing` is imported. So processes started by the `spawn` and
+    /// `forkserver` start methods run this executable and are dispatched, even if
+    /// `sys.executable` refers to another executable.
+    ///
     /// Default value: [true]
     pub multiprocessing_auto_dispatch: bool,
 
@@ -358,6 +671,27 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// when `multiprocessing` is imported. If [false], this value has no effect.
     pub multiprocessing_start_method: MultiprocessingStartMethod,
 
+    /// What `multiprocessing.get_executable()` reports.
+    ///
+    /// This is the executable run by the `spawn` and `forkserver` start
+    /// methods of `multiprocessing`. [ProgramPath::Invoked] preserves the name
+    /// of a symlink the application was invoked through, for applications
+    /// dispatching on their invoked name.
+    ///
+    /// Default value: [ProgramPath::Default]
+    ///
+    /// [Self::resolve()] behavior: same as [Self::sys_executable]. Fails if
+    /// [ProgramPath::Empty].
+    ///
+    /// Interpreter initialization behavior: if [Self::oxidized_importer] is
+    /// [true], the `OxidizedImporter` will be taught to call
+    /// `multiprocessing.set_executable()` with the resolved value when
+    /// `multiprocessing` is imported. If [ProgramPath::Default], the path of
+    /// the current executable is used if [Self::multiprocessing_auto_dispatch]
+    /// is [true] and `multiprocessing` keeps reporting `sys.executable`
+    /// otherwise.
+    pub multiprocessing_executable: ProgramPath,
+
     /// Whether to set sys.frozen=True.
     ///
     /// Setting this will enable Python to emulate "frozen" binaries, such as
@@ -383,6 +717,41 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// `sys._MEIPASS` will not be defined.
     pub sys_meipass: bool,
 
+    /// What `sys.executable` and `sys._base_executable` report.
+    ///
+    /// Libraries run `sys.executable` to start Python processes, e.g. to
+    /// run a script or `pip`. By default, this is the executable embedding
+    /// Python, which parses these arguments as its own. Setting this to
+    /// [ProgramPath::Empty] makes such libraries fail instead, or a
+    /// [ProgramPath::Static] value can point them at a separate Python
+    /// interpreter.
+    ///
+    /// The `spawn` and `forkserver` start methods of `multiprocessing` run the
+    /// current executable instead. See [Self::multiprocessing_auto_dispatch].
+    ///
+    /// Default value: [ProgramPath::Default]
+    ///
+    /// [Self::resolve()] behavior: [ProgramPath::Exe] and [ProgramPath::Invoked]
+    /// are resolved to a [ProgramPath::Static] value. The token `$ORIGIN` in
+    /// [ProgramPath::Static] values is expanded to the resolved value of
+    /// [Self::origin].
+    ///
+    /// Interpreter initialization behavior: unless [ProgramPath::Default],
+    /// `sys.executable` and `sys._base_executable` are set to the resolved
+    /// value.
+    pub sys_executable: ProgramPath,
+
+    /// What `sys.argv[0]` reports.
+    ///
+    /// Default value: [ProgramPath::Default]
+    ///
+    /// [Self::resolve()] behavior: same as [Self::sys_executable].
+    ///
+    /// Interpreter initialization behavior: unless [ProgramPath::Default],
+    /// `sys.argv[0]` is set to the resolved value. Python code run via
+    /// `runpy`, such as [PythonInterpreterConfig::run_module], may replace it.
+    pub sys_argv0: ProgramPath,
+
     /// How to resolve the `terminfo` database.
     ///
     /// Default value: [TerminfoResolution::Dynamic]
@@ -421,6 +790,303 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     ///
     /// Default value: [None]
     pub write_modules_directory_env: Option<String>,
+
+    /// Environment variable holding the path to write a startup profile to.
+    ///
+    /// If this value is set and the environment variable it refers to is set,
+    /// the time spent in the phases of interpreter initialization and the time
+    /// `OxidizedFinder` spends importing every module are recorded. On
+    /// interpreter shutdown, the recorded events are written to the path in the
+    /// Chrome trace event format. [crate::write_startup_profile()] writes them
+    /// on demand.
+    ///
+    /// This setting is useful to find out where the time starting an application
+    /// goes, including the phases `python -X importtime` doesn't see.
+    ///
+    /// Default value: [None]
+    pub startup_profile_env: Option<String>,
+
+    /// Requirements the host environment must satisfy.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if set, the host environment (glibc
+    /// version, macOS version, Windows build, CPU features) is validated against
+    /// the requirements before the interpreter is initialized. If any requirement
+    /// isn't met, interpreter initialization fails with an error describing
+    /// every unmet requirement, followed by [HostRequirements::message], if set.
+    pub host_requirements: Option<HostRequirements>,
+
+    /// Capabilities to restrict the process to with an operating system sandbox.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if set, the sandbox is applied
+    /// after the interpreter is initialized and [Self::startup_hooks] have run.
+    /// On Linux, a seccomp-bpf filter restricting networking and execution of
+    /// other programs is installed. Filesystem restrictions are only enforced
+    /// on macOS, where a sandbox profile derived from the policy is applied.
+    /// Interpreter initialization fails if the sandbox can't be applied,
+    /// including on platforms where sandboxing isn't supported.
+    pub sandbox_policy: Option<SandboxPolicy>,
+
+    /// Migrations to run once per user after the application is upgraded.
+    ///
+    /// Default value: `vec![]`
+    ///
+    /// Interpreter initialization behavior: if non-empty, migrations whose
+    /// version isn't recorded in the migrations state file of the user are run
+    /// in order after [Self::startup_hooks] have run and before
+    /// [Self::sandbox_policy] is applied. Concurrently starting processes wait
+    /// for each other. Each successful migration is recorded immediately. If a
+    /// migration raises, it is recorded as failed, later migrations aren't run
+    /// and interpreter initialization fails with an error naming the failed
+    /// migration and the migrations applied before it. The failed migration is
+    /// retried on the next run.
+    pub migrations: Vec<Migration>,
+
+    /// Name of the directory of the application in the per-user data directory.
+    ///
+    /// The per-user data directory is `%LOCALAPPDATA%` on Windows,
+    /// `~/Library/Application Support` on macOS and `$XDG_DATA_HOME`,
+    /// defaulting to `~/.local/share`, elsewhere. The state of
+    /// [Self::migrations] is recorded in `pyembed-migrations.txt` in this
+    /// directory.
+    ///
+    /// Default value: [None]
+    ///
+    /// [Self::resolve()] behavior: fails if [Self::migrations] is non-empty and
+    /// this isn't set.
+    pub migrations_app_name: Option<String>,
+
+    /// Whether to make a built-in `setproctitle` extension module available.
+    ///
+    /// The module provides an API compatible with the `setproctitle` package
+    /// from PyPI (`setproctitle()`, `getproctitle()`, `setthreadtitle()`, and
+    /// `getthreadtitle()`), implemented in Rust.
+    ///
+    /// Default value: [false]
+    ///
+    /// Interpreter initialization behavior: if [true], `PyImport_Inittab` is
+    /// extended with a `setproctitle` entry. Being a built-in extension module,
+    /// it takes precedence over any `setproctitle` package that is also
+    /// available to the interpreter.
+    pub setproctitle_module: bool,
+
+    /// Python source code to use as the `site` module.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if set, the source code is
+    /// registered with `OxidizedFinder` as the `site` module, replacing any
+    /// `site` module in packed resources. Python imports `site` during main
+    /// interpreter initialization. This happens after `OxidizedFinder` is
+    /// installed on `sys.meta_path`, so the code can import packaged modules.
+    /// But it happens before `OxidizedFinder` is installed on `sys.path_hooks`,
+    /// before filesystem importers are removed when
+    /// [Self::filesystem_importer] is [false], and before `sys.argvb`,
+    /// `sys.oxidized`, and `sys.frozen` are set.
+    ///
+    /// The standard library's `site` module isn't executed. So `.pth` files,
+    /// `sitecustomize`, and `usercustomize` aren't processed unless the
+    /// code does so.
+    ///
+    /// Requires [Self::oxidized_importer] to be [true]. Interpreter
+    /// initialization fails if [PythonInterpreterConfig::site_import] is
+    /// `Some(false)`, as `site` would never be imported.
+    pub site_module: Option<String>,
+
+    /// Packages whose modules are loaded lazily.
+    ///
+    /// Each entry applies to the named package and all its sub-packages and
+    /// modules.
+    ///
+    /// Default value: `vec![]`
+    ///
+    /// Interpreter initialization behavior: `OxidizedFinder` is configured
+    /// to return module specs whose loader is an `importlib.util.LazyLoader`
+    /// for matching Python modules. Executing a matching module is deferred
+    /// until an attribute of the module is first accessed. Extension modules
+    /// are always loaded eagerly.
+    ///
+    /// Has no effect unless [Self::oxidized_importer] is [true].
+    pub lazy_import_packages: Vec<String>,
+
+    /// Packages whose modules are searched for on the filesystem first.
+    ///
+    /// Each entry applies to the named package and all its sub-packages and
+    /// modules.
+    ///
+    /// Default value: `vec![]`
+    ///
+    /// Interpreter initialization behavior: `OxidizedFinder` is configured
+    /// to consult the standard library `PathFinder` before its own resources
+    /// for matching modules. Modules found on `sys.path` (or the parent
+    /// package's `__path__`) take precedence over embedded resources. Modules
+    /// not found on the filesystem are imported from embedded resources.
+    ///
+    /// This allows overriding individual packages without rebuilding the
+    /// binary.
+    ///
+    /// Has no effect unless [Self::oxidized_importer] is [true]. Modules can
+    /// only be found on the filesystem if [Self::filesystem_importer] is [true].
+    pub filesystem_first_packages: Vec<String>,
+
+    /// Directory to materialize in-memory modules into.
+    ///
+    /// Default value: [None]
+    ///
+    /// [Self::resolve()] behavior: the token `$ORIGIN` is expanded to the
+    /// resolved value of [Self::origin].
+    ///
+    /// Interpreter initialization behavior: `OxidizedFinder` is configured to
+    /// write the source or bytecode of in-memory modules to this directory
+    /// when they are first imported and to set `__file__` to the written
+    /// file. In-memory resources of packages are written next to the
+    /// package's `__init__` file.
+    ///
+    /// Has no effect unless [Self::oxidized_importer] is [true].
+    pub materialized_files_dir: Option<PathBuf>,
+
+    /// Path to a file overriding interpreter settings at run-time.
+    ///
+    /// Default value: [None]
+    ///
+    /// [Self::resolve()] behavior: the token `$ORIGIN` is expanded to the
+    /// resolved value of [Self::origin]. If the file exists, it is parsed
+    /// and the settings it defines are applied to
+    /// [Self::interpreter_config]. A missing file is not an error.
+    ///
+    /// The file is parsed as TOML if its extension is `.toml` and as JSON
+    /// otherwise. It contains a table whose keys are names of
+    /// [PythonInterpreterConfig] fields. Only settings listed in
+    /// [Self::config_overrides_allowed] can be overridden. Paths in
+    /// `module_search_paths` are added to `sys.path`. Setting one of
+    /// `run_command`, `run_filename` or `run_module` clears the others.
+    ///
+    /// This allows adjusting deployed binaries without rebuilding them.
+    /// Reading the file requires the `config-overrides` crate feature.
+    pub config_overrides_path: Option<PathBuf>,
+
+    /// Names of settings that [Self::config_overrides_path] can override.
+    ///
+    /// Default value: `vec![]`
+    ///
+    /// Valid names are `module_search_paths`, `quiet`, `run_command`,
+    /// `run_filename`, `run_module`, `use_environment` and `verbose`.
+    /// [Self::resolve()] fails if other names are present.
+    pub config_overrides_allowed: Vec<String>,
+
+    /// Prefix of environment variables overriding interpreter settings.
+    ///
+    /// Default value: [None]
+    ///
+    /// [Self::resolve()] behavior: for each setting in
+    /// [Self::environment_overrides_allowed], an environment variable named
+    /// after this prefix is read and, if set, applied to
+    /// [Self::interpreter_config]. These are applied after
+    /// [Self::config_overrides_path].
+    ///
+    /// With a prefix of `MYAPP`, `module_search_paths` is read from
+    /// `MYAPP_PYTHONPATH_APPEND`, whose paths are added to `sys.path`. Other
+    /// settings are read from `MYAPP_PYTHON_<SETTING>`, e.g.
+    /// `MYAPP_PYTHON_VERBOSE`. Boolean settings accept `0`, `1`, `false`
+    /// and `true`.
+    ///
+    /// Unlike [PythonInterpreterConfig::use_environment], this only honors
+    /// the declared variables. So applications can remain isolated from
+    /// `PYTHON*` environment variables while allowing targeted debugging.
+    pub environment_overrides_prefix: Option<String>,
+
+    /// Names of settings that [Self::environment_overrides_prefix] can override.
+    ///
+    /// Default value: `vec![]`
+    ///
+    /// Valid names are the same as for [Self::config_overrides_allowed].
+    pub environment_overrides_allowed: Vec<String>,
+
+    /// Describes how the binary was built.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if set, the attributes of the
+    /// `oxidized_importer.build_info` module are populated from this value.
+    /// Otherwise they are `None`.
+    ///
+    /// Has no effect unless [Self::oxidized_importer] is [true].
+    pub build_info: Option<BuildInfo>,
+
+    /// Where log records of Rust and Python code are written.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if set, records of the `log`
+    /// crate and of Python's `logging` module are written to this
+    /// destination. Unless it is [LogDestination::Host], a `log` logger
+    /// honoring [Self::log_filter] is installed before the interpreter is
+    /// initialized. This fails if the application installed a logger
+    /// already. After initialization, a handler forwarding records to the
+    /// `log` crate is added to Python's root logger, whose level is set to
+    /// the most verbose level the `log` crate enables. Python records use
+    /// the logger name as target.
+    ///
+    /// [Self::resolve()] behavior: the token `$ORIGIN` in
+    /// [LogDestination::File] paths is expanded to the resolved value of
+    /// [Self::origin].
+    pub log_destination: Option<LogDestination>,
+
+    /// Which log records to write to [Self::log_destination].
+    ///
+    /// Default value: [None], which behaves like `warn`.
+    ///
+    /// The syntax is that of `RUST_LOG`: a comma separated list of `LEVEL`,
+    /// `TARGET` and `TARGET=LEVEL` directives, e.g. `info,app::db=debug`.
+    /// Targets match themselves and targets below them, separated by `::`
+    /// for Rust modules and `.` for Python loggers. The most specific
+    /// directive for a target wins.
+    pub log_filter: Option<String>,
+
+    /// Name of an environment variable overriding [Self::log_filter].
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: if the environment variable is
+    /// set and not empty, its value is used instead of [Self::log_filter].
+    /// e.g. `RUST_LOG`.
+    pub log_filter_env: Option<String>,
+
+    /// Whether to register the Windows Event Log event source.
+    ///
+    /// Default value: [false]
+    ///
+    /// Interpreter initialization behavior: if [Self::log_destination] is
+    /// [LogDestination::WindowsEventLog], the event source is registered in
+    /// the `Application` log with the executable as message file, replacing
+    /// an existing registration. Executables built by PyOxidizer contain the
+    /// message table Event Viewer needs to render records. Registering
+    /// requires administrative privileges, which services running as
+    /// `LocalSystem` have. Failures are ignored: records are still reported
+    /// but Event Viewer doesn't render them properly.
+    ///
+    /// Has no effect on platforms other than Windows.
+    pub log_register_event_source: bool,
+
+    /// Whether to render tracebacks with paths of original source files.
+    ///
+    /// Modules compiled by PyOxidizer use their module name as filename, so
+    /// tracebacks show e.g. `File "app.main", line 3`. Packaging can record
+    /// the path of each module's source file relative to a source map root,
+    /// typically the root of a source repository.
+    ///
+    /// Default value: [false]
+    ///
+    /// Interpreter initialization behavior: if [true], `sys.excepthook` is
+    /// replaced by a function printing tracebacks of uncaught exceptions with
+    /// the recorded source paths of modules indexed by `OxidizedFinder`
+    /// instead of their module names. Source lines are still shown, as they
+    /// are loaded from the packaged resources. Frames of other modules are
+    /// rendered as usual.
+    pub traceback_source_paths: bool,
 }
 
 impl<'a> Default for OxidizedPythonInterpreterConfig<'a> {
@@ -440,20 +1106,59 @@ impl<'a> Default for OxidizedPythonInterpreterConfig<'a> {
             allocator_obj: false,
             allocator_pymalloc_arena: false,
             allocator_debug: false,
+            allocator_stats: false,
             set_missing_path_configuration: true,
             oxidized_importer: false,
             filesystem_importer: true,
+            zipimport_importer: false,
+            extra_sys_paths: vec![],
+            extra_sys_paths_env: None,
             packed_resources: vec![],
+            packed_resources_key: None,
+            packed_resources_verification: PackedResourcesVerification::None,
             extra_extension_modules: None,
+            startup_hooks: vec![],
+            stdout_callback: None,
+            stderr_callback: None,
+            stdio_callback_fds: false,
+            replace_missing_stdio: false,
+            audit_callback: None,
+            audit_events: vec![],
+            host_signals: vec![],
             argv: None,
             argvb: false,
+            console: false,
+            console_argument: None,
             multiprocessing_auto_dispatch: true,
             multiprocessing_start_method: MultiprocessingStartMethod::Auto,
+            multiprocessing_executable: ProgramPath::Default,
             sys_frozen: false,
             sys_meipass: false,
+            sys_executable: ProgramPath::Default,
+            sys_argv0: ProgramPath::Default,
             terminfo_resolution: TerminfoResolution::Dynamic,
             tcl_library: None,
             write_modules_directory_env: None,
+            startup_profile_env: None,
+            host_requirements: None,
+            sandbox_policy: None,
+            migrations: vec![],
+            migrations_app_name: None,
+            setproctitle_module: false,
+            site_module: None,
+            lazy_import_packages: vec![],
+            filesystem_first_packages: vec![],
+            materialized_files_dir: None,
+            config_overrides_path: None,
+            config_overrides_allowed: vec![],
+            environment_overrides_prefix: None,
+            environment_overrides_allowed: vec![],
+            build_info: None,
+            log_destination: None,
+            log_filter: None,
+            log_filter_env: None,
+            log_register_event_source: false,
+            traceback_source_paths: false,
         }
     }
 }
@@ -463,7 +1168,7 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
     pub fn resolve(
         self,
     ) -> Result<ResolvedOxidizedPythonInterpreterConfig<'a>, NewInterpreterError> {
-        let argv = if let Some(args) = self.argv {
+        let mut argv = if let Some(args) = self.argv {
             Some(args)
         } else if self.interpreter_config.argv.is_some() {
             None
@@ -496,53 +1201,184 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
 
         let origin_string = origin.display().to_string();
 
+        if !self.migrations.is_empty() && self.migrations_app_name.is_none() {
+            return Err(NewInterpreterError::Simple(
+                "migrations_app_name must be set to run migrations",
+            ));
+        }
+
+        let mut interpreter_config = self.interpreter_config;
+
+        let config_overrides_path = self
+            .config_overrides_path
+            .as_ref()
+            .map(|x| PathBuf::from(x.display().to_string().replace("$ORIGIN", &origin_string)));
+
+        if let Some(path) = &config_overrides_path {
+            config_overrides::validate_allowed(&self.config_overrides_allowed)?;
+            config_overrides::apply_overrides_file(
+                &mut interpreter_config,
+                path,
+                &self.config_overrides_allowed,
+                &origin,
+            )?;
+        }
+
+        if let Some(prefix) = &self.environment_overrides_prefix {
+            config_overrides::validate_allowed(&self.environment_overrides_allowed)?;
+            config_overrides::apply_environment_overrides(
+                &mut interpreter_config,
+                prefix,
+                &self.environment_overrides_allowed,
+                &origin,
+            )?;
+        }
+
+        let console = self.console
+            || match &self.console_argument {
+                Some(argument) => {
+                    let args = if let Some(args) = argv.as_mut() {
+                        Some(args)
+                    } else {
+                        interpreter_config.argv.as_mut()
+                    };
+
+                    match args {
+                        Some(args) if args.len() > 1 && args[1] == argument.as_str() => {
+                            args.remove(1);
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+                None => false,
+            };
+
         let packed_resources = self
             .packed_resources
             .into_iter()
             .map(|entry| match entry {
-                PackedResourcesSource::Memory(_) => entry,
+                PackedResourcesSource::Memory(_) | PackedResourcesSource::ExecutableOverlay => {
+                    entry
+                }
                 PackedResourcesSource::MemoryMappedPath(p) => {
                     PackedResourcesSource::MemoryMappedPath(PathBuf::from(
                         p.display().to_string().replace("$ORIGIN", &origin_string),
                     ))
                 }
+                PackedResourcesSource::MemoryMappedPathVerified(p, digest) => {
+                    PackedResourcesSource::MemoryMappedPathVerified(
+                        PathBuf::from(p.display().to_string().replace("$ORIGIN", &origin_string)),
+                        digest,
+                    )
+                }
             })
             .collect::<Vec<_>>();
 
-        let module_search_paths = self
-            .interpreter_config
-            .module_search_paths
-            .as_ref()
-            .map(|x| {
-                x.iter()
-                    .map(|p| {
-                        PathBuf::from(p.display().to_string().replace("$ORIGIN", &origin_string))
-                    })
-                    .collect::<Vec<_>>()
-            });
+        let module_search_paths = interpreter_config.module_search_paths.as_ref().map(|x| {
+            x.iter()
+                .map(|p| PathBuf::from(p.display().to_string().replace("$ORIGIN", &origin_string)))
+                .collect::<Vec<_>>()
+        });
 
         let tcl_library = self
             .tcl_library
             .as_ref()
             .map(|x| PathBuf::from(x.display().to_string().replace("$ORIGIN", &origin_string)));
 
+        let extra_sys_paths = self
+            .extra_sys_paths
+            .iter()
+            .map(|p| PathBuf::from(p.display().to_string().replace("$ORIGIN", &origin_string)))
+            .collect::<Vec<_>>();
+
+        let materialized_files_dir = self
+            .materialized_files_dir
+            .as_ref()
+            .map(|x| PathBuf::from(x.display().to_string().replace("$ORIGIN", &origin_string)));
+
+        let argv0 = argv
+            .as_ref()
+            .or(interpreter_config.argv.as_ref())
+            .and_then(|args| args.first());
+        let resolve_program_path = |value: &ProgramPath| match value {
+            ProgramPath::Exe => ProgramPath::Static(exe.display().to_string()),
+            ProgramPath::Invoked => ProgramPath::Static(
+                argv0
+                    .and_then(|argv0| invoked_exe_path(argv0, &exe))
+                    .unwrap_or_else(|| exe.clone())
+                    .display()
+                    .to_string(),
+            ),
+            ProgramPath::Static(value) => {
+                ProgramPath::Static(value.replace("$ORIGIN", &origin_string))
+            }
+            _ => value.clone(),
+        };
+        let sys_executable = resolve_program_path(&self.sys_executable);
+        let sys_argv0 = resolve_program_path(&self.sys_argv0);
+        let multiprocessing_executable = match self.multiprocessing_executable {
+            ProgramPath::Empty => {
+                return Err(NewInterpreterError::Simple(
+                    "multiprocessing_executable cannot be empty",
+                ))
+            }
+            ref value => resolve_program_path(value),
+        };
+
+        let log_destination = self.log_destination.as_ref().map(|x| match x {
+            LogDestination::File(path) => {
+                LogDestination::File(path.replace("$ORIGIN", &origin_string))
+            }
+            _ => x.clone(),
+        });
+
         Ok(ResolvedOxidizedPythonInterpreterConfig {
             inner: Self {
                 exe: Some(exe),
                 origin: Some(origin),
                 interpreter_config: PythonInterpreterConfig {
                     module_search_paths,
-                    ..self.interpreter_config
+                    ..interpreter_config
                 },
                 argv,
+                console,
                 packed_resources,
                 tcl_library,
+                extra_sys_paths,
+                materialized_files_dir,
+                config_overrides_path,
+                sys_executable,
+                sys_argv0,
+                multiprocessing_executable,
+                log_destination,
                 ..self
             },
         })
     }
 }
 
+/// Resolve the path an executable was invoked through from its first argument.
+///
+/// Returns [None] unless the path refers to `exe`, which must be canonical.
+fn invoked_exe_path(argv0: &OsStr, exe: &Path) -> Option<PathBuf> {
+    let argv0 = Path::new(argv0);
+
+    let candidate = if argv0.components().count() > 1 {
+        std::env::current_dir().ok()?.join(argv0)
+    } else {
+        std::env::split_paths(&std::env::var_os("PATH")?)
+            .map(|dir| dir.join(argv0))
+            .find(|path| path.is_file())?
+    };
+
+    if dunce::canonicalize(&candidate).ok()? == exe {
+        Some(candidate)
+    } else {
+        None
+    }
+}
+
 /// An `OxidizedPythonInterpreterConfig` that has fields resolved.
 pub struct ResolvedOxidizedPythonInterpreterConfig<'a> {
     inner: OxidizedPythonInterpreterConfig<'a>,
@@ -591,6 +1427,25 @@ impl<'a> ResolvedOxidizedPythonInterpreterConfig<'a> {
         }
     }
 
+    /// Resolve the directories to append to `sys.path`.
+    ///
+    /// These are [OxidizedPythonInterpreterConfig::extra_sys_paths] followed by
+    /// directories from [OxidizedPythonInterpreterConfig::extra_sys_paths_env].
+    pub fn resolve_extra_sys_paths(&self) -> Vec<PathBuf> {
+        let mut paths = self.inner.extra_sys_paths.clone();
+
+        if let Some(value) = self
+            .inner
+            .extra_sys_paths_env
+            .as_ref()
+            .and_then(env::var_os)
+        {
+            paths.extend(env::split_paths(&value).filter(|p| !p.as_os_str().is_empty()));
+        }
+
+        paths
+    }
+
     /// Resolve the value to use for `sys.argvb`.
     pub fn resolve_sys_argvb(&self) -> Vec<OsString> {
         if let Some(args) = &self.inner.interpreter_config.argv {
@@ -615,6 +1470,12 @@ impl<'a, 'config: 'a> TryFrom<&ResolvedOxidizedPythonInterpreterConfig<'config>>
         state.set_current_exe(config.exe().to_path_buf());
         state.set_origin(config.origin().to_path_buf());
 
+        state.set_verification(config.packed_resources_verification);
+
+        if let Some(key) = &config.packed_resources_key {
+            state.set_decryption_key(Some(key.resolve().map_err(NewInterpreterError::Dynamic)?));
+        }
+
         for source in &config.packed_resources {
             match source {
                 PackedResourcesSource::Memory(data) => {
@@ -627,6 +1488,16 @@ impl<'a, 'config: 'a> TryFrom<&ResolvedOxidizedPythonInterpreterConfig<'config>>
                         .index_path_memory_mapped(path)
                         .map_err(NewInterpreterError::Dynamic)?;
                 }
+                PackedResourcesSource::MemoryMappedPathVerified(path, digest) => {
+                    state
+                        .index_path_memory_mapped_verified(path, digest)
+                        .map_err(NewInterpreterError::Dynamic)?;
+                }
+                PackedResourcesSource::ExecutableOverlay => {
+                    state
+                        .index_executable_overlay()
+                        .map_err(NewInterpreterError::Dynamic)?;
+                }
             }
         }
 
@@ -634,6 +1505,17 @@ impl<'a, 'config: 'a> TryFrom<&ResolvedOxidizedPythonInterpreterConfig<'config>>
             .index_interpreter_builtins()
             .map_err(NewInterpreterError::Simple)?;
 
+        if let Some(source) = &config.site_module {
+            state
+                .add_resource(Resource {
+                    name: "site".into(),
+                    is_python_module: true,
+                    in_memory_source: Some(source.as_bytes().to_vec().into()),
+                    ..Resource::default()
+                })
+                .map_err(NewInterpreterError::Simple)?;
+        }
+
         Ok(state)
     }
 }
@@ -687,4 +1569,221 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_packed_resources_verified_origin() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig {
+            origin: Some(PathBuf::from("/other/origin")),
+            ..Default::default()
+        };
+
+        config
+            .packed_resources
+            .push(PackedResourcesSource::MemoryMappedPathVerified(
+                PathBuf::from("$ORIGIN/myapp.pyoxy-resources"),
+                [42; 32],
+            ));
+
+        let resolved = config.resolve()?;
+
+        assert_eq!(
+            resolved.packed_resources,
+            vec![PackedResourcesSource::MemoryMappedPathVerified(
+                PathBuf::from("/other/origin/myapp.pyoxy-resources"),
+                [42; 32],
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_destination_origin() -> Result<()> {
+        let config = OxidizedPythonInterpreterConfig {
+            origin: Some(PathBuf::from("/other/origin")),
+            log_destination: Some(LogDestination::File("$ORIGIN/app.log".to_string())),
+            ..Default::default()
+        };
+
+        let resolved = config.resolve()?;
+
+        assert_eq!(
+            resolved.log_destination,
+            Some(LogDestination::File("/other/origin/app.log".to_string()))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_program_path() -> Result<()> {
+        let exe = dunce::canonicalize(std::env::current_exe()?)?;
+
+        let config = OxidizedPythonInterpreterConfig {
+            origin: Some(PathBuf::from("/other/origin")),
+            argv: Some(vec![OsString::from("missing-app")]),
+            sys_executable: ProgramPath::Static("$ORIGIN/python3".to_string()),
+            sys_argv0: ProgramPath::Invoked,
+            multiprocessing_executable: ProgramPath::Exe,
+            ..Default::default()
+        };
+
+        let resolved = config.resolve()?;
+        assert_eq!(
+            resolved.sys_executable,
+            ProgramPath::Static("/other/origin/python3".to_string())
+        );
+        assert_eq!(
+            resolved.sys_argv0,
+            ProgramPath::Static(exe.display().to_string())
+        );
+        assert_eq!(
+            resolved.multiprocessing_executable,
+            ProgramPath::Static(exe.display().to_string())
+        );
+
+        let config = OxidizedPythonInterpreterConfig {
+            multiprocessing_executable: ProgramPath::Empty,
+            ..Default::default()
+        };
+        assert!(config.resolve().is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_program_path_symlink() -> Result<()> {
+        let exe = dunce::canonicalize(std::env::current_exe()?)?;
+        let temp_dir = tempfile::TempDir::new()?;
+        let link = temp_dir.path().join("app");
+        std::os::unix::fs::symlink(&exe, &link)?;
+
+        let config = OxidizedPythonInterpreterConfig {
+            argv: Some(vec![link.clone().into_os_string()]),
+            sys_executable: ProgramPath::Exe,
+            sys_argv0: ProgramPath::Invoked,
+            multiprocessing_executable: ProgramPath::Invoked,
+            ..Default::default()
+        };
+
+        let resolved = config.resolve()?;
+        assert_eq!(
+            resolved.sys_executable,
+            ProgramPath::Static(exe.display().to_string())
+        );
+        assert_eq!(
+            resolved.sys_argv0,
+            ProgramPath::Static(link.display().to_string())
+        );
+        assert_eq!(
+            resolved.multiprocessing_executable,
+            ProgramPath::Static(link.display().to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_console_argument() -> Result<()> {
+        let argv = |args: &[&str]| Some(args.iter().map(OsString::from).collect::<Vec<_>>());
+
+        let config = OxidizedPythonInterpreterConfig {
+            argv: argv(&["app", "--console", "arg"]),
+            console_argument: Some("--console".to_string()),
+            ..Default::default()
+        };
+
+        let resolved = config.resolve()?;
+        assert!(resolved.console);
+        assert_eq!(resolved.argv, argv(&["app", "arg"]));
+
+        let config = OxidizedPythonInterpreterConfig {
+            argv: argv(&["app", "arg", "--console"]),
+            console_argument: Some("--console".to_string()),
+            ..Default::default()
+        };
+
+        let resolved = config.resolve()?;
+        assert!(!resolved.console);
+        assert_eq!(resolved.argv, argv(&["app", "arg", "--console"]));
+
+        let mut config = OxidizedPythonInterpreterConfig {
+            console_argument: Some("--console".to_string()),
+            ..Default::default()
+        };
+        config.interpreter_config.argv = argv(&["app", "--console"]);
+
+        let resolved = config.resolve()?;
+        assert!(resolved.console);
+        assert_eq!(resolved.interpreter_config.argv, argv(&["app"]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_overrides_allowed_invalid() {
+        let config = OxidizedPythonInterpreterConfig {
+            config_overrides_path: Some(PathBuf::from("$ORIGIN/missing.json")),
+            config_overrides_allowed: vec!["profile".to_string()],
+            ..Default::default()
+        };
+
+        assert!(config.resolve().is_err());
+    }
+
+    #[test]
+    fn test_environment_overrides() -> Result<()> {
+        std::env::set_var("PYEMBED_TEST_ENV_OVERRIDES_PYTHON_VERBOSE", "1");
+        std::env::set_var("PYEMBED_TEST_ENV_OVERRIDES_PYTHON_QUIET", "1");
+
+        let config = OxidizedPythonInterpreterConfig {
+            environment_overrides_prefix: Some("PYEMBED_TEST_ENV_OVERRIDES".into()),
+            environment_overrides_allowed: vec!["verbose".into()],
+            ..Default::default()
+        };
+
+        let resolved = config.resolve()?;
+
+        assert_eq!(resolved.interpreter_config.verbose, Some(true));
+        assert_eq!(resolved.interpreter_config.quiet, None);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "config-overrides")]
+    fn test_config_overrides_path() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyembed-config-overrides")
+            .tempdir()?;
+        std::fs::write(
+            temp_dir.path().join("app.json"),
+            r#"{"module_search_paths": ["$ORIGIN/extra"], "verbose": true}"#,
+        )?;
+
+        let config = OxidizedPythonInterpreterConfig {
+            origin: Some(temp_dir.path().to_path_buf()),
+            interpreter_config: PythonInterpreterConfig {
+                module_search_paths: Some(vec![PathBuf::from("$ORIGIN/lib")]),
+                ..Default::default()
+            },
+            config_overrides_path: Some(PathBuf::from("$ORIGIN/app.json")),
+            config_overrides_allowed: vec!["module_search_paths".into(), "verbose".into()],
+            ..Default::default()
+        };
+
+        let resolved = config.resolve()?;
+
+        assert_eq!(
+            resolved.interpreter_config.module_search_paths,
+            Some(vec![
+                temp_dir.path().join("lib"),
+                temp_dir.path().join("extra")
+            ])
+        );
+        assert_eq!(resolved.interpreter_config.verbose, Some(true));
+
+        Ok(())
+    }
 }
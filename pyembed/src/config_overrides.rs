@@ -0,0 +1,434 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Override interpreter settings from a file or environment variables at run-time. */
+
+use {
+    crate::NewInterpreterError,
+    python_packaging::interpreter::PythonInterpreterConfig,
+    std::{
+        ffi::OsString,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Names of settings that can be overridden.
+///
+/// Each name corresponds to a field of [PythonInterpreterConfig].
+pub const OVERRIDABLE_SETTINGS: &[&str] = &[
+    "module_search_paths",
+    "quiet",
+    "run_command",
+    "run_filename",
+    "run_module",
+    "use_environment",
+    "verbose",
+];
+
+/// Verify all entries of an allowlist are settings that can be overridden.
+pub fn validate_allowed(allowed: &[String]) -> Result<(), NewInterpreterError> {
+    for name in allowed {
+        if !OVERRIDABLE_SETTINGS.contains(&name.as_str()) {
+            return Err(NewInterpreterError::Dynamic(format!(
+                "{} cannot be overridden at run-time; allowed settings are {}",
+                name,
+                OVERRIDABLE_SETTINGS.join(", ")
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply overrides from the file at `path` to an interpreter config.
+///
+/// Does nothing if the file does not exist. `origin` is substituted for
+/// `$ORIGIN` in paths added to `sys.path` when the config does not define
+/// [PythonInterpreterConfig::module_search_paths].
+#[cfg(feature = "config-overrides")]
+pub fn apply_overrides_file(
+    config: &mut PythonInterpreterConfig,
+    path: &Path,
+    allowed: &[String],
+    origin: &Path,
+) -> Result<(), NewInterpreterError> {
+    let data = match std::fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => {
+            return Err(NewInterpreterError::Dynamic(format!(
+                "error reading {}: {}",
+                path.display(),
+                e
+            )))
+        }
+    };
+
+    let value = if path.extension().map(|x| x == "toml").unwrap_or(false) {
+        toml::from_str::<serde_json::Value>(&data).map_err(|e| e.to_string())
+    } else {
+        serde_json::from_str::<serde_json::Value>(&data).map_err(|e| e.to_string())
+    }
+    .map_err(|e| {
+        NewInterpreterError::Dynamic(format!("error parsing {}: {}", path.display(), e))
+    })?;
+
+    apply_overrides(config, &value, allowed, origin)
+        .map_err(|e| NewInterpreterError::Dynamic(format!("{}: {}", path.display(), e)))
+}
+
+#[cfg(not(feature = "config-overrides"))]
+pub fn apply_overrides_file(
+    _config: &mut PythonInterpreterConfig,
+    path: &Path,
+    _allowed: &[String],
+    _origin: &Path,
+) -> Result<(), NewInterpreterError> {
+    if path.exists() {
+        Err(NewInterpreterError::Dynamic(format!(
+            "cannot apply {}: pyembed built without config-overrides feature",
+            path.display()
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "config-overrides")]
+fn apply_overrides(
+    config: &mut PythonInterpreterConfig,
+    value: &serde_json::Value,
+    allowed: &[String],
+    origin: &Path,
+) -> Result<(), String> {
+    let entries = value
+        .as_object()
+        .ok_or_else(|| "expected a table of settings".to_string())?;
+
+    for (name, value) in entries {
+        if !OVERRIDABLE_SETTINGS.contains(&name.as_str()) {
+            return Err(format!("unknown setting {}", name));
+        }
+        if !allowed.iter().any(|x| x == name) {
+            return Err(format!("setting {} is not allowed to be overridden", name));
+        }
+
+        match name.as_str() {
+            "module_search_paths" => {
+                let paths = value
+                    .as_array()
+                    .and_then(|x| {
+                        x.iter()
+                            .map(|p| p.as_str().map(PathBuf::from))
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| format!("{} must be a list of strings", name))?;
+
+                add_search_paths(config, paths, origin)?;
+            }
+            "run_command" | "run_filename" | "run_module" => {
+                let run = value
+                    .as_str()
+                    .ok_or_else(|| format!("{} must be a string", name))?;
+
+                set_run(config, name, run.to_string());
+            }
+            _ => {
+                let flag = value
+                    .as_bool()
+                    .ok_or_else(|| format!("{} must be a boolean", name))?;
+
+                set_flag(config, name, flag);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Obtain the name of the environment variable overriding a setting.
+pub fn environment_variable_name(prefix: &str, setting: &str) -> String {
+    match setting {
+        "module_search_paths" => format!("{}_PYTHONPATH_APPEND", prefix),
+        _ => format!("{}_PYTHON_{}", prefix, setting.to_ascii_uppercase()),
+    }
+}
+
+/// Apply overrides from environment variables to an interpreter config.
+///
+/// Only the variables of settings in `allowed` are read. Variable names are
+/// derived from `prefix` by [environment_variable_name()].
+pub fn apply_environment_overrides(
+    config: &mut PythonInterpreterConfig,
+    prefix: &str,
+    allowed: &[String],
+    origin: &Path,
+) -> Result<(), NewInterpreterError> {
+    apply_environment_overrides_from(config, prefix, allowed, origin, |key| std::env::var_os(key))
+        .map_err(NewInterpreterError::Dynamic)
+}
+
+fn apply_environment_overrides_from(
+    config: &mut PythonInterpreterConfig,
+    prefix: &str,
+    allowed: &[String],
+    origin: &Path,
+    get: impl Fn(&str) -> Option<OsString>,
+) -> Result<(), String> {
+    for name in allowed {
+        let key = environment_variable_name(prefix, name);
+
+        let value = if let Some(value) = get(&key) {
+            value
+        } else {
+            continue;
+        };
+
+        match name.as_str() {
+            "module_search_paths" => {
+                let paths = std::env::split_paths(&value)
+                    .filter(|p| !p.as_os_str().is_empty())
+                    .collect::<Vec<_>>();
+
+                add_search_paths(config, paths, origin).map_err(|e| format!("{}: {}", key, e))?;
+            }
+            "run_command" | "run_filename" | "run_module" => {
+                let run = value
+                    .into_string()
+                    .map_err(|_| format!("{} is not valid UTF-8", key))?;
+
+                set_run(config, name, run);
+            }
+            _ => {
+                let flag = match value.to_string_lossy().to_ascii_lowercase().as_str() {
+                    "1" | "true" => true,
+                    "0" | "false" => false,
+                    _ => return Err(format!("{} must be one of 0, 1, false or true", key)),
+                };
+
+                set_flag(config, name, flag);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Define the run mode of the interpreter.
+///
+/// Only a single run mode can be active. So other run modes are cleared.
+fn set_run(config: &mut PythonInterpreterConfig, name: &str, value: String) {
+    config.run_command = None;
+    config.run_filename = None;
+    config.run_module = None;
+
+    match name {
+        "run_command" => config.run_command = Some(value),
+        "run_filename" => config.run_filename = Some(PathBuf::from(value)),
+        _ => config.run_module = Some(value),
+    }
+}
+
+/// Define a boolean setting.
+fn set_flag(config: &mut PythonInterpreterConfig, name: &str, value: bool) {
+    match name {
+        "quiet" => config.quiet = Some(value),
+        "use_environment" => config.use_environment = Some(value),
+        _ => config.verbose = Some(value),
+    }
+}
+
+/// Add paths to `sys.path`.
+///
+/// Paths are appended to [PythonInterpreterConfig::module_search_paths] if
+/// it is defined. Otherwise they are added to
+/// [PythonInterpreterConfig::python_path_env] so they are added to the
+/// default `sys.path` computed by Python.
+fn add_search_paths(
+    config: &mut PythonInterpreterConfig,
+    paths: Vec<PathBuf>,
+    origin: &Path,
+) -> Result<(), String> {
+    if let Some(search_paths) = config.module_search_paths.as_mut() {
+        search_paths.extend(paths);
+        return Ok(());
+    }
+
+    let origin_string = origin.display().to_string();
+
+    let mut entries = config
+        .python_path_env
+        .as_ref()
+        .map(|x| std::env::split_paths(x).collect::<Vec<_>>())
+        .unwrap_or_default();
+    entries.extend(
+        paths
+            .iter()
+            .map(|p| PathBuf::from(p.display().to_string().replace("$ORIGIN", &origin_string))),
+    );
+
+    let joined = std::env::join_paths(entries).map_err(|e| e.to_string())?;
+    config.python_path_env = Some(joined.to_string_lossy().to_string());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, anyhow::Result, std::collections::HashMap};
+
+    fn allowed(names: &[&str]) -> Vec<String> {
+        names.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_validate_allowed() {
+        assert!(validate_allowed(&allowed(&["verbose", "run_module"])).is_ok());
+        assert!(validate_allowed(&allowed(&["profile"])).is_err());
+    }
+
+    #[test]
+    fn test_environment_variable_name() {
+        assert_eq!(
+            environment_variable_name("MYAPP", "verbose"),
+            "MYAPP_PYTHON_VERBOSE"
+        );
+        assert_eq!(
+            environment_variable_name("MYAPP", "module_search_paths"),
+            "MYAPP_PYTHONPATH_APPEND"
+        );
+    }
+
+    #[test]
+    fn test_apply_environment_overrides() -> Result<()> {
+        let env = HashMap::from([
+            ("MYAPP_PYTHON_VERBOSE", "1"),
+            ("MYAPP_PYTHON_QUIET", "true"),
+            ("MYAPP_PYTHON_RUN_MODULE", "foo"),
+            ("MYAPP_PYTHONPATH_APPEND", "$ORIGIN/extra"),
+        ]);
+        let get = |key: &str| env.get(key).map(OsString::from);
+
+        let mut config = PythonInterpreterConfig {
+            run_command: Some("import foo".into()),
+            ..Default::default()
+        };
+
+        // Only allowed settings are read.
+        apply_environment_overrides_from(
+            &mut config,
+            "MYAPP",
+            &allowed(&["verbose", "run_module", "module_search_paths"]),
+            Path::new("/origin"),
+            get,
+        )
+        .map_err(anyhow::Error::msg)?;
+
+        assert_eq!(config.verbose, Some(true));
+        assert_eq!(config.quiet, None);
+        assert_eq!(config.run_command, None);
+        assert_eq!(config.run_module, Some("foo".into()));
+        assert_eq!(config.python_path_env, Some("/origin/extra".into()));
+
+        let get = |_: &str| Some(OsString::from("yes"));
+        assert!(apply_environment_overrides_from(
+            &mut config,
+            "MYAPP",
+            &allowed(&["verbose"]),
+            Path::new("/origin"),
+            get
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "config-overrides")]
+    fn test_apply_overrides() -> Result<()> {
+        let mut config = PythonInterpreterConfig {
+            run_command: Some("import foo".into()),
+            ..Default::default()
+        };
+
+        let value = serde_json::json!({"run_module": "bar", "verbose": true});
+        apply_overrides(
+            &mut config,
+            &value,
+            &allowed(&["run_module", "verbose"]),
+            Path::new("/origin"),
+        )
+        .map_err(anyhow::Error::msg)?;
+
+        assert_eq!(config.run_command, None);
+        assert_eq!(config.run_module, Some("bar".into()));
+        assert_eq!(config.verbose, Some(true));
+
+        assert!(apply_overrides(
+            &mut config,
+            &serde_json::json!({"quiet": true}),
+            &allowed(&["verbose"]),
+            Path::new("/origin")
+        )
+        .is_err());
+        assert!(apply_overrides(
+            &mut config,
+            &serde_json::json!({"verbose": "yes"}),
+            &allowed(&["verbose"]),
+            Path::new("/origin")
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "config-overrides")]
+    fn test_add_search_paths() -> Result<()> {
+        let value = serde_json::json!({"module_search_paths": ["$ORIGIN/extra"]});
+        let names = allowed(&["module_search_paths"]);
+
+        let mut config = PythonInterpreterConfig::default();
+        apply_overrides(&mut config, &value, &names, Path::new("/origin"))
+            .map_err(anyhow::Error::msg)?;
+        assert_eq!(config.module_search_paths, None);
+        assert_eq!(config.python_path_env, Some("/origin/extra".into()));
+
+        let mut config = PythonInterpreterConfig {
+            module_search_paths: Some(vec!["$ORIGIN/lib".into()]),
+            ..Default::default()
+        };
+        apply_overrides(&mut config, &value, &names, Path::new("/origin"))
+            .map_err(anyhow::Error::msg)?;
+        assert_eq!(
+            config.module_search_paths,
+            Some(vec!["$ORIGIN/lib".into(), "$ORIGIN/extra".into()])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "config-overrides")]
+    fn test_apply_overrides_file() -> Result<()> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyembed-config-overrides")
+            .tempdir()?;
+        let path = temp_dir.path().join("app.toml");
+        let names = allowed(&["run_module"]);
+
+        let mut config = PythonInterpreterConfig::default();
+        apply_overrides_file(&mut config, &path, &names, temp_dir.path())?;
+        assert_eq!(config.run_module, None);
+
+        std::fs::write(&path, "run_module = \"foo\"\n")?;
+        apply_overrides_file(&mut config, &path, &names, temp_dir.path())?;
+        assert_eq!(config.run_module, Some("foo".into()));
+
+        std::fs::write(&path, "run_module = [\n")?;
+        assert!(apply_overrides_file(&mut config, &path, &names, temp_dir.path()).is_err());
+
+        Ok(())
+    }
+}
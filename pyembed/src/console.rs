@@ -0,0 +1,716 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Interactive console with line editing.
+
+Standalone Python distributions typically lack the `readline` module, leaving
+the interactive interpreter without line editing. The console runs
+`code.InteractiveConsole` with input read by a line editor implemented here.
+
+On Unix terminals, the editor supports cursor movement, history and tab
+completion of Python names and of importable modules, including those
+provided by `OxidizedFinder`. Elsewhere lines are read as is: the Windows
+console provides editing and history of its own.
+*/
+
+use {
+    pyo3::{
+        exceptions::{PyEOFError, PyKeyboardInterrupt, PyOSError, PySystemExit},
+        prelude::*,
+        types::PyDict,
+    },
+    std::io::{self, BufRead, Read, Write},
+};
+
+/// Maximum number of lines kept in the history.
+const MAX_HISTORY: usize = 1000;
+
+/// Indentation inserted when completing at the start of a word.
+const INDENT: &str = "    ";
+
+/// Python code defining the console.
+///
+/// `_read_line` is injected into the globals the code runs with.
+const CONSOLE_CODE: &str = r#"
+import code
+import pkgutil
+import re
+import rlcompleter
+import sys
+
+_IMPORT = re.compile(r"^\s*(?:import|from)\s+([\w.]*)$")
+_WORD = re.compile(r"[\w.]*$")
+
+
+def _module_names(word):
+    package, _, _ = word.rpartition(".")
+
+    if package:
+        path = getattr(sys.modules.get(package), "__path__", None)
+        if path is None:
+            return []
+        names = {info.name for info in pkgutil.iter_modules(path, package + ".")}
+    else:
+        names = set(sys.builtin_module_names)
+        for finder in sys.meta_path:
+            iter_modules = getattr(finder, "iter_modules", None)
+            if iter_modules is not None:
+                names.update(info[0] for info in iter_modules())
+        names.update(info.name for info in pkgutil.iter_modules())
+
+    return sorted(name for name in names if name.startswith(word))
+
+
+class Console(code.InteractiveConsole):
+    def __init__(self):
+        super().__init__({"__name__": "__console__", "__doc__": None})
+        self._completer = rlcompleter.Completer(self.locals)
+
+    def raw_input(self, prompt=""):
+        sys.stdout.flush()
+        sys.stderr.flush()
+        return _read_line(prompt, self.complete)
+
+    def complete(self, line):
+        m = _IMPORT.match(line)
+        if m:
+            return _module_names(m.group(1))
+
+        word = _WORD.search(line).group(0)
+        matches = []
+        while True:
+            match = self._completer.complete(word, len(matches))
+            if match is None:
+                return sorted(set(matches))
+            matches.append(match)
+
+
+console = Console()
+"#;
+
+/// A key read from the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Up,
+    Down,
+    KillToStart,
+    KillToEnd,
+    ClearScreen,
+    Interrupt,
+    EndOfFile,
+    Unknown,
+}
+
+/// Read a byte, returning [None] at the end of input.
+fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut buf = [0u8];
+
+    loop {
+        match input.read(&mut buf) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(buf[0])),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Decode an escape sequence following an `ESC` byte.
+fn read_escape(input: &mut impl Read) -> io::Result<Key> {
+    match read_byte(input)? {
+        Some(b'[') | Some(b'O') => {}
+        _ => return Ok(Key::Unknown),
+    }
+
+    let mut parameter = 0u32;
+
+    loop {
+        let key = match read_byte(input)? {
+            Some(b @ b'0'..=b'9') => {
+                parameter = parameter.saturating_mul(10) + u32::from(b - b'0');
+                continue;
+            }
+            Some(b';') => continue,
+            Some(b'A') => Key::Up,
+            Some(b'B') => Key::Down,
+            Some(b'C') => Key::Right,
+            Some(b'D') => Key::Left,
+            Some(b'H') => Key::Home,
+            Some(b'F') => Key::End,
+            Some(b'~') => match parameter {
+                1 | 7 => Key::Home,
+                3 => Key::Delete,
+                4 | 8 => Key::End,
+                _ => Key::Unknown,
+            },
+            _ => Key::Unknown,
+        };
+
+        return Ok(key);
+    }
+}
+
+/// Read a key, returning [None] at the end of input.
+fn read_key(input: &mut impl Read) -> io::Result<Option<Key>> {
+    let b = match read_byte(input)? {
+        Some(b) => b,
+        None => return Ok(None),
+    };
+
+    let key = match b {
+        0x01 => Key::Home,
+        0x02 => Key::Left,
+        0x03 => Key::Interrupt,
+        0x04 => Key::EndOfFile,
+        0x05 => Key::End,
+        0x06 => Key::Right,
+        0x08 | 0x7f => Key::Backspace,
+        b'\t' => Key::Tab,
+        b'\n' | b'\r' => Key::Enter,
+        0x0b => Key::KillToEnd,
+        0x0c => Key::ClearScreen,
+        0x0e => Key::Down,
+        0x10 => Key::Up,
+        0x15 => Key::KillToStart,
+        0x1b => read_escape(input)?,
+        b if b < 0x20 => Key::Unknown,
+        b if b < 0x80 => Key::Char(char::from(b)),
+        b => {
+            let len = match b {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => return Ok(Some(Key::Unknown)),
+            };
+
+            let mut buf = vec![b];
+            for _ in 1..len {
+                match read_byte(input)? {
+                    Some(b) => buf.push(b),
+                    None => return Ok(None),
+                }
+            }
+
+            match std::str::from_utf8(&buf)
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                Some(c) => Key::Char(c),
+                None => Key::Unknown,
+            }
+        }
+    };
+
+    Ok(Some(key))
+}
+
+/// The line being edited.
+#[derive(Debug, Default)]
+struct LineBuffer {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl LineBuffer {
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    fn set(&mut self, text: &str) {
+        self.chars = text.chars().collect();
+        self.cursor = self.chars.len();
+    }
+
+    fn insert(&mut self, text: &str) {
+        for c in text.chars() {
+            self.chars.insert(self.cursor, c);
+            self.cursor += 1;
+        }
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    fn kill_to_start(&mut self) {
+        self.chars.drain(..self.cursor);
+        self.cursor = 0;
+    }
+
+    fn kill_to_end(&mut self) {
+        self.chars.truncate(self.cursor);
+    }
+
+    /// The number of characters of the word before the cursor.
+    ///
+    /// Words consist of identifier characters and `.`, so they cover
+    /// attribute and module paths.
+    fn word_len(&self) -> usize {
+        self.chars[..self.cursor]
+            .iter()
+            .rev()
+            .take_while(|c| c.is_alphanumeric() || **c == '_' || **c == '.')
+            .count()
+    }
+}
+
+/// Obtain the longest common prefix of strings.
+fn common_prefix(candidates: &[String]) -> String {
+    let mut prefix = candidates[0].chars().collect::<Vec<_>>();
+
+    for candidate in &candidates[1..] {
+        let len = prefix
+            .iter()
+            .zip(candidate.chars())
+            .take_while(|(a, b)| **a == *b)
+            .count();
+        prefix.truncate(len);
+    }
+
+    prefix.into_iter().collect()
+}
+
+/// Reads lines with editing, history and completion.
+#[derive(Debug, Default)]
+struct LineEditor {
+    history: Vec<String>,
+}
+
+impl LineEditor {
+    /// Read a line from a terminal in raw mode.
+    ///
+    /// Returns [None] at the end of input. Fails with
+    /// [io::ErrorKind::Interrupted] if the user pressed Ctrl-C.
+    ///
+    /// `complete` receives the line up to the cursor and returns
+    /// replacements for the word before the cursor.
+    fn edit(
+        &mut self,
+        input: &mut impl Read,
+        output: &mut impl Write,
+        prompt: &str,
+        complete: &mut dyn FnMut(&str) -> Vec<String>,
+    ) -> io::Result<Option<String>> {
+        let mut line = LineBuffer::default();
+        let mut history_index = self.history.len();
+        let mut pending = String::new();
+
+        refresh(output, prompt, &line)?;
+
+        loop {
+            let key = match read_key(input)? {
+                Some(key) => key,
+                None if line.chars.is_empty() => return Ok(None),
+                None => Key::Enter,
+            };
+
+            match key {
+                Key::Char(c) => line.insert(c.encode_utf8(&mut [0; 4])),
+                Key::Enter => {
+                    output.write_all(b"\n")?;
+                    output.flush()?;
+
+                    let text = line.text();
+                    self.add_history(&text);
+
+                    return Ok(Some(text));
+                }
+                Key::Tab => self.complete(output, prompt, &mut line, complete)?,
+                Key::Backspace => line.backspace(),
+                Key::Delete => line.delete(),
+                Key::Left => line.cursor = line.cursor.saturating_sub(1),
+                Key::Right => line.cursor = (line.cursor + 1).min(line.chars.len()),
+                Key::Home => line.cursor = 0,
+                Key::End => line.cursor = line.chars.len(),
+                Key::Up => {
+                    if history_index > 0 {
+                        if history_index == self.history.len() {
+                            pending = line.text();
+                        }
+                        history_index -= 1;
+                        line.set(&self.history[history_index]);
+                    }
+                }
+                Key::Down => {
+                    if history_index < self.history.len() {
+                        history_index += 1;
+                        if history_index == self.history.len() {
+                            line.set(&pending);
+                        } else {
+                            line.set(&self.history[history_index]);
+                        }
+                    }
+                }
+                Key::KillToStart => line.kill_to_start(),
+                Key::KillToEnd => line.kill_to_end(),
+                Key::ClearScreen => output.write_all(b"\x1b[H\x1b[2J")?,
+                Key::Interrupt => {
+                    return Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted"));
+                }
+                Key::EndOfFile => {
+                    if line.chars.is_empty() {
+                        return Ok(None);
+                    }
+                    line.delete();
+                }
+                Key::Unknown => {}
+            }
+
+            refresh(output, prompt, &line)?;
+        }
+    }
+
+    /// Complete the word before the cursor.
+    fn complete(
+        &self,
+        output: &mut impl Write,
+        prompt: &str,
+        line: &mut LineBuffer,
+        complete: &mut dyn FnMut(&str) -> Vec<String>,
+    ) -> io::Result<()> {
+        let word_len = line.word_len();
+
+        if word_len == 0 {
+            line.insert(INDENT);
+            return Ok(());
+        }
+
+        let before = line.chars[..line.cursor].iter().collect::<String>();
+        let candidates = complete(&before);
+
+        if candidates.is_empty() {
+            output.write_all(b"\x07")?;
+            return Ok(());
+        }
+
+        let prefix = common_prefix(&candidates);
+
+        if candidates.len() == 1 || prefix.chars().count() > word_len {
+            line.cursor -= word_len;
+            line.chars.drain(line.cursor..line.cursor + word_len);
+            line.insert(&prefix);
+        } else {
+            // Print the candidates below the line, which is then redrawn.
+            refresh(output, prompt, line)?;
+            write!(output, "\n{}\n", candidates.join("  "))?;
+        }
+
+        Ok(())
+    }
+
+    fn add_history(&mut self, text: &str) {
+        if text.trim().is_empty() || self.history.last().map(|s| s.as_str()) == Some(text) {
+            return;
+        }
+
+        if self.history.len() == MAX_HISTORY {
+            self.history.remove(0);
+        }
+
+        self.history.push(text.to_string());
+    }
+
+    /// Read a line from standard input.
+    fn read_line(
+        &mut self,
+        prompt: &str,
+        complete: &mut dyn FnMut(&str) -> Vec<String>,
+    ) -> io::Result<Option<String>> {
+        #[cfg(unix)]
+        if let Some(_raw) = RawMode::enable()? {
+            return self.edit(
+                &mut io::stdin().lock(),
+                &mut io::stdout().lock(),
+                prompt,
+                complete,
+            );
+        }
+
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(prompt.as_bytes())?;
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+}
+
+/// Redraw the line being edited.
+fn refresh(output: &mut impl Write, prompt: &str, line: &LineBuffer) -> io::Result<()> {
+    write!(output, "\r{}{}\x1b[K", prompt, line.text())?;
+
+    let after = line.chars.len() - line.cursor;
+    if after > 0 {
+        write!(output, "\x1b[{}D", after)?;
+    }
+
+    output.flush()
+}
+
+/// Puts the terminal of standard input into raw mode while alive.
+#[cfg(unix)]
+struct RawMode {
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawMode {
+    /// Enable raw mode, returning [None] if standard input or output isn't a terminal.
+    fn enable() -> io::Result<Option<Self>> {
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) != 1 || libc::isatty(libc::STDOUT_FILENO) != 1 {
+                return Ok(None);
+            }
+
+            let mut original = std::mem::MaybeUninit::<libc::termios>::uninit();
+            if libc::tcgetattr(libc::STDIN_FILENO, original.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let original = original.assume_init();
+
+            // Output processing stays enabled, so `\n` still starts a new line.
+            let mut raw = original;
+            raw.c_iflag &= !(libc::BRKINT | libc::ICRNL | libc::INPCK | libc::ISTRIP | libc::IXON);
+            raw.c_lflag &= !(libc::ECHO | libc::ICANON | libc::IEXTEN | libc::ISIG);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(Some(Self { original }))
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &self.original);
+        }
+    }
+}
+
+/// Python callable reading a line for the console.
+#[pyclass(module = "pyembed", unsendable)]
+struct ConsoleReader {
+    editor: LineEditor,
+}
+
+#[pymethods]
+impl ConsoleReader {
+    /// Read a line, calling `complete(line)` to obtain completions.
+    ///
+    /// Raises `EOFError` at the end of input and `KeyboardInterrupt` on Ctrl-C,
+    /// like `input()`.
+    fn __call__(&mut self, py: Python, prompt: &str, complete: PyObject) -> PyResult<String> {
+        let editor = &mut self.editor;
+
+        let result = py.allow_threads(|| {
+            editor.read_line(prompt, &mut |line| {
+                // Completion failures must not end the console.
+                Python::with_gil(|py| {
+                    complete
+                        .call1(py, (line,))
+                        .and_then(|v| v.extract::<Vec<String>>(py))
+                        .unwrap_or_default()
+                })
+            })
+        });
+
+        match result {
+            Ok(Some(line)) => Ok(line),
+            Ok(None) => Err(PyEOFError::new_err(())),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                Err(PyKeyboardInterrupt::new_err(()))
+            }
+            Err(e) => Err(PyOSError::new_err(e.to_string())),
+        }
+    }
+}
+
+/// Run the interactive console until the user exits it.
+///
+/// Returns the exit code requested by `SystemExit` or 0 at the end of input.
+pub(crate) fn run_console(py: Python) -> PyResult<i32> {
+    let globals = PyDict::new(py);
+    globals.set_item(
+        "_read_line",
+        PyCell::new(
+            py,
+            ConsoleReader {
+                editor: LineEditor::default(),
+            },
+        )?,
+    )?;
+    py.run(CONSOLE_CODE, Some(globals), None)?;
+
+    let console = globals
+        .get_item("console")
+        .expect("console code should define console");
+
+    match console.call_method1("interact", (py.None(), "")) {
+        Ok(_) => Ok(0),
+        Err(e) if e.is_instance_of::<PySystemExit>(py) => {
+            let code = e.value(py).getattr("code")?;
+
+            if code.is_none() {
+                Ok(0)
+            } else if let Ok(code) = code.extract::<i32>() {
+                Ok(code)
+            } else {
+                eprintln!("{}", code);
+                Ok(1)
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(
+        editor: &mut LineEditor,
+        input: &[u8],
+        complete: &mut dyn FnMut(&str) -> Vec<String>,
+    ) -> io::Result<Option<String>> {
+        let mut input = input;
+        editor.edit(&mut input, &mut vec![], ">>> ", complete)
+    }
+
+    fn no_completion(_: &str) -> Vec<String> {
+        vec![]
+    }
+
+    #[test]
+    fn test_editing() -> io::Result<()> {
+        let mut editor = LineEditor::default();
+
+        assert_eq!(
+            edit(&mut editor, b"prnt\x1b[D\x1b[Di\r", &mut no_completion)?,
+            Some("print".to_string())
+        );
+        assert_eq!(
+            edit(&mut editor, b"abc\x7f\x01\x1b[3~x\r", &mut no_completion)?,
+            Some("xb".to_string())
+        );
+        assert_eq!(
+            edit(
+                &mut editor,
+                b"one two\x1b[D\x1b[D\x15\x05!\r",
+                &mut no_completion
+            )?,
+            Some("wo!".to_string())
+        );
+        assert_eq!(
+            edit(
+                &mut editor,
+                "\u{e9}t\u{e9}\r".as_bytes(),
+                &mut no_completion
+            )?,
+            Some("\u{e9}t\u{e9}".to_string())
+        );
+
+        assert_eq!(edit(&mut editor, b"", &mut no_completion)?, None);
+        assert_eq!(edit(&mut editor, b"\x04", &mut no_completion)?, None);
+        assert_eq!(
+            edit(&mut editor, b"ab\x02\x04\r", &mut no_completion)?,
+            Some("a".to_string())
+        );
+        assert_eq!(
+            edit(&mut editor, b"ab\x03", &mut no_completion)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::Interrupted
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_history() -> io::Result<()> {
+        let mut editor = LineEditor::default();
+
+        edit(&mut editor, b"first\r", &mut no_completion)?;
+        edit(&mut editor, b"second\r", &mut no_completion)?;
+        edit(&mut editor, b"second\r", &mut no_completion)?;
+        edit(&mut editor, b"  \r", &mut no_completion)?;
+        assert_eq!(editor.history, vec!["first", "second"]);
+
+        assert_eq!(
+            edit(&mut editor, b"\x1b[A\x1b[A\r", &mut no_completion)?,
+            Some("first".to_string())
+        );
+        assert_eq!(
+            edit(&mut editor, b"new\x1b[A\x1b[B\r", &mut no_completion)?,
+            Some("new".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_completion() -> io::Result<()> {
+        let mut editor = LineEditor::default();
+        let mut complete = |line: &str| -> Vec<String> {
+            if line.ends_with("import js") {
+                vec!["json".to_string()]
+            } else if line.ends_with("os.pa") {
+                vec!["os.pardir".to_string(), "os.path".to_string()]
+            } else {
+                vec![]
+            }
+        };
+
+        assert_eq!(
+            edit(&mut editor, b"import js\t\r", &mut complete)?,
+            Some("import json".to_string())
+        );
+        assert_eq!(
+            edit(&mut editor, b"x = os.pa\tt\r", &mut complete)?,
+            Some("x = os.pat".to_string())
+        );
+        assert_eq!(
+            edit(&mut editor, b"\tpass\r", &mut complete)?,
+            Some("    pass".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        assert_eq!(
+            common_prefix(&["os.path".to_string(), "os.pardir".to_string()]),
+            "os.pa"
+        );
+        assert_eq!(common_prefix(&["json".to_string()]), "json");
+    }
+}
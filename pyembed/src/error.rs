@@ -86,3 +86,150 @@ impl NewInterpreterError {
         }
     }
 }
+
+/// Represents the Python interpreter not being available to run code.
+///
+/// This occurs when the interpreter isn't initialized or is finalizing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterpreterUnavailableError;
+
+impl Display for InterpreterUnavailableError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Python interpreter is not running")
+    }
+}
+
+impl std::error::Error for InterpreterUnavailableError {}
+
+/// A frame of the traceback of a [PythonException].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PythonTracebackFrame {
+    /// Filename of the code being executed.
+    pub filename: String,
+    /// Line number being executed, if known.
+    pub line: Option<u32>,
+    /// Name of the function being executed.
+    pub function: String,
+}
+
+/// Represents an uncaught Python exception.
+///
+/// This captures the exception as plain Rust data so it remains usable after
+/// the interpreter is finalized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PythonException {
+    /// Name of the exception type.
+    ///
+    /// Builtin exception types only have their name (e.g. `ValueError`). Other
+    /// types are qualified by their module (e.g. `json.decoder.JSONDecodeError`).
+    pub type_name: String,
+    /// `str()` of the exception value.
+    pub message: String,
+    /// The traceback as rendered by the `traceback` module, including the
+    /// trailing exception line and any chained exceptions.
+    pub traceback: String,
+    /// Frames of the traceback, outermost first.
+    pub frames: Vec<PythonTracebackFrame>,
+}
+
+impl Display for PythonException {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.message.is_empty() {
+            f.write_str(&self.type_name)
+        } else {
+            write!(f, "{}: {}", self.type_name, self.message)
+        }
+    }
+}
+
+impl std::error::Error for PythonException {}
+
+impl PythonException {
+    /// Construct an instance from a Python exception value and traceback.
+    pub fn from_exception(py: Python, value: &PyAny, traceback: Option<&PyAny>) -> PyResult<Self> {
+        let exc_type = value.get_type();
+        let module = exc_type.getattr("__module__")?.str()?.to_string_lossy();
+        let name = exc_type.getattr("__qualname__")?.str()?.to_string_lossy();
+
+        let type_name = if module == "builtins" {
+            name.to_string()
+        } else {
+            format!("{}.{}", module, name)
+        };
+
+        let message = value.str()?.to_string_lossy().to_string();
+
+        let traceback_module = py.import("traceback")?;
+        let traceback = traceback.unwrap_or_else(|| py.None().into_ref(py));
+
+        let mut rendered = String::new();
+        for line in traceback_module
+            .getattr("format_exception")?
+            .call1((exc_type, value, traceback))?
+            .iter()?
+        {
+            rendered.push_str(&line?.str()?.to_string_lossy());
+        }
+
+        let mut frames = vec![];
+        if !traceback.is_none() {
+            for frame in traceback_module
+                .getattr("extract_tb")?
+                .call1((traceback,))?
+                .iter()?
+            {
+                let frame = frame?;
+
+                frames.push(PythonTracebackFrame {
+                    filename: frame.getattr("filename")?.extract()?,
+                    line: frame.getattr("lineno")?.extract()?,
+                    function: frame.getattr("name")?.extract()?,
+                });
+            }
+        }
+
+        Ok(Self {
+            type_name,
+            message,
+            traceback: rendered,
+            frames,
+        })
+    }
+
+    /// Construct an instance from a [PyErr].
+    pub fn from_pyerr(py: Python, err: &PyErr) -> PyResult<Self> {
+        Self::from_exception(py, err.value(py), err.traceback(py).map(|tb| tb.as_ref()))
+    }
+}
+
+/// Represents a failure to evaluate code on an [crate::InterpreterThread].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InterpreterThreadError {
+    /// The interpreter thread is no longer running.
+    Unavailable(InterpreterUnavailableError),
+    /// Python raised an exception.
+    Exception(PythonException),
+}
+
+impl Display for InterpreterThreadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unavailable(e) => e.fmt(f),
+            Self::Exception(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for InterpreterThreadError {}
+
+impl From<InterpreterUnavailableError> for InterpreterThreadError {
+    fn from(e: InterpreterUnavailableError) -> Self {
+        Self::Unavailable(e)
+    }
+}
+
+impl From<PythonException> for InterpreterThreadError {
+    fn from(e: PythonException) -> Self {
+        Self::Exception(e)
+    }
+}
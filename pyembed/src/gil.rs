@@ -0,0 +1,151 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Running Python code from arbitrary threads.
+
+Threads not created by Python (such as the worker threads of an async
+runtime) need to acquire the GIL before calling into Python. Doing so is
+only safe while the interpreter is alive: acquiring the GIL after
+`Py_FinalizeEx()` is undefined behavior and acquiring it while the
+interpreter is finalizing may hang the thread forever.
+
+[with_gil_on_any_thread()] tracks the lifetime of the interpreter managed by
+[crate::MainPythonInterpreter] and refuses to run once finalization has
+started. Finalization waits for in-flight calls to complete.
+*/
+
+use {
+    crate::error::InterpreterUnavailableError,
+    once_cell::sync::Lazy,
+    pyo3::{ffi as pyffi, prelude::*, types::PyCFunction},
+    std::{cell::Cell, sync::RwLock},
+};
+
+/// Whether the interpreter accepts calls from [with_gil_on_any_thread()].
+///
+/// Calls hold a read lock for their duration. Finalization acquires the write
+/// lock, so it waits for in-flight calls.
+static INTERPRETER_AVAILABLE: Lazy<RwLock<bool>> = Lazy::new(|| RwLock::new(false));
+
+thread_local! {
+    /// Number of active [with_gil_on_any_thread()] calls on the current thread.
+    ///
+    /// Nested calls don't take the lock again, as this could deadlock against
+    /// a pending finalization.
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Decrements [CALL_DEPTH] when dropped, including when unwinding.
+struct CallDepthGuard;
+
+impl CallDepthGuard {
+    fn new() -> Self {
+        CALL_DEPTH.with(|depth| depth.set(depth.get() + 1));
+        Self
+    }
+}
+
+impl Drop for CallDepthGuard {
+    fn drop(&mut self) {
+        CALL_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+extern "C" {
+    fn _PyThreadState_UncheckedGet() -> *mut pyffi::PyThreadState;
+}
+
+/// Whether the current thread holds the GIL of the main interpreter.
+///
+/// `PyGILState_Check()` can't be used, as it always returns 1 once a
+/// subinterpreter has been created.
+fn holds_gil() -> bool {
+    let tstate = unsafe { pyffi::PyGILState_GetThisThreadState() };
+
+    !tstate.is_null() && tstate == unsafe { _PyThreadState_UncheckedGet() }
+}
+
+/// Run a function with the GIL held from any thread.
+///
+/// The GIL is acquired via `PyGILState_Ensure()` and released when the
+/// function returns or panics. The calling thread doesn't need to be known to
+/// Python.
+///
+/// An error is returned if the interpreter isn't initialized or is
+/// finalizing. Finalization of the interpreter is delayed until the function
+/// returns, so the function must not wait on the thread finalizing the
+/// interpreter.
+pub fn with_gil_on_any_thread<F, R>(f: F) -> Result<R, InterpreterUnavailableError>
+where
+    F: for<'py> FnOnce(Python<'py>) -> R,
+{
+    // Nested calls are covered by the outer call. And threads already holding
+    // the GIL can't race finalization, which requires the GIL.
+    if CALL_DEPTH.with(|depth| depth.get()) > 0
+        || (unsafe { pyffi::Py_IsInitialized() } != 0 && holds_gil())
+    {
+        let _depth = CallDepthGuard::new();
+
+        return Ok(Python::with_gil(f));
+    }
+
+    let available = INTERPRETER_AVAILABLE
+        .read()
+        .unwrap_or_else(|e| e.into_inner());
+
+    if !*available {
+        return Err(InterpreterUnavailableError);
+    }
+
+    // The interpreter may have been finalized out-of-band.
+    if unsafe { pyffi::Py_IsInitialized() } == 0 {
+        return Err(InterpreterUnavailableError);
+    }
+
+    let _depth = CallDepthGuard::new();
+
+    Ok(Python::with_gil(f))
+}
+
+/// Make the interpreter available to [with_gil_on_any_thread()].
+///
+/// An `atexit` handler is registered so the interpreter is made unavailable
+/// when it starts finalizing, including when finalization isn't initiated by
+/// us (e.g. by `Py_RunMain()`).
+pub(crate) fn register_interpreter(py: Python) -> PyResult<()> {
+    let handler = PyCFunction::new_closure(
+        py,
+        Some("pyembed_unregister_interpreter"),
+        None,
+        |_args, _kwargs| {
+            unregister_interpreter();
+        },
+    )?;
+
+    py.import("atexit")?.call_method1("register", (handler,))?;
+
+    *INTERPRETER_AVAILABLE
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = true;
+
+    Ok(())
+}
+
+/// Make the interpreter unavailable to [with_gil_on_any_thread()].
+///
+/// Waits for in-flight calls to complete. If the current thread holds the
+/// GIL, it is released while waiting so these calls can make progress.
+pub(crate) fn unregister_interpreter() {
+    let mark_unavailable = || {
+        *INTERPRETER_AVAILABLE
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = false;
+    };
+
+    if unsafe { pyffi::Py_IsInitialized() } != 0 && holds_gil() {
+        unsafe { Python::assume_gil_acquired() }.allow_threads(mark_unavailable);
+    } else {
+        mark_unavailable();
+    }
+}
@@ -6,29 +6,49 @@
 
 use {
     crate::{
+        audit::AuditHook,
         config::{OxidizedPythonInterpreterConfig, ResolvedOxidizedPythonInterpreterConfig},
+        console::run_console,
         conversion::osstring_to_bytes,
-        error::NewInterpreterError,
-        osutils::resolve_terminfo_dirs,
-        pyalloc::PythonMemoryAllocator,
+        error::{NewInterpreterError, PythonException},
+        gil,
+        logging::{install_logger, install_python_handler, LogFilter},
+        migrations::run_migrations,
+        osutils::{check_host_requirements, resolve_terminfo_dirs},
+        proctitle::{PyInit_setproctitle, SETPROCTITLE_MODULE_NAME},
+        pyalloc::{python_allocator_stats, PythonMemoryAllocator},
+        sandbox::apply_sandbox_policy,
+        signals::SavedSignalHandlers,
+        startup_profile::{self, write_configured_startup_profile},
+        stdio::{install_callback_streams, replace_missing_stdio, FdRedirect},
+        subinterpreter::{SubInterpreter, SubInterpreterConfig},
+        tracebacks::install_source_paths_excepthook,
     },
     once_cell::sync::Lazy,
     oxidized_importer::{
-        install_path_hook, remove_external_importers, replace_meta_path_importers, ImporterState,
-        OxidizedFinder, PyInit_oxidized_importer, PythonResourcesState, OXIDIZED_IMPORTER_NAME,
-        OXIDIZED_IMPORTER_NAME_STR,
+        install_path_hook, install_restricted_path_importer, install_zipimport_importer,
+        remove_external_importers, replace_meta_path_importers, set_allocator_stats_function,
+        set_build_info, ImporterState, OxidizedFinder, PyInit_oxidized_importer,
+        PythonResourcesState, OXIDIZED_IMPORTER_NAME, OXIDIZED_IMPORTER_NAME_STR,
     },
     pyo3::{
-        exceptions::PyRuntimeError, ffi as pyffi, prelude::*, types::PyDict, AsPyPointer,
-        PyTypeInfo,
+        exceptions::PyRuntimeError,
+        ffi as pyffi,
+        prelude::*,
+        types::{PyCFunction, PyDict, PyList},
+        AsPyPointer, PyTypeInfo,
     },
-    python_packaging::interpreter::{MultiprocessingStartMethod, TerminfoResolution},
+    python_packaging::interpreter::{MultiprocessingStartMethod, ProgramPath, TerminfoResolution},
     std::{
         collections::BTreeSet,
-        env, fs,
+        env,
+        ffi::OsString,
+        fs,
         io::Write,
         os::raw::c_char,
         path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
     },
 };
 
@@ -84,6 +104,10 @@ static GLOBAL_INTERPRETER_GUARD: Lazy<std::sync::Mutex<()>> =
 /// inadvertently attempt to operate on a finalized interpreter. Therefore
 /// it is recommended to always go through a method on an [MainPythonInterpreter]
 /// instance in order to interact with the Python interpreter.
+///
+/// Threads not holding a reference to the instance (such as the worker threads
+/// of an async runtime) can call [crate::with_gil_on_any_thread()], which refuses
+/// to run once the interpreter starts finalizing.
 pub struct MainPythonInterpreter<'interpreter, 'resources: 'interpreter> {
     // It is possible to have a use-after-free if config is dropped before the
     // interpreter is finalized/dropped.
@@ -92,6 +116,10 @@ pub struct MainPythonInterpreter<'interpreter, 'resources: 'interpreter> {
     pub(crate) allocator: Option<PythonMemoryAllocator>,
     /// File to write containing list of modules when the interpreter finalizes.
     write_modules_path: Option<PathBuf>,
+    /// Redirected standard file descriptors, restored after finalization.
+    stdio_redirects: Vec<FdRedirect>,
+    /// Audit hook state. Dropped after finalization, which removes the hook.
+    audit_hook: Option<Box<AuditHook>>,
 }
 
 impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
@@ -101,7 +129,20 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
     pub fn new(
         config: OxidizedPythonInterpreterConfig<'resources>,
     ) -> Result<MainPythonInterpreter<'interpreter, 'resources>, NewInterpreterError> {
-        let config: ResolvedOxidizedPythonInterpreterConfig<'resources> = config.try_into()?;
+        startup_profile::start(
+            config
+                .startup_profile_env
+                .as_ref()
+                .and_then(env::var_os)
+                .map(PathBuf::from),
+        );
+
+        let config: ResolvedOxidizedPythonInterpreterConfig<'resources> =
+            startup_profile::phase("resolve config", || config.try_into())?;
+
+        if let Some(requirements) = &config.host_requirements {
+            check_host_requirements(requirements).map_err(NewInterpreterError::Dynamic)?;
+        }
 
         match config.terminfo_resolution {
             TerminfoResolution::Dynamic => {
@@ -120,9 +161,11 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             interpreter_guard: None,
             allocator: None,
             write_modules_path: None,
+            stdio_redirects: vec![],
+            audit_hook: None,
         };
 
-        res.init()?;
+        startup_profile::phase("initialize", || res.init())?;
 
         Ok(res)
     }
@@ -145,17 +188,58 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             NewInterpreterError::Simple("unable to acquire global interpreter guard")
         })?);
 
+        if self.config.site_module.is_some() {
+            if !self.config.oxidized_importer {
+                return Err(NewInterpreterError::Simple(
+                    "site_module requires oxidized_importer to be enabled",
+                ));
+            }
+
+            if self.config.interpreter_config.site_import == Some(false) {
+                return Err(NewInterpreterError::Simple(
+                    "site_module has no effect when site_import is disabled",
+                ));
+            }
+        }
+
         if let Some(tcl_library) = &self.config.tcl_library {
             std::env::set_var("TCL_LIBRARY", tcl_library);
         }
 
+        if self.config.stdio_callback_fds {
+            for (fd, callback) in [
+                (1, self.config.stdout_callback),
+                (2, self.config.stderr_callback),
+            ] {
+                if let Some(callback) = callback {
+                    self.stdio_redirects
+                        .push(FdRedirect::new(fd, callback).map_err(NewInterpreterError::Dynamic)?);
+                }
+            }
+        }
+
+        let host_signal_handlers = SavedSignalHandlers::take(&self.config.host_signals)
+            .map_err(NewInterpreterError::Dynamic)?;
+
+        if let Some(destination) = &self.config.log_destination {
+            let filter = LogFilter::resolve(
+                self.config.log_filter.as_deref(),
+                self.config.log_filter_env.as_deref(),
+            )
+            .map_err(NewInterpreterError::Dynamic)?;
+
+            install_logger(destination, &filter, self.config.log_register_event_source)
+                .map_err(NewInterpreterError::Dynamic)?;
+        }
+
         set_pyimport_inittab(&self.config);
 
         // Pre-configure Python.
         let pre_config = pyffi::PyPreConfig::try_from(&self.config)?;
 
         unsafe {
-            let status = pyffi::Py_PreInitialize(&pre_config);
+            let status =
+                startup_profile::phase("pre-initialize", || pyffi::Py_PreInitialize(&pre_config));
 
             if pyffi::PyStatus_Exception(status) != 0 {
                 return Err(NewInterpreterError::new_from_pystatus(
@@ -169,6 +253,10 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         self.allocator = PythonMemoryAllocator::from_backend(self.config.allocator_backend);
 
         if let Some(allocator) = &self.allocator {
+            if self.config.allocator_stats {
+                allocator.enable_stats();
+            }
+
             if self.config.allocator_raw {
                 allocator.set_allocator(pyffi::PyMemAllocatorDomain::PYMEM_DOMAIN_RAW);
             }
@@ -198,13 +286,23 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             }
         }
 
+        // The hook is allocated with the raw domain allocator. So it must be
+        // added after custom allocators are installed.
+        if let Some(callback) = self.config.audit_callback {
+            let hook = Box::new(AuditHook::new(callback, self.config.audit_events.clone()));
+            unsafe { hook.install() }?;
+            self.audit_hook = Some(hook);
+        }
+
         let mut py_config: pyffi::PyConfig = (&self.config).try_into()?;
 
         // Enable multi-phase initialization. This allows us to initialize
         // our custom importer before Python attempts any imports.
         py_config._init_main = 0;
 
-        let status = unsafe { pyffi::Py_InitializeFromConfig(&py_config) };
+        let status = startup_profile::phase("initialize core", || unsafe {
+            pyffi::Py_InitializeFromConfig(&py_config)
+        });
         if unsafe { pyffi::PyStatus_Exception(status) } != 0 {
             return Err(NewInterpreterError::new_from_pystatus(
                 &status,
@@ -221,7 +319,9 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         // inject our custom importer.
 
         let oxidized_finder_loaded =
-            unsafe { Python::with_gil_unchecked(|py| self.inject_oxidized_importer(py))? };
+            startup_profile::phase("inject oxidized importer", || unsafe {
+                Python::with_gil_unchecked(|py| self.inject_oxidized_importer(py))
+            })?;
 
         // The GIL is still held after calling into PyO3.
         debug_assert_eq!(unsafe { pyffi::PyGILState_Check() }, 1);
@@ -229,7 +329,8 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         // Now proceed with the Python main initialization. This will initialize
         // importlib. And if the custom importlib bytecode was registered above,
         // our extension module will get imported and initialized.
-        let status = unsafe { pyffi::_Py_InitializeMain() };
+        let status =
+            startup_profile::phase("initialize main", || unsafe { pyffi::_Py_InitializeMain() });
         if unsafe { pyffi::PyStatus_Exception(status) } != 0 {
             return Err(NewInterpreterError::new_from_pystatus(
                 &status,
@@ -240,14 +341,18 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         // The GIL is held after finishing initialization.
         debug_assert_eq!(unsafe { pyffi::PyGILState_Check() }, 1);
 
+        // Python installed its signal handlers during main initialization.
+        std::mem::drop(host_signal_handlers);
+
         // We release the GIL so we can have pyo3's GIL handling take over from
         // an "empty" state. This mirrors what pyo3's prepare_freethreaded_python() does.
         unsafe {
             pyffi::PyEval_SaveThread();
         }
 
-        self.write_modules_path =
-            self.with_gil(|py| self.init_post_main(py, oxidized_finder_loaded))?;
+        self.write_modules_path = startup_profile::phase("post-initialize", || {
+            self.with_gil(|py| self.init_post_main(py, oxidized_finder_loaded))
+        })?;
 
         debug_assert_eq!(unsafe { pyffi::PyGILState_Check() }, 0);
 
@@ -272,30 +377,26 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             NewInterpreterError::new_from_pyerr(py, err, "import of oxidized importer module")
         })?;
 
-        let cb = |importer_state: &mut ImporterState| match self.config.multiprocessing_start_method
-        {
-            MultiprocessingStartMethod::None => {}
-            MultiprocessingStartMethod::Fork
-            | MultiprocessingStartMethod::ForkServer
-            | MultiprocessingStartMethod::Spawn => {
-                importer_state.set_multiprocessing_set_start_method(Some(
-                    self.config.multiprocessing_start_method.to_string(),
-                ));
-            }
-            MultiprocessingStartMethod::Auto => {
-                // Windows uses "spawn" because "fork" isn't available.
-                // Everywhere else uses "fork." The default on macOS is "spawn." This
-                // is due to https://bugs.python.org/issue33725, which only affects
-                // Python framework builds. Our assumption is we aren't using a Python
-                // framework, so "spawn" is safe.
-                let method = if cfg!(target_family = "windows") {
-                    "spawn"
-                } else {
-                    "fork"
-                };
+        if self.config.allocator_stats {
+            wrap_pyfunction!(python_allocator_stats, py)
+                .and_then(|f| set_allocator_stats_function(oxidized_importer, f))
+                .map_err(|err| {
+                    NewInterpreterError::new_from_pyerr(
+                        py,
+                        err,
+                        "setting oxidized_importer.allocator.stats",
+                    )
+                })?;
+        }
 
-                importer_state.set_multiprocessing_set_start_method(Some(method.to_string()));
-            }
+        if let Some(build_info) = &self.config.build_info {
+            set_build_info(py, oxidized_importer, build_info).map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "setting oxidized_importer.build_info")
+            })?;
+        }
+
+        let cb = |importer_state: &mut ImporterState| {
+            configure_importer_state(&self.config, importer_state)
         };
 
         // Ownership of the resources state is transferred into the importer, where the Box
@@ -354,8 +455,18 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             remove_external_importers(sys_module).map_err(|err| {
                 NewInterpreterError::new_from_pyerr(py, err, "removing external importers")
             })?;
+
+            if self.config.zipimport_importer {
+                install_zipimport_importer(py, sys_module).map_err(|err| {
+                    NewInterpreterError::new_from_pyerr(py, err, "installing zipimport importer")
+                })?;
+            }
         }
 
+        install_extra_sys_paths(py, sys_module, &self.config).map_err(|err| {
+            NewInterpreterError::new_from_pyerr(py, err, "installing extra sys.path entries")
+        })?;
+
         // We aren't able to hold a &PyAny to OxidizedFinder through multi-phase interpreter
         // initialization. So recover an instance now if it is available.
         let oxidized_finder = if oxidized_finder_loaded {
@@ -384,6 +495,10 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             None
         };
 
+        gil::register_interpreter(py).map_err(|err| {
+            NewInterpreterError::new_from_pyerr(py, err, "registering interpreter lifetime hooks")
+        })?;
+
         if let Some(Ok(finder)) = oxidized_finder {
             install_path_hook(finder, sys_module).map_err(|err| {
                 NewInterpreterError::new_from_pyerr(
@@ -450,6 +565,40 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             }
         }
 
+        if let Some(value) = program_path_value(&self.config.sys_executable) {
+            let value = value.to_object(py);
+
+            for name in [&b"executable\0"[..], &b"_base_executable\0"[..]] {
+                match unsafe {
+                    pyffi::PySys_SetObject(name.as_ptr() as *const c_char, value.as_ptr())
+                } {
+                    0 => (),
+                    _ => return Err(NewInterpreterError::Simple("unable to set sys.executable")),
+                }
+            }
+        }
+
+        if let Some(value) = program_path_value(&self.config.sys_argv0) {
+            py.import("sys")
+                .and_then(|sys| sys.getattr("argv"))
+                .and_then(|argv| argv.set_item(0, value))
+                .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "setting sys.argv[0]"))?;
+        }
+
+        // Functions registered with atexit run however the interpreter is
+        // finalized, including by Py_RunMain().
+        if startup_profile::is_enabled() {
+            wrap_pyfunction!(write_configured_startup_profile, py)
+                .and_then(|f| py.import("atexit")?.call_method1("register", (f,)))
+                .map_err(|e| {
+                    NewInterpreterError::new_from_pyerr(
+                        py,
+                        e,
+                        "registering startup profile writer with atexit",
+                    )
+                })?;
+        }
+
         let write_modules_path = if let Some(key) = &self.config.write_modules_directory_env {
             if let Ok(path) = std::env::var(key) {
                 let path = PathBuf::from(path);
@@ -487,6 +636,45 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             None
         };
 
+        if !self.config.stdio_callback_fds {
+            install_callback_streams(py, self.config.stdout_callback, self.config.stderr_callback)
+                .map_err(|e| {
+                    NewInterpreterError::new_from_pyerr(py, e, "installing stdio callbacks")
+                })?;
+        }
+
+        if self.config.replace_missing_stdio {
+            replace_missing_stdio(py).map_err(|e| {
+                NewInterpreterError::new_from_pyerr(py, e, "replacing missing stdio")
+            })?;
+        }
+
+        if self.config.log_destination.is_some() {
+            install_python_handler(py).map_err(|e| {
+                NewInterpreterError::new_from_pyerr(py, e, "installing logging handler")
+            })?;
+        }
+
+        if self.config.traceback_source_paths {
+            install_source_paths_excepthook(py).map_err(|e| {
+                NewInterpreterError::new_from_pyerr(py, e, "installing traceback excepthook")
+            })?;
+        }
+
+        for hook in &self.config.startup_hooks {
+            hook(py)
+                .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "running startup hook"))?;
+        }
+
+        if let Some(app_name) = &self.config.migrations_app_name {
+            run_migrations(py, &self.config.migrations, app_name)?;
+        }
+
+        if let Some(policy) = &self.config.sandbox_policy {
+            apply_sandbox_policy(policy, self.config.origin())
+                .map_err(NewInterpreterError::Dynamic)?;
+        }
+
         Ok(write_modules_path)
     }
 
@@ -502,6 +690,17 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         Python::with_gil(f)
     }
 
+    /// Create an isolated subinterpreter.
+    ///
+    /// The subinterpreter has its own modules and `OxidizedFinder`, which
+    /// imports from the resources of this interpreter. See [SubInterpreter].
+    pub fn new_subinterpreter(
+        &self,
+        config: SubInterpreterConfig,
+    ) -> Result<SubInterpreter<'_>, NewInterpreterError> {
+        SubInterpreter::new(&self.config, config)
+    }
+
     /// Runs `Py_RunMain()` and finalizes the interpreter.
     ///
     /// This will execute whatever is configured by the Python interpreter config
@@ -522,11 +721,34 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         }
     }
 
+    /// Runs an interactive console with line editing and finalizes the interpreter.
+    ///
+    /// The console runs until the end of input or until `SystemExit` is
+    /// raised. Returns an integer suitable for use as a process exit code.
+    ///
+    /// See [OxidizedPythonInterpreterConfig::console] for more.
+    pub fn run_console(self) -> i32 {
+        match self.with_gil(run_console) {
+            Ok(code) => code,
+            Err(e) => {
+                self.with_gil(|py| {
+                    e.print(py);
+                });
+
+                1
+            }
+        }
+    }
+
     /// Run in "multiprocessing worker" mode.
     ///
     /// This should be called when `sys.argv[1] == "--multiprocessing-fork"`. It
     /// will parse arguments for the worker from `sys.argv` and call into the
     /// `multiprocessing` module to perform work.
+    ///
+    /// It should also be called when `sys.argv` runs the code of a
+    /// `multiprocessing` helper process, such as the forkserver, via `-c`. The
+    /// code is then run in `__main__`, as `python -c` would.
     pub fn run_multiprocessing(&self) -> PyResult<i32> {
         // This code effectively reimplements multiprocessing.spawn.freeze_support(),
         // except entirely in the Rust domain. This function effectively verifies
@@ -537,6 +759,22 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
 
         let argv = self.config.resolve_sys_argv().to_vec();
 
+        if let Some((code, args)) = multiprocessing_helper_code(&argv) {
+            return self.with_gil(|py| {
+                // `spawn_main()` inspects sys.argv. So set it as `python -c` would.
+                let sys_argv = PyList::new(py, [OsString::from("-c")]);
+                for arg in args {
+                    sys_argv.append(arg)?;
+                }
+                py.import("sys")?.setattr("argv", sys_argv)?;
+
+                let globals = py.import("__main__")?.dict();
+                py.run(code, Some(globals), None)?;
+
+                Ok(0)
+            });
+        }
+
         if argv.len() < 2 {
             panic!("run_multiprocessing() called prematurely; sys.argv does not indicate multiprocessing mode");
         }
@@ -573,7 +811,7 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             }
 
             let spawn_module = py.import("multiprocessing.spawn")?;
-            spawn_module.getattr("spawn_main")?.call1((kwargs,))?;
+            spawn_module.getattr("spawn_main")?.call((), Some(kwargs))?;
 
             Ok(0)
         })
@@ -582,12 +820,15 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
     /// Whether the Python interpreter is in "multiprocessing worker" mode.
     ///
     /// The `multiprocessing` module can work by spawning new processes
-    /// with arguments `--multiprocessing-fork [key=value] ...`. This function
-    /// detects if the current Python interpreter is configured for said execution.
+    /// with arguments `--multiprocessing-fork [key=value] ...`. The forkserver
+    /// and resource tracker processes are instead started with the arguments
+    /// `[interpreter flags] -c <code>`. This function detects if the current
+    /// Python interpreter is configured for said execution.
     pub fn is_multiprocessing(&self) -> bool {
         let argv = self.config.resolve_sys_argv();
 
-        argv.len() >= 2 && argv[1] == "--multiprocessing-fork"
+        (argv.len() >= 2 && argv[1] == "--multiprocessing-fork")
+            || multiprocessing_helper_code(argv).is_some()
     }
 
     /// Runs the Python interpreter.
@@ -596,6 +837,9 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
     /// current process invocation appears to be a spawned multiprocessing worker
     /// and dispatch to multiprocessing accordingly.
     ///
+    /// If [OxidizedPythonInterpreterConfig::console] is set, this delegates
+    /// to [Self::run_console].
+    ///
     /// Otherwise, this delegates to [Self::py_runmain].
     pub fn run(self) -> i32 {
         if self.config.multiprocessing_auto_dispatch && self.is_multiprocessing() {
@@ -609,10 +853,362 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
                     1
                 }
             }
+        } else if self.config.console {
+            self.run_console()
+        } else {
+            self.py_runmain()
+        }
+    }
+
+    /// Runs the Python interpreter, returning an uncaught exception to the caller.
+    ///
+    /// This behaves like [Self::run]. But if Python exits because of an uncaught
+    /// exception, the exception is returned instead of the exit code. The
+    /// exception is still printed to `sys.stderr`.
+    ///
+    /// Uncaught exceptions are intercepted by wrapping `sys.excepthook`. So
+    /// Python code replacing `sys.excepthook` prevents them from being returned.
+    /// `SystemExit` isn't considered an uncaught exception.
+    pub fn try_run(self) -> Result<i32, PythonException> {
+        if self.config.multiprocessing_auto_dispatch && self.is_multiprocessing() {
+            return self.run_multiprocessing().map_err(|e| {
+                self.with_gil(|py| {
+                    e.print(py);
+
+                    exception_from_value(py, e.value(py), e.traceback(py).map(|tb| tb.as_ref()))
+                })
+            });
+        }
+
+        let captured = Arc::new(Mutex::new(None));
+
+        if let Err(e) = self.with_gil(|py| install_excepthook(py, captured.clone())) {
+            return Err(self.with_gil(|py| {
+                exception_from_value(py, e.value(py), e.traceback(py).map(|tb| tb.as_ref()))
+            }));
+        }
+
+        let code = if self.config.console {
+            self.run_console()
         } else {
             self.py_runmain()
+        };
+
+        // The interactive interpreter prints exceptions via sys.excepthook
+        // without exiting. So only report an exception if it made Python fail.
+        let exception = captured.lock().unwrap().take();
+        match exception {
+            Some(exception) if code != 0 => Err(exception),
+            _ => Ok(code),
+        }
+    }
+
+    /// Finalize the interpreter, waiting a limited time for Python threads to finish.
+    ///
+    /// `atexit` handlers run first, so they can signal threads to stop. Then
+    /// non-daemon threads are joined until `timeout` elapses. Finalization
+    /// normally waits for non-daemon threads indefinitely. Threads still
+    /// running at the deadline are instead treated like daemon threads: they
+    /// are frozen when they next try to run Python code.
+    ///
+    /// Returns the threads which were still running when the interpreter was
+    /// finalized.
+    pub fn shutdown(self, timeout: Duration) -> Vec<LingeringThread> {
+        if unsafe { pyffi::Py_IsInitialized() } == 0 {
+            return vec![];
+        }
+
+        let lingering = self.with_gil(|py| {
+            join_threads(py, timeout).unwrap_or_else(|e| {
+                e.print(py);
+                vec![]
+            })
+        });
+
+        std::mem::drop(self);
+
+        lingering
+    }
+}
+
+/// A Python thread still running when [MainPythonInterpreter::shutdown] finalized the interpreter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LingeringThread {
+    /// Name of the thread.
+    pub name: String,
+    /// Identifier of the thread, as reported by `threading.get_ident()`.
+    pub ident: u64,
+    /// The stack of the thread, formatted by `traceback.format_stack()`.
+    pub stack: String,
+}
+
+/// Run `atexit` handlers and join non-daemon threads until `timeout` elapses.
+///
+/// Returns threads still running afterwards.
+fn join_threads(py: Python, timeout: Duration) -> PyResult<Vec<LingeringThread>> {
+    py.import("atexit")?.call_method0("_run_exitfuncs")?;
+
+    let sys = py.import("sys")?;
+
+    // No threads can have been started through the threading module if it
+    // isn't loaded.
+    let threading = if let Ok(threading) = sys.getattr("modules")?.get_item("threading") {
+        threading
+    } else {
+        return Ok(vec![]);
+    };
+
+    // Callbacks registered with threading._register_atexit() signal threads
+    // like those of concurrent.futures to stop. threading._shutdown() runs them
+    // before joining threads during finalization. Run them now instead.
+    if let Ok(callbacks) = threading.getattr("_threading_atexits") {
+        threading.setattr("_SHUTTING_DOWN", true)?;
+
+        for callback in callbacks.call_method0("copy")?.iter()? {
+            callback?.call0()?;
+        }
+
+        callbacks.call_method0("clear")?;
+    }
+
+    let deadline = Instant::now() + timeout;
+    let main_thread = threading.call_method0("main_thread")?;
+    let mut lingering = vec![];
+
+    for thread in threading.call_method0("enumerate")?.iter()? {
+        let thread = thread?;
+
+        if thread.is(main_thread) || thread.getattr("daemon")?.is_true()? {
+            continue;
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        thread.call_method1("join", (remaining.as_secs_f64(),))?;
+
+        if thread.call_method0("is_alive")?.is_true()? {
+            lingering.push(thread);
+        }
+    }
+
+    if lingering.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // Stop finalization from joining threads.
+    threading.setattr("_shutdown", py.eval("lambda: None", None, None)?)?;
+
+    let frames = sys.call_method0("_current_frames")?;
+    let format_stack = py.import("traceback")?.getattr("format_stack")?;
+
+    lingering
+        .into_iter()
+        .map(|thread| {
+            let ident = thread.getattr("ident")?;
+
+            let mut stack = String::new();
+            if let Ok(frame) = frames.get_item(ident) {
+                for line in format_stack.call1((frame,))?.iter()? {
+                    stack.push_str(&line?.str()?.to_string_lossy());
+                }
+            }
+
+            Ok(LingeringThread {
+                name: thread.getattr("name")?.extract()?,
+                ident: ident.extract()?,
+                stack,
+            })
+        })
+        .collect()
+}
+
+/// Code run by `multiprocessing` in helper processes via `-c`.
+///
+/// Unless `sys.frozen` is set, workers of the `spawn` start method are started
+/// this way too.
+const MULTIPROCESSING_HELPER_PREFIXES: &[&str] = &[
+    "from multiprocessing.spawn import spawn_main",
+    "from multiprocessing.forkserver import main",
+    "from multiprocessing.resource_tracker import main",
+    "from multiprocessing.semaphore_tracker import main",
+];
+
+/// Obtain the code of a `multiprocessing` helper process from its arguments.
+///
+/// `multiprocessing` starts the forkserver and resource tracker processes
+/// with the interpreter flags of the parent followed by `-c <code>`. The
+/// process runs the executable of the parent, so interpreters which don't
+/// parse arguments would run the application instead, which would start
+/// helper processes of its own.
+///
+/// Returns the code and the arguments following it.
+fn multiprocessing_helper_code(argv: &[OsString]) -> Option<(&str, &[OsString])> {
+    let mut args = argv.get(1..)?.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.to_str()? {
+            "-c" => {
+                let code = args.next()?.to_str()?;
+
+                return if MULTIPROCESSING_HELPER_PREFIXES
+                    .iter()
+                    .any(|prefix| code.starts_with(prefix))
+                {
+                    Some((code, args.as_slice()))
+                } else {
+                    None
+                };
+            }
+            // These flags take a value.
+            "-W" | "-X" => {
+                args.next()?;
+            }
+            arg if arg.starts_with('-') => {}
+            _ => return None,
         }
     }
+
+    None
+}
+
+/// Obtain the value of a resolved [ProgramPath], or [None] to keep the value of Python.
+fn program_path_value(value: &ProgramPath) -> Option<&str> {
+    match value {
+        ProgramPath::Default => None,
+        ProgramPath::Empty => Some(""),
+        ProgramPath::Static(value) => Some(value),
+        ProgramPath::Exe | ProgramPath::Invoked => {
+            panic!("program path should have been resolved")
+        }
+    }
+}
+
+/// Configure the state of an `OxidizedFinder` according to an interpreter config.
+pub(crate) fn configure_importer_state(
+    config: &ResolvedOxidizedPythonInterpreterConfig,
+    importer_state: &mut ImporterState,
+) {
+    importer_state.set_lazy_import_packages(config.lazy_import_packages.clone());
+    importer_state.set_filesystem_first_packages(config.filesystem_first_packages.clone());
+    importer_state.set_materialized_files_dir(config.materialized_files_dir.clone());
+
+    if startup_profile::is_enabled() {
+        importer_state.set_import_timing_fn(Some(startup_profile::record_import));
+    }
+
+    match config.multiprocessing_start_method {
+        MultiprocessingStartMethod::None => {}
+        MultiprocessingStartMethod::Fork
+        | MultiprocessingStartMethod::ForkServer
+        | MultiprocessingStartMethod::Spawn => {
+            importer_state.set_multiprocessing_set_start_method(Some(
+                config.multiprocessing_start_method.to_string(),
+            ));
+        }
+        MultiprocessingStartMethod::Auto => {
+            importer_state.set_multiprocessing_set_start_method(Some(
+                auto_multiprocessing_start_method().to_string(),
+            ));
+        }
+    }
+
+    // Processes started by the spawn and forkserver start methods must run
+    // this executable in order to be dispatched. sys.executable may point
+    // elsewhere.
+    if let Some(value) = program_path_value(&config.multiprocessing_executable) {
+        importer_state.set_multiprocessing_set_executable(Some(PathBuf::from(value)));
+    } else if config.multiprocessing_auto_dispatch {
+        importer_state.set_multiprocessing_set_executable(Some(config.exe().to_path_buf()));
+    }
+}
+
+/// Append the configured extra directories to `sys.path`.
+///
+/// If the filesystem importer is disabled, a restricted path based importer
+/// is installed so modules can be imported from these directories only.
+pub(crate) fn install_extra_sys_paths(
+    py: Python,
+    sys_module: &PyModule,
+    config: &ResolvedOxidizedPythonInterpreterConfig,
+) -> PyResult<()> {
+    let paths = config.resolve_extra_sys_paths();
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    let sys_path = sys_module.getattr("path")?;
+    for path in &paths {
+        sys_path.call_method1("append", (path.display().to_string(),))?;
+    }
+
+    if !config.filesystem_importer {
+        install_restricted_path_importer(py, sys_module, paths)?;
+    }
+
+    Ok(())
+}
+
+/// The `multiprocessing` start method [MultiprocessingStartMethod::Auto] resolves to.
+///
+/// Windows uses `spawn` because `fork` isn't available. macOS uses `spawn`
+/// too, as system frameworks aren't safe to use in a forked child, which can
+/// crash or hang. This is Python's default on macOS as well. Everywhere else
+/// uses `fork`, which is fastest.
+fn auto_multiprocessing_start_method() -> MultiprocessingStartMethod {
+    if cfg!(any(target_family = "windows", target_os = "macos")) {
+        MultiprocessingStartMethod::Spawn
+    } else {
+        MultiprocessingStartMethod::Fork
+    }
+}
+
+/// Convert a Python exception to a [PythonException], even if inspecting it fails.
+pub(crate) fn exception_from_value(
+    py: Python,
+    value: &PyAny,
+    traceback: Option<&PyAny>,
+) -> PythonException {
+    PythonException::from_exception(py, value, traceback).unwrap_or_else(|_| PythonException {
+        type_name: value
+            .get_type()
+            .name()
+            .unwrap_or("BaseException")
+            .to_string(),
+        message: String::new(),
+        traceback: String::new(),
+        frames: vec![],
+    })
+}
+
+/// Wrap `sys.excepthook` so the last exception it handles is stored in `captured`.
+fn install_excepthook(py: Python, captured: Arc<Mutex<Option<PythonException>>>) -> PyResult<()> {
+    let sys = py.import("sys")?;
+    let original: PyObject = sys.getattr("excepthook")?.into_py(py);
+
+    let hook = PyCFunction::new_closure(
+        py,
+        Some("excepthook"),
+        None,
+        move |args, kwargs| -> PyResult<PyObject> {
+            let py = args.py();
+
+            if let (Ok(value), Ok(traceback)) = (args.get_item(1), args.get_item(2)) {
+                let traceback = if traceback.is_none() {
+                    None
+                } else {
+                    Some(traceback)
+                };
+
+                captured
+                    .lock()
+                    .unwrap()
+                    .replace(exception_from_value(py, value, traceback));
+            }
+
+            original.call(py, args, kwargs)
+        },
+    )?;
+
+    sys.setattr("excepthook", hook)
 }
 
 static mut ORIGINAL_BUILTIN_EXTENSIONS: Option<Vec<pyffi::_inittab>> = None;
@@ -662,6 +1258,13 @@ fn set_pyimport_inittab(config: &OxidizedPythonInterpreterConfig) {
         });
     }
 
+    if config.setproctitle_module {
+        extensions.push(pyffi::_inittab {
+            name: SETPROCTITLE_MODULE_NAME.as_ptr() as *mut _,
+            initfunc: Some(PyInit_setproctitle),
+        });
+    }
+
     // Add additional extension modules from the config.
     if let Some(extra_extension_modules) = &config.extra_extension_modules {
         for extension in extra_extension_modules {
@@ -744,9 +1347,62 @@ impl<'interpreter, 'resources> Drop for MainPythonInterpreter<'interpreter, 'res
             }
         }
 
+        // Prevent other threads from running Python code once we start finalizing.
+        gil::unregister_interpreter();
+
         unsafe {
             pyffi::PyGILState_Ensure();
             pyffi::Py_FinalizeEx();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multiprocessing_helper_code() {
+        let helper = |args: &[&str]| {
+            let argv = args.iter().map(OsString::from).collect::<Vec<_>>();
+
+            multiprocessing_helper_code(&argv).map(|(code, args)| {
+                (
+                    code.to_string(),
+                    args.iter()
+                        .map(|arg| arg.to_string_lossy().to_string())
+                        .collect::<Vec<_>>(),
+                )
+            })
+        };
+
+        let code = "from multiprocessing.forkserver import main; main(3, 4, ['__main__'], **{})";
+        assert_eq!(
+            helper(&["app", "-c", code]),
+            Some((code.to_string(), vec![]))
+        );
+        assert_eq!(
+            helper(&["app", "-I", "-W", "ignore", "-X", "dev", "-c", code]),
+            Some((code.to_string(), vec![]))
+        );
+
+        let code = "from multiprocessing.resource_tracker import main;main(5)";
+        assert_eq!(
+            helper(&["app", "-B", "-c", code]),
+            Some((code.to_string(), vec![]))
+        );
+
+        let code =
+            "from multiprocessing.spawn import spawn_main; spawn_main(tracker_fd=5, pipe_handle=7)";
+        assert_eq!(
+            helper(&["app", "-c", code, "--multiprocessing-fork"]),
+            Some((code.to_string(), vec!["--multiprocessing-fork".to_string()]))
+        );
+
+        assert_eq!(helper(&[]), None);
+        assert_eq!(helper(&["app"]), None);
+        assert_eq!(helper(&["app", "-c", "import os"]), None);
+        assert_eq!(helper(&["app", "-c"]), None);
+        assert_eq!(helper(&["app", "script.py", "-c", code]), None);
+    }
+}
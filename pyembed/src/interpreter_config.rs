@@ -187,6 +187,76 @@ fn set_legacy_windows_stdio(config: &mut pyffi::PyConfig, value: bool) {
     config.legacy_windows_stdio = if value { 1 } else { 0 };
 }
 
+#[cfg(Py_3_11)]
+fn set_code_debug_ranges(
+    config: &mut pyffi::PyConfig,
+    value: bool,
+) -> Result<(), NewInterpreterError> {
+    config.code_debug_ranges = if value { 1 } else { 0 };
+    Ok(())
+}
+
+#[cfg(not(Py_3_11))]
+fn set_code_debug_ranges(
+    _config: &mut pyffi::PyConfig,
+    value: bool,
+) -> Result<(), NewInterpreterError> {
+    // Older versions don't record fine grained source locations at all.
+    if value {
+        Err(NewInterpreterError::Simple(
+            "code_debug_ranges requires Python 3.11+",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(Py_3_11)]
+fn set_safe_path(config: &mut pyffi::PyConfig, value: bool) -> Result<(), NewInterpreterError> {
+    config.safe_path = if value { 1 } else { 0 };
+    Ok(())
+}
+
+#[cfg(not(Py_3_11))]
+fn set_safe_path(_config: &mut pyffi::PyConfig, value: bool) -> Result<(), NewInterpreterError> {
+    // Older versions always behave as if this were disabled.
+    if value {
+        Err(NewInterpreterError::Simple(
+            "safe_path requires Python 3.11+",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(Py_3_12)]
+fn set_perf_profiling(
+    config: &mut pyffi::PyConfig,
+    value: bool,
+) -> Result<(), NewInterpreterError> {
+    // PyConfig.perf_profiling isn't exposed by pyo3. The `-X perf` option is
+    // equivalent.
+    if value {
+        append_wide_string_list_from_str(&mut config.xoptions, "perf", "setting perf_profiling")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(Py_3_12))]
+fn set_perf_profiling(
+    _config: &mut pyffi::PyConfig,
+    value: bool,
+) -> Result<(), NewInterpreterError> {
+    if value {
+        Err(NewInterpreterError::Simple(
+            "perf_profiling requires Python 3.12+",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
 #[cfg(target_family = "unix")]
 pub fn set_argv(
     config: &mut pyffi::PyConfig,
@@ -399,6 +469,25 @@ pub fn python_interpreter_config_to_py_config(
             )?;
         }
     }
+    if let Some(int_max_str_digits) = value.int_max_str_digits {
+        // PyConfig.int_max_str_digits is only available on Python 3.12+. But
+        // the `-X int_max_str_digits` option is also recognized by older
+        // versions having the limitation.
+        append_wide_string_list_from_str(
+            &mut config.xoptions,
+            &format!("int_max_str_digits={}", int_max_str_digits),
+            "setting int_max_str_digits",
+        )?;
+    }
+    if let Some(perf_profiling) = value.perf_profiling {
+        set_perf_profiling(&mut config, perf_profiling)?;
+    }
+    if let Some(code_debug_ranges) = value.code_debug_ranges {
+        set_code_debug_ranges(&mut config, code_debug_ranges)?;
+    }
+    if let Some(safe_path) = value.safe_path {
+        set_safe_path(&mut config, safe_path)?;
+    }
     if let Some(site_import) = value.site_import {
         config.site_import = if site_import { 1 } else { 0 };
     }
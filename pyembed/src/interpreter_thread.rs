@@ -0,0 +1,245 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Run the interpreter on a dedicated thread.
+
+[MainPythonInterpreter] runs Python on the thread that created it. Hosts
+whose main thread is taken by something else, such as the event loop of a
+GUI framework, can instead create an [InterpreterThread]. It initializes the
+interpreter on a new thread and runs callables submitted through the handle
+on that thread.
+*/
+
+use {
+    crate::{
+        config::OxidizedPythonInterpreterConfig,
+        error::{
+            InterpreterThreadError, InterpreterUnavailableError, NewInterpreterError,
+            PythonException,
+        },
+        interpreter::{exception_from_value, LingeringThread, MainPythonInterpreter},
+    },
+    pyo3::prelude::*,
+    std::{
+        panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+        sync::{
+            mpsc::{channel, sync_channel, Receiver, Sender, TryRecvError},
+            Mutex,
+        },
+        thread::JoinHandle,
+        time::Duration,
+    },
+};
+
+/// A callable to run on the interpreter thread.
+type Job = Box<dyn for<'py> FnOnce(Python<'py>) + Send>;
+
+enum Message {
+    Run(Job),
+    Shutdown(Duration),
+}
+
+/// A handle on a Python interpreter running on a dedicated thread.
+///
+/// The interpreter is initialized on a new thread by [Self::spawn()]. Callables
+/// submitted through this handle run on that thread, one at a time, with the
+/// GIL held. The GIL is released between callables, so threads started by
+/// Python code keep running.
+///
+/// Python considers the interpreter thread to be its main thread. So Python
+/// signal handlers run there.
+///
+/// Dropping the handle finalizes the interpreter once submitted callables
+/// have run and waits for the interpreter thread to exit. Call
+/// [Self::shutdown()] to limit how long finalization waits for Python
+/// threads.
+pub struct InterpreterThread {
+    sender: Mutex<Option<Sender<Message>>>,
+    thread: Option<JoinHandle<Vec<LingeringThread>>>,
+}
+
+impl InterpreterThread {
+    /// Spawn a thread and initialize a Python interpreter on it.
+    ///
+    /// Returns once the interpreter is initialized.
+    pub fn spawn(
+        config: OxidizedPythonInterpreterConfig<'static>,
+    ) -> Result<Self, NewInterpreterError> {
+        let (sender, receiver) = channel();
+        let (ready_sender, ready) = sync_channel(1);
+
+        let thread = std::thread::Builder::new()
+            .name("python".to_string())
+            .spawn(move || {
+                let interp = match MainPythonInterpreter::new(config) {
+                    Ok(interp) => {
+                        let _ = ready_sender.send(Ok(()));
+                        interp
+                    }
+                    Err(e) => {
+                        let _ = ready_sender.send(Err(e));
+                        return vec![];
+                    }
+                };
+
+                run_messages(interp, receiver)
+            })
+            .map_err(|e| {
+                NewInterpreterError::Dynamic(format!("unable to spawn interpreter thread: {}", e))
+            })?;
+
+        match ready.recv() {
+            Ok(Ok(())) => Ok(Self {
+                sender: Mutex::new(Some(sender)),
+                thread: Some(thread),
+            }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => {
+                let _ = thread.join();
+                Err(NewInterpreterError::Simple(
+                    "interpreter thread exited during initialization",
+                ))
+            }
+        }
+    }
+
+    /// Submit a callable to run on the interpreter thread without waiting for it.
+    ///
+    /// The returned [InterpreterTask] can be used to obtain the result.
+    pub fn submit<F, R>(&self, f: F) -> Result<InterpreterTask<R>, InterpreterUnavailableError>
+    where
+        F: for<'py> FnOnce(Python<'py>) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (result_sender, receiver) = sync_channel(1);
+
+        let job: Job = Box::new(move |py| {
+            let result = catch_unwind(AssertUnwindSafe(|| f(py)));
+            let _ = result_sender.send(result);
+        });
+
+        self.send(Message::Run(job))?;
+
+        Ok(InterpreterTask { receiver })
+    }
+
+    /// Run a callable on the interpreter thread and wait for its result.
+    ///
+    /// If the callable panics, the panic is resumed on the calling thread.
+    pub fn run<F, R>(&self, f: F) -> Result<R, InterpreterUnavailableError>
+    where
+        F: for<'py> FnOnce(Python<'py>) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.submit(f)?.wait()
+    }
+
+    /// Evaluate a Python expression and convert its value to a Rust type.
+    pub fn eval<T>(&self, expression: &str) -> Result<T, InterpreterThreadError>
+    where
+        T: for<'py> FromPyObject<'py> + Send + 'static,
+    {
+        let expression = expression.to_string();
+
+        let result = self.run(move |py| -> Result<T, PythonException> {
+            py.eval(&expression, None, None)
+                .and_then(|value| value.extract())
+                .map_err(|e| {
+                    exception_from_value(py, e.value(py), e.traceback(py).map(|tb| tb.as_ref()))
+                })
+        })?;
+
+        Ok(result?)
+    }
+
+    /// Finalize the interpreter, waiting a limited time for Python threads to finish.
+    ///
+    /// Callables submitted before this is called run first. See
+    /// [MainPythonInterpreter::shutdown()] for how Python threads are handled.
+    ///
+    /// Returns the threads which were still running when the interpreter was
+    /// finalized.
+    pub fn shutdown(mut self, timeout: Duration) -> Vec<LingeringThread> {
+        let _ = self.send(Message::Shutdown(timeout));
+
+        match self.thread.take().map(|thread| thread.join()) {
+            Some(Ok(lingering)) => lingering,
+            Some(Err(e)) => resume_unwind(e),
+            None => vec![],
+        }
+    }
+
+    fn send(&self, message: Message) -> Result<(), InterpreterUnavailableError> {
+        self.sender
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .ok_or(InterpreterUnavailableError)?
+            .send(message)
+            .map_err(|_| InterpreterUnavailableError)
+    }
+}
+
+impl Drop for InterpreterThread {
+    fn drop(&mut self) {
+        // Disconnecting the channel makes the interpreter thread finalize the
+        // interpreter once it has run submitted callables.
+        self.sender.lock().unwrap_or_else(|e| e.into_inner()).take();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Run callables until the handle asks for shutdown or is dropped.
+fn run_messages(
+    interp: MainPythonInterpreter,
+    receiver: Receiver<Message>,
+) -> Vec<LingeringThread> {
+    for message in receiver {
+        match message {
+            Message::Run(job) => interp.with_gil(job),
+            Message::Shutdown(timeout) => return interp.shutdown(timeout),
+        }
+    }
+
+    vec![]
+}
+
+/// The pending result of a callable submitted to an [InterpreterThread].
+pub struct InterpreterTask<R> {
+    receiver: Receiver<std::thread::Result<R>>,
+}
+
+impl<R> InterpreterTask<R> {
+    /// Wait for the callable to run and obtain its result.
+    ///
+    /// If the callable panics, the panic is resumed on the calling thread.
+    /// An error is returned if the interpreter was finalized before the
+    /// callable could run.
+    pub fn wait(self) -> Result<R, InterpreterUnavailableError> {
+        match self.receiver.recv() {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(panic)) => resume_unwind(panic),
+            Err(_) => Err(InterpreterUnavailableError),
+        }
+    }
+
+    /// Obtain the result of the callable if it has run, without waiting.
+    ///
+    /// Returns `Ok(None)` if the callable hasn't run yet. Once a result is
+    /// returned, subsequent calls return an error.
+    pub fn try_wait(&self) -> Result<Option<R>, InterpreterUnavailableError> {
+        match self.receiver.try_recv() {
+            Ok(Ok(value)) => Ok(Some(value)),
+            Ok(Err(panic)) => resume_unwind(panic),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(InterpreterUnavailableError),
+        }
+    }
+}
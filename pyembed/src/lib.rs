@@ -27,7 +27,8 @@ serves as a high-level interface for running code in the interpreter.
 # Dependencies
 
 Under the hood, `pyembed` makes direct use of the `pyo3` crate for
-low-level Python FFI bindings as well as higher-level interfacing.
+low-level Python FFI bindings as well as higher-level interfacing. The `log`
+crate is used to unify logging of Rust and Python code.
 
 **It is an explicit goal of this crate to rely on as few external dependencies
 as possible.** This is because we want to minimize bloat in produced binaries.
@@ -48,40 +49,84 @@ The optional `allocator-snmalloc` feature controls support for using
 [snmalloc](https://github.com/microsoft/snmalloc) as Python's memory allocator.
 The feature behaves similarly to `jemalloc`, which is documented above.
 
+The optional `capi` feature exposes a C API for embedding an interpreter from
+C, C++ and other languages able to call C functions. See the [capi] module. The
+`include/pyembed.h` header declares its functions. A shared library exposing
+the API is built with
+`cargo rustc -p pyembed --release --features capi --crate-type cdylib`.
+
+The optional `config-overrides` feature controls support for reading
+[OxidizedPythonInterpreterConfig::config_overrides_path] at run-time. It
+pulls in the `serde_json` and `toml` crates for parsing that file.
+
 The optional `serialization` feature controls whether configuration types
 (such as [OxidizedPythonInterpreterConfig]) implement `Serialize` and
 `Deserialize`.
+
+The optional `static-server` feature enables `oxidized_importer.serve_static()`
+for serving package resources, such as web assets, over HTTP. It doesn't pull in
+additional crates.
+
+The optional `wasm` feature enables the experimental `oxidized_importer.wasm`
+module for running extension logic compiled to WebAssembly. It pulls in
+`wasmtime`.
 */
 
 #[allow(unused)]
+mod audit;
+#[cfg(feature = "capi")]
+pub mod capi;
 mod config;
+mod config_overrides;
+mod console;
 mod conversion;
 mod error;
+mod gil;
 mod interpreter;
 mod interpreter_config;
+mod interpreter_thread;
+mod logging;
+mod migrations;
 mod osutils;
+mod proctitle;
 mod pyalloc;
+mod sandbox;
+mod signals;
+mod startup_profile;
+mod stdio;
+mod subinterpreter;
 pub mod technotes;
 #[cfg(test)]
 mod test;
+mod tracebacks;
 
 #[allow(unused_imports)]
 pub use {
     crate::{
         config::{
-            ExtensionModule, OxidizedPythonInterpreterConfig,
-            ResolvedOxidizedPythonInterpreterConfig,
+            AuditCallback, ExtensionModule, OxidizedPythonInterpreterConfig, PackedResourcesKey,
+            ResolvedOxidizedPythonInterpreterConfig, StartupHook, StdioCallback,
+        },
+        error::{
+            InterpreterThreadError, InterpreterUnavailableError, NewInterpreterError,
+            PythonException, PythonTracebackFrame,
         },
-        error::NewInterpreterError,
-        interpreter::MainPythonInterpreter,
-        pyalloc::PythonMemoryAllocator,
+        gil::with_gil_on_any_thread,
+        interpreter::{LingeringThread, MainPythonInterpreter},
+        interpreter_thread::{InterpreterTask, InterpreterThread},
+        proctitle::{process_title, set_process_title, set_thread_title, thread_title},
+        pyalloc::{allocator_stats, AllocatorStats, PythonMemoryAllocator},
+        signals::interrupt_python,
+        startup_profile::write_startup_profile,
+        subinterpreter::{SubInterpreter, SubInterpreterConfig},
     },
     oxidized_importer::{PackedResourcesSource, PythonResourcesState},
     python_packaging::{
         interpreter::{
-            Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, MemoryAllocatorBackend,
-            MultiprocessingStartMethod, PythonInterpreterConfig, PythonInterpreterProfile,
-            TerminfoResolution,
+            Allocator, BuildInfo, BytesWarning, CheckHashPycsMode, CoerceCLocale, HostRequirements,
+            LogDestination, MemoryAllocatorBackend, Migration, MultiprocessingStartMethod,
+            PackedResourcesVerification, ProgramPath, PythonInterpreterConfig,
+            PythonInterpreterProfile, SandboxPolicy, TerminfoResolution,
         },
         resource::BytecodeOptimizationLevel,
     },
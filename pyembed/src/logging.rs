@@ -0,0 +1,844 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Unify logging of Rust and Python code.
+
+Records of the `log` crate and of Python's `logging` module flow into one
+[LogDestination]:
+
+* Unless the destination is [LogDestination::Host], a `log` logger writing
+  to the destination is installed before the interpreter is initialized.
+* A handler on Python's root logger forwards Python records to the `log`
+  crate, using the logger name as target.
+
+Records are filtered by a [LogFilter]. Crates using `tracing` can reach the
+destination by enabling the `log` feature of `tracing`. On Windows, this
+includes the Windows Event Log and Event Tracing for Windows.
+*/
+
+use {
+    log::{Level, LevelFilter, Log, Metadata, Record},
+    pyo3::{prelude::*, types::PyDict},
+    python_packaging::interpreter::LogDestination,
+    std::{
+        fs::File,
+        io::Write,
+        str::FromStr,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Mutex,
+        },
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// Filter used when none is configured.
+const DEFAULT_FILTER: &str = "warn";
+
+/// Whether [install_logger()] installed a logger.
+static LOGGER_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Python code defining the handler forwarding records to Rust.
+///
+/// `_emit` is injected into the globals the code runs with.
+const HANDLER_CODE: &str = r#"
+import logging
+
+class PyembedHandler(logging.Handler):
+    """Forwards log records to Rust's log crate."""
+
+    def emit(self, record):
+        try:
+            _emit(self, record)
+        except Exception:
+            self.handleError(record)
+
+handler = PyembedHandler()
+"#;
+
+/// Per-target log levels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct LogFilter {
+    default: LevelFilter,
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    /// Parse a filter in the syntax of `RUST_LOG`.
+    ///
+    /// The filter is a comma separated list of `LEVEL`, `TARGET` and
+    /// `TARGET=LEVEL` directives. A target matches itself and targets
+    /// below it, separated by `::` for Rust modules and `.` for Python
+    /// loggers. The most specific directive for a target wins. `TARGET`
+    /// alone enables all levels.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut filter = Self {
+            default: LevelFilter::Off,
+            directives: vec![],
+        };
+
+        let level = |s: &str| {
+            LevelFilter::from_str(s.trim()).map_err(|_| format!("invalid log level: {}", s))
+        };
+
+        for directive in value.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, value)) => {
+                    filter
+                        .directives
+                        .push((target.trim().to_string(), level(value)?));
+                }
+                None => {
+                    if let Ok(level) = LevelFilter::from_str(directive) {
+                        filter.default = level;
+                    } else {
+                        filter
+                            .directives
+                            .push((directive.to_string(), LevelFilter::Trace));
+                    }
+                }
+            }
+        }
+
+        Ok(filter)
+    }
+
+    /// Resolve the filter to use.
+    ///
+    /// The value of the `env` environment variable takes precedence over
+    /// `filter` if it is set and not empty.
+    pub fn resolve(filter: Option<&str>, env: Option<&str>) -> Result<Self, String> {
+        let from_env = env
+            .and_then(std::env::var_os)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string_lossy().to_string());
+
+        let value = from_env.as_deref().or(filter).unwrap_or(DEFAULT_FILTER);
+
+        Self::parse(value)
+    }
+
+    /// The most verbose level enabled for a target.
+    pub fn level(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .filter(|(prefix, _)| target_matches(target, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    /// The most verbose level enabled for any target.
+    pub fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, std::cmp::max)
+    }
+}
+
+fn target_matches(target: &str, prefix: &str) -> bool {
+    matches!(
+        target.strip_prefix(prefix),
+        Some(rest) if rest.is_empty() || rest.starts_with("::") || rest.starts_with('.')
+    )
+}
+
+/// Convert a Python logging level to a `log` level.
+fn log_level(levelno: i64) -> Level {
+    match levelno {
+        l if l >= 40 => Level::Error,
+        l if l >= 30 => Level::Warn,
+        l if l >= 20 => Level::Info,
+        l if l >= 10 => Level::Debug,
+        _ => Level::Trace,
+    }
+}
+
+/// Convert a `log` level filter to a Python logging level.
+fn python_level(level: LevelFilter) -> i64 {
+    match level {
+        LevelFilter::Off => 100,
+        LevelFilter::Error => 40,
+        LevelFilter::Warn => 30,
+        LevelFilter::Info => 20,
+        LevelFilter::Debug => 10,
+        LevelFilter::Trace => 1,
+    }
+}
+
+/// Format a time as an RFC 3339 UTC timestamp with millisecond precision.
+fn format_timestamp(time: SystemTime) -> String {
+    let duration = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = duration.as_secs();
+
+    // Convert days since the epoch to a civil date. See
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let z = secs / 86400 + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    let time = secs % 86400;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60,
+        duration.subsec_millis()
+    )
+}
+
+/// Format a record as a line of text.
+fn format_line(record: &Record) -> String {
+    format!(
+        "{} {:<5} {}: {}\n",
+        format_timestamp(SystemTime::now()),
+        record.level(),
+        record.target(),
+        record.args()
+    )
+}
+
+/// Name identifying the process in system logs.
+fn process_identifier() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| {
+            exe.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| "python".to_string())
+}
+
+/// Build a datagram for the native journald protocol.
+///
+/// All fields use the binary-safe encoding, so values can contain newlines.
+#[cfg(target_os = "linux")]
+fn journald_datagram(identifier: &str, record: &Record) -> Vec<u8> {
+    let priority = match record.level() {
+        Level::Error => "3",
+        Level::Warn => "4",
+        Level::Info => "6",
+        Level::Debug | Level::Trace => "7",
+    };
+
+    let mut data = vec![];
+    let mut field = |name: &str, value: &[u8]| {
+        data.extend_from_slice(name.as_bytes());
+        data.push(b'\n');
+        data.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        data.extend_from_slice(value);
+        data.push(b'\n');
+    };
+
+    field("PRIORITY", priority.as_bytes());
+    field("SYSLOG_IDENTIFIER", identifier.as_bytes());
+    field("TARGET", record.target().as_bytes());
+    if let Some(file) = record.file() {
+        field("CODE_FILE", file.as_bytes());
+    }
+    if let Some(line) = record.line() {
+        field("CODE_LINE", line.to_string().as_bytes());
+    }
+    field("MESSAGE", record.args().to_string().as_bytes());
+
+    data
+}
+
+/// A GUID in the memory layout used by Windows APIs.
+#[cfg(any(windows, test))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+struct Guid {
+    data1: u32,
+    data2: u16,
+    data3: u16,
+    data4: [u8; 8],
+}
+
+/// Parse a GUID of the form `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`.
+#[cfg(any(windows, test))]
+fn parse_guid(value: &str) -> Option<Guid> {
+    let parts = value.split('-').collect::<Vec<_>>();
+    if parts.len() != 5
+        || parts.iter().map(|part| part.len()).ne([8, 4, 4, 4, 12])
+        || !value.chars().all(|c| c == '-' || c.is_ascii_hexdigit())
+    {
+        return None;
+    }
+
+    let tail = format!("{}{}", parts[3], parts[4]);
+    let mut data4 = [0u8; 8];
+    for (i, byte) in data4.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&tail[i * 2..i * 2 + 2], 16).ok()?;
+    }
+
+    Some(Guid {
+        data1: u32::from_str_radix(parts[0], 16).ok()?,
+        data2: u16::from_str_radix(parts[1], 16).ok()?,
+        data3: u16::from_str_radix(parts[2], 16).ok()?,
+        data4,
+    })
+}
+
+/// A registered Windows Event Log event source.
+#[cfg(windows)]
+struct EventSource(*mut std::ffi::c_void);
+
+// Event source handles can be used from any thread.
+#[cfg(windows)]
+unsafe impl Send for EventSource {}
+#[cfg(windows)]
+unsafe impl Sync for EventSource {}
+
+#[cfg(windows)]
+#[link(name = "advapi32")]
+extern "system" {
+    fn RegisterEventSourceW(server: *const u16, source: *const u16) -> *mut std::ffi::c_void;
+
+    fn ReportEventW(
+        log: *mut std::ffi::c_void,
+        event_type: u16,
+        category: u16,
+        event_id: u32,
+        sid: *mut std::ffi::c_void,
+        num_strings: u16,
+        data_size: u32,
+        strings: *const *const u16,
+        data: *mut std::ffi::c_void,
+    ) -> i32;
+
+    fn RegCreateKeyExW(
+        key: isize,
+        subkey: *const u16,
+        reserved: u32,
+        class: *const u16,
+        options: u32,
+        sam: u32,
+        security: *const std::ffi::c_void,
+        result: *mut isize,
+        disposition: *mut u32,
+    ) -> i32;
+
+    fn RegSetValueExW(
+        key: isize,
+        name: *const u16,
+        reserved: u32,
+        value_type: u32,
+        data: *const u8,
+        size: u32,
+    ) -> i32;
+
+    fn RegCloseKey(key: isize) -> i32;
+
+    fn EventRegister(
+        provider_id: *const Guid,
+        callback: *const std::ffi::c_void,
+        context: *mut std::ffi::c_void,
+        handle: *mut u64,
+    ) -> u32;
+
+    fn EventWriteString(handle: u64, level: u8, keyword: u64, string: *const u16) -> u32;
+}
+
+#[cfg(windows)]
+fn wide_string(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// Register an event source in the `Application` log with the executable as message file.
+#[cfg(windows)]
+fn register_event_source(name: &str) -> Result<(), String> {
+    // HKEY_LOCAL_MACHINE is a sign extended 32-bit constant.
+    const HKEY_LOCAL_MACHINE: isize = 0x8000_0002u32 as i32 as isize;
+    const KEY_SET_VALUE: u32 = 0x2;
+    const REG_EXPAND_SZ: u32 = 2;
+    const REG_DWORD: u32 = 4;
+    // Error, warning and information events.
+    const TYPES_SUPPORTED: u32 = 0x7;
+
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("unable to resolve current executable: {}", e))?;
+    let exe = wide_string(&exe.display().to_string());
+
+    let subkey = wide_string(&format!(
+        "SYSTEM\\CurrentControlSet\\Services\\EventLog\\Application\\{}",
+        name
+    ));
+
+    let mut key = 0;
+    let res = unsafe {
+        RegCreateKeyExW(
+            HKEY_LOCAL_MACHINE,
+            subkey.as_ptr(),
+            0,
+            std::ptr::null(),
+            0,
+            KEY_SET_VALUE,
+            std::ptr::null(),
+            &mut key,
+            std::ptr::null_mut(),
+        )
+    };
+    if res != 0 {
+        return Err(format!(
+            "unable to create event source registry key: {}",
+            std::io::Error::from_raw_os_error(res)
+        ));
+    }
+
+    let set = |name: &str, value_type: u32, data: &[u8]| {
+        let name = wide_string(name);
+        unsafe {
+            RegSetValueExW(
+                key,
+                name.as_ptr(),
+                0,
+                value_type,
+                data.as_ptr(),
+                data.len() as u32,
+            )
+        }
+    };
+
+    let exe_bytes = exe.iter().flat_map(|c| c.to_le_bytes()).collect::<Vec<_>>();
+    let res = match set("EventMessageFile", REG_EXPAND_SZ, &exe_bytes) {
+        0 => set("TypesSupported", REG_DWORD, &TYPES_SUPPORTED.to_le_bytes()),
+        res => res,
+    };
+
+    unsafe {
+        RegCloseKey(key);
+    }
+
+    if res == 0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "unable to set event source registry values: {}",
+            std::io::Error::from_raw_os_error(res)
+        ))
+    }
+}
+
+#[cfg(windows)]
+impl EventSource {
+    fn register(name: &str) -> Result<Self, String> {
+        let name = wide_string(name);
+        let handle = unsafe { RegisterEventSourceW(std::ptr::null(), name.as_ptr()) };
+
+        if handle.is_null() {
+            Err(format!(
+                "unable to register event source: {}",
+                std::io::Error::last_os_error()
+            ))
+        } else {
+            Ok(Self(handle))
+        }
+    }
+
+    fn report(&self, record: &Record) {
+        const EVENTLOG_ERROR_TYPE: u16 = 0x1;
+        const EVENTLOG_WARNING_TYPE: u16 = 0x2;
+        const EVENTLOG_INFORMATION_TYPE: u16 = 0x4;
+
+        let event_type = match record.level() {
+            Level::Error => EVENTLOG_ERROR_TYPE,
+            Level::Warn => EVENTLOG_WARNING_TYPE,
+            _ => EVENTLOG_INFORMATION_TYPE,
+        };
+
+        let message = wide_string(&format!("{}: {}", record.target(), record.args()));
+        let strings = [message.as_ptr()];
+
+        unsafe {
+            ReportEventW(
+                self.0,
+                event_type,
+                0,
+                0,
+                std::ptr::null_mut(),
+                1,
+                0,
+                strings.as_ptr(),
+                std::ptr::null_mut(),
+            );
+        }
+    }
+}
+
+/// A registered Event Tracing for Windows provider.
+#[cfg(windows)]
+struct EtwProvider(u64);
+
+#[cfg(windows)]
+impl EtwProvider {
+    fn register(guid: &str) -> Result<Self, String> {
+        let guid =
+            parse_guid(guid).ok_or_else(|| format!("invalid ETW provider GUID: {}", guid))?;
+
+        let mut handle = 0;
+        let res =
+            unsafe { EventRegister(&guid, std::ptr::null(), std::ptr::null_mut(), &mut handle) };
+
+        if res == 0 {
+            Ok(Self(handle))
+        } else {
+            Err(format!(
+                "unable to register ETW provider: {}",
+                std::io::Error::from_raw_os_error(res as i32)
+            ))
+        }
+    }
+
+    fn write(&self, record: &Record) {
+        let level = match record.level() {
+            Level::Error => 2,
+            Level::Warn => 3,
+            Level::Info => 4,
+            Level::Debug | Level::Trace => 5,
+        };
+
+        let message = wide_string(&format!("{}: {}", record.target(), record.args()));
+
+        unsafe {
+            EventWriteString(self.0, level, 0, message.as_ptr());
+        }
+    }
+}
+
+/// Where a [Logger] writes records.
+enum Sink {
+    Stderr,
+    File(Mutex<File>),
+    #[cfg(target_os = "linux")]
+    Journald(std::os::unix::net::UnixDatagram, String),
+    #[cfg(windows)]
+    WindowsEventLog(EventSource),
+    #[cfg(windows)]
+    Etw(EtwProvider),
+}
+
+impl Sink {
+    #[cfg_attr(not(windows), allow(unused_variables))]
+    fn open(destination: &LogDestination, register_event_source: bool) -> Result<Self, String> {
+        match destination {
+            LogDestination::Host => Err("host log destination has no sink".to_string()),
+            LogDestination::Stderr => Ok(Self::Stderr),
+            LogDestination::File(path) => std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map(|fh| Self::File(Mutex::new(fh)))
+                .map_err(|e| format!("unable to open log file {}: {}", path, e)),
+            #[cfg(target_os = "linux")]
+            LogDestination::Journald => {
+                let socket = std::os::unix::net::UnixDatagram::unbound()
+                    .and_then(|socket| {
+                        socket.connect("/run/systemd/journal/socket")?;
+                        Ok(socket)
+                    })
+                    .map_err(|e| format!("unable to connect to journald: {}", e))?;
+
+                Ok(Self::Journald(socket, process_identifier()))
+            }
+            #[cfg(not(target_os = "linux"))]
+            LogDestination::Journald => {
+                Err("journald log destination is only supported on Linux".to_string())
+            }
+            #[cfg(windows)]
+            LogDestination::WindowsEventLog(source) => {
+                let source = source.clone().unwrap_or_else(process_identifier);
+
+                if register_event_source {
+                    // Records are still reported without a registration.
+                    let _ = register_event_source(&source);
+                }
+
+                EventSource::register(&source).map(Self::WindowsEventLog)
+            }
+            #[cfg(not(windows))]
+            LogDestination::WindowsEventLog(_) => {
+                Err("windows-event-log log destination is only supported on Windows".to_string())
+            }
+            #[cfg(windows)]
+            LogDestination::Etw(guid) => EtwProvider::register(guid).map(Self::Etw),
+            #[cfg(not(windows))]
+            LogDestination::Etw(_) => {
+                Err("etw log destination is only supported on Windows".to_string())
+            }
+        }
+    }
+
+    fn write(&self, record: &Record) {
+        // Errors are ignored because there is nowhere to report them.
+        match self {
+            Self::Stderr => {
+                let _ = std::io::stderr().write_all(format_line(record).as_bytes());
+            }
+            Self::File(fh) => {
+                if let Ok(mut fh) = fh.lock() {
+                    let _ = fh.write_all(format_line(record).as_bytes());
+                }
+            }
+            #[cfg(target_os = "linux")]
+            Self::Journald(socket, identifier) => {
+                let _ = socket.send(&journald_datagram(identifier, record));
+            }
+            #[cfg(windows)]
+            Self::WindowsEventLog(source) => source.report(record),
+            #[cfg(windows)]
+            Self::Etw(provider) => provider.write(record),
+        }
+    }
+
+    fn flush(&self) {
+        match self {
+            Self::Stderr => {
+                let _ = std::io::stderr().flush();
+            }
+            Self::File(fh) => {
+                if let Ok(mut fh) = fh.lock() {
+                    let _ = fh.flush();
+                }
+            }
+            #[cfg(target_os = "linux")]
+            Self::Journald(..) => {}
+            #[cfg(windows)]
+            Self::WindowsEventLog(..) | Self::Etw(..) => {}
+        }
+    }
+}
+
+/// A `log` logger writing filtered records to a [Sink].
+struct Logger {
+    filter: LogFilter,
+    sink: Sink,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.sink.write(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.sink.flush();
+    }
+}
+
+/// Install a `log` logger writing to a destination.
+///
+/// Does nothing for [LogDestination::Host] or if a previous interpreter
+/// installed a logger already. Fails if the application installed a logger.
+///
+/// `register_event_source` controls whether the event source of
+/// [LogDestination::WindowsEventLog] is registered.
+pub(crate) fn install_logger(
+    destination: &LogDestination,
+    filter: &LogFilter,
+    register_event_source: bool,
+) -> Result<(), String> {
+    if destination == &LogDestination::Host || LOGGER_INSTALLED.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    let logger = Logger {
+        filter: filter.clone(),
+        sink: Sink::open(destination, register_event_source)?,
+    };
+
+    log::set_boxed_logger(Box::new(logger)).map_err(|_| {
+        "a log logger is installed already; use the host log destination to write to it".to_string()
+    })?;
+    log::set_max_level(filter.max_level());
+    LOGGER_INSTALLED.store(true, Ordering::SeqCst);
+
+    Ok(())
+}
+
+/// Forward a Python log record to the `log` crate.
+#[pyfunction]
+#[pyo3(name = "emit")]
+fn emit_record(py: Python, handler: &PyAny, record: &PyAny) -> PyResult<()> {
+    let level = log_level(record.getattr("levelno")?.extract()?);
+    let target = record.getattr("name")?.extract::<String>()?;
+
+    let metadata = Metadata::builder().level(level).target(&target).build();
+    if !log::logger().enabled(&metadata) {
+        return Ok(());
+    }
+
+    let message = handler
+        .call_method1("format", (record,))?
+        .extract::<String>()?;
+    let file = record.getattr("pathname")?.extract::<Option<String>>()?;
+    let line = record.getattr("lineno")?.extract::<Option<u32>>()?;
+
+    py.allow_threads(|| {
+        log::logger().log(
+            &Record::builder()
+                .metadata(metadata)
+                .args(format_args!("{}", message))
+                .file(file.as_deref())
+                .line(line)
+                .build(),
+        )
+    });
+
+    Ok(())
+}
+
+/// Add a handler forwarding records to the `log` crate to Python's root logger.
+///
+/// The root logger level is set to the most verbose level enabled by the
+/// `log` crate, so Python records are filtered by the `log` logger.
+pub(crate) fn install_python_handler(py: Python) -> PyResult<()> {
+    let globals = PyDict::new(py);
+    globals.set_item("_emit", wrap_pyfunction!(emit_record, py)?)?;
+    py.run(HANDLER_CODE, Some(globals), None)?;
+
+    let root = py.import("logging")?.call_method0("getLogger")?;
+    root.call_method1("addHandler", (globals.get_item("handler"),))?;
+    root.call_method1("setLevel", (python_level(log::max_level()),))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_filter() -> Result<(), String> {
+        let filter = LogFilter::parse("")?;
+        assert_eq!(filter.level("app"), LevelFilter::Off);
+        assert_eq!(filter.max_level(), LevelFilter::Off);
+
+        let filter = LogFilter::parse("info, app=debug, app::db=error,urllib3.connectionpool")?;
+        assert_eq!(filter.level("other"), LevelFilter::Info);
+        assert_eq!(filter.level("app"), LevelFilter::Debug);
+        assert_eq!(filter.level("app.views"), LevelFilter::Debug);
+        assert_eq!(filter.level("app::db"), LevelFilter::Error);
+        assert_eq!(filter.level("app::db::pool"), LevelFilter::Error);
+        assert_eq!(filter.level("application"), LevelFilter::Info);
+        assert_eq!(filter.level("urllib3.connectionpool"), LevelFilter::Trace);
+        assert_eq!(filter.level("urllib3"), LevelFilter::Info);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+
+        assert!(LogFilter::parse("app=loud").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_filter_resolve() -> Result<(), String> {
+        assert_eq!(LogFilter::resolve(None, None)?, LogFilter::parse("warn")?);
+        assert_eq!(
+            LogFilter::resolve(Some("debug"), Some("PYEMBED_TEST_LOG_UNSET"))?,
+            LogFilter::parse("debug")?
+        );
+
+        std::env::set_var("PYEMBED_TEST_LOG_FILTER", "app=trace");
+        assert_eq!(
+            LogFilter::resolve(Some("debug"), Some("PYEMBED_TEST_LOG_FILTER"))?,
+            LogFilter::parse("app=trace")?
+        );
+        std::env::remove_var("PYEMBED_TEST_LOG_FILTER");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(log_level(50), Level::Error);
+        assert_eq!(log_level(40), Level::Error);
+        assert_eq!(log_level(30), Level::Warn);
+        assert_eq!(log_level(25), Level::Info);
+        assert_eq!(log_level(10), Level::Debug);
+        assert_eq!(log_level(5), Level::Trace);
+
+        for level in [
+            Level::Error,
+            Level::Warn,
+            Level::Info,
+            Level::Debug,
+            Level::Trace,
+        ] {
+            assert_eq!(log_level(python_level(level.to_level_filter())), level);
+        }
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        let at = |secs: u64, millis: u64| {
+            format_timestamp(
+                UNIX_EPOCH
+                    + std::time::Duration::from_secs(secs)
+                    + std::time::Duration::from_millis(millis),
+            )
+        };
+
+        assert_eq!(at(0, 0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(at(951782400, 5), "2000-02-29T00:00:00.005Z");
+        assert_eq!(at(1672531199, 999), "2022-12-31T23:59:59.999Z");
+        assert_eq!(at(1697371234, 120), "2023-10-15T12:00:34.120Z");
+    }
+
+    #[test]
+    fn test_parse_guid() {
+        assert_eq!(
+            parse_guid("9e4a3b8c-7d21-4f6a-b0c5-2e8d1f7a6c34"),
+            Some(Guid {
+                data1: 0x9e4a3b8c,
+                data2: 0x7d21,
+                data3: 0x4f6a,
+                data4: [0xb0, 0xc5, 0x2e, 0x8d, 0x1f, 0x7a, 0x6c, 0x34],
+            })
+        );
+
+        assert_eq!(parse_guid(""), None);
+        assert_eq!(parse_guid("9e4a3b8c7d214f6ab0c52e8d1f7a6c34"), None);
+        assert_eq!(parse_guid("9e4a3b8c-7d21-4f6a-b0c5-2e8d1f7a6c3"), None);
+        assert_eq!(parse_guid("9e4a3b8c-7d21-4f6a-b0c5-2e8d1f7a6c3z"), None);
+        assert_eq!(parse_guid("+e4a3b8c-7d21-4f6a-b0c5-2e8d1f7a6c34"), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_journald_datagram() {
+        let data = journald_datagram(
+            "app",
+            &Record::builder()
+                .level(Level::Warn)
+                .target("app::db")
+                .args(format_args!("two\nlines"))
+                .build(),
+        );
+
+        assert_eq!(
+            data,
+            b"PRIORITY\n\x01\0\0\0\0\0\0\x004\n\
+              SYSLOG_IDENTIFIER\n\x03\0\0\0\0\0\0\0app\n\
+              TARGET\n\x07\0\0\0\0\0\0\0app::db\n\
+              MESSAGE\n\x09\0\0\0\0\0\0\0two\nlines\n"
+                .to_vec()
+        );
+    }
+}
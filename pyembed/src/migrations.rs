@@ -0,0 +1,239 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Run application migrations on first run after an upgrade.
+
+Applied migrations are recorded in a state file in the per-user data
+directory of the application. The file has one line per migration:
+`applied <version>` for migrations that succeeded and `failed <version>
+<error>` for the last migration that failed, if any.
+
+A lock file next to the state file serializes processes of the application
+starting concurrently. The lock is released when the process exits, even if
+it crashes.
+*/
+
+use {
+    crate::NewInterpreterError,
+    pyo3::prelude::*,
+    python_packaging::interpreter::Migration,
+    std::{
+        fs::{File, OpenOptions},
+        path::{Path, PathBuf},
+    },
+};
+
+/// Name of the file recording applied migrations.
+const STATE_FILE_NAME: &str = "pyembed-migrations.txt";
+
+/// Name of the file locked while migrations run.
+const LOCK_FILE_NAME: &str = "pyembed-migrations.lock";
+
+/// Obtain the per-user data directory.
+///
+/// This is `%LOCALAPPDATA%` on Windows, `~/Library/Application Support` on
+/// macOS and `$XDG_DATA_HOME`, defaulting to `~/.local/share`, elsewhere.
+pub fn user_data_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join("Library")
+                .join("Application Support")
+        })
+    } else {
+        match std::env::var_os("XDG_DATA_HOME").map(PathBuf::from) {
+            Some(path) if path.is_absolute() => Some(path),
+            _ => std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".local").join("share")),
+        }
+    }
+}
+
+/// Obtain the versions of applied migrations from state file content.
+fn parse_state(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.strip_prefix("applied "))
+        .map(|version| version.trim().to_string())
+        .collect()
+}
+
+/// Serialize migrations state.
+fn format_state(applied: &[String], failed: Option<(&str, &str)>) -> String {
+    let mut content = applied
+        .iter()
+        .map(|version| format!("applied {}\n", version))
+        .collect::<String>();
+
+    if let Some((version, error)) = failed {
+        // Errors are kept on a single line so the file remains line based.
+        let error = error.split_whitespace().collect::<Vec<_>>().join(" ");
+        content.push_str(&format!("failed {} {}\n", version, error));
+    }
+
+    content
+}
+
+#[cfg(unix)]
+fn lock_file(path: &Path) -> std::io::Result<File> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?;
+
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+            return Ok(file);
+        }
+
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+#[cfg(windows)]
+fn lock_file(path: &Path) -> std::io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+
+    // Opening the file without sharing it holds it exclusively until the
+    // handle is closed.
+    loop {
+        match OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .share_mode(0)
+            .open(path)
+        {
+            Ok(file) => return Ok(file),
+            Err(e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Run migrations that haven't been applied for the current user yet.
+///
+/// Migrations are run in order. Each successful migration is recorded
+/// immediately. If a migration raises, it is recorded as failed, later
+/// migrations aren't run and an error describing the failure is returned.
+/// The failed migration is retried the next time this is called.
+pub fn run_migrations(
+    py: Python,
+    migrations: &[Migration],
+    app_name: &str,
+) -> Result<(), NewInterpreterError> {
+    if migrations.is_empty() {
+        return Ok(());
+    }
+
+    let data_dir = user_data_dir().ok_or(NewInterpreterError::Simple(
+        "unable to resolve per-user data directory to record migrations in",
+    ))?;
+    run_migrations_in(py, migrations, &data_dir.join(app_name))
+}
+
+fn run_migrations_in(
+    py: Python,
+    migrations: &[Migration],
+    state_dir: &Path,
+) -> Result<(), NewInterpreterError> {
+    let io_error = |e: std::io::Error, action: &str, path: &Path| {
+        NewInterpreterError::Dynamic(format!("{} {}: {}", action, path.display(), e))
+    };
+
+    let state_path = state_dir.join(STATE_FILE_NAME);
+    let lock_path = state_dir.join(LOCK_FILE_NAME);
+
+    std::fs::create_dir_all(state_dir)
+        .map_err(|e| io_error(e, "creating migrations directory", state_dir))?;
+
+    let _lock = py
+        .allow_threads(|| lock_file(&lock_path))
+        .map_err(|e| io_error(e, "locking", &lock_path))?;
+
+    // Read state after acquiring the lock, as another process may have run
+    // migrations in the meantime.
+    let applied = match std::fs::read_to_string(&state_path) {
+        Ok(content) => parse_state(&content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(io_error(e, "reading", &state_path)),
+    };
+
+    // Versions of migrations that are no longer defined are preserved.
+    let mut recorded = applied.clone();
+    let mut applied_now = vec![];
+
+    let write_state = |recorded: &[String], failed: Option<(&str, &str)>| {
+        std::fs::write(&state_path, format_state(recorded, failed))
+            .map_err(|e| io_error(e, "writing", &state_path))
+    };
+
+    for migration in migrations
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+    {
+        let res = py
+            .import(migration.module.as_str())
+            .and_then(|module| module.getattr(migration.function.as_str()))
+            .and_then(|function| function.call0());
+
+        match res {
+            Ok(_) => {
+                recorded.push(migration.version.clone());
+                applied_now.push(migration.version.as_str());
+                write_state(&recorded, None)?;
+            }
+            Err(err) => {
+                write_state(&recorded, Some((&migration.version, &err.to_string())))?;
+
+                let context = format!(
+                    "running migration {} ({}:{}); migrations applied by this run before it: {}; \
+                    later migrations were not run and will be retried with it",
+                    migration.version,
+                    migration.module,
+                    migration.function,
+                    if applied_now.is_empty() {
+                        "none".to_string()
+                    } else {
+                        applied_now.join(", ")
+                    }
+                );
+
+                return Err(NewInterpreterError::new_from_pyerr(py, err, &context));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state() {
+        let applied = vec!["1.0".to_string(), "2.0".to_string()];
+
+        let content = format_state(&applied, Some(("3.0", "ValueError: bad\n  value")));
+        assert_eq!(
+            content,
+            "applied 1.0\napplied 2.0\nfailed 3.0 ValueError: bad value\n"
+        );
+        assert_eq!(parse_state(&content), applied);
+        assert!(parse_state("").is_empty());
+    }
+}
@@ -4,7 +4,11 @@
 
 use {
     once_cell::sync::Lazy,
-    std::path::{Path, PathBuf},
+    python_packaging::interpreter::HostRequirements,
+    std::{
+        cmp::Ordering,
+        path::{Path, PathBuf},
+    },
 };
 
 /// terminfo directories for Debian based distributions.
@@ -141,3 +145,297 @@ pub fn resolve_terminfo_dirs() -> Option<String> {
         OsVariant::Other => None,
     }
 }
+
+/// Parse a dotted version string into its numeric components.
+///
+/// Non-numeric suffixes of components are ignored. e.g. `2.17-rc1` is
+/// parsed as `[2, 17]`.
+fn parse_version(value: &str) -> Vec<u32> {
+    value
+        .trim()
+        .split('.')
+        .map(|part| {
+            part.chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>()
+                .parse::<u32>()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+/// Compare 2 dotted version strings.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let a = parse_version(a);
+    let b = parse_version(b);
+
+    for i in 0..a.len().max(b.len()) {
+        match a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)) {
+            Ordering::Equal => {}
+            ordering => return ordering,
+        }
+    }
+
+    Ordering::Equal
+}
+
+/// Resolve the version of glibc the current process is running against.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn host_glibc_version() -> Option<String> {
+    let version = unsafe { std::ffi::CStr::from_ptr(libc::gnu_get_libc_version()) };
+
+    Some(version.to_string_lossy().to_string())
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+fn host_glibc_version() -> Option<String> {
+    None
+}
+
+/// Resolve the version of macOS the current process is running on.
+#[cfg(target_os = "macos")]
+fn host_macos_version() -> Option<String> {
+    let name = std::ffi::CString::new("kern.osproductversion").ok()?;
+    let mut buffer = [0u8; 32];
+    let mut size = buffer.len();
+
+    let res = unsafe {
+        libc::sysctlbyname(
+            name.as_ptr(),
+            buffer.as_mut_ptr() as *mut libc::c_void,
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+
+    if res != 0 {
+        return None;
+    }
+
+    let version = std::ffi::CStr::from_bytes_until_nul(&buffer).ok()?;
+
+    Some(version.to_string_lossy().to_string())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn host_macos_version() -> Option<String> {
+    None
+}
+
+/// Resolve the build number of Windows the current process is running on.
+///
+/// We call `RtlGetVersion()` because `GetVersionEx()` lies about the version
+/// to applications not manifested for the running Windows version.
+#[cfg(windows)]
+fn host_windows_build() -> Option<u32> {
+    #[repr(C)]
+    struct OsVersionInfoW {
+        size: u32,
+        major_version: u32,
+        minor_version: u32,
+        build_number: u32,
+        platform_id: u32,
+        csd_version: [u16; 128],
+    }
+
+    #[link(name = "ntdll")]
+    extern "system" {
+        fn RtlGetVersion(info: *mut OsVersionInfoW) -> i32;
+    }
+
+    let mut info = OsVersionInfoW {
+        size: std::mem::size_of::<OsVersionInfoW>() as u32,
+        major_version: 0,
+        minor_version: 0,
+        build_number: 0,
+        platform_id: 0,
+        csd_version: [0; 128],
+    };
+
+    if unsafe { RtlGetVersion(&mut info) } == 0 {
+        Some(info.build_number)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(windows))]
+fn host_windows_build() -> Option<u32> {
+    None
+}
+
+/// Whether the host CPU supports a named feature.
+///
+/// Returns [None] if the feature can't be detected on this architecture.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn host_cpu_feature_detected(feature: &str) -> Option<bool> {
+    macro_rules! detect {
+        ($($name:tt),*) => {
+            match feature {
+                $($name => Some(std::is_x86_feature_detected!($name)),)*
+                _ => None,
+            }
+        };
+    }
+
+    detect!(
+        "aes",
+        "avx",
+        "avx2",
+        "avx512bw",
+        "avx512cd",
+        "avx512dq",
+        "avx512f",
+        "avx512vl",
+        "bmi1",
+        "bmi2",
+        "cmpxchg16b",
+        "f16c",
+        "fma",
+        "lzcnt",
+        "movbe",
+        "pclmulqdq",
+        "popcnt",
+        "rdrand",
+        "sha",
+        "sse",
+        "sse2",
+        "sse3",
+        "sse4.1",
+        "sse4.2",
+        "ssse3",
+        "xsave"
+    )
+}
+
+#[cfg(target_arch = "aarch64")]
+fn host_cpu_feature_detected(feature: &str) -> Option<bool> {
+    macro_rules! detect {
+        ($($name:tt),*) => {
+            match feature {
+                $($name => Some(std::arch::is_aarch64_feature_detected!($name)),)*
+                _ => None,
+            }
+        };
+    }
+
+    detect!("aes", "asimd", "crc", "dotprod", "fp", "fp16", "lse", "neon", "pmull", "sha2")
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+fn host_cpu_feature_detected(_feature: &str) -> Option<bool> {
+    None
+}
+
+/// Validate the host environment against a set of requirements.
+///
+/// Returns `Err` with a human readable description of every unmet
+/// requirement. Requirements that can't be evaluated on the current platform
+/// (e.g. a glibc version on macOS or an unknown CPU feature) are ignored.
+pub fn check_host_requirements(requirements: &HostRequirements) -> Result<(), String> {
+    let mut problems = vec![];
+
+    if let (Some(required), Some(host)) = (&requirements.glibc_version, host_glibc_version()) {
+        if compare_versions(&host, required) == Ordering::Less {
+            problems.push(format!(
+                "glibc {} or newer is required but this system has glibc {}",
+                required, host
+            ));
+        }
+    }
+
+    if let (Some(required), Some(host)) = (&requirements.macos_version, host_macos_version()) {
+        if compare_versions(&host, required) == Ordering::Less {
+            problems.push(format!(
+                "macOS {} or newer is required but this system is running macOS {}",
+                required, host
+            ));
+        }
+    }
+
+    if let (Some(required), Some(host)) = (requirements.windows_build, host_windows_build()) {
+        if host < required {
+            problems.push(format!(
+                "Windows build {} or newer is required but this system is running build {}",
+                required, host
+            ));
+        }
+    }
+
+    let missing_features = requirements
+        .cpu_features
+        .iter()
+        .filter(|feature| host_cpu_feature_detected(feature) == Some(false))
+        .map(|feature| feature.as_str())
+        .collect::<Vec<_>>();
+
+    if !missing_features.is_empty() {
+        problems.push(format!(
+            "this CPU does not support required instruction set features: {}",
+            missing_features.join(", ")
+        ));
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    let mut message = format!(
+        "this application is not compatible with this machine:\n{}",
+        problems
+            .iter()
+            .map(|p| format!("  * {}", p))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+
+    if let Some(extra) = &requirements.message {
+        message.push('\n');
+        message.push_str(extra);
+    }
+
+    Err(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("2.17", "2.17"), Ordering::Equal);
+        assert_eq!(compare_versions("2.17", "2.17.0"), Ordering::Equal);
+        assert_eq!(compare_versions("2.9", "2.17"), Ordering::Less);
+        assert_eq!(compare_versions("10.15.7", "10.9"), Ordering::Greater);
+        assert_eq!(compare_versions("11", "10.15"), Ordering::Greater);
+        assert_eq!(compare_versions("2.35-rc1", "2.34"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_check_host_requirements() {
+        assert!(check_host_requirements(&HostRequirements::default()).is_ok());
+
+        // Impossibly new versions fail on the platforms they apply to.
+        let requirements = HostRequirements {
+            glibc_version: Some("999.0".to_string()),
+            macos_version: Some("999.0".to_string()),
+            windows_build: Some(u32::MAX),
+            cpu_features: vec!["unknown-feature".to_string()],
+            message: Some("download another build".to_string()),
+        };
+
+        let res = check_host_requirements(&requirements);
+
+        if cfg!(any(
+            all(target_os = "linux", target_env = "gnu"),
+            target_os = "macos",
+            windows
+        )) {
+            let message = res.unwrap_err();
+            assert!(message.starts_with("this application is not compatible with this machine:\n"));
+            assert!(message.ends_with("\ndownload another build"));
+            assert!(!message.contains("unknown-feature"));
+        }
+    }
+}
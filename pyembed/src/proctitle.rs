@@ -0,0 +1,457 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Process and thread title management.
+
+This module implements setting the title of the current process and thread,
+as displayed by tools like `ps` and `top`. It also provides a built-in
+Python extension module exposing an API compatible with the
+[setproctitle](https://pypi.org/project/setproctitle/) package.
+
+How the process title is changed is platform dependent:
+
+* On Linux and macOS, the memory holding the original process arguments is
+  overwritten. Before that happens, the arguments are copied to the heap and
+  the process's `argv` vector is pointed at the copies, so
+  `std::env::args_os()` keeps reporting the original arguments. Titles
+  longer than the original arguments are truncated rather than extended over
+  the environment strings, which are left untouched. On Linux, the name of
+  the main thread (as seen by `top`) is also changed.
+* On FreeBSD, DragonFly BSD, NetBSD, and OpenBSD, the system's
+  `setproctitle(3)` is called.
+* On other platforms, the title is only recorded so it can be retrieved.
+
+Solaris and illumos aren't supported: their `ps` reports `pr_psargs`, a
+snapshot of the arguments taken by the kernel when the process was
+executed, which the process has no way to change.
+*/
+
+use {
+    once_cell::sync::Lazy,
+    pyo3::{ffi as pyffi, prelude::*, FromPyPointer},
+    std::sync::Mutex,
+};
+
+/// Name of the Python extension module.
+pub const SETPROCTITLE_MODULE_NAME: &[u8] = b"setproctitle\0";
+
+const DOC: &[u8] = b"Process title management implemented in Rust\0";
+
+static mut MODULE_DEF: pyffi::PyModuleDef = pyffi::PyModuleDef {
+    m_base: pyffi::PyModuleDef_HEAD_INIT,
+    m_name: SETPROCTITLE_MODULE_NAME.as_ptr() as *const _,
+    m_doc: DOC.as_ptr() as *const _,
+    m_size: 0,
+    m_methods: std::ptr::null_mut(),
+    m_slots: std::ptr::null_mut(),
+    m_traverse: None,
+    m_clear: None,
+    m_free: None,
+};
+
+/// The most recently set process title.
+static PROCESS_TITLE: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+/// The original process arguments.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+struct OriginalArgs {
+    /// The `argv` vector passed to `main()`.
+    argv: *mut *mut libc::c_char,
+    /// Number of entries in `argv`.
+    argc: usize,
+    /// Address of the memory holding the argument strings.
+    start: usize,
+    /// Length of the memory holding the argument strings.
+    len: usize,
+}
+
+/// Memory holding the original process arguments, as an address and length.
+///
+/// Resolved on first use. The arguments are moved to the heap before the
+/// memory is handed out, as its content is destroyed by setting the title.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+static ARGV_AREA: Lazy<Option<(usize, usize)>> = Lazy::new(|| {
+    let args = original_args()?;
+
+    // Anything reading `argv` after we overwrite the area (notably
+    // `std::env::args_os()`) needs to see the original values. So point
+    // every entry at a copy we leak for the lifetime of the process.
+    for i in 0..args.argc {
+        unsafe {
+            let arg = args.argv.add(i);
+            *arg = std::ffi::CStr::from_ptr(*arg).to_owned().into_raw();
+        }
+    }
+
+    Some((args.start, args.len))
+});
+
+/// The `argv` vector, as passed by glibc to `.init_array` functions.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+static ARGV: std::sync::atomic::AtomicPtr<*mut libc::c_char> =
+    std::sync::atomic::AtomicPtr::new(std::ptr::null_mut());
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+#[used]
+#[link_section = ".init_array.00099"]
+static ARGV_INIT: extern "C" fn(libc::c_int, *mut *mut libc::c_char, *mut *mut libc::c_char) = {
+    extern "C" fn capture_argv(
+        _argc: libc::c_int,
+        argv: *mut *mut libc::c_char,
+        _envp: *mut *mut libc::c_char,
+    ) {
+        ARGV.store(argv, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    capture_argv
+};
+
+/// Resolve the original process arguments.
+///
+/// `/proc/self/stat` exposes the bounds of the argument strings (fields 48
+/// and 49) regardless of the C library in use. The `argv` vector pointing
+/// into them is captured at startup on glibc. Otherwise it is found right
+/// before the initial environment vector, which is where the kernel places
+/// it. Either way, it is only used if it matches the argument strings.
+#[cfg(target_os = "linux")]
+fn original_args() -> Option<OriginalArgs> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+
+    // The command name in field 2 can contain spaces and parenthesis. So
+    // only parse fields after its closing parenthesis, starting at field 3.
+    let fields = stat[stat.rfind(')')? + 1..]
+        .split_whitespace()
+        .collect::<Vec<_>>();
+
+    let start = fields.get(45)?.parse::<usize>().ok()?;
+    let end = fields.get(46)?.parse::<usize>().ok()?;
+
+    if start == 0 || end <= start {
+        return None;
+    }
+
+    let area = unsafe { std::slice::from_raw_parts(start as *const u8, end - start) };
+    let argc = area.iter().filter(|c| **c == 0).count();
+
+    let mut candidates = vec![];
+
+    #[cfg(target_env = "gnu")]
+    candidates.push(ARGV.load(std::sync::atomic::Ordering::Relaxed));
+
+    extern "C" {
+        static mut environ: *mut *mut libc::c_char;
+    }
+
+    let envp = unsafe { environ };
+    if !envp.is_null() {
+        candidates.push(envp.wrapping_sub(argc + 1));
+    }
+
+    candidates
+        .into_iter()
+        .find(|argv| {
+            !argv.is_null()
+                && unsafe { (*argv.add(argc)).is_null() }
+                && (0..argc).all(|i| {
+                    let arg = unsafe { *argv.add(i) } as usize;
+                    arg >= start && arg < end
+                })
+        })
+        .map(|argv| OriginalArgs {
+            argv,
+            argc,
+            start,
+            len: end - start,
+        })
+}
+
+/// Resolve the original process arguments.
+///
+/// Only the leading argument strings stored contiguously in memory are
+/// considered part of the overwritable area.
+#[cfg(target_os = "macos")]
+fn original_args() -> Option<OriginalArgs> {
+    extern "C" {
+        fn _NSGetArgc() -> *mut libc::c_int;
+        fn _NSGetArgv() -> *mut *mut *mut libc::c_char;
+    }
+
+    unsafe {
+        let argc = *_NSGetArgc();
+        let argv = *_NSGetArgv();
+
+        if argc < 1 || argv.is_null() || (*argv).is_null() {
+            return None;
+        }
+
+        let start = *argv as usize;
+        let mut end = start;
+
+        for i in 0..argc as isize {
+            let arg = *argv.offset(i);
+
+            if arg.is_null() || arg as usize != end {
+                break;
+            }
+
+            end += libc::strlen(arg) + 1;
+        }
+
+        Some(OriginalArgs {
+            argv,
+            argc: argc as usize,
+            start,
+            len: end - start,
+        })
+    }
+}
+
+/// Overwrite the original process arguments with a title.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn write_argv_area(title: &str) {
+    if let Some((start, len)) = *ARGV_AREA {
+        let area = unsafe { std::slice::from_raw_parts_mut(start as *mut u8, len) };
+
+        // Always leave room for a trailing NULL.
+        let count = title.len().min(len - 1);
+        area[..count].copy_from_slice(&title.as_bytes()[..count]);
+        area[count..].fill(0);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_os_process_title(title: &str) {
+    write_argv_area(title);
+
+    // The name of the main thread is what `top` and `/proc/<pid>/comm` report.
+    if unsafe { libc::getpid() as libc::c_long == libc::syscall(libc::SYS_gettid) } {
+        set_os_thread_title(title);
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn set_os_process_title(title: &str) {
+    write_argv_area(title);
+}
+
+#[cfg(any(
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+fn set_os_process_title(title: &str) {
+    if let Ok(title) = std::ffi::CString::new(title) {
+        // The leading `-` suppresses the program name prefix.
+        unsafe {
+            libc::setproctitle(b"-%s\0".as_ptr() as *const _, title.as_ptr());
+        }
+    }
+}
+
+#[cfg(not(any(
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "netbsd",
+    target_os = "openbsd"
+)))]
+fn set_os_process_title(_title: &str) {}
+
+#[cfg(target_os = "linux")]
+fn set_os_thread_title(title: &str) {
+    if let Ok(title) = std::ffi::CString::new(title) {
+        // The kernel truncates names to 15 bytes.
+        unsafe {
+            libc::prctl(libc::PR_SET_NAME, title.as_ptr() as libc::c_ulong, 0, 0, 0);
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn set_os_thread_title(title: &str) {
+    if let Ok(title) = std::ffi::CString::new(title) {
+        unsafe {
+            libc::pthread_setname_np(title.as_ptr());
+        }
+    }
+}
+
+#[cfg(any(target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+fn set_os_thread_title(title: &str) {
+    if let Ok(title) = std::ffi::CString::new(title) {
+        unsafe {
+            libc::pthread_set_name_np(libc::pthread_self(), title.as_ptr());
+        }
+    }
+}
+
+#[cfg(not(any(
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "openbsd"
+)))]
+fn set_os_thread_title(_title: &str) {}
+
+#[cfg(target_os = "linux")]
+fn os_thread_title() -> Option<String> {
+    let mut buf = [0u8; 16];
+
+    if unsafe {
+        libc::prctl(
+            libc::PR_GET_NAME,
+            buf.as_mut_ptr() as libc::c_ulong,
+            0,
+            0,
+            0,
+        )
+    } != 0
+    {
+        return None;
+    }
+
+    let len = buf.iter().position(|c| *c == 0).unwrap_or(buf.len());
+
+    Some(String::from_utf8_lossy(&buf[..len]).to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn os_thread_title() -> Option<String> {
+    let mut buf = [0u8; 64];
+
+    if unsafe {
+        libc::pthread_getname_np(libc::pthread_self(), buf.as_mut_ptr() as *mut _, buf.len())
+    } != 0
+    {
+        return None;
+    }
+
+    let len = buf.iter().position(|c| *c == 0).unwrap_or(buf.len());
+
+    Some(String::from_utf8_lossy(&buf[..len]).to_string())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn os_thread_title() -> Option<String> {
+    None
+}
+
+/// Truncate a title at its first NULL byte, as C APIs would.
+fn normalize_title(title: &str) -> &str {
+    title.split('\0').next().unwrap_or_default()
+}
+
+/// Set the title of the current process.
+pub fn set_process_title(title: &str) {
+    let title = normalize_title(title);
+
+    let mut current = PROCESS_TITLE.lock().unwrap();
+
+    // Ensure the original arguments are moved out before we overwrite them.
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    Lazy::force(&ARGV_AREA);
+
+    set_os_process_title(title);
+    current.replace(title.to_string());
+}
+
+/// Obtain the title of the current process.
+///
+/// If a title hasn't been set, this is the space delimited process arguments.
+pub fn process_title() -> String {
+    if let Some(title) = PROCESS_TITLE.lock().unwrap().as_ref() {
+        title.clone()
+    } else {
+        std::env::args_os()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Set the title of the current thread.
+///
+/// This is a no-op on platforms not supporting thread names.
+pub fn set_thread_title(title: &str) {
+    set_os_thread_title(normalize_title(title));
+}
+
+/// Obtain the title of the current thread.
+///
+/// Returns an empty string on platforms not supporting thread names.
+pub fn thread_title() -> String {
+    os_thread_title().unwrap_or_default()
+}
+
+/// setproctitle(title)
+///
+/// Set the title of the current process.
+#[pyfunction]
+fn setproctitle(title: &str) {
+    set_process_title(title);
+}
+
+/// getproctitle()
+///
+/// Obtain the title of the current process.
+#[pyfunction]
+fn getproctitle() -> String {
+    process_title()
+}
+
+/// setthreadtitle(title)
+///
+/// Set the title of the current thread.
+#[pyfunction]
+fn setthreadtitle(title: &str) {
+    set_thread_title(title);
+}
+
+/// getthreadtitle()
+///
+/// Obtain the title of the current thread.
+#[pyfunction]
+fn getthreadtitle() -> String {
+    thread_title()
+}
+
+/// Module initialization function for the `setproctitle` extension module.
+#[allow(non_snake_case)]
+pub extern "C" fn PyInit_setproctitle() -> *mut pyffi::PyObject {
+    let py = unsafe { Python::assume_gil_acquired() };
+
+    let module = unsafe { pyffi::PyModule_Create(std::ptr::addr_of_mut!(MODULE_DEF)) };
+
+    if module.is_null() {
+        return module;
+    }
+
+    let module = match unsafe { PyModule::from_owned_ptr_or_err(py, module) } {
+        Ok(m) => m,
+        Err(e) => {
+            e.restore(py);
+            return std::ptr::null_mut();
+        }
+    };
+
+    match module_init(module) {
+        Ok(()) => module.into_ptr(),
+        Err(e) => {
+            e.restore(py);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+fn module_init(m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(setproctitle, m)?)?;
+    m.add_function(wrap_pyfunction!(getproctitle, m)?)?;
+    m.add_function(wrap_pyfunction!(setthreadtitle, m)?)?;
+    m.add_function(wrap_pyfunction!(getthreadtitle, m)?)?;
+
+    Ok(())
+}
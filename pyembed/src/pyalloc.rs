@@ -86,22 +86,126 @@ Rust's allocator API). So even if Rust's global allocator is set to a custom
 allocator, it is preferred to install the Python allocator because its bindings
 to the allocator will be more efficient.
 
+# Allocator Statistics
+
+When enabled, our custom allocators count allocations and the bytes they hold.
+Sizes are the usable sizes reported by the allocator, which may exceed requested
+sizes, so the numbers are comparable across allocators. Counters are global to
+the process and cover every domain the allocator is installed for. See
+[allocator_stats()].
+
 */
 
 use {
     core::ffi::c_void,
-    pyo3::ffi as pyffi,
+    pyo3::{ffi as pyffi, prelude::*, types::PyDict},
     python_packaging::interpreter::MemoryAllocatorBackend,
     std::{
         alloc,
         collections::HashMap,
         ops::{Deref, DerefMut},
-        sync::Mutex,
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Mutex,
+        },
     },
 };
 
 const MIN_ALIGN: usize = 16;
 
+static STATS_ENABLED: AtomicBool = AtomicBool::new(false);
+static STATS_BACKEND: Mutex<Option<MemoryAllocatorBackend>> = Mutex::new(None);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static REALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static DEALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Statistics of a custom memory allocator.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AllocatorStats {
+    /// The allocator the statistics are for.
+    pub backend: MemoryAllocatorBackend,
+
+    /// Number of allocations, including `calloc()` calls.
+    pub allocations: usize,
+
+    /// Number of reallocations of existing memory.
+    pub reallocations: usize,
+
+    /// Number of deallocations.
+    pub deallocations: usize,
+
+    /// Bytes currently allocated.
+    pub allocated_bytes: usize,
+
+    /// Highest value of [Self::allocated_bytes] so far.
+    pub peak_allocated_bytes: usize,
+}
+
+/// Obtain statistics of the custom memory allocator in use.
+///
+/// Returns `None` unless a custom allocator was installed with statistics
+/// enabled via [crate::OxidizedPythonInterpreterConfig::allocator_stats].
+pub fn allocator_stats() -> Option<AllocatorStats> {
+    let backend = (*STATS_BACKEND.lock().unwrap())?;
+
+    Some(AllocatorStats {
+        backend,
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        reallocations: REALLOCATIONS.load(Ordering::Relaxed),
+        deallocations: DEALLOCATIONS.load(Ordering::Relaxed),
+        allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+        peak_allocated_bytes: PEAK_ALLOCATED_BYTES.load(Ordering::Relaxed),
+    })
+}
+
+/// Python function returning [allocator_stats()] as a dict.
+#[pyfunction]
+#[pyo3(name = "stats")]
+pub(crate) fn python_allocator_stats(py: Python) -> PyResult<PyObject> {
+    let stats = match allocator_stats() {
+        Some(stats) => stats,
+        None => return Ok(py.None()),
+    };
+
+    let d = PyDict::new(py);
+    d.set_item("backend", stats.backend.to_string())?;
+    d.set_item("allocations", stats.allocations)?;
+    d.set_item("reallocations", stats.reallocations)?;
+    d.set_item("deallocations", stats.deallocations)?;
+    d.set_item("allocated_bytes", stats.allocated_bytes)?;
+    d.set_item("peak_allocated_bytes", stats.peak_allocated_bytes)?;
+
+    Ok(d.into_py(py))
+}
+
+#[inline]
+fn stats_enabled() -> bool {
+    STATS_ENABLED.load(Ordering::Relaxed)
+}
+
+#[inline]
+fn record_allocation(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    let allocated = ALLOCATED_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_ALLOCATED_BYTES.fetch_max(allocated, Ordering::Relaxed);
+}
+
+#[inline]
+fn record_reallocation(old_size: usize, new_size: usize) {
+    REALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_sub(old_size, Ordering::Relaxed);
+    let allocated = ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed) + new_size;
+    PEAK_ALLOCATED_BYTES.fetch_max(allocated, Ordering::Relaxed);
+}
+
+#[inline]
+fn record_deallocation(size: usize) {
+    DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_sub(size, Ordering::Relaxed);
+}
+
 /// Tracks allocations from an allocator.
 ///
 /// Some allocators need to pass the original allocation size and alignment
@@ -205,6 +309,24 @@ pub(crate) struct TrackingAllocator {
     _state: Box<AllocationTracker>,
 }
 
+#[cfg(feature = "jemalloc-sys")]
+#[inline]
+fn jemalloc_usable_size(ptr: *mut c_void) -> usize {
+    unsafe { jemalloc_sys::sallocx(ptr, 0) }
+}
+
+#[cfg(feature = "libmimalloc-sys")]
+#[inline]
+fn mimalloc_usable_size(ptr: *mut c_void) -> usize {
+    unsafe { libmimalloc_sys::mi_usable_size(ptr) }
+}
+
+#[cfg(feature = "snmalloc-sys")]
+#[inline]
+fn snmalloc_usable_size(ptr: *mut c_void) -> usize {
+    unsafe { snmalloc_sys::sn_malloc_usable_size(ptr) }
+}
+
 extern "C" fn rust_malloc(ctx: *mut c_void, size: usize) -> *mut c_void {
     let size = match size {
         0 => 1,
@@ -218,6 +340,10 @@ extern "C" fn rust_malloc(ctx: *mut c_void, size: usize) -> *mut c_void {
 
     tracker.insert_allocation(res, layout);
 
+    if stats_enabled() && !res.is_null() {
+        record_allocation(size);
+    }
+
     res
 }
 
@@ -228,7 +354,13 @@ extern "C" fn jemalloc_malloc(_ctx: *mut c_void, size: usize) -> *mut c_void {
         val => val,
     };
 
-    unsafe { jemalloc_sys::mallocx(size, 0) }
+    let res: *mut c_void = unsafe { jemalloc_sys::mallocx(size, 0) };
+
+    if stats_enabled() && !res.is_null() {
+        record_allocation(jemalloc_usable_size(res));
+    }
+
+    res
 }
 
 #[cfg(feature = "libmimalloc-sys")]
@@ -238,7 +370,13 @@ extern "C" fn mimalloc_malloc(_ctx: *mut c_void, size: usize) -> *mut c_void {
         val => val,
     };
 
-    unsafe { libmimalloc_sys::mi_malloc(size) as *mut _ }
+    let res: *mut c_void = unsafe { libmimalloc_sys::mi_malloc(size) as *mut _ };
+
+    if stats_enabled() && !res.is_null() {
+        record_allocation(mimalloc_usable_size(res));
+    }
+
+    res
 }
 
 #[cfg(feature = "snmalloc-sys")]
@@ -248,7 +386,13 @@ extern "C" fn snmalloc_malloc(_ctx: *mut c_void, size: usize) -> *mut c_void {
         val => val,
     };
 
-    unsafe { snmalloc_sys::sn_malloc(size) as *mut _ }
+    let res: *mut c_void = unsafe { snmalloc_sys::sn_malloc(size) as *mut _ };
+
+    if stats_enabled() && !res.is_null() {
+        record_allocation(snmalloc_usable_size(res));
+    }
+
+    res
 }
 
 extern "C" fn rust_calloc(ctx: *mut c_void, nelem: usize, elsize: usize) -> *mut c_void {
@@ -264,6 +408,10 @@ extern "C" fn rust_calloc(ctx: *mut c_void, nelem: usize, elsize: usize) -> *mut
 
     tracker.insert_allocation(res, layout);
 
+    if stats_enabled() && !res.is_null() {
+        record_allocation(size);
+    }
+
     res
 }
 
@@ -274,7 +422,13 @@ extern "C" fn jemalloc_calloc(_ctx: *mut c_void, nelem: usize, elsize: usize) ->
         val => val,
     };
 
-    unsafe { jemalloc_sys::mallocx(size, jemalloc_sys::MALLOCX_ZERO) }
+    let res: *mut c_void = unsafe { jemalloc_sys::mallocx(size, jemalloc_sys::MALLOCX_ZERO) };
+
+    if stats_enabled() && !res.is_null() {
+        record_allocation(jemalloc_usable_size(res));
+    }
+
+    res
 }
 
 #[cfg(feature = "libmimalloc-sys")]
@@ -284,7 +438,13 @@ extern "C" fn mimalloc_calloc(_ctx: *mut c_void, nelem: usize, elsize: usize) ->
         val => val,
     };
 
-    unsafe { libmimalloc_sys::mi_calloc(nelem, size) as *mut _ }
+    let res: *mut c_void = unsafe { libmimalloc_sys::mi_calloc(nelem, size) as *mut _ };
+
+    if stats_enabled() && !res.is_null() {
+        record_allocation(mimalloc_usable_size(res));
+    }
+
+    res
 }
 
 #[cfg(feature = "snmalloc-sys")]
@@ -294,7 +454,13 @@ extern "C" fn snmalloc_calloc(_ctx: *mut c_void, nelem: usize, elsize: usize) ->
         val => val,
     };
 
-    unsafe { snmalloc_sys::sn_calloc(nelem, size) as *mut _ }
+    let res: *mut c_void = unsafe { snmalloc_sys::sn_calloc(nelem, size) as *mut _ };
+
+    if stats_enabled() && !res.is_null() {
+        record_allocation(snmalloc_usable_size(res));
+    }
+
+    res
 }
 
 extern "C" fn rust_realloc(ctx: *mut c_void, ptr: *mut c_void, new_size: usize) -> *mut c_void {
@@ -317,6 +483,10 @@ extern "C" fn rust_realloc(ctx: *mut c_void, ptr: *mut c_void, new_size: usize)
 
     tracker.insert_allocation(res, layout);
 
+    if stats_enabled() && !res.is_null() {
+        record_reallocation(old_layout.size(), new_size);
+    }
+
     res
 }
 
@@ -331,7 +501,19 @@ extern "C" fn jemalloc_realloc(ctx: *mut c_void, ptr: *mut c_void, new_size: usi
         val => val,
     };
 
-    unsafe { jemalloc_sys::rallocx(ptr, new_size, 0) }
+    let old_size = if stats_enabled() {
+        jemalloc_usable_size(ptr)
+    } else {
+        0
+    };
+
+    let res: *mut c_void = unsafe { jemalloc_sys::rallocx(ptr, new_size, 0) };
+
+    if stats_enabled() && !res.is_null() {
+        record_reallocation(old_size, jemalloc_usable_size(res));
+    }
+
+    res
 }
 
 #[cfg(feature = "libmimalloc-sys")]
@@ -345,7 +527,20 @@ extern "C" fn mimalloc_realloc(ctx: *mut c_void, ptr: *mut c_void, new_size: usi
         val => val,
     };
 
-    unsafe { libmimalloc_sys::mi_realloc(ptr as *mut _, new_size) as *mut _ }
+    let old_size = if stats_enabled() {
+        mimalloc_usable_size(ptr)
+    } else {
+        0
+    };
+
+    let res: *mut c_void =
+        unsafe { libmimalloc_sys::mi_realloc(ptr as *mut _, new_size) as *mut _ };
+
+    if stats_enabled() && !res.is_null() {
+        record_reallocation(old_size, mimalloc_usable_size(res));
+    }
+
+    res
 }
 
 #[cfg(feature = "snmalloc-sys")]
@@ -358,7 +553,19 @@ extern "C" fn snmalloc_realloc(ctx: *mut c_void, ptr: *mut c_void, new_size: usi
         val => val,
     };
 
-    unsafe { snmalloc_sys::sn_realloc(ptr as *mut _, new_size) as *mut _ }
+    let old_size = if stats_enabled() {
+        snmalloc_usable_size(ptr)
+    } else {
+        0
+    };
+
+    let res: *mut c_void = unsafe { snmalloc_sys::sn_realloc(ptr as *mut _, new_size) as *mut _ };
+
+    if stats_enabled() && !res.is_null() {
+        record_reallocation(old_size, snmalloc_usable_size(res));
+    }
+
+    res
 }
 
 extern "C" fn rust_free(ctx: *mut c_void, ptr: *mut c_void) {
@@ -377,6 +584,10 @@ extern "C" fn rust_free(ctx: *mut c_void, ptr: *mut c_void) {
     }
 
     tracker.remove_allocation(ptr);
+
+    if stats_enabled() {
+        record_deallocation(layout.size());
+    }
 }
 
 #[cfg(feature = "jemalloc-sys")]
@@ -385,6 +596,10 @@ extern "C" fn jemalloc_free(_ctx: *mut c_void, ptr: *mut c_void) {
         return;
     }
 
+    if stats_enabled() {
+        record_deallocation(jemalloc_usable_size(ptr));
+    }
+
     unsafe { jemalloc_sys::dallocx(ptr, 0) }
 }
 
@@ -394,6 +609,10 @@ extern "C" fn mimalloc_free(_ctx: *mut c_void, ptr: *mut c_void) {
         return;
     }
 
+    if stats_enabled() {
+        record_deallocation(mimalloc_usable_size(ptr));
+    }
+
     unsafe { libmimalloc_sys::mi_free(ptr as *mut _) }
 }
 
@@ -403,6 +622,10 @@ extern "C" fn snmalloc_free(_ctx: *mut c_void, ptr: *mut c_void) {
         return;
     }
 
+    if stats_enabled() {
+        record_deallocation(snmalloc_usable_size(ptr));
+    }
+
     unsafe { snmalloc_sys::sn_free(ptr as *mut _) }
 }
 
@@ -422,6 +645,10 @@ extern "C" fn rust_arena_free(ctx: *mut c_void, ptr: *mut c_void, _size: usize)
     }
 
     tracker.remove_allocation(ptr);
+
+    if stats_enabled() {
+        record_deallocation(layout.size());
+    }
 }
 
 #[cfg(feature = "jemalloc-sys")]
@@ -430,6 +657,10 @@ extern "C" fn jemalloc_arena_free(_ctx: *mut c_void, ptr: *mut c_void, _size: us
         return;
     }
 
+    if stats_enabled() {
+        record_deallocation(jemalloc_usable_size(ptr));
+    }
+
     unsafe { jemalloc_sys::dallocx(ptr, 0) }
 }
 
@@ -439,6 +670,10 @@ extern "C" fn mimalloc_arena_free(_ctx: *mut c_void, ptr: *mut c_void, _size: us
         return;
     }
 
+    if stats_enabled() {
+        record_deallocation(mimalloc_usable_size(ptr));
+    }
+
     unsafe { libmimalloc_sys::mi_free(ptr as *mut _) }
 }
 
@@ -448,6 +683,10 @@ extern "C" fn snmalloc_arena_free(_ctx: *mut c_void, ptr: *mut c_void, _size: us
         return;
     }
 
+    if stats_enabled() {
+        record_deallocation(snmalloc_usable_size(ptr));
+    }
+
     unsafe { snmalloc_sys::sn_free(ptr as *mut _) }
 }
 
@@ -599,6 +838,15 @@ impl PythonMemoryAllocator {
         self.backend
     }
 
+    /// Enable collection of statistics for allocations by this allocator.
+    ///
+    /// This should be called before the allocator is installed, so memory
+    /// released by it was counted when allocated.
+    pub fn enable_stats(&self) {
+        STATS_BACKEND.lock().unwrap().replace(self.backend);
+        STATS_ENABLED.store(true, Ordering::Relaxed);
+    }
+
     /// Set this allocator to be the allocator for a certain "domain" in a Python interpreter.
     ///
     /// This should be called before `Py_Initialize*()`.
@@ -0,0 +1,220 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Apply operating system sandboxes to the current process. */
+
+use {python_packaging::interpreter::SandboxPolicy, std::path::Path};
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod seccomp {
+    use libc::{
+        sock_filter, sock_fprog, BPF_ABS, BPF_JEQ, BPF_JGE, BPF_JMP, BPF_K, BPF_LD, BPF_RET, BPF_W,
+    };
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    /// Syscall numbers with this bit set use the x32 ABI.
+    #[cfg(target_arch = "x86_64")]
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+    const SECCOMP_SET_MODE_FILTER: libc::c_uint = 1;
+
+    // Offsets of fields in `struct seccomp_data`.
+    const OFFSET_NR: u32 = 0;
+    const OFFSET_ARCH: u32 = 4;
+    const OFFSET_ARG0: u32 = 16;
+
+    fn stmt(code: u32, k: u32) -> sock_filter {
+        sock_filter {
+            code: code as u16,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn jump(code: u32, k: u32, jt: u8, jf: u8) -> sock_filter {
+        sock_filter {
+            code: code as u16,
+            jt,
+            jf,
+            k,
+        }
+    }
+
+    fn ret_errno(errno: i32) -> sock_filter {
+        stmt(
+            BPF_RET | BPF_K,
+            libc::SECCOMP_RET_ERRNO | (errno as u32 & libc::SECCOMP_RET_DATA),
+        )
+    }
+
+    /// Build a seccomp-bpf program denying what `allow_network` and `allow_subprocess` don't allow.
+    ///
+    /// Denied system calls fail with an error so Python raises an exception
+    /// instead of the process being killed.
+    pub fn filter(allow_network: bool, allow_subprocess: bool) -> Vec<sock_filter> {
+        let mut program = vec![
+            // System calls from foreign architectures have different numbers. Refuse them.
+            stmt(BPF_LD | BPF_W | BPF_ABS, OFFSET_ARCH),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, AUDIT_ARCH, 1, 0),
+            stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+            stmt(BPF_LD | BPF_W | BPF_ABS, OFFSET_NR),
+        ];
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            program.push(jump(BPF_JMP | BPF_JGE | BPF_K, X32_SYSCALL_BIT, 0, 1));
+            program.push(ret_errno(libc::ENOSYS));
+        }
+
+        let mut denied = vec![];
+        if !allow_subprocess {
+            denied.push((libc::SYS_execve, libc::EPERM));
+            denied.push((libc::SYS_execveat, libc::EPERM));
+        }
+        if !allow_network {
+            // io_uring can create sockets without going through socket().
+            denied.push((libc::SYS_io_uring_setup, libc::ENOSYS));
+        }
+
+        for (nr, errno) in denied {
+            program.push(jump(BPF_JMP | BPF_JEQ | BPF_K, nr as u32, 0, 1));
+            program.push(ret_errno(errno));
+        }
+
+        if !allow_network {
+            // Only allow Unix domain sockets. This clobbers the accumulator, so
+            // it must come last.
+            program.push(jump(
+                BPF_JMP | BPF_JEQ | BPF_K,
+                libc::SYS_socket as u32,
+                0,
+                4,
+            ));
+            program.push(stmt(BPF_LD | BPF_W | BPF_ABS, OFFSET_ARG0));
+            program.push(jump(BPF_JMP | BPF_JEQ | BPF_K, libc::AF_UNIX as u32, 0, 1));
+            program.push(stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_ALLOW));
+            program.push(ret_errno(libc::EACCES));
+        }
+
+        program.push(stmt(BPF_RET | BPF_K, libc::SECCOMP_RET_ALLOW));
+
+        program
+    }
+
+    /// Install a seccomp-bpf filter on all threads of the current process.
+    pub fn install(program: &mut [sock_filter]) -> Result<(), String> {
+        let prog = sock_fprog {
+            len: program.len() as u16,
+            filter: program.as_mut_ptr(),
+        };
+
+        unsafe {
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                return Err(format!(
+                    "unable to set no_new_privs: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+
+            if libc::syscall(
+                libc::SYS_seccomp,
+                SECCOMP_SET_MODE_FILTER,
+                libc::SECCOMP_FILTER_FLAG_TSYNC,
+                &prog as *const sock_fprog,
+            ) != 0
+            {
+                return Err(format!(
+                    "unable to install seccomp filter: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::{
+        ffi::{CStr, CString},
+        os::raw::{c_char, c_int},
+    };
+
+    extern "C" {
+        fn sandbox_init(profile: *const c_char, flags: u64, errorbuf: *mut *mut c_char) -> c_int;
+        fn sandbox_free_error(errorbuf: *mut c_char);
+    }
+
+    /// Apply a sandbox profile (SBPL) to the current process.
+    pub fn apply_profile(profile: &str) -> Result<(), String> {
+        let profile = CString::new(profile).map_err(|e| e.to_string())?;
+        let mut error = std::ptr::null_mut();
+
+        if unsafe { sandbox_init(profile.as_ptr(), 0, &mut error) } == 0 {
+            return Ok(());
+        }
+
+        let message = if error.is_null() {
+            "unknown error".to_string()
+        } else {
+            let message = unsafe { CStr::from_ptr(error) }
+                .to_string_lossy()
+                .to_string();
+            unsafe { sandbox_free_error(error) };
+            message
+        };
+
+        Err(format!("unable to apply sandbox profile: {}", message))
+    }
+}
+
+/// Restrict the current process to the capabilities declared by a sandbox policy.
+///
+/// On Linux, a seccomp-bpf filter restricting networking and execution of
+/// other programs is installed on all threads. Filesystem restrictions
+/// require the AppArmor profile derived from the policy to be loaded by an
+/// administrator and are not enforced by this function.
+///
+/// On macOS, a sandbox profile enforcing all restrictions is applied.
+///
+/// Sandboxes can't be lifted once applied. Returns `Err` if the current
+/// platform isn't supported or the sandbox couldn't be applied.
+#[allow(unused_variables)]
+pub fn apply_sandbox_policy(policy: &SandboxPolicy, origin: &Path) -> Result<(), String> {
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    {
+        seccomp::install(&mut seccomp::filter(
+            policy.allow_network,
+            policy.allow_subprocess,
+        ))
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        macos::apply_profile(&policy.to_macos_profile(Some(&origin.display().to_string())))
+    }
+
+    #[cfg(not(any(
+        all(
+            target_os = "linux",
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        ),
+        target_os = "macos"
+    )))]
+    {
+        Err("sandbox policies are not supported on this platform".to_string())
+    }
+}
@@ -0,0 +1,124 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Share signal handling between the Rust host and the interpreter. */
+
+use {pyo3::ffi as pyffi, std::os::raw::c_int};
+
+#[cfg(unix)]
+type SignalHandler = libc::sigaction;
+
+#[cfg(windows)]
+type SignalHandler = libc::sighandler_t;
+
+#[cfg(unix)]
+fn get_handler(signal: c_int) -> Result<SignalHandler, String> {
+    let mut handler: libc::sigaction = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::sigaction(signal, std::ptr::null(), &mut handler) } != 0 {
+        return Err(format!(
+            "unable to query handler of signal {}: {}",
+            signal,
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    Ok(handler)
+}
+
+#[cfg(unix)]
+fn set_handler(signal: c_int, handler: &SignalHandler) -> Result<(), String> {
+    if unsafe { libc::sigaction(signal, handler, std::ptr::null_mut()) } != 0 {
+        return Err(format!(
+            "unable to set handler of signal {}: {}",
+            signal,
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn default_handler() -> SignalHandler {
+    let mut handler: libc::sigaction = unsafe { std::mem::zeroed() };
+    handler.sa_sigaction = libc::SIG_DFL;
+
+    handler
+}
+
+#[cfg(windows)]
+fn get_handler(signal: c_int) -> Result<SignalHandler, String> {
+    let handler = unsafe { libc::signal(signal, libc::SIG_GET) };
+
+    if handler == libc::SIG_ERR as libc::sighandler_t {
+        return Err(format!("unable to query handler of signal {}", signal));
+    }
+
+    Ok(handler)
+}
+
+#[cfg(windows)]
+fn set_handler(signal: c_int, handler: &SignalHandler) -> Result<(), String> {
+    if unsafe { libc::signal(signal, *handler) } == libc::SIG_ERR as libc::sighandler_t {
+        return Err(format!("unable to set handler of signal {}", signal));
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn default_handler() -> SignalHandler {
+    libc::SIG_DFL
+}
+
+/// Signal handlers of the Rust host, set aside while the interpreter initializes.
+///
+/// Python only installs its handler for a signal if the signal has its
+/// default handler. So the handlers are reset to the default and reinstalled
+/// when this is dropped.
+pub(crate) struct SavedSignalHandlers {
+    handlers: Vec<(c_int, SignalHandler)>,
+}
+
+impl SavedSignalHandlers {
+    /// Save the current handlers of signals and reset them to the default.
+    pub(crate) fn take(signals: &[c_int]) -> Result<Self, String> {
+        let mut saved = Self { handlers: vec![] };
+
+        for signal in signals {
+            let handler = get_handler(*signal)?;
+            set_handler(*signal, &default_handler())?;
+            saved.handlers.push((*signal, handler));
+        }
+
+        Ok(saved)
+    }
+}
+
+impl Drop for SavedSignalHandlers {
+    fn drop(&mut self) {
+        for (signal, handler) in &self.handlers {
+            // Reinstalling a handler that was installed before can't fail.
+            let _ = set_handler(*signal, handler);
+        }
+    }
+}
+
+/// Raise `KeyboardInterrupt` in the main thread of the embedded interpreter.
+///
+/// This simulates `SIGINT` arriving, so Python runs its `SIGINT` handler the
+/// next time the main thread executes Python code. This is how a Rust host
+/// handling `SIGINT` itself can interrupt Python code.
+///
+/// This can be called from any thread and from signal handlers. It does
+/// nothing if the interpreter isn't initialized or Python code has set the
+/// `SIGINT` handler to `signal.SIG_IGN` or `signal.SIG_DFL`.
+pub fn interrupt_python() {
+    unsafe {
+        if pyffi::Py_IsInitialized() != 0 {
+            pyffi::PyErr_SetInterrupt();
+        }
+    }
+}
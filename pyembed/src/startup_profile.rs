@@ -0,0 +1,261 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Record where the time starting an interpreter goes.
+
+When enabled via [crate::OxidizedPythonInterpreterConfig::startup_profile_env],
+the phases of interpreter initialization and the time `OxidizedFinder` spends
+creating and executing every module are recorded as events.
+
+Events are written in the Chrome trace event format, which can be loaded in
+`chrome://tracing`, [Perfetto](https://ui.perfetto.dev/) or
+[speedscope](https://www.speedscope.app/). Nested imports show up as nested
+spans of the importing module.
+*/
+
+use {
+    once_cell::sync::Lazy,
+    pyo3::prelude::*,
+    std::{
+        fs::File,
+        io::{BufWriter, Write},
+        path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Mutex,
+        },
+        time::{Duration, Instant},
+    },
+};
+
+/// A span of time spent in a phase or import.
+struct Event {
+    name: String,
+    category: &'static str,
+    /// Import step the event records, if any.
+    step: Option<&'static str>,
+    start: Instant,
+    duration: Duration,
+    thread: u64,
+}
+
+struct StartupProfile {
+    /// Time events are relative to.
+    origin: Instant,
+    /// File to write events to when the interpreter finalizes.
+    path: PathBuf,
+    events: Vec<Event>,
+}
+
+static PROFILE: Lazy<Mutex<Option<StartupProfile>>> = Lazy::new(|| Mutex::new(None));
+
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static THREAD_ID: u64 = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Start a new profile written to a path, or stop profiling.
+///
+/// Events recorded by a previous interpreter are discarded.
+pub(crate) fn start(path: Option<PathBuf>) {
+    *PROFILE.lock().unwrap() = path.map(|path| StartupProfile {
+        origin: Instant::now(),
+        path,
+        events: vec![],
+    });
+}
+
+/// Whether events are being recorded.
+pub(crate) fn is_enabled() -> bool {
+    PROFILE.lock().unwrap().is_some()
+}
+
+fn record(
+    name: &str,
+    category: &'static str,
+    step: Option<&'static str>,
+    start: Instant,
+    duration: Duration,
+) {
+    if let Some(profile) = PROFILE.lock().unwrap().as_mut() {
+        profile.events.push(Event {
+            name: name.to_string(),
+            category,
+            step,
+            start,
+            duration,
+            thread: THREAD_ID.with(|id| *id),
+        });
+    }
+}
+
+/// Run a phase of interpreter initialization, recording its duration.
+pub(crate) fn phase<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    if !is_enabled() {
+        return f();
+    }
+
+    let start = Instant::now();
+    let res = f();
+    record(name, "startup", None, start, start.elapsed());
+
+    res
+}
+
+/// Record the time spent importing a module.
+///
+/// This is an [oxidized_importer::ImportTimingFn].
+pub(crate) fn record_import(name: &str, step: &'static str, start: Instant, duration: Duration) {
+    record(name, "import", Some(step), start, duration);
+}
+
+/// Write a JSON string literal.
+fn write_json_str(writer: &mut impl Write, value: &str) -> std::io::Result<()> {
+    writer.write_all(b"\"")?;
+
+    for c in value.chars() {
+        match c {
+            '"' => writer.write_all(b"\\\"")?,
+            '\\' => writer.write_all(b"\\\\")?,
+            '\n' => writer.write_all(b"\\n")?,
+            '\r' => writer.write_all(b"\\r")?,
+            '\t' => writer.write_all(b"\\t")?,
+            c if (c as u32) < 0x20 => write!(writer, "\\u{:04x}", c as u32)?,
+            c => write!(writer, "{}", c)?,
+        }
+    }
+
+    writer.write_all(b"\"")
+}
+
+/// Write events in the Chrome trace event format.
+fn write_events(writer: &mut impl Write, origin: Instant, events: &[Event]) -> std::io::Result<()> {
+    let pid = std::process::id();
+
+    writer.write_all(b"{\"traceEvents\":[")?;
+
+    for (i, event) in events.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+
+        // Timestamps and durations are in microseconds.
+        let ts = event.start.saturating_duration_since(origin).as_secs_f64() * 1e6;
+        let dur = event.duration.as_secs_f64() * 1e6;
+
+        writer.write_all(b"\n{\"name\":")?;
+        write_json_str(writer, &event.name)?;
+        write!(
+            writer,
+            ",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":{},\"tid\":{}",
+            event.category, ts, dur, pid, event.thread
+        )?;
+        if let Some(step) = event.step {
+            write!(writer, ",\"args\":{{\"step\":\"{}\"}}", step)?;
+        }
+        writer.write_all(b"}")?;
+    }
+
+    writer.write_all(b"\n],\"displayTimeUnit\":\"ms\"}\n")
+}
+
+/// Write the startup profile recorded so far to a file.
+///
+/// Events are written in the Chrome trace event format. Profiling must have
+/// been enabled via
+/// [crate::OxidizedPythonInterpreterConfig::startup_profile_env].
+///
+/// The profile is written automatically when the interpreter finalizes. This
+/// function allows writing it at other times, such as once an application
+/// finished starting.
+pub fn write_startup_profile(path: &Path) -> Result<(), String> {
+    let profile = PROFILE.lock().unwrap();
+
+    let profile = profile
+        .as_ref()
+        .ok_or_else(|| "startup profiling is not enabled".to_string())?;
+
+    File::create(path)
+        .and_then(|f| {
+            let mut writer = BufWriter::new(f);
+            write_events(&mut writer, profile.origin, &profile.events)?;
+            writer.flush()
+        })
+        .map_err(|e| format!("error writing {}: {}", path.display(), e))
+}
+
+/// Write the startup profile to its configured path.
+///
+/// This is registered with `atexit` so the profile is written however the
+/// interpreter is finalized.
+#[pyfunction]
+pub(crate) fn write_configured_startup_profile() {
+    let path = match PROFILE.lock().unwrap().as_ref() {
+        Some(profile) => profile.path.clone(),
+        None => return,
+    };
+
+    if let Err(e) = write_startup_profile(&path) {
+        eprintln!("error writing startup profile: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_json_str() -> std::io::Result<()> {
+        let mut buf = vec![];
+        write_json_str(&mut buf, "a\"b\\c\nd\u{1}é")?;
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "\"a\\\"b\\\\c\\nd\\u0001é\""
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_events() -> std::io::Result<()> {
+        let origin = Instant::now();
+        let events = vec![
+            Event {
+                name: "initialize".to_string(),
+                category: "startup",
+                step: None,
+                start: origin + Duration::from_micros(10),
+                duration: Duration::from_micros(1500),
+                thread: 1,
+            },
+            Event {
+                name: "encodings".to_string(),
+                category: "import",
+                step: Some("exec"),
+                start: origin + Duration::from_micros(20),
+                duration: Duration::from_micros(250),
+                thread: 1,
+            },
+        ];
+
+        let mut buf = vec![];
+        write_events(&mut buf, origin, &events)?;
+
+        let pid = std::process::id();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            format!(
+                "{{\"traceEvents\":[\n\
+                {{\"name\":\"initialize\",\"cat\":\"startup\",\"ph\":\"X\",\"ts\":10.000,\"dur\":1500.000,\"pid\":{pid},\"tid\":1}},\n\
+                {{\"name\":\"encodings\",\"cat\":\"import\",\"ph\":\"X\",\"ts\":20.000,\"dur\":250.000,\"pid\":{pid},\"tid\":1,\"args\":{{\"step\":\"exec\"}}}}\n\
+                ],\"displayTimeUnit\":\"ms\"}}\n",
+                pid = pid
+            )
+        );
+
+        Ok(())
+    }
+}
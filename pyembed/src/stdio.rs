@@ -0,0 +1,293 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Route standard output and error to Rust callbacks.
+
+Standard streams missing because the process has no console can also be
+replaced by streams of the null device.
+
+There are two mechanisms:
+
+* `sys.stdout` and `sys.stderr` are replaced by text streams writing to a
+  [CallbackWriter]. This captures everything written by Python code and works
+  even if the process has no console, as is the case for Windows GUI
+  applications.
+* The stdout and stderr file descriptors are replaced by pipes whose content
+  is forwarded to the callbacks by a background thread. This additionally
+  captures output of C code and of child processes.
+*/
+
+use {
+    crate::config::StdioCallback,
+    pyo3::{
+        buffer::PyBuffer,
+        prelude::*,
+        types::{PyBytes, PyDict},
+    },
+    std::{
+        os::raw::{c_int, c_void},
+        sync::mpsc::{sync_channel, Receiver},
+        time::Duration,
+    },
+};
+
+/// How long to wait for buffered output to be forwarded when restoring a file descriptor.
+const FD_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A binary stream passing written data to a [StdioCallback].
+///
+/// This implements enough of the `io.RawIOBase` interface to be wrapped by
+/// `io.TextIOWrapper`.
+#[pyclass(module = "pyembed")]
+pub(crate) struct CallbackWriter {
+    callback: StdioCallback,
+}
+
+#[pymethods]
+impl CallbackWriter {
+    fn write(&self, py: Python, data: &PyAny) -> PyResult<usize> {
+        let callback = self.callback;
+
+        if let Ok(data) = data.downcast::<PyBytes>() {
+            let data = data.as_bytes();
+            py.allow_threads(|| callback(data));
+
+            Ok(data.len())
+        } else {
+            let data = PyBuffer::<u8>::get(data)?.to_vec(py)?;
+            py.allow_threads(|| callback(&data));
+
+            Ok(data.len())
+        }
+    }
+
+    fn flush(&self) {}
+
+    fn close(&self) {}
+
+    #[getter]
+    fn closed(&self) -> bool {
+        false
+    }
+
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn seekable(&self) -> bool {
+        false
+    }
+
+    fn isatty(&self) -> bool {
+        false
+    }
+
+    fn fileno(&self, py: Python) -> PyResult<c_int> {
+        let exc = py.import("io")?.getattr("UnsupportedOperation")?;
+
+        Err(PyErr::from_value(exc.call1(("fileno",))?))
+    }
+}
+
+/// Replace `sys.stdout` and `sys.stderr` with streams writing to callbacks.
+///
+/// The encoding and error handler of the replaced streams are preserved. If a
+/// stream is `None` because the process has no console, UTF-8 is used.
+pub(crate) fn install_callback_streams(
+    py: Python,
+    stdout: Option<StdioCallback>,
+    stderr: Option<StdioCallback>,
+) -> PyResult<()> {
+    let sys = py.import("sys")?;
+    let text_io_wrapper = py.import("io")?.getattr("TextIOWrapper")?;
+
+    for (name, callback, default_errors) in [
+        ("stdout", stdout, "strict"),
+        ("stderr", stderr, "backslashreplace"),
+    ] {
+        let callback = if let Some(callback) = callback {
+            callback
+        } else {
+            continue;
+        };
+
+        let current = sys.getattr(name)?;
+        let (encoding, errors) = if current.is_none() {
+            ("utf-8".into_py(py), default_errors.into_py(py))
+        } else {
+            (
+                current.getattr("encoding")?.into_py(py),
+                current.getattr("errors")?.into_py(py),
+            )
+        };
+
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("encoding", encoding)?;
+        kwargs.set_item("errors", errors)?;
+        kwargs.set_item("line_buffering", true)?;
+        kwargs.set_item("write_through", true)?;
+
+        let stream =
+            text_io_wrapper.call((Py::new(py, CallbackWriter { callback })?,), Some(kwargs))?;
+        sys.setattr(name, stream)?;
+    }
+
+    Ok(())
+}
+
+/// Replace standard streams that are `None` by text streams of the null device.
+pub(crate) fn replace_missing_stdio(py: Python) -> PyResult<()> {
+    let sys = py.import("sys")?;
+    let io = py.import("io")?;
+    let devnull = py.import("os")?.getattr("devnull")?;
+
+    for (name, mode, errors) in [
+        ("stdin", "r", "strict"),
+        ("stdout", "w", "strict"),
+        ("stderr", "w", "backslashreplace"),
+    ] {
+        if !sys.getattr(name)?.is_none() {
+            continue;
+        }
+
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("encoding", "utf-8")?;
+        kwargs.set_item("errors", errors)?;
+
+        let stream = io.call_method("open", (devnull, mode), Some(kwargs))?;
+        sys.setattr(name, stream)?;
+
+        let original = format!("__{}__", name);
+        if sys.getattr(original.as_str())?.is_none() {
+            sys.setattr(original.as_str(), stream)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_pipe() -> std::io::Result<[c_int; 2]> {
+    let mut fds = [0; 2];
+
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    // Child processes inherit the write end as their stdout or stderr. But
+    // they have no business with the read end.
+    unsafe { libc::fcntl(fds[0], libc::F_SETFD, libc::FD_CLOEXEC) };
+
+    Ok(fds)
+}
+
+#[cfg(windows)]
+fn create_pipe() -> std::io::Result<[c_int; 2]> {
+    let mut fds = [0; 2];
+
+    if unsafe { libc::pipe(fds.as_mut_ptr(), 65536, libc::O_BINARY | libc::O_NOINHERIT) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(fds)
+}
+
+#[cfg(unix)]
+fn read_fd(fd: c_int, buffer: &mut [u8]) -> isize {
+    unsafe { libc::read(fd, buffer.as_mut_ptr() as *mut c_void, buffer.len()) }
+}
+
+#[cfg(windows)]
+fn read_fd(fd: c_int, buffer: &mut [u8]) -> isize {
+    unsafe { libc::read(fd, buffer.as_mut_ptr() as *mut c_void, buffer.len() as _) as isize }
+}
+
+/// A file descriptor whose content is forwarded to a [StdioCallback].
+///
+/// The original file descriptor is restored when this is dropped.
+pub(crate) struct FdRedirect {
+    fd: c_int,
+    saved: c_int,
+    done: Receiver<()>,
+}
+
+impl FdRedirect {
+    /// Redirect a file descriptor to a callback.
+    pub(crate) fn new(fd: c_int, callback: StdioCallback) -> Result<Self, String> {
+        let [reader, writer] =
+            create_pipe().map_err(|e| format!("unable to create pipe: {}", e))?;
+
+        // The file descriptor may not be open, e.g. in Windows GUI applications.
+        let saved = unsafe { libc::dup(fd) };
+        #[cfg(unix)]
+        if saved >= 0 {
+            unsafe { libc::fcntl(saved, libc::F_SETFD, libc::FD_CLOEXEC) };
+        }
+
+        if unsafe { libc::dup2(writer, fd) } < 0 {
+            let error = std::io::Error::last_os_error();
+            unsafe {
+                libc::close(reader);
+                libc::close(writer);
+                if saved >= 0 {
+                    libc::close(saved);
+                }
+            }
+
+            return Err(format!(
+                "unable to redirect file descriptor {}: {}",
+                fd, error
+            ));
+        }
+        unsafe { libc::close(writer) };
+
+        let (sender, done) = sync_channel(1);
+
+        std::thread::spawn(move || {
+            let mut buffer = vec![0; 8192];
+
+            loop {
+                let count = read_fd(reader, &mut buffer);
+
+                if count > 0 {
+                    callback(&buffer[0..count as usize]);
+                } else if count < 0
+                    && std::io::Error::last_os_error().raw_os_error() == Some(libc::EINTR)
+                {
+                    continue;
+                } else {
+                    break;
+                }
+            }
+
+            unsafe { libc::close(reader) };
+            let _ = sender.send(());
+        });
+
+        Ok(Self { fd, saved, done })
+    }
+}
+
+impl Drop for FdRedirect {
+    fn drop(&mut self) {
+        unsafe {
+            libc::fflush(std::ptr::null_mut());
+
+            if self.saved >= 0 {
+                libc::dup2(self.saved, self.fd);
+                libc::close(self.saved);
+            } else {
+                libc::close(self.fd);
+            }
+        }
+
+        // The pipe reaches end of file once all writers are closed. Child
+        // processes that outlive us may hold a writer. So don't wait forever.
+        let _ = self.done.recv_timeout(FD_DRAIN_TIMEOUT);
+    }
+}
@@ -0,0 +1,439 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Isolated subinterpreters.
+
+A subinterpreter is a separate Python interpreter in the same process as the
+main interpreter. It has its own modules, `sys` state and builtins. So code
+running in it can't see objects of other interpreters.
+
+On Python 3.12+, subinterpreters can have their own GIL (PEP 684) and run
+Python code in parallel with other interpreters. Otherwise all interpreters
+share the GIL of the main interpreter.
+*/
+
+use {
+    crate::{
+        config::ResolvedOxidizedPythonInterpreterConfig,
+        error::NewInterpreterError,
+        interpreter::{configure_importer_state, install_extra_sys_paths},
+    },
+    oxidized_importer::{
+        install_path_hook, install_zipimport_importer, new_isolated_finder,
+        remove_external_importers, replace_meta_path_importers, ImporterState,
+        PyInit_oxidized_importer, PythonResourcesState, OXIDIZED_IMPORTER_NAME_STR,
+    },
+    pyo3::{ffi as pyffi, prelude::*, types::PyModule},
+    std::{
+        cell::Cell,
+        marker::PhantomData,
+        panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+    },
+};
+
+#[cfg(Py_3_9)]
+use pyo3::ffi::PyInterpreterState_Get;
+
+#[cfg(not(Py_3_9))]
+extern "C" {
+    #[link_name = "_PyInterpreterState_Get"]
+    fn PyInterpreterState_Get() -> *mut pyffi::PyInterpreterState;
+}
+
+#[cfg(Py_3_12)]
+#[allow(non_snake_case)]
+mod ffi {
+    use {pyo3::ffi as pyffi, std::os::raw::c_int};
+
+    #[repr(C)]
+    pub struct PyInterpreterConfig {
+        pub use_main_obmalloc: c_int,
+        pub allow_fork: c_int,
+        pub allow_exec: c_int,
+        pub allow_threads: c_int,
+        pub allow_daemon_threads: c_int,
+        pub check_multi_interp_extensions: c_int,
+        pub gil: c_int,
+    }
+
+    pub const PyInterpreterConfig_OWN_GIL: c_int = 2;
+
+    extern "C" {
+        pub fn Py_NewInterpreterFromConfig(
+            tstate_p: *mut *mut pyffi::PyThreadState,
+            config: *const PyInterpreterConfig,
+        ) -> pyffi::PyStatus;
+    }
+}
+
+thread_local! {
+    /// Whether the current thread is running code in a subinterpreter.
+    static IN_SUBINTERPRETER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the current thread as running code in a subinterpreter.
+///
+/// Switching from one subinterpreter to another would deadlock on the GIL,
+/// so nesting is refused.
+struct SubInterpreterGuard;
+
+impl SubInterpreterGuard {
+    fn new() -> Self {
+        if IN_SUBINTERPRETER.with(|active| active.replace(true)) {
+            panic!("subinterpreters can't be entered from a subinterpreter");
+        }
+
+        Self
+    }
+}
+
+impl Drop for SubInterpreterGuard {
+    fn drop(&mut self) {
+        IN_SUBINTERPRETER.with(|active| active.set(false));
+    }
+}
+
+/// Configures a [SubInterpreter].
+#[derive(Clone, Debug)]
+pub struct SubInterpreterConfig {
+    /// Whether the subinterpreter has its own GIL.
+    ///
+    /// Requires Python 3.12+. The subinterpreter can then run Python code in
+    /// parallel with other interpreters. In exchange, it can't fork, exec or
+    /// start daemon threads and can only import extension modules supporting
+    /// multi-phase initialization (PEP 489).
+    pub own_gil: bool,
+
+    /// Whether to install an `OxidizedFinder` in the subinterpreter.
+    ///
+    /// The finder imports from the resources of the main interpreter and
+    /// behaves like the finder of the main interpreter. Has no effect if the
+    /// main interpreter doesn't use `oxidized_importer`.
+    ///
+    /// PyO3 shares type objects and module state between interpreters, which
+    /// is only sound if they share a GIL. So with [Self::own_gil], the
+    /// subinterpreter instead gets a finder keeping its state in a module of
+    /// its own. It imports modules from source and bytecode, but leaves
+    /// extension modules to the filesystem and doesn't provide package
+    /// resources or `OxidizedFinder` APIs beyond the standard importer
+    /// interfaces.
+    pub oxidized_importer: bool,
+}
+
+impl Default for SubInterpreterConfig {
+    fn default() -> Self {
+        Self {
+            own_gil: false,
+            oxidized_importer: true,
+        }
+    }
+}
+
+/// An isolated Python interpreter running beside the main interpreter.
+///
+/// Instances are created by [crate::MainPythonInterpreter::new_subinterpreter()]
+/// and can't outlive the main interpreter. Dropping the instance waits for
+/// non-daemon threads of the subinterpreter and destroys it. The process
+/// aborts if daemon threads are still running then.
+///
+/// Instances can be used from any thread. Code in the subinterpreter runs
+/// via [Self::with_gil()].
+///
+/// Python objects must not be moved between interpreters. Since PyO3 doesn't
+/// know which interpreter an object belongs to, this can't be enforced.
+pub struct SubInterpreter<'interpreter> {
+    interp: *mut pyffi::PyInterpreterState,
+    /// The thread state the interpreter was created with.
+    ///
+    /// It is kept until the interpreter is destroyed, as Python 3.11 can't
+    /// create another thread state after the first one is deleted.
+    tstate: *mut pyffi::PyThreadState,
+    own_gil: bool,
+    _main: PhantomData<&'interpreter ()>,
+}
+
+// Each call creates a thread state for the calling thread and the interpreter's
+// GIL serializes calls. So the interpreter can be used from any thread.
+unsafe impl Send for SubInterpreter<'_> {}
+unsafe impl Sync for SubInterpreter<'_> {}
+
+impl<'interpreter> SubInterpreter<'interpreter> {
+    pub(crate) fn new(
+        main_config: &'interpreter ResolvedOxidizedPythonInterpreterConfig,
+        config: SubInterpreterConfig,
+    ) -> Result<Self, NewInterpreterError> {
+        if config.own_gil && cfg!(not(Py_3_12)) {
+            return Err(NewInterpreterError::Simple(
+                "subinterpreters with their own GIL require Python 3.12",
+            ));
+        }
+
+        let _guard = SubInterpreterGuard::new();
+
+        unsafe {
+            let gstate = pyffi::PyGILState_Ensure();
+            let main_tstate = pyffi::PyThreadState_Get();
+
+            let tstate = match new_interpreter(config.own_gil) {
+                Ok(tstate) => tstate,
+                Err(e) => {
+                    pyffi::PyGILState_Release(gstate);
+                    return Err(e);
+                }
+            };
+
+            let interp = PyInterpreterState_Get();
+
+            let res = if config.oxidized_importer && main_config.oxidized_importer {
+                let pool = Python::assume_gil_acquired().new_pool();
+
+                if config.own_gil {
+                    install_isolated_oxidized_finder(pool.python(), main_config)
+                } else {
+                    install_oxidized_finder(pool.python(), main_config)
+                }
+            } else {
+                Ok(())
+            };
+
+            if res.is_err() {
+                pyffi::Py_EndInterpreter(tstate);
+            }
+
+            pyffi::PyThreadState_Swap(main_tstate);
+            pyffi::PyGILState_Release(gstate);
+
+            res.map(|_| Self {
+                interp,
+                tstate,
+                own_gil: config.own_gil,
+                _main: PhantomData,
+            })
+        }
+    }
+
+    /// Whether the subinterpreter has its own GIL.
+    pub fn own_gil(&self) -> bool {
+        self.own_gil
+    }
+
+    /// Run a function with the GIL of the subinterpreter held.
+    ///
+    /// Python code run via the provided [Python] runs in the subinterpreter.
+    /// If the function panics, the panic is resumed once the subinterpreter
+    /// has been left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within another call to this function, including
+    /// for a different subinterpreter.
+    pub fn with_gil<F, R>(&self, f: F) -> R
+    where
+        F: for<'py> FnOnce(Python<'py>) -> R,
+    {
+        let _guard = SubInterpreterGuard::new();
+
+        let res = unsafe {
+            let gstate = pyffi::PyGILState_Ensure();
+            let tstate = pyffi::PyThreadState_New(self.interp);
+            let previous = pyffi::PyThreadState_Swap(tstate);
+
+            let pool = Python::assume_gil_acquired().new_pool();
+            let res = catch_unwind(AssertUnwindSafe(|| f(pool.python())));
+            std::mem::drop(pool);
+
+            pyffi::PyThreadState_Clear(tstate);
+            pyffi::PyThreadState_Swap(previous);
+            pyffi::PyThreadState_Delete(tstate);
+            pyffi::PyGILState_Release(gstate);
+
+            res
+        };
+
+        res.unwrap_or_else(|e| resume_unwind(e))
+    }
+}
+
+impl Drop for SubInterpreter<'_> {
+    fn drop(&mut self) {
+        let _guard = SubInterpreterGuard::new();
+
+        unsafe {
+            let gstate = pyffi::PyGILState_Ensure();
+            let previous = pyffi::PyThreadState_Swap(self.tstate);
+            pyffi::Py_EndInterpreter(self.tstate);
+            pyffi::PyThreadState_Swap(previous);
+            pyffi::PyGILState_Release(gstate);
+        }
+    }
+}
+
+/// Create an interpreter and make its thread state current.
+///
+/// Must be called with the GIL of the main interpreter held.
+#[cfg(Py_3_12)]
+unsafe fn new_interpreter(own_gil: bool) -> Result<*mut pyffi::PyThreadState, NewInterpreterError> {
+    if !own_gil {
+        return new_interpreter_shared_gil();
+    }
+
+    let config = ffi::PyInterpreterConfig {
+        use_main_obmalloc: 0,
+        allow_fork: 0,
+        allow_exec: 0,
+        allow_threads: 1,
+        allow_daemon_threads: 0,
+        check_multi_interp_extensions: 1,
+        gil: ffi::PyInterpreterConfig_OWN_GIL,
+    };
+
+    let mut tstate = std::ptr::null_mut();
+    let status = ffi::Py_NewInterpreterFromConfig(&mut tstate, &config);
+
+    if pyffi::PyStatus_Exception(status) != 0 {
+        return Err(NewInterpreterError::new_from_pystatus(
+            &status,
+            "creating subinterpreter",
+        ));
+    }
+
+    Ok(tstate)
+}
+
+/// Create an interpreter and make its thread state current.
+///
+/// Must be called with the GIL of the main interpreter held.
+#[cfg(not(Py_3_12))]
+unsafe fn new_interpreter(
+    _own_gil: bool,
+) -> Result<*mut pyffi::PyThreadState, NewInterpreterError> {
+    new_interpreter_shared_gil()
+}
+
+unsafe fn new_interpreter_shared_gil() -> Result<*mut pyffi::PyThreadState, NewInterpreterError> {
+    let tstate = pyffi::Py_NewInterpreter();
+
+    if tstate.is_null() {
+        Err(NewInterpreterError::Simple(
+            "unable to create subinterpreter",
+        ))
+    } else {
+        Ok(tstate)
+    }
+}
+
+/// Install an `OxidizedFinder` over the resources of the main interpreter.
+///
+/// Initialization of the subinterpreter installed the standard importers.
+/// These are adjusted like [crate::MainPythonInterpreter] adjusts them.
+fn install_oxidized_finder(
+    py: Python,
+    main_config: &ResolvedOxidizedPythonInterpreterConfig,
+) -> Result<(), NewInterpreterError> {
+    let resources_state = Box::new(PythonResourcesState::try_from(main_config)?);
+
+    let sys = py
+        .import("sys")
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "obtaining sys module"))?;
+
+    // The module uses single-phase initialization, which subinterpreters with
+    // their own GIL refuse to import. So create it directly.
+    let oxidized_importer = unsafe {
+        py.from_owned_ptr_or_err::<PyModule>(PyInit_oxidized_importer())
+            .map_err(|e| {
+                NewInterpreterError::new_from_pyerr(py, e, "creating oxidized importer module")
+            })?
+    };
+
+    sys.getattr("modules")
+        .and_then(|modules| modules.set_item(OXIDIZED_IMPORTER_NAME_STR, oxidized_importer))
+        .map_err(|e| {
+            NewInterpreterError::new_from_pyerr(py, e, "registering oxidized importer module")
+        })?;
+
+    let finder = replace_meta_path_importers(
+        py,
+        oxidized_importer,
+        resources_state,
+        Some(|state: &mut ImporterState| configure_importer_state(main_config, state)),
+    )
+    .map_err(|e| {
+        NewInterpreterError::new_from_pyerr(py, e, "initialization of oxidized importer")
+    })?;
+
+    if main_config.filesystem_importer {
+        // replace_meta_path_importers() removed the path based finder.
+        py.import("_frozen_importlib_external")
+            .and_then(|m| m.getattr("PathFinder"))
+            .and_then(|path_finder| {
+                sys.getattr("meta_path")?
+                    .call_method1("append", (path_finder,))
+            })
+            .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "installing PathFinder"))?;
+    } else {
+        remove_external_importers(sys).map_err(|e| {
+            NewInterpreterError::new_from_pyerr(py, e, "removing external importers")
+        })?;
+
+        if main_config.zipimport_importer {
+            install_zipimport_importer(py, sys).map_err(|e| {
+                NewInterpreterError::new_from_pyerr(py, e, "installing zipimport importer")
+            })?;
+        }
+    }
+
+    install_extra_sys_paths(py, sys, main_config).map_err(|e| {
+        NewInterpreterError::new_from_pyerr(py, e, "installing extra sys.path entries")
+    })?;
+
+    install_path_hook(finder, sys).map_err(|e| {
+        NewInterpreterError::new_from_pyerr(py, e, "installing OxidizedFinder in sys.path_hooks")
+    })?;
+
+    Ok(())
+}
+
+/// Install a finder keeping its state in the subinterpreter.
+///
+/// The finder is used by subinterpreters with their own GIL. Each one has a
+/// separate `oxidized_importer` module holding its own [PythonResourcesState].
+/// The packed resources of the main interpreter are shared read-only.
+fn install_isolated_oxidized_finder(
+    py: Python,
+    main_config: &ResolvedOxidizedPythonInterpreterConfig,
+) -> Result<(), NewInterpreterError> {
+    let resources_state = Box::new(PythonResourcesState::try_from(main_config)?);
+
+    let sys = py
+        .import("sys")
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "obtaining sys module"))?;
+
+    // remove_external_importers() expects finders named OxidizedFinder to
+    // provide a path hook. So the finder is installed afterwards.
+    if !main_config.filesystem_importer {
+        remove_external_importers(sys).map_err(|e| {
+            NewInterpreterError::new_from_pyerr(py, e, "removing external importers")
+        })?;
+
+        if main_config.zipimport_importer {
+            install_zipimport_importer(py, sys).map_err(|e| {
+                NewInterpreterError::new_from_pyerr(py, e, "installing zipimport importer")
+            })?;
+        }
+    }
+
+    let finder = new_isolated_finder(py, resources_state).map_err(|e| {
+        NewInterpreterError::new_from_pyerr(py, e, "initialization of oxidized importer")
+    })?;
+
+    sys.getattr("meta_path")
+        .and_then(|meta_path| meta_path.call_method1("insert", (0, finder)))
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "installing OxidizedFinder"))?;
+
+    install_extra_sys_paths(py, sys, main_config).map_err(|e| {
+        NewInterpreterError::new_from_pyerr(py, e, "installing extra sys.path entries")
+    })?;
+
+    Ok(())
+}
@@ -95,6 +95,12 @@ rusty_fork_test! {
         run_py_test("test_importer_construction.py").unwrap()
     }
 
+    /// Run test_importer_coverage.py.
+    #[test]
+    fn importer_coverage_py() {
+        run_py_test("test_importer_coverage.py").unwrap()
+    }
+
     #[test]
     fn importer_indexing() {
         run_py_test("test_importer_indexing.py").unwrap()
@@ -153,6 +159,30 @@ rusty_fork_test! {
         run_py_test("test_importer_path_entry_finder.py").unwrap()
     }
 
+    /// Run test_importer_wheel.py.
+    #[test]
+    fn importer_wheel_py() {
+        run_py_test("test_importer_wheel.py").unwrap()
+    }
+
+    /// Run test_importer_diagnostics.py.
+    #[test]
+    fn importer_diagnostics_py() {
+        run_py_test("test_importer_diagnostics.py").unwrap()
+    }
+
+    /// Run test_importer_static_server.py.
+    #[test]
+    fn importer_static_server_py() {
+        run_py_test("test_importer_static_server.py").unwrap()
+    }
+
+    /// Run test_importer_wasm.py.
+    #[test]
+    fn importer_wasm_py() {
+        run_py_test("test_importer_wasm.py").unwrap()
+    }
+
     /// Run test_zip_importer.py
     #[test]
     fn zip_importer_py() {
@@ -4,18 +4,22 @@
 
 use {
     super::{default_interpreter_config, set_sys_paths, PYTHON_INTERPRETER_PATH},
-    crate::{MainPythonInterpreter, OxidizedPythonInterpreterConfig},
+    crate::{
+        ExtensionModule, MainPythonInterpreter, OxidizedPythonInterpreterConfig, SandboxPolicy,
+    },
     pyo3::{
         ffi as pyffi,
         prelude::*,
         types::{PyBytes, PyList, PyString, PyStringData},
     },
     python_packaging::{
-        interpreter::{BytesWarning, MemoryAllocatorBackend, PythonInterpreterProfile},
+        interpreter::{
+            BytesWarning, LogDestination, MemoryAllocatorBackend, PythonInterpreterProfile,
+        },
         resource::BytecodeOptimizationLevel,
     },
     rusty_fork::rusty_fork_test,
-    std::{ffi::OsString, path::PathBuf},
+    std::{ffi::OsString, path::PathBuf, sync::Mutex},
 };
 
 #[cfg(target_family = "unix")]
@@ -24,6 +28,45 @@ use std::os::unix::ffi::OsStringExt;
 #[cfg(target_family = "windows")]
 use std::os::windows::ffi::OsStringExt;
 
+#[pymodule]
+fn pyembed_test_host(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("VALUE", 42)?;
+
+    Ok(())
+}
+
+fn set_host_value(py: Python) -> PyResult<()> {
+    py.import("pyembed_test_host")?.setattr("HOOKED", true)
+}
+
+static CAPTURED_STDOUT: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+static CAPTURED_STDERR: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+fn capture_stdout(data: &[u8]) {
+    CAPTURED_STDOUT.lock().unwrap().extend_from_slice(data);
+}
+
+fn capture_stderr(data: &[u8]) {
+    CAPTURED_STDERR.lock().unwrap().extend_from_slice(data);
+}
+
+static AUDIT_EVENTS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+fn capture_audit_event(event: &str, args: &str) {
+    AUDIT_EVENTS
+        .lock()
+        .unwrap()
+        .push((event.to_string(), args.to_string()));
+}
+
+#[cfg(unix)]
+static HOST_SIGINT_COUNT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+#[cfg(unix)]
+extern "C" fn host_sigint_handler(_signal: std::os::raw::c_int) {
+    HOST_SIGINT_COUNT.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+}
+
 #[cfg(target_family = "unix")]
 fn get_unicode_argument() -> OsString {
     // 中文 = U+4e2d / 20013 + U+6587 / 25991
@@ -301,6 +344,69 @@ rusty_fork_test! {
         assert_eq!(interp.allocator.as_ref().unwrap().backend(), MemoryAllocatorBackend::Snmalloc);
     }
 
+    #[test]
+    fn test_allocator_stats() {
+        let mut config = default_interpreter_config();
+
+        config.allocator_backend = MemoryAllocatorBackend::Rust;
+        config.allocator_raw = true;
+        config.allocator_stats = true;
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        assert!(interp.allocator.is_some());
+
+        let stats = crate::allocator_stats().unwrap();
+        assert_eq!(stats.backend, MemoryAllocatorBackend::Rust);
+        assert!(stats.allocations > 0);
+        assert!(stats.deallocations > 0);
+        assert!(stats.peak_allocated_bytes >= stats.allocated_bytes);
+    }
+
+    #[test]
+    fn test_allocator_stats_disabled() {
+        let mut config = default_interpreter_config();
+
+        config.allocator_backend = MemoryAllocatorBackend::Rust;
+        config.allocator_raw = true;
+
+        MainPythonInterpreter::new(config).unwrap();
+
+        assert!(crate::allocator_stats().is_none());
+    }
+
+    #[test]
+    fn test_log_destination_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.log");
+
+        let mut config = default_interpreter_config();
+        config.log_destination = Some(LogDestination::File(path.display().to_string()));
+        config.log_filter = Some("info,noisy=error".to_string());
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        log::info!(target: "app::rust", "from rust");
+        log::debug!(target: "app::rust", "hidden");
+
+        interp.with_gil(|py| {
+            py.run(
+                "import logging\n\
+                 logging.getLogger('app.python').info('from %s', 'python')\n\
+                 logging.getLogger('app.python').debug('hidden')\n\
+                 logging.getLogger('noisy').warning('hidden')\n",
+                None,
+                None,
+            )
+            .unwrap();
+        });
+
+        let lines = std::fs::read_to_string(&path).unwrap();
+        let lines = lines.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(" INFO  app::rust: from rust"));
+        assert!(lines[1].ends_with(" INFO  app.python: from python"));
+    }
+
     #[test]
     fn test_allocator_debug() {
         let mut config = default_interpreter_config();
@@ -779,4 +885,157 @@ rusty_fork_test! {
             assert_eq!(flags.getattr("dont_write_bytecode").unwrap().extract::<i64>().unwrap(), 1);
         });
     }
+
+    #[test]
+    fn test_extension_module_and_startup_hook() {
+        let mut config = default_interpreter_config();
+        config.extra_extension_modules = Some(vec![ExtensionModule::new(
+            "pyembed_test_host",
+            pyembed_test_host::init,
+        )]);
+        config.startup_hooks = vec![set_host_value];
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let module = py.import("pyembed_test_host").unwrap();
+
+            assert_eq!(module.getattr("VALUE").unwrap().extract::<i64>().unwrap(), 42);
+            assert!(module.getattr("HOOKED").unwrap().extract::<bool>().unwrap());
+        });
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[test]
+    fn test_sandbox_policy() {
+        let mut config = default_interpreter_config();
+        config.sandbox_policy = Some(SandboxPolicy::default());
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            py.run(
+                "import socket\n\
+                 socket.socket(socket.AF_UNIX).close()\n\
+                 try:\n    \
+                     socket.socket(socket.AF_INET)\n    \
+                     raise AssertionError('IP socket was allowed')\n\
+                 except PermissionError:\n    \
+                     pass\n",
+                None,
+                None,
+            )
+            .unwrap();
+        });
+    }
+
+    #[test]
+    fn test_stdio_callbacks() {
+        let mut config = default_interpreter_config();
+        config.stdout_callback = Some(capture_stdout);
+        config.stderr_callback = Some(capture_stderr);
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            py.run(
+                "import sys\n\
+                 print('hello', '\\u20ac')\n\
+                 print('error', file=sys.stderr)\n\
+                 sys.stdout.buffer.write(b'raw')\n",
+                None,
+                None,
+            )
+            .unwrap();
+        });
+
+        assert_eq!(
+            CAPTURED_STDOUT.lock().unwrap().as_slice(),
+            "hello \u{20ac}\nraw".as_bytes()
+        );
+        assert_eq!(CAPTURED_STDERR.lock().unwrap().as_slice(), b"error\n");
+    }
+
+    #[test]
+    fn test_audit_callback() {
+        let mut config = default_interpreter_config();
+        config.audit_callback = Some(capture_audit_event);
+        config.audit_events = vec!["pyembed_test".to_string(), "import".to_string()];
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            py.run(
+                "import sys\n\
+                 sys.audit('pyembed_test.event', 1, 'a')\n\
+                 sys.audit('pyembed_testing', 2)\n\
+                 import json\n",
+                None,
+                None,
+            )
+            .unwrap();
+        });
+
+        let events = AUDIT_EVENTS.lock().unwrap();
+        assert!(events.contains(&("pyembed_test.event".to_string(), "(1, 'a')".to_string())));
+        assert!(events
+            .iter()
+            .any(|(event, args)| event == "import" && args.starts_with("('json',")));
+        assert!(events
+            .iter()
+            .all(|(event, _)| event == "import" || event == "pyembed_test.event"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stdio_callback_fds() {
+        let mut config = default_interpreter_config();
+        config.stdout_callback = Some(capture_stdout);
+        config.stdio_callback_fds = true;
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            py.run(
+                "import os, sys\n\
+                 print('python')\n\
+                 sys.stdout.flush()\n\
+                 os.write(1, b'fd\\n')\n",
+                None,
+                None,
+            )
+            .unwrap();
+        });
+
+        drop(interp);
+
+        assert_eq!(CAPTURED_STDOUT.lock().unwrap().as_slice(), b"python\nfd\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_host_signals() {
+        unsafe {
+            libc::signal(
+                libc::SIGINT,
+                host_sigint_handler as extern "C" fn(std::os::raw::c_int) as libc::sighandler_t,
+            )
+        };
+
+        let mut config = default_interpreter_config();
+        config.interpreter_config.install_signal_handlers = Some(true);
+        config.host_signals = vec![libc::SIGINT];
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            unsafe { libc::raise(libc::SIGINT) };
+            assert_eq!(HOST_SIGINT_COUNT.load(std::sync::atomic::Ordering::SeqCst), 1);
+            py.check_signals().unwrap();
+
+            crate::interrupt_python();
+            let err = py.check_signals().unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyKeyboardInterrupt>(py));
+        });
+    }
 }
@@ -4,8 +4,11 @@
 
 use {
     super::{default_interpreter_config, run_py_test},
-    crate::MainPythonInterpreter,
-    pyo3::ffi as pyffi,
+    crate::{
+        BuildInfo, InterpreterThread, InterpreterThreadError, MainPythonInterpreter,
+        MemoryAllocatorBackend, SubInterpreterConfig,
+    },
+    pyo3::{ffi as pyffi, types::PyDict},
     rusty_fork::rusty_fork_test,
 };
 
@@ -35,6 +38,627 @@ rusty_fork_test! {
         std::mem::drop(interp);
     }
 
+    #[test]
+    fn setproctitle_module() {
+        let mut config = default_interpreter_config();
+        config.setproctitle_module = true;
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        let args = std::env::args_os().collect::<Vec<_>>();
+
+        interp.with_gil(|py| {
+            let m = py.import("setproctitle").unwrap();
+            m.getattr("setproctitle")
+                .unwrap()
+                .call1(("pyembed-test: worker",))
+                .unwrap();
+
+            let title = m.getattr("getproctitle").unwrap().call0().unwrap();
+            assert_eq!(title.extract::<String>().unwrap(), "pyembed-test: worker");
+        });
+
+        assert_eq!(crate::process_title(), "pyembed-test: worker");
+        assert_eq!(std::env::args_os().collect::<Vec<_>>(), args);
+
+        #[cfg(target_os = "linux")]
+        {
+            let cmdline = std::fs::read("/proc/self/cmdline").unwrap();
+            assert!(cmdline.starts_with(b"pyembed-test: worker\0"));
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        interp.with_gil(|py| {
+            let m = py.import("setproctitle").unwrap();
+            m.getattr("setthreadtitle")
+                .unwrap()
+                .call1(("test-thread",))
+                .unwrap();
+
+            let title = m.getattr("getthreadtitle").unwrap().call0().unwrap();
+            assert_eq!(title.extract::<String>().unwrap(), "test-thread");
+        });
+    }
+
+    #[test]
+    fn setproctitle_module_disabled() {
+        let config = default_interpreter_config();
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            assert!(py.import("setproctitle").is_err());
+        });
+    }
+
+    #[test]
+    fn site_module() {
+        let mut config = default_interpreter_config();
+        config.oxidized_importer = true;
+        config.interpreter_config.site_import = Some(true);
+        config.site_module = Some(
+            "import sys\n\
+            sys.site_meta_path = [type(f).__name__ for f in sys.meta_path]\n\
+            sys.site_oxidized = hasattr(sys, 'oxidized')\n"
+                .to_string(),
+        );
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let sys = py.import("sys").unwrap();
+
+            let meta_path = sys.getattr("site_meta_path").unwrap();
+            assert_eq!(
+                meta_path.get_item(0).unwrap().extract::<String>().unwrap(),
+                "OxidizedFinder"
+            );
+            assert!(!sys.getattr("site_oxidized").unwrap().extract::<bool>().unwrap());
+
+            let site = py.import("site").unwrap();
+            assert!(!site.hasattr("addsitedir").unwrap());
+        });
+    }
+
+    #[test]
+    fn allocator_stats() {
+        let mut config = default_interpreter_config();
+        config.oxidized_importer = true;
+        config.allocator_backend = MemoryAllocatorBackend::Rust;
+        config.allocator_raw = true;
+        config.allocator_stats = true;
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let stats = py
+                .import("oxidized_importer.allocator")
+                .unwrap()
+                .call_method0("stats")
+                .unwrap()
+                .downcast::<PyDict>()
+                .unwrap();
+
+            let get = |name: &str| stats.get_item(name).unwrap();
+            assert_eq!(get("backend").extract::<String>().unwrap(), "rust");
+            assert!(get("allocations").extract::<usize>().unwrap() > 0);
+            assert!(
+                get("peak_allocated_bytes").extract::<usize>().unwrap()
+                    >= get("allocated_bytes").extract::<usize>().unwrap()
+            );
+        });
+    }
+
+    #[test]
+    fn build_info() {
+        let mut config = default_interpreter_config();
+        config.oxidized_importer = true;
+        config.build_info = Some(BuildInfo {
+            target_triple: "x86_64-unknown-linux-gnu".to_string(),
+            python_version: "3.10.9".to_string(),
+            python_flavor: "standalone-static".to_string(),
+            resources_location: "in-memory".to_string(),
+            resources_location_fallback: None,
+            features: vec!["allocator-jemalloc".to_string()],
+        });
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let m = py.import("oxidized_importer.build_info").unwrap();
+
+            let get = |name: &str| m.getattr(name).unwrap();
+            assert_eq!(
+                get("target_triple").extract::<String>().unwrap(),
+                "x86_64-unknown-linux-gnu"
+            );
+            assert_eq!(get("python_flavor").extract::<String>().unwrap(), "standalone-static");
+            assert_eq!(get("resources_location").extract::<String>().unwrap(), "in-memory");
+            assert!(get("resources_location_fallback").is_none());
+            assert_eq!(
+                get("features").extract::<Vec<String>>().unwrap(),
+                vec!["allocator-jemalloc".to_string()]
+            );
+        });
+    }
+
+    #[test]
+    fn startup_profile() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("profile.json");
+        let on_demand_path = temp_dir.path().join("on-demand.json");
+        std::env::set_var("PYEMBED_TEST_STARTUP_PROFILE", &path);
+
+        let mut config = default_interpreter_config();
+        config.oxidized_importer = true;
+        config.filesystem_importer = true;
+        config.startup_profile_env = Some("PYEMBED_TEST_STARTUP_PROFILE".to_string());
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            py.import("_json").unwrap();
+        });
+
+        crate::write_startup_profile(&on_demand_path).unwrap();
+        let profile = std::fs::read_to_string(&on_demand_path).unwrap();
+        assert!(profile.starts_with("{\"traceEvents\":["));
+        assert!(profile.contains("\"name\":\"initialize core\",\"cat\":\"startup\""));
+        assert!(profile.contains("\"name\":\"_json\",\"cat\":\"import\""));
+        assert!(!path.exists());
+
+        // The profile is written when the interpreter finalizes.
+        std::mem::drop(interp);
+        let profile = std::fs::read_to_string(&path).unwrap();
+        assert!(profile.contains("\"name\":\"initialize\",\"cat\":\"startup\""));
+    }
+
+    #[test]
+    fn extra_sys_paths() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let plugins = temp_dir.path().join("plugins");
+        let env_plugins = temp_dir.path().join("env-plugins");
+        let other = temp_dir.path().join("other");
+        for (dir, module) in [(&plugins, "my_plugin"), (&env_plugins, "env_plugin"), (&other, "other")] {
+            std::fs::create_dir(dir).unwrap();
+            std::fs::write(dir.join(format!("{}.py", module)), "VALUE = 42\n").unwrap();
+        }
+        std::env::set_var("PYEMBED_TEST_PLUGINS_PATH", &env_plugins);
+
+        let mut config = default_interpreter_config();
+        config.filesystem_importer = false;
+        config.extra_sys_paths = vec![plugins.clone()];
+        config.extra_sys_paths_env = Some("PYEMBED_TEST_PLUGINS_PATH".to_string());
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let sys_path = py.import("sys").unwrap().getattr("path").unwrap();
+            let paths = sys_path.extract::<Vec<String>>().unwrap();
+            assert_eq!(
+                paths[paths.len() - 2..],
+                [plugins.display().to_string(), env_plugins.display().to_string()]
+            );
+
+            py.import("my_plugin").unwrap();
+            py.import("env_plugin").unwrap();
+
+            // Other filesystem locations remain off limits.
+            sys_path.call_method1("append", (other.display().to_string(),)).unwrap();
+            assert!(py.import("other").is_err());
+        });
+    }
+
+    #[test]
+    fn migrations() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let modules = temp_dir.path().join("modules");
+        let data = temp_dir.path().join("data");
+        std::fs::create_dir(&modules).unwrap();
+        std::fs::write(
+            modules.join("pyembed_test_migrations.py"),
+            "import sys\n\
+            def applied():\n    raise AssertionError('already applied')\n\
+            def rename():\n    sys.pyembed_test_migrated = True\n\
+            def broken():\n    raise ValueError('cannot migrate')\n",
+        )
+        .unwrap();
+        for var in ["HOME", "XDG_DATA_HOME", "LOCALAPPDATA"] {
+            std::env::set_var(var, &data);
+        }
+
+        let state_dir = crate::migrations::user_data_dir().unwrap().join("myapp");
+        std::fs::create_dir_all(&state_dir).unwrap();
+        let state_path = state_dir.join("pyembed-migrations.txt");
+        std::fs::write(&state_path, "applied 1.0\n").unwrap();
+
+        let mut config = default_interpreter_config();
+        config.extra_sys_paths = vec![modules];
+        config.migrations = ["applied", "rename", "broken"]
+            .iter()
+            .enumerate()
+            .map(|(i, function)| {
+                crate::Migration::try_from(
+                    format!("{}.0=pyembed_test_migrations:{}", i + 1, function).as_str(),
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let err = config.clone().resolve().err().unwrap().to_string();
+        assert!(err.contains("migrations_app_name must be set"));
+
+        config.migrations_app_name = Some("myapp".to_string());
+        let err = MainPythonInterpreter::new(config).err().unwrap().to_string();
+        assert!(err.contains(
+            "running migration 3.0 (pyembed_test_migrations:broken); \
+            migrations applied by this run before it: 2.0"
+        ));
+        assert!(err.contains("cannot migrate"));
+
+        assert_eq!(
+            std::fs::read_to_string(&state_path).unwrap(),
+            "applied 1.0\napplied 2.0\nfailed 3.0 ValueError: cannot migrate\n"
+        );
+    }
+
+    #[test]
+    fn replace_missing_stdio() {
+        let mut config = default_interpreter_config();
+        config.replace_missing_stdio = true;
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let globals = PyDict::new(py);
+            py.run(
+                "import sys\n\
+                 stdout = sys.stdout\n\
+                 sys.stdin = sys.stdout = sys.__stdout__ = None",
+                Some(globals),
+                None,
+            )
+            .unwrap();
+
+            crate::stdio::replace_missing_stdio(py).unwrap();
+
+            py.run(
+                "assert sys.stdin.read() == ''\n\
+                 assert sys.stdout.write('discarded') == 9\n\
+                 assert sys.__stdout__ is sys.stdout\n\
+                 assert sys.stderr is not None and sys.stderr is not sys.stdout\n\
+                 sys.stdout = sys.__stdout__ = stdout",
+                Some(globals),
+                None,
+            )
+            .unwrap();
+        });
+    }
+
+    #[test]
+    fn traceback_source_paths() {
+        let mut config = default_interpreter_config();
+        config.traceback_source_paths = true;
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let globals = PyDict::new(py);
+            py.run(
+                r#"
+import io, linecache, sys
+
+class Resource:
+    source_map_path = "src/app/main.py"
+
+class Finder:
+    def find_spec(self, name, path, target=None):
+        return None
+
+    def find_resource(self, name):
+        return Resource() if name == "app.main" else None
+
+sys.meta_path.insert(0, Finder())
+
+source = "def fail():\n    raise ValueError('boom')\n"
+linecache.cache["app.main"] = (len(source), None, source.splitlines(True), "app.main")
+namespace = {}
+exec(compile(source, "app.main", "exec"), namespace)
+
+stderr, sys.stderr = sys.stderr, io.StringIO()
+try:
+    namespace["fail"]()
+except ValueError:
+    sys.excepthook(*sys.exc_info())
+output, sys.stderr = sys.stderr.getvalue(), stderr
+"#,
+                Some(globals),
+                None,
+            )
+            .unwrap();
+
+            let output = globals
+                .get_item("output")
+                .unwrap()
+                .extract::<String>()
+                .unwrap();
+            assert!(output.contains("File \"src/app/main.py\", line 2, in fail"));
+            assert!(output.contains("raise ValueError('boom')"));
+            assert!(output.contains("ValueError: boom"));
+            assert!(!output.contains("app.main"));
+        });
+    }
+
+    #[test]
+    fn int_max_str_digits() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.int_max_str_digits = Some(5000);
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let digits = py
+                .import("sys")
+                .unwrap()
+                .call_method0("get_int_max_str_digits")
+                .unwrap();
+            assert_eq!(digits.extract::<i64>().unwrap(), 5000);
+        });
+    }
+
+    #[cfg(Py_3_11)]
+    #[test]
+    fn safe_path_and_code_debug_ranges() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.safe_path = Some(true);
+        config.interpreter_config.code_debug_ranges = Some(false);
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            let flags = py.import("sys").unwrap().getattr("flags").unwrap();
+            assert!(flags.getattr("safe_path").unwrap().extract::<bool>().unwrap());
+
+            let positions = py
+                .eval("list(compile('x + 1', '<test>', 'eval').co_positions())", None, None)
+                .unwrap()
+                .extract::<Vec<(Option<i64>, Option<i64>, Option<i64>, Option<i64>)>>()
+                .unwrap();
+            assert!(positions.iter().all(|(_, _, start, end)| start.is_none() && end.is_none()));
+        });
+    }
+
+    #[cfg(not(Py_3_12))]
+    #[test]
+    fn perf_profiling_requires_python_3_12() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.perf_profiling = Some(true);
+
+        assert!(MainPythonInterpreter::new(config).is_err());
+    }
+
+    #[test]
+    fn site_module_requires_oxidized_importer() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.site_import = Some(true);
+        config.site_module = Some("".to_string());
+
+        assert!(MainPythonInterpreter::new(config).is_err());
+    }
+
+    #[test]
+    fn with_gil_on_any_thread() {
+        assert!(crate::with_gil_on_any_thread(|_| ()).is_err());
+
+        let config = default_interpreter_config();
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        let threads = (0..4)
+            .map(|i| {
+                std::thread::spawn(move || {
+                    crate::with_gil_on_any_thread(|py| {
+                        assert_eq!(unsafe { pyffi::PyGILState_Check() }, 1);
+
+                        // Nested calls reuse the outer call's state.
+                        crate::with_gil_on_any_thread(|py| {
+                            py.eval(&format!("{} + 1", i), None, None)
+                                .unwrap()
+                                .extract::<i32>()
+                                .unwrap()
+                        })
+                        .unwrap()
+                            + py.eval("1", None, None).unwrap().extract::<i32>().unwrap()
+                    })
+                    .unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let values = threads
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![2, 3, 4, 5]);
+
+        std::mem::drop(interp);
+
+        let res = std::thread::spawn(|| crate::with_gil_on_any_thread(|_| ()))
+            .join()
+            .unwrap();
+        assert_eq!(res, Err(crate::InterpreterUnavailableError));
+    }
+
+    #[test]
+    fn try_run_success() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.run_command = Some("import sys".to_string());
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        assert_eq!(interp.try_run(), Ok(0));
+    }
+
+    #[test]
+    fn try_run_exception() {
+        let mut config = default_interpreter_config();
+        config.interpreter_config.run_command = Some(
+            "import json\ndef parse():\n    json.loads('{')\nparse()\n".to_string(),
+        );
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        let exception = interp.try_run().unwrap_err();
+
+        assert_eq!(exception.type_name, "json.decoder.JSONDecodeError");
+        assert!(exception.message.starts_with("Expecting property name"));
+        assert!(exception
+            .traceback
+            .starts_with("Traceback (most recent call last):\n"));
+        assert!(exception
+            .traceback
+            .ends_with(&format!("{}\n", exception)));
+        assert_eq!(exception.frames[0].filename, "<string>");
+        assert_eq!(exception.frames[0].line, Some(4));
+        assert_eq!(exception.frames[1].function, "parse");
+    }
+
+    #[test]
+    fn shutdown_lingering_threads() {
+        let config = default_interpreter_config();
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            py.run(
+                "import atexit, threading\n\
+                 stop = threading.Event()\n\
+                 atexit.register(stop.set)\n\
+                 threading.Thread(target=stop.wait, name='stops').start()\n\
+                 threading.Thread(target=threading.Event().wait, name='stuck').start()\n",
+                None,
+                None,
+            )
+            .unwrap();
+        });
+
+        let lingering = interp.shutdown(std::time::Duration::from_millis(100));
+
+        assert_eq!(unsafe { pyffi::Py_IsInitialized() }, 0);
+        assert_eq!(lingering.len(), 1);
+        assert_eq!(lingering[0].name, "stuck");
+        assert!(lingering[0].stack.contains("in wait"));
+    }
+
+    #[test]
+    fn interpreter_thread() {
+        let interp = InterpreterThread::spawn(default_interpreter_config()).unwrap();
+
+        let host_thread = std::thread::current().id();
+        interp
+            .run(move |_| assert_ne!(std::thread::current().id(), host_thread))
+            .unwrap();
+
+        assert!(interp
+            .eval::<bool>("__import__('threading').current_thread() is __import__('threading').main_thread()")
+            .unwrap());
+        assert_eq!(interp.eval::<i64>("1 + 2").unwrap(), 3);
+
+        match interp.eval::<i64>("1 / 0") {
+            Err(InterpreterThreadError::Exception(e)) => {
+                assert_eq!(e.type_name, "ZeroDivisionError");
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        let task = interp
+            .submit(|py| -> String { py.eval("'x' * 2", None, None).unwrap().extract().unwrap() })
+            .unwrap();
+        assert_eq!(task.wait().unwrap(), "xx");
+
+        assert!(interp.shutdown(std::time::Duration::from_secs(1)).is_empty());
+        assert_eq!(unsafe { pyffi::Py_IsInitialized() }, 0);
+    }
+
+    #[test]
+    fn subinterpreter() {
+        let mut config = default_interpreter_config();
+        config.oxidized_importer = true;
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| py.run("import sys; sys.tenant = 'main'", None, None).unwrap());
+
+        let sub = interp
+            .new_subinterpreter(SubInterpreterConfig::default())
+            .unwrap();
+        assert!(!sub.own_gil());
+
+        sub.with_gil(|py| {
+            let sys = py.import("sys").unwrap();
+            assert!(!sys.hasattr("tenant").unwrap());
+            sys.setattr("tenant", "sub").unwrap();
+
+            let meta_path = sys.getattr("meta_path").unwrap();
+            assert_eq!(
+                meta_path
+                    .get_item(0)
+                    .unwrap()
+                    .get_type()
+                    .name()
+                    .unwrap(),
+                "OxidizedFinder"
+            );
+            py.import("json").unwrap();
+        });
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let tenant = sub.with_gil(|py| -> String {
+                    py.eval("__import__('sys').tenant", None, None)
+                        .unwrap()
+                        .extract()
+                        .unwrap()
+                });
+                assert_eq!(tenant, "sub");
+            });
+        });
+
+        interp.with_gil(|py| {
+            let tenant: String = py
+                .eval("__import__('sys').tenant", None, None)
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(tenant, "main");
+        });
+
+        std::mem::drop(sub);
+
+        #[cfg(Py_3_12)]
+        {
+            let isolated = interp
+                .new_subinterpreter(SubInterpreterConfig {
+                    own_gil: true,
+                    oxidized_importer: true,
+                })
+                .unwrap();
+            assert!(isolated.own_gil());
+
+            std::thread::scope(|scope| {
+                scope.spawn(|| {
+                    isolated.with_gil(|py| {
+                        let sys = py.import("sys").unwrap();
+                        assert!(!sys.hasattr("tenant").unwrap());
+
+                        let finder = sys.getattr("meta_path").unwrap().get_item(0).unwrap();
+                        assert_eq!(finder.get_type().name().unwrap(), "OxidizedFinder");
+
+                        let json = py.import("json").unwrap();
+                        assert!(json
+                            .getattr("__spec__")
+                            .unwrap()
+                            .getattr("loader")
+                            .unwrap()
+                            .is(finder));
+                    });
+                });
+            });
+        }
+
+        #[cfg(not(Py_3_12))]
+        assert!(interp
+            .new_subinterpreter(SubInterpreterConfig {
+                own_gil: true,
+                oxidized_importer: true,
+            })
+            .is_err());
+    }
+
     #[test]
     fn multiprocessing_py() {
         run_py_test("test_multiprocessing.py").unwrap()
@@ -3,11 +3,12 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    crate::OxidizedPythonInterpreterConfig,
+    crate::{OxidizedPythonInterpreterConfig, PackedResourcesKey, PackedResourcesVerification},
     anyhow::{anyhow, Result},
     oxidized_importer::{PackedResourcesSource, PyTempDir, PythonResourcesState},
     python_packed_resources::Resource,
     rusty_fork::rusty_fork_test,
+    sha2::{Digest, Sha256},
 };
 
 #[test]
@@ -21,7 +22,7 @@ fn multiple_resource_blobs() -> Result<()> {
             ..Default::default()
         })
         .unwrap();
-    let data0 = state0.serialize_resources(true, true)?;
+    let data0 = state0.serialize_resources(true, true, 3)?;
 
     let mut state1 = PythonResourcesState::default();
     state1
@@ -32,7 +33,7 @@ fn multiple_resource_blobs() -> Result<()> {
             ..Default::default()
         })
         .unwrap();
-    let data1 = state1.serialize_resources(true, true)?;
+    let data1 = state1.serialize_resources(true, true, 3)?;
 
     let config = OxidizedPythonInterpreterConfig::default().resolve()?;
 
@@ -62,7 +63,7 @@ fn test_memory_mapped_file_resources() -> Result<()> {
             ..Default::default()
         })
         .unwrap();
-    let data0 = state0.serialize_resources(true, true)?;
+    let data0 = state0.serialize_resources(true, true, 3)?;
 
     let resources_dir = current_dir.join("resources");
     if !resources_dir.exists() {
@@ -99,6 +100,162 @@ fn test_memory_mapped_file_resources() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_memory_mapped_file_resources_verified() -> Result<()> {
+    let current_dir = std::env::current_exe()?
+        .parent()
+        .ok_or_else(|| anyhow!("unable to find current exe parent"))?
+        .to_path_buf();
+
+    let mut state0 = PythonResourcesState::default();
+    state0
+        .add_resource(Resource {
+            name: "foo".into(),
+            is_python_module: true,
+            in_memory_source: Some(vec![42].into()),
+            ..Default::default()
+        })
+        .unwrap();
+    let data0 = state0.serialize_resources(true, true, 3)?;
+    let digest: [u8; 32] = Sha256::digest(&data0).into();
+
+    let resources_dir = current_dir.join("resources");
+    if !resources_dir.exists() {
+        std::fs::create_dir(&resources_dir)?;
+    }
+
+    let resources_path = resources_dir.join("test_memory_mapped_file_resources_verified");
+    std::fs::write(&resources_path, data0)?;
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config
+        .packed_resources
+        .push(PackedResourcesSource::MemoryMappedPathVerified(
+            resources_path.clone(),
+            digest,
+        ));
+
+    let resolved = config.resolve()?;
+    let resources = PythonResourcesState::try_from(&resolved)?;
+    assert!(resources.has_resource("foo"));
+
+    // A digest mismatch refuses to load the file.
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config
+        .packed_resources
+        .push(PackedResourcesSource::MemoryMappedPathVerified(
+            resources_path,
+            [0; 32],
+        ));
+
+    let resolved = config.resolve()?;
+    assert!(PythonResourcesState::try_from(&resolved).is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "encryption")]
+fn test_encrypted_resources() -> Result<()> {
+    let mut state0 = PythonResourcesState::default();
+    state0
+        .add_resource(Resource {
+            name: "foo".into(),
+            is_python_module: true,
+            in_memory_source: Some(vec![42].into()),
+            ..Default::default()
+        })
+        .unwrap();
+    let data0 = state0.serialize_resources(true, true, 3)?;
+    let encrypted = python_packed_resources::encrypt_packed_resources(&data0, &[42; 32])
+        .map_err(|e| anyhow!(e))?;
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config
+        .packed_resources
+        .push(PackedResourcesSource::Memory(&encrypted));
+
+    // Loading fails without a key.
+    let resolved = config.clone().resolve()?;
+    assert!(PythonResourcesState::try_from(&resolved).is_err());
+
+    config.packed_resources_key = Some(PackedResourcesKey::Key([0; 32]));
+    let resolved = config.clone().resolve()?;
+    assert!(PythonResourcesState::try_from(&resolved).is_err());
+
+    config.packed_resources_key = Some(PackedResourcesKey::Key([42; 32]));
+    let resolved = config.clone().resolve()?;
+    let resources = PythonResourcesState::try_from(&resolved)?;
+    assert!(resources.has_resource("foo"));
+
+    config.packed_resources_key = Some(PackedResourcesKey::Callback(|| Ok([42; 32])));
+    let resolved = config.resolve()?;
+    let resources = PythonResourcesState::try_from(&resolved)?;
+    assert!(resources.has_resource("foo"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "encryption"))]
+fn test_encrypted_resources_unsupported() -> Result<()> {
+    let mut data = python_packed_resources::ENCRYPTED_HEADER.to_vec();
+    data.extend_from_slice(&[0; 64]);
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config
+        .packed_resources
+        .push(PackedResourcesSource::Memory(&data));
+    config.packed_resources_key = Some(PackedResourcesKey::Key([42; 32]));
+
+    let resolved = config.resolve()?;
+    assert!(PythonResourcesState::try_from(&resolved).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_resources_verification() -> Result<()> {
+    let mut state0 = PythonResourcesState::default();
+    state0
+        .add_resource(Resource {
+            name: "foo".into(),
+            is_python_module: true,
+            in_memory_source: Some(b"import io".to_vec().into()),
+            ..Default::default()
+        })
+        .unwrap();
+    let mut data0 = state0.serialize_resources(true, true, 4)?;
+
+    // Corrupt the module source.
+    let offset = data0
+        .windows(b"import io".len())
+        .position(|w| w == b"import io")
+        .ok_or_else(|| anyhow!("module source not found"))?;
+    data0[offset] = b'I';
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config
+        .packed_resources
+        .push(PackedResourcesSource::Memory(&data0));
+
+    let resolved = config.clone().resolve()?;
+    let resources = PythonResourcesState::try_from(&resolved)?;
+    assert!(resources.verify_resource("foo").is_ok());
+
+    config.packed_resources_verification = PackedResourcesVerification::OnLoad;
+    let resolved = config.clone().resolve()?;
+    let resources = PythonResourcesState::try_from(&resolved)?;
+    assert!(resources.has_resource("foo"));
+    assert!(resources.verify_resource("foo").is_err());
+
+    config.packed_resources_verification = PackedResourcesVerification::Startup;
+    let resolved = config.resolve()?;
+    assert!(PythonResourcesState::try_from(&resolved).is_err());
+
+    Ok(())
+}
+
 fn get_interpreter<'interp, 'rsrc>() -> crate::MainPythonInterpreter<'interp, 'rsrc> {
     let mut config = crate::OxidizedPythonInterpreterConfig::default();
     config.interpreter_config.parse_argv = Some(false);
@@ -42,6 +42,27 @@ class TestImporterConstruction(unittest.TestCase):
         f = OxidizedFinder()
         self.assertIsNone(f.multiprocessing_set_start_method)
 
+    def test_filesystem_first_packages(self):
+        f = OxidizedFinder()
+        self.assertEqual(f.filesystem_first_packages, [])
+
+        f = OxidizedFinder(filesystem_first_packages=["foo", "bar.baz"])
+        self.assertEqual(f.filesystem_first_packages, ["foo", "bar.baz"])
+
+    def test_lazy_import_packages(self):
+        f = OxidizedFinder()
+        self.assertEqual(f.lazy_import_packages, [])
+
+        f = OxidizedFinder(lazy_import_packages=["foo", "bar.baz"])
+        self.assertEqual(f.lazy_import_packages, ["foo", "bar.baz"])
+
+    def test_materialized_files_dir(self):
+        f = OxidizedFinder()
+        self.assertIsNone(f.materialized_files_dir)
+
+        f = OxidizedFinder(materialized_files_dir="/tmp/materialized")
+        self.assertEqual(f.materialized_files_dir, "/tmp/materialized")
+
     def test_origin_bad_value(self):
         with self.assertRaises(TypeError):
             OxidizedFinder(relative_path_origin=True)
@@ -0,0 +1,159 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+import importlib
+import io
+import marshal
+import os
+import pathlib
+import sys
+import tempfile
+import unittest
+
+from oxidized_importer import (
+    OxidizedFinder,
+    OxidizedResource,
+    OxidizedResourceCollector,
+    coverage_init,
+    find_resources_in_path,
+)
+
+try:
+    import coverage
+except ImportError:
+    coverage = None
+
+
+class FakeRegistry:
+    def __init__(self):
+        self.file_tracers = []
+        self.configurers = []
+
+    def add_file_tracer(self, plugin):
+        self.file_tracers.append(plugin)
+
+    def add_configurer(self, plugin):
+        self.configurers.append(plugin)
+
+
+@unittest.skipIf(coverage is None, "coverage not available")
+class TestImporterCoverage(unittest.TestCase):
+    def setUp(self):
+        self.raw_temp_dir = tempfile.TemporaryDirectory(
+            prefix="oxidized_importer-test-"
+        )
+        self.td = pathlib.Path(self.raw_temp_dir.name)
+        self.old_meta_path = list(sys.meta_path)
+
+        with (self.td / "my_module.py").open("wb") as fh:
+            fh.write(b"def one():\n    return 1\n\ndef two():\n    return 2\n")
+
+        (self.td / "my_package").mkdir()
+        with (self.td / "my_package" / "__init__.py").open("wb") as fh:
+            fh.write(b"value = 42\n")
+
+    def tearDown(self):
+        sys.meta_path[:] = self.old_meta_path
+        for name in ("my_module", "my_package"):
+            sys.modules.pop(name, None)
+        self.raw_temp_dir.cleanup()
+        del self.raw_temp_dir
+        del self.td
+
+    def _finder_from_td(self):
+        collector = OxidizedResourceCollector(allowed_locations=["in-memory"])
+        for r in find_resources_in_path(self.td):
+            collector.add_in_memory(r)
+
+        f = OxidizedFinder()
+        f.add_resources(collector.oxidize()[0])
+
+        return f
+
+    def _plugin(self):
+        reg = FakeRegistry()
+        coverage_init(reg, {})
+
+        self.assertEqual(len(reg.file_tracers), 1)
+        self.assertEqual(reg.configurers, reg.file_tracers)
+
+        return reg.file_tracers[0]
+
+    def test_file_tracer(self):
+        f = self._finder_from_td()
+        sys.meta_path.insert(0, f)
+
+        plugin = self._plugin()
+
+        tracer = plugin.file_tracer(os.path.join(os.getcwd(), "my_module"))
+        self.assertIsNotNone(tracer)
+        self.assertFalse(tracer.has_dynamic_source_filename())
+        self.assertEqual(
+            tracer.source_filename(),
+            os.path.join(f.path_hook_base_str, "my_module.py"),
+        )
+
+        tracer = plugin.file_tracer(os.path.join(os.getcwd(), "my_package"))
+        self.assertEqual(
+            tracer.source_filename(),
+            os.path.join(f.path_hook_base_str, "my_package", "__init__.py"),
+        )
+
+        self.assertIsNone(plugin.file_tracer(os.path.join(os.getcwd(), "missing")))
+        self.assertIsNone(plugin.file_tracer(__file__))
+
+    def test_file_tracer_no_source(self):
+        f = OxidizedFinder()
+
+        r = OxidizedResource()
+        r.name = "my_module"
+        r.is_module = True
+        r.in_memory_bytecode = marshal.dumps(compile("value = 42", "my_module", "exec"))
+        f.add_resource(r)
+
+        sys.meta_path.insert(0, f)
+
+        plugin = self._plugin()
+        self.assertIsNone(plugin.file_tracer(os.path.join(os.getcwd(), "my_module")))
+
+    def test_file_reporter(self):
+        f = self._finder_from_td()
+        sys.meta_path.insert(0, f)
+
+        plugin = self._plugin()
+
+        self.assertEqual(plugin.file_reporter(__file__), "python")
+
+        reporter = plugin.file_reporter(
+            os.path.join(f.path_hook_base_str, "my_package", "__init__.py")
+        )
+        self.assertEqual(reporter.source(), "value = 42\n")
+
+    def test_measure(self):
+        f = self._finder_from_td()
+        sys.meta_path.insert(0, f)
+
+        cov = coverage.Coverage(data_file=None, config_file=False)
+        cov.set_option("run:plugins", ["oxidized_importer"])
+        cov.start()
+        try:
+            m = importlib.import_module("my_module")
+            m.one()
+        finally:
+            cov.stop()
+
+        filename = os.path.join(f.path_hook_base_str, "my_module.py")
+        self.assertIn(filename, cov.get_data().measured_files())
+
+        _, statements, _, missing, _ = cov.analysis2(filename)
+        self.assertEqual(statements, [1, 2, 4, 5])
+        self.assertEqual(missing, [5])
+
+        out = io.StringIO()
+        cov.report(morfs=[filename], file=out, show_missing=True)
+        self.assertIn("my_module.py", out.getvalue())
+
+
+if __name__ == "__main__":
+    unittest.main()
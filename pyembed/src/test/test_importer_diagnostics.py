@@ -0,0 +1,118 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+import contextlib
+import io
+import os
+import unittest
+
+from oxidized_importer import OxidizedFinder, OxidizedResource
+
+
+class TestImporterDiagnostics(unittest.TestCase):
+    def _find_spec(self, f, name):
+        stderr = io.StringIO()
+
+        with contextlib.redirect_stderr(stderr):
+            spec = f.find_spec(name, None)
+
+        return spec, stderr.getvalue()
+
+    def test_disabled(self):
+        f = OxidizedFinder()
+        self.assertFalse(f.diagnostics)
+
+        spec, output = self._find_spec(f, "missing")
+        self.assertIsNone(spec)
+        self.assertEqual(output, "")
+
+    def test_environment_variable(self):
+        os.environ["OXIDIZED_IMPORTER_DIAGNOSTICS"] = "1"
+        try:
+            self.assertTrue(OxidizedFinder().diagnostics)
+        finally:
+            del os.environ["OXIDIZED_IMPORTER_DIAGNOSTICS"]
+
+        os.environ["OXIDIZED_IMPORTER_DIAGNOSTICS"] = "0"
+        try:
+            self.assertFalse(OxidizedFinder().diagnostics)
+        finally:
+            del os.environ["OXIDIZED_IMPORTER_DIAGNOSTICS"]
+
+    def test_not_indexed(self):
+        f = OxidizedFinder()
+        f.diagnostics = True
+
+        spec, output = self._find_spec(f, "missing")
+        self.assertIsNone(spec)
+        self.assertEqual(
+            output,
+            "oxidized_importer: find_spec module=missing location=resources "
+            "result=not_found reason=not_indexed\n",
+        )
+
+    def test_not_a_module(self):
+        f = OxidizedFinder()
+        f.diagnostics = True
+
+        resource = OxidizedResource()
+        resource.name = "data"
+        resource.in_memory_package_resources = {"file.txt": b"content"}
+        f.add_resource(resource)
+
+        spec, output = self._find_spec(f, "data")
+        self.assertIsNone(spec)
+        self.assertIn("result=not_found reason=not_a_module", output)
+
+    def test_no_bytecode_for_optimization_level(self):
+        f = OxidizedFinder()
+        f.diagnostics = True
+
+        resource = OxidizedResource()
+        resource.name = "optimized"
+        resource.is_module = True
+        resource.in_memory_bytecode_opt2 = b"bytecode"
+        f.add_resource(resource)
+
+        spec, output = self._find_spec(f, "optimized")
+        self.assertIsNone(spec)
+        self.assertIn("reason=no_bytecode_for_optimization_level", output)
+
+    def test_found(self):
+        f = OxidizedFinder()
+        f.diagnostics = True
+
+        resource = OxidizedResource()
+        resource.name = "found"
+        resource.is_module = True
+        resource.in_memory_source = b"VALUE = 42\n"
+        f.add_resource(resource)
+
+        spec, output = self._find_spec(f, "found")
+        self.assertIsNotNone(spec)
+        self.assertEqual(
+            output,
+            "oxidized_importer: find_spec module=found location=memory "
+            "flavor=module result=found\n",
+        )
+
+    def test_filesystem_first(self):
+        f = OxidizedFinder(filesystem_first_packages=["missing_on_filesystem"])
+        f.diagnostics = True
+
+        spec, output = self._find_spec(f, "missing_on_filesystem")
+        self.assertIsNone(spec)
+        self.assertEqual(
+            output.splitlines(),
+            [
+                "oxidized_importer: find_spec module=missing_on_filesystem "
+                "location=filesystem result=not_found",
+                "oxidized_importer: find_spec module=missing_on_filesystem "
+                "location=resources result=not_found reason=not_indexed",
+            ],
+        )
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -2,6 +2,7 @@
 # License, v. 2.0. If a copy of the MPL was not distributed with this
 # file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+import hashlib
 import os
 import pathlib
 import sys
@@ -9,6 +10,7 @@ import tempfile
 import unittest
 
 from oxidized_importer import (
+    OxidizedResource,
     OxidizedResourceCollector,
     OxidizedFinder,
     find_resources_in_path,
@@ -43,6 +45,20 @@ class TestImporterConstruction(unittest.TestCase):
 
         return finder.serialize_indexed_resources()
 
+    def write_module_resources(self, filename: str, name: str, source: bytes):
+        r = OxidizedResource()
+        r.name = name
+        r.is_module = True
+        r.in_memory_source = source
+
+        finder = OxidizedFinder()
+        finder.add_resource(r)
+
+        path = self.td / filename
+        path.write_bytes(finder.serialize_indexed_resources())
+
+        return path
+
     def test_index_interpreter_builtins(self):
         f = OxidizedFinder()
         f.index_interpreter_builtins()
@@ -81,6 +97,42 @@ class TestImporterConstruction(unittest.TestCase):
         f = OxidizedFinder()
         f.index_file_memory_mapped(path)
 
+    def test_install_resources_file(self):
+        f = OxidizedFinder()
+
+        path = self.write_module_resources("v1", "plugin_module", b"VALUE = 1")
+        self.assertEqual(f.install_resources_file(path), ["plugin_module"])
+        self.assertEqual(f.get_source("plugin_module"), "VALUE = 1")
+
+        # Modules that haven't been imported yet are replaced.
+        path = self.write_module_resources("v2", "plugin_module", b"VALUE = 2")
+        f.install_resources_file(path)
+        self.assertEqual(f.get_source("plugin_module"), "VALUE = 2")
+
+    def test_install_resources_file_sha256(self):
+        f = OxidizedFinder()
+
+        path = self.write_module_resources("plugin", "plugin_module", b"")
+
+        with self.assertRaisesRegex(ValueError, "does not match expected SHA-256"):
+            f.install_resources_file(path, sha256=b"\x00" * 32)
+        self.assertIsNone(f.find_spec("plugin_module", None))
+
+        with self.assertRaisesRegex(ValueError, "sha256 must be 32 bytes"):
+            f.install_resources_file(path, sha256=b"\x00")
+
+        f.install_resources_file(path, sha256=hashlib.sha256(path.read_bytes()).digest())
+        self.assertIsNotNone(f.find_spec("plugin_module", None))
+
+    def test_install_resources_file_imported(self):
+        f = OxidizedFinder()
+
+        path = self.write_module_resources("plugin", "unittest", b"")
+
+        with self.assertRaisesRegex(ValueError, "replaces already imported modules: unittest"):
+            f.install_resources_file(path)
+        self.assertIsNone(f.find_spec("unittest", None))
+
 
 if __name__ == "__main__":
     unittest.main()
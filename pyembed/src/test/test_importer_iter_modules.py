@@ -166,6 +166,72 @@ class TestImporterIterModules(unittest.TestCase):
                 prefix = on.__name__ + "."
                 assert_iter_modules(prefix, prefixed, on.__path__, prefix)
 
+    def test_iter_modules_package_path_without_path_hook(self):
+        self._make_package("plugins")
+        (self.td / "plugins" / "one.py").touch()
+        self._make_package("plugins.two")
+
+        f = self._finder_from_td()
+
+        sys.path = []
+        sys.meta_path = [f]
+        with patch.dict(sys.modules), patch.dict(
+            sys.path_importer_cache, clear=True
+        ), patch.object(sys, "path_hooks", []):
+            import plugins
+
+            res = list(pkgutil.iter_modules(plugins.__path__))
+            self.assertCountEqual(
+                [(mi.name, mi.ispkg) for mi in res], [("one", False), ("two", True)]
+            )
+            for mi in res:
+                self.assertEqual(
+                    mi.module_finder.__class__.__name__, "OxidizedPathEntryFinder"
+                )
+                self.assertEqual(mi.module_finder._package, "plugins")
+
+    def test_walk_packages(self):
+        self._make_package("app.commands.nested")
+        (self.td / "app" / "commands" / "build.py").touch()
+        (self.td / "app" / "commands" / "nested" / "deep.py").touch()
+        (self.td / "other.py").touch()
+
+        f = self._finder_from_td()
+
+        sys.path = []
+        sys.meta_path = [f]
+        with patch.dict(sys.modules), patch.dict(
+            sys.path_importer_cache, clear=True
+        ), patch.object(sys, "path_hooks", []):
+            import app.commands
+
+            res = list(
+                pkgutil.walk_packages(
+                    app.commands.__path__, app.commands.__name__ + "."
+                )
+            )
+            self.assertCountEqual(
+                [(mi.name, mi.ispkg) for mi in res],
+                [
+                    ("app.commands.build", False),
+                    ("app.commands.nested", True),
+                    ("app.commands.nested.deep", False),
+                ],
+            )
+
+            res = list(pkgutil.walk_packages())
+            self.assertCountEqual(
+                [(mi.name, mi.ispkg) for mi in res],
+                [
+                    ("app", True),
+                    ("app.commands", True),
+                    ("app.commands.build", False),
+                    ("app.commands.nested", True),
+                    ("app.commands.nested.deep", False),
+                    ("other", False),
+                ],
+            )
+
 
 if __name__ == "__main__":
     unittest.main()
@@ -291,14 +291,161 @@ class TestImporterMetadata(unittest.TestCase):
         self.assertIsInstance(requires, list)
         self.assertEqual(requires, ["foo"])
 
+    def test_find_distributions_name_normalization(self):
+        metadata_path = self.td / "zope.interface-1.0.dist-info" / "METADATA"
+        metadata_path.parent.mkdir()
+        metadata_path.write_text("Name: zope.interface\nVersion: 1.0\n")
+
+        (self.td / "zope.interface-1.0.dist-info").rename(
+            self.td / "zope_interface-1.0.dist-info"
+        )
+
+        f = self._finder_from_td()
+
+        for name in ("zope.interface", "Zope-Interface", "zope__interface"):
+            dists = list(
+                f.find_distributions(
+                    importlib.metadata.DistributionFinder.Context(name=name)
+                )
+            )
+            self.assertEqual(len(dists), 1, name)
+
     def test_distribution_locate_file(self):
         self._write_metadata()
         f = self._finder_from_td()
 
         dist = list(f.find_distributions())[0]
 
-        with self.assertRaises(AttributeError):
-            dist.locate_file("METADATA")
+        path = dist.locate_file("my_package/__init__.py")
+        self.assertIsInstance(path, pathlib.Path)
+        self.assertEqual(
+            path, pathlib.Path(f.path_hook_base_str) / "my_package" / "__init__.py"
+        )
+
+    def test_files_missing(self):
+        self._write_metadata()
+        f = self._finder_from_td()
+
+        dists = list(f.find_distributions())
+
+        self.assertIsNone(dists[0].files)
+
+    def test_files_record(self):
+        self._write_metadata()
+
+        record_path = self.td / "my_package-1.0.dist-info" / "RECORD"
+        with record_path.open("w", encoding="utf-8") as fh:
+            fh.write("my_package/__init__.py,sha256=abc,12\n")
+            fh.write('"my_package/data, with comma.txt",,\n')
+            fh.write("my_package-1.0.dist-info/RECORD,,\n")
+
+        f = self._finder_from_td()
+        dist = list(f.find_distributions())[0]
+
+        files = dist.files
+        self.assertIsInstance(files, list)
+        self.assertEqual(
+            [str(p) for p in files],
+            [
+                "my_package/__init__.py",
+                "my_package/data, with comma.txt",
+                "my_package-1.0.dist-info/RECORD",
+            ],
+        )
+
+        p = files[0]
+        self.assertIsInstance(p, importlib.metadata.PackagePath)
+        self.assertIsInstance(p.hash, importlib.metadata.FileHash)
+        self.assertEqual(p.hash.mode, "sha256")
+        self.assertEqual(p.hash.value, "abc")
+        self.assertEqual(p.size, 12)
+        self.assertIs(p.dist, dist)
+        self.assertEqual(p.locate(), dist.locate_file("my_package/__init__.py"))
+
+        self.assertIsNone(files[1].hash)
+        self.assertIsNone(files[1].size)
+
+    def test_files_egg_info(self):
+        pkginfo_path = self.td / "my_package-1.0.egg-info" / "PKG-INFO"
+        pkginfo_path.parent.mkdir()
+
+        with pkginfo_path.open("w", encoding="utf-8") as fh:
+            fh.write("Name: my_package\n")
+            fh.write("Version: 1.0\n")
+
+        sources_path = self.td / "my_package-1.0.egg-info" / "SOURCES.txt"
+        with sources_path.open("w", encoding="utf-8") as fh:
+            fh.write("setup.py\nmy_package/__init__.py\n")
+
+        f = self._finder_from_td()
+        dist = list(f.find_distributions())[0]
+
+        self.assertEqual(
+            [str(p) for p in dist.files], ["setup.py", "my_package/__init__.py"]
+        )
+        self.assertIsNone(dist.files[0].hash)
+
+    @unittest.skipIf(sys.version_info < (3, 10), "requires Python 3.10+")
+    def test_entry_points_select(self):
+        self._write_metadata()
+
+        entry_points_path = self.td / "my_package-1.0.dist-info" / "entry_points.txt"
+        with entry_points_path.open("w", encoding="utf-8") as fh:
+            fh.write("[console_scripts]\n")
+            fh.write("script = my_package:main\n")
+            fh.write("[my_plugins]\n")
+            fh.write("plugin = my_package.plugin:Plugin\n")
+
+        f = self._finder_from_td()
+
+        sys.meta_path = [f]
+        sys.path = []
+
+        eps = importlib.metadata.entry_points(group="my_plugins")
+        self.assertIsInstance(eps, importlib.metadata.EntryPoints)
+        self.assertEqual(eps.names, {"plugin"})
+
+        ep = eps["plugin"]
+        self.assertEqual(ep.value, "my_package.plugin:Plugin")
+        self.assertEqual(ep.dist.name, "my_package")
+
+        dist = importlib.metadata.distribution("my_package")
+        self.assertIsInstance(dist.entry_points, importlib.metadata.EntryPoints)
+        self.assertEqual(dist.entry_points.groups, {"console_scripts", "my_plugins"})
+        self.assertEqual(
+            dist.entry_points.select(group="console_scripts").names, {"script"}
+        )
+
+    @unittest.skipIf(sys.version_info < (3, 10), "requires Python 3.10+")
+    def test_packages_distributions(self):
+        self._write_metadata()
+
+        record_path = self.td / "my_package-1.0.dist-info" / "RECORD"
+        with record_path.open("w", encoding="utf-8") as fh:
+            fh.write("my_package/__init__.py,,\n")
+            fh.write("my_module.py,,\n")
+            fh.write("my_package-1.0.dist-info/METADATA,,\n")
+
+        f = self._finder_from_td()
+
+        sys.meta_path = [f]
+        sys.path = []
+
+        self.assertEqual(
+            importlib.metadata.packages_distributions(),
+            {"my_package": ["my_package"], "my_module": ["my_package"]},
+        )
+
+        top_level_path = self.td / "my_package-1.0.dist-info" / "top_level.txt"
+        top_level_path.write_text("declared\n")
+
+        sys.meta_path = self.old_finders
+        f = self._finder_from_td()
+        sys.meta_path = [f]
+
+        self.assertEqual(
+            importlib.metadata.packages_distributions(), {"declared": ["my_package"]}
+        )
 
     def test_distribution_from_name(self):
         self._write_metadata()
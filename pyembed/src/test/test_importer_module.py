@@ -11,6 +11,7 @@ SYMBOL_ATTRIBUTES = {
         "entry_points",
         "files",
         "from_name",
+        "locate_file",
         "metadata",
         "name",
         "read_text",
@@ -20,10 +21,14 @@ SYMBOL_ATTRIBUTES = {
     "OxidizedFinder": {
         "add_resource",
         "add_resources",
+        "add_wheel",
         "create_module",
+        "diagnostics",
         "exec_module",
         "find_distributions",
         "find_module",
+        "filesystem_first_packages",
+        "find_resource",
         "find_spec",
         "get_code",
         "get_data",
@@ -36,8 +41,11 @@ SYMBOL_ATTRIBUTES = {
         "index_interpreter_builtin_extension_modules",
         "index_interpreter_frozen_modules",
         "indexed_resources",
+        "install_resources_file",
         "invalidate_caches",
         "iter_modules",
+        "lazy_import_packages",
+        "materialized_files_dir",
         "multiprocessing_set_start_method",
         "origin",
         "path_hook",
@@ -69,10 +77,14 @@ SYMBOL_ATTRIBUTES = {
         "in_memory_bytecode_opt1",
         "in_memory_bytecode_opt2",
         "in_memory_bytecode",
+        "in_memory_bytecode_compression",
         "in_memory_distribution_resources",
         "in_memory_extension_module_shared_library",
+        "in_memory_localized_package_resources",
         "in_memory_package_resources",
+        "in_memory_package_resources_compression",
         "in_memory_shared_library",
+        "in_memory_size",
         "in_memory_source",
         "is_builtin_extension_module",
         "is_extension_module",
@@ -81,7 +93,9 @@ SYMBOL_ATTRIBUTES = {
         "is_namespace_package",
         "is_package",
         "is_shared_library",
+        "locations",
         "name",
+        "origin_collection",
         "relative_path_distribution_resources",
         "relative_path_extension_module_shared_library",
         "relative_path_module_bytecode_opt1",
@@ -89,7 +103,9 @@ SYMBOL_ATTRIBUTES = {
         "relative_path_module_bytecode",
         "relative_path_module_source",
         "relative_path_package_resources",
+        "sha256",
         "shared_library_dependency_names",
+        "source_map_path",
     },
     "OxidizedResourceCollector": {
         "add_filesystem_relative",
@@ -99,10 +115,23 @@ SYMBOL_ATTRIBUTES = {
     },
     "OxidizedResourceReader": {
         "contents",
+        "files",
         "is_resource",
         "open_resource",
         "resource_path",
     },
+    "OxidizedTraversable": {
+        "__rtruediv__",
+        "__truediv__",
+        "is_dir",
+        "is_file",
+        "iterdir",
+        "joinpath",
+        "name",
+        "open",
+        "read_bytes",
+        "read_text",
+    },
     "OxidizedZipFinder": {
         "create_module",
         "exec_module",
@@ -124,13 +153,27 @@ SYMBOL_ATTRIBUTES = {
     },
     "PythonModuleSource": {"is_package", "module", "source"},
     "PythonPackageDistributionResource": {"data", "name", "package", "version"},
-    "PythonPackageResource": {"data", "name", "package"},
+    "PythonPackageResource": {"data", "locale", "name", "package"},
+    "allocator": {"__all__", "stats"},
+    "build_info": {
+        "features",
+        "python_flavor",
+        "python_version",
+        "resources_location",
+        "resources_location_fallback",
+        "target_triple",
+    },
+    "coverage_init": set(),
     "decode_source": set(),
     "find_resources_in_path": set(),
     "pkg_resources_find_distributions": set(),
     "register_pkg_resources": set(),
+    "select_locale": set(),
 }
 
+# Symbols only present when optional features are enabled.
+OPTIONAL_SYMBOLS = {"StaticServer", "serve_static"}
+
 COMMON_CLASS_DUNDER_ATTRIBUTES = {
     "__class__",
     "__delattr__",
@@ -158,6 +201,14 @@ COMMON_CLASS_DUNDER_ATTRIBUTES = {
     "__new__",
 }
 
+COMMON_MODULE_DUNDER_ATTRIBUTES = {
+    "__doc__",
+    "__loader__",
+    "__name__",
+    "__package__",
+    "__spec__",
+}
+
 COMMON_FUNCTION_DUNDER_ATTRIBUTES = {
     "__call__",
     "__class__",
@@ -196,6 +247,7 @@ class TestImporterModule(unittest.TestCase):
         import oxidized_importer as importer
 
         attrs = {a for a in dir(importer) if not a.startswith("__")}
+        attrs -= OPTIONAL_SYMBOLS
         self.assertEqual(
             attrs, set(SYMBOL_ATTRIBUTES.keys()), "module symbols match expected"
         )
@@ -206,7 +258,9 @@ class TestImporterModule(unittest.TestCase):
         for (symbol, expected) in sorted(SYMBOL_ATTRIBUTES.items()):
             o = getattr(importer, symbol)
 
-            if symbol.lower() == symbol:
+            if isinstance(o, type(importer)):
+                extra = COMMON_MODULE_DUNDER_ATTRIBUTES
+            elif symbol.lower() == symbol:
                 extra = COMMON_FUNCTION_DUNDER_ATTRIBUTES
             else:
                 extra = COMMON_CLASS_DUNDER_ATTRIBUTES
@@ -216,6 +270,22 @@ class TestImporterModule(unittest.TestCase):
             attrs = set(dir(o))
             self.assertEqual(attrs, expected, "attributes on %s" % symbol)
 
+    def test_build_info(self):
+        import oxidized_importer.build_info as build_info
+
+        from oxidized_importer import build_info as build_info_attr
+
+        self.assertIs(build_info, build_info_attr)
+        self.assertIsInstance(build_info.features, tuple)
+
+    def test_allocator(self):
+        import oxidized_importer.allocator as allocator
+
+        from oxidized_importer import allocator as allocator_attr
+
+        self.assertIs(allocator, allocator_attr)
+        self.assertIsNone(allocator.stats())
+
 
 if __name__ == "__main__":
     unittest.main()
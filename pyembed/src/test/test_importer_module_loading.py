@@ -5,15 +5,20 @@
 import importlib.machinery
 import importlib.util
 import importlib
+import inspect
+import linecache
 import marshal
 import os
 import pathlib
 import sys
 import tempfile
+import threading
+import traceback
 import unittest
 
 from oxidized_importer import (
     OxidizedFinder,
+    OxidizedResource,
     OxidizedResourceCollector,
     find_resources_in_path,
 )
@@ -45,12 +50,12 @@ class TestImporterModuleLoading(unittest.TestCase):
 
         return package_path
 
-    def _finder_from_td(self):
+    def _finder_from_td(self, **kwargs):
         collector = OxidizedResourceCollector(allowed_locations=["in-memory"])
         for r in find_resources_in_path(self.td):
             collector.add_in_memory(r)
 
-        f = OxidizedFinder()
+        f = OxidizedFinder(**kwargs)
         f.add_resources(collector.oxidize()[0])
 
         return f
@@ -148,6 +153,147 @@ class TestImporterModuleLoading(unittest.TestCase):
         with self.assertRaises(ImportError):
             f.get_filename("my_package")
 
+    def test_relative_path_bytecode(self):
+        code = compile("value = 42", "my_module", "exec")
+
+        with (self.td / "my_module.pyc").open("wb") as fh:
+            fh.write(importlib.util.MAGIC_NUMBER + b"\0" * 12)
+            fh.write(marshal.dumps(code))
+
+        r = OxidizedResource()
+        r.name = "my_module"
+        r.is_module = True
+        r.relative_path_module_bytecode = "my_module.pyc"
+
+        f = OxidizedFinder(relative_path_origin=self.td)
+        f.add_resource(r)
+
+        self.assertEqual(f.get_code("my_module"), code)
+
+        sys.meta_path.insert(0, f)
+
+        try:
+            m = importlib.import_module("my_module")
+            self.assertEqual(m.value, 42)
+        finally:
+            sys.modules.pop("my_module", None)
+
+    def test_concurrent_imports(self):
+        f = OxidizedFinder(relative_path_origin=self.td)
+
+        names = ["concurrent_%d" % i for i in range(32)]
+
+        for i, name in enumerate(names):
+            code = compile("value = %d" % i, name, "exec")
+
+            with (self.td / ("%s.pyc" % name)).open("wb") as fh:
+                fh.write(importlib.util.MAGIC_NUMBER + b"\0" * 12)
+                fh.write(marshal.dumps(code))
+
+            r = OxidizedResource()
+            r.name = name
+            r.is_module = True
+            r.relative_path_module_bytecode = "%s.pyc" % name
+            f.add_resource(r)
+
+        sys.meta_path.insert(0, f)
+
+        barrier = threading.Barrier(8)
+        errors = []
+
+        def import_all(offset):
+            barrier.wait()
+
+            try:
+                for i in range(len(names)):
+                    i = (i + offset) % len(names)
+                    m = importlib.import_module(names[i])
+                    self.assertEqual(m.value, i)
+            except Exception as e:
+                errors.append(e)
+
+        threads = [
+            threading.Thread(target=import_all, args=(i * 4,)) for i in range(8)
+        ]
+
+        try:
+            for t in threads:
+                t.start()
+            for t in threads:
+                t.join()
+        finally:
+            for name in names:
+                sys.modules.pop(name, None)
+
+        self.assertEqual(errors, [])
+
+    def test_imports_while_adding_resources(self):
+        f = OxidizedFinder(relative_path_origin=self.td)
+
+        # Each thread imports its own modules, so threads don't race to
+        # remove each other's modules from sys.modules.
+        names = [["adding_%d_%d" % (t, i) for i in range(4)] for t in range(4)]
+
+        def make_resource(name):
+            r = OxidizedResource()
+            r.name = name
+            r.is_module = True
+            r.relative_path_module_source = "%s.py" % name
+            return r
+
+        for thread_names in names:
+            for i, name in enumerate(thread_names):
+                with (self.td / ("%s.py" % name)).open("wb") as fh:
+                    fh.write(b"value = %d\n" % i)
+
+                f.add_resource(make_resource(name))
+
+        sys.meta_path.insert(0, f)
+
+        stop = threading.Event()
+        errors = []
+
+        def import_loop(thread_names):
+            try:
+                while not stop.is_set():
+                    for i, name in enumerate(thread_names):
+                        m = importlib.import_module(name)
+                        self.assertEqual(m.value, i)
+                        del sys.modules[name]
+            # Panics in Rust code raise exceptions not derived from Exception.
+            except BaseException as e:
+                errors.append(e)
+
+        threads = [
+            threading.Thread(target=import_loop, args=(thread_names,))
+            for thread_names in names
+        ]
+
+        try:
+            for t in threads:
+                t.start()
+
+            # Adding resources grows the resources map and replaces resources
+            # of modules being imported while other threads run Python code.
+            for i in range(2000):
+                r = OxidizedResource()
+                r.name = "filler_%d" % i
+                r.is_module = True
+                r.in_memory_source = b""
+                f.add_resource(r)
+
+                for name in names[i % len(names)]:
+                    f.add_resource(make_resource(name))
+        finally:
+            stop.set()
+            for t in threads:
+                t.join()
+            for thread_names in names:
+                for name in thread_names:
+                    sys.modules.pop(name, None)
+
+        self.assertEqual(errors, [])
+
     def test_dot_init(self):
         p = self._make_package("dotinit")
 
@@ -182,6 +328,330 @@ class TestImporterModuleLoading(unittest.TestCase):
         self.assertIn("dotinit.bar", sys.modules)
         self.assertNotIn("dotinit.__init__", sys.modules)
 
+    def test_lazy_import(self):
+        p = self._make_package("lazypkg")
+
+        with (p / "__init__.py").open("wb") as fh:
+            fh.write(b"import sys\nsys.lazypkg_executed = True\nvalue = 42\n")
+
+        with (p / "sub.py").open("wb") as fh:
+            fh.write(b"value = 43\n")
+
+        self._make_package("eagerpkg")
+
+        f = self._finder_from_td(lazy_import_packages=["lazypkg"])
+        self.assertEqual(f.lazy_import_packages, ["lazypkg"])
+
+        spec = f.find_spec("lazypkg", None)
+        self.assertIsInstance(spec.loader, importlib.util.LazyLoader)
+        spec = f.find_spec("lazypkg.sub", None)
+        self.assertIsInstance(spec.loader, importlib.util.LazyLoader)
+        spec = f.find_spec("eagerpkg", None)
+        self.assertIsInstance(spec.loader, OxidizedFinder)
+
+        sys.meta_path.insert(0, f)
+
+        try:
+            m = importlib.import_module("lazypkg")
+            self.assertFalse(hasattr(sys, "lazypkg_executed"))
+
+            self.assertEqual(m.value, 42)
+            self.assertTrue(sys.lazypkg_executed)
+            self.assertIsInstance(m.__loader__, OxidizedFinder)
+
+            self.assertEqual(importlib.import_module("lazypkg.sub").value, 43)
+        finally:
+            for name in ("lazypkg", "lazypkg.sub"):
+                sys.modules.pop(name, None)
+
+            if hasattr(sys, "lazypkg_executed"):
+                del sys.lazypkg_executed
+
+
+    def test_filesystem_first_packages(self):
+        memory_root = self.td / "memory"
+        filesystem_root = self.td / "filesystem"
+
+        for root in (memory_root, filesystem_root):
+            for name in ("override", "other"):
+                p = root / name
+                p.mkdir(parents=True)
+
+                with (p / "__init__.py").open("wb") as fh:
+                    fh.write(("location = %r\n" % root.name).encode("utf-8"))
+
+        with (memory_root / "override" / "memory_only.py").open("wb") as fh:
+            fh.write(b"location = 'memory'\n")
+
+        collector = OxidizedResourceCollector(allowed_locations=["in-memory"])
+        for r in find_resources_in_path(memory_root):
+            collector.add_in_memory(r)
+
+        f = OxidizedFinder(filesystem_first_packages=["override"])
+        f.add_resources(collector.oxidize()[0])
+
+        spec = f.find_spec("override", [str(filesystem_root)])
+        self.assertIsInstance(spec.loader, importlib.machinery.SourceFileLoader)
+        self.assertEqual(
+            spec.origin, str(filesystem_root / "override" / "__init__.py")
+        )
+
+        # Modules not found on the filesystem are served from memory.
+        spec = f.find_spec("override.memory_only", [str(filesystem_root / "override")])
+        self.assertIsInstance(spec.loader, OxidizedFinder)
+        spec = f.find_spec("override", [str(self.td / "missing")])
+        self.assertIsInstance(spec.loader, OxidizedFinder)
+
+        # Path entries serviced by our path hook are ignored.
+        spec = f.find_spec("override", [f.path_hook_base_str])
+        self.assertIsInstance(spec.loader, OxidizedFinder)
+
+        spec = f.find_spec("other", [str(filesystem_root)])
+        self.assertIsInstance(spec.loader, OxidizedFinder)
+
+        sys.meta_path.insert(0, f)
+        sys.path.insert(0, str(filesystem_root))
+
+        try:
+            self.assertEqual(importlib.import_module("override").location, "filesystem")
+            self.assertEqual(
+                importlib.import_module("override.memory_only").location, "memory"
+            )
+            self.assertEqual(importlib.import_module("other").location, "memory")
+        finally:
+            sys.path.remove(str(filesystem_root))
+            for name in ("override", "override.memory_only", "other"):
+                sys.modules.pop(name, None)
+
+    def test_namespace_package(self):
+        # `my_namespace` doesn't have an `__init__.py`. One portion is indexed
+        # in memory and another is on the filesystem.
+        memory_root = self.td / "memory"
+        filesystem_root = self.td / "filesystem"
+
+        for root, name in ((memory_root, "in_memory"), (filesystem_root, "on_fs")):
+            p = root / "my_namespace" / name
+            p.mkdir(parents=True)
+
+            with (p / "__init__.py").open("wb") as fh:
+                fh.write(("value = %r\n" % name).encode("utf-8"))
+
+        collector = OxidizedResourceCollector(allowed_locations=["in-memory"])
+        for r in find_resources_in_path(memory_root):
+            collector.add_in_memory(r)
+
+        f = OxidizedFinder()
+        f.add_resources(collector.oxidize()[0])
+
+        resources = {r.name: r for r in f.indexed_resources()}
+        self.assertTrue(resources["my_namespace"].is_namespace_package)
+        self.assertFalse(resources["my_namespace.in_memory"].is_namespace_package)
+
+        in_memory_path = os.path.join(f.path_hook_base_str, "my_namespace")
+        filesystem_path = str(filesystem_root / "my_namespace")
+
+        spec = f.find_spec("my_namespace", [])
+        self.assertIsInstance(spec, importlib.machinery.ModuleSpec)
+        self.assertEqual(spec.name, "my_namespace")
+        self.assertIsNone(spec.loader)
+        self.assertIsNone(spec.origin)
+        self.assertFalse(spec.has_location)
+        self.assertEqual(list(spec.submodule_search_locations), [in_memory_path])
+
+        spec = f.find_spec("my_namespace", [str(filesystem_root)])
+        self.assertEqual(
+            list(spec.submodule_search_locations), [in_memory_path, filesystem_path]
+        )
+
+        # Path entries serviced by our path hook don't contribute portions.
+        spec = f.find_spec("my_namespace", [f.path_hook_base_str])
+        self.assertEqual(list(spec.submodule_search_locations), [in_memory_path])
+
+        # A regular package on the filesystem takes precedence.
+        regular_root = self.td / "regular"
+        (regular_root / "my_namespace").mkdir(parents=True)
+        with (regular_root / "my_namespace" / "__init__.py").open("wb"):
+            pass
+
+        self.assertIsNone(f.find_spec("my_namespace", [str(regular_root)]))
+
+        sys.meta_path.insert(0, f)
+        old_path = list(sys.path)
+        sys.path.insert(0, str(filesystem_root))
+
+        try:
+            m = importlib.import_module("my_namespace")
+            self.assertEqual(type(m.__path__).__name__, "_NamespacePath")
+            self.assertIsNone(getattr(m, "__file__", None))
+            self.assertEqual(list(m.__path__), [in_memory_path, filesystem_path])
+
+            m = importlib.import_module("my_namespace.in_memory")
+            self.assertIsInstance(m.__loader__, OxidizedFinder)
+            self.assertEqual(m.value, "in_memory")
+
+            m = importlib.import_module("my_namespace.on_fs")
+            self.assertIsInstance(m.__loader__, importlib.machinery.SourceFileLoader)
+            self.assertEqual(m.value, "on_fs")
+        finally:
+            sys.path[:] = old_path
+
+            for name in (
+                "my_namespace",
+                "my_namespace.in_memory",
+                "my_namespace.on_fs",
+            ):
+                sys.modules.pop(name, None)
+
+    def test_materialized_source_module(self):
+        with (self.td / "my_module.py").open("wb") as fh:
+            fh.write(b"value = __file__\n")
+
+        cache = self.td / "cache"
+        f = self._finder_from_td(materialized_files_dir=cache)
+
+        spec = f.find_spec("my_module", None)
+        self.assertEqual(spec.origin, str(cache / "my_module.py"))
+        self.assertTrue(spec.has_location)
+        self.assertEqual(f.get_filename("my_module"), spec.origin)
+        self.assertEqual((cache / "my_module.py").read_bytes(), b"value = __file__\n")
+
+        m = importlib.util.module_from_spec(spec)
+        f.exec_module(m)
+        self.assertEqual(m.__file__, spec.origin)
+        self.assertEqual(m.value, spec.origin)
+
+    def test_materialized_package(self):
+        p = self._make_package("my_package")
+        with (p / "data.txt").open("wb") as fh:
+            fh.write(b"resource")
+
+        cache = self.td / "cache"
+        f = self._finder_from_td(materialized_files_dir=cache)
+
+        spec = f.find_spec("my_package", None)
+        self.assertEqual(spec.origin, str(cache / "my_package" / "__init__.py"))
+        self.assertEqual(
+            spec.submodule_search_locations,
+            [
+                str(cache / "my_package"),
+                os.path.join(f.path_hook_base_str, "my_package"),
+            ],
+        )
+        self.assertEqual((cache / "my_package" / "data.txt").read_bytes(), b"resource")
+
+    def test_materialized_bytecode_module(self):
+        f = OxidizedFinder(materialized_files_dir=self.td / "cache")
+
+        r = OxidizedResource()
+        r.name = "my_module"
+        r.is_module = True
+        r.in_memory_bytecode = marshal.dumps(compile("value = 42", "my_module", "exec"))
+        f.add_resource(r)
+
+        spec = f.find_spec("my_module", None)
+        self.assertEqual(spec.origin, str(self.td / "cache" / "my_module.pyc"))
+
+        with open(spec.origin, "rb") as fh:
+            self.assertEqual(fh.read(4), importlib.util.MAGIC_NUMBER)
+
+        m = importlib.util.module_from_spec(spec)
+        f.exec_module(m)
+        self.assertEqual(m.value, 42)
+
+    def test_not_materialized_by_default(self):
+        with (self.td / "my_module.py").open("wb"):
+            pass
+
+        f = self._finder_from_td()
+
+        spec = f.find_spec("my_module", None)
+        self.assertIsNone(spec.origin)
+        self.assertFalse(spec.has_location)
+
+        with self.assertRaises(ImportError):
+            f.get_filename("my_module")
+
+    def test_linecache_source(self):
+        with (self.td / "my_module.py").open("wb") as fh:
+            fh.write(b"def fail():\n    raise ValueError('bad')\n")
+
+        f = self._finder_from_td()
+        sys.meta_path.insert(0, f)
+
+        try:
+            m = importlib.import_module("my_module")
+
+            # Lines are available without module globals.
+            self.assertIn("my_module", linecache.cache)
+            self.assertEqual(
+                linecache.getline("my_module", 2), "    raise ValueError('bad')\n"
+            )
+            self.assertEqual(
+                inspect.getsource(m.fail),
+                "def fail():\n    raise ValueError('bad')\n",
+            )
+
+            try:
+                m.fail()
+            except ValueError:
+                formatted = traceback.format_exc()
+            self.assertIn('File "my_module", line 2, in fail', formatted)
+            self.assertIn("raise ValueError('bad')", formatted)
+        finally:
+            sys.modules.pop("my_module", None)
+            linecache.cache.pop("my_module", None)
+
+    def test_linecache_no_source(self):
+        f = OxidizedFinder()
+
+        r = OxidizedResource()
+        r.name = "my_module"
+        r.is_module = True
+        r.in_memory_bytecode = marshal.dumps(compile("value = 42", "my_module", "exec"))
+        f.add_resource(r)
+
+        sys.meta_path.insert(0, f)
+
+        try:
+            importlib.import_module("my_module")
+            self.assertNotIn("my_module", linecache.cache)
+        finally:
+            sys.modules.pop("my_module", None)
+
+
+    @unittest.skipUnless(sys.platform == "linux", "memfd loading is Linux only")
+    def test_in_memory_extension_module(self):
+        # Find an extension module shared library that isn't loaded yet.
+        for name in ("_testmultiphase", "_ctypes_test", "_xxtestfuzz"):
+            if name in sys.modules:
+                continue
+
+            spec = importlib.machinery.PathFinder.find_spec(name)
+            if spec and isinstance(spec.loader, importlib.machinery.ExtensionFileLoader):
+                break
+        else:
+            self.skipTest("no extension module shared library available")
+
+        f = OxidizedFinder()
+
+        r = OxidizedResource()
+        r.name = name
+        r.is_extension_module = True
+        with open(spec.origin, "rb") as fh:
+            r.in_memory_extension_module_shared_library = fh.read()
+        f.add_resource(r)
+
+        sys.meta_path.insert(0, f)
+
+        try:
+            m = importlib.import_module(name)
+            self.assertIs(m.__loader__, f)
+            self.assertFalse(hasattr(m, "__file__"))
+
+            with open("/proc/self/maps", "r") as fh:
+                self.assertIn("/memfd:%s " % name, fh.read())
+        finally:
+            sys.modules.pop(name, None)
 
 if __name__ == "__main__":
     unittest.main()
@@ -2,9 +2,11 @@
 # License, v. 2.0. If a copy of the MPL was not distributed with this
 # file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+import importlib.resources
 import io
 import os
 import pathlib
+import sys
 import tempfile
 import unittest
 
@@ -12,7 +14,10 @@ from oxidized_importer import (
     OxidizedFinder,
     OxidizedResourceCollector,
     OxidizedResourceReader,
+    OxidizedTraversable,
+    PythonPackageResource,
     find_resources_in_path,
+    select_locale,
 )
 
 
@@ -22,8 +27,13 @@ class TestImporterResourceReading(unittest.TestCase):
             prefix="oxidized_importer-test-"
         )
         self.td = pathlib.Path(self.raw_temp_dir.name)
+        self.old_meta_path = list(sys.meta_path)
 
     def tearDown(self):
+        select_locale(None)
+        sys.meta_path[:] = self.old_meta_path
+        for name in [n for n in sys.modules if n.split(".")[0] == "my_package"]:
+            del sys.modules[name]
         self.raw_temp_dir.cleanup()
         del self.raw_temp_dir
         del self.td
@@ -41,11 +51,17 @@ class TestImporterResourceReading(unittest.TestCase):
 
         return package_path
 
-    def _finder_from_td(self):
+    def _finder_from_td(self, locales=None):
         collector = OxidizedResourceCollector(allowed_locations=["in-memory"])
         for r in find_resources_in_path(self.td):
             collector.add_in_memory(r)
 
+        for locale, path in (locales or {}).items():
+            for r in find_resources_in_path(path):
+                if isinstance(r, PythonPackageResource):
+                    r.locale = locale
+                    collector.add_in_memory(r)
+
         f = OxidizedFinder()
         f.add_resources(collector.oxidize()[0])
 
@@ -125,6 +141,173 @@ class TestImporterResourceReading(unittest.TestCase):
         self.assertEqual(r.open_resource("child0/a.txt").getvalue(), b"a")
         self.assertEqual(r.open_resource("child1/b.txt").getvalue(), b"b")
 
+    def _make_resources(self):
+        p = self._make_package("my_package")
+        sub = self._make_package("my_package.sub")
+
+        (p / "resource.txt").write_bytes(b"my resource")
+        (p / "data" / "nested").mkdir(parents=True)
+        (p / "data" / "a.txt").write_bytes(b"a")
+        (p / "data" / "nested" / "b.bin").write_bytes(b"\x00b")
+        (sub / "sub.txt").write_bytes(b"sub")
+
+    def test_files(self):
+        self._make_resources()
+
+        f = self._finder_from_td()
+        root = f.get_resource_reader("my_package").files()
+
+        self.assertIsInstance(root, OxidizedTraversable)
+        self.assertEqual(root.name, "my_package")
+        self.assertTrue(root.is_dir())
+        self.assertFalse(root.is_file())
+
+        self.assertEqual(
+            sorted(t.name for t in root.iterdir()),
+            ["data", "resource.txt", "sub"],
+        )
+
+        resource = root / "resource.txt"
+        self.assertEqual(resource.name, "resource.txt")
+        self.assertTrue(resource.is_file())
+        self.assertFalse(resource.is_dir())
+        self.assertEqual(resource.read_bytes(), b"my resource")
+        self.assertEqual(resource.read_text(encoding="utf-8"), "my resource")
+        self.assertEqual(resource.open("rb").read(), b"my resource")
+        with resource.open("r", encoding="utf-8") as fh:
+            self.assertEqual(fh.read(), "my resource")
+        with self.assertRaises(ValueError):
+            resource.open("w")
+        with self.assertRaises(NotADirectoryError):
+            list(resource.iterdir())
+
+        data = root.joinpath("data")
+        self.assertTrue(data.is_dir())
+        self.assertEqual(sorted(t.name for t in data.iterdir()), ["a.txt", "nested"])
+        self.assertEqual(root.joinpath("data", "nested", "b.bin").read_bytes(), b"\x00b")
+        self.assertEqual(root.joinpath("data/nested/b.bin"), data / "nested" / "b.bin")
+        with self.assertRaises(IsADirectoryError):
+            data.read_bytes()
+
+        # Sub-packages are exposed as directories.
+        sub = root / "sub"
+        self.assertTrue(sub.is_dir())
+        self.assertEqual([t.name for t in sub.iterdir()], ["sub.txt"])
+        self.assertEqual((sub / "sub.txt").read_bytes(), b"sub")
+        self.assertEqual(
+            f.get_resource_reader("my_package.sub").files() / "sub.txt",
+            sub / "sub.txt",
+        )
+
+        missing = root / "missing"
+        self.assertFalse(missing.is_file())
+        self.assertFalse(missing.is_dir())
+        with self.assertRaises(FileNotFoundError):
+            missing.read_bytes()
+        with self.assertRaises(FileNotFoundError):
+            list(missing.iterdir())
+
+        with self.assertRaises(ValueError):
+            root / ".." / "other"
+
+    @unittest.skipIf(sys.version_info < (3, 10), "requires Python 3.10+")
+    def test_importlib_resources_files(self):
+        self._make_resources()
+
+        f = self._finder_from_td()
+        sys.meta_path.insert(0, f)
+
+        files = importlib.resources.files("my_package")
+        self.assertIsInstance(files, OxidizedTraversable)
+        self.assertEqual(
+            files.joinpath("data", "nested", "b.bin").read_bytes(), b"\x00b"
+        )
+
+        with importlib.resources.as_file(files / "resource.txt") as path:
+            self.assertIsInstance(path, pathlib.Path)
+            self.assertEqual(path.read_bytes(), b"my resource")
+        self.assertFalse(path.exists())
+
+        self.assertEqual(
+            importlib.resources.files("my_package.sub").joinpath("sub.txt").read_text(),
+            "sub",
+        )
+
+    def _make_localized_resources(self, locale, files):
+        root = self.td / "locales" / locale
+        package_path = root / "my_package"
+        package_path.mkdir(parents=True)
+        (package_path / "__init__.py").write_bytes(b"")
+
+        for name, data in files.items():
+            (package_path / name).write_bytes(data)
+
+        return root
+
+    def test_select_locale(self):
+        p = self._make_package("my_package")
+        (p / "messages.txt").write_bytes(b"hello")
+        (p / "other.txt").write_bytes(b"other")
+
+        locales = {
+            "de": self._make_localized_resources(
+                "de", {"messages.txt": b"hallo", "only_de.txt": b"nur"}
+            ),
+            "de_AT": self._make_localized_resources(
+                "de_AT", {"messages.txt": b"servus"}
+            ),
+        }
+        f = self._finder_from_td(locales)
+
+        entry = [r for r in f.indexed_resources() if r.name == "my_package"][0]
+        self.assertEqual(
+            entry.in_memory_localized_package_resources,
+            {
+                "de": {"messages.txt": b"hallo", "only_de.txt": b"nur"},
+                "de_AT": {"messages.txt": b"servus"},
+            },
+        )
+
+        r = f.get_resource_reader("my_package")
+
+        self.assertEqual(r.open_resource("messages.txt").getvalue(), b"hello")
+        with self.assertRaises(FileNotFoundError):
+            r.is_resource("only_de.txt")
+        self.assertEqual(r.contents(), ["messages.txt", "other.txt"])
+
+        select_locale("de_DE.UTF-8")
+        self.assertEqual(r.open_resource("messages.txt").getvalue(), b"hallo")
+        self.assertEqual(r.open_resource("other.txt").getvalue(), b"other")
+        self.assertTrue(r.is_resource("only_de.txt"))
+        self.assertEqual(r.contents(), ["messages.txt", "only_de.txt", "other.txt"])
+        self.assertEqual((r.files() / "messages.txt").read_bytes(), b"hallo")
+
+        # An exact match takes precedence over the language.
+        select_locale("de_AT")
+        self.assertEqual(r.open_resource("messages.txt").getvalue(), b"servus")
+        self.assertEqual(r.open_resource("only_de.txt").getvalue(), b"nur")
+
+        select_locale("fr_FR")
+        self.assertEqual(r.open_resource("messages.txt").getvalue(), b"hello")
+
+        select_locale(None)
+        self.assertEqual(r.open_resource("messages.txt").getvalue(), b"hello")
+        with self.assertRaises(FileNotFoundError):
+            r.is_resource("only_de.txt")
+
+    def test_localized_resource_relative_path(self):
+        p = self._make_package("my_package")
+        (p / "messages.txt").write_bytes(b"hello")
+
+        collector = OxidizedResourceCollector(allowed_locations=["filesystem-relative"])
+        for r in find_resources_in_path(self.td):
+            if isinstance(r, PythonPackageResource):
+                r.locale = "de"
+                with self.assertRaisesRegex(
+                    ValueError, "can only be loaded from memory"
+                ):
+                    collector.add_filesystem_relative("", r)
+
 
 if __name__ == "__main__":
     unittest.main()
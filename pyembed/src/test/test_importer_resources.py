@@ -2,6 +2,7 @@
 # License, v. 2.0. If a copy of the MPL was not distributed with this
 # file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+import hashlib
 import importlib.machinery
 import marshal
 import pathlib
@@ -55,6 +56,13 @@ class TestImporterResources(unittest.TestCase):
         self.assertIsNone(resource.relative_path_extension_module_shared_library)
         self.assertIsNone(resource.relative_path_package_resources)
         self.assertIsNone(resource.relative_path_distribution_resources)
+        self.assertIsNone(resource.in_memory_bytecode_compression)
+        self.assertIsNone(resource.in_memory_package_resources_compression)
+        self.assertIsNone(resource.source_map_path)
+        self.assertEqual(resource.locations, [])
+        self.assertEqual(resource.in_memory_size, 0)
+        self.assertIsNone(resource.sha256)
+        self.assertEqual(resource.origin_collection, "builtin")
 
     def test_resources_frozen(self):
         f = OxidizedFinder()
@@ -588,6 +596,77 @@ class TestImporterResources(unittest.TestCase):
         with self.assertRaises(TypeError):
             resource.relative_path_distribution_resources = {"foo": None}
 
+    def test_resource_in_memory_bytecode_compression(self):
+        resource = OxidizedResource()
+        self.assertIsNone(resource.in_memory_bytecode_compression)
+        self.assertIsNone(resource.in_memory_package_resources_compression)
+
+        resource.in_memory_bytecode_compression = "zstd"
+        self.assertEqual(resource.in_memory_bytecode_compression, "zstd")
+
+        resource.in_memory_package_resources_compression = "zstd-dict"
+        self.assertEqual(resource.in_memory_package_resources_compression, "zstd-dict")
+
+        resource.in_memory_bytecode_compression = None
+        self.assertIsNone(resource.in_memory_bytecode_compression)
+
+        with self.assertRaises(ValueError):
+            resource.in_memory_bytecode_compression = "lzma"
+
+    def test_resource_source_map_path(self):
+        resource = OxidizedResource()
+        self.assertIsNone(resource.source_map_path)
+
+        resource.source_map_path = "src/app/main.py"
+        self.assertEqual(resource.source_map_path, "src/app/main.py")
+
+        resource.source_map_path = None
+        self.assertIsNone(resource.source_map_path)
+
+        with self.assertRaises(TypeError):
+            resource.source_map_path = b"src/app/main.py"
+
+    def test_resource_data_summary(self):
+        resource = OxidizedResource()
+        self.assertIsNone(resource.origin_collection)
+        self.assertEqual(resource.locations, [])
+        self.assertEqual(resource.in_memory_size, 0)
+        self.assertIsNone(resource.sha256)
+
+        resource.in_memory_source = b"source"
+        self.assertEqual(resource.locations, ["in-memory"])
+        self.assertEqual(resource.in_memory_size, 6)
+        self.assertEqual(resource.sha256, hashlib.sha256(b"source").hexdigest())
+
+        resource.in_memory_package_resources = {"b.txt": b"b", "a.txt": b"a"}
+        resource.relative_path_module_bytecode = "lib/foo.pyc"
+        self.assertEqual(resource.locations, ["in-memory", "filesystem-relative"])
+        self.assertEqual(resource.in_memory_size, 8)
+        self.assertEqual(resource.sha256, hashlib.sha256(b"sourceab").hexdigest())
+
+        resource.in_memory_source = None
+        resource.in_memory_package_resources = None
+        self.assertEqual(resource.locations, ["filesystem-relative"])
+
+    def test_find_resource(self):
+        f = OxidizedFinder()
+        self.assertIsNone(f.find_resource("my_module"))
+
+        resource = OxidizedResource()
+        resource.is_module = True
+        resource.name = "my_module"
+        resource.in_memory_source = b"value = 42"
+        f.add_resource(resource)
+
+        found = f.find_resource("my_module")
+        self.assertIsInstance(found, OxidizedResource)
+        self.assertEqual(found.name, "my_module")
+        self.assertEqual(found.in_memory_source, b"value = 42")
+        self.assertEqual(found.origin_collection, "added")
+
+        f.index_bytes(f.serialize_indexed_resources())
+        self.assertEqual(f.find_resource("my_module").origin_collection, "memory")
+
     def test_add_resource_bad_type(self):
         f = OxidizedFinder()
 
@@ -0,0 +1,153 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+import pathlib
+import sys
+import tempfile
+import unittest
+import urllib.error
+import urllib.request
+
+from oxidized_importer import (
+    OxidizedFinder,
+    OxidizedResource,
+    OxidizedResourceCollector,
+    find_resources_in_path,
+)
+
+try:
+    from oxidized_importer import serve_static
+except ImportError:
+    serve_static = None
+
+
+@unittest.skipIf(
+    serve_static is None, "oxidized_importer built without static-server feature"
+)
+class TestImporterStaticServer(unittest.TestCase):
+    def setUp(self):
+        self.raw_temp_dir = tempfile.TemporaryDirectory(
+            prefix="oxidized_importer-test-"
+        )
+        self.td = pathlib.Path(self.raw_temp_dir.name)
+        self.old_meta_path = list(sys.meta_path)
+
+    def tearDown(self):
+        sys.meta_path[:] = self.old_meta_path
+        self.raw_temp_dir.cleanup()
+        del self.raw_temp_dir
+        del self.td
+
+    def _finder(self):
+        web = self.td / "my_app" / "web"
+        (web / "css").mkdir(parents=True)
+        (self.td / "my_app" / "__init__.py").touch()
+        (web / "__init__.py").touch()
+        (web / "index.html").write_bytes(b"<html>index</html>")
+        (web / "css" / "app.css").write_bytes(b"body {}")
+        (web / "css" / "index.html").write_bytes(b"css index")
+
+        collector = OxidizedResourceCollector(allowed_locations=["in-memory"])
+        for r in find_resources_in_path(self.td):
+            collector.add_in_memory(r)
+
+        f = OxidizedFinder()
+        f.add_resources(collector.oxidize()[0])
+
+        return f
+
+    def _get(self, url, method="GET"):
+        req = urllib.request.Request(url, method=method)
+        with urllib.request.urlopen(req, timeout=10) as res:
+            return res.status, res.headers, res.read()
+
+    def _status(self, url, method="GET"):
+        with self.assertRaises(urllib.error.HTTPError) as e:
+            self._get(url, method=method)
+
+        return e.exception.code
+
+    def test_serve(self):
+        with serve_static("my_app.web", finder=self._finder()) as server:
+            self.assertNotEqual(server.port, 0)
+            self.assertEqual(server.url, "http://127.0.0.1:%d/" % server.port)
+
+            status, headers, body = self._get(server.url)
+            self.assertEqual(status, 200)
+            self.assertEqual(headers["Content-Type"], "text/html; charset=utf-8")
+            self.assertEqual(body, b"<html>index</html>")
+
+            status, headers, body = self._get(server.url + "css/app.css?v=1")
+            self.assertEqual(headers["Content-Type"], "text/css; charset=utf-8")
+            self.assertEqual(headers["X-Content-Type-Options"], "nosniff")
+            self.assertEqual(body, b"body {}")
+
+            self.assertEqual(self._get(server.url + "css")[2], b"css index")
+            self.assertEqual(self._get(server.url + "css/")[2], b"css index")
+
+            # Extensionless paths fall back to the root index.html.
+            self.assertEqual(
+                self._get(server.url + "settings/profile")[2], b"<html>index</html>"
+            )
+
+            status, headers, body = self._get(server.url + "css/app.css", "HEAD")
+            self.assertEqual(headers["Content-Length"], "7")
+            self.assertEqual(body, b"")
+
+            self.assertEqual(self._status(server.url + "missing.js"), 404)
+            self.assertEqual(self._status(server.url + "css/../__init__.py"), 404)
+            self.assertEqual(self._status(server.url + "index.html", "POST"), 405)
+
+    def test_directory_prefix(self):
+        with serve_static("my_app.web/css/", finder=self._finder()) as server:
+            self.assertEqual(self._get(server.url)[2], b"css index")
+            self.assertEqual(self._get(server.url + "app.css")[2], b"body {}")
+
+    def test_relative_path_resources(self):
+        (self.td / "static").mkdir()
+        (self.td / "static" / "app.js").write_bytes(b"main()")
+
+        r = OxidizedResource()
+        r.name = "my_app"
+        r.is_package = True
+        r.relative_path_package_resources = {"app.js": "static/app.js"}
+
+        f = OxidizedFinder(relative_path_origin=self.td)
+        f.add_resource(r)
+
+        with serve_static("my_app", finder=f) as server:
+            status, headers, body = self._get(server.url + "app.js")
+            self.assertEqual(
+                headers["Content-Type"], "text/javascript; charset=utf-8"
+            )
+            self.assertEqual(body, b"main()")
+
+    def test_meta_path_finder(self):
+        sys.meta_path.insert(0, self._finder())
+
+        with serve_static("my_app.web") as server:
+            self.assertEqual(self._get(server.url)[2], b"<html>index</html>")
+
+    def test_shutdown(self):
+        server = serve_static("my_app.web", finder=self._finder())
+        url = server.url
+        server.shutdown()
+        server.shutdown()
+
+        with self.assertRaises(urllib.error.URLError):
+            self._get(url)
+
+    def test_no_resources(self):
+        with self.assertRaisesRegex(ValueError, "no resources to serve in missing"):
+            serve_static("missing", finder=self._finder())
+
+        with self.assertRaisesRegex(ValueError, "no OxidizedFinder on sys.meta_path"):
+            sys.meta_path[:] = [
+                f for f in sys.meta_path if not isinstance(f, OxidizedFinder)
+            ]
+            serve_static("my_app.web")
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,80 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+import unittest
+
+try:
+    from oxidized_importer.wasm import WasmModule
+except ImportError:
+    WasmModule = None
+
+
+ADD_WAT = b"""
+(module
+  (memory (export "memory") 1)
+  (func (export "add") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.add)
+  (func (export "scale") (param f64) (result f64 f64)
+    local.get 0
+    local.get 0
+    f64.const 2
+    f64.mul)
+  (func (export "loop")
+    (loop br 0)))
+"""
+
+
+@unittest.skipIf(WasmModule is None, "oxidized_importer built without wasm feature")
+class TestImporterWasm(unittest.TestCase):
+    def test_call(self):
+        m = WasmModule(ADD_WAT)
+
+        self.assertEqual(m.exports, ("add", "scale", "loop"))
+        self.assertEqual(m.call("add", 2, 3), 5)
+        self.assertEqual(m.call("scale", 1.5), (1.5, 3.0))
+
+    def test_call_errors(self):
+        m = WasmModule(ADD_WAT)
+
+        with self.assertRaisesRegex(ValueError, "does not export function missing"):
+            m.call("missing")
+
+        with self.assertRaisesRegex(TypeError, "add\\(\\) takes 2 arguments"):
+            m.call("add", 1)
+
+        with self.assertRaises(TypeError):
+            m.call("add", "1", 2)
+
+    def test_memory(self):
+        m = WasmModule(ADD_WAT)
+
+        self.assertEqual(m.memory_size, 65536)
+        m.write_memory(16, b"hello")
+        self.assertEqual(m.read_memory(16, 5), b"hello")
+
+        with self.assertRaises(RuntimeError):
+            m.read_memory(65534, 4)
+
+    def test_fuel(self):
+        m = WasmModule(ADD_WAT, fuel=10000)
+
+        with self.assertRaisesRegex(RuntimeError, "fuel"):
+            m.call("loop")
+
+        # Fuel is refilled for every call.
+        self.assertEqual(m.call("add", 2, 3), 5)
+
+    def test_max_memory(self):
+        with self.assertRaises(RuntimeError):
+            WasmModule(ADD_WAT, max_memory=1024)
+
+    def test_imports_rejected(self):
+        with self.assertRaisesRegex(ValueError, "imports env.f"):
+            WasmModule(b'(module (import "env" "f" (func)))')
+
+
+if __name__ == "__main__":
+    unittest.main()
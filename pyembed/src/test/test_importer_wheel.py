@@ -0,0 +1,170 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+# email.parser may be unused. However, it is needed by Rust code and some
+# sys.path mucking in tests may prevent it from being imported. So import
+# here to ensure it is cached in sys.modules so Rust can import it.
+import email.parser
+import importlib.metadata
+import importlib.resources
+import pathlib
+import sys
+import tempfile
+import unittest
+import zipfile
+from importlib.machinery import EXTENSION_SUFFIXES
+from unittest.mock import patch
+
+from oxidized_importer import OxidizedFinder
+
+
+WHEEL_FILES = {
+    "my_plugin/__init__.py": "NAME = 'my_plugin'\n",
+    "my_plugin/commands.py": "from . import NAME\n",
+    "my_plugin/data/config.txt": "config",
+    "my_plugin-1.0.dist-info/METADATA": "Name: my-plugin\nVersion: 1.0\n",
+    "my_plugin-1.0.dist-info/WHEEL": "Wheel-Version: 1.0\nRoot-Is-Purelib: true\n",
+    "my_plugin-1.0.dist-info/entry_points.txt": "[acme.plugins]\nmy = my_plugin\n",
+    "my_plugin-1.0.dist-info/RECORD": "",
+}
+
+
+@unittest.skipIf(
+    not hasattr(OxidizedFinder, "add_wheel"),
+    "oxidized_importer built without wheel feature",
+)
+class TestImporterWheel(unittest.TestCase):
+    def setUp(self):
+        self.raw_temp_dir = tempfile.TemporaryDirectory(
+            prefix="oxidized_importer-test-"
+        )
+        self.td = pathlib.Path(self.raw_temp_dir.name)
+        self.old_finders = list(sys.meta_path)
+        self.old_path = list(sys.path)
+
+    def tearDown(self):
+        self.raw_temp_dir.cleanup()
+        del self.raw_temp_dir
+        del self.td
+        sys.meta_path[:] = self.old_finders
+        sys.path[:] = self.old_path
+
+    def _make_wheel(self, files=None):
+        path = self.td / "my_plugin-1.0-py3-none-any.whl"
+
+        with zipfile.ZipFile(path, "w") as zf:
+            for name, content in (files or WHEEL_FILES).items():
+                zf.writestr(name, content)
+
+        return path
+
+    def test_add_wheel_path(self):
+        f = OxidizedFinder()
+        f.add_wheel(self._make_wheel())
+
+        names = {r.name for r in f.indexed_resources()}
+        self.assertIn("my_plugin", names)
+        self.assertIn("my_plugin.commands", names)
+
+        sys.meta_path = [f]
+        sys.path = []
+        with patch.dict(sys.modules):
+            import my_plugin.commands
+
+            self.assertEqual(my_plugin.commands.NAME, "my_plugin")
+            self.assertIsInstance(my_plugin.__loader__, OxidizedFinder)
+            self.assertEqual(
+                importlib.resources.files("my_plugin")
+                .joinpath("data/config.txt")
+                .read_text(),
+                "config",
+            )
+
+    def test_add_wheel_bytes(self):
+        f = OxidizedFinder()
+        f.add_wheel(self._make_wheel().read_bytes())
+
+        sys.meta_path = [f]
+        sys.path = []
+        with patch.dict(sys.modules):
+            import my_plugin
+
+            self.assertEqual(my_plugin.NAME, "my_plugin")
+
+    def test_add_wheel_metadata(self):
+        f = OxidizedFinder()
+        f.add_wheel(self._make_wheel())
+
+        dists = list(f.find_distributions())
+        self.assertEqual(len(dists), 1)
+        self.assertEqual(dists[0].version, "1.0")
+
+        eps = [ep for ep in dists[0].entry_points if ep.group == "acme.plugins"]
+        self.assertEqual(len(eps), 1)
+        self.assertEqual(eps[0].value, "my_plugin")
+
+    def test_add_wheel_after_import_attempt(self):
+        f = OxidizedFinder()
+        sys.meta_path = [f]
+        sys.path = []
+
+        with patch.dict(sys.modules):
+            with self.assertRaises(ImportError):
+                import my_plugin
+
+            f.add_wheel(self._make_wheel())
+
+            import my_plugin
+
+            self.assertEqual(my_plugin.NAME, "my_plugin")
+
+    def test_add_wheel_namespace_parent(self):
+        f = OxidizedFinder()
+        f.add_wheel(
+            self._make_wheel(
+                {
+                    "my_plugin/__init__.py": "NAME = 'original'\n",
+                    "my_plugin-1.0.dist-info/WHEEL": "Wheel-Version: 1.0\n",
+                }
+            )
+        )
+
+        f.add_wheel(
+            self._make_wheel(
+                {
+                    "my_plugin/extra/__init__.py": "",
+                    "my_plugin-1.0.dist-info/WHEEL": "Wheel-Version: 1.0\n",
+                }
+            )
+        )
+
+        sys.meta_path = [f]
+        sys.path = []
+        with patch.dict(sys.modules):
+            import my_plugin.extra
+
+            self.assertEqual(my_plugin.NAME, "original")
+
+    def test_add_wheel_extension_module(self):
+        f = OxidizedFinder()
+
+        with self.assertRaisesRegex(ValueError, "only pure Python wheels"):
+            f.add_wheel(
+                self._make_wheel(
+                    {
+                        "my_plugin/_speedups" + EXTENSION_SUFFIXES[0]: b"\x00",
+                        "my_plugin-1.0.dist-info/WHEEL": "Wheel-Version: 1.0\n",
+                    }
+                )
+            )
+
+    def test_add_wheel_invalid(self):
+        f = OxidizedFinder()
+
+        with self.assertRaisesRegex(ValueError, "error reading wheel"):
+            f.add_wheel(b"not a wheel")
+
+
+if __name__ == "__main__":
+    unittest.main()
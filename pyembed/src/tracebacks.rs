@@ -0,0 +1,80 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Render tracebacks with paths of original source files.
+
+Modules compiled at packaging time use their module name as filename. When
+packed resources record the path of the source file a module was packaged
+from, an excepthook rewrites the filenames of traceback frames to these
+paths.
+
+Source lines are looked up before filenames are rewritten, as `linecache`
+knows packaged modules by their module name.
+*/
+
+use pyo3::{prelude::*, types::PyDict};
+
+/// Python code defining the excepthook.
+const EXCEPTHOOK_CODE: &str = r#"
+import sys
+import traceback
+
+
+def _source_map_path(filename):
+    for finder in sys.meta_path:
+        find_resource = getattr(finder, "find_resource", None)
+        if find_resource is None:
+            continue
+
+        try:
+            resource = find_resource(filename)
+        except Exception:
+            continue
+
+        path = getattr(resource, "source_map_path", None)
+        if path is not None:
+            return path
+
+    return None
+
+
+def _map_paths(exc, seen):
+    if exc is None or id(exc) in seen:
+        return
+    seen.add(id(exc))
+
+    for frame in exc.stack:
+        path = _source_map_path(frame.filename)
+        if path is not None:
+            # Resolve the source line while linecache can find it.
+            frame.line
+            frame.filename = path
+
+    _map_paths(exc.__cause__, seen)
+    _map_paths(exc.__context__, seen)
+    for child in getattr(exc, "exceptions", None) or ():
+        _map_paths(child, seen)
+
+
+def excepthook(exc_type, exc_value, exc_tb):
+    try:
+        exc = traceback.TracebackException(exc_type, exc_value, exc_tb)
+        _map_paths(exc, set())
+        lines = list(exc.format())
+    except Exception:
+        sys.__excepthook__(exc_type, exc_value, exc_tb)
+        return
+
+    if sys.stderr is not None:
+        sys.stderr.write("".join(lines))
+"#;
+
+/// Replace `sys.excepthook` by a hook rendering source map paths.
+pub(crate) fn install_source_paths_excepthook(py: Python) -> PyResult<()> {
+    let globals = PyDict::new(py);
+    py.run(EXCEPTHOOK_CODE, Some(globals), None)?;
+
+    py.import("sys")?
+        .setattr("excepthook", globals.get_item("excepthook"))
+}
@@ -0,0 +1,326 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Inputs consulted while evaluating config files and building.
+
+PyOxidizer records the environment variables, files and tool versions it
+consults. `pyoxidizer build --build-inputs <path>` writes them to a JSON
+manifest. With `--frozen`, the build instead fails if an input differs from
+the manifest, catching drift between machines and over time.
+
+Recording is process-wide, as inputs are consulted throughout the code base.
+Files are recorded by path. Their content is digested when a manifest is
+created.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    once_cell::sync::Lazy,
+    serde::{Deserialize, Serialize},
+    sha2::{Digest, Sha256},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        path::{Path, PathBuf},
+        sync::Mutex,
+    },
+};
+
+/// Prefixes of environment variables consulted by `cargo` and `rustc`.
+pub const CARGO_ENV_PREFIXES: &[&str] = &["CARGO_", "RUSTC", "RUSTDOCFLAGS", "RUSTFLAGS"];
+
+/// Prefixes of environment variables consulted by `pip`.
+pub const PIP_ENV_PREFIXES: &[&str] = &["PIP_"];
+
+/// Inputs recorded so far.
+#[derive(Debug, Default)]
+struct Recorded {
+    env: BTreeMap<String, Option<String>>,
+    paths: BTreeSet<PathBuf>,
+    tools: BTreeMap<String, String>,
+}
+
+static RECORDED: Lazy<Mutex<Recorded>> = Lazy::new(|| Mutex::new(Recorded::default()));
+
+/// Obtain the value of an environment variable, recording it as an input.
+///
+/// Values which aren't valid Unicode are treated as missing.
+pub fn env_var(name: &str) -> Option<String> {
+    let value = std::env::var(name).ok();
+
+    RECORDED
+        .lock()
+        .unwrap()
+        .env
+        .insert(name.to_string(), value.clone());
+
+    value
+}
+
+/// Record all set environment variables whose name starts with a prefix.
+///
+/// Used when the environment is passed to a tool consulting variables by prefix.
+pub fn record_env_prefixes(prefixes: &[&str]) {
+    let mut recorded = RECORDED.lock().unwrap();
+
+    for (name, value) in std::env::vars() {
+        if prefixes.iter().any(|prefix| name.starts_with(prefix)) {
+            recorded.env.insert(name, Some(value));
+        }
+    }
+}
+
+/// Record a file or directory as an input.
+pub fn record_path(path: impl AsRef<Path>) {
+    RECORDED
+        .lock()
+        .unwrap()
+        .paths
+        .insert(path.as_ref().to_path_buf());
+}
+
+/// Record the version of a tool as an input.
+pub fn record_tool(name: impl ToString, version: impl ToString) {
+    RECORDED
+        .lock()
+        .unwrap()
+        .tools
+        .insert(name.to_string(), version.to_string());
+}
+
+/// Compute the SHA-256 digest of a file or directory.
+///
+/// Directory digests cover the relative paths and content of all files in
+/// the directory. Returns [None] if the path doesn't exist.
+fn digest_path(path: &Path) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let mut hasher = Sha256::new();
+
+    if path.is_dir() {
+        for entry in walkdir::WalkDir::new(path).sort_by_file_name() {
+            let entry = entry?;
+
+            if entry.file_type().is_file() {
+                let rel_path = entry.path().strip_prefix(path)?;
+                let data = std::fs::read(entry.path())
+                    .with_context(|| format!("reading {}", entry.path().display()))?;
+
+                hasher.update(rel_path.display().to_string().as_bytes());
+                hasher.update([0]);
+                hasher.update(Sha256::digest(&data));
+            }
+        }
+    } else {
+        let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        hasher.update(&data);
+    }
+
+    Ok(Some(hex::encode(hasher.finalize())))
+}
+
+/// Describe changes of a map of inputs.
+fn map_differences<V: PartialEq + std::fmt::Debug>(
+    kind: &str,
+    current: &BTreeMap<String, V>,
+    recorded: &BTreeMap<String, V>,
+) -> Vec<String> {
+    let mut res = vec![];
+
+    for (key, value) in current {
+        match recorded.get(key) {
+            Some(recorded_value) if recorded_value == value => {}
+            Some(recorded_value) => res.push(format!(
+                "{} {} changed from {:?} to {:?}",
+                kind, key, recorded_value, value
+            )),
+            None => res.push(format!("{} {} is not recorded", kind, key)),
+        }
+    }
+
+    for key in recorded.keys() {
+        if !current.contains_key(key) {
+            res.push(format!("{} {} is no longer consulted", kind, key));
+        }
+    }
+
+    res
+}
+
+/// A manifest of build inputs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BuildInputs {
+    /// Environment variables and their values. [None] if not set.
+    pub env: BTreeMap<String, Option<String>>,
+    /// Paths of files and directories and the SHA-256 digests of their content.
+    ///
+    /// [None] if the path doesn't exist.
+    pub files: BTreeMap<String, Option<String>>,
+    /// Versions of tools, keyed by tool name.
+    pub tools: BTreeMap<String, String>,
+}
+
+impl BuildInputs {
+    /// Obtain the inputs recorded so far, digesting recorded paths.
+    pub fn recorded() -> Result<Self> {
+        let recorded = RECORDED.lock().unwrap();
+
+        let mut tools = recorded.tools.clone();
+        tools.insert(
+            "pyoxidizer".to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        );
+
+        Ok(Self {
+            env: recorded.env.clone(),
+            files: recorded
+                .paths
+                .iter()
+                .map(|path| Ok((path.display().to_string(), digest_path(path)?)))
+                .collect::<Result<_>>()?,
+            tools,
+        })
+    }
+
+    /// Resolve the current state of the environment variables and files of this manifest.
+    ///
+    /// Tool versions can't be resolved without running the build. So they
+    /// are copied.
+    pub fn current(&self) -> Result<Self> {
+        Ok(Self {
+            env: self
+                .env
+                .keys()
+                .map(|name| (name.clone(), std::env::var(name).ok()))
+                .collect(),
+            files: self
+                .files
+                .keys()
+                .map(|path| Ok((path.clone(), digest_path(Path::new(path))?)))
+                .collect::<Result<_>>()?,
+            tools: self.tools.clone(),
+        })
+    }
+
+    /// Describe how these inputs differ from recorded inputs.
+    pub fn differences(&self, recorded: &Self) -> Vec<String> {
+        let mut res = map_differences("environment variable", &self.env, &recorded.env);
+        res.extend(map_differences("file", &self.files, &recorded.files));
+        res.extend(map_differences("tool", &self.tools, &recorded.tools));
+
+        res
+    }
+
+    /// Fail if these inputs differ from recorded inputs.
+    pub fn verify(&self, recorded: &Self, manifest_path: &Path) -> Result<()> {
+        let differences = self.differences(recorded);
+
+        if differences.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "build inputs differ from {}:\n{}",
+                manifest_path.display(),
+                differences.join("\n")
+            ))
+        }
+    }
+
+    /// Read a manifest from a JSON file.
+    pub fn read_json(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+
+        serde_json::from_slice(&data).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    /// Write the manifest to a file as JSON.
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_vec_pretty(self)?;
+
+        std::fs::write(path, data).with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, tempfile::TempDir};
+
+    #[test]
+    fn test_digest_path() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("dir");
+
+        assert_eq!(digest_path(&path)?, None);
+
+        std::fs::create_dir(&path)?;
+        std::fs::write(path.join("a.py"), "a = 1\n")?;
+        let digest = digest_path(&path)?;
+        assert!(digest.is_some());
+        assert_ne!(digest_path(&path.join("a.py"))?, digest);
+
+        std::fs::write(path.join("a.py"), "a = 2\n")?;
+        assert_ne!(digest_path(&path)?, digest);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_differences() {
+        let recorded = BuildInputs {
+            env: [
+                ("A".to_string(), Some("1".to_string())),
+                ("B".to_string(), None),
+            ]
+            .into_iter()
+            .collect(),
+            files: [("pyoxidizer.bzl".to_string(), Some("abc".to_string()))]
+                .into_iter()
+                .collect(),
+            tools: [("rustc".to_string(), "1.66.0".to_string())]
+                .into_iter()
+                .collect(),
+        };
+
+        assert!(recorded.differences(&recorded).is_empty());
+
+        let mut current = recorded.clone();
+        current.env.insert("A".to_string(), Some("2".to_string()));
+        current.env.remove("B");
+        current.env.insert("C".to_string(), None);
+        current
+            .tools
+            .insert("rustc".to_string(), "1.67.0".to_string());
+
+        assert_eq!(
+            current.differences(&recorded),
+            vec![
+                "environment variable A changed from Some(\"1\") to Some(\"2\")",
+                "environment variable C is not recorded",
+                "environment variable B is no longer consulted",
+                "tool rustc changed from \"1.66.0\" to \"1.67.0\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_current() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("pyoxidizer.bzl");
+        std::fs::write(&path, "")?;
+
+        let recorded = BuildInputs {
+            files: [(path.display().to_string(), digest_path(&path)?)]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        assert!(recorded.current()?.differences(&recorded).is_empty());
+
+        std::fs::write(&path, "changed")?;
+        assert_eq!(recorded.current()?.differences(&recorded).len(), 1);
+
+        Ok(())
+    }
+}
@@ -4,14 +4,22 @@
 
 use {
     crate::{
+        build_inputs,
+        compat_testing::{self, ResourcesMode},
+        config_testing,
         environment::{default_target_triple, PYOXIDIZER_VERSION},
+        log_capture::CapturingStderr,
         project_building, projectmgmt,
+        py_packaging::resource_scanners::CommandResourceScanner,
+        resource_packing, rpc_server,
     },
     anyhow::{anyhow, Context, Result},
     clap::{value_parser, Arg, ArgAction, ArgMatches, Command},
+    python_packaging::filesystem_scanning::register_resource_scanner,
     std::{
         collections::HashMap,
         path::{Path, PathBuf},
+        sync::Arc,
     },
 };
 
@@ -23,6 +31,80 @@ existing PyOxidizer enabled project.
 
 This command will invoke Rust's build system tool (Cargo) to build
 the project.
+
+When multiple targets are to be built, `--jobs N` allows up to N targets not
+sharing any dependencies to be built concurrently. The output of each target
+is printed in the order targets were requested.
+
+With `--containerized-build`, the build runs inside a container image
+providing the toolchain for the target triple. Linux targets default to
+manylinux and musllinux images. Other targets need `--container-image`.
+The container runtime defaults to `docker` and can be changed with the
+PYOXIDIZER_CONTAINER_RUNTIME environment variable.
+
+`--build-inputs FILE` records the environment variables, files and tool
+versions consulted by the build in FILE. With `--frozen`, the build fails
+if any of them differ from FILE instead.
+";
+
+const BUILD_SCRIPT_ABOUT: &str = "\
+Build an executable from a single Python script.
+
+The script can declare its dependencies and supported Python versions in a
+PEP 723 inline metadata block:
+
+  # /// script
+  # requires-python = \">=3.9\"
+  # dependencies = [\"requests<3\"]
+  # ///
+
+Dependencies are installed with `pip install` and embedded in the
+executable along with the script, which runs as `__main__` when the
+executable starts. No PyOxidizer configuration file is needed.
+
+The executable is named after the script and written to the directory
+specified by `--output-dir`.
+";
+
+const COMPAT_TEST_ABOUT: &str = "\
+Test the compatibility of Python packages with PyOxidizer.
+
+Each PACKAGE argument is a pip requirement string, such as `requests` or
+`numpy==1.24.2`.
+
+For every package and resources mode, this command builds a throwaway
+executable embedding the package and runs a smoke test in it. Popular
+packages have smoke tests exercising some of their functionality. Other
+packages are imported.
+
+The following resources modes are tested by default:
+
+in-memory
+   Resources are loaded from memory.
+filesystem-relative
+   Resources are installed in a `lib` directory next to the executable.
+in-memory-fallback
+   Resources are loaded from memory, falling back to the filesystem for
+   resources that can't be loaded from memory.
+
+A compatibility matrix is printed once all tests have run. The command
+fails if any executable failed to build or any smoke test failed.
+";
+
+const CONFIG_TEST_ABOUT: &str = "\
+Run tests against a PyOxidizer configuration file.
+
+Test files are Starlark files defining functions whose names begin with
+`test_`. Each TEST_FILE argument is a path to a test file. If no test files
+are given, files in the project directory ending with `_test.bzl` are tested.
+
+Each test function is called after evaluating the project's configuration
+file and then the test file. Evaluation happens from scratch for every test.
+No targets are resolved by the configuration file and nothing is built.
+
+Tests use `assert_eq()`, `assert_true()`, `assert_resources_contains()`, and
+`assert_resources_not_contains()` to verify the configuration. The command
+fails if any test fails.
 ";
 
 const INIT_RUST_PROJECT_ABOUT: &str = "\
@@ -68,6 +150,40 @@ appropriate for your use case, you can always define a configuration file to
 customize them and use `run-build-script` to produce similar output files.
 ";
 
+const INSTALL_ABOUT: &str = "\
+Install a target's files to a prefix.
+
+The TARGET must resolve to a `FileManifest`. If no TARGET is specified,
+the default target is installed. Targets are built as needed.
+
+Files are installed to PREFIX, which defaults to `/usr/local`. Executable
+files and directories are installed with mode 0755. Other files are
+installed with mode 0644. Existing files are replaced.
+
+When `--destdir` or the `DESTDIR` environment variable is set, files are
+staged under that directory instead, e.g. `DESTDIR=/tmp/stage` and
+`--prefix /usr` install files to `/tmp/stage/usr`. This is useful for
+building distribution packages.
+
+The absolute paths of installed files, without DESTDIR, are written to an
+uninstall manifest. It defaults to `install_manifest.txt` in the build
+directory.
+";
+
+const LIST_PYTHON_DISTRIBUTIONS_ABOUT: &str = "\
+List Python distributions and check for newer releases.
+
+By default, the Python distributions built into this version of PyOxidizer
+are listed. With `--lock-file`, the distributions recorded in a
+`pyoxidizer.lock` file are listed instead. Lock files record the
+distributions that channels passed to `default_python_distribution()`
+resolve to.
+
+`--check-updates` queries the latest python-build-standalone release and
+reports newer equivalents of the listed distributions. `--update` rewrites
+the lock file to use them, so the change can be reviewed and committed.
+";
+
 const RUN_BUILD_SCRIPT_ABOUT: &str = "\
 Runs a crate build script to generate Python artifacts.
 
@@ -79,6 +195,25 @@ This command executes the functionality to derive various artifacts and
 emits special lines that tell the Rust build system how to consume them.
 ";
 
+const PACK_RESOURCES_ABOUT: &str = "\
+Pack Python resources of an existing Python installation.
+
+This command scans site-packages directories, such as those of a virtualenv,
+and writes a packed resources file that `oxidized_importer.OxidizedFinder`
+can load. It allows the fast importer to be used with a stock Python
+interpreter without performing a full PyOxidizer build.
+
+The --python argument is the Python interpreter that will import the
+resources. It is used to compile bytecode. If no PATH arguments are given,
+site-packages directories of that interpreter are scanned.
+
+Top-level packages containing extension modules are not packed: they
+continue to be imported from the filesystem by the standard importer.
+
+Python code registering an `OxidizedFinder` for the resources is written to
+the path given by --loader or printed if that argument isn't given.
+";
+
 const RESOURCES_SCAN_ABOUT: &str = "\
 Scan a directory or file for Python resources.
 
@@ -98,6 +233,34 @@ conversion is critical for properly packaging Python applications and
 bugs can result in incorrect install layouts, missing resources, etc.
 ";
 
+const SERVE_ABOUT: &str = "\
+Serve PyOxidizer functionality over JSON-RPC on stdin/stdout.
+
+This command is intended to be spawned by editor integrations and other
+tools. It reads JSON-RPC 2.0 requests framed with `Content-Length` headers
+(like the Language Server Protocol) and exposes methods to evaluate
+configuration files, list targets, complete Starlark attributes, query the
+resources of an executable and build targets.
+
+Python distributions are cached for the lifetime of the process, making
+repeated evaluations and builds faster.
+";
+
+const RESOURCE_SCANNER_HELP: &str = "\
+Register a command converting files ending in <suffix> into resources.
+
+When scanning directories for Python resources, the command is invoked for
+every file ending in <suffix> with the path of a copy of the file and an
+empty output directory as arguments. Files the command writes to the output
+directory replace the scanned file and are classified as if they were in
+its directory.
+
+This allows packaging files in custom formats, such as archives of Python
+modules. <command> is split on whitespace.
+
+Can be specified multiple times. The first matching scanner is used.
+";
+
 const VAR_HELP: &str = "\
 Defines a single string key to set in the VARS global dict.
 
@@ -128,6 +291,18 @@ be `None` instead of a `string`.
 If a Starlark variable is defined multiple times, an error occurs.
 ";
 
+const ENABLE_EXTRA_HELP: &str = "\
+Enables an extra registered by the configuration file.
+
+Extras are named groups of optional dependencies defined with
+register_extra(). Enabled extras are available in the EXTRAS global list.
+Requirements of enabled extras are installed by
+PythonExecutable.pip_install_extras(). Resources belonging to extras that
+aren't enabled are excluded by packaging policies.
+
+This argument can be specified multiple times.
+";
+
 fn add_env_args(app: Command) -> Command {
     app.arg(
         Arg::new("vars")
@@ -147,6 +322,14 @@ fn add_env_args(app: Command) -> Command {
             .help("Define an environment variable in Starlark environment")
             .long_help(ENV_VAR_HELP),
     )
+    .arg(
+        Arg::new("extras")
+            .long("enable-extra")
+            .action(ArgAction::Append)
+            .value_name("name")
+            .help("Enable an extra registered in the configuration file")
+            .long_help(ENABLE_EXTRA_HELP),
+    )
 }
 
 fn add_python_distribution_args(app: Command) -> Command {
@@ -191,13 +374,20 @@ fn starlark_vars(args: &ArgMatches) -> Result<HashMap<String, Option<String>>> {
                 return Err(anyhow!("Starlark variable {} already defined", name));
             }
 
-            res.insert(name.to_string(), std::env::var(env).ok());
+            res.insert(name.to_string(), build_inputs::env_var(env));
         }
     }
 
     Ok(res)
 }
 
+fn enabled_extras(args: &ArgMatches) -> Vec<String> {
+    args.get_many::<String>("extras")
+        .unwrap_or_default()
+        .cloned()
+        .collect()
+}
+
 pub fn run_cli() -> Result<()> {
     let mut env = crate::environment::Environment::new()?;
 
@@ -216,6 +406,16 @@ pub fn run_cli() -> Result<()> {
                 .action(ArgAction::SetTrue)
                 .help("Use a system install of Rust instead of a self-managed Rust installation"),
         )
+        .arg(
+            Arg::new("resource_scanners")
+                .long("resource-scanner")
+                .global(true)
+                .action(ArgAction::Append)
+                .value_names(["suffix", "command"])
+                .number_of_values(2)
+                .help("Register a command converting files into Python resources")
+                .long_help(RESOURCE_SCANNER_HELP),
+        )
         .arg(
             Arg::new("verbose")
                 .long("verbose")
@@ -225,13 +425,21 @@ pub fn run_cli() -> Result<()> {
         );
 
     let app = app.subcommand(
-        Command::new("analyze").about("Analyze a built binary").arg(
-            Arg::new("path")
-                .action(ArgAction::Set)
-                .value_parser(value_parser!(PathBuf))
-                .required(true)
-                .help("Path to executable to analyze"),
-        ),
+        Command::new("analyze")
+            .about("Analyze a built binary")
+            .arg(
+                Arg::new("av_report")
+                    .long("av-report")
+                    .action(ArgAction::SetTrue)
+                    .help("Report traits commonly triggering anti-virus heuristics"),
+            )
+            .arg(
+                Arg::new("path")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .required(true)
+                    .help("Path to executable to analyze"),
+            ),
     );
 
     let app = app.subcommand(add_env_args(
@@ -250,6 +458,56 @@ pub fn run_cli() -> Result<()> {
                     .action(ArgAction::SetTrue)
                     .help("Build a release binary"),
             )
+            .arg(
+                Arg::new("jobs")
+                    .long("jobs")
+                    .short('j')
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(usize))
+                    .default_value("1")
+                    .value_name("N")
+                    .help("Number of independent targets to build concurrently"),
+            )
+            .arg(
+                Arg::new("metrics")
+                    .long("metrics")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("FILE")
+                    .help("Write anonymized build metrics to a JSON file"),
+            )
+            .arg(
+                Arg::new("build_inputs")
+                    .long("build-inputs")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("FILE")
+                    .help("Write inputs consulted by the build to a JSON file"),
+            )
+            .arg(
+                Arg::new("frozen")
+                    .long("frozen")
+                    .action(ArgAction::SetTrue)
+                    .requires("build_inputs")
+                    .help(
+                        "Verify build inputs against the --build-inputs file instead of writing it",
+                    ),
+            )
+            .arg(
+                Arg::new("containerized_build")
+                    .long("containerized-build")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with_all(["metrics", "build_inputs"])
+                    .help("Run the build inside a container"),
+            )
+            .arg(
+                Arg::new("container_image")
+                    .long("container-image")
+                    .action(ArgAction::Set)
+                    .requires("containerized_build")
+                    .value_name("IMAGE")
+                    .help("Container image to build in"),
+            )
             .arg(
                 Arg::new("path")
                     .long("path")
@@ -268,9 +526,102 @@ pub fn run_cli() -> Result<()> {
             ),
     ));
 
+    let app = app.subcommand(
+        Command::new("build-script")
+            .about("Build an executable from a single Python script")
+            .long_about(BUILD_SCRIPT_ABOUT)
+            .arg(
+                Arg::new("target_triple")
+                    .long("target-triple")
+                    .action(ArgAction::Set)
+                    .help("Rust target triple to build for"),
+            )
+            .arg(
+                Arg::new("release")
+                    .long("release")
+                    .action(ArgAction::SetTrue)
+                    .help("Build a release binary"),
+            )
+            .arg(
+                Arg::new("output_dir")
+                    .long("output-dir")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .default_value(".")
+                    .value_name("DIR")
+                    .help("Directory to write the executable to"),
+            )
+            .arg(
+                Arg::new("script")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .required(true)
+                    .value_name("SCRIPT")
+                    .help("Path to Python script to build"),
+            ),
+    );
+
     let app =
         app.subcommand(Command::new("cache-clear").about("Clear PyOxidizer's user-specific cache"));
 
+    let app = app.subcommand(
+        Command::new("compat-test")
+            .about("Test the compatibility of Python packages with PyOxidizer")
+            .long_about(COMPAT_TEST_ABOUT)
+            .arg(
+                Arg::new("target_triple")
+                    .long("target-triple")
+                    .action(ArgAction::Set)
+                    .default_value(default_target_triple())
+                    .help("Rust target triple to build for"),
+            )
+            .arg(
+                Arg::new("modes")
+                    .long("mode")
+                    .action(ArgAction::Append)
+                    .value_parser(["in-memory", "filesystem-relative", "in-memory-fallback"])
+                    .value_name("MODE")
+                    .help("Resources mode to test. Can be specified multiple times"),
+            )
+            .arg(
+                Arg::new("packages")
+                    .action(ArgAction::Append)
+                    .num_args(1..)
+                    .required(true)
+                    .value_name("PACKAGE")
+                    .help("Python package requirement to test"),
+            ),
+    );
+
+    let app = app.subcommand(add_env_args(
+        Command::new("config-test")
+            .about("Run tests against a PyOxidizer configuration file")
+            .long_about(CONFIG_TEST_ABOUT)
+            .arg(
+                Arg::new("target_triple")
+                    .long("target-triple")
+                    .action(ArgAction::Set)
+                    .default_value(default_target_triple())
+                    .help("Rust target triple to evaluate the configuration for"),
+            )
+            .arg(
+                Arg::new("path")
+                    .long("path")
+                    .action(ArgAction::Set)
+                    .default_value(".")
+                    .value_name("PATH")
+                    .help("Directory containing project to test"),
+            )
+            .arg(
+                Arg::new("test_files")
+                    .action(ArgAction::Append)
+                    .num_args(0..)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("TEST_FILE")
+                    .help("Test file to run"),
+            ),
+    ));
+
     let app = app.subcommand(
         Command::new("find-resources")
             .about("Find resources in a file or directory")
@@ -369,6 +720,63 @@ pub fn run_cli() -> Result<()> {
             ),
     );
 
+    let app = app.subcommand(add_env_args(
+        Command::new("install")
+            .about("Install a target's files to a prefix")
+            .long_about(INSTALL_ABOUT)
+            .arg(
+                Arg::new("target_triple")
+                    .long("target-triple")
+                    .action(ArgAction::Set)
+                    .help("Rust target triple to build for"),
+            )
+            .arg(
+                Arg::new("release")
+                    .long("release")
+                    .action(ArgAction::SetTrue)
+                    .help("Install release artifacts"),
+            )
+            .arg(
+                Arg::new("path")
+                    .long("path")
+                    .action(ArgAction::Set)
+                    .default_value(".")
+                    .value_name("PATH")
+                    .help("Directory containing project to build"),
+            )
+            .arg(
+                Arg::new("target")
+                    .long("target")
+                    .action(ArgAction::Set)
+                    .help("Build target to install"),
+            )
+            .arg(
+                Arg::new("prefix")
+                    .long("prefix")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .default_value("/usr/local")
+                    .value_name("PREFIX")
+                    .help("Directory to install files to"),
+            )
+            .arg(
+                Arg::new("destdir")
+                    .long("destdir")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("DESTDIR")
+                    .help("Directory to stage installed files in [default: $DESTDIR]"),
+            )
+            .arg(
+                Arg::new("uninstall_manifest")
+                    .long("uninstall-manifest")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("PATH")
+                    .help("File to record installed paths in"),
+            ),
+    ));
+
     let app = app.subcommand(
         Command::new("list-targets")
             .about("List targets available to resolve in a configuration file")
@@ -381,6 +789,73 @@ pub fn run_cli() -> Result<()> {
             ),
     );
 
+    let app = app.subcommand(
+        Command::new("list-python-distributions")
+            .about("List Python distributions and check for newer releases")
+            .long_about(LIST_PYTHON_DISTRIBUTIONS_ABOUT)
+            .arg(
+                Arg::new("lock_file")
+                    .long("lock-file")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("PATH")
+                    .help("Lock file whose distributions to list"),
+            )
+            .arg(
+                Arg::new("check_updates")
+                    .long("check-updates")
+                    .action(ArgAction::SetTrue)
+                    .help("Check for newer python-build-standalone releases"),
+            )
+            .arg(
+                Arg::new("update")
+                    .long("update")
+                    .action(ArgAction::SetTrue)
+                    .requires("lock_file")
+                    .help("Update the lock file to the newest releases"),
+            ),
+    );
+
+    let app = app.subcommand(
+        Command::new("pack-resources")
+            .about("Pack Python resources of an existing Python installation")
+            .long_about(PACK_RESOURCES_ABOUT)
+            .arg(
+                Arg::new("python")
+                    .long("python")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("PYTHON")
+                    .required(true)
+                    .help("Python interpreter that will import the resources"),
+            )
+            .arg(
+                Arg::new("output")
+                    .long("output")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("FILE")
+                    .required(true)
+                    .help("Path to write packed resources to"),
+            )
+            .arg(
+                Arg::new("loader")
+                    .long("loader")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("FILE")
+                    .help("Path to write Python code registering the resources to"),
+            )
+            .arg(
+                Arg::new("paths")
+                    .action(ArgAction::Append)
+                    .num_args(0..)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("PATH")
+                    .help("Directory to scan for resources"),
+            ),
+    );
+
     let app = app.subcommand(
         Command::new("python-distribution-extract")
             .about("Extract a Python distribution archive to a directory")
@@ -511,6 +986,20 @@ pub fn run_cli() -> Result<()> {
             ),
     );
 
+    let app = app.subcommand(
+        Command::new("serve")
+            .about("Serve PyOxidizer functionality over JSON-RPC on stdin/stdout")
+            .long_about(SERVE_ABOUT)
+            .arg(
+                Arg::new("path")
+                    .long("path")
+                    .action(ArgAction::Set)
+                    .value_parser(value_parser!(PathBuf))
+                    .value_name("PATH")
+                    .help("Directory relative paths in requests are resolved against"),
+            ),
+    );
+
     let matches = app.get_matches();
 
     let verbose = matches.contains_id("verbose");
@@ -529,7 +1018,8 @@ pub fn run_cli() -> Result<()> {
     builder
         .format_timestamp(None)
         .format_level(false)
-        .format_target(false);
+        .format_target(false)
+        .target(env_logger::Target::Pipe(Box::new(CapturingStderr)));
 
     builder.init();
 
@@ -537,6 +1027,12 @@ pub fn run_cli() -> Result<()> {
         env.unmanage_rust().context("unmanaging Rust")?;
     }
 
+    if let Some(mut values) = matches.get_many::<String>("resource_scanners") {
+        while let (Some(suffix), Some(command)) = (values.next(), values.next()) {
+            register_resource_scanner(Arc::new(CommandResourceScanner::new(suffix, command)?));
+        }
+    }
+
     let (command, args) = matches
         .subcommand()
         .ok_or_else(|| anyhow!("invalid sub-command"))?;
@@ -545,9 +1041,13 @@ pub fn run_cli() -> Result<()> {
         "analyze" => {
             let path = args.get_one::<PathBuf>("path").unwrap();
 
-            tugger_binary_analysis::analyze_file(path.clone());
+            if args.get_flag("av_report") {
+                projectmgmt::av_report(path)
+            } else {
+                tugger_binary_analysis::analyze_file(path.clone());
 
-            Ok(())
+                Ok(())
+            }
         }
 
         "build" => {
@@ -558,6 +1058,24 @@ pub fn run_cli() -> Result<()> {
             let resolve_targets = args
                 .get_many::<String>("targets")
                 .map(|x| x.cloned().collect::<Vec<_>>());
+            let jobs = *args.get_one::<usize>("jobs").unwrap();
+            let metrics_path = args.get_one::<PathBuf>("metrics");
+            let build_inputs_path = args.get_one::<PathBuf>("build_inputs");
+
+            if args.get_flag("containerized_build") {
+                return projectmgmt::build_containerized(
+                    path,
+                    target_triple.map(|x| x.as_str()),
+                    resolve_targets,
+                    starlark_vars,
+                    enabled_extras(args),
+                    release,
+                    verbose,
+                    jobs,
+                    args.get_one::<String>("container_image")
+                        .map(|x| x.as_str()),
+                );
+            }
 
             projectmgmt::build(
                 &env,
@@ -565,13 +1083,73 @@ pub fn run_cli() -> Result<()> {
                 target_triple.map(|x| x.as_str()),
                 resolve_targets,
                 starlark_vars,
+                enabled_extras(args),
                 release,
                 verbose,
+                jobs,
+                metrics_path.map(|x| x.as_path()),
+                build_inputs_path.map(|x| x.as_path()),
+                args.get_flag("frozen"),
+            )
+        }
+
+        "build-script" => {
+            let target_triple = args.get_one::<String>("target_triple");
+            let output_dir = args.get_one::<PathBuf>("output_dir").unwrap();
+            let script = args.get_one::<PathBuf>("script").unwrap();
+
+            projectmgmt::build_script(
+                &env,
+                script,
+                output_dir,
+                target_triple.map(|x| x.as_str()),
+                args.get_flag("release"),
+                verbose,
             )
         }
 
         "cache-clear" => projectmgmt::cache_clear(&env),
 
+        "compat-test" => {
+            let target_triple = args.get_one::<String>("target_triple").unwrap();
+            let packages = args
+                .get_many::<String>("packages")
+                .unwrap_or_default()
+                .map(|x| x.as_str())
+                .collect::<Vec<_>>();
+            let modes = if let Some(modes) = args.get_many::<String>("modes") {
+                modes
+                    .map(|x| ResourcesMode::try_from(x.as_str()).map_err(|e| anyhow!(e)))
+                    .collect::<Result<Vec<_>>>()?
+            } else {
+                ResourcesMode::ALL.to_vec()
+            };
+
+            compat_testing::compat_test(&env, target_triple, &packages, &modes, verbose)
+        }
+
+        "config-test" => {
+            let target_triple = args.get_one::<String>("target_triple").unwrap();
+            let path = args.get_one::<String>("path").unwrap();
+            let test_files = args
+                .get_many::<PathBuf>("test_files")
+                .unwrap_or_default()
+                .cloned()
+                .collect::<Vec<_>>();
+
+            config_testing::config_test(
+                &env,
+                Path::new(path),
+                &test_files,
+                &config_testing::ConfigTestOptions {
+                    target_triple,
+                    extra_vars: starlark_vars(args)?,
+                    enabled_extras: enabled_extras(args),
+                    verbose,
+                },
+            )
+        }
+
         "find-resources" => {
             let path = args.get_one::<PathBuf>("path");
             let distributions_dir = args.get_one::<PathBuf>("distributions_dir");
@@ -634,12 +1212,53 @@ pub fn run_cli() -> Result<()> {
             )
         }
 
+        "install" => {
+            let starlark_vars = starlark_vars(args)?;
+            let target_triple = args.get_one::<String>("target_triple");
+            let release = args.get_flag("release");
+            let path = args.get_one::<String>("path").unwrap();
+            let target = args.get_one::<String>("target");
+            let prefix = args.get_one::<PathBuf>("prefix").unwrap();
+            let destdir = args.get_one::<PathBuf>("destdir").cloned().or_else(|| {
+                std::env::var_os("DESTDIR")
+                    .filter(|v| !v.is_empty())
+                    .map(PathBuf::from)
+            });
+            let uninstall_manifest = args.get_one::<PathBuf>("uninstall_manifest");
+
+            projectmgmt::install(
+                &env,
+                Path::new(path),
+                target_triple.map(|x| x.as_str()),
+                release,
+                target.map(|x| x.as_str()),
+                starlark_vars,
+                enabled_extras(args),
+                verbose,
+                prefix,
+                destdir.as_deref(),
+                uninstall_manifest.map(|x| x.as_path()),
+            )
+        }
+
         "list-targets" => {
             let path = args.get_one::<String>("path").unwrap();
 
             projectmgmt::list_targets(&env, Path::new(path))
         }
 
+        "list-python-distributions" => {
+            let lock_file = args.get_one::<PathBuf>("lock_file");
+            let check_updates = args.get_flag("check_updates");
+            let update = args.get_flag("update");
+
+            projectmgmt::list_python_distributions(
+                lock_file.map(|x| x.as_path()),
+                check_updates,
+                update,
+            )
+        }
+
         "init-rust-project" => {
             let path = args.get_one::<String>("path").unwrap();
             let project_path = Path::new(path);
@@ -647,6 +1266,25 @@ pub fn run_cli() -> Result<()> {
             projectmgmt::init_rust_project(&env, project_path)
         }
 
+        "pack-resources" => {
+            let python = args.get_one::<PathBuf>("python").unwrap();
+            let output = args.get_one::<PathBuf>("output").unwrap();
+            let loader = args.get_one::<PathBuf>("loader");
+            let paths = args
+                .get_many::<PathBuf>("paths")
+                .unwrap_or_default()
+                .cloned()
+                .collect::<Vec<_>>();
+
+            resource_packing::pack_resources(
+                &env,
+                python,
+                &paths,
+                output,
+                loader.map(|x| x.as_path()),
+            )
+        }
+
         "python-distribution-extract" => {
             let download_default = args.get_flag("download-default");
             let archive_path = args.get_one::<String>("archive-path");
@@ -689,6 +1327,7 @@ pub fn run_cli() -> Result<()> {
                 build_script,
                 target.map(|x| x.as_str()),
                 starlark_vars,
+                enabled_extras(args),
             )
         }
 
@@ -711,6 +1350,7 @@ pub fn run_cli() -> Result<()> {
                 release,
                 target.map(|x| x.as_str()),
                 starlark_vars,
+                enabled_extras(args),
                 &extra,
                 verbose,
             )
@@ -733,6 +1373,12 @@ pub fn run_cli() -> Result<()> {
             )
         }
 
+        "serve" => {
+            let path = args.get_one::<PathBuf>("path");
+
+            rpc_server::serve_stdio(&env, path.map(|x| x.as_path()))
+        }
+
         _ => Err(anyhow!("invalid sub-command")),
     }
 }
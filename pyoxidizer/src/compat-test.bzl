@@ -0,0 +1,35 @@
+# Configuration file evaluated by `pyoxidizer compat-test`.
+#
+# Builds an executable embedding a single Python package and running a smoke
+# test against it. The package, resource locations, and smoke test are passed
+# in via VARS.
+
+def make_exe():
+    dist = default_python_distribution()
+
+    policy = dist.make_python_packaging_policy()
+    policy.resources_location = VARS["RESOURCES_LOCATION"]
+    policy.resources_location_fallback = VARS.get("RESOURCES_LOCATION_FALLBACK")
+
+    python_config = dist.make_python_interpreter_config()
+    python_config.run_command = VARS["SMOKE_TEST"]
+
+    exe = dist.to_python_executable(
+        name = "compat-test",
+        packaging_policy = policy,
+        config = python_config,
+    )
+    exe.add_python_resources(exe.pip_install([VARS["REQUIREMENT"]]))
+
+    return exe
+
+def make_install(exe):
+    files = FileManifest()
+    files.add_python_resource(".", exe)
+
+    return files
+
+register_target("exe", make_exe)
+register_target("install", make_install, depends = ["exe"], default = True)
+
+resolve_targets()
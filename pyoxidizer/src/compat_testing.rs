@@ -0,0 +1,453 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Test compatibility of Python packages with PyOxidizer.
+
+This powers `pyoxidizer compat-test`, which builds throwaway executables
+embedding individual Python packages and runs a smoke test in each. Each
+package is tested once per resources mode, as packages often work when
+loaded from the filesystem but not from memory.
+
+The results form a compatibility matrix of packages and resources modes.
+*/
+
+use {
+    crate::{
+        environment::Environment, py_packaging::distribution::DistributionCache,
+        starlark::eval::EvaluationContextBuilder,
+    },
+    anyhow::{anyhow, Context, Result},
+    starlark_dialect_build_targets::RunMode,
+    std::{collections::HashMap, fmt::Write, sync::Arc},
+};
+
+/// Configuration file used to build test executables.
+const COMPAT_TEST_CONFIG: &str = include_str!("compat-test.bzl");
+
+/// Smoke tests for popular packages going beyond importing the package.
+///
+/// Keys are normalized package names.
+const SMOKE_TESTS: &[(&str, &str)] = &[
+    (
+        "requests",
+        "import requests\n\
+         s = requests.Session()\n\
+         r = requests.Request('GET', 'https://example.com', params={'a': 1}).prepare()\n\
+         assert r.url == 'https://example.com/?a=1', r.url\n\
+         assert requests.certs.where()\n",
+    ),
+    (
+        "flask",
+        "import flask\n\
+         app = flask.Flask('compat')\n\
+         app.add_url_rule('/', 'index', lambda: flask.render_template_string('{{ 1 + 1 }}'))\n\
+         assert app.test_client().get('/').data == b'2'\n",
+    ),
+    (
+        "numpy",
+        "import numpy\n\
+         assert numpy.arange(10).sum() == 45\n\
+         assert numpy.linalg.det(numpy.eye(3)) == 1.0\n",
+    ),
+    (
+        "pyyaml",
+        "import yaml\n\
+         assert yaml.safe_load('a: [1, 2]') == {'a': [1, 2]}\n",
+    ),
+    (
+        "jinja2",
+        "import jinja2\n\
+         assert jinja2.Template('{{ x }}').render(x=42) == '42'\n",
+    ),
+    (
+        "pillow",
+        "from PIL import Image\n\
+         assert Image.new('RGB', (2, 2)).size == (2, 2)\n",
+    ),
+    (
+        "certifi",
+        "import certifi, os\n\
+         assert os.path.exists(certifi.where()), certifi.where()\n",
+    ),
+];
+
+/// Import names of popular packages not matching their package name.
+///
+/// Keys are normalized package names.
+const IMPORT_NAMES: &[(&str, &str)] = &[
+    ("beautifulsoup4", "bs4"),
+    ("pillow", "PIL"),
+    ("python-dateutil", "dateutil"),
+    ("pyyaml", "yaml"),
+    ("scikit-learn", "sklearn"),
+];
+
+/// Where resources of test executables are installed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResourcesMode {
+    /// Resources are loaded from memory.
+    InMemory,
+    /// Resources are installed next to the executable.
+    FilesystemRelative,
+    /// Resources are loaded from memory, falling back to the filesystem.
+    InMemoryWithFallback,
+}
+
+impl ResourcesMode {
+    /// All modes, in the order they are tested.
+    pub const ALL: &'static [Self] = &[
+        Self::InMemory,
+        Self::FilesystemRelative,
+        Self::InMemoryWithFallback,
+    ];
+
+    /// The `PythonPackagingPolicy.resources_location` value for this mode.
+    fn resources_location(&self) -> &'static str {
+        match self {
+            Self::InMemory | Self::InMemoryWithFallback => "in-memory",
+            Self::FilesystemRelative => "filesystem-relative:lib",
+        }
+    }
+
+    /// The `PythonPackagingPolicy.resources_location_fallback` value for this mode.
+    fn resources_location_fallback(&self) -> Option<&'static str> {
+        match self {
+            Self::InMemory | Self::FilesystemRelative => None,
+            Self::InMemoryWithFallback => Some("filesystem-relative:lib"),
+        }
+    }
+}
+
+impl std::fmt::Display for ResourcesMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::InMemory => "in-memory",
+            Self::FilesystemRelative => "filesystem-relative",
+            Self::InMemoryWithFallback => "in-memory-fallback",
+        })
+    }
+}
+
+impl TryFrom<&str> for ResourcesMode {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "in-memory" => Ok(Self::InMemory),
+            "filesystem-relative" => Ok(Self::FilesystemRelative),
+            "in-memory-fallback" => Ok(Self::InMemoryWithFallback),
+            _ => Err(format!(
+                "{} is not a valid resources mode; use in-memory, filesystem-relative, or in-memory-fallback",
+                value
+            )),
+        }
+    }
+}
+
+/// Obtain the normalized name of the package a requirement string refers to.
+fn requirement_package_name(requirement: &str) -> String {
+    requirement
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'))
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+        .replace(['_', '.'], "-")
+}
+
+/// Resolve the Python code to run to test a package given its requirement string.
+pub fn smoke_test_code(requirement: &str) -> String {
+    let name = requirement_package_name(requirement);
+
+    if let Some((_, code)) = SMOKE_TESTS.iter().find(|(package, _)| *package == name) {
+        return code.to_string();
+    }
+
+    let import_name = IMPORT_NAMES
+        .iter()
+        .find(|(package, _)| *package == name)
+        .map(|(_, module)| module.to_string())
+        .unwrap_or_else(|| name.replace('-', "_"));
+
+    format!("import {}\n", import_name)
+}
+
+/// The outcome of testing a package in a resources mode.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CompatOutcome {
+    /// The executable was built and the smoke test passed.
+    Passed,
+    /// The executable failed to build.
+    BuildFailed(String),
+    /// The smoke test failed.
+    TestFailed(String),
+}
+
+impl CompatOutcome {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Passed => "ok",
+            Self::BuildFailed(_) => "build failed",
+            Self::TestFailed(_) => "test failed",
+        }
+    }
+}
+
+/// The result of testing a package in a resources mode.
+#[derive(Clone, Debug)]
+pub struct CompatResult {
+    pub requirement: String,
+    pub mode: ResourcesMode,
+    pub outcome: CompatOutcome,
+}
+
+/// Build an executable embedding a package and run its smoke test.
+fn run_package_test(
+    env: &Environment,
+    distribution_cache: &Arc<DistributionCache>,
+    target_triple: &str,
+    requirement: &str,
+    mode: ResourcesMode,
+    verbose: bool,
+) -> Result<CompatOutcome> {
+    let temp_dir = env.temporary_directory("pyoxidizer-compat-test")?;
+    let config_path = temp_dir.path().join("pyoxidizer.bzl");
+    std::fs::write(&config_path, COMPAT_TEST_CONFIG)
+        .with_context(|| format!("writing {}", config_path.display()))?;
+
+    let vars = HashMap::from([
+        ("REQUIREMENT".to_string(), Some(requirement.to_string())),
+        (
+            "RESOURCES_LOCATION".to_string(),
+            Some(mode.resources_location().to_string()),
+        ),
+        (
+            "RESOURCES_LOCATION_FALLBACK".to_string(),
+            mode.resources_location_fallback().map(|x| x.to_string()),
+        ),
+        ("SMOKE_TEST".to_string(), Some(smoke_test_code(requirement))),
+    ]);
+
+    let built = match EvaluationContextBuilder::new(env, config_path, target_triple)
+        .extra_vars(vars)
+        .verbose(verbose)
+        .distribution_cache(distribution_cache.clone())
+        .resolve_target("install")
+        .build_targets()
+    {
+        Ok(built) => built,
+        Err(e) => return Ok(CompatOutcome::BuildFailed(format!("{:?}", e))),
+    };
+
+    let exe_path = built
+        .into_iter()
+        .find_map(|(_, target)| match target.run_mode {
+            RunMode::Path { path } => Some(path),
+            RunMode::None => None,
+        })
+        .ok_or_else(|| anyhow!("unable to resolve path of built executable"))?;
+
+    let output = std::process::Command::new(&exe_path)
+        .current_dir(temp_dir.path())
+        .output()
+        .with_context(|| format!("running {}", exe_path.display()))?;
+
+    if output.status.success() {
+        Ok(CompatOutcome::Passed)
+    } else {
+        Ok(CompatOutcome::TestFailed(format!(
+            "{}\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        )))
+    }
+}
+
+/// Render results as a table of packages and resources modes.
+pub fn format_matrix(modes: &[ResourcesMode], results: &[CompatResult]) -> String {
+    let mut requirements = vec![];
+    for result in results {
+        if !requirements.contains(&result.requirement.as_str()) {
+            requirements.push(result.requirement.as_str());
+        }
+    }
+
+    let mut columns = vec!["package".to_string()];
+    columns.extend(modes.iter().map(|mode| mode.to_string()));
+
+    let mut rows = vec![columns];
+    for requirement in requirements {
+        let mut row = vec![requirement.to_string()];
+
+        for mode in modes {
+            row.push(
+                results
+                    .iter()
+                    .find(|r| r.requirement == requirement && r.mode == *mode)
+                    .map(|r| r.outcome.label())
+                    .unwrap_or("-")
+                    .to_string(),
+            );
+        }
+
+        rows.push(row);
+    }
+
+    let widths = (0..rows[0].len())
+        .map(|i| rows.iter().map(|row| row[i].len()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+
+    let mut s = String::new();
+    for row in rows {
+        let line = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+
+        writeln!(s, "{}", line.trim_end()).unwrap();
+    }
+
+    s
+}
+
+/// Test the compatibility of packages and print a compatibility matrix.
+///
+/// Returns an error if any package failed to build or its smoke test failed.
+pub fn compat_test(
+    env: &Environment,
+    target_triple: &str,
+    requirements: &[&str],
+    modes: &[ResourcesMode],
+    verbose: bool,
+) -> Result<()> {
+    let distribution_cache = Arc::new(DistributionCache::new(Some(
+        &env.python_distributions_dir(),
+    )));
+
+    let mut results = vec![];
+
+    for requirement in requirements {
+        for mode in modes {
+            println!("testing {} ({})", requirement, mode);
+
+            let outcome = run_package_test(
+                env,
+                &distribution_cache,
+                target_triple,
+                requirement,
+                *mode,
+                verbose,
+            )?;
+
+            println!("  {}", outcome.label());
+
+            results.push(CompatResult {
+                requirement: requirement.to_string(),
+                mode: *mode,
+                outcome,
+            });
+        }
+    }
+
+    for result in &results {
+        let details = match &result.outcome {
+            CompatOutcome::Passed => continue,
+            CompatOutcome::BuildFailed(details) | CompatOutcome::TestFailed(details) => details,
+        };
+
+        println!();
+        println!(
+            "{} ({}): {}",
+            result.requirement,
+            result.mode,
+            result.outcome.label()
+        );
+        for line in details.lines() {
+            println!("  {}", line);
+        }
+    }
+
+    println!();
+    print!("{}", format_matrix(modes, &results));
+
+    let failures = results
+        .iter()
+        .filter(|r| r.outcome != CompatOutcome::Passed)
+        .count();
+
+    if failures == 0 {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} of {} compatibility tests failed",
+            failures,
+            results.len()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smoke_test_code() {
+        assert_eq!(smoke_test_code("six"), "import six\n");
+        assert_eq!(
+            smoke_test_code("typing-extensions"),
+            "import typing_extensions\n"
+        );
+        assert_eq!(
+            smoke_test_code("Typing_Extensions>=4"),
+            "import typing_extensions\n"
+        );
+        assert_eq!(smoke_test_code("PyYAML==6.0"), SMOKE_TESTS[3].1);
+        assert_eq!(smoke_test_code("beautifulsoup4[lxml]"), "import bs4\n");
+        assert!(smoke_test_code("requests").contains("requests.Session()"));
+    }
+
+    #[test]
+    fn test_resources_mode() {
+        for mode in ResourcesMode::ALL {
+            assert_eq!(
+                ResourcesMode::try_from(mode.to_string().as_str()),
+                Ok(*mode)
+            );
+        }
+
+        assert!(ResourcesMode::try_from("memory").is_err());
+    }
+
+    #[test]
+    fn test_format_matrix() {
+        let results = vec![
+            CompatResult {
+                requirement: "requests".into(),
+                mode: ResourcesMode::InMemory,
+                outcome: CompatOutcome::Passed,
+            },
+            CompatResult {
+                requirement: "numpy".into(),
+                mode: ResourcesMode::InMemory,
+                outcome: CompatOutcome::TestFailed("exit status: 1".into()),
+            },
+            CompatResult {
+                requirement: "numpy".into(),
+                mode: ResourcesMode::FilesystemRelative,
+                outcome: CompatOutcome::Passed,
+            },
+        ];
+
+        assert_eq!(
+            format_matrix(
+                &[ResourcesMode::InMemory, ResourcesMode::FilesystemRelative],
+                &results
+            ),
+            "package   in-memory    filesystem-relative\n\
+             requests  ok           -\n\
+             numpy     test failed  ok\n"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Run tests against PyOxidizer configuration files.
+
+This powers `pyoxidizer config-test`. Test files are Starlark files defining
+functions whose names begin with `test_`. Each test function is called after
+a fresh evaluation of the project's configuration file followed by the test
+file, so tests can't influence each other.
+
+Configuration files are evaluated without resolving any targets and nothing
+is built. Tests resolve the targets they inspect via `resolve_target()` or
+by passing a target name to `assert_resources_contains()`.
+*/
+
+use {
+    crate::{
+        environment::Environment, project_building::find_pyoxidizer_config_file_env,
+        py_packaging::distribution::DistributionCache, starlark::eval::EvaluationContextBuilder,
+    },
+    anyhow::{anyhow, Context, Result},
+    std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+};
+
+/// Suffix of files discovered as test files.
+pub const TEST_FILE_SUFFIX: &str = "_test.bzl";
+
+/// Find test files in a project directory.
+fn find_test_files(project_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+
+    for entry in std::fs::read_dir(project_path)
+        .with_context(|| format!("reading directory {}", project_path.display()))?
+    {
+        let path = entry?.path();
+
+        if path.is_file()
+            && path
+                .file_name()
+                .map(|x| x.to_string_lossy().ends_with(TEST_FILE_SUFFIX))
+                .unwrap_or_default()
+        {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Obtain the names of test functions defined by Starlark source code.
+///
+/// Only functions defined at the top level are considered.
+fn test_function_names(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter_map(|line| line.strip_prefix("def "))
+        .filter_map(|line| line.split_once('('))
+        .map(|(name, _)| name.trim().to_string())
+        .filter(|name| name.starts_with("test_"))
+        .collect()
+}
+
+/// Options influencing how configuration files are evaluated.
+pub struct ConfigTestOptions<'a> {
+    pub target_triple: &'a str,
+    pub extra_vars: HashMap<String, Option<String>>,
+    pub enabled_extras: Vec<String>,
+    pub verbose: bool,
+}
+
+/// Evaluate a configuration file and a test file and call a test function.
+fn run_test(
+    env: &Environment,
+    distribution_cache: &Arc<DistributionCache>,
+    config_path: &Path,
+    test_path: &Path,
+    name: &str,
+    options: &ConfigTestOptions,
+) -> Result<()> {
+    let mut context = EvaluationContextBuilder::new(env, config_path, options.target_triple)
+        .verbose(options.verbose)
+        .distribution_cache(distribution_cache.clone())
+        .extra_vars(options.extra_vars.clone())
+        .enabled_extras(options.enabled_extras.clone())
+        .resolve_targets(vec![])
+        .into_context()?;
+
+    context.evaluate_files_and_eval(&[config_path, test_path], &format!("{}()", name))?;
+
+    Ok(())
+}
+
+/// Run configuration file tests for a project.
+///
+/// If `test_paths` is empty, files in the project directory ending with
+/// `_test.bzl` are tested.
+///
+/// Returns an error if any test failed.
+pub fn config_test(
+    env: &Environment,
+    project_path: &Path,
+    test_paths: &[PathBuf],
+    options: &ConfigTestOptions,
+) -> Result<()> {
+    let config_path = find_pyoxidizer_config_file_env(project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })?;
+
+    let test_paths = if test_paths.is_empty() {
+        find_test_files(project_path)?
+    } else {
+        test_paths.to_vec()
+    };
+
+    if test_paths.is_empty() {
+        return Err(anyhow!(
+            "no test files found in {}; test files end with {}",
+            project_path.display(),
+            TEST_FILE_SUFFIX
+        ));
+    }
+
+    run_tests(env, &config_path, &test_paths, options)
+}
+
+/// Run tests in test files against a configuration file and print results.
+///
+/// Returns an error if any test failed.
+fn run_tests(
+    env: &Environment,
+    config_path: &Path,
+    test_paths: &[PathBuf],
+    options: &ConfigTestOptions,
+) -> Result<()> {
+    let distribution_cache = Arc::new(DistributionCache::new(Some(
+        &env.python_distributions_dir(),
+    )));
+
+    let mut failures = vec![];
+    let mut count = 0;
+
+    for test_path in test_paths {
+        let source = std::fs::read_to_string(test_path)
+            .with_context(|| format!("reading {}", test_path.display()))?;
+
+        for name in test_function_names(&source) {
+            count += 1;
+            let label = format!("{}::{}", test_path.display(), name);
+
+            match run_test(
+                env,
+                &distribution_cache,
+                config_path,
+                test_path,
+                &name,
+                options,
+            ) {
+                Ok(()) => {
+                    println!("{} ... ok", label);
+                }
+                Err(e) => {
+                    println!("{} ... FAILED", label);
+                    failures.push((label, e));
+                }
+            }
+        }
+    }
+
+    for (label, e) in &failures {
+        println!();
+        println!("{}:", label);
+        for line in format!("{:?}", e).lines() {
+            println!("  {}", line);
+        }
+    }
+
+    println!();
+    println!(
+        "{} passed; {} failed",
+        count - failures.len(),
+        failures.len()
+    );
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} of {} configuration tests failed",
+            failures.len(),
+            count
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{environment::default_target_triple, testutil::*},
+    };
+
+    #[test]
+    fn test_test_function_names() {
+        assert_eq!(
+            test_function_names(
+                "def make_exe():\n    pass\n\n\
+                 def test_exe():\n    def test_nested():\n        pass\n\n\
+                 def test_other ():\n    pass\n\
+                 # def test_comment():\n"
+            ),
+            vec!["test_exe".to_string(), "test_other".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_config_test() -> Result<()> {
+        let env = get_env()?;
+        let temp_dir = env.temporary_directory("pyoxidizer-test")?;
+        let project_path = temp_dir.path();
+
+        std::fs::write(
+            project_path.join("pyoxidizer.bzl"),
+            "def make_value():\n    return VARS.get('VALUE', 'default')\n\n\
+             register_target('value', make_value)\n\
+             resolve_targets()\n",
+        )?;
+        std::fs::write(
+            project_path.join("value_test.bzl"),
+            "def test_value():\n    assert_eq(resolve_target('value'), 'default')\n",
+        )?;
+        std::fs::write(
+            project_path.join("other.bzl"),
+            "def test_fails():\n    assert_true(False)\n",
+        )?;
+
+        let options = ConfigTestOptions {
+            target_triple: default_target_triple(),
+            extra_vars: HashMap::new(),
+            enabled_extras: vec![],
+            verbose: false,
+        };
+
+        let config_path = project_path.join("pyoxidizer.bzl");
+        let test_paths = find_test_files(project_path)?;
+        assert_eq!(test_paths, vec![project_path.join("value_test.bzl")]);
+
+        run_tests(&env, &config_path, &test_paths, &options)?;
+        assert!(run_tests(
+            &env,
+            &config_path,
+            &[project_path.join("other.bzl")],
+            &options
+        )
+        .is_err());
+
+        let options = ConfigTestOptions {
+            extra_vars: [("VALUE".to_string(), Some("other".to_string()))]
+                .into_iter()
+                .collect(),
+            ..options
+        };
+        assert!(run_tests(&env, &config_path, &test_paths, &options).is_err());
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+}
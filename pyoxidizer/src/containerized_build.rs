@@ -0,0 +1,461 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Builds running inside containers.
+
+`pyoxidizer build --containerized-build` runs the build of a target inside a
+container image providing the toolchain for that target, such as a
+manylinux image for Linux targets. This makes builds independent of the
+machine running them.
+
+The project is copied into a new container, `pyoxidizer build` runs inside
+it and the build directory of the target is copied back. Caches are kept in
+a named volume per target triple, so later builds don't download Python
+distributions and crates again.
+
+Only the container runtime CLI is used to talk to the container engine.
+Since nothing is bind mounted from the host, the engine can run on another
+machine, e.g. a Windows host building Windows targets reached via
+`DOCKER_HOST`.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    log::warn,
+    std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+        process::{Command, Stdio},
+    },
+};
+
+/// Environment variable defining the container runtime executable.
+pub const CONTAINER_RUNTIME_ENV: &str = "PYOXIDIZER_CONTAINER_RUNTIME";
+
+/// Container runtime used when `PYOXIDIZER_CONTAINER_RUNTIME` isn't set.
+const DEFAULT_CONTAINER_RUNTIME: &str = "docker";
+
+/// Value of `--var-env` for variables that are undefined on the host.
+///
+/// This environment variable is never set in the container.
+const UNDEFINED_VAR_ENV: &str = "PYOXIDIZER_CONTAINER_UNDEFINED_VAR";
+
+/// Operating system of a container.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContainerPlatform {
+    Linux,
+    Windows,
+}
+
+impl ContainerPlatform {
+    /// Resolve the platform of containers building a target triple.
+    ///
+    /// Apple targets are cross-compiled from Linux containers.
+    pub fn from_target_triple(target_triple: &str) -> Self {
+        if target_triple.contains("-windows-") {
+            Self::Windows
+        } else {
+            Self::Linux
+        }
+    }
+
+    /// Directory the project is copied to.
+    pub fn project_dir(&self) -> &'static str {
+        match self {
+            Self::Linux => "/project",
+            Self::Windows => "C:\\project",
+        }
+    }
+
+    /// Directory the cache volume is mounted at.
+    pub fn cache_dir(&self) -> &'static str {
+        match self {
+            Self::Linux => "/pyoxidizer-cache",
+            Self::Windows => "C:\\pyoxidizer-cache",
+        }
+    }
+
+    fn join(&self, a: &str, b: &str) -> String {
+        match self {
+            Self::Linux => format!("{}/{}", a, b),
+            Self::Windows => format!("{}\\{}", a, b),
+        }
+    }
+}
+
+/// Resolve the default container image building a target triple.
+///
+/// Only Linux targets have default images. Other targets need an image
+/// providing their toolchain, e.g. osxcross for Apple targets.
+pub fn default_container_image(target_triple: &str) -> Option<&'static str> {
+    match target_triple {
+        "x86_64-unknown-linux-gnu"
+        | "x86_64_v2-unknown-linux-gnu"
+        | "x86_64_v3-unknown-linux-gnu" => Some("quay.io/pypa/manylinux2014_x86_64"),
+        "aarch64-unknown-linux-gnu" => Some("quay.io/pypa/manylinux2014_aarch64"),
+        "i686-unknown-linux-gnu" => Some("quay.io/pypa/manylinux2014_i686"),
+        "x86_64-unknown-linux-musl"
+        | "x86_64_v2-unknown-linux-musl"
+        | "x86_64_v3-unknown-linux-musl" => Some("quay.io/pypa/musllinux_1_1_x86_64"),
+        _ => None,
+    }
+}
+
+/// Arguments to `pyoxidizer build` inside the container.
+pub fn container_build_args(
+    target_triple: &str,
+    resolve_targets: Option<&[String]>,
+    extra_vars: &HashMap<String, Option<String>>,
+    enabled_extras: &[String],
+    release: bool,
+    verbose: bool,
+    jobs: usize,
+) -> Vec<String> {
+    let mut args = vec![
+        "build".to_string(),
+        "--target-triple".to_string(),
+        target_triple.to_string(),
+        "--jobs".to_string(),
+        jobs.to_string(),
+    ];
+
+    if release {
+        args.push("--release".to_string());
+    }
+    if verbose {
+        args.push("--verbose".to_string());
+    }
+
+    let mut vars = extra_vars.iter().collect::<Vec<_>>();
+    vars.sort();
+
+    for (name, value) in vars {
+        match value {
+            Some(value) => args.extend(["--var".to_string(), name.clone(), value.clone()]),
+            None => args.extend([
+                "--var-env".to_string(),
+                name.clone(),
+                UNDEFINED_VAR_ENV.to_string(),
+            ]),
+        }
+    }
+
+    for extra in enabled_extras {
+        args.extend(["--enable-extra".to_string(), extra.clone()]);
+    }
+
+    if let Some(targets) = resolve_targets {
+        args.extend(targets.iter().cloned());
+    }
+
+    args
+}
+
+/// Runs builds of a target inside containers.
+#[derive(Clone, Debug)]
+pub struct ContainerizedBuild {
+    /// Container runtime executable, e.g. `docker` or `podman`.
+    pub runtime: String,
+
+    /// Container image to build in.
+    pub image: String,
+
+    /// Rust target triple being built.
+    pub target_triple: String,
+
+    /// Operating system of the container.
+    pub platform: ContainerPlatform,
+
+    /// `pyoxidizer` executable to copy into the container.
+    ///
+    /// If `None`, the image must provide `pyoxidizer` in `PATH`.
+    pub pyoxidizer_exe: Option<PathBuf>,
+}
+
+impl ContainerizedBuild {
+    /// Construct an instance building a target triple.
+    ///
+    /// `image` defaults to [default_container_image]. The running `pyoxidizer`
+    /// executable is copied into Linux containers when running on Linux.
+    pub fn new(target_triple: &str, image: Option<&str>) -> Result<Self> {
+        let image = image
+            .or_else(|| default_container_image(target_triple))
+            .ok_or_else(|| {
+                anyhow!(
+                    "no default container image for {}; specify one with --container-image",
+                    target_triple
+                )
+            })?;
+
+        let runtime = std::env::var(CONTAINER_RUNTIME_ENV)
+            .unwrap_or_else(|_| DEFAULT_CONTAINER_RUNTIME.to_string());
+
+        let platform = ContainerPlatform::from_target_triple(target_triple);
+
+        let pyoxidizer_exe = if cfg!(target_os = "linux") && platform == ContainerPlatform::Linux {
+            Some(std::env::current_exe().context("resolving pyoxidizer executable")?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            runtime,
+            image: image.to_string(),
+            target_triple: target_triple.to_string(),
+            platform,
+            pyoxidizer_exe,
+        })
+    }
+
+    /// Name of the volume holding caches.
+    ///
+    /// Each target triple has its own volume since images differ.
+    pub fn cache_volume(&self) -> String {
+        format!("pyoxidizer-cache-{}", self.target_triple)
+    }
+
+    /// Path of `pyoxidizer` inside the container.
+    fn container_pyoxidizer_exe(&self) -> &'static str {
+        if self.pyoxidizer_exe.is_some() {
+            "/usr/local/bin/pyoxidizer"
+        } else {
+            "pyoxidizer"
+        }
+    }
+
+    /// Arguments to the container runtime creating the build container.
+    pub fn create_args(&self, name: &str, build_args: &[String]) -> Vec<String> {
+        let cache_dir = self.platform.cache_dir();
+
+        let mut args = vec![
+            "create".to_string(),
+            "--name".to_string(),
+            name.to_string(),
+            "--volume".to_string(),
+            format!("{}:{}", self.cache_volume(), cache_dir),
+            "--env".to_string(),
+            format!("PYOXIDIZER_CACHE_DIR={}", cache_dir),
+            "--env".to_string(),
+            format!("CARGO_HOME={}", self.platform.join(cache_dir, "cargo")),
+            "--workdir".to_string(),
+            self.platform.project_dir().to_string(),
+            self.image.clone(),
+            self.container_pyoxidizer_exe().to_string(),
+        ];
+
+        args.extend(build_args.iter().cloned());
+
+        args
+    }
+
+    fn runtime_command(&self) -> Command {
+        let mut command = Command::new(&self.runtime);
+        command.stdin(Stdio::null());
+        command
+    }
+
+    fn run(&self, args: &[&str]) -> Result<()> {
+        let status = self
+            .runtime_command()
+            .args(args)
+            .status()
+            .with_context(|| format!("running {}", self.runtime))?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!("{} {} failed: {}", self.runtime, args[0], status))
+        }
+    }
+
+    /// Copy the project into the container, excluding its build directory.
+    fn copy_project(&self, name: &str, project_path: &Path) -> Result<()> {
+        let mut child = self
+            .runtime_command()
+            .args([
+                "cp",
+                "-",
+                &format!("{}:{}", name, self.platform.project_dir()),
+            ])
+            .stdin(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("running {}", self.runtime))?;
+
+        {
+            let mut builder = tar::Builder::new(child.stdin.take().unwrap());
+            builder.follow_symlinks(false);
+
+            for entry in std::fs::read_dir(project_path)? {
+                let entry = entry?;
+
+                if entry.file_name() == "build" {
+                    continue;
+                }
+
+                if entry.file_type()?.is_dir() {
+                    builder.append_dir_all(entry.file_name(), entry.path())?;
+                } else {
+                    builder.append_path_with_name(entry.path(), entry.file_name())?;
+                }
+            }
+
+            builder.finish()?;
+        }
+
+        let status = child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!("copying project into container failed: {}", status))
+        }
+    }
+
+    /// Build a project inside a new container.
+    ///
+    /// The build directory of the target triple is copied to the build
+    /// directory of the project. The container is removed afterwards.
+    pub fn build(&self, project_path: &Path, build_args: &[String]) -> Result<()> {
+        let name = format!(
+            "pyoxidizer-build-{}-{}",
+            self.target_triple,
+            std::process::id()
+        );
+
+        warn!(
+            "building {} in container image {}",
+            self.target_triple, self.image
+        );
+
+        let create_args = self.create_args(&name, build_args);
+        self.run(&create_args.iter().map(|x| x.as_str()).collect::<Vec<_>>())?;
+
+        let res = self.build_in_container(&name, project_path);
+
+        if let Err(e) = self.run(&["rm", "--force", &name]) {
+            warn!("unable to remove container {}: {:?}", name, e);
+        }
+
+        res
+    }
+
+    fn build_in_container(&self, name: &str, project_path: &Path) -> Result<()> {
+        self.copy_project(name, project_path)?;
+
+        if let Some(exe) = &self.pyoxidizer_exe {
+            self.run(&[
+                "cp",
+                &exe.display().to_string(),
+                &format!("{}:{}", name, self.container_pyoxidizer_exe()),
+            ])?;
+        }
+
+        self.run(&["start", "--attach", name])?;
+
+        let dest_dir = project_path.join("build").join(&self.target_triple);
+        std::fs::create_dir_all(&dest_dir)?;
+
+        let build_dir = self.platform.join(
+            &self.platform.join(self.platform.project_dir(), "build"),
+            &self.target_triple,
+        );
+
+        // Trailing `.` copies the content of the directory.
+        self.run(&[
+            "cp",
+            &format!("{}:{}", name, self.platform.join(&build_dir, ".")),
+            &dest_dir.display().to_string(),
+        ])?;
+
+        warn!("build artifacts copied to {}", dest_dir.display());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_container_image() {
+        assert_eq!(
+            default_container_image("x86_64-unknown-linux-gnu"),
+            Some("quay.io/pypa/manylinux2014_x86_64")
+        );
+        assert_eq!(
+            default_container_image("x86_64-unknown-linux-musl"),
+            Some("quay.io/pypa/musllinux_1_1_x86_64")
+        );
+        assert_eq!(default_container_image("aarch64-apple-darwin"), None);
+        assert!(ContainerizedBuild::new("x86_64-pc-windows-msvc", None).is_err());
+    }
+
+    #[test]
+    fn test_container_build_args() {
+        let vars = HashMap::from([
+            ("b".to_string(), None),
+            ("a".to_string(), Some("1".to_string())),
+        ]);
+
+        assert_eq!(
+            container_build_args(
+                "x86_64-unknown-linux-gnu",
+                Some(&["exe".to_string()]),
+                &vars,
+                &["tls".to_string()],
+                true,
+                false,
+                2,
+            ),
+            vec![
+                "build",
+                "--target-triple",
+                "x86_64-unknown-linux-gnu",
+                "--jobs",
+                "2",
+                "--release",
+                "--var",
+                "a",
+                "1",
+                "--var-env",
+                "b",
+                UNDEFINED_VAR_ENV,
+                "--enable-extra",
+                "tls",
+                "exe",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_create_args() {
+        let build = ContainerizedBuild {
+            runtime: "docker".to_string(),
+            image: "image".to_string(),
+            target_triple: "x86_64-pc-windows-msvc".to_string(),
+            platform: ContainerPlatform::Windows,
+            pyoxidizer_exe: None,
+        };
+
+        assert_eq!(
+            build.create_args("name", &["build".to_string()]),
+            vec![
+                "create",
+                "--name",
+                "name",
+                "--volume",
+                "pyoxidizer-cache-x86_64-pc-windows-msvc:C:\\pyoxidizer-cache",
+                "--env",
+                "PYOXIDIZER_CACHE_DIR=C:\\pyoxidizer-cache",
+                "--env",
+                "CARGO_HOME=C:\\pyoxidizer-cache\\cargo",
+                "--workdir",
+                "C:\\project",
+                "image",
+                "pyoxidizer",
+                "build",
+            ]
+        );
+    }
+}
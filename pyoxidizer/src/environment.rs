@@ -5,7 +5,9 @@
 //! Resolve details about the PyOxidizer execution environment.
 
 use {
-    crate::{project_layout::PyembedLocation, py_packaging::distribution::AppleSdkInfo},
+    crate::{
+        build_inputs, project_layout::PyembedLocation, py_packaging::distribution::AppleSdkInfo,
+    },
     anyhow::{anyhow, Context, Result},
     apple_sdk::{AppleSdk, ParsedSdk, SdkSearch, SdkSearchLocation, SdkSorting},
     log::{info, warn},
@@ -278,7 +280,7 @@ impl Environment {
     pub fn new() -> Result<Self> {
         let pyoxidizer_source = PyOxidizerSource::default();
 
-        let cache_dir = if let Ok(p) = std::env::var("PYOXIDIZER_CACHE_DIR") {
+        let cache_dir = if let Some(p) = build_inputs::env_var("PYOXIDIZER_CACHE_DIR") {
             PathBuf::from(p)
         } else if let Some(cache_dir) = dirs::cache_dir() {
             cache_dir.join("pyoxidizer")
@@ -286,7 +288,7 @@ impl Environment {
             dirs::home_dir().ok_or_else(|| anyhow!("could not resolve home dir as part of resolving PyOxidizer cache directory"))?.join(".pyoxidizer").join("cache")
         };
 
-        let managed_rust = std::env::var("PYOXIDIZER_SYSTEM_RUST").is_err();
+        let managed_rust = build_inputs::env_var("PYOXIDIZER_SYSTEM_RUST").is_none();
 
         Ok(Self {
             pyoxidizer_source,
@@ -378,6 +380,8 @@ impl Environment {
                 self.system_rust_environment()?
             };
 
+            build_inputs::record_tool("rustc", &rust_env.rust_version.short_version_string);
+
             cached.replace(rust_env);
         }
 
@@ -395,7 +399,7 @@ impl Environment {
     /// Not exposed as public because we want all consumers of rustc to go
     /// through validation logic in [self.rust_environment()].
     fn rustc_exe(&self) -> which::Result<Option<PathBuf>> {
-        if let Some(v) = std::env::var_os("RUSTC") {
+        if let Some(v) = build_inputs::env_var("RUSTC") {
             let p = PathBuf::from(v);
 
             if p.exists() {
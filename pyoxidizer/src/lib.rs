@@ -11,14 +11,25 @@ distribution and embedding it in a larger binary, oftentimes an executable.
 This library exposes that functionality to other tools.
 */
 
+pub mod build_inputs;
+pub mod compat_testing;
+pub mod config_testing;
+pub mod containerized_build;
 mod default_python_distributions;
 pub mod environment;
 pub mod licensing;
+pub mod log_capture;
+pub mod metrics;
 pub mod project_building;
+pub mod project_installing;
 pub mod project_layout;
 pub mod projectmgmt;
 pub mod py_packaging;
+pub mod python_distribution_channels;
 pub mod python_distributions;
+pub mod resource_packing;
+pub mod rpc_server;
+pub mod script_metadata;
 pub mod starlark;
 
 #[cfg(test)]
@@ -0,0 +1,84 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Capturing log output of individual threads.
+
+When targets are built concurrently, their log output would interleave.
+The CLI logger writes to [CapturingStderr], which buffers the output of
+threads running [capture()] so it can be emitted in one piece later.
+*/
+
+use std::{
+    cell::RefCell,
+    io::{Result, Write},
+};
+
+thread_local! {
+    static CAPTURED: RefCell<Option<Vec<u8>>> = const { RefCell::new(None) };
+}
+
+/// Log destination buffering output of threads capturing it.
+///
+/// Output of other threads is written to stderr.
+pub struct CapturingStderr;
+
+impl Write for CapturingStderr {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let captured = CAPTURED.with(|captured| {
+            if let Some(buffer) = captured.borrow_mut().as_mut() {
+                buffer.extend_from_slice(buf);
+                true
+            } else {
+                false
+            }
+        });
+
+        if captured {
+            Ok(buf.len())
+        } else {
+            std::io::stderr().write(buf)
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        std::io::stderr().flush()
+    }
+}
+
+/// Call a function, capturing output written to [CapturingStderr] by the current thread.
+///
+/// Returns the function's result and the captured output.
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<u8>) {
+    let previous = CAPTURED.with(|captured| captured.replace(Some(vec![])));
+    let res = f();
+    let output = CAPTURED
+        .with(|captured| captured.replace(previous))
+        .unwrap_or_default();
+
+    (res, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture() -> Result<()> {
+        let (res, output) = capture(|| -> Result<u32> {
+            CapturingStderr.write_all(b"outer ")?;
+
+            let (_, inner) = capture(|| CapturingStderr.write_all(b"inner"));
+            assert_eq!(inner, b"inner");
+
+            CapturingStderr.write_all(b"done")?;
+
+            Ok(42)
+        });
+
+        assert_eq!(res?, 42);
+        assert_eq!(output, b"outer done");
+
+        Ok(())
+    }
+}
@@ -27,15 +27,26 @@ is implemented in C. Rust is simply a tool to achieve an end goal, albeit
 a rather effective and powerful tool.
 */
 
+mod build_inputs;
 mod cli;
+mod compat_testing;
+mod config_testing;
+mod containerized_build;
 mod default_python_distributions;
 mod environment;
 mod licensing;
+mod log_capture;
+mod metrics;
 mod project_building;
+mod project_installing;
 mod project_layout;
 mod projectmgmt;
 mod py_packaging;
+mod python_distribution_channels;
 mod python_distributions;
+mod resource_packing;
+mod rpc_server;
+mod script_metadata;
 pub mod starlark;
 #[cfg(test)]
 mod testutil;
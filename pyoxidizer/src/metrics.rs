@@ -0,0 +1,227 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Anonymized metrics describing builds.
+
+Metrics are only collected when requested. `pyoxidizer build --metrics`
+writes them to a local file, which users can attach to bug reports. If the
+`PYOXIDIZER_TELEMETRY_URL` environment variable is set, they are also
+submitted to that URL. Nothing is submitted otherwise.
+
+Metrics never contain paths, target names or other content of the project.
+Targets are identified by the order they were built in.
+*/
+
+use {
+    crate::{environment::default_target_triple, py_packaging::distribution::DistributionCache},
+    anyhow::{Context, Result},
+    serde::Serialize,
+    std::{
+        path::Path,
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// Environment variable holding the URL metrics are submitted to.
+pub const TELEMETRY_URL_ENV: &str = "PYOXIDIZER_TELEMETRY_URL";
+
+/// Time spent in a phase of the build.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PhaseMetrics {
+    /// Name of the phase.
+    pub name: String,
+    /// Duration of the phase, in milliseconds.
+    pub duration_ms: u64,
+}
+
+/// Metrics of a built target.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TargetMetrics {
+    /// Duration of the build of the target, in milliseconds.
+    pub duration_ms: u64,
+    /// Number of files in the output directory of the target.
+    pub output_files: u64,
+    /// Size of the files in the output directory of the target, in bytes.
+    pub output_bytes: u64,
+}
+
+/// Usage of a cache.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CacheMetrics {
+    /// Number of lookups served from the cache.
+    pub hits: u64,
+    /// Number of lookups not served from the cache.
+    pub misses: u64,
+}
+
+/// Anonymized metrics describing a build.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BuildMetrics {
+    /// Version of PyOxidizer performing the build.
+    pub pyoxidizer_version: String,
+    /// Rust target triple PyOxidizer is running on.
+    pub host_triple: String,
+    /// Rust target triple being built for.
+    pub target_triple: String,
+    /// Whether a release build was performed.
+    pub release: bool,
+    /// Number of targets built concurrently.
+    pub jobs: usize,
+    /// Whether the build succeeded.
+    pub success: bool,
+    /// Duration of the whole build, in milliseconds.
+    pub duration_ms: u64,
+    /// Phases of the build, in the order they completed.
+    pub phases: Vec<PhaseMetrics>,
+    /// Built targets, in the order they completed.
+    pub targets: Vec<TargetMetrics>,
+    /// Lookups of resolved Python distributions.
+    pub distribution_cache: CacheMetrics,
+}
+
+impl BuildMetrics {
+    /// Write metrics to a file as JSON.
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_vec_pretty(self)?;
+
+        std::fs::write(path, data).with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// Submit metrics as JSON to a URL with an HTTP POST request.
+    pub fn submit(&self, url: &str) -> Result<()> {
+        tugger_common::http::get_http_client()?
+            .post(url)
+            .header("content-type", "application/json")
+            .body(serde_json::to_string(self)?)
+            .send()?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Obtain the URL metrics are submitted to, if telemetry is enabled.
+pub fn telemetry_url() -> Option<String> {
+    std::env::var(TELEMETRY_URL_ENV)
+        .ok()
+        .filter(|url| !url.is_empty())
+}
+
+/// Collects metrics while a build is running.
+///
+/// Instances are shared by threads building targets concurrently.
+#[derive(Debug)]
+pub struct MetricsRecorder {
+    start: Instant,
+    metrics: Mutex<BuildMetrics>,
+}
+
+impl MetricsRecorder {
+    pub fn new(target_triple: &str, release: bool, jobs: usize) -> Self {
+        Self {
+            start: Instant::now(),
+            metrics: Mutex::new(BuildMetrics {
+                pyoxidizer_version: env!("CARGO_PKG_VERSION").to_string(),
+                host_triple: default_target_triple().to_string(),
+                target_triple: target_triple.to_string(),
+                release,
+                jobs,
+                success: false,
+                duration_ms: 0,
+                phases: vec![],
+                targets: vec![],
+                distribution_cache: CacheMetrics::default(),
+            }),
+        }
+    }
+
+    /// Record the duration of a build phase.
+    pub fn record_phase(&self, name: &str, duration: Duration) {
+        self.metrics.lock().unwrap().phases.push(PhaseMetrics {
+            name: name.to_string(),
+            duration_ms: duration.as_millis() as u64,
+        });
+    }
+
+    /// Record a built target, measuring the files in its output directory.
+    pub fn record_target(&self, duration: Duration, output_path: &Path) {
+        let mut output_files = 0;
+        let mut output_bytes = 0;
+
+        for entry in walkdir::WalkDir::new(output_path)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+        {
+            if let Ok(metadata) = entry.metadata() {
+                if metadata.is_file() {
+                    output_files += 1;
+                    output_bytes += metadata.len();
+                }
+            }
+        }
+
+        self.metrics.lock().unwrap().targets.push(TargetMetrics {
+            duration_ms: duration.as_millis() as u64,
+            output_files,
+            output_bytes,
+        });
+    }
+
+    /// Obtain the collected metrics once the build has finished.
+    pub fn finish(&self, success: bool, distribution_cache: &DistributionCache) -> BuildMetrics {
+        let mut metrics = self.metrics.lock().unwrap().clone();
+
+        metrics.success = success;
+        metrics.duration_ms = self.start.elapsed().as_millis() as u64;
+        metrics.distribution_cache = distribution_cache.metrics();
+
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        std::fs::write(temp_dir.path().join("a"), b"foo")?;
+        std::fs::create_dir(temp_dir.path().join("dir"))?;
+        std::fs::write(temp_dir.path().join("dir").join("b"), b"barbaz")?;
+
+        let recorder = MetricsRecorder::new("x86_64-unknown-linux-gnu", true, 2);
+        recorder.record_phase("evaluate", Duration::from_millis(5));
+        recorder.record_target(Duration::from_millis(10), temp_dir.path());
+
+        let metrics = recorder.finish(true, &DistributionCache::new(None));
+
+        assert!(metrics.success);
+        assert_eq!(metrics.target_triple, "x86_64-unknown-linux-gnu");
+        assert_eq!(
+            metrics.phases,
+            vec![PhaseMetrics {
+                name: "evaluate".to_string(),
+                duration_ms: 5,
+            }]
+        );
+        assert_eq!(
+            metrics.targets,
+            vec![TargetMetrics {
+                duration_ms: 10,
+                output_files: 2,
+                output_bytes: 9,
+            }]
+        );
+        assert_eq!(metrics.distribution_cache, CacheMetrics::default());
+
+        let path = temp_dir.path().join("metrics.json");
+        metrics.write_json(&path)?;
+        let value: serde_json::Value = serde_json::from_slice(&std::fs::read(&path)?)?;
+        assert_eq!(value["targets"][0]["output_bytes"], 9);
+
+        Ok(())
+    }
+}
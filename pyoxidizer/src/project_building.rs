@@ -4,11 +4,12 @@
 
 use {
     crate::{
+        build_inputs,
         environment::{canonicalize_path, Environment, RustEnvironment},
         licensing::{licenses_from_cargo_manifest, log_licensing_info},
-        project_layout::initialize_project,
+        project_layout::{initialize_project, WindowsResources},
         py_packaging::{
-            binary::{LibpythonLinkMode, PythonBinaryBuilder},
+            binary::{LibpythonLinkMode, PythonBinaryBuilder, WindowsCrtLinkage},
             distribution::AppleSdkInfo,
             embedding::{EmbeddedPythonContext, DEFAULT_PYTHON_CONFIG_FILENAME},
         },
@@ -59,7 +60,7 @@ pub fn find_pyoxidizer_config_file(start_dir: &Path) -> Option<PathBuf> {
 /// If none of the above find a config file, we fall back to traversing ancestors
 /// of `start_dir`.
 pub fn find_pyoxidizer_config_file_env(start_dir: &Path) -> Option<PathBuf> {
-    if let Ok(path) = std::env::var("PYOXIDIZER_CONFIG") {
+    if let Some(path) = build_inputs::env_var("PYOXIDIZER_CONFIG") {
         warn!(
             "using PyOxidizer config file from PYOXIDIZER_CONFIG: {}",
             path
@@ -67,7 +68,7 @@ pub fn find_pyoxidizer_config_file_env(start_dir: &Path) -> Option<PathBuf> {
         return Some(PathBuf::from(path));
     }
 
-    if let Ok(path) = std::env::var("OUT_DIR") {
+    if let Some(path) = build_inputs::env_var("OUT_DIR") {
         warn!("looking for config file in ancestry of {}", path);
         let res = find_pyoxidizer_config_file(Path::new(&path));
         if res.is_some() {
@@ -97,6 +98,8 @@ impl BuildEnvironment {
         pyo3_config_path: impl AsRef<Path>,
         libpython_link_mode: LibpythonLinkMode,
         apple_sdk_info: Option<&AppleSdkInfo>,
+        crt_linkage: WindowsCrtLinkage,
+        extra_rust_flags: &[String],
     ) -> Result<Self> {
         let rust_environment = env
             .ensure_rust_toolchain(Some(target_triple))
@@ -165,28 +168,29 @@ impl BuildEnvironment {
 
         let mut rust_flags = vec![];
 
-        // Windows standalone_static distributions require the non-DLL CRT.
-        // This requires telling Rust to use the static CRT.
-        //
-        // In addition, these distributions also have some symbols defined in
+        // Windows standalone_static distributions require the non-DLL CRT and
+        // standalone_dynamic distributions require the DLL CRT. We always tell
+        // Rust which CRT to use so the executable matches the distribution
+        // regardless of the toolchain's default.
+        if target_triple.contains("-windows-") {
+            rust_flags.extend([
+                "-C".to_string(),
+                format!("target-feature={}", crt_linkage.rustc_target_feature()),
+            ]);
+        }
+
+        // Windows standalone_static distributions also have some symbols defined in
         // multiple object files. See https://github.com/indygreg/python-build-standalone/issues/71.
         // This can lead to a linker error unless we suppress it via /FORCE:MULTIPLE.
         // This workaround is not ideal.
         // TODO remove /FORCE:MULTIPLE once the distributions eliminate duplicate
         // symbols.
         if target_triple.contains("-windows-") && libpython_link_mode == LibpythonLinkMode::Static {
-            rust_flags.extend(
-                [
-                    "-C".to_string(),
-                    "target-feature=+crt-static".to_string(),
-                    "-C".to_string(),
-                    "link-args=/FORCE:MULTIPLE".to_string(),
-                ]
-                .iter()
-                .map(|x| x.to_string()),
-            );
+            rust_flags.extend(["-C".to_string(), "link-args=/FORCE:MULTIPLE".to_string()]);
         }
 
+        rust_flags.extend(extra_rust_flags.iter().cloned());
+
         if !rust_flags.is_empty() {
             let extra_flags = rust_flags.join(" ");
 
@@ -215,6 +219,8 @@ impl BuildEnvironment {
 
     /// Resolve the full set of environment variables to use in build processes.
     pub fn environment_variables(&self) -> HashMap<String, String> {
+        build_inputs::record_env_prefixes(build_inputs::CARGO_ENV_PREFIXES);
+
         let mut envs = std::env::vars().collect::<HashMap<_, _>>();
 
         for (k, v) in &self.extra_environment_vars {
@@ -241,6 +247,14 @@ pub fn cargo_features(exe: &dyn PythonBinaryBuilder) -> Vec<&str> {
         res.push("global-allocator-snmalloc");
         res.push("allocator-snmalloc");
     }
+    if exe.requires_config_overrides() {
+        res.push("config-overrides");
+    }
+    if exe.requires_encryption() {
+        res.push("encryption");
+    }
+
+    res.extend(exe.cargo_features().iter().map(|x| x.as_str()));
 
     res
 }
@@ -287,16 +301,25 @@ pub fn build_executable_with_rust_project<'a>(
         .write_files(artifacts_path)
         .context("writing embedded python context files")?;
 
-    let build_env = BuildEnvironment::new(
+    let mut build_env = BuildEnvironment::new(
         env,
         exe.target_triple(),
         artifacts_path,
         embedded_data.pyo3_config_path(artifacts_path),
         exe.libpython_link_mode(),
         exe.apple_sdk_info(),
+        exe.windows_crt_linkage(),
+        exe.cargo_rustflags(),
     )
     .context("resolving build environment")?;
 
+    // Cargo profile settings are passed via environment variables so they apply
+    // without modifying the project's Cargo.toml.
+    build_env.extra_environment_vars.extend(
+        exe.cargo_profile()
+            .environment_variables(if release { "release" } else { "dev" }),
+    );
+
     warn!(
         "building with Rust {}",
         build_env.rust_environment.rust_version.semver
@@ -381,8 +404,17 @@ pub fn build_executable_with_rust_project<'a>(
         return Err(anyhow!("{} does not exist", exe_path.display()));
     }
 
-    let exe_data =
+    let mut exe_data =
         std::fs::read(&exe_path).with_context(|| format!("reading {}", exe_path.display()))?;
+
+    if let Some(overlay) = &embedded_data.executable_overlay {
+        warn!(
+            "appending {} bytes of resources data to executable",
+            overlay.len()
+        );
+        python_packed_resources::write_executable_overlay(exe_data.len(), overlay, &mut exe_data)
+            .context("appending resources data to executable")?;
+    }
     let exe_name = exe_path.file_name().unwrap().to_string_lossy().to_string();
 
     // Construct unified licensing info by combining the Python licensing metadata
@@ -441,6 +473,12 @@ pub fn build_python_executable<'a>(
         None,
         &[],
         exe.windows_subsystem(),
+        &WindowsResources {
+            version_info: exe.windows_version_info().clone(),
+            icon_path: exe.windows_icon_path().map(|x| x.to_path_buf()),
+            long_path_aware: exe.windows_long_path_aware(),
+            dpi_awareness: exe.windows_dpi_awareness(),
+        },
     )
     .context("initializing project")?;
 
@@ -482,6 +520,7 @@ pub fn build_pyembed_artifacts(
     artifacts_path: &Path,
     resolve_target: Option<&str>,
     extra_vars: HashMap<String, Option<String>>,
+    enabled_extras: Vec<String>,
     target_triple: &str,
     release: bool,
     verbose: bool,
@@ -497,6 +536,7 @@ pub fn build_pyembed_artifacts(
     let mut context: EvaluationContext =
         EvaluationContextBuilder::new(env, config_path, target_triple.to_string())
             .extra_vars(extra_vars)
+            .enabled_extras(enabled_extras)
             .release(release)
             .verbose(verbose)
             .resolve_target_optional(resolve_target)
@@ -565,6 +605,7 @@ pub fn run_from_build(
     build_script: &str,
     resolve_target: Option<&str>,
     extra_vars: HashMap<String, Option<String>>,
+    enabled_extras: Vec<String>,
 ) -> Result<()> {
     // Adding our our rerun-if-changed lines will overwrite the default, so
     // we need to emit the build script name explicitly.
@@ -603,6 +644,7 @@ pub fn run_from_build(
         &dest_dir,
         resolve_target,
         extra_vars,
+        enabled_extras,
         &target,
         profile == "release",
         false,
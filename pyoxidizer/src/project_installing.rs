@@ -0,0 +1,230 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Install the files of built targets to a prefix.
+
+This powers `pyoxidizer install`. Files are installed like `make install`
+would: to a prefix, optionally staged in a `DESTDIR`. The installed paths
+are recorded in an uninstall manifest.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    log::warn,
+    simple_file_manifest::FileManifest,
+    std::path::{Component, Path, PathBuf},
+};
+
+/// Permissions of installed directories and executable files.
+#[cfg(unix)]
+const EXECUTABLE_MODE: u32 = 0o755;
+
+/// Permissions of installed non-executable files.
+#[cfg(unix)]
+const FILE_MODE: u32 = 0o644;
+
+/// Resolve the directory files are written to.
+///
+/// With a `destdir`, this is `prefix` re-rooted under `destdir`, like
+/// `$(DESTDIR)$(prefix)` in makefiles.
+pub fn install_root(prefix: &Path, destdir: Option<&Path>) -> PathBuf {
+    if let Some(destdir) = destdir {
+        prefix
+            .components()
+            .filter(|c| !matches!(c, Component::Prefix(_) | Component::RootDir))
+            .fold(destdir.to_path_buf(), |path, c| path.join(c))
+    } else {
+        prefix.to_path_buf()
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .with_context(|| format!("setting permissions of {}", path.display()))
+}
+
+/// Create a directory and missing ancestors with installed directory permissions.
+///
+/// Permissions of existing directories are left alone.
+fn create_directory(path: &Path) -> Result<()> {
+    let missing = path
+        .ancestors()
+        .take_while(|p| !p.as_os_str().is_empty() && !p.exists())
+        .collect::<Vec<_>>();
+
+    std::fs::create_dir_all(path).with_context(|| format!("creating {}", path.display()))?;
+
+    #[cfg(unix)]
+    for dir in missing {
+        set_mode(dir, EXECUTABLE_MODE)?;
+    }
+    #[cfg(not(unix))]
+    let _ = missing;
+
+    Ok(())
+}
+
+/// Install the files of a manifest to a prefix.
+///
+/// Existing files are replaced. Returns the installed paths relative to
+/// the root of the filesystem, i.e. under `prefix` and without `destdir`.
+pub fn install_file_manifest(
+    manifest: &FileManifest,
+    prefix: &Path,
+    destdir: Option<&Path>,
+) -> Result<Vec<PathBuf>> {
+    let root = install_root(prefix, destdir);
+
+    warn!("installing files to {}", root.display());
+
+    let mut installed = vec![];
+
+    for (rel_path, entry) in manifest.iter_entries() {
+        let dest_path = root.join(rel_path);
+
+        if let Some(parent) = dest_path.parent() {
+            create_directory(parent)?;
+        }
+
+        // Don't write through symlinks or into files that may be in use.
+        if dest_path.symlink_metadata().is_ok() {
+            std::fs::remove_file(&dest_path)
+                .with_context(|| format!("removing {}", dest_path.display()))?;
+        }
+
+        entry
+            .write_to_path(&dest_path)
+            .with_context(|| format!("installing {}", dest_path.display()))?;
+
+        #[cfg(unix)]
+        if entry.link_target().is_none() {
+            set_mode(
+                &dest_path,
+                if entry.is_executable() {
+                    EXECUTABLE_MODE
+                } else {
+                    FILE_MODE
+                },
+            )?;
+        }
+
+        installed.push(prefix.join(rel_path));
+    }
+
+    Ok(installed)
+}
+
+/// Write an uninstall manifest listing installed paths.
+///
+/// The manifest lists one path per line, like CMake's `install_manifest.txt`.
+/// e.g. `xargs rm < install_manifest.txt` uninstalls the files.
+pub fn write_uninstall_manifest(path: &Path, installed: &[PathBuf]) -> Result<()> {
+    let mut content = String::new();
+
+    for p in installed {
+        let s = p
+            .to_str()
+            .ok_or_else(|| anyhow!("installed path is not valid UTF-8: {}", p.display()))?;
+
+        if s.contains('\n') {
+            return Err(anyhow!("installed path contains a newline: {}", s));
+        }
+
+        content.push_str(s);
+        content.push('\n');
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("creating {}", parent.display()))?;
+    }
+
+    std::fs::write(path, content).with_context(|| format!("writing {}", path.display()))?;
+
+    warn!("wrote uninstall manifest to {}", path.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, simple_file_manifest::FileEntry};
+
+    #[test]
+    fn test_install_root() {
+        assert_eq!(
+            install_root(Path::new("/usr/local"), None),
+            PathBuf::from("/usr/local")
+        );
+        assert_eq!(
+            install_root(Path::new("/usr/local"), Some(Path::new("/tmp/stage"))),
+            PathBuf::from("/tmp/stage/usr/local")
+        );
+        assert_eq!(
+            install_root(Path::new("opt"), Some(Path::new("stage"))),
+            PathBuf::from("stage/opt")
+        );
+    }
+
+    #[test]
+    fn test_install_file_manifest() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let mut manifest = FileManifest::default();
+        manifest.add_file_entry("bin/myapp", FileEntry::new_from_data(b"exe".to_vec(), true))?;
+        manifest.add_file_entry(
+            "lib/foo/__init__.py",
+            FileEntry::new_from_data(b"foo".to_vec(), false),
+        )?;
+
+        let destdir = td.path().join("stage");
+        let prefix = Path::new("/usr/local");
+
+        // Existing files are replaced.
+        std::fs::create_dir_all(destdir.join("usr/local/bin"))?;
+        std::fs::write(destdir.join("usr/local/bin/myapp"), b"old")?;
+
+        let installed = install_file_manifest(&manifest, prefix, Some(&destdir))?;
+
+        assert_eq!(
+            installed,
+            vec![
+                PathBuf::from("/usr/local/bin/myapp"),
+                PathBuf::from("/usr/local/lib/foo/__init__.py"),
+            ]
+        );
+        assert_eq!(std::fs::read(destdir.join("usr/local/bin/myapp"))?, b"exe");
+        assert_eq!(
+            std::fs::read(destdir.join("usr/local/lib/foo/__init__.py"))?,
+            b"foo"
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = |p: &str| -> Result<u32> {
+                Ok(std::fs::metadata(destdir.join(p))?.permissions().mode() & 0o777)
+            };
+
+            assert_eq!(mode("usr/local/bin/myapp")?, 0o755);
+            assert_eq!(mode("usr/local/lib/foo/__init__.py")?, 0o644);
+            assert_eq!(mode("usr/local/lib/foo")?, 0o755);
+        }
+
+        let manifest_path = td.path().join("install_manifest.txt");
+        write_uninstall_manifest(&manifest_path, &installed)?;
+        assert_eq!(
+            std::fs::read_to_string(&manifest_path)?,
+            "/usr/local/bin/myapp\n/usr/local/lib/foo/__init__.py\n"
+        );
+
+        Ok(())
+    }
+}
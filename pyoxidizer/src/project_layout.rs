@@ -5,7 +5,10 @@
 //! Handle file layout of PyOxidizer projects.
 
 use {
-    crate::environment::{PyOxidizerSource, BUILD_GIT_COMMIT, PYOXIDIZER_VERSION},
+    crate::{
+        environment::{PyOxidizerSource, BUILD_GIT_COMMIT, PYOXIDIZER_VERSION},
+        py_packaging::binary::WindowsDpiAwareness,
+    },
     anyhow::{anyhow, Context, Result},
     handlebars::Handlebars,
     once_cell::sync::Lazy,
@@ -54,6 +57,12 @@ static HANDLEBARS: Lazy<Handlebars<'static>> = Lazy::new(|| {
             include_str!("templates/new-pyoxidizer.bzl.hbs"),
         )
         .unwrap();
+    handlebars
+        .register_template_string(
+            "script-pyoxidizer.bzl",
+            include_str!("templates/script-pyoxidizer.bzl.hbs"),
+        )
+        .unwrap();
 
     handlebars
 });
@@ -77,6 +86,89 @@ struct PythonDistribution {
     sha256: String,
 }
 
+#[derive(Serialize)]
+struct VersionInfoString {
+    name: String,
+    value: String,
+}
+
+#[derive(Serialize)]
+struct WindowsVersionInfo {
+    file_version: String,
+    product_version: String,
+    strings: Vec<VersionInfoString>,
+}
+
+impl WindowsVersionInfo {
+    fn new(strings: &BTreeMap<String, String>) -> Self {
+        Self {
+            file_version: rc_version(strings.get("FileVersion")),
+            product_version: rc_version(
+                strings
+                    .get("ProductVersion")
+                    .or_else(|| strings.get("FileVersion")),
+            ),
+            strings: strings
+                .iter()
+                .map(|(name, value)| VersionInfoString {
+                    name: name.clone(),
+                    value: value.replace('"', "\"\""),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Windows resources to compile into the executable of a project.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WindowsResources {
+    /// `VS_VERSIONINFO` strings.
+    ///
+    /// An empty map means no version info resource is defined.
+    pub version_info: BTreeMap<String, String>,
+
+    /// Path of an `.ico` file to use as the application icon.
+    pub icon_path: Option<PathBuf>,
+
+    /// Whether the application manifest declares long path awareness.
+    pub long_path_aware: bool,
+
+    /// DPI awareness declared by the application manifest.
+    pub dpi_awareness: WindowsDpiAwareness,
+}
+
+impl Default for WindowsResources {
+    fn default() -> Self {
+        Self {
+            version_info: BTreeMap::new(),
+            icon_path: None,
+            long_path_aware: true,
+            dpi_awareness: WindowsDpiAwareness::Unaware,
+        }
+    }
+}
+
+/// Convert a version string to the 4 comma delimited integers used by `VERSIONINFO`.
+///
+/// Leading numeric components are retained and missing components are 0.
+fn rc_version(version: Option<&String>) -> String {
+    let mut parts = version
+        .map(|v| {
+            v.split('.')
+                .map_while(|part| u16::from_str(part).ok())
+                .take(4)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    parts.resize(4, 0);
+
+    parts
+        .iter()
+        .map(|part| part.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 #[derive(Serialize)]
 struct TemplateData {
     pyoxidizer_version: Option<String>,
@@ -88,8 +180,16 @@ struct TemplateData {
 
     python_distributions: Vec<PythonDistribution>,
     program_name: Option<String>,
+    windows_version_info: Option<WindowsVersionInfo>,
+    windows_icon: Option<String>,
+    windows_long_path_aware: bool,
+    windows_dpi_aware: Option<String>,
+    windows_dpi_awareness: Option<String>,
     code: Option<String>,
     pip_install_simple: Vec<String>,
+    python_version: Option<String>,
+    module: Option<String>,
+    dependencies: Option<String>,
 }
 
 impl TemplateData {
@@ -103,8 +203,16 @@ impl TemplateData {
             pyoxidizer_git_tag: None,
             python_distributions: Vec::new(),
             program_name: None,
+            windows_version_info: None,
+            windows_icon: None,
+            windows_long_path_aware: false,
+            windows_dpi_aware: None,
+            windows_dpi_awareness: None,
             code: None,
             pip_install_simple: Vec::new(),
+            python_version: None,
+            module: None,
+            dependencies: None,
         }
     }
 }
@@ -309,17 +417,113 @@ pub fn write_new_pyoxidizer_config_file(
     Ok(())
 }
 
+/// Writes a PyOxidizer config file packaging a single Python script.
+///
+/// The script must be available as module `module` in `project_dir`.
+/// `dependencies` are installed with `pip install`.
+pub fn write_script_pyoxidizer_config_file(
+    project_dir: &Path,
+    name: &str,
+    module: &str,
+    python_version: &str,
+    dependencies: &[String],
+) -> Result<()> {
+    let path = project_dir.join("pyoxidizer.bzl");
+
+    let mut data = TemplateData::new();
+    data.program_name = Some(name.to_string());
+    data.module = Some(module.to_string());
+    data.python_version = Some(python_version.to_string());
+
+    if !dependencies.is_empty() {
+        // A JSON array of strings is also a valid Starlark list.
+        data.dependencies = Some(serde_json::to_string(dependencies)?);
+    }
+
+    let t = HANDLEBARS.render("script-pyoxidizer.bzl", &data)?;
+
+    let mut fh = std::fs::File::create(path)?;
+    fh.write_all(t.as_bytes())?;
+
+    Ok(())
+}
+
+/// Build a message table mapping event ID 0 to a message consisting of its first insertion string.
+///
+/// Events reported by pyembed's Windows Event Log destination use event ID 0
+/// and the formatted record as insertion string. With this message table in
+/// the executable registered as the event message file, Event Viewer shows
+/// the record as is.
+fn event_log_message_table() -> Vec<u8> {
+    // MESSAGE_RESOURCE_ENTRY holding a NUL terminated UTF-16 string, padded
+    // to a multiple of 4 bytes.
+    let mut text = "%1\0"
+        .encode_utf16()
+        .flat_map(|c| c.to_le_bytes())
+        .collect::<Vec<_>>();
+    text.resize((text.len() + 3) & !3, 0);
+
+    let mut entry = vec![];
+    entry.extend_from_slice(&(text.len() as u16 + 4).to_le_bytes());
+    // MESSAGE_RESOURCE_UNICODE.
+    entry.extend_from_slice(&1u16.to_le_bytes());
+    entry.extend_from_slice(&text);
+
+    // MESSAGE_RESOURCE_DATA with a single MESSAGE_RESOURCE_BLOCK for IDs 0 to 0.
+    let mut data = vec![];
+    data.extend_from_slice(&1u32.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&16u32.to_le_bytes());
+    data.extend_from_slice(&entry);
+
+    data
+}
+
 /// Write an application manifest and corresponding resource file.
 ///
-/// This is used on Windows to allow the built executable to use long paths.
+/// This is used on Windows to allow the built executable to use long paths
+/// and to declare its DPI awareness, as defined by `resources`.
+///
+/// The resource file also defines a message table, so the executable can
+/// serve as event message file of the Windows Event Log event source that
+/// pyembed reports log records with.
+///
+/// If `resources` has version info strings, the resource file also defines a
+/// `VS_VERSIONINFO` resource with them. If it has an icon, the icon is copied
+/// next to the resource file and becomes the application icon.
 ///
 /// Windows 10 version 1607 and above enable long paths by default. So we
 /// might be able to remove this someday. It isn't clear if you get long
 /// paths support if using that version of the Windows SDK or if you have
 /// to be running on a modern Windows version as well.
-pub fn write_application_manifest(project_dir: &Path, program_name: &str) -> Result<()> {
+pub fn write_application_manifest(
+    project_dir: &Path,
+    program_name: &str,
+    resources: &WindowsResources,
+) -> Result<()> {
     let mut data = TemplateData::new();
     data.program_name = Some(program_name.to_string());
+    if !resources.version_info.is_empty() {
+        data.windows_version_info = Some(WindowsVersionInfo::new(&resources.version_info));
+    }
+    data.windows_long_path_aware = resources.long_path_aware;
+    let (dpi_aware, dpi_awareness) = match resources.dpi_awareness {
+        WindowsDpiAwareness::Unaware => (None, None),
+        WindowsDpiAwareness::System => (Some("true"), None),
+        WindowsDpiAwareness::PerMonitor => (Some("true/pm"), None),
+        // dpiAware is for versions of Windows not knowing dpiAwareness.
+        WindowsDpiAwareness::PerMonitorV2 => (Some("true/pm"), Some("PerMonitorV2, PerMonitor")),
+    };
+    data.windows_dpi_aware = dpi_aware.map(|x| x.to_string());
+    data.windows_dpi_awareness = dpi_awareness.map(|x| x.to_string());
+
+    if let Some(icon_path) = &resources.icon_path {
+        let icon_name = format!("{}.ico", program_name);
+        std::fs::copy(icon_path, project_dir.join(&icon_name))
+            .with_context(|| format!("copying icon {}", icon_path.display()))?;
+        data.windows_icon = Some(icon_name);
+    }
 
     let manifest_path = project_dir.join(format!("{}.exe.manifest", program_name));
     let manifest_data = HANDLEBARS.render("exe.manifest", &data)?;
@@ -327,6 +531,10 @@ pub fn write_application_manifest(project_dir: &Path, program_name: &str) -> Res
     let mut fh = std::fs::File::create(&manifest_path)?;
     fh.write_all(manifest_data.as_bytes())?;
 
+    let message_table_path = project_dir.join(format!("{}.msgtable.bin", program_name));
+    println!("writing {}", message_table_path.display());
+    std::fs::write(&message_table_path, event_log_message_table())?;
+
     let rc_path = project_dir.join(format!("{}-manifest.rc", program_name));
     let rc_data = HANDLEBARS.render("application-manifest.rc", &data)?;
     println!("writing {}", rc_path.display());
@@ -414,6 +622,9 @@ pub fn update_new_cargo_toml(path: &Path, pyembed_location: &PyembedLocation) ->
 ///
 /// `windows_subsystem` is the value of the `windows_subsystem` compiler
 /// attribute.
+///
+/// `windows_resources` describes resources to compile into the Windows
+/// executable.
 pub fn initialize_project(
     source: &PyOxidizerSource,
     project_path: &Path,
@@ -421,6 +632,7 @@ pub fn initialize_project(
     code: Option<&str>,
     pip_install: &[&str],
     windows_subsystem: &str,
+    windows_resources: &WindowsResources,
 ) -> Result<()> {
     let status = std::process::Command::new(cargo_exe)
         .arg("init")
@@ -445,7 +657,124 @@ pub fn initialize_project(
         .context("writing main.rs")?;
     write_new_pyoxidizer_config_file(source, &path, name, code, pip_install)
         .context("writing PyOxidizer config file")?;
-    write_application_manifest(&path, name).context("writing application manifest")?;
+    write_application_manifest(&path, name, windows_resources)
+        .context("writing application manifest")?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_application_manifest_version_info() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+
+        write_application_manifest(temp_dir.path(), "myapp", &WindowsResources::default())?;
+        let rc = std::fs::read_to_string(temp_dir.path().join("myapp-manifest.rc"))?;
+        assert!(!rc.contains("VERSIONINFO"));
+
+        let resources = WindowsResources {
+            version_info: BTreeMap::from_iter([
+                ("CompanyName".to_string(), "Acme \"Widgets\"".to_string()),
+                ("FileVersion".to_string(), "1.2.3-rc1".to_string()),
+            ]),
+            ..Default::default()
+        };
+        write_application_manifest(temp_dir.path(), "myapp", &resources)?;
+        let rc = std::fs::read_to_string(temp_dir.path().join("myapp-manifest.rc"))?;
+        assert!(rc.contains("1 VERSIONINFO\nFILEVERSION 1,2,0,0\nPRODUCTVERSION 1,2,0,0\n"));
+        assert!(rc.contains("      VALUE \"CompanyName\", \"Acme \"\"Widgets\"\"\"\n"));
+        assert!(rc.contains("      VALUE \"FileVersion\", \"1.2.3-rc1\"\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_application_manifest_icon_and_settings() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let manifest_path = temp_dir.path().join("myapp.exe.manifest");
+        let rc_path = temp_dir.path().join("myapp-manifest.rc");
+
+        write_application_manifest(temp_dir.path(), "myapp", &WindowsResources::default())?;
+        let manifest = std::fs::read_to_string(&manifest_path)?;
+        assert!(manifest.contains("<ws2:longPathAware>true</ws2:longPathAware>"));
+        assert!(!manifest.contains("dpiAware"));
+        assert!(!std::fs::read_to_string(&rc_path)?.contains("ICON"));
+
+        let icon_path = temp_dir.path().join("source.ico");
+        std::fs::write(&icon_path, b"icon")?;
+
+        let resources = WindowsResources {
+            icon_path: Some(icon_path),
+            long_path_aware: false,
+            dpi_awareness: WindowsDpiAwareness::PerMonitorV2,
+            ..Default::default()
+        };
+        write_application_manifest(temp_dir.path(), "myapp", &resources)?;
+        let manifest = std::fs::read_to_string(&manifest_path)?;
+        assert!(!manifest.contains("longPathAware"));
+        assert!(manifest.contains("<ws1:dpiAware>true/pm</ws1:dpiAware>"));
+        assert!(manifest.contains("<ws2:dpiAwareness>PerMonitorV2, PerMonitor</ws2:dpiAwareness>"));
+        assert!(std::fs::read_to_string(&rc_path)?.contains("1 ICON \"myapp.ico\"\n"));
+        assert_eq!(std::fs::read(temp_dir.path().join("myapp.ico"))?, b"icon");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_application_manifest_message_table() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+
+        write_application_manifest(temp_dir.path(), "myapp", &WindowsResources::default())?;
+        let rc = std::fs::read_to_string(temp_dir.path().join("myapp-manifest.rc"))?;
+        assert!(rc.contains("1 MESSAGETABLE \"myapp.msgtable.bin\"\n"));
+
+        // One block for IDs 0 to 0 with entries at offset 16, then a 12 byte
+        // UTF-16 entry holding "%1" with NUL terminator and padding.
+        let expected = [
+            &[1, 0, 0, 0][..],
+            &[0, 0, 0, 0, 0, 0, 0, 0, 16, 0, 0, 0],
+            &[12, 0, 1, 0],
+            &[b'%', 0, b'1', 0, 0, 0, 0, 0],
+        ]
+        .concat();
+        assert_eq!(
+            std::fs::read(temp_dir.path().join("myapp.msgtable.bin"))?,
+            expected
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_script_pyoxidizer_config_file() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let config_path = temp_dir.path().join("pyoxidizer.bzl");
+
+        write_script_pyoxidizer_config_file(temp_dir.path(), "my-tool", "my_tool", "3.9", &[])?;
+        let config = std::fs::read_to_string(&config_path)?;
+        assert!(config.contains("default_python_distribution(python_version=\"3.9\")"));
+        assert!(config.contains("python_config.run_module = \"my_tool\""));
+        assert!(config.contains("name=\"my-tool\""));
+        assert!(!config.contains("pip_install"));
+
+        write_script_pyoxidizer_config_file(
+            temp_dir.path(),
+            "my-tool",
+            "my_tool",
+            "3.9",
+            &[
+                "rich".to_string(),
+                "foo; python_version < \"3.10\"".to_string(),
+            ],
+        )?;
+        let config = std::fs::read_to_string(&config_path)?;
+        assert!(
+            config.contains("exe.pip_install([\"rich\",\"foo; python_version < \\\"3.10\\\"\"])")
+        );
+
+        Ok(())
+    }
+}
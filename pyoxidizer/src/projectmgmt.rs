@@ -6,19 +6,28 @@
 
 use {
     crate::{
+        build_inputs::BuildInputs,
+        containerized_build::{container_build_args, ContainerizedBuild},
         environment::{canonicalize_path, default_target_triple, Environment, PyOxidizerSource},
         licensing::{licenses_from_cargo_manifest, log_licensing_info},
+        metrics::{self, MetricsRecorder},
         project_building::find_pyoxidizer_config_file_env,
-        project_layout::{initialize_project, write_new_pyoxidizer_config_file},
+        project_installing::{install_file_manifest, install_root, write_uninstall_manifest},
+        project_layout::{
+            initialize_project, write_new_pyoxidizer_config_file,
+            write_script_pyoxidizer_config_file, WindowsResources,
+        },
         py_packaging::{
             distribution::{
                 default_distribution_location, resolve_distribution,
                 resolve_python_distribution_archive, BinaryLibpythonLinkMode, DistributionCache,
-                DistributionFlavor, PythonDistribution,
+                DistributionFlavor, PythonDistribution, PythonDistributionLocation,
             },
             standalone_distribution::StandaloneDistribution,
         },
-        python_distributions::PYTHON_DISTRIBUTIONS,
+        python_distribution_channels::{fetch_latest_release, fetch_sha256, LockFile},
+        python_distributions::{DEFAULT_PYTHON_VERSION, PYTHON_DISTRIBUTIONS},
+        script_metadata::{script_module_name, ScriptMetadata},
         starlark::eval::EvaluationContextBuilder,
     },
     anyhow::{anyhow, Context, Result},
@@ -35,7 +44,10 @@ use {
         fs::create_dir_all,
         io::{Cursor, Read},
         path::{Path, PathBuf},
+        sync::Arc,
     },
+    tugger::starlark::file_manifest::FileManifestValue,
+    tugger_binary_analysis::{find_av_heuristics, AvHeuristic},
 };
 
 /// Attempt to resolve the default Rust target for a build.
@@ -112,9 +124,24 @@ pub fn build(
     target_triple: Option<&str>,
     resolve_targets: Option<Vec<String>>,
     extra_vars: HashMap<String, Option<String>>,
+    enabled_extras: Vec<String>,
     release: bool,
     verbose: bool,
+    jobs: usize,
+    metrics_path: Option<&Path>,
+    build_inputs_path: Option<&Path>,
+    frozen: bool,
 ) -> Result<()> {
+    // Fail before building if a recorded input has already changed.
+    let frozen_inputs = match build_inputs_path {
+        Some(path) if frozen => {
+            let recorded = BuildInputs::read_json(path)?;
+            recorded.current()?.verify(&recorded, path)?;
+            Some(recorded)
+        }
+        _ => None,
+    };
+
     let config_path = find_pyoxidizer_config_file_env(project_path).ok_or_else(|| {
         anyhow!(
             "unable to find PyOxidizer config file at {}",
@@ -123,22 +150,186 @@ pub fn build(
     })?;
     let target_triple = resolve_target(target_triple)?;
 
-    let mut context = EvaluationContextBuilder::new(env, config_path.clone(), target_triple)
+    let builder = EvaluationContextBuilder::new(env, config_path, &target_triple)
         .extra_vars(extra_vars)
+        .enabled_extras(enabled_extras)
         .release(release)
         .verbose(verbose)
         .resolve_targets_optional(resolve_targets)
-        .into_context()?;
+        .jobs(jobs);
 
-    context.evaluate_file(&config_path)?;
+    let telemetry_url = metrics::telemetry_url();
+
+    if metrics_path.is_none() && telemetry_url.is_none() {
+        builder.build_targets()?;
+
+        return finish_build_inputs(build_inputs_path, frozen_inputs.as_ref());
+    }
+
+    let recorder = Arc::new(MetricsRecorder::new(&target_triple, release, jobs));
+    let cache = Arc::new(DistributionCache::new(Some(
+        &env.python_distributions_dir(),
+    )));
 
-    for target in context.targets_to_resolve()? {
-        context.build_resolved_target(&target)?;
+    let res = builder
+        .metrics(recorder.clone())
+        .distribution_cache(cache.clone())
+        .build_targets();
+    let metrics = recorder.finish(res.is_ok(), &cache);
+
+    if let Some(path) = metrics_path {
+        metrics.write_json(path)?;
+        println!("wrote build metrics to {}", path.display());
+    }
+
+    if let Some(url) = telemetry_url {
+        if let Err(e) = metrics.submit(&url) {
+            eprintln!("unable to submit build metrics to {}: {:?}", url, e);
+        }
+    }
+
+    res?;
+
+    finish_build_inputs(build_inputs_path, frozen_inputs.as_ref())
+}
+
+/// Write the inputs recorded by a build to a manifest or verify them against one.
+fn finish_build_inputs(path: Option<&Path>, frozen: Option<&BuildInputs>) -> Result<()> {
+    if let Some(path) = path {
+        let inputs = BuildInputs::recorded()?;
+
+        if let Some(frozen) = frozen {
+            inputs.verify(frozen, path)?;
+        } else {
+            inputs.write_json(path)?;
+            println!("wrote build inputs to {}", path.display());
+        }
     }
 
     Ok(())
 }
 
+/// Build a PyOxidizer enabled project inside a container.
+///
+/// See [crate::containerized_build] for how this works.
+#[allow(clippy::too_many_arguments)]
+pub fn build_containerized(
+    project_path: &Path,
+    target_triple: Option<&str>,
+    resolve_targets: Option<Vec<String>>,
+    extra_vars: HashMap<String, Option<String>>,
+    enabled_extras: Vec<String>,
+    release: bool,
+    verbose: bool,
+    jobs: usize,
+    image: Option<&str>,
+) -> Result<()> {
+    let config_path = find_pyoxidizer_config_file_env(project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })?;
+    let project_path = config_path
+        .parent()
+        .ok_or_else(|| anyhow!("unable to resolve project directory"))?;
+    let target_triple = resolve_target(target_triple)?;
+
+    let build = ContainerizedBuild::new(&target_triple, image)?;
+
+    let args = container_build_args(
+        &target_triple,
+        resolve_targets.as_deref(),
+        &extra_vars,
+        &enabled_extras,
+        release,
+        verbose,
+        jobs,
+    );
+
+    build.build(project_path, &args)
+}
+
+/// Build an executable from a single Python script.
+///
+/// Dependencies and the supported Python versions are read from the PEP 723
+/// inline metadata of the script. A PyOxidizer project packaging the script
+/// is generated in a temporary directory and built. The installed files of
+/// the build are copied to `output_path`.
+pub fn build_script(
+    env: &Environment,
+    script_path: &Path,
+    output_path: &Path,
+    target_triple: Option<&str>,
+    release: bool,
+    verbose: bool,
+) -> Result<()> {
+    let source = std::fs::read_to_string(script_path)
+        .with_context(|| format!("reading {}", script_path.display()))?;
+    let metadata = ScriptMetadata::from_source(&source)
+        .with_context(|| format!("parsing {}", script_path.display()))?
+        .unwrap_or_default();
+
+    let name = script_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| anyhow!("unable to resolve name of {}", script_path.display()))?;
+    let module = script_module_name(name);
+
+    let target_triple = resolve_target(target_triple)?;
+    let python_version = metadata.resolve_python_version(
+        DEFAULT_PYTHON_VERSION,
+        PYTHON_DISTRIBUTIONS
+            .iter()
+            .filter(|dist| dist.target_triple == target_triple)
+            .map(|dist| dist.python_major_minor_version.as_str()),
+    )?;
+
+    let project_dir = tempfile::Builder::new()
+        .prefix("pyoxidizer-script-")
+        .tempdir()?;
+
+    std::fs::write(project_dir.path().join(format!("{}.py", module)), &source)?;
+    write_script_pyoxidizer_config_file(
+        project_dir.path(),
+        name,
+        &module,
+        python_version,
+        &metadata.dependencies,
+    )?;
+
+    let config_path = project_dir.path().join("pyoxidizer.bzl");
+
+    let built = EvaluationContextBuilder::new(env, config_path, &target_triple)
+        .release(release)
+        .verbose(verbose)
+        .resolve_target("install")
+        .build_targets()?;
+
+    let (_, install) = built
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("install target was not built"))?;
+
+    create_dir_all(output_path)?;
+
+    for entry in walkdir::WalkDir::new(&install.output_path) {
+        let entry = entry?;
+        let dest_path = output_path.join(entry.path().strip_prefix(&install.output_path)?);
+
+        if entry.file_type().is_dir() {
+            create_dir_all(&dest_path)?;
+        } else {
+            println!("writing {}", dest_path.display());
+            std::fs::copy(entry.path(), &dest_path)?;
+        }
+    }
+
+    project_dir.close()?;
+
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn run(
     env: &Environment,
@@ -147,6 +338,7 @@ pub fn run(
     release: bool,
     target: Option<&str>,
     extra_vars: HashMap<String, Option<String>>,
+    enabled_extras: Vec<String>,
     _extra_args: &[&str],
     verbose: bool,
 ) -> Result<()> {
@@ -160,6 +352,7 @@ pub fn run(
 
     let mut context = EvaluationContextBuilder::new(env, config_path.clone(), target_triple)
         .extra_vars(extra_vars)
+        .enabled_extras(enabled_extras)
         .release(release)
         .verbose(verbose)
         .resolve_target_optional(target)
@@ -170,6 +363,152 @@ pub fn run(
     context.run_target(target)
 }
 
+/// Install the files of a target in a PyOxidizer configuration file to a prefix.
+///
+/// The target must resolve to a `FileManifest`. Installed paths are written
+/// to an uninstall manifest, defaulting to `install_manifest.txt` in the
+/// build directory.
+#[allow(clippy::too_many_arguments)]
+pub fn install(
+    env: &Environment,
+    project_path: &Path,
+    target_triple: Option<&str>,
+    release: bool,
+    target: Option<&str>,
+    extra_vars: HashMap<String, Option<String>>,
+    enabled_extras: Vec<String>,
+    verbose: bool,
+    prefix: &Path,
+    destdir: Option<&Path>,
+    uninstall_manifest: Option<&Path>,
+) -> Result<()> {
+    let config_path = find_pyoxidizer_config_file_env(project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })?;
+    let target_triple = resolve_target(target_triple)?;
+
+    let mut context = EvaluationContextBuilder::new(env, config_path.clone(), target_triple)
+        .extra_vars(extra_vars)
+        .enabled_extras(enabled_extras)
+        .release(release)
+        .verbose(verbose)
+        .resolve_target_optional(target)
+        .into_context()?;
+
+    context.evaluate_file(&config_path)?;
+
+    let target = match target {
+        Some(target) => target.to_string(),
+        None => context
+            .default_target()?
+            .ok_or_else(|| anyhow!("unable to determine target to install"))?,
+    };
+
+    let value = context
+        .resolved_target_value(&target)?
+        .ok_or_else(|| anyhow!("target {} was not resolved", target))?;
+    let manifest_value = value.downcast_ref::<FileManifestValue>().ok_or_else(|| {
+        anyhow!(
+            "target {} resolves to {}; only FileManifest targets can be installed",
+            target,
+            value.get_type()
+        )
+    })?;
+
+    let installed = {
+        let manifest = manifest_value
+            .inner("install")
+            .map_err(|e| anyhow!("{:?}", e))?;
+
+        install_file_manifest(&manifest, prefix, destdir)?
+    };
+
+    let uninstall_manifest = match uninstall_manifest {
+        Some(path) => path.to_path_buf(),
+        None => context
+            .build_path()
+            .map_err(|e| anyhow!("{:?}", e))?
+            .join("install_manifest.txt"),
+    };
+
+    write_uninstall_manifest(&uninstall_manifest, &installed)?;
+
+    println!(
+        "installed {} files to {}",
+        installed.len(),
+        install_root(prefix, destdir).display()
+    );
+
+    Ok(())
+}
+
+/// Describe how to remediate a binary trait triggering anti-virus heuristics.
+fn av_heuristic_remediation(heuristic: AvHeuristic) -> &'static str {
+    match heuristic {
+        AvHeuristic::WritableExecutable => {
+            "Binaries built by PyOxidizer don't need writable and executable memory. This \
+            usually means the binary was post-processed by another tool. Distribute the \
+            binary as produced by the linker."
+        }
+        AvHeuristic::ExecutablePacker => {
+            "Executable packers are frequently used by malware to evade detection and \
+            packed binaries are often quarantined. Don't compress the binary with a packer."
+        }
+        AvHeuristic::HighEntropyData => {
+            "Compressed or encrypted data resembles an embedded payload. If this is packed \
+            resources data, don't compress it or set \
+            `PythonExecutable.packed_resources_load_mode` to \
+            `binary-relative-memory-mapped:<filename>` to store it in a file next to the \
+            executable."
+        }
+        AvHeuristic::MissingVersionInfo => {
+            "Binaries without version metadata look anonymous. Set \
+            `PythonExecutable.windows_version_info` to embed a VS_VERSIONINFO resource \
+            describing the application."
+        }
+        AvHeuristic::MissingSignature => {
+            "Unsigned binaries are frequently quarantined or blocked. Sign the binary \
+            (Authenticode on Windows, a Developer ID certificate plus notarization on \
+            macOS). See the `CodeSigner` Starlark type."
+        }
+    }
+}
+
+/// Print a report of binary traits commonly triggering anti-virus heuristics.
+pub fn av_report(path: &Path) -> Result<()> {
+    let data = std::fs::read(path)?;
+    let findings = find_av_heuristics(&data)
+        .map_err(|e| anyhow!("unable to analyze {}: {}", path.display(), e))?;
+
+    println!("Anti-Virus Heuristics");
+    println!("=====================");
+    println!();
+
+    if findings.is_empty() {
+        println!("No traits commonly triggering anti-virus heuristics found");
+        return Ok(());
+    }
+
+    let mut heuristics = findings.iter().map(|f| f.heuristic).collect::<Vec<_>>();
+    heuristics.sort();
+    heuristics.dedup();
+
+    for heuristic in heuristics {
+        println!("{}", heuristic);
+        for finding in findings.iter().filter(|f| f.heuristic == heuristic) {
+            println!("  {}", finding.detail);
+        }
+        println!();
+        println!("  Remediation: {}", av_heuristic_remediation(heuristic));
+        println!();
+    }
+
+    Ok(())
+}
+
 pub fn cache_clear(env: &Environment) -> Result<()> {
     let cache_dir = env.cache_dir();
 
@@ -347,6 +686,7 @@ pub fn init_rust_project(env: &Environment, project_path: &Path) -> Result<()> {
         None,
         &[],
         "console",
+        &WindowsResources::default(),
     )?;
     println!();
     println!(
@@ -377,6 +717,77 @@ for configuration changes to take effect.
     Ok(())
 }
 
+/// List Python distributions, optionally checking for newer releases.
+///
+/// The built-in distributions are listed unless `lock_path` is given, in which
+/// case the distributions recorded in that lock file are listed. With `update`,
+/// locked distributions are moved to the latest release.
+pub fn list_python_distributions(
+    lock_path: Option<&Path>,
+    check_updates: bool,
+    update: bool,
+) -> Result<()> {
+    let release = if check_updates || update {
+        let release = fetch_latest_release()?;
+        println!(
+            "latest python-build-standalone release: {}",
+            release.tag_name
+        );
+        Some(release)
+    } else {
+        None
+    };
+
+    if let Some(lock_path) = lock_path {
+        let mut lock = LockFile::from_path(lock_path)?;
+        let mut changed = false;
+
+        for entry in lock.python_distributions.iter_mut() {
+            println!(
+                "{} {} {}: {}",
+                entry.channel, entry.target_triple, entry.flavor, entry.url
+            );
+
+            if let Some(url) = release.as_ref().and_then(|r| r.updated_url(&entry.url)) {
+                println!("  update available: {}", url);
+
+                if update {
+                    entry.sha256 = fetch_sha256(&url)?;
+                    entry.url = url;
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            lock.write_path(lock_path)?;
+            println!("updated {}", lock_path.display());
+        }
+    } else {
+        if update {
+            return Err(anyhow!("updating distributions requires a lock file"));
+        }
+
+        for dist in PYTHON_DISTRIBUTIONS.iter() {
+            let url = match &dist.location {
+                PythonDistributionLocation::Url { url, .. } => url,
+                PythonDistributionLocation::Local { local_path, .. } => local_path,
+            };
+
+            println!(
+                "{} {}: {}",
+                dist.python_major_minor_version, dist.target_triple, url
+            );
+
+            if let Some(url) = release.as_ref().and_then(|r| r.updated_url(url)) {
+                println!("  update available: {}", url);
+            }
+        }
+    }
+
+    Ok(())
+}
+
 pub fn python_distribution_extract(
     download_default: bool,
     archive_path: Option<&str>,
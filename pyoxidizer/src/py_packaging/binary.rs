@@ -11,7 +11,7 @@ use {
         environment::Environment,
         py_packaging::{distribution::AppleSdkInfo, embedding::EmbeddedPythonContext},
     },
-    anyhow::Result,
+    anyhow::{anyhow, Result},
     python_packaging::{
         licensing::{LicensedComponent, LicensedComponents},
         policy::PythonPackagingPolicy,
@@ -23,11 +23,31 @@ use {
             AddResourceAction, PrePackagedResource, PythonResourceAddCollectionContext,
         },
     },
-    simple_file_manifest::File,
-    std::{collections::HashMap, path::Path, sync::Arc},
+    simple_file_manifest::{File, FileData, FileManifest},
+    std::{
+        collections::{BTreeMap, HashMap},
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
     tugger_windows::VcRedistributablePlatform,
 };
 
+/// Keys allowed in the `StringFileInfo` block of Windows `VS_VERSIONINFO` resources.
+pub const WINDOWS_VERSION_INFO_KEYS: &[&str] = &[
+    "Comments",
+    "CompanyName",
+    "FileDescription",
+    "FileVersion",
+    "InternalName",
+    "LegalCopyright",
+    "LegalTrademarks",
+    "OriginalFilename",
+    "PrivateBuild",
+    "ProductName",
+    "ProductVersion",
+    "SpecialBuild",
+];
+
 /// How a binary should link against libpython.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LibpythonLinkMode {
@@ -37,6 +57,58 @@ pub enum LibpythonLinkMode {
     Dynamic,
 }
 
+/// Extension of the packed resources file written next to binaries in sidecar mode.
+pub const SIDECAR_RESOURCES_EXTENSION: &str = "pyoxy-resources";
+
+/// Extension of the packed resources file holding the application layer.
+pub const APPLICATION_LAYER_RESOURCES_EXTENSION: &str = "app-resources";
+
+/// Default alignment in bytes of packed resources data embedded in a named section.
+///
+/// This matches the alignment of payloads within packed resources data.
+pub const DEFAULT_PACKED_RESOURCES_SECTION_ALIGNMENT: usize =
+    python_packed_resources::PAYLOAD_ALIGNMENT;
+
+/// Obtain the default name of the section holding embedded packed resources data.
+///
+/// Mach-O section names are qualified by a segment name. Resources data is placed
+/// in the read-only `__TEXT` segment.
+pub fn default_packed_resources_section(target_triple: &str) -> &'static str {
+    if target_triple.contains("-apple-") {
+        "__TEXT,__pyoxres"
+    } else {
+        ".pyoxres"
+    }
+}
+
+/// Validate the name of a section holding embedded packed resources data for a target.
+pub fn validate_packed_resources_section(target_triple: &str, name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '"') {
+        return Err(anyhow!("invalid section name: {:?}", name));
+    }
+
+    if target_triple.contains("-apple-") {
+        match name.split_once(',') {
+            Some((segment, section))
+                if !segment.is_empty()
+                    && segment.len() <= 16
+                    && !section.is_empty()
+                    && section.len() <= 16
+                    && !section.contains(',') => {}
+            _ => {
+                return Err(anyhow!(
+                    "Mach-O section name {} must have form `segment,section` with each part at most 16 bytes",
+                    name
+                ));
+            }
+        }
+    } else if target_triple.contains("-windows-") && name.len() > 8 {
+        return Err(anyhow!("PE section name {} must be at most 8 bytes", name));
+    }
+
+    Ok(())
+}
+
 /// Determines how packed resources are loaded by the generated binary.
 ///
 /// This effectively controls how resources file are written to disk
@@ -59,6 +131,25 @@ pub enum PackedResourcesLoadMode {
     /// The configuration will reference the file via a relative path using
     /// `$ORIGIN` expansion. Memory mapped I/O will be used to read the file.
     BinaryRelativePathMemoryMapped(String),
+
+    /// Resources data will be serialized to a `<exe>.pyoxy-resources` file next
+    /// to the built binary.
+    ///
+    /// The SHA-256 digest of the file is embedded in the binary and the file
+    /// is verified against it before being loaded with memory mapped I/O.
+    Sidecar,
+
+    /// Resources data will be embedded in a named section of the binary.
+    ///
+    /// Fields are the name of the section and the alignment of the data in bytes.
+    /// `None` values resolve to a target-specific default section name and to
+    /// [DEFAULT_PACKED_RESOURCES_SECTION_ALIGNMENT].
+    EmbeddedInSection(Option<String>, Option<usize>),
+
+    /// Resources data will be appended to the built binary.
+    ///
+    /// The binary memory maps itself to read the data.
+    ExecutableOverlay,
 }
 
 impl ToString for PackedResourcesLoadMode {
@@ -69,6 +160,17 @@ impl ToString for PackedResourcesLoadMode {
             Self::BinaryRelativePathMemoryMapped(path) => {
                 format!("binary-relative-memory-mapped:{}", path)
             }
+            Self::Sidecar => "sidecar".to_string(),
+            Self::EmbeddedInSection(None, None) => "section".to_string(),
+            Self::EmbeddedInSection(section, None) => {
+                format!("section:{}", section.as_deref().unwrap_or_default())
+            }
+            Self::EmbeddedInSection(section, Some(alignment)) => format!(
+                "section:{}:{}",
+                section.as_deref().unwrap_or_default(),
+                alignment
+            ),
+            Self::ExecutableOverlay => "overlay".to_string(),
         }
     }
 }
@@ -79,6 +181,39 @@ impl TryFrom<&str> for PackedResourcesLoadMode {
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         if value == "none" {
             Ok(Self::None)
+        } else if value == "sidecar" {
+            Ok(Self::Sidecar)
+        } else if value == "overlay" {
+            Ok(Self::ExecutableOverlay)
+        } else if value == "section" {
+            Ok(Self::EmbeddedInSection(None, None))
+        } else if let Some(value) = value.strip_prefix("section:") {
+            let (section, alignment) = match value.rsplit_once(':') {
+                Some((section, alignment)) => {
+                    let alignment = alignment
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|x| x.is_power_of_two() && *x <= 1 << 29)
+                        .ok_or_else(|| {
+                            format!(
+                                "section alignment {} is not a power of 2 not exceeding 2^29",
+                                alignment
+                            )
+                        })?;
+
+                    (section, Some(alignment))
+                }
+                None => (value, None),
+            };
+
+            Ok(Self::EmbeddedInSection(
+                if section.is_empty() {
+                    None
+                } else {
+                    Some(section.to_string())
+                },
+                alignment,
+            ))
         } else {
             let parts = value.splitn(2, ':').collect::<Vec<_>>();
             if parts.len() != 2 {
@@ -97,7 +232,7 @@ impl TryFrom<&str> for PackedResourcesLoadMode {
                     "binary-relative-memory-mapped" => {
                         Ok(Self::BinaryRelativePathMemoryMapped(value.to_string()))
                     }
-                    _ => Err(format!("{} is not a valid prefix; must be 'embedded' or 'binary-relative-memory-mapped'", prefix))
+                    _ => Err(format!("{} is not a valid prefix; must be 'embedded', 'binary-relative-memory-mapped', or 'section'", prefix))
                 }
             }
         }
@@ -143,6 +278,191 @@ impl TryFrom<&str> for WindowsRuntimeDllsMode {
     }
 }
 
+/// DPI awareness declared by the application manifest of Windows executables.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowsDpiAwareness {
+    /// Don't declare DPI awareness.
+    ///
+    /// Windows scales the windows of the application as bitmaps on high DPI
+    /// displays, which makes them look blurry.
+    #[default]
+    Unaware,
+
+    /// Use the DPI of the primary display at the time the user logged in.
+    System,
+
+    /// Use the DPI of the display a window is on.
+    PerMonitor,
+
+    /// Like [Self::PerMonitor] but also scale non-client areas and dialogs.
+    ///
+    /// Requires Windows 10 version 1703. Older versions use [Self::PerMonitor].
+    PerMonitorV2,
+}
+
+impl std::fmt::Display for WindowsDpiAwareness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Unaware => "unaware",
+            Self::System => "system",
+            Self::PerMonitor => "per-monitor",
+            Self::PerMonitorV2 => "per-monitor-v2",
+        })
+    }
+}
+
+impl TryFrom<&str> for WindowsDpiAwareness {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "unaware" => Ok(Self::Unaware),
+            "system" => Ok(Self::System),
+            "per-monitor" => Ok(Self::PerMonitor),
+            "per-monitor-v2" => Ok(Self::PerMonitorV2),
+            _ => Err(format!(
+                "{} is not a valid DPI awareness; must be 'unaware', 'system', 'per-monitor', or 'per-monitor-v2'",
+                value
+            )),
+        }
+    }
+}
+
+/// How Windows executables link the C runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowsCrtLinkage {
+    /// Statically link the C runtime (`/MT`, the `crt-static` target feature).
+    Static,
+
+    /// Dynamically link the C runtime DLLs (`/MD`).
+    Dynamic,
+}
+
+impl WindowsCrtLinkage {
+    /// The C runtime linkage required by a libpython link mode.
+    ///
+    /// Object files of statically linked libpython are compiled against the
+    /// static C runtime. The Python DLL of dynamically linked libpython uses
+    /// the C runtime DLLs, which the executable must share so C runtime state
+    /// like the heap and file descriptors is shared.
+    pub fn for_libpython_link_mode(link_mode: LibpythonLinkMode) -> Self {
+        match link_mode {
+            LibpythonLinkMode::Static => Self::Static,
+            LibpythonLinkMode::Dynamic => Self::Dynamic,
+        }
+    }
+
+    /// The `target-feature` value selecting this linkage in `rustc`.
+    pub fn rustc_target_feature(&self) -> &'static str {
+        match self {
+            Self::Static => "+crt-static",
+            Self::Dynamic => "-crt-static",
+        }
+    }
+}
+
+impl std::fmt::Display for WindowsCrtLinkage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Static => "static",
+            Self::Dynamic => "dynamic",
+        })
+    }
+}
+
+impl TryFrom<&str> for WindowsCrtLinkage {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "static" => Ok(Self::Static),
+            "dynamic" => Ok(Self::Dynamic),
+            _ => Err(format!(
+                "{} is not a valid C runtime linkage; must be 'static' or 'dynamic'",
+                value
+            )),
+        }
+    }
+}
+
+/// Cargo profile settings to use when building the generated Rust project.
+///
+/// Settings are passed to Cargo via `CARGO_PROFILE_<name>_*` environment
+/// variables, which take precedence over `[profile]` sections in `Cargo.toml`.
+/// `None` values leave the setting from `Cargo.toml` in place.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CargoProfile {
+    /// Optimization level. e.g. `3` or `z`.
+    pub opt_level: Option<String>,
+
+    /// Link-time optimization kind. e.g. `fat`, `thin`, or `off`.
+    pub lto: Option<String>,
+
+    /// Number of code generation units.
+    pub codegen_units: Option<u32>,
+
+    /// Panic strategy. `unwind` or `abort`.
+    pub panic: Option<String>,
+
+    /// What to strip from the binary. e.g. `none`, `debuginfo`, or `symbols`.
+    pub strip: Option<String>,
+}
+
+impl CargoProfile {
+    /// Ensure all settings have values Cargo accepts.
+    pub fn validate(&self) -> Result<()> {
+        fn check(name: &str, value: &Option<String>, allowed: &[&str]) -> Result<()> {
+            match value {
+                Some(value) if !allowed.contains(&value.as_str()) => Err(anyhow!(
+                    "{} is not a valid value for {}; must be one of {}",
+                    value,
+                    name,
+                    allowed.join(", ")
+                )),
+                _ => Ok(()),
+            }
+        }
+
+        check(
+            "opt_level",
+            &self.opt_level,
+            &["0", "1", "2", "3", "s", "z"],
+        )?;
+        check("lto", &self.lto, &["true", "false", "fat", "thin", "off"])?;
+        check("panic", &self.panic, &["unwind", "abort"])?;
+        check(
+            "strip",
+            &self.strip,
+            &["true", "false", "none", "debuginfo", "symbols"],
+        )?;
+
+        if self.codegen_units == Some(0) {
+            return Err(anyhow!("codegen_units must be greater than 0"));
+        }
+
+        Ok(())
+    }
+
+    /// Obtain environment variables applying these settings to a Cargo profile.
+    pub fn environment_variables(&self, profile: &str) -> BTreeMap<String, String> {
+        let prefix = format!(
+            "CARGO_PROFILE_{}_",
+            profile.to_uppercase().replace('-', "_")
+        );
+
+        [
+            ("OPT_LEVEL", self.opt_level.clone()),
+            ("LTO", self.lto.clone()),
+            ("CODEGEN_UNITS", self.codegen_units.map(|x| x.to_string())),
+            ("PANIC", self.panic.clone()),
+            ("STRIP", self.strip.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| (format!("{}{}", prefix, key), value)))
+        .collect()
+    }
+}
+
 /// A callable that can influence PythonResourceAddCollectionContext.
 pub type ResourceAddCollectionContextCallback<'a> = Box<
     dyn Fn(
@@ -223,6 +543,61 @@ pub trait PythonBinaryBuilder {
     /// Set the value of the `windows_subsystem` Rust attribute for generated Rust projects.
     fn set_windows_subsystem(&mut self, value: &str) -> Result<()>;
 
+    /// `VS_VERSIONINFO` strings to embed in Windows executables.
+    ///
+    /// Keys are `StringFileInfo` names like `CompanyName` and `FileVersion`.
+    /// An empty map means no version info resource is embedded.
+    fn windows_version_info(&self) -> &BTreeMap<String, String>;
+
+    /// Set the `VS_VERSIONINFO` strings to embed in Windows executables.
+    fn set_windows_version_info(&mut self, value: BTreeMap<String, String>) -> Result<()>;
+
+    /// Path of an `.ico` file to embed as the icon of Windows executables.
+    fn windows_icon_path(&self) -> Option<&Path>;
+
+    /// Set the path of an `.ico` file to embed as the icon of Windows executables.
+    fn set_windows_icon_path(&mut self, value: Option<PathBuf>) -> Result<()>;
+
+    /// Whether the application manifest of Windows executables declares long path awareness.
+    fn windows_long_path_aware(&self) -> bool;
+
+    /// Set whether the application manifest of Windows executables declares long path awareness.
+    fn set_windows_long_path_aware(&mut self, value: bool);
+
+    /// DPI awareness declared by the application manifest of Windows executables.
+    fn windows_dpi_awareness(&self) -> WindowsDpiAwareness;
+
+    /// Set the DPI awareness declared by the application manifest of Windows executables.
+    fn set_windows_dpi_awareness(&mut self, value: WindowsDpiAwareness);
+
+    /// How Windows executables link the C runtime.
+    fn windows_crt_linkage(&self) -> WindowsCrtLinkage;
+
+    /// Set how Windows executables link the C runtime.
+    ///
+    /// Fails if the linkage is incompatible with how the binary links libpython.
+    fn set_windows_crt_linkage(&mut self, value: WindowsCrtLinkage) -> Result<()>;
+
+    /// Extra Cargo features to enable when building the generated Rust project.
+    ///
+    /// Features of `pyembed` can be selected via `pyembed/<feature>`.
+    fn cargo_features(&self) -> &[String];
+
+    /// Set extra Cargo features to enable when building the generated Rust project.
+    fn set_cargo_features(&mut self, value: Vec<String>);
+
+    /// Extra `RUSTFLAGS` to use when building the generated Rust project.
+    fn cargo_rustflags(&self) -> &[String];
+
+    /// Set extra `RUSTFLAGS` to use when building the generated Rust project.
+    fn set_cargo_rustflags(&mut self, value: Vec<String>);
+
+    /// Cargo profile settings to use when building the generated Rust project.
+    fn cargo_profile(&self) -> &CargoProfile;
+
+    /// Set Cargo profile settings to use when building the generated Rust project.
+    fn set_cargo_profile(&mut self, value: CargoProfile) -> Result<()>;
+
     /// Obtain the path of a filename to write containing a licensing report.
     fn licenses_filename(&self) -> Option<&str>;
 
@@ -235,6 +610,64 @@ pub trait PythonBinaryBuilder {
     /// Set how packed Python resources will be loaded by the binary.
     fn set_packed_resources_load_mode(&mut self, load_mode: PackedResourcesLoadMode);
 
+    /// Key that packed Python resources are encrypted with.
+    fn packed_resources_encryption_key(&self) -> Option<&[u8; 32]>;
+
+    /// Set the key that packed Python resources are encrypted with.
+    ///
+    /// `None` disables encryption.
+    fn set_packed_resources_encryption_key(&mut self, key: Option<[u8; 32]>);
+
+    /// Path of a Rust function providing the packed resources key at run-time.
+    fn packed_resources_key_provider(&self) -> Option<&str>;
+
+    /// Set the path of a Rust function providing the packed resources key at run-time.
+    ///
+    /// The function has the signature `fn() -> Result<[u8; 32], String>`. If set,
+    /// the key isn't stored in the binary.
+    fn set_packed_resources_key_provider(&mut self, path: Option<String>);
+
+    /// Packages whose resources form the application layer.
+    ///
+    /// Resources of these packages are written to a separate
+    /// `<name>.app-resources` file next to the binary instead of being
+    /// loaded according to `packed_resources_load_mode()`. An empty list
+    /// disables the application layer.
+    fn application_layer_packages(&self) -> &[String];
+
+    /// Set packages whose resources form the application layer.
+    fn set_application_layer_packages(&mut self, value: Vec<String>);
+
+    /// Whether Python modules the application doesn't import are removed.
+    ///
+    /// When enabled, imports are statically analyzed starting from the
+    /// interpreter's configured entry point and modules that aren't reachable
+    /// are removed when the binary is built.
+    fn import_analysis(&self) -> bool;
+
+    /// Set whether Python modules the application doesn't import are removed.
+    fn set_import_analysis(&mut self, value: bool);
+
+    /// Modules and packages that import analysis always considers imported.
+    ///
+    /// This is used to retain modules imported dynamically.
+    fn import_analysis_allow(&self) -> &[String];
+
+    /// Set modules and packages that import analysis always considers imported.
+    fn set_import_analysis_allow(&mut self, names: Vec<String>);
+
+    /// Whether modules imported dynamically by collected modules are included.
+    ///
+    /// Calls to `importlib.import_module()` and `__import__()` with literal
+    /// module names are found in the source code of collected modules. When
+    /// enabled, modules they name which weren't included by the packaging
+    /// policy are included when the binary is built. Otherwise a warning is
+    /// emitted for them. Modules without source aren't scanned.
+    fn include_dynamic_imports(&self) -> bool;
+
+    /// Set whether modules imported dynamically by collected modules are included.
+    fn set_include_dynamic_imports(&mut self, value: bool);
+
     /// Obtain an iterator over all resource entries that will be embedded in the binary.
     ///
     /// This likely does not return extension modules that are statically linked
@@ -384,6 +817,23 @@ pub trait PythonBinaryBuilder {
         glob_patterns: &[&str],
     ) -> Result<()>;
 
+    /// Replace the data of a resource with other data.
+    ///
+    /// `path` is the path of a file relative to the root of the package tree,
+    /// such as `pkg/data/config.json`. The override applies to resources
+    /// already added and to resources added later.
+    fn override_resource(&mut self, path: &str, data: FileData) -> Result<()>;
+
+    /// Directory that source map paths of packaged modules are relative to.
+    fn source_map_root(&self) -> Option<&Path>;
+
+    /// Set the directory that source map paths of packaged modules are relative to.
+    ///
+    /// Modules whose source is collected from a file under this directory
+    /// record the path of that file relative to it, allowing tracebacks to be
+    /// rendered with the paths of original source files.
+    fn set_source_map_root(&mut self, value: Option<PathBuf>);
+
     /// Whether the binary requires the jemalloc library.
     fn requires_jemalloc(&self) -> bool;
 
@@ -393,6 +843,12 @@ pub trait PythonBinaryBuilder {
     /// Whether the binary requires the Snmalloc library.
     fn requires_snmalloc(&self) -> bool;
 
+    /// Whether the binary requires support for run-time config overrides.
+    fn requires_config_overrides(&self) -> bool;
+
+    /// Whether the binary requires support for decrypting packed resources.
+    fn requires_encryption(&self) -> bool;
+
     /// Obtain software licensing information.
     fn licensed_components(&self) -> Result<LicensedComponents>;
 
@@ -409,6 +865,14 @@ pub trait PythonBinaryBuilder {
         env: &Environment,
         opt_level: &str,
     ) -> Result<EmbeddedPythonContext>;
+
+    /// Obtain files constituting a conventional Python installation.
+    ///
+    /// Instead of a binary embedding Python, this is the target distribution's
+    /// `python` executable plus the collected resources installed as files in
+    /// its standard library directory. Returns the path of the `python`
+    /// executable within the manifest and the manifest.
+    fn to_runtime_file_manifest(&self, env: &Environment) -> Result<(PathBuf, FileManifest)>;
 }
 
 #[cfg(test)]
@@ -430,6 +894,26 @@ mod tests {
                 .to_string(),
             "binary-relative-memory-mapped:relative-resources".to_string()
         );
+        assert_eq!(
+            PackedResourcesLoadMode::Sidecar.to_string(),
+            "sidecar".to_string()
+        );
+        assert_eq!(
+            PackedResourcesLoadMode::EmbeddedInSection(None, None).to_string(),
+            "section".to_string()
+        );
+        assert_eq!(
+            PackedResourcesLoadMode::EmbeddedInSection(Some(".res".into()), None).to_string(),
+            "section:.res".to_string()
+        );
+        assert_eq!(
+            PackedResourcesLoadMode::EmbeddedInSection(None, Some(4096)).to_string(),
+            "section::4096".to_string()
+        );
+        assert_eq!(
+            PackedResourcesLoadMode::ExecutableOverlay.to_string(),
+            "overlay".to_string()
+        );
     }
 
     #[test]
@@ -446,6 +930,122 @@ mod tests {
             PackedResourcesLoadMode::try_from("binary-relative-memory-mapped:relative").unwrap(),
             PackedResourcesLoadMode::BinaryRelativePathMemoryMapped("relative".into())
         );
+        assert_eq!(
+            PackedResourcesLoadMode::try_from("sidecar").unwrap(),
+            PackedResourcesLoadMode::Sidecar
+        );
+        assert_eq!(
+            PackedResourcesLoadMode::try_from("section").unwrap(),
+            PackedResourcesLoadMode::EmbeddedInSection(None, None)
+        );
+        assert_eq!(
+            PackedResourcesLoadMode::try_from("section:__TEXT,__res").unwrap(),
+            PackedResourcesLoadMode::EmbeddedInSection(Some("__TEXT,__res".into()), None)
+        );
+        assert_eq!(
+            PackedResourcesLoadMode::try_from("section:.res:4096").unwrap(),
+            PackedResourcesLoadMode::EmbeddedInSection(Some(".res".into()), Some(4096))
+        );
+        assert_eq!(
+            PackedResourcesLoadMode::try_from("section::16").unwrap(),
+            PackedResourcesLoadMode::EmbeddedInSection(None, Some(16))
+        );
+        assert!(PackedResourcesLoadMode::try_from("section:.res:3").is_err());
+        assert_eq!(
+            PackedResourcesLoadMode::try_from("overlay").unwrap(),
+            PackedResourcesLoadMode::ExecutableOverlay
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_packed_resources_section() {
+        for triple in [
+            "x86_64-unknown-linux-gnu",
+            "x86_64-apple-darwin",
+            "x86_64-pc-windows-msvc",
+        ] {
+            validate_packed_resources_section(triple, default_packed_resources_section(triple))
+                .unwrap();
+            assert!(validate_packed_resources_section(triple, "").is_err());
+        }
+
+        validate_packed_resources_section("x86_64-unknown-linux-gnu", ".long_section_name")
+            .unwrap();
+        assert!(validate_packed_resources_section("x86_64-apple-darwin", ".pyoxres").is_err());
+        assert!(validate_packed_resources_section(
+            "x86_64-apple-darwin",
+            "__TEXT,__a_long_section_name"
+        )
+        .is_err());
+        assert!(validate_packed_resources_section("x86_64-pc-windows-msvc", ".longname").is_err());
+    }
+
+    #[test]
+    fn test_windows_crt_linkage() {
+        for value in ["static", "dynamic"] {
+            assert_eq!(
+                WindowsCrtLinkage::try_from(value).unwrap().to_string(),
+                value
+            );
+        }
+        assert!(WindowsCrtLinkage::try_from("MT").is_err());
+
+        assert_eq!(
+            WindowsCrtLinkage::for_libpython_link_mode(LibpythonLinkMode::Static),
+            WindowsCrtLinkage::Static
+        );
+        assert_eq!(
+            WindowsCrtLinkage::for_libpython_link_mode(LibpythonLinkMode::Dynamic),
+            WindowsCrtLinkage::Dynamic
+        );
+    }
+
+    #[test]
+    fn test_cargo_profile() -> Result<()> {
+        let profile = CargoProfile::default();
+        profile.validate()?;
+        assert!(profile.environment_variables("release").is_empty());
+
+        let profile = CargoProfile {
+            opt_level: Some("z".into()),
+            lto: Some("fat".into()),
+            codegen_units: Some(1),
+            panic: Some("abort".into()),
+            strip: Some("symbols".into()),
+        };
+        profile.validate()?;
+        assert_eq!(
+            profile.environment_variables("release"),
+            [
+                ("CARGO_PROFILE_RELEASE_CODEGEN_UNITS", "1"),
+                ("CARGO_PROFILE_RELEASE_LTO", "fat"),
+                ("CARGO_PROFILE_RELEASE_OPT_LEVEL", "z"),
+                ("CARGO_PROFILE_RELEASE_PANIC", "abort"),
+                ("CARGO_PROFILE_RELEASE_STRIP", "symbols"),
+            ]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<BTreeMap<_, _>>()
+        );
+        assert_eq!(
+            profile.environment_variables("dev")["CARGO_PROFILE_DEV_LTO"],
+            "fat"
+        );
+
+        assert!(CargoProfile {
+            lto: Some("yes".into()),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(CargoProfile {
+            codegen_units: Some(0),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
 
         Ok(())
     }
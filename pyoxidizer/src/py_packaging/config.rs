@@ -11,15 +11,17 @@ use {
     itertools::Itertools,
     python_packaging::{
         interpreter::{
-            Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, MemoryAllocatorBackend,
-            MultiprocessingStartMethod, PythonInterpreterConfig, PythonInterpreterProfile,
-            TerminfoResolution,
+            Allocator, BuildInfo, BytesWarning, CheckHashPycsMode, CoerceCLocale, HostRequirements,
+            LogDestination, MemoryAllocatorBackend, Migration, MultiprocessingStartMethod,
+            PackedResourcesVerification, ProgramPath, PythonInterpreterConfig,
+            PythonInterpreterProfile, SandboxPolicy, TerminfoResolution,
         },
         resource::BytecodeOptimizationLevel,
     },
     std::{
         io::Write,
         path::{Path, PathBuf},
+        str::FromStr,
     },
 };
 
@@ -36,6 +38,100 @@ pub fn default_memory_allocator(target_triple: &str) -> MemoryAllocatorBackend {
     }
 }
 
+/// Determine the host requirements of binaries built for a target triple.
+///
+/// `python_major_minor_version` is the `X.Y` version of the Python distribution
+/// being embedded and `macos_deployment_target` the minimum macOS version the
+/// distribution was built to target, if known.
+///
+/// `glibc_version` is the newest glibc symbol version required by shared
+/// libraries loaded at run-time. The dynamic loader enforces the symbol
+/// versions required by the executable itself before any of its code runs,
+/// so those can't be checked.
+pub fn default_host_requirements(
+    target_triple: &str,
+    python_major_minor_version: &str,
+    macos_deployment_target: Option<&str>,
+    glibc_version: Option<&str>,
+) -> HostRequirements {
+    let glibc_version = if target_triple.ends_with("-linux-gnu") {
+        glibc_version.map(|x| x.to_string())
+    } else {
+        None
+    };
+
+    let macos_version = if target_triple.ends_with("-apple-darwin") {
+        macos_deployment_target.map(|x| x.to_string())
+    } else {
+        None
+    };
+
+    // Python 3.9 dropped support for Windows 7.
+    let windows_build = if target_triple.ends_with("-pc-windows-msvc") {
+        Some(if python_major_minor_version == "3.8" {
+            7601
+        } else {
+            9600
+        })
+    } else {
+        None
+    };
+
+    // Microarchitecture levels as defined by the x86-64 psABI. Each level
+    // implies the features of the levels before it.
+    let mut cpu_features = vec![];
+    let level = target_triple.split('-').next().unwrap_or_default();
+
+    if matches!(level, "x86_64_v2" | "x86_64_v3" | "x86_64_v4") {
+        cpu_features.extend(["cmpxchg16b", "popcnt", "sse3", "sse4.1", "sse4.2", "ssse3"]);
+    }
+    if matches!(level, "x86_64_v3" | "x86_64_v4") {
+        cpu_features.extend([
+            "avx", "avx2", "bmi1", "bmi2", "f16c", "fma", "lzcnt", "movbe", "xsave",
+        ]);
+    }
+    if level == "x86_64_v4" {
+        cpu_features.extend(["avx512f", "avx512bw", "avx512cd", "avx512dq", "avx512vl"]);
+    }
+
+    HostRequirements {
+        glibc_version,
+        macos_version,
+        windows_build,
+        cpu_features: cpu_features.into_iter().map(|x| x.to_string()).collect(),
+        message: None,
+    }
+}
+
+/// Whether pyembed can apply a sandbox policy at run-time on a target triple.
+pub fn sandbox_enforcement_supported(target_triple: &str) -> bool {
+    target_triple.contains("-apple-")
+        || ((target_triple.starts_with("x86_64") || target_triple.starts_with("aarch64"))
+            && target_triple.contains("-linux-"))
+}
+
+/// Obtain the files holding sandbox profiles for an executable.
+///
+/// Returns the filename and content of each profile. Linux targets get an
+/// AppArmor profile and Apple targets a sandbox profile usable with
+/// `sandbox-exec`. Other targets don't get a profile.
+pub fn sandbox_profile_files(
+    policy: &SandboxPolicy,
+    exe_name: &str,
+    target_triple: &str,
+) -> Vec<(String, String)> {
+    if target_triple.contains("-linux-") {
+        vec![(
+            format!("{}.apparmor", exe_name),
+            policy.to_apparmor_profile(exe_name),
+        )]
+    } else if target_triple.contains("-apple-") {
+        vec![(format!("{}.sb", exe_name), policy.to_macos_profile(None))]
+    } else {
+        vec![]
+    }
+}
+
 fn optional_bool_to_string(value: &Option<bool>) -> String {
     match value {
         Some(value) => format!("Some({})", value),
@@ -64,6 +160,116 @@ fn optional_pathbuf_to_string(value: &Option<PathBuf>) -> String {
     }
 }
 
+fn host_requirements_to_string(value: &HostRequirements) -> String {
+    format!(
+        "pyembed::HostRequirements {{ \
+        glibc_version: {}, \
+        macos_version: {}, \
+        windows_build: {}, \
+        cpu_features: vec![{}], \
+        message: {} \
+        }}",
+        optional_string_to_string(&value.glibc_version),
+        optional_string_to_string(&value.macos_version),
+        match value.windows_build {
+            Some(build) => format!("Some({})", build),
+            None => "None".to_string(),
+        },
+        value
+            .cpu_features
+            .iter()
+            .map(|x| format!("\"{}\".to_string()", x.escape_default()))
+            .collect::<Vec<_>>()
+            .join(", "),
+        optional_string_to_string(&value.message),
+    )
+}
+
+fn build_info_to_string(value: &BuildInfo) -> String {
+    format!(
+        "pyembed::BuildInfo {{ \
+        target_triple: \"{}\".to_string(), \
+        python_version: \"{}\".to_string(), \
+        python_flavor: \"{}\".to_string(), \
+        resources_location: \"{}\".to_string(), \
+        resources_location_fallback: {}, \
+        features: vec![{}] \
+        }}",
+        value.target_triple.escape_default(),
+        value.python_version.escape_default(),
+        value.python_flavor.escape_default(),
+        value.resources_location.escape_default(),
+        optional_string_to_string(&value.resources_location_fallback),
+        value
+            .features
+            .iter()
+            .map(|x| format!("\"{}\".to_string()", x.escape_default()))
+            .collect::<Vec<_>>()
+            .join(", "),
+    )
+}
+
+fn log_destination_to_string(value: &LogDestination) -> String {
+    match value {
+        LogDestination::Host => "pyembed::LogDestination::Host".to_string(),
+        LogDestination::Stderr => "pyembed::LogDestination::Stderr".to_string(),
+        LogDestination::File(path) => format!(
+            "pyembed::LogDestination::File(\"{}\".to_string())",
+            path.escape_default()
+        ),
+        LogDestination::Journald => "pyembed::LogDestination::Journald".to_string(),
+        LogDestination::WindowsEventLog(source) => format!(
+            "pyembed::LogDestination::WindowsEventLog({})",
+            optional_string_to_string(source)
+        ),
+        LogDestination::Etw(guid) => format!(
+            "pyembed::LogDestination::Etw(\"{}\".to_string())",
+            guid.escape_default()
+        ),
+    }
+}
+
+fn program_path_to_string(value: &ProgramPath) -> String {
+    match value {
+        ProgramPath::Default => "pyembed::ProgramPath::Default".to_string(),
+        ProgramPath::Exe => "pyembed::ProgramPath::Exe".to_string(),
+        ProgramPath::Invoked => "pyembed::ProgramPath::Invoked".to_string(),
+        ProgramPath::Empty => "pyembed::ProgramPath::Empty".to_string(),
+        ProgramPath::Static(value) => format!(
+            "pyembed::ProgramPath::Static(\"{}\".to_string())",
+            value.escape_default()
+        ),
+    }
+}
+
+fn sandbox_policy_to_string(value: &SandboxPolicy) -> String {
+    format!(
+        "pyembed::SandboxPolicy {{ \
+        allow_network: {}, \
+        allow_subprocess: {}, \
+        read_paths: {}, \
+        write_paths: {} \
+        }}",
+        value.allow_network,
+        value.allow_subprocess,
+        optional_vec_string_to_string(&value.read_paths),
+        optional_vec_string_to_string(&value.write_paths),
+    )
+}
+
+fn migration_to_string(value: &Migration) -> String {
+    format!(
+        "pyembed::Migration {{ \
+        version: \"{}\".to_string(), \
+        module: \"{}\".to_string(), \
+        function: \"{}\".to_string() \
+        }}",
+        value.version.escape_default(),
+        value.module.escape_default(),
+        value.function.escape_default(),
+    )
+}
+
 fn optional_vec_string_to_string(value: &Option<Vec<String>>) -> String {
     match value {
         Some(value) => format!(
@@ -78,8 +284,29 @@ fn optional_vec_string_to_string(value: &Option<Vec<String>>) -> String {
     }
 }
 
+/// Represents how the key decrypting packed resources data is obtained.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PyembedPackedResourcesKey {
+    /// The key is stored in the binary.
+    Key([u8; 32]),
+    /// The key is obtained by calling a Rust function at run-time.
+    ///
+    /// The value is the path of a `fn() -> Result<[u8; 32], String>`.
+    Callback(String),
+}
+
+impl std::fmt::Display for PyembedPackedResourcesKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "pyembed::PackedResourcesKey::Key({:?})", key),
+            Self::Callback(path) => write!(f, "pyembed::PackedResourcesKey::Callback({})", path),
+        }
+    }
+}
+
 /// Represents sources for loading packed resources data.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
 pub enum PyembedPackedResourcesSource {
     /// Load from memory via an `include_bytes!` directive.
     MemoryIncludeBytes(PathBuf),
@@ -87,6 +314,17 @@ pub enum PyembedPackedResourcesSource {
     ///
     /// The string `$ORIGIN` is expanded at runtime.
     MemoryMappedPath(PathBuf),
+    /// Load from a file using memory mapped I/O after verifying its SHA-256 digest.
+    ///
+    /// The string `$ORIGIN` is expanded at runtime.
+    MemoryMappedPathVerified(PathBuf, [u8; 32]),
+    /// Load from memory via an `include_bytes!` directive placing the data in a named section.
+    ///
+    /// Fields are the path of the file to include, the name of the section, and the
+    /// alignment of the data in bytes.
+    MemoryIncludeBytesInSection(PathBuf, String, usize),
+    /// Load from data appended to the executable using memory mapped I/O.
+    ExecutableOverlay,
 }
 
 impl ToString for PyembedPackedResourcesSource {
@@ -104,6 +342,69 @@ impl ToString for PyembedPackedResourcesSource {
                     path_to_string(path)
                 )
             }
+            Self::MemoryMappedPathVerified(path, digest) => {
+                format!(
+                    "pyembed::PackedResourcesSource::MemoryMappedPathVerified({}, {:?})",
+                    path_to_string(path),
+                    digest
+                )
+            }
+            Self::MemoryIncludeBytesInSection(path, section, alignment) => {
+                format!(
+                    "pyembed::PackedResourcesSource::Memory({{\n\
+                     #[repr(C, align({alignment}))]\n\
+                     struct Aligned<T>(T);\n\
+                     const LENGTH: usize = include_bytes!(r#\"{path}\"#).len();\n\
+                     #[used]\n\
+                     #[link_section = {section:?}]\n\
+                     static DATA: Aligned<[u8; LENGTH]> = Aligned(*include_bytes!(r#\"{path}\"#));\n\
+                     &DATA.0\n\
+                     }})",
+                    alignment = alignment,
+                    path = path.display(),
+                    section = section,
+                )
+            }
+            Self::ExecutableOverlay => {
+                "pyembed::PackedResourcesSource::ExecutableOverlay".to_string()
+            }
+        }
+    }
+}
+
+/// Defines how a sandbox policy is used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SandboxMode {
+    /// The sandbox policy isn't used.
+    Off,
+    /// Sandbox profiles are generated next to the executable.
+    Profiles,
+    /// Sandbox profiles are generated and the executable applies the policy at run-time.
+    Enforce,
+}
+
+impl std::fmt::Display for SandboxMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Off => "off",
+            Self::Profiles => "profiles",
+            Self::Enforce => "enforce",
+        })
+    }
+}
+
+impl FromStr for SandboxMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "profiles" => Ok(Self::Profiles),
+            "enforce" => Ok(Self::Enforce),
+            _ => Err(format!(
+                "{} is not a valid sandbox mode; must be 'off', 'profiles', or 'enforce'",
+                s
+            )),
         }
     }
 }
@@ -123,18 +424,53 @@ pub struct PyembedPythonInterpreterConfig {
     pub allocator_obj: bool,
     pub allocator_pymalloc_arena: bool,
     pub allocator_debug: bool,
+    pub allocator_stats: bool,
     pub set_missing_path_configuration: bool,
     pub oxidized_importer: bool,
     pub filesystem_importer: bool,
+    pub zipimport_importer: bool,
+    pub extra_sys_paths: Vec<PathBuf>,
+    pub extra_sys_paths_env: Option<String>,
     pub packed_resources: Vec<PyembedPackedResourcesSource>,
+    pub packed_resources_key: Option<PyembedPackedResourcesKey>,
+    pub packed_resources_verification: PackedResourcesVerification,
+    pub audit_events: Vec<String>,
     pub argvb: bool,
+    pub console: bool,
+    pub console_argument: Option<String>,
     pub multiprocessing_auto_dispatch: bool,
     pub multiprocessing_start_method: MultiprocessingStartMethod,
+    pub multiprocessing_executable: ProgramPath,
     pub sys_frozen: bool,
     pub sys_meipass: bool,
+    pub sys_executable: ProgramPath,
+    pub sys_argv0: ProgramPath,
     pub terminfo_resolution: TerminfoResolution,
     pub tcl_library: Option<PathBuf>,
     pub write_modules_directory_env: Option<String>,
+    pub startup_profile_env: Option<String>,
+    pub host_requirements_check: bool,
+    pub host_requirements: HostRequirements,
+    pub sandbox_mode: SandboxMode,
+    pub sandbox_policy: SandboxPolicy,
+    pub migrations: Vec<Migration>,
+    pub migrations_app_name: Option<String>,
+    pub setproctitle_module: bool,
+    pub site_module: Option<String>,
+    pub lazy_import_packages: Vec<String>,
+    pub filesystem_first_packages: Vec<String>,
+    pub materialized_files_dir: Option<PathBuf>,
+    pub config_overrides_path: Option<PathBuf>,
+    pub config_overrides_allowed: Vec<String>,
+    pub environment_overrides_prefix: Option<String>,
+    pub environment_overrides_allowed: Vec<String>,
+    pub build_info: Option<BuildInfo>,
+    pub log_destination: Option<LogDestination>,
+    pub log_filter: Option<String>,
+    pub log_filter_env: Option<String>,
+    pub log_register_event_source: bool,
+    pub traceback_source_paths: bool,
+    pub replace_missing_stdio: bool,
 }
 
 impl Default for PyembedPythonInterpreterConfig {
@@ -158,18 +494,53 @@ impl Default for PyembedPythonInterpreterConfig {
             allocator_obj: false,
             allocator_pymalloc_arena: false,
             allocator_debug: false,
+            allocator_stats: false,
             set_missing_path_configuration: true,
             oxidized_importer: true,
             filesystem_importer: false,
+            zipimport_importer: false,
+            extra_sys_paths: vec![],
+            extra_sys_paths_env: None,
             packed_resources: vec![],
+            packed_resources_key: None,
+            packed_resources_verification: PackedResourcesVerification::None,
+            audit_events: vec![],
             argvb: false,
+            console: false,
+            console_argument: None,
             multiprocessing_auto_dispatch: true,
             multiprocessing_start_method: MultiprocessingStartMethod::Auto,
+            multiprocessing_executable: ProgramPath::Default,
             sys_frozen: true,
             sys_meipass: false,
+            sys_executable: ProgramPath::Default,
+            sys_argv0: ProgramPath::Default,
             terminfo_resolution: TerminfoResolution::None,
             tcl_library: None,
             write_modules_directory_env: None,
+            startup_profile_env: None,
+            host_requirements_check: false,
+            host_requirements: HostRequirements::default(),
+            sandbox_mode: SandboxMode::Off,
+            sandbox_policy: SandboxPolicy::default(),
+            migrations: vec![],
+            migrations_app_name: None,
+            setproctitle_module: false,
+            site_module: None,
+            lazy_import_packages: vec![],
+            filesystem_first_packages: vec![],
+            materialized_files_dir: None,
+            config_overrides_path: None,
+            config_overrides_allowed: vec![],
+            environment_overrides_prefix: None,
+            environment_overrides_allowed: vec![],
+            build_info: None,
+            log_destination: None,
+            log_filter: None,
+            log_filter_env: None,
+            log_register_event_source: false,
+            traceback_source_paths: false,
+            replace_missing_stdio: false,
         }
     }
 }
@@ -202,6 +573,7 @@ impl PyembedPythonInterpreterConfig {
             buffered_stdio: {},\n        \
             bytes_warning: {},\n        \
             check_hash_pycs_mode: {},\n        \
+            code_debug_ranges: {},\n        \
             configure_c_stdio: {},\n        \
             dump_refs: {},\n        \
             exec_prefix: {},\n        \
@@ -214,6 +586,7 @@ impl PyembedPythonInterpreterConfig {
             import_time: {},\n        \
             inspect: {},\n        \
             install_signal_handlers: {},\n        \
+            int_max_str_digits: {},\n        \
             interactive: {},\n        \
             legacy_windows_stdio: {},\n        \
             malloc_stats: {},\n        \
@@ -221,6 +594,7 @@ impl PyembedPythonInterpreterConfig {
             optimization_level: {},\n        \
             parser_debug: {},\n        \
             pathconfig_warnings: {},\n        \
+            perf_profiling: {},\n        \
             prefix: {},\n        \
             program_name: {},\n        \
             pycache_prefix: {},\n        \
@@ -229,6 +603,7 @@ impl PyembedPythonInterpreterConfig {
             run_command: {},\n        \
             run_filename: {},\n        \
             run_module: {},\n        \
+            safe_path: {},\n        \
             show_ref_count: {},\n        \
             site_import: {},\n        \
             skip_first_source_line: {},\n        \
@@ -247,20 +622,59 @@ impl PyembedPythonInterpreterConfig {
             allocator_obj: {},\n    \
             allocator_pymalloc_arena: {},\n    \
             allocator_debug: {},\n    \
+            allocator_stats: {},\n    \
             set_missing_path_configuration: {},\n    \
             oxidized_importer: {},\n    \
             filesystem_importer: {},\n    \
+            zipimport_importer: {},\n    \
+            extra_sys_paths: vec![{}],\n    \
+            extra_sys_paths_env: {},\n    \
             packed_resources: {},\n    \
+            packed_resources_key: {},\n    \
+            packed_resources_verification: {},\n    \
             extra_extension_modules: None,\n    \
+            startup_hooks: vec![],\n    \
+            stdout_callback: None,\n    \
+            stderr_callback: None,\n    \
+            stdio_callback_fds: false,\n    \
+            audit_callback: None,\n    \
+            audit_events: vec![{}],\n    \
+            host_signals: vec![],\n    \
             argv: None,\n    \
             argvb: {},\n    \
+            console: {},\n    \
+            console_argument: {},\n    \
             multiprocessing_auto_dispatch: {},\n    \
             multiprocessing_start_method: {},\n    \
+            multiprocessing_executable: {},\n    \
             sys_frozen: {},\n    \
             sys_meipass: {},\n    \
+            sys_executable: {},\n    \
+            sys_argv0: {},\n    \
             terminfo_resolution: {},\n    \
             tcl_library: {},\n    \
             write_modules_directory_env: {},\n    \
+            startup_profile_env: {},\n    \
+            host_requirements: {},\n    \
+            sandbox_policy: {},\n    \
+            migrations: vec![{}],\n    \
+            migrations_app_name: {},\n    \
+            setproctitle_module: {},\n    \
+            site_module: {},\n    \
+            lazy_import_packages: vec![{}],\n    \
+            filesystem_first_packages: vec![{}],\n    \
+            materialized_files_dir: {},\n    \
+            config_overrides_path: {},\n    \
+            config_overrides_allowed: vec![{}],\n    \
+            environment_overrides_prefix: {},\n    \
+            environment_overrides_allowed: vec![{}],\n    \
+            build_info: {},\n    \
+            log_destination: {},\n    \
+            log_filter: {},\n    \
+            log_filter_env: {},\n    \
+            log_register_event_source: {},\n    \
+            traceback_source_paths: {},\n    \
+            replace_missing_stdio: {},\n    \
             }}\n\
             ",
             match self.config.profile {
@@ -306,6 +720,7 @@ impl PyembedPythonInterpreterConfig {
                 Some(CheckHashPycsMode::Never) => "Some(pyembed::CheckHashPycsMode::Never)",
                 None => "None",
             },
+            optional_bool_to_string(&self.config.code_debug_ranges),
             optional_bool_to_string(&self.config.configure_c_stdio),
             optional_bool_to_string(&self.config.dump_refs),
             optional_pathbuf_to_string(&self.config.exec_prefix),
@@ -321,6 +736,10 @@ impl PyembedPythonInterpreterConfig {
             optional_bool_to_string(&self.config.import_time),
             optional_bool_to_string(&self.config.inspect),
             optional_bool_to_string(&self.config.install_signal_handlers),
+            match &self.config.int_max_str_digits {
+                Some(value) => format!("Some({})", value),
+                None => "None".to_string(),
+            },
             optional_bool_to_string(&self.config.interactive),
             optional_bool_to_string(&self.config.legacy_windows_stdio),
             optional_bool_to_string(&self.config.malloc_stats),
@@ -348,6 +767,7 @@ impl PyembedPythonInterpreterConfig {
             },
             optional_bool_to_string(&self.config.parser_debug),
             optional_bool_to_string(&self.config.pathconfig_warnings),
+            optional_bool_to_string(&self.config.perf_profiling),
             optional_pathbuf_to_string(&self.config.prefix),
             optional_pathbuf_to_string(&self.config.program_name),
             optional_pathbuf_to_string(&self.config.pycache_prefix),
@@ -356,6 +776,7 @@ impl PyembedPythonInterpreterConfig {
             optional_string_to_string(&self.config.run_command),
             optional_pathbuf_to_string(&self.config.run_filename),
             optional_string_to_string(&self.config.run_module),
+            optional_bool_to_string(&self.config.safe_path),
             optional_bool_to_string(&self.config.show_ref_count),
             optional_bool_to_string(&self.config.site_import),
             optional_bool_to_string(&self.config.skip_first_source_line),
@@ -379,9 +800,17 @@ impl PyembedPythonInterpreterConfig {
             self.allocator_obj,
             self.allocator_pymalloc_arena,
             self.allocator_debug,
+            self.allocator_stats,
             self.set_missing_path_configuration,
             self.oxidized_importer,
             self.filesystem_importer,
+            self.zipimport_importer,
+            self.extra_sys_paths
+                .iter()
+                .map(|x| path_to_string(x))
+                .collect::<Vec<_>>()
+                .join(", "),
+            optional_string_to_string(&self.extra_sys_paths_env),
             format!(
                 "vec![{}]",
                 self.packed_resources
@@ -389,7 +818,25 @@ impl PyembedPythonInterpreterConfig {
                     .map(|e| e.to_string())
                     .join(", ")
             ),
+            match &self.packed_resources_key {
+                Some(key) => format!("Some({})", key),
+                None => "None".to_string(),
+            },
+            match self.packed_resources_verification {
+                PackedResourcesVerification::None => "pyembed::PackedResourcesVerification::None",
+                PackedResourcesVerification::OnLoad =>
+                    "pyembed::PackedResourcesVerification::OnLoad",
+                PackedResourcesVerification::Startup =>
+                    "pyembed::PackedResourcesVerification::Startup",
+            },
+            self.audit_events
+                .iter()
+                .map(|x| format!("\"{}\".to_string()", x.escape_default()))
+                .collect::<Vec<_>>()
+                .join(", "),
             self.argvb,
+            self.console,
+            optional_string_to_string(&self.console_argument),
             self.multiprocessing_auto_dispatch,
             match self.multiprocessing_start_method {
                 MultiprocessingStartMethod::None =>
@@ -403,8 +850,11 @@ impl PyembedPythonInterpreterConfig {
                 MultiprocessingStartMethod::Auto =>
                     "pyembed::MultiprocessingStartMethod::Auto".to_string(),
             },
+            program_path_to_string(&self.multiprocessing_executable),
             self.sys_frozen,
             self.sys_meipass,
+            program_path_to_string(&self.sys_executable),
+            program_path_to_string(&self.sys_argv0),
             match self.terminfo_resolution {
                 TerminfoResolution::Dynamic => "pyembed::TerminfoResolution::Dynamic".to_string(),
                 TerminfoResolution::None => "pyembed::TerminfoResolution::None".to_string(),
@@ -414,6 +864,64 @@ impl PyembedPythonInterpreterConfig {
             },
             optional_pathbuf_to_string(&self.tcl_library),
             optional_string_to_string(&self.write_modules_directory_env),
+            optional_string_to_string(&self.startup_profile_env),
+            if self.host_requirements_check {
+                format!(
+                    "Some({})",
+                    host_requirements_to_string(&self.host_requirements)
+                )
+            } else {
+                "None".to_string()
+            },
+            if self.sandbox_mode == SandboxMode::Enforce {
+                format!("Some({})", sandbox_policy_to_string(&self.sandbox_policy))
+            } else {
+                "None".to_string()
+            },
+            self.migrations
+                .iter()
+                .map(migration_to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            optional_string_to_string(&self.migrations_app_name),
+            self.setproctitle_module,
+            optional_string_to_string(&self.site_module),
+            self.lazy_import_packages
+                .iter()
+                .map(|x| format!("\"{}\".to_string()", x.escape_default()))
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.filesystem_first_packages
+                .iter()
+                .map(|x| format!("\"{}\".to_string()", x.escape_default()))
+                .collect::<Vec<_>>()
+                .join(", "),
+            optional_pathbuf_to_string(&self.materialized_files_dir),
+            optional_pathbuf_to_string(&self.config_overrides_path),
+            self.config_overrides_allowed
+                .iter()
+                .map(|x| format!("\"{}\".to_string()", x.escape_default()))
+                .collect::<Vec<_>>()
+                .join(", "),
+            optional_string_to_string(&self.environment_overrides_prefix),
+            self.environment_overrides_allowed
+                .iter()
+                .map(|x| format!("\"{}\".to_string()", x.escape_default()))
+                .collect::<Vec<_>>()
+                .join(", "),
+            match &self.build_info {
+                Some(value) => format!("Some({})", build_info_to_string(value)),
+                None => "None".to_string(),
+            },
+            match &self.log_destination {
+                Some(value) => format!("Some({})", log_destination_to_string(value)),
+                None => "None".to_string(),
+            },
+            optional_string_to_string(&self.log_filter),
+            optional_string_to_string(&self.log_filter_env),
+            self.log_register_event_source,
+            self.traceback_source_paths,
+            self.replace_missing_stdio,
         );
 
         Ok(code)
@@ -500,6 +1008,29 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_serialize_packed_resources_section() -> Result<()> {
+        let config = PyembedPythonInterpreterConfig {
+            packed_resources: vec![
+                PyembedPackedResourcesSource::MemoryIncludeBytesInSection(
+                    PathBuf::from("packed-resources"),
+                    "__TEXT,__pyoxres".to_string(),
+                    4096,
+                ),
+                PyembedPackedResourcesSource::ExecutableOverlay,
+            ],
+            ..Default::default()
+        };
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+
+        assert!(code.contains("#[repr(C, align(4096))]"));
+        assert!(code.contains("#[link_section = \"__TEXT,__pyoxres\"]"));
+        assert!(code.contains("pyembed::PackedResourcesSource::ExecutableOverlay"));
+
+        Ok(())
+    }
+
     #[test]
     fn test_backslash_in_path() -> Result<()> {
         let config = PyembedPythonInterpreterConfig {
@@ -515,6 +1046,211 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_serialize_host_requirements() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "host_requirements: None,")?;
+
+        config.host_requirements_check = true;
+        config.host_requirements =
+            default_host_requirements("x86_64_v2-unknown-linux-gnu", "3.10", None, Some("2.28"));
+        config.host_requirements.message = Some("get a \"newer\" machine".to_string());
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "host_requirements: Some(pyembed::HostRequirements { \
+            glibc_version: Some(\"2.28\".to_string()), \
+            macos_version: None, \
+            windows_build: None, \
+            cpu_features: vec![\"cmpxchg16b\".to_string(), \"popcnt\".to_string(), \
+            \"sse3\".to_string(), \"sse4.1\".to_string(), \"sse4.2\".to_string(), \
+            \"ssse3\".to_string()], \
+            message: Some(\"get a \\\"newer\\\" machine\".to_string()) }),",
+        )
+    }
+
+    #[test]
+    fn test_serialize_build_info() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "build_info: None,")?;
+
+        config.build_info = Some(BuildInfo {
+            target_triple: "x86_64-unknown-linux-gnu".to_string(),
+            python_version: "3.10.9".to_string(),
+            python_flavor: "standalone-static".to_string(),
+            resources_location: "in-memory".to_string(),
+            resources_location_fallback: Some("filesystem-relative:lib".to_string()),
+            features: vec!["build-mode-prebuilt-artifacts".to_string()],
+        });
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "build_info: Some(pyembed::BuildInfo { \
+            target_triple: \"x86_64-unknown-linux-gnu\".to_string(), \
+            python_version: \"3.10.9\".to_string(), \
+            python_flavor: \"standalone-static\".to_string(), \
+            resources_location: \"in-memory\".to_string(), \
+            resources_location_fallback: Some(\"filesystem-relative:lib\".to_string()), \
+            features: vec![\"build-mode-prebuilt-artifacts\".to_string()] }),",
+        )
+    }
+
+    #[test]
+    fn test_serialize_log_destination() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "log_destination: None,")?;
+
+        config.log_destination = Some(LogDestination::Journald);
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "log_destination: Some(pyembed::LogDestination::Journald),",
+        )?;
+
+        config.log_destination = Some(LogDestination::WindowsEventLog(Some(
+            "My Service".to_string(),
+        )));
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "log_destination: Some(pyembed::LogDestination::WindowsEventLog(Some(\"My Service\".to_string()))),",
+        )?;
+
+        config.log_destination = Some(LogDestination::Etw(
+            "9e4a3b8c-7d21-4f6a-b0c5-2e8d1f7a6c34".to_string(),
+        ));
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "log_destination: Some(pyembed::LogDestination::Etw(\"9e4a3b8c-7d21-4f6a-b0c5-2e8d1f7a6c34\".to_string())),",
+        )?;
+
+        config.log_destination = Some(LogDestination::File("$ORIGIN/app.log".to_string()));
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "log_destination: Some(pyembed::LogDestination::File(\"$ORIGIN/app.log\".to_string())),",
+        )
+    }
+
+    #[test]
+    fn test_serialize_packed_resources_key() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "packed_resources_key: None,")?;
+
+        config.packed_resources_key = Some(PyembedPackedResourcesKey::Key([1; 32]));
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            &format!(
+                "packed_resources_key: Some(pyembed::PackedResourcesKey::Key({:?})),",
+                [1u8; 32]
+            ),
+        )?;
+
+        config.packed_resources_key = Some(PyembedPackedResourcesKey::Callback(
+            "crate::resources_key".to_string(),
+        ));
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "packed_resources_key: Some(pyembed::PackedResourcesKey::Callback(crate::resources_key)),",
+        )
+    }
+
+    #[test]
+    fn test_serialize_sandbox_policy() -> Result<()> {
+        let mut config = PyembedPythonInterpreterConfig {
+            sandbox_mode: SandboxMode::Profiles,
+            sandbox_policy: SandboxPolicy {
+                allow_network: true,
+                allow_subprocess: false,
+                read_paths: None,
+                write_paths: Some(vec!["$ORIGIN/data".to_string()]),
+            },
+            ..Default::default()
+        };
+
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(&code, "sandbox_policy: None,")?;
+
+        config.sandbox_mode = SandboxMode::Enforce;
+        let code = config.to_oxidized_python_interpreter_config_rs()?;
+        assert_contains(
+            &code,
+            "sandbox_policy: Some(pyembed::SandboxPolicy { \
+            allow_network: true, \
+            allow_subprocess: false, \
+            read_paths: None, \
+            write_paths: Some(vec![\"$ORIGIN/data\".to_string()]) }),",
+        )
+    }
+
+    #[test]
+    fn test_default_host_requirements() {
+        let requirements =
+            default_host_requirements("x86_64-unknown-linux-gnu", "3.10", None, None);
+        assert_eq!(requirements.glibc_version, None);
+        assert!(requirements.cpu_features.is_empty());
+
+        let requirements =
+            default_host_requirements("x86_64-unknown-linux-gnu", "3.10", None, Some("2.28"));
+        assert_eq!(requirements.glibc_version, Some("2.28".to_string()));
+
+        let requirements =
+            default_host_requirements("x86_64_v3-unknown-linux-gnu", "3.10", None, None);
+        assert!(requirements.cpu_features.contains(&"sse4.2".to_string()));
+        assert!(requirements.cpu_features.contains(&"avx2".to_string()));
+        assert!(!requirements.cpu_features.contains(&"avx512f".to_string()));
+
+        let requirements =
+            default_host_requirements("x86_64-unknown-linux-musl", "3.10", None, Some("2.28"));
+        assert_eq!(requirements, HostRequirements::default());
+
+        let requirements =
+            default_host_requirements("aarch64-apple-darwin", "3.10", Some("11.0"), Some("2.28"));
+        assert_eq!(requirements.macos_version, Some("11.0".to_string()));
+        assert_eq!(requirements.glibc_version, None);
+
+        let requirements = default_host_requirements("x86_64-pc-windows-msvc", "3.8", None, None);
+        assert_eq!(requirements.windows_build, Some(7601));
+        let requirements = default_host_requirements("x86_64-pc-windows-msvc", "3.10", None, None);
+        assert_eq!(requirements.windows_build, Some(9600));
+    }
+
+    #[test]
+    fn test_sandbox_profile_files() {
+        let policy = SandboxPolicy::default();
+
+        assert!(sandbox_enforcement_supported("x86_64-unknown-linux-gnu"));
+        assert!(sandbox_enforcement_supported("aarch64-unknown-linux-musl"));
+        assert!(sandbox_enforcement_supported("aarch64-apple-darwin"));
+        assert!(!sandbox_enforcement_supported("i686-unknown-linux-gnu"));
+        assert!(!sandbox_enforcement_supported("x86_64-pc-windows-msvc"));
+
+        let files = sandbox_profile_files(&policy, "myapp", "x86_64-unknown-linux-gnu");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, "myapp.apparmor");
+        assert!(files[0].1.contains("profile myapp "));
+
+        let files = sandbox_profile_files(&policy, "myapp", "x86_64-apple-darwin");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, "myapp.sb");
+        assert!(files[0].1.starts_with("(version 1)\n"));
+
+        assert!(sandbox_profile_files(&policy, "myapp", "x86_64-pc-windows-msvc").is_empty());
+    }
+
     // TODO enable once CI has a linkable Python.
     #[test]
     #[ignore]
@@ -543,6 +1279,7 @@ mod tests {
                 buffered_stdio: Some(false),
                 bytes_warning: Some(BytesWarning::Raise),
                 check_hash_pycs_mode: Some(CheckHashPycsMode::Always),
+                code_debug_ranges: Some(false),
                 configure_c_stdio: Some(true),
                 dump_refs: Some(true),
                 exec_prefix: Some("path".into()),
@@ -555,6 +1292,7 @@ mod tests {
                 import_time: Some(true),
                 inspect: Some(false),
                 install_signal_handlers: Some(true),
+                int_max_str_digits: Some(5000),
                 interactive: Some(true),
                 legacy_windows_stdio: Some(false),
                 malloc_stats: Some(false),
@@ -562,6 +1300,7 @@ mod tests {
                 optimization_level: Some(BytecodeOptimizationLevel::One),
                 parser_debug: Some(true),
                 pathconfig_warnings: Some(false),
+                perf_profiling: Some(true),
                 prefix: Some("prefix".into()),
                 program_name: Some("program_name".into()),
                 pycache_prefix: Some("prefix".into()),
@@ -570,6 +1309,7 @@ mod tests {
                 run_command: Some("command".into()),
                 run_filename: Some("filename".into()),
                 run_module: Some("module".into()),
+                safe_path: Some(true),
                 show_ref_count: Some(false),
                 site_import: Some(true),
                 skip_first_source_line: Some(false),
@@ -588,23 +1328,86 @@ mod tests {
             allocator_obj: true,
             allocator_pymalloc_arena: true,
             allocator_debug: true,
+            allocator_stats: true,
             set_missing_path_configuration: false,
             oxidized_importer: true,
             filesystem_importer: true,
+            zipimport_importer: true,
+            extra_sys_paths: vec!["$ORIGIN/plugins".into()],
+            extra_sys_paths_env: Some("APP_PLUGINS_PATH".into()),
             packed_resources: vec![
                 PyembedPackedResourcesSource::MemoryIncludeBytes(PathBuf::from("packed-resources")),
                 PyembedPackedResourcesSource::MemoryMappedPath(PathBuf::from(
                     "$ORIGIN/packed-resources",
                 )),
+                PyembedPackedResourcesSource::MemoryMappedPathVerified(
+                    PathBuf::from("$ORIGIN/all_config_fields.pyoxy-resources"),
+                    [42; 32],
+                ),
+                PyembedPackedResourcesSource::MemoryIncludeBytesInSection(
+                    PathBuf::from("packed-resources"),
+                    crate::py_packaging::binary::default_packed_resources_section(
+                        default_target_triple(),
+                    )
+                    .to_string(),
+                    64,
+                ),
+                PyembedPackedResourcesSource::ExecutableOverlay,
             ],
+            packed_resources_key: Some(PyembedPackedResourcesKey::Key([42; 32])),
+            packed_resources_verification: PackedResourcesVerification::OnLoad,
+            audit_events: vec!["import".into(), "subprocess".into()],
             argvb: true,
+            console: true,
+            console_argument: Some("--console".into()),
             sys_frozen: false,
             sys_meipass: true,
+            sys_executable: ProgramPath::Empty,
+            sys_argv0: ProgramPath::Static("$ORIGIN/app".into()),
             terminfo_resolution: TerminfoResolution::Dynamic,
             tcl_library: Some("path".into()),
             write_modules_directory_env: Some("env".into()),
+            startup_profile_env: Some("profile_env".into()),
+            host_requirements_check: true,
+            host_requirements: HostRequirements {
+                glibc_version: Some("2.17".into()),
+                macos_version: Some("10.9".into()),
+                windows_build: Some(9600),
+                cpu_features: vec!["sse4.2".into()],
+                message: Some("message".into()),
+            },
+            sandbox_mode: SandboxMode::Enforce,
+            sandbox_policy: SandboxPolicy {
+                allow_network: true,
+                allow_subprocess: true,
+                read_paths: Some(vec!["/etc/app".into()]),
+                write_paths: Some(vec!["$ORIGIN/data".into()]),
+            },
+            migrations: vec![Migration {
+                version: "1.1".into(),
+                module: "myapp.migrations".into(),
+                function: "move_cache".into(),
+            }],
+            migrations_app_name: Some("myapp".into()),
+            setproctitle_module: true,
+            site_module: Some("import sys\nsys.flags\n".into()),
+            lazy_import_packages: vec!["foo".into(), "bar.baz".into()],
+            filesystem_first_packages: vec!["foo".into()],
+            materialized_files_dir: Some("$ORIGIN/materialized".into()),
+            config_overrides_path: Some("$ORIGIN/app.toml".into()),
+            config_overrides_allowed: vec!["verbose".into(), "run_module".into()],
+            environment_overrides_prefix: Some("MYAPP".into()),
+            environment_overrides_allowed: vec!["verbose".into(), "module_search_paths".into()],
+            build_info: None,
+            log_destination: Some(LogDestination::File("$ORIGIN/app.log".into())),
+            log_filter: Some("info,app=debug".into()),
+            log_filter_env: Some("RUST_LOG".into()),
+            log_register_event_source: true,
+            traceback_source_paths: true,
+            replace_missing_stdio: true,
             multiprocessing_auto_dispatch: false,
             multiprocessing_start_method: MultiprocessingStartMethod::Spawn,
+            multiprocessing_executable: ProgramPath::Invoked,
         };
 
         let builder = dist.as_python_executable_builder(
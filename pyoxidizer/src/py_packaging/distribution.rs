@@ -12,7 +12,10 @@ use {
         config::PyembedPythonInterpreterConfig,
         standalone_distribution::StandaloneDistribution,
     },
-    crate::{environment::Environment, python_distributions::PYTHON_DISTRIBUTIONS},
+    crate::{
+        build_inputs, environment::Environment, metrics::CacheMetrics,
+        python_distributions::PYTHON_DISTRIBUTIONS,
+    },
     anyhow::{anyhow, Context, Result},
     fs2::FileExt,
     log::info,
@@ -30,7 +33,10 @@ use {
         io::Read,
         ops::DerefMut,
         path::{Path, PathBuf},
-        sync::{Arc, Mutex},
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
     },
     tugger_common::http::get_http_client,
     url::Url,
@@ -428,6 +434,11 @@ pub fn resolve_python_distribution_from_location(
         PythonDistributionLocation::Url { sha256, .. } => sha256,
     };
 
+    build_inputs::record_tool(
+        format!("python distribution {}", location),
+        distribution_hash,
+    );
+
     let distribution_path = distributions_dir.join(format!("python.{}", &distribution_hash[0..12]));
 
     Ok((path, distribution_path))
@@ -484,6 +495,8 @@ type DistributionCacheValue = Arc<Mutex<Option<Arc<StandaloneDistribution>>>>;
 pub struct DistributionCache {
     cache: Mutex<HashMap<DistributionCacheKey, DistributionCacheValue>>,
     default_dest_dir: Option<PathBuf>,
+    hits: AtomicU64,
+    misses: AtomicU64,
 }
 
 impl DistributionCache {
@@ -491,6 +504,16 @@ impl DistributionCache {
         Self {
             cache: Mutex::new(HashMap::new()),
             default_dest_dir: default_dest_dir.map(|x| x.to_path_buf()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Obtain how many distributions were resolved from this cache.
+    pub fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
         }
     }
 
@@ -565,8 +588,11 @@ impl DistributionCache {
         let value = lock.deref_mut();
 
         if let Some(dist) = value {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+
             Ok(dist.clone())
         } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
             let dist = Arc::new(StandaloneDistribution::from_location(location, dest_dir)?);
 
             lock.replace(dist.clone());
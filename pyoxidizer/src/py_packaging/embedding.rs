@@ -11,6 +11,7 @@ use {
         BuildFlags, InterpreterConfig as PyO3InterpreterConfig, PythonImplementation, PythonVersion,
     },
     python_packaging::{
+        interpreter::PackedResourcesVerification,
         licensing::{LicensedComponent, LicensedComponents},
         resource_collection::CompiledResourcesCollection,
     },
@@ -18,6 +19,44 @@ use {
     std::path::{Path, PathBuf},
 };
 
+/// Serialize packed resources data.
+///
+/// Version 4 data recording content digests is written if `content_digests` is
+/// true. Otherwise version 3 data is written. The data is encrypted if a key is
+/// given.
+pub fn serialize_packed_resources(
+    collection: &CompiledResourcesCollection,
+    encryption_key: Option<&[u8; 32]>,
+    content_digests: bool,
+) -> Result<Vec<u8>> {
+    let mut buffer = vec![];
+    if content_digests {
+        collection.write_packed_resources_v4(&mut buffer)
+    } else {
+        collection.write_packed_resources(&mut buffer)
+    }
+    .context("serializing packed resources")?;
+
+    if let Some(key) = encryption_key {
+        buffer = encrypt_packed_resources(&buffer, key)?;
+    }
+
+    Ok(buffer)
+}
+
+#[cfg(feature = "encryption")]
+fn encrypt_packed_resources(data: &[u8], key: &[u8; 32]) -> Result<Vec<u8>> {
+    python_packed_resources::encrypt_packed_resources(data, key)
+        .map_err(|e| anyhow!("encrypting packed resources: {}", e))
+}
+
+#[cfg(not(feature = "encryption"))]
+fn encrypt_packed_resources(_data: &[u8], _key: &[u8; 32]) -> Result<Vec<u8>> {
+    Err(anyhow!(
+        "encrypting packed resources requires PyOxidizer built with the encryption feature"
+    ))
+}
+
 /// Describes extra behavior for a linker invocation.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum LinkingAnnotation {
@@ -290,6 +329,12 @@ pub struct EmbeddedPythonContext<'a> {
     /// Python resources that need to be serialized to a file.
     pub pending_resources: Vec<(CompiledResourcesCollection<'a>, PathBuf)>,
 
+    /// Serialized resources data to append to the produced binary.
+    pub executable_overlay: Option<Vec<u8>>,
+
+    /// Key to encrypt packed resources with.
+    pub packed_resources_encryption_key: Option<[u8; 32]>,
+
     /// Extra files to install next to produced binary.
     pub extra_files: FileManifest,
 
@@ -376,13 +421,13 @@ impl<'a> EmbeddedPythonContext<'a> {
         for (collection, path) in &self.pending_resources {
             let dest_path = dest_dir.as_ref().join(path);
 
-            let mut writer = std::io::BufWriter::new(
-                std::fs::File::create(&dest_path)
-                    .with_context(|| format!("opening {} for writing", dest_path.display()))?,
-            );
-            collection
-                .write_packed_resources(&mut writer)
-                .context("writing packed resources")?;
+            let data = serialize_packed_resources(
+                collection,
+                self.packed_resources_encryption_key.as_ref(),
+                self.config.packed_resources_verification != PackedResourcesVerification::None,
+            )?;
+            std::fs::write(&dest_path, data)
+                .with_context(|| format!("writing {}", dest_path.display()))?;
         }
 
         Ok(())
@@ -17,5 +17,6 @@ pub mod filtering;
 pub mod libpython;
 pub mod packaging_tool;
 pub mod resource;
+pub mod resource_scanners;
 pub mod standalone_builder;
 pub mod standalone_distribution;
@@ -11,18 +11,24 @@ use {
         binary::LibpythonLinkMode, distribution::PythonDistribution,
         distutils::read_built_extensions, standalone_distribution::resolve_python_paths,
     },
-    crate::environment::Environment,
+    crate::{
+        build_inputs,
+        environment::{canonicalize_path, Environment},
+    },
     anyhow::{anyhow, Context, Result},
     duct::{cmd, ReaderHandle},
-    log::warn,
+    log::{info, warn},
     python_packaging::{
-        filesystem_scanning::find_python_resources, policy::PythonPackagingPolicy,
-        resource::PythonResource, wheel::WheelArchive,
+        filesystem_scanning::find_python_resources,
+        policy::{PythonPackagingPolicy, StaticRebuildMode},
+        resource::{PythonPathExtension, PythonPathExtensionEntry, PythonResource},
+        wheel::WheelArchive,
     },
+    simple_file_manifest::FileData,
     std::{
-        collections::{hash_map::RandomState, HashMap},
+        collections::{hash_map::RandomState, BTreeSet, HashMap},
         hash::BuildHasher,
-        io::{BufRead, BufReader},
+        io::{BufRead, BufReader, Cursor, Read},
         path::{Path, PathBuf},
     },
 };
@@ -66,7 +72,16 @@ pub fn find_resources<'a>(
         policy.file_scanner_emit_files(),
         policy.file_scanner_classify_files(),
     )? {
-        let r = r?.to_memory()?;
+        let r = r?;
+
+        if let PythonResource::PathExtension(pe) = &r {
+            res.extend(
+                resolve_path_extension(dist, policy, Some(path), pe)
+                    .context("resolving .pth file")?,
+            );
+        }
+
+        let r = r.to_memory()?;
 
         match r {
             PythonResource::ExtensionModule(e) => {
@@ -83,6 +98,220 @@ pub fn find_resources<'a>(
         }
     }
 
+    static_rebuild_extension_modules(policy, res)
+}
+
+/// Extract the members of a static library archive.
+fn static_archive_objects(data: &[u8]) -> Result<Vec<FileData>> {
+    let mut archive = ar::Archive::new(Cursor::new(data));
+    let mut res = vec![];
+
+    while let Some(entry) = archive.next_entry() {
+        let mut entry = entry?;
+
+        let identifier = entry.header().identifier();
+        if identifier.is_empty() || identifier.starts_with(b"__.SYMDEF") {
+            continue;
+        }
+
+        let mut object = vec![];
+        entry.read_to_end(&mut object)?;
+        res.push(FileData::Memory(object));
+    }
+
+    Ok(res)
+}
+
+/// Convert extension modules to statically linkable variants, as allowed by the policy.
+///
+/// Extension modules whose package contains a static archive of the extension
+/// (e.g. `_foo.a`, `lib_foo.a`, or `_foo.lib` for extension `pkg._foo`) have
+/// that archive's object files attached so the extension can be linked into
+/// the binary as a built-in. The static archive resource is consumed.
+///
+/// Errors if the policy requires static relinking and an extension module
+/// doesn't have a static archive.
+pub fn static_rebuild_extension_modules<'a>(
+    policy: &PythonPackagingPolicy,
+    resources: Vec<PythonResource<'a>>,
+) -> Result<Vec<PythonResource<'a>>> {
+    if policy.static_rebuild() == StaticRebuildMode::Never {
+        return Ok(resources);
+    }
+
+    let mut archives = HashMap::new();
+    for r in &resources {
+        if let PythonResource::PackageResource(resource) = r {
+            archives.insert(
+                (
+                    resource.leaf_package.clone(),
+                    resource.relative_name.clone(),
+                ),
+                resource.data.clone(),
+            );
+        }
+    }
+
+    let mut consumed = BTreeSet::new();
+    let mut res = vec![];
+
+    for r in resources {
+        match r {
+            PythonResource::ExtensionModule(em)
+                if !em.is_stdlib && em.object_file_data.is_empty() =>
+            {
+                let (package, leaf) = match em.name.rsplit_once('.') {
+                    Some((package, leaf)) => (package.to_string(), leaf.to_string()),
+                    None => (String::new(), em.name.clone()),
+                };
+
+                let archive = [
+                    format!("{}.a", leaf),
+                    format!("lib{}.a", leaf),
+                    format!("{}.lib", leaf),
+                ]
+                .into_iter()
+                .find_map(|name| {
+                    let key = (package.clone(), name);
+                    archives.get(&key).map(|data| (key, data))
+                });
+
+                if let Some((key, data)) = archive {
+                    info!(
+                        "statically relinking extension module {} from {}/{}",
+                        em.name, key.0, key.1
+                    );
+
+                    let mut em = em.into_owned();
+                    em.object_file_data = static_archive_objects(&data.resolve_content()?)
+                        .with_context(|| format!("reading static archive {}/{}", key.0, key.1))?;
+                    em.init_fn = Some(format!("PyInit_{}", leaf));
+
+                    consumed.insert(key);
+                    res.push(em.into());
+                } else if policy.static_rebuild() == StaticRebuildMode::Required {
+                    return Err(anyhow!(
+                        "static relinking of extension module {} required but no static archive found",
+                        em.name
+                    ));
+                } else {
+                    res.push(PythonResource::ExtensionModule(em));
+                }
+            }
+            r => res.push(r),
+        }
+    }
+
+    Ok(res
+        .into_iter()
+        .filter(|r| match r {
+            PythonResource::PackageResource(resource) => !consumed.contains(&(
+                resource.leaf_package.clone(),
+                resource.relative_name.clone(),
+            )),
+            _ => true,
+        })
+        .collect::<Vec<_>>())
+}
+
+/// Translate a `.pth` file into equivalent resources.
+///
+/// `.pth` files are processed by `site` at interpreter startup. Packaged
+/// applications don't process them, so their behavior needs to be captured
+/// at packaging time.
+///
+/// `site_dir` is the directory the `.pth` file is installed in. It is `None`
+/// if the `.pth` file isn't installed, as is the case for `.pth` files in
+/// wheels. Path entries are relative to the directory the `.pth` file is
+/// installed in, so they can't be resolved then.
+///
+/// Path entries referring to existing directories outside of `site_dir` are
+/// scanned for resources, which are returned. Path entries that can't be
+/// resolved and lines executing Python code can't be translated: a warning
+/// describing the behavior that will be lost is emitted for each.
+pub fn resolve_path_extension<'a>(
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    site_dir: Option<&Path>,
+    extension: &PythonPathExtension,
+) -> Result<Vec<PythonResource<'a>>> {
+    let pth_name = extension
+        .data
+        .backing_path()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| "<memory>".to_string());
+
+    let mut res = vec![];
+
+    for entry in extension.entries()? {
+        match entry {
+            PythonPathExtensionEntry::Path(entry_path) => {
+                let site_dir = if let Some(site_dir) = site_dir {
+                    site_dir
+                } else {
+                    warn!(
+                        "{}: path {} is relative to the directory the .pth file is installed \
+                        in, which isn't known; ignoring",
+                        pth_name, entry_path
+                    );
+                    continue;
+                };
+
+                let resolved = site_dir.join(&entry_path);
+
+                if !resolved.is_dir() {
+                    warn!("{}: path {} does not exist; ignoring", pth_name, entry_path);
+                    continue;
+                }
+
+                // Paths are compared after resolving `..` components and symlinks.
+                // Otherwise entries like `../src` would appear to be within `site_dir`.
+                let resolved = canonicalize_path(&resolved)
+                    .with_context(|| format!("canonicalizing {}", resolved.display()))?;
+                let canonical_site_dir = canonicalize_path(site_dir)
+                    .with_context(|| format!("canonicalizing {}", site_dir.display()))?;
+
+                if resolved.starts_with(&canonical_site_dir) {
+                    warn!(
+                        "{}: path {} is within {}; its content will not be importable \
+                        as top-level modules in the packaged application",
+                        pth_name,
+                        entry_path,
+                        site_dir.display()
+                    );
+                } else {
+                    info!(
+                        "{}: collecting resources from path {}",
+                        pth_name,
+                        resolved.display()
+                    );
+
+                    for r in find_python_resources(
+                        &resolved,
+                        dist.cache_tag(),
+                        &dist.python_module_suffixes()?,
+                        policy.file_scanner_emit_files(),
+                        policy.file_scanner_classify_files(),
+                    )? {
+                        match r? {
+                            // We don't follow .pth files recursively, just as site doesn't.
+                            PythonResource::PathExtension(_) => {}
+                            r => res.push(r.to_memory()?),
+                        }
+                    }
+                }
+            }
+            PythonPathExtensionEntry::Import(code) => {
+                warn!(
+                    "{}: code `{}` is executed by site at startup but will not be \
+                    executed in the packaged application; features relying on it (such \
+                    as editable install finders or namespace package shims) will not work",
+                    pth_name, code
+                );
+            }
+        }
+    }
+
     Ok(res)
 }
 
@@ -179,17 +408,26 @@ pub fn pip_download<'a>(
     for path in &files {
         let wheel = WheelArchive::from_path(path)?;
 
-        res.extend(wheel.python_resources(
+        for r in wheel.python_resources(
             taget_dist.cache_tag(),
             &taget_dist.python_module_suffixes()?,
             policy.file_scanner_emit_files(),
             policy.file_scanner_classify_files(),
-        )?);
+        )? {
+            if let PythonResource::PathExtension(pe) = &r {
+                res.extend(
+                    resolve_path_extension(taget_dist, policy, None, pe)
+                        .context("resolving .pth file")?,
+                );
+            }
+
+            res.push(r);
+        }
     }
 
     temp_dir.close().context("closing temporary directory")?;
 
-    Ok(res)
+    static_rebuild_extension_modules(policy, res)
 }
 
 /// Run `pip install` and return found resources.
@@ -206,6 +444,7 @@ pub fn pip_install<'a, S: BuildHasher>(
 
     dist.ensure_pip()?;
 
+    build_inputs::record_env_prefixes(build_inputs::PIP_ENV_PREFIXES);
     let mut env: HashMap<String, String, RandomState> = std::env::vars().collect();
     for (k, v) in dist.resolve_distutils(libpython_link_mode, temp_dir.path(), &[])? {
         env.insert(k, v);
@@ -301,6 +540,7 @@ pub fn setup_py_install<'a, S: BuildHasher>(
 
     std::fs::create_dir_all(&python_paths.site_packages)?;
 
+    build_inputs::record_env_prefixes(build_inputs::PIP_ENV_PREFIXES);
     let mut envs: HashMap<String, String, RandomState> = std::env::vars().collect();
     for (k, v) in dist.resolve_distutils(
         libpython_link_mode,
@@ -368,7 +608,8 @@ mod tests {
     use {
         super::*,
         crate::testutil::*,
-        std::{collections::BTreeSet, ops::Deref},
+        python_packaging::resource::{PythonExtensionModule, PythonPackageResource},
+        std::ops::Deref,
     };
 
     #[test]
@@ -392,6 +633,88 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_find_resources_pth() -> Result<()> {
+        let env = get_env()?;
+        let distribution = get_default_distribution(None)?;
+        let policy = distribution.create_packaging_policy()?;
+
+        let temp_dir = env.temporary_directory("pyoxidizer-test")?;
+        let site_packages = temp_dir.path().join("site-packages");
+        let src_dir = temp_dir.path().join("src");
+        std::fs::create_dir_all(&site_packages)?;
+        std::fs::create_dir_all(src_dir.join("foo"))?;
+        std::fs::write(src_dir.join("foo").join("__init__.py"), "")?;
+        std::fs::write(
+            site_packages.join("foo.pth"),
+            "../src\nimport sys; sys.flags\n",
+        )?;
+
+        let resources = find_resources(distribution.deref(), &policy, &site_packages, None)?;
+
+        assert!(resources.iter().any(|r| r.full_name() == "foo"));
+        assert!(resources
+            .iter()
+            .any(|r| matches!(r, PythonResource::PathExtension(_))));
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_static_rebuild_extension_modules() -> Result<()> {
+        let mut builder = ar::Builder::new(vec![]);
+        builder.append(&ar::Header::new(b"ext.o".to_vec(), 3), &b"foo"[..])?;
+        let archive = builder.into_inner()?;
+
+        let em = PythonExtensionModule {
+            name: "pkg._ext".to_string(),
+            init_fn: None,
+            extension_file_suffix: ".so".to_string(),
+            shared_library: Some(FileData::Memory(vec![42])),
+            object_file_data: vec![],
+            is_package: false,
+            link_libraries: vec![],
+            is_stdlib: false,
+            builtin_default: false,
+            required: false,
+            variant: None,
+            license: None,
+        };
+        let static_archive = PythonPackageResource {
+            leaf_package: "pkg".to_string(),
+            relative_name: "_ext.a".to_string(),
+            data: FileData::Memory(archive),
+            is_stdlib: false,
+            is_test: false,
+            locale: None,
+        };
+        let resources: Vec<PythonResource> = vec![em.clone().into(), static_archive.into()];
+
+        let mut policy = PythonPackagingPolicy::default();
+        assert_eq!(
+            static_rebuild_extension_modules(&policy, resources.clone())?,
+            resources
+        );
+
+        policy.set_static_rebuild(StaticRebuildMode::IfAvailable);
+        let res = static_rebuild_extension_modules(&policy, resources.clone())?;
+        assert_eq!(res.len(), 1);
+        match &res[0] {
+            PythonResource::ExtensionModule(em) => {
+                assert_eq!(em.init_fn, Some("PyInit__ext".to_string()));
+                assert_eq!(em.object_file_data, vec![FileData::Memory(b"foo".to_vec())]);
+            }
+            _ => panic!("expected extension module"),
+        }
+
+        policy.set_static_rebuild(StaticRebuildMode::Required);
+        assert!(static_rebuild_extension_modules(&policy, vec![em.into()]).is_err());
+
+        Ok(())
+    }
+
     #[test]
     #[cfg(windows)]
     fn test_install_cffi() -> Result<()> {
@@ -0,0 +1,154 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Resource scanners running external commands.
+
+These allow plugging in support for custom file formats from the command
+line, via `pyoxidizer --resource-scanner SUFFIX COMMAND`.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    python_packaging::{
+        filesystem_scanning::{walk_tree_files, ResourceScanner},
+        resource::PythonResource,
+    },
+    simple_file_manifest::{File, FileData, FileEntry},
+    std::path::Path,
+};
+
+/// A [ResourceScanner] extracting files with an external command.
+///
+/// The command is invoked with the path of a file ending in the suffix and
+/// an empty output directory as arguments. Files written to the output
+/// directory are classified as if they were in the directory of the scanned
+/// file.
+#[derive(Clone, Debug)]
+pub struct CommandResourceScanner {
+    name: String,
+    suffix: String,
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandResourceScanner {
+    /// Construct an instance handling files ending in `suffix`.
+    ///
+    /// `command` is split on whitespace into a program and its leading
+    /// arguments.
+    pub fn new(suffix: &str, command: &str) -> Result<Self> {
+        let mut parts = command.split_whitespace().map(|s| s.to_string());
+        let program = parts
+            .next()
+            .ok_or_else(|| anyhow!("resource scanner command for {} is empty", suffix))?;
+
+        if suffix.is_empty() {
+            return Err(anyhow!("resource scanner suffix cannot be empty"));
+        }
+
+        Ok(Self {
+            name: format!("{} ({})", command, suffix),
+            suffix: suffix.to_string(),
+            program,
+            args: parts.collect(),
+        })
+    }
+}
+
+impl ResourceScanner for CommandResourceScanner {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn scan_file(
+        &self,
+        relative_path: &Path,
+        data: &FileData,
+    ) -> Result<Option<Vec<PythonResource<'static>>>> {
+        let file_name = match relative_path.file_name() {
+            Some(name) if name.to_string_lossy().ends_with(&self.suffix) => name,
+            _ => return Ok(None),
+        };
+
+        let temp_dir = tempfile::Builder::new()
+            .prefix("pyoxidizer-resource-scanner")
+            .tempdir()?;
+
+        let input_path = temp_dir.path().join(file_name);
+        let output_dir = temp_dir.path().join("output");
+        std::fs::write(&input_path, data.resolve_content()?)?;
+        std::fs::create_dir(&output_dir)?;
+
+        let output = std::process::Command::new(&self.program)
+            .args(&self.args)
+            .arg(&input_path)
+            .arg(&output_dir)
+            .output()
+            .with_context(|| format!("running {}", self.program))?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{} failed: {}\n{}",
+                self.program,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            ));
+        }
+
+        let parent = relative_path.parent().unwrap_or_else(|| Path::new(""));
+
+        let resources = walk_tree_files(&output_dir)
+            .map(|entry| {
+                let path = entry.path();
+                let rel_path = path.strip_prefix(&output_dir)?;
+                let entry = FileEntry::try_from(path)?;
+
+                Ok(File::new(
+                    parent.join(rel_path),
+                    FileEntry::new_from_data(entry.resolve_content()?, entry.is_executable()),
+                )
+                .into())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        temp_dir.close()?;
+
+        Ok(Some(resources))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        assert!(CommandResourceScanner::new(".bundle", "").is_err());
+        assert!(CommandResourceScanner::new("", "unbundle").is_err());
+
+        let scanner = CommandResourceScanner::new(".bundle", "unbundle --verbose").unwrap();
+        assert_eq!(scanner.program, "unbundle");
+        assert_eq!(scanner.args, vec!["--verbose"]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_scan_file() -> Result<()> {
+        let scanner = CommandResourceScanner::new(".bundle", "cp")?;
+        let data = FileData::Memory(b"data".to_vec());
+
+        assert_eq!(scanner.scan_file(Path::new("foo/a.txt"), &data)?, None);
+        assert_eq!(
+            scanner.scan_file(Path::new("foo/a.bundle"), &data)?,
+            Some(vec![File::new(
+                "foo/a.bundle",
+                FileEntry::new_from_data(b"data".to_vec(), false)
+            )
+            .into()])
+        );
+
+        Ok(())
+    }
+}
@@ -5,14 +5,24 @@
 use {
     super::{
         binary::{
+            default_packed_resources_section, validate_packed_resources_section, CargoProfile,
             LibpythonLinkMode, PackedResourcesLoadMode, PythonBinaryBuilder,
-            ResourceAddCollectionContextCallback, WindowsRuntimeDllsMode,
+            ResourceAddCollectionContextCallback, WindowsCrtLinkage, WindowsDpiAwareness,
+            WindowsRuntimeDllsMode, APPLICATION_LAYER_RESOURCES_EXTENSION,
+            DEFAULT_PACKED_RESOURCES_SECTION_ALIGNMENT, SIDECAR_RESOURCES_EXTENSION,
+            WINDOWS_VERSION_INFO_KEYS,
+        },
+        config::{
+            default_host_requirements, sandbox_enforcement_supported, sandbox_profile_files,
+            PyembedPackedResourcesKey, PyembedPackedResourcesSource,
+            PyembedPythonInterpreterConfig, SandboxMode,
+        },
+        distribution::{
+            AppleSdkInfo, BinaryLibpythonLinkMode, DistributionFlavor, PythonDistribution,
         },
-        config::{PyembedPackedResourcesSource, PyembedPythonInterpreterConfig},
-        distribution::{AppleSdkInfo, BinaryLibpythonLinkMode, PythonDistribution},
         embedding::{
-            EmbeddedPythonContext, LibpythonLinkSettings, LinkSharedLibraryPath,
-            LinkStaticLibraryData, LinkingAnnotation,
+            serialize_packed_resources, EmbeddedPythonContext, LibpythonLinkSettings,
+            LinkSharedLibraryPath, LinkStaticLibraryData, LinkingAnnotation,
         },
         filtering::{filter_btreemap, resolve_resource_names_from_files},
         libpython::link_libpython,
@@ -21,39 +31,71 @@ use {
         },
         standalone_distribution::StandaloneDistribution,
     },
-    crate::environment::Environment,
+    crate::{build_inputs, environment::Environment, project_building::cargo_features},
     anyhow::{anyhow, Context, Result},
-    log::warn,
+    log::{info, warn},
     once_cell::sync::Lazy,
     pyo3_build_config::{BuildFlag, BuildFlags, PythonImplementation, PythonVersion},
     python_packaging::{
-        bytecode::BytecodeCompiler,
-        interpreter::MemoryAllocatorBackend,
+        bytecode::{BytecodeCompiler, CompileMode, PythonBytecodeCompiler},
+        interpreter::{
+            BuildInfo, HostRequirements, MemoryAllocatorBackend, PackedResourcesVerification,
+        },
         libpython::LibPythonBuildContext,
         licensing::{
             derive_package_license_infos, ComponentFlavor, LicensedComponent, LicensedComponents,
         },
         location::AbstractResourceLocation,
-        policy::PythonPackagingPolicy,
+        module_util::packages_from_module_name,
+        policy::{
+            CryptoLinkage, PythonPackagingPolicy, TypeInformationMode, CRYPTO_EXTENSION_MODULES,
+            OPENSSL_LIBRARIES,
+        },
+        python_source::{find_imports, DynamicImport},
         resource::{
-            PythonExtensionModule, PythonModuleSource, PythonPackageDistributionResource,
-            PythonPackageResource, PythonResource,
+            BytecodeOptimizationLevel, PythonExtensionModule, PythonModuleSource,
+            PythonPackageDistributionResource, PythonPackageResource, PythonResource,
         },
         resource_collection::{
-            AddResourceAction, PrePackagedResource, PythonResourceAddCollectionContext,
-            PythonResourceCollector,
+            AddResourceAction, CompiledResourcesCollection, PrePackagedResource,
+            PythonResourceAddCollectionContext, PythonResourceCollector,
         },
     },
+    sha2::{Digest, Sha256},
     simple_file_manifest::{File, FileData, FileEntry, FileManifest},
     std::{
+        borrow::Cow,
         collections::{BTreeMap, BTreeSet, HashMap},
         path::{Path, PathBuf},
         str::FromStr,
         sync::Arc,
     },
+    tugger_binary_analysis::find_required_symbol_version,
     tugger_windows::{find_visual_cpp_redistributable, VcRedistributablePlatform},
 };
 
+/// Modules imported by the interpreter or by pyembed regardless of the application.
+///
+/// Packages are retained in full, as their modules may be imported dynamically.
+const IMPORT_ANALYSIS_RUNTIME_MODULES: &[&str] = &[
+    "_collections_abc",
+    "_sitebuiltins",
+    "abc",
+    "codecs",
+    "encodings",
+    "genericpath",
+    "importlib",
+    "io",
+    "ntpath",
+    "os",
+    "pathlib",
+    "posixpath",
+    "site",
+    "stat",
+    "tokenize",
+    "zipimport",
+];
+
 /// Libraries that we should not link against on Linux.
 static LINUX_IGNORE_LIBRARIES: Lazy<Vec<&'static str>> = Lazy::new(|| vec!["dl", "m"]);
 
@@ -71,6 +113,74 @@ fn ignored_libraries_for_target(target_triple: &str) -> Vec<&'static str> {
     }
 }
 
+/// Resolve the newest glibc version required by shared libraries loaded at run-time.
+///
+/// These are extension modules and shared libraries loaded from memory or
+/// installed next to the binary. Unlike the requirements of the executable,
+/// which the dynamic loader enforces before any code runs, these can be
+/// checked when the interpreter starts, before the libraries are loaded.
+fn run_time_libraries_glibc_version(
+    resources: &CompiledResourcesCollection,
+) -> Result<Option<String>> {
+    let mut datas = vec![];
+
+    for resource in resources.resources.values() {
+        datas.extend(resource.in_memory_extension_module_shared_library.clone());
+        datas.extend(resource.in_memory_shared_library.clone());
+    }
+
+    for (path, location, _) in &resources.extra_files {
+        let is_shared_library = path
+            .file_name()
+            .map(|name| name.to_string_lossy().contains(".so"))
+            .unwrap_or_default();
+
+        if is_shared_library {
+            datas.push(Cow::Owned(location.resolve_content()?));
+        }
+    }
+
+    Ok(datas
+        .iter()
+        .filter_map(|data| find_required_symbol_version(data, "GLIBC"))
+        .max_by(|a, b| match version_compare::compare(a, b) {
+            Ok(version_compare::Cmp::Lt) => std::cmp::Ordering::Less,
+            Ok(version_compare::Cmp::Gt) => std::cmp::Ordering::Greater,
+            _ => std::cmp::Ordering::Equal,
+        }))
+}
+
+/// Link an extension module against the system's OpenSSL instead of the distribution's.
+///
+/// The distribution's static OpenSSL libraries are replaced by dynamic
+/// linking of the system libraries of the same name. This is only possible
+/// for extension modules linked into the binary.
+fn link_system_openssl(
+    name: &str,
+    target_triple: &str,
+    build_context: Option<&mut LibPythonBuildContext>,
+) -> Result<()> {
+    CryptoLinkage::System.validate_target(target_triple)?;
+
+    let build_context = build_context.ok_or_else(|| {
+        anyhow!(
+            "extension module {} is not linked into the binary; system crypto linkage requires it to be",
+            name
+        )
+    })?;
+
+    for library in OPENSSL_LIBRARIES {
+        if build_context.static_libraries.remove(*library) {
+            build_context.system_libraries.insert(library.to_string());
+        }
+    }
+
+    // The distribution's OpenSSL is no longer distributed with the binary.
+    build_context.licensed_components = LicensedComponents::default();
+
+    Ok(())
+}
+
 /// A self-contained Python executable before it is compiled.
 #[derive(Clone)]
 pub struct StandalonePythonExecutableBuilder {
@@ -106,6 +216,12 @@ pub struct StandalonePythonExecutableBuilder {
     /// How packed resources will be loaded at run-time.
     resources_load_mode: PackedResourcesLoadMode,
 
+    /// Key to encrypt packed resources with.
+    resources_encryption_key: Option<[u8; 32]>,
+
+    /// Path of a Rust function providing the resources key at run-time.
+    resources_key_provider: Option<String>,
+
     /// Holds state necessary to link libpython.
     core_build_context: LibPythonBuildContext,
 
@@ -121,17 +237,65 @@ pub struct StandalonePythonExecutableBuilder {
     /// Path to python executable that can be invoked at build time.
     host_python_exe: PathBuf,
 
+    /// Path to python executable used to compile bytecode.
+    bytecode_python_exe: PathBuf,
+
+    /// Emulator to run `bytecode_python_exe` with.
+    bytecode_python_emulator: Option<PathBuf>,
+
     /// Filename to write out with licensing information.
     licenses_filename: Option<String>,
 
     /// Value for the `windows_subsystem` Rust attribute for generated Rust projects.
     windows_subsystem: String,
 
+    /// `VS_VERSIONINFO` strings to embed in Windows executables.
+    windows_version_info: BTreeMap<String, String>,
+
+    /// Path of an `.ico` file to embed as the icon of Windows executables.
+    windows_icon_path: Option<PathBuf>,
+
+    /// Whether the application manifest declares long path awareness.
+    windows_long_path_aware: bool,
+
+    /// DPI awareness declared by the application manifest.
+    windows_dpi_awareness: WindowsDpiAwareness,
+
+    /// How Windows executables link the C runtime.
+    windows_crt_linkage: WindowsCrtLinkage,
+
+    /// Packages whose resources are written to the application layer.
+    application_layer_packages: Vec<String>,
+
+    /// Extra Cargo features to enable when building the generated Rust project.
+    cargo_features: Vec<String>,
+
+    /// Extra `RUSTFLAGS` to use when building the generated Rust project.
+    cargo_rustflags: Vec<String>,
+
+    /// Cargo profile settings to use when building the generated Rust project.
+    cargo_profile: CargoProfile,
+
     /// Path to install tcl/tk files into.
     tcl_files_path: Option<String>,
 
     /// Describes how Windows runtime DLLs should be handled during builds.
     windows_runtime_dlls_mode: WindowsRuntimeDllsMode,
+
+    /// Whether to remove Python modules the application doesn't import.
+    import_analysis: bool,
+
+    /// Modules and packages import analysis always considers imported.
+    import_analysis_allow: Vec<String>,
+
+    /// Whether to include modules imported dynamically by collected modules.
+    include_dynamic_imports: bool,
+
+    /// Python module sources that weren't included, keyed by module name.
+    ///
+    /// These are candidates for inclusion when imported dynamically.
+    excluded_module_sources:
+        BTreeMap<String, (PythonModuleSource, PythonResourceAddCollectionContext)>,
 }
 
 impl StandalonePythonExecutableBuilder {
@@ -148,6 +312,40 @@ impl StandalonePythonExecutableBuilder {
     ) -> Result<Box<Self>> {
         let host_python_exe = host_distribution.python_exe_path().to_path_buf();
 
+        // Bytecode must be compiled by an interpreter of the target's version. When
+        // the target distribution can't run on this machine, it can be run through
+        // an emulator. Otherwise we use the host distribution and verify its bytecode
+        // is compatible at compile time.
+        let (bytecode_python_exe, bytecode_python_emulator) = match build_inputs::env_var(
+            "PYOXIDIZER_TARGET_PYTHON_EMULATOR",
+        ) {
+            Some(emulator)
+                if !target_distribution
+                    .compatible_host_triples()
+                    .contains(&host_triple) =>
+            {
+                warn!(
+                    "compiling bytecode with target Python distribution via {}",
+                    PathBuf::from(&emulator).display()
+                );
+                (
+                    target_distribution.python_exe_path().to_path_buf(),
+                    Some(PathBuf::from(emulator)),
+                )
+            }
+            _ => {
+                if host_distribution.python_version() != target_distribution.python_version() {
+                    warn!(
+                            "host Python {} differs from target Python {}; bytecode compatibility will be verified",
+                            host_distribution.python_version(),
+                            target_distribution.python_version()
+                        );
+                }
+
+                (host_python_exe.clone(), None)
+            }
+        };
+
         let (supports_static_libpython, supports_dynamic_libpython) =
             target_distribution.libpython_link_support();
 
@@ -190,6 +388,19 @@ impl StandalonePythonExecutableBuilder {
         if let Some(fallback) = packaging_policy.resources_location_fallback() {
             allowed_locations.push(AbstractResourceLocation::from(fallback));
         }
+        if std::iter::once(packaging_policy.type_information())
+            .chain(packaging_policy.package_type_information().values())
+            .any(|mode| matches!(mode, TypeInformationMode::FilesystemRelative(_)))
+        {
+            allowed_locations.push(AbstractResourceLocation::RelativePath);
+        }
+        if !packaging_policy.framework_data_hooks().is_empty()
+            && !packaging_policy.framework_data_packages().is_empty()
+        {
+            allowed_locations.push(AbstractResourceLocation::from(
+                packaging_policy.framework_data_location(),
+            ));
+        }
 
         let mut allowed_extension_module_locations = vec![];
 
@@ -223,14 +434,31 @@ impl StandalonePythonExecutableBuilder {
             resources_load_mode: PackedResourcesLoadMode::EmbeddedInBinary(
                 "packed-resources".to_string(),
             ),
+            resources_encryption_key: None,
+            resources_key_provider: None,
             core_build_context: LibPythonBuildContext::default(),
             extension_build_contexts: BTreeMap::new(),
             config,
             host_python_exe,
+            bytecode_python_exe,
+            bytecode_python_emulator,
             licenses_filename: Some("COPYING.txt".into()),
             windows_subsystem: "console".to_string(),
+            windows_version_info: BTreeMap::new(),
+            windows_icon_path: None,
+            windows_long_path_aware: true,
+            windows_dpi_awareness: WindowsDpiAwareness::Unaware,
+            windows_crt_linkage: WindowsCrtLinkage::for_libpython_link_mode(link_mode),
+            application_layer_packages: vec![],
+            cargo_features: vec![],
+            cargo_rustflags: vec![],
+            cargo_profile: CargoProfile::default(),
             tcl_files_path: None,
             windows_runtime_dlls_mode: WindowsRuntimeDllsMode::WhenPresent,
+            import_analysis: false,
+            import_analysis_allow: vec![],
+            include_dynamic_imports: false,
+            excluded_module_sources: BTreeMap::new(),
         });
 
         builder.add_distribution_core_state()?;
@@ -238,6 +466,191 @@ impl StandalonePythonExecutableBuilder {
         Ok(builder)
     }
 
+    /// Resolve the modules import analysis starts from.
+    fn import_analysis_roots(&self) -> Result<BTreeSet<String>> {
+        let mut roots = self
+            .import_analysis_allow
+            .iter()
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        let mut have_entry_point = false;
+
+        if let Some(module) = &self.config.config.run_module {
+            roots.insert(module.clone());
+            have_entry_point = true;
+        }
+
+        if let Some(command) = &self.config.config.run_command {
+            roots.extend(find_imports(command.as_bytes(), "__main__", false));
+            have_entry_point = true;
+        }
+
+        if !have_entry_point && roots.is_empty() {
+            return Err(anyhow!(
+                "import analysis requires the interpreter to be configured with run_module \
+                or run_command or modules to be named by import_analysis_allow"
+            ));
+        }
+
+        roots.extend(
+            IMPORT_ANALYSIS_RUNTIME_MODULES
+                .iter()
+                .map(|s| s.to_string()),
+        );
+
+        if self.config.write_modules_directory_env.is_some() {
+            roots.insert("uuid".to_string());
+        }
+
+        if self.config.multiprocessing_auto_dispatch {
+            roots.insert("multiprocessing".to_string());
+        }
+
+        Ok(roots)
+    }
+
+    /// Resolve modules imported dynamically by collected modules.
+    ///
+    /// The source code of collected modules is scanned for dynamic imports naming
+    /// modules which weren't included. If `include` is set, these modules and
+    /// their parent packages are added to the collector, unless excluded by name
+    /// by the packaging policy. This is repeated until no more modules are added,
+    /// so dynamic imports of added modules are followed. Otherwise a warning is
+    /// emitted for each of them.
+    fn resolve_dynamic_imports(
+        &self,
+        collector: &mut Cow<'_, PythonResourceCollector>,
+        include: bool,
+    ) -> Result<()> {
+        let mut scanned = BTreeSet::new();
+        let mut reported = BTreeSet::new();
+
+        loop {
+            let collected = collector
+                .iter_resources()
+                .filter(|(_, r)| r.is_python_resource())
+                .map(|(name, _)| name.clone())
+                .collect::<BTreeSet<_>>();
+
+            let mut added = false;
+
+            for (caller, imports) in collector.find_dynamic_imports()? {
+                if !scanned.insert(caller.clone()) {
+                    continue;
+                }
+
+                // Warnings about the standard library aren't actionable.
+                let report = !self.target_distribution.py_modules.contains_key(&caller);
+
+                for import in imports {
+                    let name = match import {
+                        DynamicImport::Module(name) => name,
+                        DynamicImport::Computed(function) => {
+                            if report {
+                                warn!(
+                                    "{} calls {}() with a computed module name; modules it imports \
+                                    must be added explicitly",
+                                    caller, function
+                                );
+                            }
+                            continue;
+                        }
+                    };
+
+                    let mut names = packages_from_module_name(&name);
+                    names.insert(name.clone());
+
+                    for name in names {
+                        if collected.contains(&name) {
+                            continue;
+                        }
+
+                        if let Some((source, add_context)) = self.excluded_module_sources.get(&name)
+                        {
+                            if self
+                                .packaging_policy
+                                .exclude_patterns()
+                                .iter()
+                                .any(|pattern| pattern.matches(&name))
+                            {
+                                continue;
+                            }
+
+                            if !include {
+                                if report && reported.insert(name.clone()) {
+                                    warn!(
+                                        "{} imports {} dynamically but it was excluded; set \
+                                        include_dynamic_imports = True to include it",
+                                        caller, name
+                                    );
+                                }
+                                continue;
+                            }
+
+                            if reported.insert(name.clone()) {
+                                warn!("including {} imported dynamically by {}", name, caller);
+                            }
+
+                            let mut add_context = add_context.clone();
+                            add_context.include = true;
+                            collector
+                                .to_mut()
+                                .add_python_module_source_with_context(source, &add_context)?;
+                            added = true;
+                        } else if report && reported.insert(name.clone()) {
+                            warn!(
+                                "{} imports {} dynamically but it isn't available; importing it \
+                                will fail unless it is available from the filesystem at run-time",
+                                caller, name
+                            );
+                        }
+                    }
+                }
+            }
+
+            if !added {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Obtain a resources collector with modules the application doesn't import removed.
+    fn import_analyzed_resources_collector(
+        &self,
+        resources_collector: &PythonResourceCollector,
+    ) -> Result<PythonResourceCollector> {
+        let roots = self.import_analysis_roots()?;
+
+        let analysis = resources_collector
+            .analyze_imports(&roots)
+            .context("analyzing imports")?;
+
+        for name in &analysis.opaque {
+            warn!(
+                "imports of {} cannot be analyzed; retaining all modules in its top-level package",
+                name
+            );
+        }
+
+        for name in &analysis.unreachable {
+            info!("import analysis removing {}", name);
+        }
+
+        info!(
+            "import analysis removed {} of {} Python modules",
+            analysis.unreachable.len(),
+            analysis.reachable.len() + analysis.unreachable.len()
+        );
+
+        let mut collector = resources_collector.clone();
+        collector.remove_unreachable_modules(&analysis);
+
+        Ok(collector)
+    }
+
     fn add_distribution_core_state(&mut self) -> Result<()> {
         self.core_build_context.inittab_cflags =
             Some(self.target_distribution.inittab_cflags.clone());
@@ -372,6 +785,55 @@ impl StandalonePythonExecutableBuilder {
         }
     }
 
+    /// Obtain the resources collector whose resources will be packaged.
+    ///
+    /// This applies the inclusion of dynamic imports and import analysis, if enabled.
+    /// Dynamic imports of excluded modules are reported otherwise.
+    fn resolve_resources_collector(&self) -> Result<Cow<'_, PythonResourceCollector>> {
+        self.resources_collector.check_resource_overrides()?;
+
+        for o in self.resources_collector.resource_overrides() {
+            match o.data.backing_path() {
+                Some(path) => warn!("resource {} overridden by {}", o.path, path.display()),
+                None => warn!("resource {} overridden", o.path),
+            }
+        }
+
+        let mut resources_collector = Cow::Borrowed(&self.resources_collector);
+
+        self.resolve_dynamic_imports(&mut resources_collector, self.include_dynamic_imports)?;
+
+        if self.import_analysis {
+            resources_collector =
+                Cow::Owned(self.import_analyzed_resources_collector(&resources_collector)?);
+        }
+
+        Ok(resources_collector)
+    }
+
+    /// Obtain a compiler producing bytecode for the target distribution.
+    fn bytecode_compiler(&self, temp_dir: &Path) -> Result<BytecodeCompiler> {
+        let compiler = BytecodeCompiler::new_with_emulator(
+            self.bytecode_python_emulator.as_deref(),
+            &self.bytecode_python_exe,
+            temp_dir,
+        )?;
+
+        let expected_magic = self.target_distribution.bytecode_magic_number()?;
+        if compiler.get_magic_number() != expected_magic {
+            return Err(anyhow!(
+                "bytecode magic number {} from {} does not match magic number {} expected by target Python {}; \
+                define PYOXIDIZER_TARGET_PYTHON_EMULATOR to compile bytecode with the target distribution",
+                compiler.get_magic_number(),
+                self.bytecode_python_exe.display(),
+                expected_magic,
+                self.target_distribution.python_version()
+            ));
+        }
+
+        Ok(compiler)
+    }
+
     /// Resolves Windows runtime DLLs file needed for this binary given current settings.
     fn resolve_windows_runtime_dll_files(&self) -> Result<FileManifest> {
         let mut manifest = FileManifest::default();
@@ -506,6 +968,110 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         Ok(())
     }
 
+    fn windows_version_info(&self) -> &BTreeMap<String, String> {
+        &self.windows_version_info
+    }
+
+    fn set_windows_version_info(&mut self, value: BTreeMap<String, String>) -> Result<()> {
+        if let Some(key) = value
+            .keys()
+            .find(|key| !WINDOWS_VERSION_INFO_KEYS.contains(&key.as_str()))
+        {
+            return Err(anyhow!(
+                "{} is not a valid version info key; expected one of {}",
+                key,
+                WINDOWS_VERSION_INFO_KEYS.join(", ")
+            ));
+        }
+
+        self.windows_version_info = value;
+
+        Ok(())
+    }
+
+    fn windows_icon_path(&self) -> Option<&Path> {
+        self.windows_icon_path.as_deref()
+    }
+
+    fn set_windows_icon_path(&mut self, value: Option<PathBuf>) -> Result<()> {
+        if let Some(path) = &value {
+            if !matches!(path.extension(), Some(ext) if ext.eq_ignore_ascii_case("ico")) {
+                return Err(anyhow!("{} is not an .ico file", path.display()));
+            }
+        }
+
+        self.windows_icon_path = value;
+
+        Ok(())
+    }
+
+    fn windows_long_path_aware(&self) -> bool {
+        self.windows_long_path_aware
+    }
+
+    fn set_windows_long_path_aware(&mut self, value: bool) {
+        self.windows_long_path_aware = value;
+    }
+
+    fn windows_dpi_awareness(&self) -> WindowsDpiAwareness {
+        self.windows_dpi_awareness
+    }
+
+    fn set_windows_dpi_awareness(&mut self, value: WindowsDpiAwareness) {
+        self.windows_dpi_awareness = value;
+    }
+
+    fn windows_crt_linkage(&self) -> WindowsCrtLinkage {
+        self.windows_crt_linkage
+    }
+
+    fn set_windows_crt_linkage(&mut self, value: WindowsCrtLinkage) -> Result<()> {
+        let required = WindowsCrtLinkage::for_libpython_link_mode(self.link_mode);
+
+        if value != required {
+            return Err(anyhow!(
+                "{} C runtime linkage is incompatible with {} linking of libpython, which requires {} C runtime linkage",
+                value,
+                match self.link_mode {
+                    LibpythonLinkMode::Static => "static",
+                    LibpythonLinkMode::Dynamic => "dynamic",
+                },
+                required
+            ));
+        }
+
+        self.windows_crt_linkage = value;
+
+        Ok(())
+    }
+
+    fn cargo_features(&self) -> &[String] {
+        &self.cargo_features
+    }
+
+    fn set_cargo_features(&mut self, value: Vec<String>) {
+        self.cargo_features = value;
+    }
+
+    fn cargo_rustflags(&self) -> &[String] {
+        &self.cargo_rustflags
+    }
+
+    fn set_cargo_rustflags(&mut self, value: Vec<String>) {
+        self.cargo_rustflags = value;
+    }
+
+    fn cargo_profile(&self) -> &CargoProfile {
+        &self.cargo_profile
+    }
+
+    fn set_cargo_profile(&mut self, value: CargoProfile) -> Result<()> {
+        value.validate()?;
+        self.cargo_profile = value;
+
+        Ok(())
+    }
+
     fn licenses_filename(&self) -> Option<&str> {
         self.licenses_filename.as_deref()
     }
@@ -522,6 +1088,54 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         self.resources_load_mode = load_mode;
     }
 
+    fn packed_resources_encryption_key(&self) -> Option<&[u8; 32]> {
+        self.resources_encryption_key.as_ref()
+    }
+
+    fn set_packed_resources_encryption_key(&mut self, key: Option<[u8; 32]>) {
+        self.resources_encryption_key = key;
+    }
+
+    fn packed_resources_key_provider(&self) -> Option<&str> {
+        self.resources_key_provider.as_deref()
+    }
+
+    fn set_packed_resources_key_provider(&mut self, path: Option<String>) {
+        self.resources_key_provider = path;
+    }
+
+    fn application_layer_packages(&self) -> &[String] {
+        &self.application_layer_packages
+    }
+
+    fn set_application_layer_packages(&mut self, value: Vec<String>) {
+        self.application_layer_packages = value;
+    }
+
+    fn import_analysis(&self) -> bool {
+        self.import_analysis
+    }
+
+    fn set_import_analysis(&mut self, value: bool) {
+        self.import_analysis = value;
+    }
+
+    fn import_analysis_allow(&self) -> &[String] {
+        &self.import_analysis_allow
+    }
+
+    fn set_import_analysis_allow(&mut self, names: Vec<String>) {
+        self.import_analysis_allow = names;
+    }
+
+    fn include_dynamic_imports(&self) -> bool {
+        self.include_dynamic_imports
+    }
+
+    fn set_include_dynamic_imports(&mut self, value: bool) {
+        self.include_dynamic_imports = value;
+    }
+
     fn iter_resources<'a>(
         &'a self,
     ) -> Box<dyn Iterator<Item = (&'a String, &'a PrePackagedResource)> + 'a> {
@@ -749,6 +1363,13 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
                 .derive_add_collection_context(&module.into())
         });
 
+        if add_context.include {
+            self.excluded_module_sources.remove(&module.name);
+        } else {
+            self.excluded_module_sources
+                .insert(module.name.clone(), (module.clone(), add_context.clone()));
+        }
+
         self.resources_collector
             .add_python_module_source_with_context(module, &add_context)
     }
@@ -791,10 +1412,20 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
                 .derive_add_collection_context(&extension_module.into())
         });
 
-        let (actions, build_context) = self
+        let (actions, mut build_context) = self
             .resources_collector
             .add_python_extension_module_with_context(extension_module, &add_context)?;
 
+        if self.packaging_policy.crypto_linkage() == &CryptoLinkage::System
+            && CRYPTO_EXTENSION_MODULES.contains(&extension_module.name.as_str())
+        {
+            link_system_openssl(
+                &extension_module.name,
+                &self.target_triple,
+                build_context.as_mut(),
+            )?;
+        }
+
         if let Some(mut build_context) = build_context {
             // Resources collector doesn't doesn't know about ignored libraries. So filter
             // them here.
@@ -860,6 +1491,18 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         Ok(())
     }
 
+    fn override_resource(&mut self, path: &str, data: FileData) -> Result<()> {
+        self.resources_collector.override_resource(path, data)
+    }
+
+    fn source_map_root(&self) -> Option<&Path> {
+        self.resources_collector.source_map_root()
+    }
+
+    fn set_source_map_root(&mut self, value: Option<PathBuf>) {
+        self.resources_collector.set_source_map_root(value);
+    }
+
     fn requires_jemalloc(&self) -> bool {
         self.config.allocator_backend == MemoryAllocatorBackend::Jemalloc
     }
@@ -872,6 +1515,14 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         self.config.allocator_backend == MemoryAllocatorBackend::Snmalloc
     }
 
+    fn requires_config_overrides(&self) -> bool {
+        self.config.config_overrides_path.is_some()
+    }
+
+    fn requires_encryption(&self) -> bool {
+        self.resources_encryption_key.is_some()
+    }
+
     fn licensed_components(&self) -> Result<LicensedComponents> {
         Ok(self.resources_collector.normalized_licensed_components())
     }
@@ -885,8 +1536,24 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         env: &Environment,
         opt_level: &str,
     ) -> Result<EmbeddedPythonContext> {
+        if self.config.site_module.is_some() {
+            if !self.config.oxidized_importer {
+                return Err(anyhow!(
+                    "site_module requires oxidized_importer to be enabled"
+                ));
+            }
+
+            if self.config.config.site_import == Some(false) {
+                return Err(anyhow!(
+                    "site_module has no effect when site_import is disabled"
+                ));
+            }
+        }
+
+        let resources_collector = self.resolve_resources_collector()?;
+
         let mut file_seen = false;
-        for module in self.resources_collector.find_dunder_file()? {
+        for module in resources_collector.find_dunder_file()? {
             file_seen = true;
             warn!("warning: {} contains __file__", module);
         }
@@ -897,22 +1564,129 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             warn!("See https://github.com/indygreg/PyOxidizer/issues/69 for more");
         }
 
-        let compiled_resources = {
+        let mut compiled_resources = {
             let temp_dir = env.temporary_directory("pyoxidizer-bytecode-compile")?;
-            let mut compiler = BytecodeCompiler::new(self.host_python_exe_path(), temp_dir.path())?;
-            let resources = self.resources_collector.compile_resources(&mut compiler)?;
+            let mut compiler = self.bytecode_compiler(temp_dir.path())?;
+
+            if let Some(source) = &self.config.site_module {
+                compiler
+                    .compile(
+                        source.as_bytes(),
+                        "site",
+                        BytecodeOptimizationLevel::Zero,
+                        CompileMode::Bytecode,
+                    )
+                    .context("compiling site_module")?;
+            }
+
+            let resources = resources_collector.compile_resources(&mut compiler)?;
 
             temp_dir.close().context("closing temporary directory")?;
 
             resources
         };
 
+        let glibc_version = if self.config.host_requirements_check
+            && self.target_triple().ends_with("-linux-gnu")
+        {
+            run_time_libraries_glibc_version(&compiled_resources)?
+        } else {
+            None
+        };
+
         let mut pending_resources = vec![];
+        let mut executable_overlay = None;
 
         let mut extra_files = compiled_resources.extra_files_manifest()?;
 
+        // Application layer resources are split off before the remaining base
+        // resources are serialized so the base doesn't change with them.
+        let application_layer = if self.application_layer_packages.is_empty() {
+            None
+        } else {
+            Some(compiled_resources.split_packages(&self.application_layer_packages))
+        };
+
         let mut config = self.config.clone();
 
+        // Host requirements are derived from the distribution being embedded
+        // and the shared libraries loaded at run-time. Only the message is
+        // user customizable.
+        if config.host_requirements_check {
+            config.host_requirements = HostRequirements {
+                message: config.host_requirements.message.take(),
+                ..default_host_requirements(
+                    self.target_distribution.target_triple(),
+                    &self.target_distribution.python_major_minor_version(),
+                    self.target_distribution
+                        .apple_sdk_info()
+                        .map(|info| info.deployment_target.as_str()),
+                    glibc_version.as_deref(),
+                )
+            };
+        }
+
+        if config.sandbox_mode != SandboxMode::Off {
+            let target_triple = self.target_distribution.target_triple();
+
+            if config.sandbox_mode == SandboxMode::Enforce
+                && !sandbox_enforcement_supported(target_triple)
+            {
+                return Err(anyhow!(
+                    "sandbox policies cannot be enforced on {}; use sandbox_mode = \"profiles\" instead",
+                    target_triple
+                ));
+            }
+
+            for (filename, content) in
+                sandbox_profile_files(&config.sandbox_policy, &self.exe_name, target_triple)
+            {
+                extra_files.add_file_entry(Path::new(&filename), content.into_bytes())?;
+            }
+        }
+
+        config.build_info = Some(BuildInfo {
+            target_triple: self.target_distribution.target_triple().to_string(),
+            python_version: self.target_distribution.python_version().to_string(),
+            python_flavor: match self.link_mode {
+                LibpythonLinkMode::Static => DistributionFlavor::StandaloneStatic,
+                LibpythonLinkMode::Dynamic => DistributionFlavor::StandaloneDynamic,
+            }
+            .to_string(),
+            resources_location: self.packaging_policy.resources_location().to_string(),
+            resources_location_fallback: self
+                .packaging_policy
+                .resources_location_fallback()
+                .as_ref()
+                .map(|x| x.to_string()),
+            features: cargo_features(self)
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect(),
+        });
+
+        config.packed_resources_key =
+            match (&self.resources_encryption_key, &self.resources_key_provider) {
+                (Some(_), Some(path)) => Some(PyembedPackedResourcesKey::Callback(path.clone())),
+                (Some(key), None) => Some(PyembedPackedResourcesKey::Key(*key)),
+                (None, Some(_)) => {
+                    return Err(anyhow!(
+                        "packed_resources_key_provider requires packed_resources_encryption_key"
+                    ));
+                }
+                (None, None) => None,
+            };
+
+        // Executables using the windows subsystem have no console and therefore
+        // no standard streams.
+        if self.windows_subsystem == "windows" {
+            config.replace_missing_stdio = true;
+        }
+
+        // Verifying resources requires the content digests of version 4 data.
+        let content_digests =
+            config.packed_resources_verification != PackedResourcesVerification::None;
+
         match &self.resources_load_mode {
             PackedResourcesLoadMode::None => {}
             PackedResourcesLoadMode::EmbeddedInBinary(filename) => {
@@ -925,10 +1699,11 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             }
             PackedResourcesLoadMode::BinaryRelativePathMemoryMapped(path) => {
                 // We need to materialize the file in extra_files. So compile now.
-                let mut buffer = vec![];
-                compiled_resources
-                    .write_packed_resources(&mut buffer)
-                    .context("serializing packed resources")?;
+                let buffer = serialize_packed_resources(
+                    &compiled_resources,
+                    self.resources_encryption_key.as_ref(),
+                    content_digests,
+                )?;
                 extra_files.add_file_entry(Path::new(path), buffer)?;
 
                 config
@@ -937,6 +1712,74 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
                         PathBuf::from("$ORIGIN").join(path),
                     ));
             }
+            PackedResourcesLoadMode::Sidecar => {
+                let buffer = serialize_packed_resources(
+                    &compiled_resources,
+                    self.resources_encryption_key.as_ref(),
+                    content_digests,
+                )?;
+                let digest: [u8; 32] = Sha256::digest(&buffer).into();
+
+                let path = format!("{}.{}", self.exe_name, SIDECAR_RESOURCES_EXTENSION);
+                extra_files.add_file_entry(Path::new(&path), buffer)?;
+
+                config.packed_resources.push(
+                    PyembedPackedResourcesSource::MemoryMappedPathVerified(
+                        PathBuf::from("$ORIGIN").join(path),
+                        digest,
+                    ),
+                );
+            }
+            PackedResourcesLoadMode::EmbeddedInSection(section, alignment) => {
+                let target_triple = self.target_distribution.target_triple();
+                let section = section
+                    .as_deref()
+                    .unwrap_or_else(|| default_packed_resources_section(target_triple));
+                validate_packed_resources_section(target_triple, section)?;
+
+                let filename = PathBuf::from("packed-resources");
+                pending_resources.push((compiled_resources, filename.clone()));
+                config.packed_resources.push(
+                    PyembedPackedResourcesSource::MemoryIncludeBytesInSection(
+                        filename,
+                        section.to_string(),
+                        alignment.unwrap_or(DEFAULT_PACKED_RESOURCES_SECTION_ALIGNMENT),
+                    ),
+                );
+            }
+            PackedResourcesLoadMode::ExecutableOverlay => {
+                executable_overlay.replace(serialize_packed_resources(
+                    &compiled_resources,
+                    self.resources_encryption_key.as_ref(),
+                    content_digests,
+                )?);
+
+                config
+                    .packed_resources
+                    .push(PyembedPackedResourcesSource::ExecutableOverlay);
+            }
+        }
+
+        // The application layer is referenced without a digest so the binary
+        // and base resources remain identical when only the layer changes.
+        if let Some(layer) = application_layer {
+            let buffer = serialize_packed_resources(
+                &layer,
+                self.resources_encryption_key.as_ref(),
+                content_digests,
+            )?;
+
+            let path = format!(
+                "{}.{}",
+                self.exe_name, APPLICATION_LAYER_RESOURCES_EXTENSION
+            );
+            extra_files.add_file_entry(Path::new(&path), buffer)?;
+
+            config
+                .packed_resources
+                .push(PyembedPackedResourcesSource::MemoryMappedPath(
+                    PathBuf::from("$ORIGIN").join(path),
+                ));
         }
 
         let link_settings = self.resolve_python_link_settings(env, opt_level)?;
@@ -1035,6 +1878,8 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             config,
             link_settings,
             pending_resources,
+            executable_overlay,
+            packed_resources_encryption_key: self.resources_encryption_key,
             extra_files,
             host_triple: self.host_triple.clone(),
             target_triple: self.target_triple.clone(),
@@ -1050,6 +1895,91 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
 
         Ok(context)
     }
+
+    fn to_runtime_file_manifest(&self, env: &Environment) -> Result<(PathBuf, FileManifest)> {
+        let distribution = &self.target_distribution;
+
+        // Files are installed at the same paths they have in the distribution.
+        // So the interpreter finds its standard library the way it normally does.
+        let root = distribution
+            .stdlib_path
+            .ancestors()
+            .find(|p| distribution.python_exe.starts_with(p))
+            .ok_or_else(|| anyhow!("unable to resolve Python distribution install root"))?;
+        let exe_path = distribution.python_exe.strip_prefix(root)?;
+        let exe_dir = exe_path.parent().unwrap_or_else(|| Path::new(""));
+        let stdlib_prefix = distribution
+            .stdlib_path
+            .strip_prefix(root)?
+            .display()
+            .to_string()
+            .replace('\\', "/");
+
+        let module_suffixes = distribution.python_module_suffixes()?;
+        let extension_module_suffix = module_suffixes.extension.first().ok_or_else(|| {
+            anyhow!("Python distribution does not define extension module suffixes")
+        })?;
+
+        let resources_collector = self.resolve_resources_collector()?.to_relative_path(
+            &stdlib_prefix,
+            self.cache_tag(),
+            extension_module_suffix,
+        )?;
+
+        let compiled_resources = {
+            let temp_dir = env.temporary_directory("pyoxidizer-bytecode-compile")?;
+            let mut compiler = self.bytecode_compiler(temp_dir.path())?;
+
+            let resources = resources_collector.compile_resources(&mut compiler)?;
+
+            temp_dir.close().context("closing temporary directory")?;
+
+            resources
+        };
+
+        let mut manifest = compiled_resources.extra_files_manifest()?;
+
+        manifest.add_file_entry(
+            exe_path,
+            FileEntry::new_from_path(&distribution.python_exe, true),
+        )?;
+
+        if let Some(p) = &distribution.libpython_shared_library {
+            manifest.add_file_entry(p.strip_prefix(root)?, FileEntry::new_from_path(p, true))?;
+
+            let python3_dll_path = p.with_file_name("python3.dll");
+            if python3_dll_path.exists() {
+                manifest.add_file_entry(
+                    python3_dll_path.strip_prefix(root)?,
+                    FileEntry::new_from_path(&python3_dll_path, true),
+                )?;
+            }
+        }
+
+        if let Some(tcl_files_path) = self.tcl_files_path() {
+            for (path, location) in distribution.tcl_files()? {
+                manifest.add_file_entry(exe_dir.join(tcl_files_path).join(path), location)?;
+            }
+        }
+
+        for (path, entry) in self.resolve_windows_runtime_dll_files()?.iter_entries() {
+            manifest.add_file_entry(exe_dir.join(path), entry.clone())?;
+        }
+
+        if let Some(filename) = &self.licenses_filename {
+            manifest.add_file_entry(
+                exe_dir.join(filename),
+                FileEntry::new_from_data(
+                    self.licensed_components()?
+                        .aggregate_license_document(false)?
+                        .as_bytes(),
+                    false,
+                ),
+            )?;
+        }
+
+        Ok((exe_path.to_path_buf(), manifest))
+    }
 }
 
 #[cfg(test)]
@@ -1142,6 +2072,7 @@ pub mod tests {
         pub resources_location: Option<ConcreteResourceLocation>,
         pub resources_location_fallback: Option<Option<ConcreteResourceLocation>>,
         pub allow_in_memory_shared_library_loading: Option<bool>,
+        pub type_information: Option<TypeInformationMode>,
         pub config: PyembedPythonInterpreterConfig,
     }
 
@@ -1158,6 +2089,7 @@ pub mod tests {
                 resources_location: None,
                 resources_location_fallback: None,
                 allow_in_memory_shared_library_loading: None,
+                type_information: None,
                 config: PyembedPythonInterpreterConfig::default(),
             }
         }
@@ -1201,6 +2133,9 @@ pub mod tests {
             if let Some(value) = &self.allow_in_memory_shared_library_loading {
                 policy.set_allow_in_memory_shared_library_loading(*value);
             }
+            if let Some(mode) = &self.type_information {
+                policy.set_type_information(mode.clone());
+            }
 
             let mut builder = StandalonePythonExecutableBuilder::from_distribution(
                 host_distribution,
@@ -1358,6 +2293,309 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_sidecar_resources() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+        exe.resources_load_mode = PackedResourcesLoadMode::Sidecar;
+
+        let embedded = exe.to_embedded_python_context(&get_env()?, "0")?;
+
+        let path = format!("{}.pyoxy-resources", exe.name());
+        let entry = embedded
+            .extra_files
+            .get(&path)
+            .ok_or_else(|| anyhow!("sidecar resources file should be in extra files manifest"))?;
+        let digest: [u8; 32] = Sha256::digest(entry.resolve_content()?).into();
+
+        assert_eq!(
+            &embedded.config.packed_resources,
+            &vec![PyembedPackedResourcesSource::MemoryMappedPathVerified(
+                PathBuf::from("$ORIGIN").join(&path),
+                digest,
+            )],
+            "load mode should have mapped to MemoryMappedPathVerified"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resources_format_version() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+        exe.resources_load_mode =
+            PackedResourcesLoadMode::BinaryRelativePathMemoryMapped("resources".into());
+
+        let embedded = exe.to_embedded_python_context(&get_env()?, "0")?;
+        let data = embedded
+            .extra_files
+            .get("resources")
+            .ok_or_else(|| anyhow!("resources file should be in extra files manifest"))?
+            .resolve_content()?;
+        assert!(data.starts_with(python_packed_resources::HEADER_V3));
+
+        exe.config.packed_resources_verification = PackedResourcesVerification::OnLoad;
+
+        let embedded = exe.to_embedded_python_context(&get_env()?, "0")?;
+        let data = embedded
+            .extra_files
+            .get("resources")
+            .ok_or_else(|| anyhow!("resources file should be in extra files manifest"))?
+            .resolve_content()?;
+        assert!(data.starts_with(python_packed_resources::HEADER_V4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_type_information_filesystem_relative() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions {
+            type_information: Some(TypeInformationMode::FilesystemRelative("lib".to_string())),
+            ..StandalonePythonExecutableBuilderOptions::default()
+        };
+        let mut exe = options.new_builder()?;
+        assert!(exe
+            .python_packaging_policy()
+            .framework_data_hooks()
+            .is_empty());
+
+        exe.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "typed".to_string(),
+                relative_name: "py.typed".to_string(),
+                data: FileData::Memory(vec![]),
+                is_stdlib: false,
+                is_test: false,
+                locale: None,
+            },
+            None,
+        )?;
+
+        let (_, resource) = exe
+            .iter_resources()
+            .find(|(name, _)| *name == "typed")
+            .unwrap();
+        assert!(resource.in_memory_resources.is_none());
+        assert_eq!(
+            resource
+                .relative_path_package_resources
+                .as_ref()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["py.typed"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_crt_linkage() -> Result<()> {
+        for (libpython_link_mode, required, other) in [
+            (
+                BinaryLibpythonLinkMode::Static,
+                WindowsCrtLinkage::Static,
+                WindowsCrtLinkage::Dynamic,
+            ),
+            (
+                BinaryLibpythonLinkMode::Dynamic,
+                WindowsCrtLinkage::Dynamic,
+                WindowsCrtLinkage::Static,
+            ),
+        ] {
+            let options = StandalonePythonExecutableBuilderOptions {
+                target_triple: "x86_64-unknown-linux-gnu".to_string(),
+                libpython_link_mode,
+                ..StandalonePythonExecutableBuilderOptions::default()
+            };
+            let mut exe = options.new_builder()?;
+
+            assert_eq!(exe.windows_crt_linkage(), required);
+            exe.set_windows_crt_linkage(required)?;
+            assert!(exe.set_windows_crt_linkage(other).is_err());
+            assert_eq!(exe.windows_crt_linkage(), required);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_application_layer() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+        exe.resources_load_mode = PackedResourcesLoadMode::Sidecar;
+
+        exe.add_python_module_source(
+            &PythonModuleSource {
+                name: "app".to_string(),
+                source: FileData::Memory(b"import json\n".to_vec()),
+                is_package: false,
+                cache_tag: exe.cache_tag().to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            None,
+        )?;
+
+        let sidecar = format!("{}.pyoxy-resources", exe.name());
+        let layer = format!("{}.app-resources", exe.name());
+
+        let base_data = exe
+            .to_embedded_python_context(&get_env()?, "0")?
+            .extra_files
+            .get(&sidecar)
+            .unwrap()
+            .resolve_content()?;
+
+        exe.set_application_layer_packages(vec!["app".to_string()]);
+        let embedded = exe.to_embedded_python_context(&get_env()?, "0")?;
+
+        assert_eq!(embedded.config.packed_resources.len(), 2);
+        assert!(matches!(
+            &embedded.config.packed_resources[0],
+            PyembedPackedResourcesSource::MemoryMappedPathVerified(..)
+        ));
+        assert_eq!(
+            embedded.config.packed_resources[1],
+            PyembedPackedResourcesSource::MemoryMappedPath(PathBuf::from("$ORIGIN").join(&layer))
+        );
+
+        let sidecar_data = embedded
+            .extra_files
+            .get(&sidecar)
+            .unwrap()
+            .resolve_content()?;
+        let layer_data = embedded
+            .extra_files
+            .get(&layer)
+            .unwrap()
+            .resolve_content()?;
+
+        assert!(sidecar_data.len() < base_data.len());
+
+        let names = python_packed_resources::load_resources(&layer_data)
+            .map_err(|e| anyhow!("{}", e))?
+            .map(|resource| resource.map(|resource| resource.name.to_string()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow!("{}", e))?;
+        assert_eq!(names, vec!["app".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_subsystem_replaces_missing_stdio() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+
+        let embedded = exe.to_embedded_python_context(&get_env()?, "0")?;
+        assert!(!embedded.config.replace_missing_stdio);
+
+        exe.set_windows_subsystem("windows")?;
+        let embedded = exe.to_embedded_python_context(&get_env()?, "0")?;
+        assert!(embedded.config.replace_missing_stdio);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "encryption")]
+    fn test_encrypted_resources() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+        exe.resources_load_mode =
+            PackedResourcesLoadMode::BinaryRelativePathMemoryMapped("resources".into());
+        exe.set_packed_resources_encryption_key(Some([42; 32]));
+        assert!(exe.requires_encryption());
+
+        let embedded = exe.to_embedded_python_context(&get_env()?, "0")?;
+
+        assert_eq!(
+            embedded.config.packed_resources_key,
+            Some(PyembedPackedResourcesKey::Key([42; 32]))
+        );
+
+        let data = embedded
+            .extra_files
+            .get("resources")
+            .ok_or_else(|| anyhow!("resources file should be in extra files manifest"))?
+            .resolve_content()?;
+        assert!(python_packed_resources::is_encrypted(&data));
+
+        let data = python_packed_resources::decrypt_packed_resources(&data, &[42; 32])
+            .map_err(|e| anyhow!(e))?;
+        assert!(python_packed_resources::load_resources(&data).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_resources_key_provider() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+        exe.set_packed_resources_key_provider(Some("crate::resources_key".into()));
+
+        assert!(exe.to_embedded_python_context(&get_env()?, "0").is_err());
+
+        exe.set_packed_resources_encryption_key(Some([42; 32]));
+        let embedded = exe.to_embedded_python_context(&get_env()?, "0")?;
+
+        assert_eq!(
+            embedded.config.packed_resources_key,
+            Some(PyembedPackedResourcesKey::Callback(
+                "crate::resources_key".into()
+            ))
+        );
+        assert_eq!(embedded.packed_resources_encryption_key, Some([42; 32]));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(feature = "encryption"))]
+    fn test_encrypted_resources_unsupported() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+        exe.resources_load_mode =
+            PackedResourcesLoadMode::BinaryRelativePathMemoryMapped("resources".into());
+        exe.set_packed_resources_encryption_key(Some([42; 32]));
+
+        assert!(exe.to_embedded_python_context(&get_env()?, "0").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_runtime_file_manifest() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let exe = options.new_builder()?;
+
+        let (exe_path, manifest) = exe.to_runtime_file_manifest(&get_env()?)?;
+
+        let root = exe
+            .target_distribution
+            .stdlib_path
+            .ancestors()
+            .find(|p| exe.target_distribution.python_exe.starts_with(p))
+            .unwrap();
+        let stdlib = exe.target_distribution.stdlib_path.strip_prefix(root)?;
+
+        assert_eq!(
+            exe_path,
+            exe.target_distribution.python_exe.strip_prefix(root)?
+        );
+        assert!(manifest.get(&exe_path).unwrap().is_executable());
+        assert!(manifest.has_path(
+            stdlib
+                .join("encodings")
+                .join("__pycache__")
+                .join(format!("__init__.{}.pyc", exe.cache_tag()))
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn test_minimal_extensions_present() -> Result<()> {
         let options = StandalonePythonExecutableBuilderOptions::default();
@@ -3217,4 +4455,29 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_link_system_openssl() -> Result<()> {
+        let mut context = LibPythonBuildContext::default();
+        context.static_libraries.insert("ssl".to_string());
+        context.static_libraries.insert("crypto".to_string());
+        context.static_libraries.insert("z".to_string());
+
+        link_system_openssl("_ssl", "x86_64-unknown-linux-gnu", Some(&mut context))?;
+        assert_eq!(context.static_libraries, BTreeSet::from(["z".to_string()]));
+        assert_eq!(
+            context.system_libraries,
+            BTreeSet::from(["crypto".to_string(), "ssl".to_string()])
+        );
+
+        assert!(link_system_openssl("_ssl", "x86_64-unknown-linux-gnu", None).is_err());
+        assert!(link_system_openssl(
+            "_ssl",
+            "x86_64-pc-windows-msvc",
+            Some(&mut LibPythonBuildContext::default())
+        )
+        .is_err());
+
+        Ok(())
+    }
 }
@@ -268,6 +268,62 @@ fn parse_python_major_minor_version(version: &str) -> String {
         .join(".")
 }
 
+/// Parse the bytecode magic number from the source of `importlib._bootstrap_external`.
+///
+/// The returned value is the little endian integer of the 4 byte magic
+/// header emitted at the beginning of `.pyc` files.
+fn parse_bytecode_magic_number(source: &str) -> Result<u32> {
+    let line = source
+        .lines()
+        .find(|line| line.starts_with("MAGIC_NUMBER = ("))
+        .ok_or_else(|| anyhow!("MAGIC_NUMBER not found"))?;
+
+    let number = line
+        .trim_start_matches("MAGIC_NUMBER = (")
+        .split(')')
+        .next()
+        .ok_or_else(|| anyhow!("unable to parse MAGIC_NUMBER line: {}", line))?
+        .parse::<u16>()
+        .with_context(|| format!("parsing MAGIC_NUMBER line: {}", line))?;
+
+    // The magic number is followed by \r\n.
+    Ok(u32::from(number) | (0x0a0d << 16))
+}
+
+/// Parse the bytecode magic number from the source of `pycore_magic_number.h`.
+///
+/// Python 3.14 moved the definition of the magic number from
+/// `importlib._bootstrap_external` to this header.
+fn parse_bytecode_magic_number_header(source: &str) -> Result<u32> {
+    let number = source
+        .lines()
+        .find_map(|line| line.strip_prefix("#define PYC_MAGIC_NUMBER "))
+        .ok_or_else(|| anyhow!("PYC_MAGIC_NUMBER not found"))?
+        .trim()
+        .parse::<u16>()
+        .context("parsing PYC_MAGIC_NUMBER")?;
+
+    Ok(u32::from(number) | (0x0a0d << 16))
+}
+
+/// Parse the major and minor components of a Python version string as integers.
+fn parse_python_version_tuple(version: &str) -> Result<(u32, u32)> {
+    let mut parts = version.split('.');
+
+    let mut component = |name| -> Result<u32> {
+        let part = parts.next().unwrap_or("0");
+        let digits = part
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(part, |end| &part[..end]);
+
+        digits
+            .parse::<u32>()
+            .with_context(|| format!("parsing {} version of Python {}", name, version))
+    };
+
+    Ok((component("major")?, component("minor")?))
+}
+
 /// Resolve the path to a `python` executable in a Python distribution.
 pub fn python_exe_path(dist_dir: &Path) -> Result<PathBuf> {
     let pi = parse_python_json_from_distribution(dist_dir)?;
@@ -969,6 +1025,14 @@ impl StandaloneDistribution {
 
         let inittab_object = python_path.join(pi.build_info.inittab_object);
 
+        // The PyO3 version pyembed uses doesn't know the object layout of
+        // free-threaded (Py_GIL_DISABLED) builds.
+        if pi.python_config_vars.get("Py_GIL_DISABLED") == Some(&"1".to_string()) {
+            return Err(anyhow!(
+                "free-threaded Python distributions are not supported"
+            ));
+        }
+
         Ok(Self {
             base_dir: dist_dir.to_path_buf(),
             target_triple: pi.target_triple,
@@ -1044,6 +1108,41 @@ impl StandaloneDistribution {
         self.extension_module_loading
             .contains(&"shared-library".to_string())
     }
+
+    /// Resolve the bytecode magic number expected by this distribution's interpreter.
+    ///
+    /// This is derived from the distribution's standard library and doesn't
+    /// require running the interpreter.
+    pub fn bytecode_magic_number(&self) -> Result<u32> {
+        if parse_python_version_tuple(&self.version)? >= (3, 14) {
+            let header = Path::new("internal").join("pycore_magic_number.h");
+            let path = self
+                .includes
+                .iter()
+                .find_map(|(name, path)| {
+                    if Path::new(name) == header {
+                        Some(path)
+                    } else {
+                        None
+                    }
+                })
+                .ok_or_else(|| anyhow!("{} not found in distribution", header.display()))?;
+            let source = std::fs::read_to_string(path)
+                .with_context(|| format!("reading {}", path.display()))?;
+
+            return parse_bytecode_magic_number_header(&source)
+                .with_context(|| format!("parsing {}", path.display()));
+        }
+
+        let path = self
+            .stdlib_path
+            .join("importlib")
+            .join("_bootstrap_external.py");
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("reading {}", path.display()))?;
+
+        parse_bytecode_magic_number(&source).with_context(|| format!("parsing {}", path.display()))
+    }
 }
 
 impl PythonDistribution for StandaloneDistribution {
@@ -1195,8 +1294,11 @@ impl PythonDistribution for StandaloneDistribution {
         let mut policy = PythonPackagingPolicy::default();
 
         // In-memory shared library loading is brittle. Disable this configuration
-        // even if supported because it leads to pain.
-        if self.supports_in_memory_shared_library_loading() {
+        // even if supported because it leads to pain. On Linux, in-memory loading
+        // is opt-in and doesn't change the default locations.
+        if self.target_triple.contains("pc-windows")
+            && self.supports_in_memory_shared_library_loading()
+        {
             policy.set_resources_location(ConcreteResourceLocation::InMemory);
             policy.set_resources_location_fallback(Some(ConcreteResourceLocation::RelativePath(
                 "lib".to_string(),
@@ -1293,6 +1395,7 @@ impl PythonDistribution for StandaloneDistribution {
                     data: FileData::Path(path.clone()),
                     is_stdlib: true,
                     is_test: self.is_stdlib_test_package(package),
+                    locale: None,
                 })
             })
         });
@@ -1355,11 +1458,13 @@ impl PythonDistribution for StandaloneDistribution {
 
     /// Determines whether dynamically linked extension modules can be loaded from memory.
     fn supports_in_memory_shared_library_loading(&self) -> bool {
-        // Loading from memory is only supported on Windows where symbols are
-        // declspec(dllexport) and the distribution is capable of loading
-        // shared library extensions.
-        self.target_triple.contains("pc-windows")
-            && self.python_symbol_visibility == "dllexport"
+        // Loading from memory is supported on Windows where symbols are
+        // declspec(dllexport) and on Linux, where libraries are loaded from
+        // memory backed files. In both cases the distribution must be capable
+        // of loading shared library extensions.
+        (self.target_triple.contains("linux")
+            || (self.target_triple.contains("pc-windows")
+                && self.python_symbol_visibility == "dllexport"))
             && self
                 .extension_module_loading
                 .contains(&"shared-library".to_string())
@@ -1412,6 +1517,56 @@ pub mod tests {
         std::collections::BTreeSet,
     };
 
+    #[test]
+    fn test_parse_bytecode_magic_number() -> Result<()> {
+        assert_eq!(
+            parse_bytecode_magic_number(
+                "_RAW_MAGIC_NUMBER = 0\nMAGIC_NUMBER = (3439).to_bytes(2, 'little') + b'\\r\\n'\n"
+            )?,
+            168627567
+        );
+        assert!(parse_bytecode_magic_number("MAGIC_NUMBER = b'foo'").is_err());
+        assert!(parse_bytecode_magic_number("").is_err());
+
+        assert_eq!(
+            parse_bytecode_magic_number_header(
+                "#define PYC_MAGIC_NUMBER_TOKEN 0\n#define PYC_MAGIC_NUMBER 3627\n"
+            )?,
+            u32::from_le_bytes([0x2b, 0x0e, 0x0d, 0x0a])
+        );
+        assert!(parse_bytecode_magic_number_header("").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_python_version_tuple() -> Result<()> {
+        assert_eq!(parse_python_version_tuple("3.9.16")?, (3, 9));
+        assert_eq!(parse_python_version_tuple("3.10.9")?, (3, 10));
+        assert_eq!(parse_python_version_tuple("3.14.0a1")?, (3, 14));
+        assert_eq!(parse_python_version_tuple("3.14")?, (3, 14));
+        assert_eq!(parse_python_version_tuple("3")?, (3, 0));
+        assert!(parse_python_version_tuple("3.10")? > parse_python_version_tuple("3.9")?);
+        assert!(parse_python_version_tuple("x.y").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytecode_magic_number() -> Result<()> {
+        let distribution = get_default_distribution(None)?;
+
+        let temp_dir = get_env()?.temporary_directory("pyoxidizer-test")?;
+        let compiler = BytecodeCompiler::new(distribution.python_exe_path(), temp_dir.path())?;
+
+        assert_eq!(
+            distribution.bytecode_magic_number()?,
+            compiler.get_magic_number()
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_stdlib_annotations() -> Result<()> {
         let distribution = get_default_distribution(None)?;
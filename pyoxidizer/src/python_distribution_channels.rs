@@ -0,0 +1,797 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Python distribution channels and lock files.
+
+A channel (e.g. `cpython-3.10-latest`) names a stream of Python distributions
+instead of a specific archive. The first time a channel is resolved for a
+target, the resolved archive URL and SHA-256 are recorded in a lock file next
+to the configuration file. Later resolutions use the locked archive, so
+upgrading a Python distribution is an explicit change to the lock file rather
+than a side-effect of upgrading PyOxidizer.
+
+Lock files can also pin the extension module variants of a distribution,
+such as whether `readline` links against libedit or readline, and the
+libraries each variant links. A distribution whose extension modules differ
+from the pinned ones is rejected until the change is accepted, so upgrading
+a distribution doesn't change interpreter behavior unnoticed.
+*/
+
+use {
+    crate::{
+        py_packaging::distribution::{
+            DistributionFlavor, PythonDistribution, PythonDistributionLocation,
+        },
+        python_distributions::PYTHON_DISTRIBUTIONS,
+    },
+    anyhow::{anyhow, Context, Result},
+    log::warn,
+    python_packaging::{licensing::LicenseFlavor, resource::PythonResource},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::BTreeMap,
+        fmt::{Display, Formatter},
+        path::Path,
+        str::FromStr,
+    },
+    tugger_common::http::get_http_client,
+    url::Url,
+};
+
+/// Filename of the lock file recording resolved Python distributions.
+pub const LOCK_FILE_NAME: &str = "pyoxidizer.lock";
+
+/// GitHub API URL describing the latest python-build-standalone release.
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/indygreg/python-build-standalone/releases/latest";
+
+/// A named stream of Python distributions.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DistributionChannel {
+    /// X.Y major.minor version of Python.
+    pub python_major_minor_version: String,
+}
+
+impl Display for DistributionChannel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cpython-{}-latest", self.python_major_minor_version)
+    }
+}
+
+impl FromStr for DistributionChannel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let version = s
+            .strip_prefix("cpython-")
+            .and_then(|s| s.strip_suffix("-latest"))
+            .filter(|v| {
+                let parts = v.split('.').collect::<Vec<_>>();
+                parts.len() == 2
+                    && parts
+                        .iter()
+                        .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+            })
+            .ok_or_else(|| {
+                format!(
+                    "distribution channel {} not recognized; expected cpython-X.Y-latest",
+                    s
+                )
+            })?;
+
+        Ok(Self {
+            python_major_minor_version: version.to_string(),
+        })
+    }
+}
+
+/// A Python distribution a channel was resolved to.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct LockedPythonDistribution {
+    /// Name of the channel.
+    pub channel: String,
+
+    /// Rust target triple the distribution runs on.
+    pub target_triple: String,
+
+    /// Flavor of the distribution.
+    pub flavor: String,
+
+    /// URL of the distribution archive.
+    pub url: String,
+
+    /// SHA-256 of the distribution archive.
+    pub sha256: String,
+}
+
+impl LockedPythonDistribution {
+    fn key(&self) -> (&str, &str, &str) {
+        (&self.channel, &self.target_triple, &self.flavor)
+    }
+
+    /// Obtain the location of the distribution archive.
+    pub fn location(&self) -> PythonDistributionLocation {
+        PythonDistributionLocation::Url {
+            url: self.url.clone(),
+            sha256: self.sha256.clone(),
+        }
+    }
+}
+
+/// Properties of a variant of an extension module that affect behavior.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ExtensionModuleVariant {
+    /// Name of the variant.
+    pub variant: String,
+
+    /// Whether the variant is compiled into libpython by default.
+    pub builtin_default: bool,
+
+    /// Whether the extension module is required to initialize Python.
+    pub required: bool,
+
+    /// Names of libraries the variant links against, sorted.
+    pub link_libraries: Vec<String>,
+
+    /// License of the variant.
+    pub license: String,
+}
+
+/// Extension module variants of a distribution, keyed by module name.
+pub type ExtensionModuleVariants = BTreeMap<String, Vec<ExtensionModuleVariant>>;
+
+/// Extension module variants pinned for a distribution.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PinnedExtensionModules {
+    /// Name identifying the distribution.
+    pub distribution: String,
+
+    /// The pinned extension module variants.
+    pub extension_modules: ExtensionModuleVariants,
+}
+
+/// Content of a lock file.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct LockFile {
+    /// Resolved Python distributions.
+    pub python_distributions: Vec<LockedPythonDistribution>,
+
+    /// Pinned extension module variants.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension_modules: Vec<PinnedExtensionModules>,
+}
+
+impl LockFile {
+    /// Read a lock file.
+    ///
+    /// An empty lock file is returned if the file doesn't exist.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+
+        serde_json::from_slice(&data).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    /// Write the lock file to a path.
+    pub fn write_path(&self, path: &Path) -> Result<()> {
+        let mut data = serde_json::to_string_pretty(self)?;
+        data.push('\n');
+
+        std::fs::write(path, data).with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// Find the locked distribution for a channel, target triple, and flavor.
+    pub fn find(
+        &self,
+        channel: &DistributionChannel,
+        target_triple: &str,
+        flavor: &DistributionFlavor,
+    ) -> Option<&LockedPythonDistribution> {
+        let channel = channel.to_string();
+        let flavor = flavor.to_string();
+
+        self.python_distributions
+            .iter()
+            .find(|d| d.key() == (channel.as_str(), target_triple, flavor.as_str()))
+    }
+
+    /// Record a locked distribution, replacing an existing entry for the same key.
+    ///
+    /// Entries are kept sorted so the file diffs cleanly.
+    pub fn insert(&mut self, entry: LockedPythonDistribution) {
+        self.python_distributions.retain(|d| d.key() != entry.key());
+        self.python_distributions.push(entry);
+        self.python_distributions
+            .sort_by(|a, b| a.key().cmp(&b.key()));
+    }
+
+    /// Find the pinned extension modules of a distribution.
+    pub fn find_extension_modules(&self, distribution: &str) -> Option<&PinnedExtensionModules> {
+        self.extension_modules
+            .iter()
+            .find(|p| p.distribution == distribution)
+    }
+
+    /// Record pinned extension modules, replacing an existing entry for the same distribution.
+    pub fn insert_extension_modules(&mut self, entry: PinnedExtensionModules) {
+        self.extension_modules
+            .retain(|p| p.distribution != entry.distribution);
+        self.extension_modules.push(entry);
+        self.extension_modules
+            .sort_by(|a, b| a.distribution.cmp(&b.distribution));
+    }
+}
+
+/// Resolve the Python distribution of a channel, recording it in a lock file.
+///
+/// If the lock file has an entry for the channel, target triple, and flavor,
+/// its archive is used. Otherwise the channel resolves to the default
+/// distribution known to this version of PyOxidizer and the lock file is
+/// updated.
+pub fn resolve_channel_distribution(
+    lock_path: &Path,
+    channel: &DistributionChannel,
+    target_triple: &str,
+    flavor: &DistributionFlavor,
+) -> Result<PythonDistributionLocation> {
+    let mut lock = LockFile::from_path(lock_path)?;
+
+    if let Some(entry) = lock.find(channel, target_triple, flavor) {
+        return Ok(entry.location());
+    }
+
+    let dist = PYTHON_DISTRIBUTIONS
+        .find_distribution(
+            target_triple,
+            flavor,
+            Some(&channel.python_major_minor_version),
+        )
+        .ok_or_else(|| {
+            anyhow!(
+                "could not find Python distribution for channel {} and {}",
+                channel,
+                target_triple
+            )
+        })?;
+
+    let (url, sha256) = match &dist.location {
+        PythonDistributionLocation::Url { url, sha256 } => (url.clone(), sha256.clone()),
+        PythonDistributionLocation::Local { .. } => {
+            return Err(anyhow!("channel distributions must be remote"));
+        }
+    };
+
+    warn!(
+        "locking {} for {} to {} in {}",
+        channel,
+        target_triple,
+        url,
+        lock_path.display()
+    );
+
+    lock.insert(LockedPythonDistribution {
+        channel: channel.to_string(),
+        target_triple: target_triple.to_string(),
+        flavor: flavor.to_string(),
+        url,
+        sha256,
+    });
+    lock.write_path(lock_path)?;
+
+    Ok(dist.location)
+}
+
+/// Obtain the default name identifying a distribution in pinned extension modules.
+///
+/// Distributions of the same Python implementation, X.Y version and target
+/// share a name, so upgrading to a newer build of them is compared against
+/// the same pin.
+pub fn default_extension_modules_pin_name(dist: &dyn PythonDistribution) -> String {
+    format!(
+        "{}-{}-{}",
+        dist.python_implementation(),
+        dist.python_major_minor_version(),
+        dist.target_triple(),
+    )
+}
+
+/// Obtain the extension module variants of a distribution.
+pub fn extension_module_variants(dist: &dyn PythonDistribution) -> ExtensionModuleVariants {
+    let mut res = ExtensionModuleVariants::new();
+
+    for resource in dist.python_resources() {
+        let em = match resource {
+            PythonResource::ExtensionModule(em) => em,
+            _ => continue,
+        };
+
+        let mut link_libraries = em
+            .link_libraries
+            .iter()
+            .map(|l| l.name.clone())
+            .collect::<Vec<_>>();
+        link_libraries.sort();
+        link_libraries.dedup();
+
+        let license = match em.license.as_ref().map(|l| l.license()) {
+            Some(LicenseFlavor::Spdx(expression)) => expression.to_string(),
+            Some(LicenseFlavor::OtherExpression(expression)) => expression.to_string(),
+            Some(LicenseFlavor::PublicDomain) => "public domain".to_string(),
+            Some(LicenseFlavor::Unknown(terms)) => terms.join(","),
+            Some(LicenseFlavor::None) | None => "none".to_string(),
+        };
+
+        res.entry(em.name.clone())
+            .or_default()
+            .push(ExtensionModuleVariant {
+                variant: em.variant.clone().unwrap_or_else(|| "default".to_string()),
+                builtin_default: em.builtin_default,
+                required: em.required,
+                link_libraries,
+                license,
+            });
+    }
+
+    for variants in res.values_mut() {
+        variants.sort_by(|a, b| a.variant.cmp(&b.variant));
+    }
+
+    res
+}
+
+/// Describe the differences between two sets of extension module variants.
+///
+/// Returns a line per difference.
+pub fn diff_extension_module_variants(
+    old: &ExtensionModuleVariants,
+    new: &ExtensionModuleVariants,
+) -> Vec<String> {
+    let mut res = vec![];
+
+    let variant_names = |variants: &[ExtensionModuleVariant]| {
+        variants
+            .iter()
+            .map(|v| v.variant.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    for (name, old_variants) in old {
+        if !new.contains_key(name) {
+            res.push(format!(
+                "{}: removed (variants: {})",
+                name,
+                variant_names(old_variants)
+            ));
+        }
+    }
+
+    for (name, new_variants) in new {
+        let old_variants = match old.get(name) {
+            Some(variants) => variants,
+            None => {
+                res.push(format!(
+                    "{}: added (variants: {})",
+                    name,
+                    variant_names(new_variants)
+                ));
+                continue;
+            }
+        };
+
+        for old_variant in old_variants {
+            if !new_variants
+                .iter()
+                .any(|v| v.variant == old_variant.variant)
+            {
+                res.push(format!("{}: variant {} removed", name, old_variant.variant));
+            }
+        }
+
+        for new_variant in new_variants {
+            let old_variant = match old_variants
+                .iter()
+                .find(|v| v.variant == new_variant.variant)
+            {
+                Some(v) => v,
+                None => {
+                    res.push(format!("{}: variant {} added", name, new_variant.variant));
+                    continue;
+                }
+            };
+
+            let prefix = format!("{} ({})", name, new_variant.variant);
+
+            if old_variant.builtin_default != new_variant.builtin_default {
+                res.push(format!(
+                    "{}: builtin_default {} -> {}",
+                    prefix, old_variant.builtin_default, new_variant.builtin_default
+                ));
+            }
+            if old_variant.required != new_variant.required {
+                res.push(format!(
+                    "{}: required {} -> {}",
+                    prefix, old_variant.required, new_variant.required
+                ));
+            }
+            if old_variant.link_libraries != new_variant.link_libraries {
+                res.push(format!(
+                    "{}: link libraries [{}] -> [{}]",
+                    prefix,
+                    old_variant.link_libraries.join(", "),
+                    new_variant.link_libraries.join(", ")
+                ));
+            }
+            if old_variant.license != new_variant.license {
+                res.push(format!(
+                    "{}: license {} -> {}",
+                    prefix, old_variant.license, new_variant.license
+                ));
+            }
+        }
+    }
+
+    res
+}
+
+/// Verify extension module variants against those pinned in a lock file.
+///
+/// If the lock file doesn't pin extension modules for the distribution, they
+/// are pinned. If the pinned variants differ, an error describing the
+/// differences is returned, unless `update` is set, in which case the pin is
+/// updated.
+pub fn pin_extension_module_variants(
+    lock_path: &Path,
+    distribution: &str,
+    variants: ExtensionModuleVariants,
+    update: bool,
+) -> Result<()> {
+    let mut lock = LockFile::from_path(lock_path)?;
+
+    if let Some(pinned) = lock.find_extension_modules(distribution) {
+        let changes = diff_extension_module_variants(&pinned.extension_modules, &variants);
+
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        if !update {
+            return Err(anyhow!(
+                "extension modules of {} differ from those pinned in {}:\n  {}\n\
+                review these changes and pass update=True to accept them",
+                distribution,
+                lock_path.display(),
+                changes.join("\n  ")
+            ));
+        }
+
+        for change in &changes {
+            warn!("{}: {}", distribution, change);
+        }
+    }
+
+    warn!(
+        "pinning extension modules of {} in {}",
+        distribution,
+        lock_path.display()
+    );
+
+    lock.insert_extension_modules(PinnedExtensionModules {
+        distribution: distribution.to_string(),
+        extension_modules: variants,
+    });
+    lock.write_path(lock_path)
+}
+
+/// Obtain the python-build-standalone release tag of a distribution URL.
+pub fn release_tag(url: &str) -> Option<&str> {
+    let mut parts = url.split('/');
+
+    parts.find(|p| *p == "download")?;
+
+    parts.next()
+}
+
+/// An asset of a python-build-standalone release.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+/// A python-build-standalone release.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub assets: Vec<ReleaseAsset>,
+}
+
+impl Release {
+    /// Find the URL of the equivalent of a distribution in this release.
+    ///
+    /// The equivalent has the same Python X.Y version, target triple, and
+    /// build configuration. Returns `None` if the distribution isn't older
+    /// than this release or this release has no equivalent.
+    pub fn updated_url(&self, url: &str) -> Option<String> {
+        let tag = release_tag(url)?;
+
+        if tag >= self.tag_name.as_str() {
+            return None;
+        }
+
+        // Filenames look like cpython-3.10.9+20221220-x86_64-unknown-linux-gnu-pgo-full.tar.zst.
+        let filename = Url::parse(url)
+            .ok()?
+            .path_segments()?
+            .next_back()?
+            .replace("%2B", "+");
+        let (version, suffix) = filename.strip_prefix("cpython-")?.split_once('+')?;
+        let suffix = suffix.strip_prefix(tag)?;
+        let major_minor = version.rsplit_once('.')?.0;
+
+        let prefix = format!("cpython-{}.", major_minor);
+        let suffix = format!("+{}{}", self.tag_name, suffix);
+
+        self.assets
+            .iter()
+            .find(|a| {
+                a.name
+                    .strip_prefix(&prefix)
+                    .and_then(|s| s.strip_suffix(&suffix))
+                    .map(|patch| !patch.is_empty() && patch.chars().all(|c| c.is_ascii_digit()))
+                    .unwrap_or(false)
+            })
+            .map(|a| a.browser_download_url.clone())
+    }
+}
+
+/// Fetch metadata of the latest python-build-standalone release.
+pub fn fetch_latest_release() -> Result<Release> {
+    let client = get_http_client()?;
+    let response = client
+        .get(LATEST_RELEASE_URL)
+        .header("User-Agent", "pyoxidizer")
+        .send()?
+        .error_for_status()?;
+
+    serde_json::from_reader(response).context("parsing python-build-standalone release")
+}
+
+/// Fetch the SHA-256 of a python-build-standalone release asset.
+///
+/// Releases publish the digest of each asset in a `.sha256` file next to it.
+pub fn fetch_sha256(url: &str) -> Result<String> {
+    let client = get_http_client()?;
+    let text = client
+        .get(format!("{}.sha256", url))
+        .send()?
+        .error_for_status()?
+        .text()?;
+
+    let sha256 = text.split_whitespace().next().unwrap_or_default();
+
+    if sha256.len() != 64 || hex::decode(sha256).is_err() {
+        return Err(anyhow!("invalid SHA-256 for {}: {}", url, text.trim()));
+    }
+
+    Ok(sha256.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://github.com/indygreg/python-build-standalone/releases/download/20221220/cpython-3.10.9%2B20221220-x86_64-unknown-linux-gnu-pgo-full.tar.zst";
+
+    #[test]
+    fn test_channel_parse() {
+        assert_eq!(
+            DistributionChannel::from_str("cpython-3.10-latest"),
+            Ok(DistributionChannel {
+                python_major_minor_version: "3.10".to_string()
+            })
+        );
+        assert_eq!(
+            DistributionChannel::from_str("cpython-3.10-latest")
+                .unwrap()
+                .to_string(),
+            "cpython-3.10-latest"
+        );
+        assert!(DistributionChannel::from_str("cpython-3.10").is_err());
+        assert!(DistributionChannel::from_str("cpython-3-latest").is_err());
+        assert!(DistributionChannel::from_str("pypy-3.10-latest").is_err());
+    }
+
+    #[test]
+    fn test_release_tag() {
+        assert_eq!(release_tag(URL), Some("20221220"));
+        assert_eq!(release_tag("https://example.com/python.tar.zst"), None);
+    }
+
+    #[test]
+    fn test_updated_url() {
+        let asset = |name: &str| ReleaseAsset {
+            name: name.to_string(),
+            browser_download_url: format!(
+                "https://github.com/indygreg/python-build-standalone/releases/download/20230116/{}",
+                name.replace('+', "%2B")
+            ),
+        };
+
+        let release = Release {
+            tag_name: "20230116".to_string(),
+            assets: vec![
+                asset("cpython-3.9.16+20230116-x86_64-unknown-linux-gnu-pgo-full.tar.zst"),
+                asset("cpython-3.10.9+20230116-x86_64-unknown-linux-gnu-debug-full.tar.zst"),
+                asset("cpython-3.10.9+20230116-x86_64-unknown-linux-gnu-pgo-full.tar.zst"),
+                asset("cpython-3.10.9+20230116-x86_64-unknown-linux-gnu-pgo-full.tar.zst.sha256"),
+            ],
+        };
+
+        assert_eq!(
+            release.updated_url(URL),
+            Some("https://github.com/indygreg/python-build-standalone/releases/download/20230116/cpython-3.10.9%2B20230116-x86_64-unknown-linux-gnu-pgo-full.tar.zst".to_string())
+        );
+
+        let release = Release {
+            tag_name: "20221220".to_string(),
+            ..release
+        };
+        assert_eq!(release.updated_url(URL), None);
+    }
+
+    #[test]
+    fn test_resolve_channel_distribution() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let lock_path = td.path().join(LOCK_FILE_NAME);
+
+        let channel = DistributionChannel::from_str("cpython-3.10-latest").unwrap();
+        let flavor = DistributionFlavor::Standalone;
+        let target = "x86_64-unknown-linux-gnu";
+
+        let wanted = PYTHON_DISTRIBUTIONS
+            .find_distribution(target, &flavor, Some("3.10"))
+            .unwrap();
+
+        assert_eq!(
+            resolve_channel_distribution(&lock_path, &channel, target, &flavor)?,
+            wanted.location
+        );
+
+        let mut lock = LockFile::from_path(&lock_path)?;
+        assert_eq!(lock.python_distributions.len(), 1);
+        assert_eq!(
+            lock.find(&channel, target, &flavor).map(|d| d.location()),
+            Some(wanted.location)
+        );
+
+        // The locked distribution wins over the built-in default.
+        lock.insert(LockedPythonDistribution {
+            channel: channel.to_string(),
+            target_triple: target.to_string(),
+            flavor: flavor.to_string(),
+            url: "https://example.com/python.tar.zst".to_string(),
+            sha256: "00".repeat(32),
+        });
+        assert_eq!(lock.python_distributions.len(), 1);
+        lock.write_path(&lock_path)?;
+
+        assert_eq!(
+            resolve_channel_distribution(&lock_path, &channel, target, &flavor)?,
+            PythonDistributionLocation::Url {
+                url: "https://example.com/python.tar.zst".to_string(),
+                sha256: "00".repeat(32),
+            }
+        );
+
+        Ok(())
+    }
+
+    fn variant(name: &str, link_libraries: &[&str]) -> ExtensionModuleVariant {
+        ExtensionModuleVariant {
+            variant: name.to_string(),
+            builtin_default: false,
+            required: false,
+            link_libraries: link_libraries.iter().map(|l| l.to_string()).collect(),
+            license: "Python-2.0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_diff_extension_module_variants() {
+        let mut old = ExtensionModuleVariants::new();
+        old.insert(
+            "_ssl".to_string(),
+            vec![variant("default", &["crypto", "ssl"])],
+        );
+        old.insert(
+            "readline".to_string(),
+            vec![
+                variant("libedit", &["edit"]),
+                variant("readline", &["readline"]),
+            ],
+        );
+        old.insert("_tkinter".to_string(), vec![variant("default", &["tcl"])]);
+
+        assert!(diff_extension_module_variants(&old, &old).is_empty());
+
+        let mut new = old.clone();
+        new.remove("_tkinter");
+        new.insert(
+            "_sqlite3".to_string(),
+            vec![variant("default", &["sqlite3"])],
+        );
+        new.get_mut("_ssl").unwrap()[0].link_libraries = vec!["ssl".to_string()];
+        new.get_mut("_ssl").unwrap()[0].builtin_default = true;
+        new.get_mut("readline").unwrap().remove(0);
+
+        assert_eq!(
+            diff_extension_module_variants(&old, &new),
+            vec![
+                "_tkinter: removed (variants: default)",
+                "_sqlite3: added (variants: default)",
+                "_ssl (default): builtin_default false -> true",
+                "_ssl (default): link libraries [crypto, ssl] -> [ssl]",
+                "readline: variant libedit removed",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pin_extension_module_variants() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let lock_path = td.path().join(LOCK_FILE_NAME);
+
+        let mut variants = ExtensionModuleVariants::new();
+        variants.insert("readline".to_string(), vec![variant("libedit", &["edit"])]);
+
+        pin_extension_module_variants(&lock_path, "cpython-3.10", variants.clone(), false)?;
+
+        let lock = LockFile::from_path(&lock_path)?;
+        assert!(lock.python_distributions.is_empty());
+        assert_eq!(
+            lock.find_extension_modules("cpython-3.10")
+                .map(|p| &p.extension_modules),
+            Some(&variants)
+        );
+
+        // Unchanged variants pass.
+        pin_extension_module_variants(&lock_path, "cpython-3.10", variants.clone(), false)?;
+
+        let mut changed = variants.clone();
+        changed.insert(
+            "readline".to_string(),
+            vec![variant("readline", &["readline"])],
+        );
+
+        let err = pin_extension_module_variants(&lock_path, "cpython-3.10", changed.clone(), false)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("readline: variant libedit removed\n  readline: variant readline added"));
+        assert_eq!(
+            LockFile::from_path(&lock_path)?
+                .find_extension_modules("cpython-3.10")
+                .map(|p| &p.extension_modules),
+            Some(&variants)
+        );
+
+        pin_extension_module_variants(&lock_path, "cpython-3.10", changed.clone(), true)?;
+        assert_eq!(
+            LockFile::from_path(&lock_path)?
+                .find_extension_modules("cpython-3.10")
+                .map(|p| &p.extension_modules),
+            Some(&changed)
+        );
+
+        Ok(())
+    }
+}
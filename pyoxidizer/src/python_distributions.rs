@@ -49,7 +49,6 @@ impl PythonDistributionCollection {
     }
 
     /// Obtain records for all registered distributions.
-    #[allow(unused)]
     pub fn iter(&self) -> impl Iterator<Item = &PythonDistributionRecord> {
         self.dists.iter()
     }
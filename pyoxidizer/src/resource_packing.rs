@@ -0,0 +1,326 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Pack resources of an existing Python installation for `oxidized_importer`.
+
+This powers `pyoxidizer pack-resources`. Unlike a regular PyOxidizer build,
+no Python distribution is involved: resources are found in the site-packages
+directories of an existing Python interpreter (typically a virtualenv) and
+bytecode is compiled with that interpreter. The result is a packed resources
+file that an `OxidizedFinder` running in that interpreter can index.
+
+Extension modules can't be loaded from memory by a stock interpreter. So
+top-level packages containing extension modules are left out entirely and
+continue to be imported by the standard path-based importer.
+*/
+
+use {
+    crate::environment::Environment,
+    anyhow::{anyhow, Context, Result},
+    duct::cmd,
+    python_packaging::{
+        bytecode::BytecodeCompiler,
+        filesystem_scanning::find_python_resources,
+        location::{AbstractResourceLocation, ConcreteResourceLocation},
+        module_util::PythonModuleSuffixes,
+        resource::{BytecodeOptimizationLevel, PythonResource},
+        resource_collection::PythonResourceCollector,
+    },
+    serde::Deserialize,
+    std::{
+        collections::BTreeSet,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Python code printing information about the running interpreter as JSON.
+const INTERPRETER_INFO_SCRIPT: &str = "\
+import importlib.machinery as m, json, sys, sysconfig
+paths = sysconfig.get_paths()
+print(json.dumps({
+    'cache_tag': sys.implementation.cache_tag,
+    'source': m.SOURCE_SUFFIXES,
+    'bytecode': m.BYTECODE_SUFFIXES,
+    'debug_bytecode': getattr(m, 'DEBUG_BYTECODE_SUFFIXES', []),
+    'optimized_bytecode': getattr(m, 'OPTIMIZED_BYTECODE_SUFFIXES', []),
+    'extension': m.EXTENSION_SUFFIXES,
+    'site_packages': list(dict.fromkeys([paths['purelib'], paths['platlib']])),
+}))
+";
+
+/// Information about a Python interpreter.
+#[derive(Deserialize)]
+struct InterpreterInfo {
+    cache_tag: String,
+    source: Vec<String>,
+    bytecode: Vec<String>,
+    debug_bytecode: Vec<String>,
+    optimized_bytecode: Vec<String>,
+    extension: Vec<String>,
+    site_packages: Vec<PathBuf>,
+}
+
+impl InterpreterInfo {
+    fn suffixes(&self) -> PythonModuleSuffixes {
+        PythonModuleSuffixes {
+            source: self.source.clone(),
+            bytecode: self.bytecode.clone(),
+            debug_bytecode: self.debug_bytecode.clone(),
+            optimized_bytecode: self.optimized_bytecode.clone(),
+            extension: self.extension.clone(),
+        }
+    }
+}
+
+/// Run a Python interpreter to obtain information about it.
+fn resolve_interpreter_info(python_exe: &Path) -> Result<InterpreterInfo> {
+    let output = cmd(python_exe, &["-c", INTERPRETER_INFO_SCRIPT])
+        .stdout_capture()
+        .run()
+        .with_context(|| format!("running {}", python_exe.display()))?;
+
+    serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("parsing interpreter info from {}", python_exe.display()))
+}
+
+/// Obtain the top-level package a resource belongs to.
+///
+/// Returns `None` for resources not belonging to a package.
+fn resource_top_level_package<'a>(resource: &'a PythonResource) -> Option<&'a str> {
+    let name = match resource {
+        PythonResource::ModuleSource(m) => &m.name,
+        PythonResource::ExtensionModule(m) => &m.name,
+        PythonResource::PackageResource(r) => &r.leaf_package,
+        _ => return None,
+    };
+
+    name.split('.').next()
+}
+
+/// Generate Python code registering an `OxidizedFinder` for a packed resources file.
+///
+/// If the loader is written next to the resources file, the resources file is
+/// resolved relative to the loader's location so both can be moved together.
+pub fn loader_source(resources_path: &Path, loader_path: Option<&Path>) -> String {
+    let resources_expr = match (resources_path.parent(), resources_path.file_name()) {
+        (Some(parent), Some(name)) if loader_path.and_then(|p| p.parent()) == Some(parent) => {
+            format!(
+                "os.path.join(os.path.dirname(os.path.abspath(__file__)), {:?})",
+                name.to_string_lossy()
+            )
+        }
+        _ => format!("{:?}", resources_path.display().to_string()),
+    };
+
+    format!(
+        "# Generated by `pyoxidizer pack-resources`.\n\
+         import os\n\
+         import sys\n\
+         \n\
+         import oxidized_importer\n\
+         \n\
+         finder = oxidized_importer.OxidizedFinder()\n\
+         finder.index_file_memory_mapped({})\n\
+         sys.meta_path.insert(0, finder)\n",
+        resources_expr
+    )
+}
+
+/// Pack resources from site-packages directories into a packed resources file.
+///
+/// `python_exe` is the interpreter that will load the resources. It is used to
+/// compile bytecode. If `paths` is empty, its site-packages directories are
+/// scanned.
+///
+/// If `loader_path` is defined, Python code registering an `OxidizedFinder`
+/// for the resources is written there. Otherwise it is printed.
+pub fn pack_resources(
+    env: &Environment,
+    python_exe: &Path,
+    paths: &[PathBuf],
+    output_path: &Path,
+    loader_path: Option<&Path>,
+) -> Result<()> {
+    let info = resolve_interpreter_info(python_exe)?;
+    let suffixes = info.suffixes();
+
+    let paths = if paths.is_empty() {
+        info.site_packages.clone()
+    } else {
+        paths.to_vec()
+    };
+
+    let mut resources = vec![];
+    let mut extension_packages = BTreeSet::new();
+
+    for path in paths.iter().filter(|p| p.is_dir()) {
+        println!("scanning {}", path.display());
+
+        for resource in find_python_resources(path, &info.cache_tag, &suffixes, false, true)? {
+            let resource = resource?;
+
+            if let PythonResource::ExtensionModule(_) = &resource {
+                if let Some(package) = resource_top_level_package(&resource) {
+                    extension_packages.insert(package.to_string());
+                }
+            }
+
+            resources.push(resource);
+        }
+    }
+
+    for package in &extension_packages {
+        println!(
+            "skipping {} because it contains extension modules; it will be imported from the filesystem",
+            package
+        );
+    }
+
+    let mut collector = PythonResourceCollector::new(
+        vec![AbstractResourceLocation::InMemory],
+        vec![],
+        false,
+        false,
+    );
+    let location = ConcreteResourceLocation::InMemory;
+
+    for resource in &resources {
+        if resource_top_level_package(resource)
+            .map(|package| extension_packages.contains(package))
+            .unwrap_or_default()
+        {
+            continue;
+        }
+
+        match resource {
+            PythonResource::ModuleSource(module) => {
+                collector.add_python_module_source(module, &location)?;
+                collector.add_python_module_bytecode_from_source(
+                    &module.as_bytecode_module(BytecodeOptimizationLevel::Zero),
+                    &location,
+                )?;
+            }
+            PythonResource::PackageResource(resource) => {
+                collector.add_python_package_resource(resource, &location)?;
+            }
+            PythonResource::PackageDistributionResource(resource) => {
+                collector.add_python_package_distribution_resource(resource, &location)?;
+            }
+            _ => {}
+        }
+    }
+
+    let temp_dir = env.temporary_directory("pyoxidizer-bytecode-compile")?;
+    let mut compiler = BytecodeCompiler::new(python_exe, temp_dir.path())?;
+    let compiled = collector
+        .compile_resources(&mut compiler)
+        .context("compiling resources")?;
+    drop(compiler);
+    temp_dir.close()?;
+
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("creating directory {}", parent.display()))?;
+    }
+    let mut fh = std::fs::File::create(output_path)
+        .with_context(|| format!("creating {}", output_path.display()))?;
+    compiled
+        .write_packed_resources(&mut fh)
+        .with_context(|| format!("writing {}", output_path.display()))?;
+
+    println!(
+        "wrote {} resources to {}",
+        compiled.resources.len(),
+        output_path.display()
+    );
+
+    let source = loader_source(output_path, loader_path);
+
+    if let Some(loader_path) = loader_path {
+        std::fs::write(loader_path, source)
+            .with_context(|| format!("writing {}", loader_path.display()))?;
+        println!("wrote loader to {}", loader_path.display());
+    } else {
+        println!();
+        println!("register the resources with the following Python code:");
+        println!();
+        print!("{}", source);
+    }
+
+    if compiled.resources.is_empty() {
+        Err(anyhow!("no resources found"))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::testutil::*};
+
+    #[test]
+    fn test_loader_source() {
+        let resources = Path::new("/app/resources");
+
+        assert!(loader_source(resources, Some(Path::new("/app/loader.py")))
+            .contains("os.path.join(os.path.dirname(os.path.abspath(__file__)), \"resources\")"));
+        assert!(
+            loader_source(resources, Some(Path::new("/other/loader.py")))
+                .contains("finder.index_file_memory_mapped(\"/app/resources\")")
+        );
+        assert!(loader_source(resources, None)
+            .contains("finder.index_file_memory_mapped(\"/app/resources\")"));
+    }
+
+    #[test]
+    fn test_pack_resources() -> Result<()> {
+        let env = get_env()?;
+        let temp_dir = env.temporary_directory("pyoxidizer-test")?;
+        let root = temp_dir.path();
+
+        let site_packages = root.join("site-packages");
+        std::fs::create_dir_all(site_packages.join("foo"))?;
+        std::fs::write(site_packages.join("foo").join("__init__.py"), "VALUE = 1\n")?;
+        std::fs::write(site_packages.join("foo").join("data.txt"), "data")?;
+        std::fs::create_dir_all(site_packages.join("ext"))?;
+        std::fs::write(site_packages.join("ext").join("__init__.py"), "")?;
+        std::fs::write(
+            site_packages.join("ext").join(if cfg!(windows) {
+                "_speedups.pyd"
+            } else {
+                "_speedups.so"
+            }),
+            "",
+        )?;
+
+        let python_exe = which::which("python3")?;
+        let output_path = root.join("packed-resources");
+
+        pack_resources(
+            &env,
+            &python_exe,
+            &[site_packages],
+            &output_path,
+            Some(&root.join("loader.py")),
+        )?;
+
+        let data = std::fs::read(&output_path)?;
+        let resources = python_packed_resources::load_resources(&data)
+            .map_err(|e| anyhow!("{}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow!("{}", e))?;
+        let names = resources
+            .iter()
+            .map(|r| r.name.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["foo".to_string()]);
+        assert!(resources[0].in_memory_bytecode.is_some());
+        assert!(resources[0].in_memory_package_resources.is_some());
+        assert!(root.join("loader.py").exists());
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,754 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! JSON-RPC interface to PyOxidizer functionality.
+
+This powers `pyoxidizer serve`, which allows editor integrations and other
+tools to evaluate configuration files, list targets, query resources and
+build targets without spawning a new process for every operation.
+
+Messages are JSON-RPC 2.0 payloads framed like the Language Server Protocol:
+each message is preceded by a `Content-Length` header and an empty line.
+*/
+
+use {
+    crate::{
+        environment::{default_target_triple, Environment, PYOXIDIZER_VERSION},
+        project_building::find_pyoxidizer_config_file,
+        py_packaging::distribution::DistributionCache,
+        starlark::{
+            eval::{EvaluationContext, EvaluationContextBuilder},
+            python_executable::PythonExecutableValue,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    python_packaging::resource_collection::PrePackagedResource,
+    serde::{Deserialize, Serialize},
+    serde_json::Value as JsonValue,
+    starlark_dialect_build_targets::RunMode,
+    std::{
+        collections::HashMap,
+        io::{BufRead, Write},
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+};
+
+/// Invalid JSON was received.
+pub const PARSE_ERROR: i64 = -32700;
+/// The JSON sent is not a valid request object.
+pub const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Invalid method parameters.
+pub const INVALID_PARAMS: i64 = -32602;
+/// The operation requested by a valid request failed.
+pub const REQUEST_FAILED: i64 = -32000;
+
+/// Methods understood by the server.
+pub const METHODS: &[&str] = &[
+    "initialize",
+    "shutdown",
+    "exit",
+    "config/evaluate",
+    "config/targets",
+    "config/completions",
+    "target/resources",
+    "target/build",
+];
+
+/// A JSON-RPC request or notification.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    /// Absent for notifications, which don't receive a response.
+    #[serde(default)]
+    pub id: Option<JsonValue>,
+    pub method: String,
+    #[serde(default)]
+    pub params: JsonValue,
+}
+
+/// A JSON-RPC error object.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ResponseError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl ResponseError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// A JSON-RPC response.
+#[derive(Clone, Debug, Serialize)]
+pub struct Response {
+    pub jsonrpc: &'static str,
+    pub id: JsonValue,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ResponseError>,
+}
+
+impl Response {
+    fn new(id: JsonValue, result: Result<JsonValue, ResponseError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+/// Parameters common to methods evaluating a configuration file.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ConfigParams {
+    /// Path to the config file or a directory to search for one.
+    path: Option<PathBuf>,
+    target_triple: Option<String>,
+    release: bool,
+    vars: HashMap<String, Option<String>>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct CompletionsParams {
+    #[serde(flatten)]
+    config: ConfigParams,
+    expression: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct TargetParams {
+    #[serde(flatten)]
+    config: ConfigParams,
+    target: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct BuildParams {
+    #[serde(flatten)]
+    config: ConfigParams,
+    targets: Option<Vec<String>>,
+    jobs: Option<usize>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TargetInfo {
+    name: String,
+    depends: Vec<String>,
+    variant: Option<String>,
+    default: bool,
+    build_path: PathBuf,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourceInfo {
+    name: String,
+    is_module: bool,
+    is_package: bool,
+    is_extension_module: bool,
+    is_builtin_extension_module: bool,
+    is_shared_library: bool,
+    is_file: bool,
+    in_memory: bool,
+    relative_path: bool,
+}
+
+impl From<&PrePackagedResource> for ResourceInfo {
+    fn from(r: &PrePackagedResource) -> Self {
+        Self {
+            name: r.name.clone(),
+            is_module: r.is_module,
+            is_package: r.is_package,
+            is_extension_module: r.is_extension_module,
+            is_builtin_extension_module: r.is_builtin_extension_module,
+            is_shared_library: r.is_shared_library,
+            is_file: r.is_utf8_filename_data,
+            in_memory: r.in_memory_source.is_some()
+                || r.in_memory_bytecode.is_some()
+                || r.in_memory_bytecode_opt1.is_some()
+                || r.in_memory_bytecode_opt2.is_some()
+                || r.in_memory_extension_module_shared_library.is_some()
+                || r.in_memory_resources.is_some()
+                || r.in_memory_localized_resources.is_some()
+                || r.in_memory_distribution_resources.is_some()
+                || r.in_memory_shared_library.is_some()
+                || r.file_data_embedded.is_some(),
+            relative_path: r.relative_path_module_source.is_some()
+                || r.relative_path_bytecode.is_some()
+                || r.relative_path_bytecode_opt1.is_some()
+                || r.relative_path_bytecode_opt2.is_some()
+                || r.relative_path_extension_module_shared_library.is_some()
+                || r.relative_path_package_resources.is_some()
+                || r.relative_path_distribution_resources.is_some()
+                || r.relative_path_shared_library.is_some()
+                || r.file_data_utf8_relative_path.is_some(),
+        }
+    }
+}
+
+/// Read a message framed with a `Content-Length` header.
+///
+/// Returns `None` on end of input.
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<Vec<u8>>> {
+    let mut content_length = None;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return if content_length.is_none() {
+                Ok(None)
+            } else {
+                Err(anyhow!("unexpected end of input in message headers"))
+            };
+        }
+
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+
+            continue;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .context("parsing Content-Length header")?,
+                );
+            }
+        } else {
+            return Err(anyhow!("malformed message header: {}", line));
+        }
+    }
+
+    let mut data = vec![0; content_length.unwrap()];
+    reader.read_exact(&mut data)?;
+
+    Ok(Some(data))
+}
+
+/// Write a message framed with a `Content-Length` header.
+pub fn write_message(writer: &mut impl Write, data: &[u8]) -> Result<()> {
+    write!(writer, "Content-Length: {}\r\n\r\n", data.len())?;
+    writer.write_all(data)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn parse_params<T: for<'de> Deserialize<'de> + Default>(
+    value: JsonValue,
+) -> Result<T, ResponseError> {
+    if value.is_null() {
+        Ok(T::default())
+    } else {
+        serde_json::from_value(value).map_err(|e| ResponseError::new(INVALID_PARAMS, e))
+    }
+}
+
+fn failed(e: anyhow::Error) -> ResponseError {
+    ResponseError::new(REQUEST_FAILED, format!("{:?}", e))
+}
+
+/// Serves requests against a PyOxidizer environment.
+///
+/// Python distributions are cached for the lifetime of the server, so
+/// repeated evaluations and builds don't fetch them again.
+pub struct Server {
+    env: Environment,
+    cwd: PathBuf,
+    distribution_cache: Arc<DistributionCache>,
+    shutdown_requested: bool,
+    exit_requested: bool,
+}
+
+impl Server {
+    pub fn new(env: &Environment) -> Result<Self> {
+        let distribution_cache = Arc::new(DistributionCache::new(Some(
+            &env.python_distributions_dir(),
+        )));
+
+        Ok(Self {
+            env: env.clone(),
+            cwd: std::env::current_dir()?,
+            distribution_cache,
+            shutdown_requested: false,
+            exit_requested: false,
+        })
+    }
+
+    /// Whether an `exit` notification has been received.
+    pub fn exit_requested(&self) -> bool {
+        self.exit_requested
+    }
+
+    /// Handle a raw message, returning the response to send, if any.
+    pub fn handle_message(&mut self, data: &[u8]) -> Option<Response> {
+        let value = match serde_json::from_slice::<JsonValue>(data) {
+            Ok(value) => value,
+            Err(e) => {
+                return Some(Response::new(
+                    JsonValue::Null,
+                    Err(ResponseError::new(PARSE_ERROR, e)),
+                ));
+            }
+        };
+
+        let id = value.get("id").cloned().unwrap_or(JsonValue::Null);
+
+        let request = match serde_json::from_value::<Request>(value) {
+            Ok(request) if request.jsonrpc == "2.0" => request,
+            Ok(_) => {
+                return Some(Response::new(
+                    id,
+                    Err(ResponseError::new(
+                        INVALID_REQUEST,
+                        "only JSON-RPC 2.0 is supported",
+                    )),
+                ));
+            }
+            Err(e) => {
+                return Some(Response::new(
+                    id,
+                    Err(ResponseError::new(INVALID_REQUEST, e)),
+                ));
+            }
+        };
+
+        self.handle_request(request)
+    }
+
+    /// Handle a request, returning the response to send, if any.
+    pub fn handle_request(&mut self, request: Request) -> Option<Response> {
+        let result = self.dispatch(&request.method, request.params);
+
+        request.id.map(|id| Response::new(id, result))
+    }
+
+    fn dispatch(&mut self, method: &str, params: JsonValue) -> Result<JsonValue, ResponseError> {
+        if self.shutdown_requested && method != "exit" {
+            return Err(ResponseError::new(
+                INVALID_REQUEST,
+                "server is shutting down",
+            ));
+        }
+
+        match method {
+            "initialize" => Ok(serde_json::json!({
+                "name": "pyoxidizer",
+                "version": PYOXIDIZER_VERSION,
+                "methods": METHODS,
+            })),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Ok(JsonValue::Null)
+            }
+            "exit" => {
+                self.exit_requested = true;
+                Ok(JsonValue::Null)
+            }
+            "config/evaluate" => self.evaluate(parse_params(params)?).map_err(failed),
+            "config/targets" => self.targets(parse_params(params)?).map_err(failed),
+            "config/completions" => self.completions(parse_params(params)?).map_err(failed),
+            "target/resources" => self.resources(parse_params(params)?).map_err(failed),
+            "target/build" => self.build(parse_params(params)?).map_err(failed),
+            _ => Err(ResponseError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method: {}", method),
+            )),
+        }
+    }
+
+    fn config_path(&self, params: &ConfigParams) -> Result<PathBuf> {
+        let path = match &params.path {
+            Some(path) if path.is_absolute() => path.clone(),
+            Some(path) => self.cwd.join(path),
+            None => self.cwd.clone(),
+        };
+
+        if path.is_file() {
+            Ok(path)
+        } else {
+            find_pyoxidizer_config_file(&path).ok_or_else(|| {
+                anyhow!(
+                    "unable to find PyOxidizer config file at {}",
+                    path.display()
+                )
+            })
+        }
+    }
+
+    fn builder(&self, params: &ConfigParams) -> Result<(PathBuf, EvaluationContextBuilder)> {
+        let config_path = self.config_path(params)?;
+        let target_triple = params
+            .target_triple
+            .clone()
+            .unwrap_or_else(|| default_target_triple().to_string());
+
+        let builder = EvaluationContextBuilder::new(&self.env, &config_path, target_triple)
+            .extra_vars(params.vars.clone())
+            .release(params.release)
+            .distribution_cache(self.distribution_cache.clone());
+
+        Ok((config_path, builder))
+    }
+
+    /// Evaluate a config file without resolving any targets.
+    fn evaluate_config(&self, params: &ConfigParams) -> Result<(PathBuf, EvaluationContext)> {
+        let (config_path, builder) = self.builder(params)?;
+
+        let mut context = builder.resolve_targets(vec![]).into_context()?;
+        context.evaluate_file(&config_path)?;
+
+        Ok((config_path, context))
+    }
+
+    fn target_infos(&self, context: &EvaluationContext) -> Result<Vec<TargetInfo>> {
+        let default_target = context.default_target()?;
+
+        context
+            .targets()?
+            .into_iter()
+            .map(|(name, target)| {
+                let default = default_target.as_ref() == Some(&name)
+                    || matches!(
+                        (&default_target, &target.variant),
+                        (Some(default), Some(variant))
+                            if starlark_dialect_build_targets::variant_target_name(default, variant) == name
+                    );
+
+                Ok(TargetInfo {
+                    build_path: context.target_build_path(&name)?,
+                    name,
+                    depends: target.depends,
+                    variant: target.variant,
+                    default,
+                })
+            })
+            .collect()
+    }
+
+    fn evaluate(&self, params: ConfigParams) -> Result<JsonValue> {
+        let (config_path, context) = self.evaluate_config(&params)?;
+
+        Ok(serde_json::json!({
+            "configPath": config_path,
+            "defaultTarget": context.default_target()?,
+            "targets": self.target_infos(&context)?,
+        }))
+    }
+
+    fn targets(&self, params: ConfigParams) -> Result<JsonValue> {
+        let (_, context) = self.evaluate_config(&params)?;
+
+        Ok(serde_json::to_value(self.target_infos(&context)?)?)
+    }
+
+    fn completions(&self, params: CompletionsParams) -> Result<JsonValue> {
+        let (_, mut context) = self.evaluate_config(&params.config)?;
+
+        let value = context.eval(&params.expression)?;
+
+        Ok(serde_json::json!({
+            "type": value.get_type(),
+            "attributes": context.value_attribute_names(&value),
+        }))
+    }
+
+    fn resolve_target(&self, params: &TargetParams) -> Result<(String, EvaluationContext)> {
+        let (config_path, builder) = self.builder(&params.config)?;
+
+        let mut context = builder
+            .resolve_target_optional(params.target.clone())
+            .into_context()?;
+        context.evaluate_file(&config_path)?;
+
+        let target = match &params.target {
+            Some(target) => target.clone(),
+            None => context
+                .default_target()?
+                .ok_or_else(|| anyhow!("config file does not define any targets"))?,
+        };
+
+        Ok((target, context))
+    }
+
+    fn resources(&self, params: TargetParams) -> Result<JsonValue> {
+        let (target, context) = self.resolve_target(&params)?;
+
+        let value = context
+            .resolved_target_value(&target)?
+            .ok_or_else(|| anyhow!("target {} was not resolved", target))?;
+
+        let exe = value
+            .downcast_ref::<PythonExecutableValue>()
+            .ok_or_else(|| {
+                anyhow!(
+                    "target {} resolved to {}; expected PythonExecutable",
+                    target,
+                    value.get_type()
+                )
+            })?;
+
+        let inner = exe
+            .inner("target/resources")
+            .map_err(|e| anyhow!("{:?}", e))?;
+
+        let resources = inner
+            .iter_resources()
+            .map(|(_, r)| ResourceInfo::from(r))
+            .collect::<Vec<_>>();
+
+        Ok(serde_json::json!({
+            "target": target,
+            "resources": resources,
+        }))
+    }
+
+    fn build(&self, params: BuildParams) -> Result<JsonValue> {
+        let (_, builder) = self.builder(&params.config)?;
+
+        let built = builder
+            .resolve_targets_optional(params.targets)
+            .jobs(params.jobs.unwrap_or(1))
+            .build_targets()?;
+
+        Ok(JsonValue::Array(
+            built
+                .into_iter()
+                .map(|(target, resolved)| {
+                    let run_path = match &resolved.run_mode {
+                        RunMode::None => None,
+                        RunMode::Path { path } => Some(path.clone()),
+                    };
+
+                    serde_json::json!({
+                        "target": target,
+                        "outputPath": resolved.output_path,
+                        "runPath": run_path,
+                    })
+                })
+                .collect(),
+        ))
+    }
+
+    /// Serve requests from a reader until input ends or `exit` is received.
+    pub fn serve(&mut self, reader: &mut impl BufRead, writer: &mut impl Write) -> Result<()> {
+        while let Some(data) = read_message(reader)? {
+            if let Some(response) = self.handle_message(&data) {
+                write_message(writer, &serde_json::to_vec(&response)?)?;
+            }
+
+            if self.exit_requested() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Serve requests over stdin and stdout.
+///
+/// On UNIX, anything else written to stdout (such as output from commands we
+/// run) is redirected to stderr so it can't corrupt the message stream.
+pub fn serve_stdio(env: &Environment, cwd: Option<&Path>) -> Result<()> {
+    if let Some(dir) = cwd {
+        std::env::set_current_dir(dir)
+            .with_context(|| format!("changing directory to {}", dir.display()))?;
+    }
+
+    let mut server = Server::new(env)?;
+
+    let stdin = std::io::stdin();
+    let mut reader = stdin.lock();
+
+    #[cfg(unix)]
+    let mut writer = {
+        use std::os::unix::io::FromRawFd;
+
+        std::io::stdout().flush()?;
+
+        // Keep a handle on the original stdout for messages and point file
+        // descriptor 1 at stderr.
+        let fd = unsafe { libc::dup(libc::STDOUT_FILENO) };
+        if fd < 0 || unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+            return Err(std::io::Error::last_os_error()).context("redirecting stdout");
+        }
+
+        std::io::BufWriter::new(unsafe { std::fs::File::from_raw_fd(fd) })
+    };
+
+    #[cfg(not(unix))]
+    let mut writer = std::io::stdout();
+
+    server.serve(&mut reader, &mut writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::testutil::*};
+
+    fn request(server: &mut Server, id: i64, method: &str, params: JsonValue) -> Response {
+        server
+            .handle_request(Request {
+                jsonrpc: "2.0".to_string(),
+                id: Some(id.into()),
+                method: method.to_string(),
+                params,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_framing() -> Result<()> {
+        let mut buffer = vec![];
+        write_message(&mut buffer, b"{\"a\": 1}")?;
+        write_message(&mut buffer, b"[]")?;
+
+        assert_eq!(
+            buffer,
+            b"Content-Length: 8\r\n\r\n{\"a\": 1}Content-Length: 2\r\n\r\n[]".to_vec()
+        );
+
+        let mut reader = std::io::Cursor::new(buffer);
+        assert_eq!(read_message(&mut reader)?, Some(b"{\"a\": 1}".to_vec()));
+        assert_eq!(read_message(&mut reader)?, Some(b"[]".to_vec()));
+        assert_eq!(read_message(&mut reader)?, None);
+
+        let mut reader = std::io::Cursor::new(b"Content-Length: 10\r\n".to_vec());
+        assert!(read_message(&mut reader).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_protocol_errors() -> Result<()> {
+        let env = get_env()?;
+        let mut server = Server::new(&env)?;
+
+        let response = server.handle_message(b"{").unwrap();
+        assert_eq!(response.error.unwrap().code, PARSE_ERROR);
+
+        let response = server
+            .handle_message(br#"{"jsonrpc": "1.0", "id": 1, "method": "initialize"}"#)
+            .unwrap();
+        assert_eq!(response.id, JsonValue::from(1));
+        assert_eq!(response.error.unwrap().code, INVALID_REQUEST);
+
+        let response = request(&mut server, 2, "missing", JsonValue::Null);
+        assert_eq!(response.error.unwrap().code, METHOD_NOT_FOUND);
+
+        let response = request(
+            &mut server,
+            3,
+            "config/evaluate",
+            serde_json::json!({"release": "yes"}),
+        );
+        assert_eq!(response.error.unwrap().code, INVALID_PARAMS);
+
+        // Notifications don't get responses.
+        assert!(server
+            .handle_message(br#"{"jsonrpc": "2.0", "method": "initialize"}"#)
+            .is_none());
+
+        let response = request(&mut server, 4, "initialize", JsonValue::Null);
+        assert_eq!(response.result.unwrap()["name"], "pyoxidizer");
+
+        request(&mut server, 5, "shutdown", JsonValue::Null);
+        let response = request(&mut server, 6, "initialize", JsonValue::Null);
+        assert_eq!(response.error.unwrap().code, INVALID_REQUEST);
+
+        assert!(!server.exit_requested());
+        assert!(server
+            .handle_message(br#"{"jsonrpc": "2.0", "method": "exit"}"#)
+            .is_none());
+        assert!(server.exit_requested());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_evaluate() -> Result<()> {
+        let env = get_env()?;
+        let temp_dir = env.temporary_directory("pyoxidizer-test")?;
+
+        let config_path = temp_dir.path().join("pyoxidizer.bzl");
+        std::fs::write(
+            &config_path,
+            "def make_a():\n    return 'a'\n\
+             def make_b(a):\n    return a\n\
+             register_target('a', make_a)\n\
+             register_target('b', make_b, depends=['a'], default=True)\n\
+             resolve_targets()\n",
+        )?;
+
+        let mut server = Server::new(&env)?;
+
+        let params = serde_json::json!({"path": temp_dir.path()});
+
+        let result = request(&mut server, 1, "config/evaluate", params.clone())
+            .result
+            .unwrap();
+        assert_eq!(result["defaultTarget"], "b");
+        assert_eq!(result["targets"][0]["name"], "a");
+        assert_eq!(result["targets"][1]["depends"], serde_json::json!(["a"]));
+        assert_eq!(result["targets"][1]["default"], true);
+
+        let result = request(&mut server, 2, "config/targets", params)
+            .result
+            .unwrap();
+        assert_eq!(result.as_array().unwrap().len(), 2);
+
+        let result = request(
+            &mut server,
+            3,
+            "config/completions",
+            serde_json::json!({"path": &config_path, "expression": "'value'"}),
+        )
+        .result
+        .unwrap();
+        assert_eq!(result["type"], "string");
+        assert!(result["attributes"]
+            .as_array()
+            .unwrap()
+            .contains(&JsonValue::from("startswith")));
+
+        std::fs::write(&config_path, "register_target(\n")?;
+        let response = request(
+            &mut server,
+            4,
+            "config/evaluate",
+            serde_json::json!({"path": &config_path}),
+        );
+        assert_eq!(response.error.unwrap().code, REQUEST_FAILED);
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+}
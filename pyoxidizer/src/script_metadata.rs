@@ -0,0 +1,286 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Inline script metadata as defined by PEP 723.
+
+Single file Python scripts can declare their dependencies in a comment block:
+
+```python
+# /// script
+# requires-python = ">=3.9"
+# dependencies = [
+#   "requests<3",
+# ]
+# ///
+```
+
+`pyoxidizer build-script` reads this block to package a script into an
+executable without a configuration file.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    serde::Deserialize,
+    version_compare::Cmp,
+};
+
+/// The `script` metadata block of a Python script.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ScriptMetadata {
+    /// Requirement specifiers of packages the script depends on.
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+
+    /// Version specifier of the Python versions the script supports.
+    pub requires_python: Option<String>,
+}
+
+impl ScriptMetadata {
+    /// Parse the `script` metadata block from Python source code.
+    ///
+    /// Returns `None` if the source doesn't contain a `script` block.
+    pub fn from_source(source: &str) -> Result<Option<Self>> {
+        let mut content: Option<Vec<&str>> = None;
+
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+
+            if let Some(lines) = content.as_mut() {
+                if line == "# ///" {
+                    let metadata =
+                        toml::from_str(&lines.join("\n")).context("parsing script metadata")?;
+                    Self::ensure_single_block(source, i + 1)?;
+
+                    return Ok(Some(metadata));
+                } else if line == "#" {
+                    lines.push("");
+                } else if let Some(stripped) = line.strip_prefix("# ") {
+                    lines.push(stripped);
+                } else {
+                    return Err(anyhow!(
+                        "script metadata block not closed before line {}",
+                        i + 1
+                    ));
+                }
+            } else if line == "# /// script" {
+                content = Some(vec![]);
+            }
+        }
+
+        if content.is_some() {
+            Err(anyhow!("script metadata block is not closed"))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Error if another `script` block follows the line at `offset`.
+    fn ensure_single_block(source: &str, offset: usize) -> Result<()> {
+        if source
+            .lines()
+            .skip(offset)
+            .any(|line| line.trim_end_matches('\r') == "# /// script")
+        {
+            Err(anyhow!("multiple script metadata blocks"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Whether a Python `X.Y` version satisfies `requires-python`.
+    ///
+    /// Versions in the specifier are compared at `X.Y` granularity, since
+    /// that is how Python distributions are chosen.
+    pub fn supports_python(&self, version: &str) -> Result<bool> {
+        let requires = match &self.requires_python {
+            Some(requires) => requires,
+            None => return Ok(true),
+        };
+
+        for clause in requires.split(',').map(|s| s.trim()) {
+            if clause.is_empty() {
+                continue;
+            }
+
+            let (op, wanted) = ["~=", "==", "!=", ">=", "<=", ">", "<"]
+                .iter()
+                .find_map(|op| clause.strip_prefix(op).map(|v| (*op, v.trim())))
+                .ok_or_else(|| anyhow!("unsupported Python version specifier: {}", clause))?;
+
+            let wanted = wanted.trim_end_matches(".*");
+            let wanted = wanted.split('.').take(2).collect::<Vec<_>>().join(".");
+
+            let cmp = version_compare::compare(version, &wanted)
+                .map_err(|_| anyhow!("invalid Python version specifier: {}", clause))?;
+
+            let satisfied = match op {
+                "~=" => cmp != Cmp::Lt && version.split('.').next() == wanted.split('.').next(),
+                "==" => cmp == Cmp::Eq,
+                "!=" => cmp != Cmp::Eq,
+                ">=" => cmp != Cmp::Lt,
+                "<=" => cmp != Cmp::Gt,
+                ">" => cmp == Cmp::Gt,
+                "<" => cmp == Cmp::Lt,
+                _ => unreachable!(),
+            };
+
+            if !satisfied {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Choose the Python `X.Y` version to build the script with.
+    ///
+    /// `default` is preferred if it satisfies `requires-python`. Otherwise the
+    /// newest of `available` satisfying it is chosen.
+    pub fn resolve_python_version<'a>(
+        &self,
+        default: &'a str,
+        available: impl Iterator<Item = &'a str>,
+    ) -> Result<&'a str> {
+        if self.supports_python(default)? {
+            return Ok(default);
+        }
+
+        let mut best: Option<&str> = None;
+        for version in available {
+            if !self.supports_python(version)? {
+                continue;
+            }
+
+            if let Some(best) = best {
+                if !version_compare::compare_to(version, best, Cmp::Gt).unwrap_or(false) {
+                    continue;
+                }
+            }
+
+            best = Some(version);
+        }
+
+        best.ok_or_else(|| {
+            anyhow!(
+                "no available Python distribution satisfies requires-python {}",
+                self.requires_python.as_deref().unwrap_or_default()
+            )
+        })
+    }
+}
+
+/// Derive a Python module name from the file name of a script.
+pub fn script_module_name(stem: &str) -> String {
+    let mut name = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_source() -> Result<()> {
+        assert_eq!(ScriptMetadata::from_source("print('hello')\n")?, None);
+
+        let source = "#!/usr/bin/env python3\n\
+            # /// script\n\
+            # requires-python = \">=3.9\"\n\
+            # dependencies = [\n\
+            #   \"requests<3\",\n\
+            #\n\
+            #   \"rich\",\n\
+            # ]\n\
+            # ///\n\
+            \n\
+            import requests\n";
+
+        assert_eq!(
+            ScriptMetadata::from_source(source)?,
+            Some(ScriptMetadata {
+                dependencies: vec!["requests<3".to_string(), "rich".to_string()],
+                requires_python: Some(">=3.9".to_string()),
+            })
+        );
+
+        assert_eq!(
+            ScriptMetadata::from_source("# /// script\n# ///\n")?,
+            Some(ScriptMetadata::default())
+        );
+
+        // Other block types are ignored.
+        assert_eq!(
+            ScriptMetadata::from_source("# /// pyproject\n# foo = 1\n# ///\n")?,
+            None
+        );
+
+        assert!(ScriptMetadata::from_source("# /// script\n# dependencies = []\n").is_err());
+        assert!(ScriptMetadata::from_source("# /// script\nimport os\n# ///\n").is_err());
+        assert!(ScriptMetadata::from_source("# /// script\n# ///\n# /// script\n# ///\n").is_err());
+        assert!(ScriptMetadata::from_source("# /// script\n# dependencies = 1\n# ///\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_supports_python() -> Result<()> {
+        let metadata = |requires: &str| ScriptMetadata {
+            requires_python: Some(requires.to_string()),
+            ..Default::default()
+        };
+
+        assert!(ScriptMetadata::default().supports_python("3.8")?);
+        assert!(metadata(">=3.9").supports_python("3.10")?);
+        assert!(!metadata(">=3.9").supports_python("3.8")?);
+        assert!(metadata(">=3.9.1").supports_python("3.9")?);
+        assert!(metadata(">=3.8, <3.10").supports_python("3.9")?);
+        assert!(!metadata(">=3.8, <3.10").supports_python("3.10")?);
+        assert!(metadata("==3.9.*").supports_python("3.9")?);
+        assert!(!metadata("!=3.9").supports_python("3.9")?);
+        assert!(metadata("~=3.8").supports_python("3.10")?);
+        assert!(metadata("").supports_python("3.10")?);
+        assert!(metadata("3.9").supports_python("3.9").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_python_version() -> Result<()> {
+        let available = ["3.8", "3.9", "3.10"];
+        let metadata = |requires: &str| ScriptMetadata {
+            requires_python: Some(requires.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            ScriptMetadata::default().resolve_python_version("3.10", available.into_iter())?,
+            "3.10"
+        );
+        assert_eq!(
+            metadata("<3.10").resolve_python_version("3.10", available.into_iter())?,
+            "3.9"
+        );
+        assert!(metadata(">=3.11")
+            .resolve_python_version("3.10", available.into_iter())
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_script_module_name() {
+        assert_eq!(script_module_name("tool"), "tool");
+        assert_eq!(script_module_name("my-tool"), "my_tool");
+        assert_eq!(script_module_name("2to3"), "_2to3");
+    }
+}
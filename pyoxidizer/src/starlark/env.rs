@@ -3,6 +3,7 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
+    super::extras::PythonExtra,
     crate::py_packaging::distribution::DistributionCache,
     anyhow::{Context, Result},
     starlark::{
@@ -15,7 +16,7 @@ use {
     },
     starlark_dialect_build_targets::{get_context_value, EnvironmentContext},
     std::{
-        collections::HashMap,
+        collections::{BTreeMap, BTreeSet, HashMap},
         path::{Path, PathBuf},
         sync::Arc,
     },
@@ -59,6 +60,12 @@ pub struct PyOxidizerEnvironmentContext {
 
     /// Extra variables to inject into Starlark environment.
     extra_vars: HashMap<String, Option<String>>,
+
+    /// Names of extras enabled for this evaluation.
+    pub enabled_extras: BTreeSet<String>,
+
+    /// Extras registered by the configuration file.
+    pub extras: BTreeMap<String, PythonExtra>,
 }
 
 impl PyOxidizerEnvironmentContext {
@@ -73,6 +80,7 @@ impl PyOxidizerEnvironmentContext {
         build_opt_level: &str,
         distribution_cache: Option<Arc<DistributionCache>>,
         extra_vars: HashMap<String, Option<String>>,
+        enabled_extras: Vec<String>,
     ) -> Result<PyOxidizerEnvironmentContext> {
         let parent = config_path
             .parent()
@@ -101,6 +109,8 @@ impl PyOxidizerEnvironmentContext {
             build_opt_level: build_opt_level.to_string(),
             distribution_cache,
             extra_vars,
+            enabled_extras: enabled_extras.into_iter().collect(),
+            extras: BTreeMap::new(),
         })
     }
 
@@ -182,6 +192,9 @@ pub fn register_starlark_dialect(
     super::python_embedded_resources::python_embedded_resources_module(env, type_values);
     super::python_executable::python_executable_env(env, type_values);
     super::python_packaging_policy::python_packaging_policy_module(env, type_values);
+    super::extras::extras_module(env, type_values);
+    super::record::record_module(env, type_values);
+    super::testing::testing_module(env, type_values);
 
     Ok(())
 }
@@ -228,6 +241,16 @@ pub fn populate_environment(
     }
 
     env.set("VARS", Value::try_from(vars.get_content().clone()).unwrap())?;
+    env.set(
+        "EXTRAS",
+        Value::from(
+            context
+                .enabled_extras
+                .iter()
+                .map(|x| Value::from(x.as_str()))
+                .collect::<Vec<_>>(),
+        ),
+    )?;
     env.set("CWD", Value::from(context.cwd.display().to_string()))?;
     env.set(
         "CONFIG_PATH",
@@ -4,7 +4,10 @@
 
 use {
     crate::{
+        build_inputs,
         environment::default_target_triple,
+        log_capture,
+        metrics::MetricsRecorder,
         py_packaging::distribution::DistributionCache,
         starlark::env::{
             populate_environment, register_starlark_dialect, PyOxidizerContext,
@@ -12,7 +15,7 @@ use {
         },
     },
     anyhow::{anyhow, Result},
-    codemap::CodeMap,
+    codemap::{CodeMap, File},
     codemap_diagnostic::{Diagnostic, Emitter},
     log::error,
     starlark::{
@@ -25,16 +28,20 @@ use {
         },
     },
     starlark_dialect_build_targets::{
-        build_target, run_target, EnvironmentContext, ResolvedTarget,
+        build_target, run_target, starlark_resolve_target, EnvironmentContext, ResolvedTarget,
+        Target,
     },
     std::{
-        collections::HashMap,
+        collections::{BTreeMap, HashMap, VecDeque},
+        io::Write,
         path::{Path, PathBuf},
         sync::{Arc, Mutex},
+        time::Instant,
     },
 };
 
 /// Builder type to construct `EvaluationContext` instances.
+#[derive(Clone)]
 pub struct EvaluationContextBuilder {
     env: crate::environment::Environment,
     config_path: PathBuf,
@@ -46,6 +53,9 @@ pub struct EvaluationContextBuilder {
     build_opt_level: String,
     distribution_cache: Option<Arc<DistributionCache>>,
     extra_vars: HashMap<String, Option<String>>,
+    enabled_extras: Vec<String>,
+    jobs: usize,
+    metrics: Option<Arc<MetricsRecorder>>,
 }
 
 impl EvaluationContextBuilder {
@@ -65,6 +75,9 @@ impl EvaluationContextBuilder {
             build_opt_level: "0".to_string(),
             distribution_cache: None,
             extra_vars: HashMap::new(),
+            enabled_extras: vec![],
+            jobs: 1,
+            metrics: None,
         }
     }
 
@@ -139,6 +152,223 @@ impl EvaluationContextBuilder {
         self.extra_vars = extra_vars;
         self
     }
+
+    /// Set the names of extras to enable.
+    #[must_use]
+    pub fn enabled_extras(mut self, value: Vec<String>) -> Self {
+        self.enabled_extras = value;
+        self
+    }
+
+    /// Set the maximum number of target groups to build concurrently.
+    ///
+    /// Values less than 1 are treated as 1.
+    #[must_use]
+    pub fn jobs(mut self, value: usize) -> Self {
+        self.jobs = value.max(1);
+        self
+    }
+
+    /// Set the recorder collecting metrics of [Self::build_targets()].
+    #[must_use]
+    pub fn metrics(mut self, recorder: Arc<MetricsRecorder>) -> Self {
+        self.metrics = Some(recorder);
+        self
+    }
+
+    /// Evaluate the config file, recording how long it took.
+    fn evaluate_config(&self, context: &mut EvaluationContext) -> Result<()> {
+        let start = Instant::now();
+        context.evaluate_file(&self.config_path)?;
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_phase("evaluate", start.elapsed());
+        }
+
+        Ok(())
+    }
+
+    /// Build a target, recording how long it took and what it produced.
+    fn build_target(
+        &self,
+        context: &mut EvaluationContext,
+        target: &str,
+    ) -> Result<ResolvedTarget> {
+        let start = Instant::now();
+        let built = context.build_resolved_target(target)?;
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_target(start.elapsed(), &built.output_path);
+        }
+
+        Ok(built)
+    }
+
+    /// Evaluate the config file and build all targets that should be resolved.
+    ///
+    /// When more than 1 job is allowed, the targets to resolve, including
+    /// default targets, are partitioned into groups not sharing any
+    /// dependencies and groups are built concurrently. Starlark values cannot
+    /// be shared across threads, so every worker thread evaluates the config
+    /// file once in its own `EvaluationContext` and resolves targets as it
+    /// builds them. The context used to discover the groups is reused by the
+    /// calling thread.
+    ///
+    /// Log output of concurrently built targets is captured (see
+    /// [crate::log_capture]) and emitted one target at a time, in the order
+    /// targets were requested. Built targets are returned in that order as
+    /// well. If multiple targets fail, the error from the target requested
+    /// first is returned.
+    pub fn build_targets(self) -> Result<Vec<(String, ResolvedTarget)>> {
+        if self.jobs < 2 {
+            let mut context = self.clone().into_context()?;
+            self.evaluate_config(&mut context)?;
+
+            return context
+                .targets_to_resolve()?
+                .into_iter()
+                .map(|target| {
+                    let built = self.build_target(&mut context, &target)?;
+                    Ok((target, built))
+                })
+                .collect();
+        }
+
+        // Evaluate the config without resolving anything, so targets can be
+        // resolved individually once they are scheduled.
+        let mut context = self.clone().resolve_targets(vec![]).into_context()?;
+        self.evaluate_config(&mut context)?;
+
+        let targets = context.requested_targets_to_resolve(self.resolve_targets.as_deref())?;
+        let groups = context.independent_target_groups(&targets)?;
+
+        // Share distributions across threads so each is only fetched once.
+        let builder = if self.distribution_cache.is_some() {
+            self
+        } else {
+            let cache = Arc::new(DistributionCache::new(Some(
+                &self.env.python_distributions_dir(),
+            )));
+            self.distribution_cache(cache)
+        };
+
+        let jobs = builder.jobs.min(groups.len());
+        let queue = Mutex::new(groups.into_iter().collect::<VecDeque<_>>());
+        let output = Mutex::new(TargetOutput::new(&targets));
+
+        let work = |context: &mut EvaluationContext| loop {
+            let group = match queue.lock().unwrap().pop_front() {
+                Some(group) => group,
+                None => break,
+            };
+
+            for target in group {
+                let (res, log) = log_capture::capture(|| {
+                    context.resolve_target(&target)?;
+                    builder.build_target(context, &target)
+                });
+
+                output.lock().unwrap().record(&target, res, log);
+            }
+        };
+
+        std::thread::scope(|scope| {
+            for _ in 1..jobs {
+                scope.spawn(|| {
+                    let (res, log) = log_capture::capture(|| {
+                        let mut context = builder.clone().resolve_targets(vec![]).into_context()?;
+                        builder.evaluate_config(&mut context)?;
+
+                        Ok(context)
+                    });
+
+                    // Other threads build the remaining groups if evaluation fails.
+                    match res {
+                        Ok(mut context) => work(&mut context),
+                        Err(e) => output.lock().unwrap().write_worker_error(&e, &log),
+                    }
+                });
+            }
+
+            work(&mut context);
+        });
+
+        output.into_inner().unwrap().finish()
+    }
+
+    /// Asynchronous version of [Self::build_targets()].
+    ///
+    /// Evaluation and building block, so they run on the blocking thread pool
+    /// of the current tokio runtime. Targets are built concurrently according
+    /// to [Self::jobs()] as with the synchronous version.
+    ///
+    /// Panics if not called from within a tokio runtime.
+    pub async fn build_targets_async(self) -> Result<Vec<(String, ResolvedTarget)>> {
+        tokio::task::spawn_blocking(move || self.build_targets()).await?
+    }
+}
+
+/// Results and captured log output of concurrently built targets.
+///
+/// Log output is written to stderr as soon as the output of all previously
+/// requested targets has been written.
+struct TargetOutput {
+    targets: Vec<String>,
+    results: Vec<Option<Result<ResolvedTarget>>>,
+    logs: Vec<Option<Vec<u8>>>,
+    next_log: usize,
+}
+
+impl TargetOutput {
+    fn new(targets: &[String]) -> Self {
+        Self {
+            targets: targets.to_vec(),
+            results: targets.iter().map(|_| None).collect(),
+            logs: targets.iter().map(|_| None).collect(),
+            next_log: 0,
+        }
+    }
+
+    /// Record the result and log output of building a target.
+    fn record(&mut self, target: &str, res: Result<ResolvedTarget>, log: Vec<u8>) {
+        if let Some(index) = self.targets.iter().position(|t| t == target) {
+            self.results[index] = Some(res);
+            self.logs[index] = Some(log);
+        }
+
+        let mut stderr = std::io::stderr().lock();
+        while let Some(Some(log)) = self.logs.get_mut(self.next_log) {
+            // Failing to write logs shouldn't fail the build.
+            let _ = stderr.write_all(log);
+            log.clear();
+            self.next_log += 1;
+        }
+    }
+
+    /// Write the log output of a worker thread failing to evaluate the config file.
+    fn write_worker_error(&mut self, error: &anyhow::Error, log: &[u8]) {
+        let mut stderr = std::io::stderr().lock();
+        let _ = stderr.write_all(log);
+        let _ = writeln!(
+            stderr,
+            "error evaluating config in build thread: {:?}",
+            error
+        );
+    }
+
+    /// Obtain built targets in request order or the first error.
+    fn finish(self) -> Result<Vec<(String, ResolvedTarget)>> {
+        let mut built = vec![];
+
+        for (target, res) in self.targets.into_iter().zip(self.results) {
+            match res {
+                Some(res) => built.push((target, res?)),
+                None => return Err(anyhow!("target {} was not built", target)),
+            }
+        }
+
+        Ok(built)
+    }
 }
 
 /// Interface to evaluate Starlark configuration files.
@@ -162,6 +392,18 @@ impl TryFrom<EvaluationContextBuilder> for EvaluationContext {
     }
 }
 
+/// Record files added to a code map after the `start` file as build inputs.
+fn record_evaluated_files(map: &mut CodeMap, start: &File) {
+    let end = map.add_file(String::new(), String::new());
+
+    let mut pos = start.span.high() + 1;
+    while pos < end.span.low() {
+        let file = map.find_file(pos);
+        build_inputs::record_path(file.name());
+        pos = file.span.high() + 1;
+    }
+}
+
 impl EvaluationContext {
     pub fn from_builder(builder: EvaluationContextBuilder) -> Result<Self> {
         let context = PyOxidizerEnvironmentContext::new(
@@ -174,9 +416,11 @@ impl EvaluationContext {
             &builder.build_opt_level,
             builder.distribution_cache,
             builder.extra_vars,
+            builder.enabled_extras,
         )?;
 
-        let (mut parent_env, mut type_values) = starlark::stdlib::global_environment();
+        let (mut parent_env, mut type_values) =
+            starlark::stdlib::global_environment_with_extensions();
 
         register_starlark_dialect(&mut parent_env, &mut type_values)
             .map_err(|e| anyhow!("error creating Starlark environment: {:?}", e))?;
@@ -216,15 +460,21 @@ impl EvaluationContext {
         let map = Arc::new(Mutex::new(CodeMap::new()));
         let file_loader_env = self.parent_env.clone();
 
-        starlark::eval::simple::eval_file(
+        // Marks the start of files added by evaluation, so they can be recorded.
+        let start = map.lock().unwrap().add_file(String::new(), String::new());
+
+        let res = starlark::eval::simple::eval_file(
             &map,
             &config_path.display().to_string(),
             Dialect::Bzl,
             &mut self.child_env,
             &self.type_values,
             file_loader_env,
-        )
-        .map_err(|e| {
+        );
+
+        record_evaluated_files(&mut map.lock().unwrap(), &start);
+
+        res.map_err(|e| {
             let mut msg = Vec::new();
             let raw_map = map.lock().unwrap();
             {
@@ -286,6 +536,48 @@ impl EvaluationContext {
             })
     }
 
+    /// Evaluate Starlark files followed by code.
+    ///
+    /// All evaluations share a code map, so errors raised in functions defined
+    /// by any of the files can be rendered.
+    pub fn evaluate_files_and_eval(&mut self, paths: &[&Path], code: &str) -> Result<Value> {
+        let map = Arc::new(Mutex::new(CodeMap::new()));
+
+        let render = |diagnostic: Diagnostic| {
+            let unlocked_map = map.lock().unwrap();
+
+            let mut buffer = vec![];
+            Emitter::vec(&mut buffer, Some(&unlocked_map)).emit(&[diagnostic]);
+
+            anyhow!("{}", String::from_utf8_lossy(&buffer))
+        };
+
+        for path in paths {
+            starlark::eval::simple::eval_file(
+                &map,
+                &path.display().to_string(),
+                Dialect::Bzl,
+                &mut self.child_env,
+                &self.type_values,
+                self.parent_env.clone(),
+            )
+            .map_err(render)?;
+        }
+
+        let file_loader_env = self.child_env.clone();
+
+        starlark::eval::simple::eval(
+            &map,
+            "<no_file>",
+            code,
+            Dialect::Bzl,
+            &mut self.child_env,
+            &self.type_values,
+            file_loader_env,
+        )
+        .map_err(render)
+    }
+
     /// Evaluate code with a placeholder value for the filename.
     pub fn eval(&mut self, code: &str) -> Result<Value> {
         self.eval_code_with_path("<no_file>", code)
@@ -348,6 +640,46 @@ impl EvaluationContext {
             .collect::<Vec<_>>())
     }
 
+    /// Obtain all registered targets.
+    pub fn targets(&self) -> Result<BTreeMap<String, Target>> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        Ok(context.targets().clone())
+    }
+
+    /// Obtain the value a target resolved to, if it has been resolved.
+    pub fn resolved_target_value(&self, target: &str) -> Result<Option<Value>> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        Ok(context
+            .get_target(target)
+            .and_then(|t| t.resolved_value.clone()))
+    }
+
+    /// Obtain the names of attributes and methods available on a value.
+    ///
+    /// Attributes are only known for types supporting `dir()`.
+    pub fn value_attribute_names(&self, value: &Value) -> Vec<String> {
+        let mut names = value
+            .dir_attr()
+            .unwrap_or_default()
+            .into_iter()
+            .chain(self.type_values.list_type_value(value))
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+
+        names.sort();
+        names.dedup();
+
+        names
+    }
+
     /// Obtain targets that should be resolved.
     pub fn targets_to_resolve(&self) -> Result<Vec<String>> {
         let raw_context = self.build_targets_context_value()?;
@@ -358,6 +690,38 @@ impl EvaluationContext {
         Ok(context.targets_to_resolve())
     }
 
+    /// Obtain targets that should be resolved for explicitly requested targets.
+    ///
+    /// `None` resolves the default targets.
+    pub fn requested_targets_to_resolve(&self, targets: Option<&[String]>) -> Result<Vec<String>> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        Ok(context.requested_targets_to_resolve(targets))
+    }
+
+    /// Partition targets into groups that can be built independently.
+    pub fn independent_target_groups(&self, targets: &[String]) -> Result<Vec<Vec<String>>> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        Ok(context.independent_target_groups(targets))
+    }
+
+    /// Resolve a target and its dependencies if they aren't resolved yet.
+    pub fn resolve_target(&mut self, target: &str) -> Result<()> {
+        let mut call_stack = CallStack::default();
+
+        starlark_resolve_target(&self.type_values, &mut call_stack, target.to_string())
+            .map_err(|e| anyhow!("error resolving target {}: {:?}", target, e))?;
+
+        Ok(())
+    }
+
     pub fn build_resolved_target(&mut self, target: &str) -> Result<ResolvedTarget> {
         let mut call_stack = CallStack::default();
 
@@ -383,7 +747,10 @@ impl EvaluationContext {
 
 #[cfg(test)]
 mod tests {
-    use {super::*, crate::testutil::*, starlark::values::dict::Dictionary};
+    use {
+        super::*, crate::starlark::testutil::starlark_ok, crate::testutil::*,
+        starlark::values::dict::Dictionary,
+    };
 
     #[test]
     fn test_load() -> Result<()> {
@@ -420,6 +787,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_evaluated_files_recorded() -> Result<()> {
+        let env = get_env()?;
+        let temp_dir = env.temporary_directory("pyoxidizer-test")?;
+
+        let load_path = temp_dir.path().join("load.bzl");
+        std::fs::write(&load_path, "value = 42\n")?;
+
+        let main_path = temp_dir.path().join("main.bzl");
+        std::fs::write(
+            &main_path,
+            format!(
+                "load('{}', 'value')\n",
+                load_path.display().to_string().escape_default()
+            ),
+        )?;
+
+        let mut context = EvaluationContextBuilder::new(
+            &env,
+            main_path.clone(),
+            default_target_triple().to_string(),
+        )
+        .into_context()?;
+        context.evaluate_file(&main_path)?;
+
+        let files = build_inputs::BuildInputs::recorded()?.files;
+        assert!(files.contains_key(&main_path.display().to_string()));
+        assert!(files.contains_key(&load_path.display().to_string()));
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_register_target() -> Result<()> {
         let env = get_env()?;
@@ -442,6 +843,70 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_build_default_targets_concurrently() -> Result<()> {
+        let env = get_env()?;
+        let temp_dir = env.temporary_directory("pyoxidizer-test")?;
+
+        let config_path = temp_dir.path().join("pyoxidizer.bzl");
+        std::fs::write(
+            &config_path,
+            "def make_files(variant):\n    return FileManifest()\n\
+             register_target('files', make_files, variants=['a', 'b', 'c'], default=True)\n\
+             resolve_targets()\n",
+        )?;
+
+        let built =
+            EvaluationContextBuilder::new(&env, config_path, default_target_triple().to_string())
+                .jobs(2)
+                .build_targets()?;
+
+        assert_eq!(
+            built
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["files-a", "files-b", "files-c"]
+        );
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_targets_async() -> Result<()> {
+        let env = get_env()?;
+        let temp_dir = env.temporary_directory("pyoxidizer-test")?;
+
+        let config_path = temp_dir.path().join("pyoxidizer.bzl");
+        std::fs::write(
+            &config_path,
+            "def make_files(variant):\n    return FileManifest()\n\
+             register_target('files', make_files, variants=['a', 'b'], default=True)\n\
+             resolve_targets()\n",
+        )?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+        let built = runtime.block_on(
+            EvaluationContextBuilder::new(&env, config_path, default_target_triple().to_string())
+                .jobs(2)
+                .build_targets_async(),
+        )?;
+
+        assert_eq!(
+            built
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["files-a", "files-b"]
+        );
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+
     #[test]
     fn extra_vars() -> Result<()> {
         let env = get_env()?;
@@ -473,4 +938,14 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_language_extensions() {
+        let value = starlark_ok("s = struct(a=1, b=set([1, 2, 2]))\nlen(s.b) + s.a");
+        assert_eq!(value.to_int().unwrap(), 3);
+
+        // Configs defining their own struct or set still work.
+        let value = starlark_ok("def struct(x):\n    return x\n\nset = 2\nstruct(set)");
+        assert_eq!(value.to_int().unwrap(), 2);
+    }
 }
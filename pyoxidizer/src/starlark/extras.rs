@@ -0,0 +1,230 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Optional dependency groups toggled at build time. */
+
+use {
+    super::env::{get_context, PyOxidizerEnvironmentContext},
+    python_packaging::policy::{PythonPackagingPolicy, ResourceNamePattern},
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            {Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::optional_list_arg,
+};
+
+/// A named group of optional dependencies.
+#[derive(Clone, Debug)]
+pub struct PythonExtra {
+    /// Name of the extra.
+    pub name: String,
+
+    /// Requirements to `pip install` when the extra is enabled.
+    pub requirements: Vec<String>,
+
+    /// Patterns of resource names belonging to the extra.
+    ///
+    /// Matching resources are excluded when the extra isn't enabled.
+    pub resource_patterns: Vec<ResourceNamePattern>,
+}
+
+impl PyOxidizerEnvironmentContext {
+    /// Resolve the registered extras that are enabled.
+    ///
+    /// Errors if an enabled extra isn't registered.
+    pub fn resolve_enabled_extras(&self, label: &str) -> Result<Vec<&PythonExtra>, ValueError> {
+        self.enabled_extras
+            .iter()
+            .map(|name| {
+                self.extras.get(name).ok_or_else(|| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: format!(
+                            "extra {} is enabled but not registered; registered extras: {}",
+                            name,
+                            self.extras.keys().cloned().collect::<Vec<_>>().join(", ")
+                        ),
+                        label: label.to_string(),
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// Add the resource patterns of disabled extras to a policy's exclude patterns.
+    pub fn apply_extras_to_policy(
+        &self,
+        policy: &mut PythonPackagingPolicy,
+        label: &str,
+    ) -> Result<(), ValueError> {
+        self.resolve_enabled_extras(label)?;
+
+        let patterns = policy
+            .exclude_patterns()
+            .iter()
+            .cloned()
+            .chain(
+                self.extras
+                    .values()
+                    .filter(|extra| !self.enabled_extras.contains(&extra.name))
+                    .flat_map(|extra| extra.resource_patterns.iter().cloned()),
+            )
+            .collect::<Vec<_>>();
+
+        policy.set_exclude_patterns(patterns);
+
+        Ok(())
+    }
+}
+
+/// register_extra(name, requirements=None, resource_patterns=None)
+fn starlark_register_extra(
+    type_values: &TypeValues,
+    name: String,
+    requirements: &Value,
+    resource_patterns: &Value,
+) -> ValueResult {
+    const LABEL: &str = "register_extra()";
+
+    optional_list_arg("requirements", "string", requirements)?;
+    optional_list_arg("resource_patterns", "string", resource_patterns)?;
+
+    let requirements = match requirements.get_type() {
+        "list" => requirements.iter()?.iter().map(|x| x.to_string()).collect(),
+        _ => vec![],
+    };
+
+    let resource_patterns = match resource_patterns.get_type() {
+        "list" => resource_patterns
+            .iter()?
+            .iter()
+            .map(|x| {
+                ResourceNamePattern::try_from(x.to_string().as_str()).map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: e,
+                        label: LABEL.to_string(),
+                    })
+                })
+            })
+            .collect::<Result<Vec<_>, ValueError>>()?,
+        _ => vec![],
+    };
+
+    let pyoxidizer_context_value = get_context(type_values)?;
+    let mut pyoxidizer_context = pyoxidizer_context_value
+        .downcast_mut::<PyOxidizerEnvironmentContext>()?
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    if pyoxidizer_context.extras.contains_key(&name) {
+        return Err(ValueError::from(RuntimeError {
+            code: "PYOXIDIZER_BUILD",
+            message: format!("extra {} is already registered", name),
+            label: LABEL.to_string(),
+        }));
+    }
+
+    pyoxidizer_context.extras.insert(
+        name.clone(),
+        PythonExtra {
+            name,
+            requirements,
+            resource_patterns,
+        },
+    );
+
+    Ok(Value::new(NoneType::None))
+}
+
+starlark_module! { extras_module =>
+    register_extra(
+        env env,
+        name: String,
+        requirements=NoneType::None,
+        resource_patterns=NoneType::None
+    ) {
+        starlark_register_extra(env, name, &requirements, &resource_patterns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::super::testutil::*, super::*, anyhow::Result};
+
+    #[test]
+    fn test_register_extra() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("register_extra('postgres', requirements = ['psycopg2-binary'])")?;
+        assert!(env.eval("register_extra('postgres')").is_err());
+        assert!(env
+            .eval("register_extra('gui', resource_patterns = ['re:('])")
+            .is_err());
+        assert!(env
+            .eval("register_extra('gui', requirements = 'foo')")
+            .is_err());
+
+        eval_assert(&mut env, "EXTRAS == []")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_enabled_extras() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?
+            .enabled_extras(vec!["postgres".to_string()])
+            .into_context()?;
+
+        eval_assert(&mut env, "EXTRAS == ['postgres']")?;
+
+        env.eval("register_extra('postgres', resource_patterns = ['psycopg2*'])")?;
+        env.eval("register_extra('gui', resource_patterns = ['PySide6*', 'shiboken6*'])")?;
+
+        let context_value = env.get_var("CONTEXT").unwrap();
+        let context = context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .unwrap();
+
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_exclude_patterns([ResourceNamePattern::try_from("*.tests.*").unwrap()]);
+        context.apply_extras_to_policy(&mut policy, "test").unwrap();
+
+        assert_eq!(policy.exclude_patterns().len(), 3);
+        assert!(policy.filter_resource_name("psycopg2._psycopg"));
+        assert!(!policy.filter_resource_name("PySide6.QtCore"));
+        assert!(!policy.filter_resource_name("shiboken6"));
+        assert!(!policy.filter_resource_name("foo.tests.test_bar"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unregistered_extra() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?
+            .enabled_extras(vec!["postgres".to_string()])
+            .into_context()?;
+
+        env.eval("register_extra('gui')")?;
+
+        let context_value = env.get_var("CONTEXT").unwrap();
+        let context = context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .unwrap();
+
+        assert!(context.resolve_enabled_extras("test").is_err());
+        assert!(context
+            .apply_extras_to_policy(&mut PythonPackagingPolicy::default(), "test")
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -75,6 +75,36 @@ pub fn file_manifest_add_python_executable(
     Ok(())
 }
 
+/// Add a conventional Python installation derived from an executable to a manifest.
+pub fn file_manifest_add_python_runtime(
+    manifest: &mut FileManifestValue,
+    env: &crate::environment::Environment,
+    prefix: &str,
+    exe: &dyn PythonBinaryBuilder,
+) -> Result<()> {
+    const LABEL: &str = "FileManifest.add_python_runtime()";
+
+    let (exe_path, runtime_files) = exe
+        .to_runtime_file_manifest(env)
+        .context("resolving Python runtime files")?;
+
+    let use_prefix = if prefix == "." { "" } else { prefix };
+
+    let mut files = FileManifest::default();
+    for (path, entry) in runtime_files.iter_entries() {
+        files.add_file_entry(Path::new(use_prefix).join(path), entry.clone())?;
+    }
+
+    manifest
+        .inner(LABEL)
+        .map_err(|e| anyhow!("{:?}", e))?
+        .add_manifest(&files)?;
+
+    manifest.run_path = Some(Path::new(use_prefix).join(exe_path));
+
+    Ok(())
+}
+
 /// FileManifest.add_python_resource(prefix, resource)
 pub fn file_manifest_add_python_resource(
     manifest: &mut FileManifestValue,
@@ -278,6 +308,7 @@ mod tests {
             data: FileData::Memory(vec![]),
             is_stdlib: false,
             is_test: false,
+            locale: None,
         }));
 
         let mut env = test_evaluation_context_builder()?.into_context()?;
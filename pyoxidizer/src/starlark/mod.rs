@@ -10,6 +10,7 @@ define Oxidized Python binaries.
 
 pub mod env;
 pub mod eval;
+pub mod extras;
 pub mod file;
 pub mod file_resource;
 pub mod python_distribution;
@@ -22,6 +23,8 @@ pub mod python_package_distribution_resource;
 pub mod python_package_resource;
 pub mod python_packaging_policy;
 pub mod python_resource;
+pub mod record;
+pub mod testing;
 #[cfg(test)]
 mod testutil;
 pub mod util;
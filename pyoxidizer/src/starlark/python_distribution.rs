@@ -10,11 +10,17 @@ use {
         python_packaging_policy::PythonPackagingPolicyValue,
         python_resource::{add_context_for_value, python_resource_to_value},
     },
-    crate::py_packaging::{
-        distribution::BinaryLibpythonLinkMode,
-        distribution::{
-            default_distribution_location, DistributionFlavor, PythonDistribution,
-            PythonDistributionLocation,
+    crate::{
+        py_packaging::{
+            distribution::BinaryLibpythonLinkMode,
+            distribution::{
+                default_distribution_location, DistributionFlavor, PythonDistribution,
+                PythonDistributionLocation,
+            },
+        },
+        python_distribution_channels::{
+            self, default_extension_modules_pin_name, resolve_channel_distribution,
+            DistributionChannel, LOCK_FILE_NAME,
         },
     },
     anyhow::{anyhow, Result},
@@ -27,6 +33,7 @@ use {
         environment::TypeValues,
         eval::call_stack::CallStack,
         values::{
+            dict::Dictionary,
             error::{RuntimeError, ValueError, INCORRECT_PARAMETER_TYPE_ERROR_CODE},
             none::NoneType,
             {Mutable, TypedValue, Value, ValueResult},
@@ -37,7 +44,7 @@ use {
         },
     },
     starlark_dialect_build_targets::{optional_str_arg, optional_type_arg},
-    std::{ops::Deref, sync::Arc},
+    std::{ops::Deref, str::FromStr, sync::Arc},
 };
 
 /// A Starlark Value wrapper for `PythonDistribution` traits.
@@ -106,15 +113,25 @@ impl TypedValue for PythonDistributionValue {
 
 // Starlark functions.
 impl PythonDistributionValue {
-    /// default_python_distribution(flavor, build_target=None, python_version=None)
+    /// default_python_distribution(flavor, build_target=None, python_version=None, channel=None)
     fn default_python_distribution(
         type_values: &TypeValues,
         flavor: String,
         build_target: &Value,
         python_version: &Value,
+        channel: &Value,
     ) -> ValueResult {
         let build_target = optional_str_arg("build_target", build_target)?;
         let python_version = optional_str_arg("python_version", python_version)?;
+        let channel = optional_str_arg("channel", channel)?;
+
+        if python_version.is_some() && channel.is_some() {
+            return Err(ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: "cannot define both python_version and channel".to_string(),
+                label: "default_python_distribution()".to_string(),
+            }));
+        }
 
         let pyoxidizer_context_value = get_context(type_values)?;
         let pyoxidizer_context = pyoxidizer_context_value
@@ -136,15 +153,32 @@ impl PythonDistributionValue {
 
         let python_version_str = python_version.as_deref();
 
-        let location = default_distribution_location(&flavor, &build_target, python_version_str)
-            .map_err(|e| {
+        let location = if let Some(channel) = channel {
+            let channel = DistributionChannel::from_str(&channel).map_err(|e| {
                 ValueError::from(RuntimeError {
                     code: "PYOXIDIZER_BUILD",
-                    message: format!("{:?}", e),
+                    message: e,
                     label: "default_python_distribution()".to_string(),
                 })
             })?;
 
+            resolve_channel_distribution(
+                &pyoxidizer_context.cwd.join(LOCK_FILE_NAME),
+                &channel,
+                &build_target,
+                &flavor,
+            )
+        } else {
+            default_distribution_location(&flavor, &build_target, python_version_str)
+        }
+        .map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{:?}", e),
+                label: "default_python_distribution()".to_string(),
+            })
+        })?;
+
         warn!(
             "target Python distribution for {} resolves to: {}",
             build_target, location
@@ -198,7 +232,7 @@ impl PythonDistributionValue {
     fn make_python_packaging_policy_starlark(&mut self, type_values: &TypeValues) -> ValueResult {
         let dist = self.resolve_distribution(type_values, "resolve_distribution")?;
 
-        let policy = dist.create_packaging_policy().map_err(|e| {
+        let mut policy = dist.create_packaging_policy().map_err(|e| {
             ValueError::from(RuntimeError {
                 code: "PYOXIDIZER_BUILD",
                 message: format!("{:?}", e),
@@ -206,6 +240,13 @@ impl PythonDistributionValue {
             })
         })?;
 
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        pyoxidizer_context.apply_extras_to_policy(&mut policy, "make_python_packaging_policy()")?;
+
         Ok(Value::new(PythonPackagingPolicyValue::new(policy)))
     }
 
@@ -250,15 +291,22 @@ impl PythonDistributionValue {
         let dist = self.resolve_distribution(type_values, "resolve_distribution()")?;
 
         let policy = if packaging_policy.get_type() == "NoneType" {
-            Ok(PythonPackagingPolicyValue::new(
-                dist.create_packaging_policy().map_err(|e| {
-                    ValueError::from(RuntimeError {
-                        code: "PYOXIDIZER_BUILD",
-                        message: format!("{:?}", e),
-                        label: "to_python_executable_starlark()".to_string(),
-                    })
-                })?,
-            ))
+            let mut policy = dist.create_packaging_policy().map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:?}", e),
+                    label: "to_python_executable_starlark()".to_string(),
+                })
+            })?;
+
+            let pyoxidizer_context_value = get_context(type_values)?;
+            let pyoxidizer_context = pyoxidizer_context_value
+                .downcast_ref::<PyOxidizerEnvironmentContext>()
+                .ok_or(ValueError::IncorrectParameterType)?;
+
+            pyoxidizer_context.apply_extras_to_policy(&mut policy, LABEL)?;
+
+            Ok(PythonPackagingPolicyValue::new(policy))
         } else {
             match packaging_policy.downcast_ref::<PythonPackagingPolicyValue>() {
                 Some(policy) => Ok(policy.clone()),
@@ -413,6 +461,83 @@ impl PythonDistributionValue {
 
         Ok(Value::from(values))
     }
+
+    /// PythonDistribution.extension_module_variants()
+    pub fn extension_module_variants_starlark(&mut self, type_values: &TypeValues) -> ValueResult {
+        let dist = self.resolve_distribution(type_values, "resolve_distribution")?;
+
+        let mut modules = Dictionary::default();
+
+        for (name, variants) in
+            python_distribution_channels::extension_module_variants(dist.deref())
+        {
+            let variants = variants
+                .into_iter()
+                .map(|v| {
+                    let mut variant = Dictionary::default();
+                    variant.insert(Value::from("variant"), Value::from(v.variant))?;
+                    variant.insert(
+                        Value::from("builtin_default"),
+                        Value::from(v.builtin_default),
+                    )?;
+                    variant.insert(Value::from("required"), Value::from(v.required))?;
+                    variant.insert(
+                        Value::from("link_libraries"),
+                        Value::from(
+                            v.link_libraries
+                                .into_iter()
+                                .map(Value::from)
+                                .collect::<Vec<_>>(),
+                        ),
+                    )?;
+                    variant.insert(Value::from("license"), Value::from(v.license))?;
+
+                    Value::try_from(variant.get_content().clone())
+                })
+                .collect::<Result<Vec<Value>, ValueError>>()?;
+
+            modules.insert(Value::from(name), Value::from(variants))?;
+        }
+
+        Value::try_from(modules.get_content().clone())
+    }
+
+    /// PythonDistribution.pin_extension_module_variants(name=None, update=False)
+    pub fn pin_extension_module_variants_starlark(
+        &mut self,
+        type_values: &TypeValues,
+        name: &Value,
+        update: bool,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonDistribution.pin_extension_module_variants()";
+
+        let name = optional_str_arg("name", name)?;
+
+        let dist = self.resolve_distribution(type_values, "resolve_distribution")?;
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let name = name.unwrap_or_else(|| default_extension_modules_pin_name(dist.deref()));
+
+        python_distribution_channels::pin_extension_module_variants(
+            &pyoxidizer_context.cwd.join(LOCK_FILE_NAME),
+            &name,
+            python_distribution_channels::extension_module_variants(dist.deref()),
+            update,
+        )
+        .map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYTHON_DISTRIBUTION",
+                message: format!("{}", e),
+                label: LABEL.to_string(),
+            })
+        })?;
+
+        Ok(Value::new(NoneType::None))
+    }
 }
 
 starlark_module! { python_distribution_module =>
@@ -421,6 +546,11 @@ starlark_module! { python_distribution_module =>
         PythonDistributionValue::from_args(sha256, &local_path, &url, flavor)
     }
 
+    PythonDistribution.extension_module_variants(env env, this) {
+        let mut this = this.downcast_mut::<PythonDistributionValue>().unwrap().unwrap();
+        this.extension_module_variants_starlark(env)
+    }
+
     PythonDistribution.make_python_packaging_policy(env env, this) {
         let mut this = this.downcast_mut::<PythonDistributionValue>().unwrap().unwrap();
         this.make_python_packaging_policy_starlark(env)
@@ -431,6 +561,16 @@ starlark_module! { python_distribution_module =>
         this.make_python_interpreter_config_starlark(env)
     }
 
+    PythonDistribution.pin_extension_module_variants(
+        env env,
+        this,
+        name=NoneType::None,
+        update: bool = false
+    ) {
+        let mut this = this.downcast_mut::<PythonDistributionValue>().unwrap().unwrap();
+        this.pin_extension_module_variants_starlark(env, &name, update)
+    }
+
     PythonDistribution.python_resources(env env, call_stack cs, this) {
         let mut this = this.downcast_mut::<PythonDistributionValue>().unwrap().unwrap();
         this.python_resources_starlark(env, cs)
@@ -458,9 +598,10 @@ starlark_module! { python_distribution_module =>
         env env,
         flavor: String = "standalone".to_string(),
         build_target=NoneType::None,
-        python_version=NoneType::None
+        python_version=NoneType::None,
+        channel=NoneType::None
     ) {
-        PythonDistributionValue::default_python_distribution(env, flavor, &build_target, &python_version)
+        PythonDistributionValue::default_python_distribution(env, flavor, &build_target, &python_version, &channel)
     }
 }
 
@@ -471,12 +612,15 @@ mod tests {
         crate::{
             environment::default_target_triple,
             py_packaging::distribution::DistributionFlavor,
+            python_distribution_channels::LockFile,
             python_distributions::PYTHON_DISTRIBUTIONS,
             starlark::{
+                eval::EvaluationContextBuilder,
                 python_extension_module::PythonExtensionModuleValue,
                 python_module_source::PythonModuleSourceValue,
                 python_package_resource::PythonPackageResourceValue, testutil::*,
             },
+            testutil::{get_env, DISTRIBUTION_CACHE},
         },
     };
 
@@ -580,6 +724,20 @@ mod tests {
         assert_eq!(x.source, host_distribution.location)
     }
 
+    #[test]
+    fn test_default_python_distribution_channel_errors() {
+        let err = starlark_nok(
+            "default_python_distribution(python_version='3.10', channel='cpython-3.10-latest')",
+        );
+        assert_eq!(err.message, "cannot define both python_version and channel");
+
+        let err = starlark_nok("default_python_distribution(channel='cpython-3.10')");
+        assert_eq!(
+            err.message,
+            "distribution channel cpython-3.10 not recognized; expected cpython-X.Y-latest"
+        );
+    }
+
     #[test]
     fn test_python_distribution_no_args() {
         let err = starlark_nok("PythonDistribution()");
@@ -662,4 +820,76 @@ mod tests {
             .filter(|v| v.get_type() == PythonPackageResourceValue::TYPE)
             .all(|v| v.get_attr("is_stdlib").unwrap().to_bool()));
     }
+
+    #[test]
+    fn test_extension_module_variants() {
+        let modules = starlark_ok("default_python_distribution().extension_module_variants()");
+        assert_eq!(modules.get_type(), "dict");
+
+        let variants = modules.at(Value::from("_sqlite3")).unwrap();
+        assert_eq!(variants.get_type(), "list");
+
+        let variant = variants.at(Value::from(0)).unwrap();
+        assert_eq!(
+            variant.at(Value::from("variant")).unwrap().to_str(),
+            "default"
+        );
+        assert!(variant
+            .at(Value::from("link_libraries"))
+            .unwrap()
+            .iter()
+            .unwrap()
+            .iter()
+            .any(|l| l.to_str() == "sqlite3"));
+    }
+
+    #[test]
+    fn test_pin_extension_module_variants() -> Result<()> {
+        let env = get_env()?;
+        let temp_dir = env.temporary_directory("pyoxidizer-test")?;
+        let lock_path = temp_dir.path().join(LOCK_FILE_NAME);
+
+        let mut eval = EvaluationContextBuilder::new(
+            &env,
+            temp_dir.path().join("pyoxidizer.bzl"),
+            default_target_triple(),
+        )
+        .distribution_cache(DISTRIBUTION_CACHE.clone())
+        .into_context()?;
+
+        eval.eval("dist = default_python_distribution()")?;
+        eval.eval("dist.pin_extension_module_variants()")?;
+
+        let mut lock = LockFile::from_path(&lock_path)?;
+        assert_eq!(lock.extension_modules.len(), 1);
+        let name = lock.extension_modules[0].distribution.clone();
+        assert!(name.starts_with("cpython-3."));
+
+        // Pinned variants match.
+        eval.eval("dist.pin_extension_module_variants()")?;
+
+        lock.extension_modules[0]
+            .extension_modules
+            .get_mut("_sqlite3")
+            .unwrap()[0]
+            .link_libraries = vec![];
+        lock.write_path(&lock_path)?;
+
+        let err = eval
+            .eval("dist.pin_extension_module_variants()")
+            .unwrap_err();
+        assert!(str::contains(
+            &std::format!("{:?}", err),
+            "_sqlite3 (default): link libraries [] -> [sqlite3]"
+        ));
+
+        eval.eval("dist.pin_extension_module_variants(update=True)")?;
+        assert_ne!(LockFile::from_path(&lock_path)?, lock);
+
+        // Pins of distributions are independent.
+        eval.eval("dist.pin_extension_module_variants(name='other')")?;
+        assert_eq!(LockFile::from_path(&lock_path)?.extension_modules.len(), 2);
+
+        Ok(())
+    }
 }
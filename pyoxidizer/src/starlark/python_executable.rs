@@ -6,7 +6,7 @@ use {
     super::{
         env::{get_context, PyOxidizerEnvironmentContext},
         file::FileValue,
-        file_resource::file_manifest_add_python_executable,
+        file_resource::{file_manifest_add_python_executable, file_manifest_add_python_runtime},
         python_embedded_resources::PythonEmbeddedResourcesValue,
         python_extension_module::PythonExtensionModuleValue,
         python_module_source::PythonModuleSourceValue,
@@ -17,15 +17,20 @@ use {
         util::ToValue,
     },
     crate::{
+        build_inputs,
         licensing::licenses_from_cargo_manifest,
         project_building::build_python_executable,
         py_packaging::binary::PythonBinaryBuilder,
-        py_packaging::binary::{PackedResourcesLoadMode, WindowsRuntimeDllsMode},
+        py_packaging::binary::{
+            PackedResourcesLoadMode, WindowsCrtLinkage, WindowsDpiAwareness, WindowsRuntimeDllsMode,
+        },
     },
     anyhow::{anyhow, Context, Result},
     linked_hash_map::LinkedHashMap,
     log::{info, warn},
-    python_packaging::resource::PythonModuleSource,
+    python_packaging::{
+        filesystem_scanning::find_package_resources_in_directory, resource::PythonModuleSource,
+    },
     simple_file_manifest::FileData,
     starlark::{
         environment::TypeValues,
@@ -43,11 +48,12 @@ use {
         },
     },
     starlark_dialect_build_targets::{
-        optional_dict_arg, optional_list_arg, optional_str_arg, optional_type_arg,
-        required_list_arg, ResolvedTarget, ResolvedTargetValue, RunMode, ToOptional,
+        optional_dict_arg, optional_int_arg, optional_list_arg, optional_str_arg,
+        optional_type_arg, required_dict_arg, required_list_arg, ResolvedTarget,
+        ResolvedTargetValue, RunMode, ToOptional,
     },
     std::{
-        collections::HashMap,
+        collections::{BTreeMap, HashMap},
         io::Write,
         ops::Deref,
         path::{Path, PathBuf},
@@ -77,11 +83,17 @@ where
 }
 
 pub fn build_internal(
-    exe: MutexGuard<Box<dyn PythonBinaryBuilder>>,
+    mut exe: MutexGuard<Box<dyn PythonBinaryBuilder>>,
     type_values: &TypeValues,
     target: &str,
     context: &PyOxidizerEnvironmentContext,
 ) -> Result<(ResolvedTarget, PathBuf)> {
+    // Relative icon paths are relative to the directory of the config file.
+    if let Some(icon_path) = exe.windows_icon_path().map(|path| context.cwd.join(path)) {
+        build_inputs::record_path(&icon_path);
+        exe.set_windows_icon_path(Some(icon_path))?;
+    }
+
     // Build an executable by writing out a temporary Rust project
     // and building it.
     let build = build_python_executable(
@@ -176,7 +188,20 @@ impl TypedValue for PythonExecutableValue {
         let exe = self.inner(&format!("PythonExecutable.{}", attribute))?;
 
         match attribute {
+            "import_analysis" => Ok(Value::from(exe.import_analysis())),
+            "import_analysis_allow" => Ok(Value::from(
+                exe.import_analysis_allow()
+                    .iter()
+                    .map(|name| Value::from(name.as_str()))
+                    .collect::<Vec<_>>(),
+            )),
+            "include_dynamic_imports" => Ok(Value::from(exe.include_dynamic_imports())),
             "licenses_filename" => Ok(exe.licenses_filename().to_value()),
+            "packed_resources_encryption_key" => Ok(exe
+                .packed_resources_encryption_key()
+                .map(hex::encode)
+                .to_value()),
+            "packed_resources_key_provider" => Ok(exe.packed_resources_key_provider().to_value()),
             "packed_resources_load_mode" => {
                 Ok(Value::from(exe.packed_resources_load_mode().to_string()))
             }
@@ -188,6 +213,49 @@ impl TypedValue for PythonExecutableValue {
                 Ok(Value::from(exe.windows_runtime_dlls_mode().to_string()))
             }
             "windows_subsystem" => Ok(Value::from(exe.windows_subsystem())),
+            "windows_version_info" => Ok(Value::try_from(
+                exe.windows_version_info()
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect::<HashMap<String, String>>(),
+            )?),
+            "windows_icon_path" => match exe.windows_icon_path() {
+                Some(value) => Ok(Value::from(format!("{}", value.display()))),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            "windows_long_path_aware" => Ok(Value::from(exe.windows_long_path_aware())),
+            "windows_dpi_awareness" => Ok(Value::from(exe.windows_dpi_awareness().to_string())),
+            "windows_crt_linkage" => Ok(Value::from(exe.windows_crt_linkage().to_string())),
+            "source_map_root" => match exe.source_map_root() {
+                Some(value) => Ok(Value::from(format!("{}", value.display()))),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            "application_layer_packages" => Ok(Value::from(
+                exe.application_layer_packages()
+                    .iter()
+                    .map(|x| Value::from(x.as_str()))
+                    .collect::<Vec<_>>(),
+            )),
+            "cargo_features" => Ok(Value::from(
+                exe.cargo_features()
+                    .iter()
+                    .map(|x| Value::from(x.as_str()))
+                    .collect::<Vec<_>>(),
+            )),
+            "cargo_rustflags" => Ok(Value::from(
+                exe.cargo_rustflags()
+                    .iter()
+                    .map(|x| Value::from(x.as_str()))
+                    .collect::<Vec<_>>(),
+            )),
+            "cargo_profile_opt_level" => Ok(exe.cargo_profile().opt_level.clone().to_value()),
+            "cargo_profile_lto" => Ok(exe.cargo_profile().lto.clone().to_value()),
+            "cargo_profile_codegen_units" => match exe.cargo_profile().codegen_units {
+                Some(value) => Ok(Value::from(value as i64)),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            "cargo_profile_panic" => Ok(exe.cargo_profile().panic.clone().to_value()),
+            "cargo_profile_strip" => Ok(exe.cargo_profile().strip.clone().to_value()),
             _ => Err(ValueError::OperationNotSupported {
                 op: UnsupportedOperation::GetAttr(attribute.to_string()),
                 left: Self::TYPE.to_string(),
@@ -199,11 +267,30 @@ impl TypedValue for PythonExecutableValue {
     fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
         Ok(matches!(
             attribute,
-            "licenses_filename"
+            "import_analysis"
+                | "import_analysis_allow"
+                | "include_dynamic_imports"
+                | "licenses_filename"
+                | "packed_resources_encryption_key"
+                | "packed_resources_key_provider"
                 | "packed_resources_load_mode"
                 | "tcl_files_path"
                 | "windows_runtime_dlls_mode"
                 | "windows_subsystem"
+                | "windows_version_info"
+                | "windows_icon_path"
+                | "windows_long_path_aware"
+                | "windows_dpi_awareness"
+                | "windows_crt_linkage"
+                | "source_map_root"
+                | "application_layer_packages"
+                | "cargo_features"
+                | "cargo_rustflags"
+                | "cargo_profile_opt_level"
+                | "cargo_profile_lto"
+                | "cargo_profile_codegen_units"
+                | "cargo_profile_panic"
+                | "cargo_profile_strip"
         ))
     }
 
@@ -211,12 +298,60 @@ impl TypedValue for PythonExecutableValue {
         let mut exe = self.inner(&format!("PythonExecutable.{}", attribute))?;
 
         match attribute {
+            "import_analysis" => {
+                exe.set_import_analysis(value.to_bool());
+
+                Ok(())
+            }
+            "import_analysis_allow" => {
+                required_list_arg(attribute, "string", &value)?;
+
+                exe.set_import_analysis_allow(
+                    value
+                        .iter()?
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<_>>(),
+                );
+
+                Ok(())
+            }
+            "include_dynamic_imports" => {
+                exe.set_include_dynamic_imports(value.to_bool());
+
+                Ok(())
+            }
             "licenses_filename" => {
                 let value = optional_str_arg("licenses_filename", &value)?;
                 exe.set_licenses_filename(value);
 
                 Ok(())
             }
+            "packed_resources_encryption_key" => {
+                let key = optional_str_arg(attribute, &value)?
+                    .map(|value| {
+                        let mut key = [0u8; 32];
+                        hex::decode_to_slice(&value, &mut key).map(|_| key)
+                    })
+                    .transpose()
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: format!("key must be 64 hex characters: {}", e),
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?;
+
+                exe.set_packed_resources_encryption_key(key);
+
+                Ok(())
+            }
+            "packed_resources_key_provider" => {
+                let value = optional_str_arg(attribute, &value)?;
+                exe.set_packed_resources_key_provider(value);
+
+                Ok(())
+            }
             "packed_resources_load_mode" => {
                 exe.set_packed_resources_load_mode(
                     PackedResourcesLoadMode::try_from(value.to_string().as_str()).map_err(|e| {
@@ -260,6 +395,155 @@ impl TypedValue for PythonExecutableValue {
 
                 Ok(())
             }
+            "windows_version_info" => {
+                required_dict_arg(attribute, "string", "string", &value)?;
+
+                let version_info = value
+                    .iter()?
+                    .iter()
+                    .map(|key| {
+                        let v = value.at(key.clone())?.to_string();
+                        Ok((key.to_string(), v))
+                    })
+                    .collect::<Result<BTreeMap<_, _>, ValueError>>()?;
+
+                exe.set_windows_version_info(version_info).map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: format!("{:?}", e),
+                        label: format!("{}.{}", Self::TYPE, attribute),
+                    })
+                })?;
+
+                Ok(())
+            }
+            "windows_icon_path" => {
+                let value: Option<String> = value.to_optional();
+
+                exe.set_windows_icon_path(value.map(PathBuf::from))
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: format!("{:?}", e),
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?;
+
+                Ok(())
+            }
+            "windows_long_path_aware" => {
+                exe.set_windows_long_path_aware(value.to_bool());
+
+                Ok(())
+            }
+            "windows_dpi_awareness" => {
+                exe.set_windows_dpi_awareness(
+                    WindowsDpiAwareness::try_from(value.to_string().as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: e,
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?,
+                );
+
+                Ok(())
+            }
+            "windows_crt_linkage" => {
+                let linkage =
+                    WindowsCrtLinkage::try_from(value.to_string().as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: e,
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?;
+
+                exe.set_windows_crt_linkage(linkage).map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: format!("{:?}", e),
+                        label: format!("{}.{}", Self::TYPE, attribute),
+                    })
+                })?;
+
+                Ok(())
+            }
+            "source_map_root" => {
+                let value: Option<String> = value.to_optional();
+
+                exe.set_source_map_root(value.map(PathBuf::from));
+
+                Ok(())
+            }
+            "application_layer_packages" => {
+                required_list_arg(attribute, "string", &value)?;
+
+                exe.set_application_layer_packages(
+                    value.iter()?.iter().map(|x| x.to_string()).collect(),
+                );
+
+                Ok(())
+            }
+            "cargo_features" => {
+                required_list_arg(attribute, "string", &value)?;
+
+                exe.set_cargo_features(value.iter()?.iter().map(|x| x.to_string()).collect());
+
+                Ok(())
+            }
+            "cargo_rustflags" => {
+                required_list_arg(attribute, "string", &value)?;
+
+                exe.set_cargo_rustflags(value.iter()?.iter().map(|x| x.to_string()).collect());
+
+                Ok(())
+            }
+            "cargo_profile_opt_level"
+            | "cargo_profile_lto"
+            | "cargo_profile_codegen_units"
+            | "cargo_profile_panic"
+            | "cargo_profile_strip" => {
+                let mut profile = exe.cargo_profile().clone();
+
+                match attribute {
+                    "cargo_profile_opt_level" => {
+                        profile.opt_level = optional_str_arg(attribute, &value)?;
+                    }
+                    "cargo_profile_lto" => {
+                        profile.lto = optional_str_arg(attribute, &value)?;
+                    }
+                    "cargo_profile_codegen_units" => {
+                        profile.codegen_units = optional_int_arg(attribute, &value)?
+                            .map(u32::try_from)
+                            .transpose()
+                            .map_err(|e| {
+                                ValueError::from(RuntimeError {
+                                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                                    message: format!("{}", e),
+                                    label: format!("{}.{}", Self::TYPE, attribute),
+                                })
+                            })?;
+                    }
+                    "cargo_profile_panic" => {
+                        profile.panic = optional_str_arg(attribute, &value)?;
+                    }
+                    "cargo_profile_strip" => {
+                        profile.strip = optional_str_arg(attribute, &value)?;
+                    }
+                    _ => unreachable!(),
+                }
+
+                exe.set_cargo_profile(profile).map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: format!("{:?}", e),
+                        label: format!("{}.{}", Self::TYPE, attribute),
+                    })
+                })?;
+
+                Ok(())
+            }
             _ => Err(ValueError::OperationNotSupported {
                 op: UnsupportedOperation::SetAttr(attribute.to_string()),
                 left: Self::TYPE.to_string(),
@@ -450,6 +734,41 @@ impl PythonExecutableValue {
         Ok(Value::from(resources))
     }
 
+    /// PythonExecutable.pip_install_extras(extra_envs=None)
+    pub fn pip_install_extras(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        extra_envs: &Value,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.pip_install_extras()";
+
+        let requirements = {
+            let pyoxidizer_context_value = get_context(type_values)?;
+            let pyoxidizer_context = pyoxidizer_context_value
+                .downcast_ref::<PyOxidizerEnvironmentContext>()
+                .ok_or(ValueError::IncorrectParameterType)?;
+
+            pyoxidizer_context
+                .resolve_enabled_extras(LABEL)?
+                .into_iter()
+                .flat_map(|extra| extra.requirements.iter())
+                .map(|x| Value::from(x.as_str()))
+                .collect::<Vec<_>>()
+        };
+
+        if requirements.is_empty() {
+            return Ok(Value::from(Vec::<Value>::new()));
+        }
+
+        self.pip_install(
+            type_values,
+            call_stack,
+            &Value::from(requirements),
+            extra_envs,
+        )
+    }
+
     /// PythonExecutable.read_package_root(path, packages)
     pub fn read_package_root(
         &mut self,
@@ -472,6 +791,8 @@ impl PythonExecutableValue {
 
         let mut exe = self.inner(LABEL)?;
 
+        build_inputs::record_path(&path);
+
         let resources =
             error_context(LABEL, || exe.read_package_root(Path::new(&path), &packages))?;
 
@@ -492,6 +813,39 @@ impl PythonExecutableValue {
         Ok(Value::from(resources))
     }
 
+    /// PythonExecutable.read_static_assets(path, package)
+    pub fn read_static_assets(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        path: String,
+        package: String,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.read_static_assets()";
+
+        let python_packaging_policy = self.python_packaging_policy();
+
+        build_inputs::record_path(&path);
+
+        let resources = error_context(LABEL, || {
+            find_package_resources_in_directory(Path::new(&path), &package)
+        })?;
+
+        resources
+            .into_iter()
+            .map(|r| {
+                python_resource_to_value(
+                    LABEL,
+                    type_values,
+                    call_stack,
+                    &r.into(),
+                    &python_packaging_policy,
+                )
+            })
+            .collect::<Result<Vec<Value>, ValueError>>()
+            .map(Value::from)
+    }
+
     /// PythonExecutable.read_virtualenv(path)
     pub fn read_virtualenv(
         &mut self,
@@ -505,6 +859,8 @@ impl PythonExecutableValue {
 
         let mut exe = self.inner(LABEL)?;
 
+        build_inputs::record_path(&path);
+
         let resources = error_context(LABEL, || exe.read_virtualenv(Path::new(&path)))?;
 
         let resources = resources
@@ -574,6 +930,8 @@ impl PythonExecutableValue {
             PathBuf::from(&pyoxidizer_context.cwd).join(package_path)
         };
 
+        build_inputs::record_path(&package_path);
+
         let python_packaging_policy = self.python_packaging_policy();
 
         let mut exe = self.inner(LABEL)?;
@@ -857,6 +1215,40 @@ impl PythonExecutableValue {
         Ok(manifest_value.clone())
     }
 
+    /// PythonExecutable.to_runtime_file_manifest(prefix)
+    pub fn to_runtime_file_manifest(
+        &self,
+        type_values: &TypeValues,
+        prefix: String,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.to_runtime_file_manifest()";
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let manifest_value = FileManifestValue::new_from_args()?;
+        let mut manifest = manifest_value
+            .downcast_mut::<FileManifestValue>()
+            .unwrap()
+            .unwrap();
+
+        let exe = self.inner(LABEL)?;
+
+        error_context(LABEL, || {
+            file_manifest_add_python_runtime(
+                &mut manifest,
+                pyoxidizer_context.env(),
+                &prefix,
+                &**exe,
+            )
+            .context("adding Python runtime to FileManifest")
+        })?;
+
+        Ok(manifest_value.clone())
+    }
+
     /// PythonExecutable.to_wix_bundle_builder(id_prefix, name, version, manufacturer, msi_builder_callback)
     #[allow(clippy::too_many_arguments)]
     pub fn to_wix_bundle_builder(
@@ -1003,6 +1395,25 @@ impl PythonExecutableValue {
 
         Ok(Value::new(NoneType::None))
     }
+
+    /// PythonExecutable.override_resource(path, local_path)
+    pub fn override_resource(&mut self, path: String, local_path: String) -> ValueResult {
+        const LABEL: &str = "PythonExecutable.override_resource()";
+
+        let local_path = PathBuf::from(local_path);
+
+        let mut exe = self.inner(LABEL)?;
+
+        error_context(LABEL, || {
+            if !local_path.is_file() {
+                return Err(anyhow!("{} is not a file", local_path.display()));
+            }
+
+            exe.override_resource(&path, FileData::Path(local_path))
+        })?;
+
+        Ok(Value::new(NoneType::None))
+    }
 }
 
 starlark_module! { python_executable_env =>
@@ -1044,6 +1455,16 @@ starlark_module! { python_executable_env =>
         this.pip_install(env, cs, &args, &extra_envs)
     }
 
+    PythonExecutable.pip_install_extras(
+        env env,
+        call_stack cs,
+        this,
+        extra_envs=NoneType::None
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.pip_install_extras(env, cs, &extra_envs)
+    }
+
     PythonExecutable.read_package_root(
         env env,
         call_stack cs,
@@ -1055,6 +1476,17 @@ starlark_module! { python_executable_env =>
         this.read_package_root(env, cs, path, &packages)
     }
 
+    PythonExecutable.read_static_assets(
+        env env,
+        call_stack cs,
+        this,
+        path: String,
+        package: String
+    ) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.read_static_assets(env, cs, path, package)
+    }
+
     PythonExecutable.read_virtualenv(
         env env,
         call_stack cs,
@@ -1118,6 +1550,11 @@ starlark_module! { python_executable_env =>
         this.filter_resources_from_files(&files, &glob_files)
     }
 
+    PythonExecutable.override_resource(this, path: String, local_path: String) {
+        let mut this = this.downcast_mut::<PythonExecutableValue>().unwrap().unwrap();
+        this.override_resource(path, local_path)
+    }
+
     PythonExecutable.to_embedded_resources(this) {
         let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
         this.to_embedded_resources()
@@ -1128,6 +1565,11 @@ starlark_module! { python_executable_env =>
         this.to_file_manifest(env, prefix)
     }
 
+    PythonExecutable.to_runtime_file_manifest(env env, this, prefix: String) {
+        let this = this.downcast_ref::<PythonExecutableValue>().unwrap();
+        this.to_runtime_file_manifest(env, prefix)
+    }
+
     PythonExecutable.to_wix_bundle_builder(
         env env,
         call_stack cs,
@@ -1169,7 +1611,10 @@ mod tests {
     use {
         super::super::testutil::*,
         super::*,
-        crate::{python_distributions::PYTHON_DISTRIBUTIONS, testutil::*},
+        crate::{
+            py_packaging::binary::CargoProfile, python_distributions::PYTHON_DISTRIBUTIONS,
+            testutil::*,
+        },
     };
 
     #[test]
@@ -1364,6 +1809,98 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_read_static_assets() -> Result<()> {
+        let temp_dir = get_env()?.temporary_directory("pyoxidizer-test")?;
+
+        let root = temp_dir.path();
+        std::fs::create_dir(root.join("css"))?;
+        std::fs::write(root.join("css").join("app.css"), "body {}")?;
+        std::fs::write(root.join("index.html"), "<html></html>")?;
+
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let resources = env.eval(&format!(
+            "exe.read_static_assets(\"{}\", \"app.web\")",
+            root.display().to_string().replace('\\', "/")
+        ))?;
+
+        assert_eq!(resources.get_type(), "list");
+        assert_eq!(resources.length().unwrap(), 2);
+
+        let raw_it = resources.iter().unwrap();
+        let mut it = raw_it.iter();
+
+        let v = it.next().unwrap();
+        assert_eq!(v.get_type(), PythonPackageResourceValue::TYPE);
+        let x = v.downcast_ref::<PythonPackageResourceValue>().unwrap();
+        let inner = x.inner("ignored").unwrap();
+        assert_eq!(inner.r.leaf_package, "app.web");
+        assert_eq!(inner.r.relative_name, "css/app.css");
+        assert_eq!(inner.r.data.resolve_content().unwrap(), b"body {}");
+        assert!(inner.add_context.is_some());
+        drop(inner);
+
+        let v = it.next().unwrap();
+        let x = v.downcast_ref::<PythonPackageResourceValue>().unwrap();
+        let inner = x.inner("ignored").unwrap();
+        assert_eq!(inner.r.relative_name, "index.html");
+        drop(inner);
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_override_resource() -> Result<()> {
+        let temp_dir = get_env()?.temporary_directory("pyoxidizer-test")?;
+
+        let local_path = temp_dir.path().join("message.py");
+        std::fs::write(&local_path, "# overridden")?;
+        let local_path = local_path.display().to_string().replace('\\', "/");
+
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        env.eval(&format!(
+            "exe.override_resource('email/message.py', '{}')",
+            local_path
+        ))?;
+        assert!(env
+            .eval(&format!(
+                "exe.override_resource('email/message.py', '{}')",
+                local_path
+            ))
+            .is_err());
+        assert!(env
+            .eval("exe.override_resource('email/missing.py', 'does-not-exist')")
+            .is_err());
+
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+        let inner = exe.inner("ignored").unwrap();
+
+        let (_, resource) = inner
+            .iter_resources()
+            .find(|(name, _)| name.as_str() == "email.message")
+            .unwrap();
+        assert_eq!(
+            resource
+                .in_memory_source
+                .as_ref()
+                .unwrap()
+                .resolve_content()?,
+            b"# overridden"
+        );
+        drop(inner);
+
+        temp_dir.close()?;
+
+        Ok(())
+    }
+
     #[test]
     fn licenses_filename() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
@@ -1429,6 +1966,55 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_packed_resources_encryption_key() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.packed_resources_encryption_key")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let key = "2a".repeat(32);
+        let value = env.eval(&format!(
+            "exe.packed_resources_encryption_key = '{}'; exe.packed_resources_encryption_key",
+            key
+        ))?;
+        assert_eq!(value.get_type(), "string");
+        assert_eq!(value.to_string(), key);
+
+        assert!(env
+            .eval("exe.packed_resources_encryption_key = 'abcd'")
+            .is_err());
+
+        let value = env.eval(
+            "exe.packed_resources_encryption_key = None; exe.packed_resources_encryption_key",
+        )?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_resources_key_provider() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.packed_resources_key_provider")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval(
+            "exe.packed_resources_key_provider = 'crate::resources_key'; exe.packed_resources_key_provider",
+        )?;
+        assert_eq!(value.get_type(), "string");
+        assert_eq!(value.to_string(), "crate::resources_key");
+
+        let value = env
+            .eval("exe.packed_resources_key_provider = None; exe.packed_resources_key_provider")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        Ok(())
+    }
+
     #[test]
     fn test_windows_subsystem() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
@@ -1445,6 +2031,244 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_windows_version_info() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.windows_version_info")?;
+        assert_eq!(value.get_type(), "dict");
+        assert_eq!(value.length().unwrap(), 0);
+
+        let value = env.eval(
+            "exe.windows_version_info = {'CompanyName': 'Acme', 'FileVersion': '1.2.3'}; \
+            exe.windows_version_info",
+        )?;
+        assert_eq!(value.get_type(), "dict");
+        assert_eq!(value.length().unwrap(), 2);
+        assert_eq!(
+            value.at(Value::from("CompanyName")).unwrap(),
+            Value::from("Acme")
+        );
+
+        assert!(env
+            .eval("exe.windows_version_info = {'Company': 'Acme'}")
+            .is_err());
+        assert!(env
+            .eval("exe.windows_version_info = {'FileVersion': 1}")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_icon_path() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.windows_icon_path")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval("exe.windows_icon_path = 'icons/app.ico'; exe.windows_icon_path")?;
+        assert_eq!(value.to_string(), "icons/app.ico");
+
+        assert!(env.eval("exe.windows_icon_path = 'app.png'").is_err());
+
+        let value = env.eval("exe.windows_icon_path = None; exe.windows_icon_path")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_manifest_settings() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.windows_long_path_aware")?;
+        assert_eq!(value, Value::from(true));
+
+        let value = env.eval("exe.windows_long_path_aware = False; exe.windows_long_path_aware")?;
+        assert_eq!(value, Value::from(false));
+
+        let value = env.eval("exe.windows_dpi_awareness")?;
+        assert_eq!(value.to_string(), "unaware");
+
+        let value =
+            env.eval("exe.windows_dpi_awareness = 'per-monitor-v2'; exe.windows_dpi_awareness")?;
+        assert_eq!(value.to_string(), "per-monitor-v2");
+
+        assert!(env.eval("exe.windows_dpi_awareness = 'high'").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_crt_linkage() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.windows_crt_linkage")?.to_string();
+        assert!(value == "static" || value == "dynamic");
+
+        let value = env.eval(&format!(
+            "exe.windows_crt_linkage = '{}'; exe.windows_crt_linkage",
+            value
+        ))?;
+        assert!(value.to_string() == "static" || value.to_string() == "dynamic");
+
+        let other = if value.to_string() == "static" {
+            "dynamic"
+        } else {
+            "static"
+        };
+        assert!(env
+            .eval(&format!("exe.windows_crt_linkage = '{}'", other))
+            .is_err());
+        assert!(env.eval("exe.windows_crt_linkage = 'MT'").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_source_map_root() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.source_map_root")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval("exe.source_map_root = '/src/app'; exe.source_map_root")?;
+        assert_eq!(value.to_string(), "/src/app");
+
+        let value = env.eval("exe.source_map_root = None; exe.source_map_root")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_application_layer_packages() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.application_layer_packages")?;
+        assert_eq!(value.get_type(), "list");
+        assert_eq!(value.length().unwrap(), 0);
+
+        let value = env.eval(
+            "exe.application_layer_packages = ['myapp', 'myapp_plugins']; exe.application_layer_packages",
+        )?;
+        assert_eq!(value.to_string(), "[\"myapp\", \"myapp_plugins\"]");
+
+        assert!(env
+            .eval("exe.application_layer_packages = 'myapp'")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cargo_build_settings() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.cargo_features")?;
+        assert_eq!(value.get_type(), "list");
+        assert_eq!(value.length().unwrap(), 0);
+
+        let value =
+            env.eval("exe.cargo_features = ['pyembed/serialization']; exe.cargo_features")?;
+        assert_eq!(value.to_string(), "[\"pyembed/serialization\"]");
+
+        let value =
+            env.eval("exe.cargo_rustflags = ['-C', 'target-cpu=native']; exe.cargo_rustflags")?;
+        assert_eq!(value.to_string(), "[\"-C\", \"target-cpu=native\"]");
+
+        for attr in [
+            "cargo_profile_opt_level",
+            "cargo_profile_lto",
+            "cargo_profile_codegen_units",
+            "cargo_profile_panic",
+            "cargo_profile_strip",
+        ] {
+            let value = env.eval(&format!("exe.{}", attr))?;
+            assert_eq!(value.get_type(), "NoneType");
+        }
+
+        env.eval("exe.cargo_profile_opt_level = 'z'")?;
+        env.eval("exe.cargo_profile_lto = 'fat'")?;
+        env.eval("exe.cargo_profile_codegen_units = 1")?;
+        env.eval("exe.cargo_profile_panic = 'abort'")?;
+        env.eval("exe.cargo_profile_strip = 'symbols'")?;
+
+        let value = env.eval("exe.cargo_profile_codegen_units")?;
+        assert_eq!(value.to_int().unwrap(), 1);
+
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutableValue>().unwrap();
+        assert_eq!(
+            exe.inner("ignored").unwrap().cargo_profile(),
+            &CargoProfile {
+                opt_level: Some("z".into()),
+                lto: Some("fat".into()),
+                codegen_units: Some(1),
+                panic: Some("abort".into()),
+                strip: Some("symbols".into()),
+            }
+        );
+
+        assert!(env.eval("exe.cargo_profile_lto = 'yes'").is_err());
+        assert!(env.eval("exe.cargo_profile_codegen_units = 0").is_err());
+        assert!(env.eval("exe.cargo_profile_codegen_units = -1").is_err());
+
+        let value = env.eval("exe.cargo_profile_lto = None; exe.cargo_profile_lto")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_analysis() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.import_analysis")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(!value.to_bool());
+
+        let value = env.eval("exe.import_analysis = True; exe.import_analysis")?;
+        assert!(value.to_bool());
+
+        let value = env.eval("exe.import_analysis_allow")?;
+        assert_eq!(value.get_type(), "list");
+        assert_eq!(value.length().unwrap(), 0);
+
+        let value = env.eval(
+            "exe.import_analysis_allow = ['plugins', 'decimal']; exe.import_analysis_allow",
+        )?;
+        assert_eq!(value.to_string(), "[\"plugins\", \"decimal\"]");
+
+        assert!(env.eval("exe.import_analysis_allow = 'plugins'").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_dynamic_imports() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        let value = env.eval("exe.include_dynamic_imports")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(!value.to_bool());
+
+        let value = env.eval("exe.include_dynamic_imports = True; exe.include_dynamic_imports")?;
+        assert!(value.to_bool());
+
+        Ok(())
+    }
+
     #[test]
     fn test_tcl_files_path() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
@@ -4,11 +4,12 @@
 
 use {
     super::util::ToValue,
-    crate::py_packaging::config::PyembedPythonInterpreterConfig,
+    crate::py_packaging::config::{PyembedPythonInterpreterConfig, SandboxMode},
     python_packaging::{
         interpreter::{
-            Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, MemoryAllocatorBackend,
-            MultiprocessingStartMethod, PythonInterpreterProfile, TerminfoResolution,
+            Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, LogDestination,
+            MemoryAllocatorBackend, Migration, MultiprocessingStartMethod,
+            PackedResourcesVerification, ProgramPath, PythonInterpreterProfile, TerminfoResolution,
         },
         resource::BytecodeOptimizationLevel,
     },
@@ -19,8 +20,9 @@ use {
         none::NoneType,
         {Mutable, TypedValue, Value, ValueResult},
     },
-    starlark_dialect_build_targets::{ToOptional, TryToOptional},
+    starlark_dialect_build_targets::{required_list_arg, ToOptional, TryToOptional},
     std::{
+        path::PathBuf,
         str::FromStr,
         sync::{Arc, Mutex, MutexGuard},
     },
@@ -38,6 +40,12 @@ impl ToValue for TerminfoResolution {
     }
 }
 
+impl ToValue for ProgramPath {
+    fn to_value(&self) -> Value {
+        Value::from(self.to_string())
+    }
+}
+
 impl ToValue for Option<CoerceCLocale> {
     fn to_value(&self) -> Value {
         match self {
@@ -171,6 +179,7 @@ impl TypedValue for PythonInterpreterConfigValue {
             "buffered_stdio" => inner.config.buffered_stdio.to_value(),
             "bytes_warning" => inner.config.bytes_warning.to_value(),
             "check_hash_pycs_mode" => inner.config.check_hash_pycs_mode.to_value(),
+            "code_debug_ranges" => inner.config.code_debug_ranges.to_value(),
             "configure_c_stdio" => inner.config.configure_c_stdio.to_value(),
             "dump_refs" => inner.config.dump_refs.to_value(),
             "exec_prefix" => inner.config.exec_prefix.to_value(),
@@ -183,6 +192,7 @@ impl TypedValue for PythonInterpreterConfigValue {
             "import_time" => inner.config.import_time.to_value(),
             "inspect" => inner.config.inspect.to_value(),
             "install_signal_handlers" => inner.config.install_signal_handlers.to_value(),
+            "int_max_str_digits" => inner.config.int_max_str_digits.to_value(),
             "interactive" => inner.config.interactive.to_value(),
             "legacy_windows_stdio" => inner.config.legacy_windows_stdio.to_value(),
             "malloc_stats" => inner.config.malloc_stats.to_value(),
@@ -190,6 +200,7 @@ impl TypedValue for PythonInterpreterConfigValue {
             "optimization_level" => inner.config.optimization_level.to_value(),
             "parser_debug" => inner.config.parser_debug.to_value(),
             "pathconfig_warnings" => inner.config.pathconfig_warnings.to_value(),
+            "perf_profiling" => inner.config.perf_profiling.to_value(),
             "prefix" => inner.config.prefix.to_value(),
             "program_name" => inner.config.program_name.to_value(),
             "pycache_prefix" => inner.config.pycache_prefix.to_value(),
@@ -198,6 +209,7 @@ impl TypedValue for PythonInterpreterConfigValue {
             "run_command" => inner.config.run_command.to_value(),
             "run_filename" => inner.config.run_filename.to_value(),
             "run_module" => inner.config.run_module.to_value(),
+            "safe_path" => inner.config.safe_path.to_value(),
             "show_ref_count" => inner.config.show_ref_count.to_value(),
             "site_import" => inner.config.site_import.to_value(),
             "skip_first_source_line" => inner.config.skip_first_source_line.to_value(),
@@ -215,17 +227,100 @@ impl TypedValue for PythonInterpreterConfigValue {
             "allocator_obj" => Value::from(inner.allocator_obj),
             "allocator_pymalloc_arena" => Value::from(inner.allocator_pymalloc_arena),
             "allocator_debug" => Value::from(inner.allocator_debug),
+            "allocator_stats" => Value::from(inner.allocator_stats),
             "oxidized_importer" => Value::from(inner.oxidized_importer),
             "filesystem_importer" => Value::from(inner.filesystem_importer),
+            "zipimport_importer" => Value::from(inner.zipimport_importer),
+            "extra_sys_paths" => Value::from(
+                inner
+                    .extra_sys_paths
+                    .iter()
+                    .map(|x| Value::from(format!("{}", x.display())))
+                    .collect::<Vec<_>>(),
+            ),
+            "extra_sys_paths_env" => inner.extra_sys_paths_env.to_value(),
             "argvb" => Value::from(inner.argvb),
+            "console" => Value::from(inner.console),
+            "console_argument" => inner.console_argument.to_value(),
             "multiprocessing_auto_dispatch" => Value::from(inner.multiprocessing_auto_dispatch),
             "multiprocessing_start_method" => {
                 Value::from(inner.multiprocessing_start_method.to_string())
             }
+            "multiprocessing_executable" => inner.multiprocessing_executable.to_value(),
+            "packed_resources_verification" => {
+                Value::from(inner.packed_resources_verification.to_string())
+            }
+            "audit_events" => Value::from(
+                inner
+                    .audit_events
+                    .iter()
+                    .map(|x| Value::from(x.as_str()))
+                    .collect::<Vec<_>>(),
+            ),
             "sys_frozen" => Value::from(inner.sys_frozen),
             "sys_meipass" => Value::from(inner.sys_meipass),
+            "sys_executable" => inner.sys_executable.to_value(),
+            "sys_argv0" => inner.sys_argv0.to_value(),
             "terminfo_resolution" => inner.terminfo_resolution.to_value(),
             "write_modules_directory_env" => inner.write_modules_directory_env.to_value(),
+            "startup_profile_env" => inner.startup_profile_env.to_value(),
+            "host_requirements_check" => Value::from(inner.host_requirements_check),
+            "host_requirements_message" => inner.host_requirements.message.to_value(),
+            "sandbox_mode" => Value::from(inner.sandbox_mode.to_string()),
+            "sandbox_allow_network" => Value::from(inner.sandbox_policy.allow_network),
+            "sandbox_allow_subprocess" => Value::from(inner.sandbox_policy.allow_subprocess),
+            "sandbox_read_paths" => inner.sandbox_policy.read_paths.to_value(),
+            "sandbox_write_paths" => inner.sandbox_policy.write_paths.to_value(),
+            "migrations" => Value::from(
+                inner
+                    .migrations
+                    .iter()
+                    .map(|x| Value::from(x.to_string()))
+                    .collect::<Vec<_>>(),
+            ),
+            "migrations_app_name" => inner.migrations_app_name.to_value(),
+            "setproctitle_module" => Value::from(inner.setproctitle_module),
+            "site_module" => inner.site_module.to_value(),
+            "lazy_import_packages" => Value::from(
+                inner
+                    .lazy_import_packages
+                    .iter()
+                    .map(|x| Value::from(x.as_str()))
+                    .collect::<Vec<_>>(),
+            ),
+            "filesystem_first_packages" => Value::from(
+                inner
+                    .filesystem_first_packages
+                    .iter()
+                    .map(|x| Value::from(x.as_str()))
+                    .collect::<Vec<_>>(),
+            ),
+            "materialized_files_dir" => inner.materialized_files_dir.to_value(),
+            "config_overrides_path" => inner.config_overrides_path.to_value(),
+            "config_overrides_allowed" => Value::from(
+                inner
+                    .config_overrides_allowed
+                    .iter()
+                    .map(|x| Value::from(x.as_str()))
+                    .collect::<Vec<_>>(),
+            ),
+            "environment_overrides_prefix" => inner.environment_overrides_prefix.to_value(),
+            "environment_overrides_allowed" => Value::from(
+                inner
+                    .environment_overrides_allowed
+                    .iter()
+                    .map(|x| Value::from(x.as_str()))
+                    .collect::<Vec<_>>(),
+            ),
+            "log_destination" => match &inner.log_destination {
+                Some(value) => Value::from(value.to_string()),
+                None => Value::from(NoneType::None),
+            },
+            "log_filter" => inner.log_filter.to_value(),
+            "log_filter_env" => inner.log_filter_env.to_value(),
+            "log_register_event_source" => Value::from(inner.log_register_event_source),
+            "traceback_source_paths" => Value::from(inner.traceback_source_paths),
+            "replace_missing_stdio" => Value::from(inner.replace_missing_stdio),
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: UnsupportedOperation::GetAttr(attr.to_string()),
@@ -258,6 +353,7 @@ impl TypedValue for PythonInterpreterConfigValue {
                 | "buffered_stdio"
                 | "bytes_warning"
                 | "check_hash_pycs_mode"
+                | "code_debug_ranges"
                 | "configure_c_stdio"
                 | "dump_refs"
                 | "exec_prefix"
@@ -270,6 +366,7 @@ impl TypedValue for PythonInterpreterConfigValue {
                 | "import_time"
                 | "inspect"
                 | "install_signal_handlers"
+                | "int_max_str_digits"
                 | "interactive"
                 | "legacy_windows_stdio"
                 | "malloc_stats"
@@ -277,6 +374,7 @@ impl TypedValue for PythonInterpreterConfigValue {
                 | "optimization_level"
                 | "parser_debug"
                 | "pathconfig_warnings"
+                | "perf_profiling"
                 | "prefix"
                 | "program_name"
                 | "pycache_prefix"
@@ -285,6 +383,7 @@ impl TypedValue for PythonInterpreterConfigValue {
                 | "run_command"
                 | "run_filename"
                 | "run_module"
+                | "safe_path"
                 | "show_ref_count"
                 | "site_import"
                 | "skip_first_source_line"
@@ -302,15 +401,51 @@ impl TypedValue for PythonInterpreterConfigValue {
                 | "allocator_obj"
                 | "allocator_pymalloc_arena"
                 | "allocator_debug"
+                | "allocator_stats"
                 | "oxidized_importer"
                 | "filesystem_importer"
+                | "zipimport_importer"
+                | "extra_sys_paths"
+                | "extra_sys_paths_env"
                 | "argvb"
+                | "console"
+                | "console_argument"
                 | "multiprocessing_auto_dispatch"
                 | "multiprocessing_start_method"
+                | "multiprocessing_executable"
+                | "packed_resources_verification"
+                | "audit_events"
                 | "sys_frozen"
                 | "sys_meipass"
+                | "sys_executable"
+                | "sys_argv0"
                 | "terminfo_resolution"
                 | "write_modules_directory_env"
+                | "startup_profile_env"
+                | "host_requirements_check"
+                | "host_requirements_message"
+                | "sandbox_mode"
+                | "sandbox_allow_network"
+                | "sandbox_allow_subprocess"
+                | "sandbox_read_paths"
+                | "sandbox_write_paths"
+                | "migrations"
+                | "migrations_app_name"
+                | "setproctitle_module"
+                | "site_module"
+                | "lazy_import_packages"
+                | "filesystem_first_packages"
+                | "materialized_files_dir"
+                | "config_overrides_path"
+                | "config_overrides_allowed"
+                | "environment_overrides_prefix"
+                | "environment_overrides_allowed"
+                | "log_destination"
+                | "log_filter"
+                | "log_filter_env"
+                | "log_register_event_source"
+                | "traceback_source_paths"
+                | "replace_missing_stdio"
         ))
     }
 
@@ -426,6 +561,9 @@ impl TypedValue for PythonInterpreterConfigValue {
                     )
                 };
             }
+            "code_debug_ranges" => {
+                inner.config.code_debug_ranges = value.to_optional();
+            }
             "configure_c_stdio" => {
                 inner.config.configure_c_stdio = value.to_optional();
             }
@@ -462,6 +600,24 @@ impl TypedValue for PythonInterpreterConfigValue {
             "install_signal_handlers" => {
                 inner.config.install_signal_handlers = value.to_optional();
             }
+            "int_max_str_digits" => {
+                let digits: Option<i64> = value.try_to_optional()?;
+
+                if let Some(digits) = digits {
+                    if digits != 0 && !(640..=i32::MAX as i64).contains(&digits) {
+                        return Err(ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: format!(
+                                "int_max_str_digits must be 0 or at least 640; got {}",
+                                digits
+                            ),
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        }));
+                    }
+                }
+
+                inner.config.int_max_str_digits = digits;
+            }
             "interactive" => {
                 inner.config.interactive = value.to_optional();
             }
@@ -492,6 +648,9 @@ impl TypedValue for PythonInterpreterConfigValue {
             "pathconfig_warnings" => {
                 inner.config.pathconfig_warnings = value.to_optional();
             }
+            "perf_profiling" => {
+                inner.config.perf_profiling = value.to_optional();
+            }
             "prefix" => {
                 inner.config.prefix = value.to_optional();
             }
@@ -516,6 +675,9 @@ impl TypedValue for PythonInterpreterConfigValue {
             "run_module" => {
                 inner.config.run_module = value.to_optional();
             }
+            "safe_path" => {
+                inner.config.safe_path = value.to_optional();
+            }
             "show_ref_count" => {
                 inner.config.show_ref_count = value.to_optional();
             }
@@ -574,15 +736,38 @@ impl TypedValue for PythonInterpreterConfigValue {
             "allocator_debug" => {
                 inner.allocator_debug = value.to_bool();
             }
+            "allocator_stats" => {
+                inner.allocator_stats = value.to_bool();
+            }
             "oxidized_importer" => {
                 inner.oxidized_importer = value.to_bool();
             }
             "filesystem_importer" => {
                 inner.filesystem_importer = value.to_bool();
             }
+            "zipimport_importer" => {
+                inner.zipimport_importer = value.to_bool();
+            }
+            "extra_sys_paths" => {
+                required_list_arg(attribute, "string", &value)?;
+                inner.extra_sys_paths = value
+                    .iter()?
+                    .iter()
+                    .map(|x| PathBuf::from(x.to_string()))
+                    .collect();
+            }
+            "extra_sys_paths_env" => {
+                inner.extra_sys_paths_env = value.to_optional();
+            }
             "argvb" => {
                 inner.argvb = value.to_bool();
             }
+            "console" => {
+                inner.console = value.to_bool();
+            }
+            "console_argument" => {
+                inner.console_argument = value.to_optional();
+            }
             "multiprocessing_auto_dispatch" => {
                 inner.multiprocessing_auto_dispatch = value.to_bool();
             }
@@ -598,12 +783,53 @@ impl TypedValue for PythonInterpreterConfigValue {
                     })
                 })?;
             }
+            "packed_resources_verification" => {
+                inner.packed_resources_verification = PackedResourcesVerification::from_str(
+                    value.to_string().as_str(),
+                )
+                .map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: e,
+                        label: format!("{}.{}", Self::TYPE, attribute),
+                    })
+                })?;
+            }
+            "audit_events" => {
+                required_list_arg(attribute, "string", &value)?;
+                inner.audit_events = value.iter()?.iter().map(|x| x.to_string()).collect();
+            }
             "sys_frozen" => {
                 inner.sys_frozen = value.to_bool();
             }
             "sys_meipass" => {
                 inner.sys_meipass = value.to_bool();
             }
+            "sys_executable" | "sys_argv0" | "multiprocessing_executable" => {
+                let program_path = ProgramPath::try_from(value.to_string().as_str())
+                    .and_then(|program_path| {
+                        if attribute == "multiprocessing_executable"
+                            && program_path == ProgramPath::Empty
+                        {
+                            Err("multiprocessing executable cannot be empty".to_string())
+                        } else {
+                            Ok(program_path)
+                        }
+                    })
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: e,
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?;
+
+                match attribute {
+                    "sys_executable" => inner.sys_executable = program_path,
+                    "sys_argv0" => inner.sys_argv0 = program_path,
+                    _ => inner.multiprocessing_executable = program_path,
+                }
+            }
             "terminfo_resolution" => {
                 inner.terminfo_resolution =
                     TerminfoResolution::try_from(value.to_string().as_str()).map_err(|e| {
@@ -617,6 +843,120 @@ impl TypedValue for PythonInterpreterConfigValue {
             "write_modules_directory_env" => {
                 inner.write_modules_directory_env = value.to_optional();
             }
+            "startup_profile_env" => {
+                inner.startup_profile_env = value.to_optional();
+            }
+            "host_requirements_check" => {
+                inner.host_requirements_check = value.to_bool();
+            }
+            "host_requirements_message" => {
+                inner.host_requirements.message = value.to_optional();
+            }
+            "sandbox_mode" => {
+                inner.sandbox_mode =
+                    SandboxMode::from_str(value.to_string().as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: e,
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?;
+            }
+            "sandbox_allow_network" => {
+                inner.sandbox_policy.allow_network = value.to_bool();
+            }
+            "sandbox_allow_subprocess" => {
+                inner.sandbox_policy.allow_subprocess = value.to_bool();
+            }
+            "sandbox_read_paths" => {
+                inner.sandbox_policy.read_paths = value.try_to_optional()?;
+            }
+            "sandbox_write_paths" => {
+                inner.sandbox_policy.write_paths = value.try_to_optional()?;
+            }
+            "migrations" => {
+                required_list_arg(attribute, "string", &value)?;
+                inner.migrations = value
+                    .iter()?
+                    .iter()
+                    .map(|x| {
+                        Migration::try_from(x.to_string().as_str()).map_err(|e| {
+                            ValueError::from(RuntimeError {
+                                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                                message: e,
+                                label: format!("{}.{}", Self::TYPE, attribute),
+                            })
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+            }
+            "migrations_app_name" => {
+                inner.migrations_app_name = value.to_optional();
+            }
+            "setproctitle_module" => {
+                inner.setproctitle_module = value.to_bool();
+            }
+            "site_module" => {
+                inner.site_module = value.to_optional();
+            }
+            "lazy_import_packages" => {
+                required_list_arg(attribute, "string", &value)?;
+                inner.lazy_import_packages = value.iter()?.iter().map(|x| x.to_string()).collect();
+            }
+            "filesystem_first_packages" => {
+                required_list_arg(attribute, "string", &value)?;
+                inner.filesystem_first_packages =
+                    value.iter()?.iter().map(|x| x.to_string()).collect();
+            }
+            "materialized_files_dir" => {
+                inner.materialized_files_dir = value.to_optional();
+            }
+            "config_overrides_path" => {
+                inner.config_overrides_path = value.to_optional();
+            }
+            "config_overrides_allowed" => {
+                required_list_arg(attribute, "string", &value)?;
+                inner.config_overrides_allowed =
+                    value.iter()?.iter().map(|x| x.to_string()).collect();
+            }
+            "environment_overrides_prefix" => {
+                inner.environment_overrides_prefix = value.to_optional();
+            }
+            "environment_overrides_allowed" => {
+                required_list_arg(attribute, "string", &value)?;
+                inner.environment_overrides_allowed =
+                    value.iter()?.iter().map(|x| x.to_string()).collect();
+            }
+            "log_destination" => {
+                inner.log_destination = if value.get_type() == "NoneType" {
+                    None
+                } else {
+                    Some(
+                        LogDestination::try_from(value.to_string().as_str()).map_err(|e| {
+                            ValueError::from(RuntimeError {
+                                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                                message: e,
+                                label: format!("{}.{}", Self::TYPE, attribute),
+                            })
+                        })?,
+                    )
+                };
+            }
+            "log_filter" => {
+                inner.log_filter = value.to_optional();
+            }
+            "log_filter_env" => {
+                inner.log_filter_env = value.to_optional();
+            }
+            "log_register_event_source" => {
+                inner.log_register_event_source = value.to_bool();
+            }
+            "traceback_source_paths" => {
+                inner.traceback_source_paths = value.to_bool();
+            }
+            "replace_missing_stdio" => {
+                inner.replace_missing_stdio = value.to_bool();
+            }
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: UnsupportedOperation::SetAttr(attr.to_string()),
@@ -827,6 +1167,17 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_code_debug_ranges() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.code_debug_ranges == None")?;
+        env.eval("config.code_debug_ranges = False")?;
+        eval_assert(&mut env, "config.code_debug_ranges == False")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_configure_c_stdio() -> Result<()> {
         let mut env = get_env()?;
@@ -935,6 +1286,21 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_int_max_str_digits() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.int_max_str_digits == None")?;
+        env.eval("config.int_max_str_digits = 0")?;
+        eval_assert(&mut env, "config.int_max_str_digits == 0")?;
+        env.eval("config.int_max_str_digits = 10000")?;
+        eval_assert(&mut env, "config.int_max_str_digits == 10000")?;
+        assert!(env.eval("config.int_max_str_digits = 100").is_err());
+        assert!(env.eval("config.int_max_str_digits = '10000'").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_interactive() -> Result<()> {
         let mut env = get_env()?;
@@ -1017,6 +1383,17 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_perf_profiling() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.perf_profiling == None")?;
+        env.eval("config.perf_profiling = True")?;
+        eval_assert(&mut env, "config.perf_profiling == True")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_prefix() -> Result<()> {
         let mut env = get_env()?;
@@ -1089,6 +1466,17 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_safe_path() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.safe_path == None")?;
+        env.eval("config.safe_path = True")?;
+        eval_assert(&mut env, "config.safe_path == True")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_show_ref_count() -> Result<()> {
         let mut env = get_env()?;
@@ -1278,6 +1666,18 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_allocator_stats() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.allocator_stats == False")?;
+
+        env.eval("config.allocator_stats = True")?;
+        eval_assert(&mut env, "config.allocator_stats == True")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_oxidized_importer() -> Result<()> {
         let mut env = get_env()?;
@@ -1296,6 +1696,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_zipimport_importer() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.zipimport_importer == False")?;
+        env.eval("config.zipimport_importer = True")?;
+        eval_assert(&mut env, "config.zipimport_importer == True")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extra_sys_paths() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.extra_sys_paths == []")?;
+        env.eval("config.extra_sys_paths = ['$ORIGIN/plugins']")?;
+        eval_assert(&mut env, "config.extra_sys_paths == ['$ORIGIN/plugins']")?;
+        assert!(env
+            .eval("config.extra_sys_paths = '$ORIGIN/plugins'")
+            .is_err());
+
+        eval_assert(&mut env, "config.extra_sys_paths_env == None")?;
+        env.eval("config.extra_sys_paths_env = 'APP_PLUGINS_PATH'")?;
+        eval_assert(&mut env, "config.extra_sys_paths_env == 'APP_PLUGINS_PATH'")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_argvb() -> Result<()> {
         let mut env = get_env()?;
@@ -1305,6 +1734,25 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_console() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.console == False")?;
+        eval_assert(&mut env, "config.console_argument == None")?;
+
+        env.eval("config.console = True")?;
+        eval_assert(&mut env, "config.console == True")?;
+
+        env.eval("config.console_argument = '--console'")?;
+        eval_assert(&mut env, "config.console_argument == '--console'")?;
+
+        env.eval("config.console_argument = None")?;
+        eval_assert(&mut env, "config.console_argument == None")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_multiprocessing_auto_dispatch() -> Result<()> {
         let mut env = get_env()?;
@@ -1344,6 +1792,43 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_packed_resources_verification() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.packed_resources_verification == 'none'")?;
+
+        env.eval("config.packed_resources_verification = 'on-load'")?;
+        eval_assert(
+            &mut env,
+            "config.packed_resources_verification == 'on-load'",
+        )?;
+
+        env.eval("config.packed_resources_verification = 'startup'")?;
+        eval_assert(
+            &mut env,
+            "config.packed_resources_verification == 'startup'",
+        )?;
+
+        assert!(env
+            .eval("config.packed_resources_verification = 'always'")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit_events() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.audit_events == []")?;
+        env.eval("config.audit_events = ['import', 'subprocess']")?;
+        eval_assert(&mut env, "config.audit_events == ['import', 'subprocess']")?;
+        assert!(env.eval("config.audit_events = 'import'").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_sys_frozen() -> Result<()> {
         let mut env = get_env()?;
@@ -1362,6 +1847,46 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_sys_executable() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.sys_executable == 'default'")?;
+        eval_assert(&mut env, "config.sys_argv0 == 'default'")?;
+
+        env.eval("config.sys_executable = 'empty'")?;
+        eval_assert(&mut env, "config.sys_executable == 'empty'")?;
+
+        env.eval("config.sys_argv0 = 'static:$ORIGIN/app'")?;
+        eval_assert(&mut env, "config.sys_argv0 == 'static:$ORIGIN/app'")?;
+
+        assert!(env.eval("config.sys_executable = 'real'").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiprocessing_executable() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.multiprocessing_executable == 'default'")?;
+
+        env.eval("config.multiprocessing_executable = 'invoked'")?;
+        eval_assert(&mut env, "config.multiprocessing_executable == 'invoked'")?;
+
+        env.eval("config.multiprocessing_executable = 'static:$ORIGIN/worker'")?;
+        eval_assert(
+            &mut env,
+            "config.multiprocessing_executable == 'static:$ORIGIN/worker'",
+        )?;
+
+        assert!(env
+            .eval("config.multiprocessing_executable = 'empty'")
+            .is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_terminfo_resolution() -> Result<()> {
         let mut env = get_env()?;
@@ -1385,4 +1910,278 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_startup_profile_env() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.startup_profile_env == None")?;
+
+        env.eval("config.startup_profile_env = 'APP_STARTUP_PROFILE'")?;
+        eval_assert(
+            &mut env,
+            "config.startup_profile_env == 'APP_STARTUP_PROFILE'",
+        )?;
+
+        env.eval("config.startup_profile_env = None")?;
+        eval_assert(&mut env, "config.startup_profile_env == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_host_requirements() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.host_requirements_check == False")?;
+        eval_assert(&mut env, "config.host_requirements_message == None")?;
+
+        env.eval("config.host_requirements_check = True")?;
+        eval_assert(&mut env, "config.host_requirements_check == True")?;
+
+        env.eval("config.host_requirements_message = 'download the baseline build'")?;
+        eval_assert(
+            &mut env,
+            "config.host_requirements_message == 'download the baseline build'",
+        )?;
+
+        env.eval("config.host_requirements_message = None")?;
+        eval_assert(&mut env, "config.host_requirements_message == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sandbox() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.sandbox_mode == 'off'")?;
+        eval_assert(&mut env, "config.sandbox_allow_network == False")?;
+        eval_assert(&mut env, "config.sandbox_allow_subprocess == False")?;
+        eval_assert(&mut env, "config.sandbox_read_paths == None")?;
+        eval_assert(&mut env, "config.sandbox_write_paths == None")?;
+
+        env.eval("config.sandbox_mode = 'enforce'")?;
+        eval_assert(&mut env, "config.sandbox_mode == 'enforce'")?;
+        assert!(env.eval("config.sandbox_mode = 'bad'").is_err());
+
+        env.eval("config.sandbox_allow_network = True")?;
+        eval_assert(&mut env, "config.sandbox_allow_network == True")?;
+        env.eval("config.sandbox_allow_subprocess = True")?;
+        eval_assert(&mut env, "config.sandbox_allow_subprocess == True")?;
+
+        env.eval("config.sandbox_read_paths = ['/etc/app']")?;
+        eval_assert(&mut env, "config.sandbox_read_paths == ['/etc/app']")?;
+        env.eval("config.sandbox_write_paths = ['$ORIGIN/data']")?;
+        eval_assert(&mut env, "config.sandbox_write_paths == ['$ORIGIN/data']")?;
+        env.eval("config.sandbox_write_paths = None")?;
+        eval_assert(&mut env, "config.sandbox_write_paths == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrations() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.migrations == []")?;
+        eval_assert(&mut env, "config.migrations_app_name == None")?;
+
+        env.eval("config.migrations = ['1.1=myapp.migrations:move_cache']")?;
+        eval_assert(
+            &mut env,
+            "config.migrations == ['1.1=myapp.migrations:move_cache']",
+        )?;
+        assert!(env
+            .eval("config.migrations = ['myapp.migrations:move_cache']")
+            .is_err());
+
+        env.eval("config.migrations_app_name = 'myapp'")?;
+        eval_assert(&mut env, "config.migrations_app_name == 'myapp'")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_setproctitle_module() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.setproctitle_module == False")?;
+        env.eval("config.setproctitle_module = True")?;
+        eval_assert(&mut env, "config.setproctitle_module == True")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_site_module() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.site_module == None")?;
+        env.eval("config.site_module = 'import sys'")?;
+        eval_assert(&mut env, "config.site_module == 'import sys'")?;
+        env.eval("config.site_module = None")?;
+        eval_assert(&mut env, "config.site_module == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lazy_import_packages() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.lazy_import_packages == []")?;
+        env.eval("config.lazy_import_packages = ['foo', 'bar.baz']")?;
+        eval_assert(
+            &mut env,
+            "config.lazy_import_packages == ['foo', 'bar.baz']",
+        )?;
+        assert!(env.eval("config.lazy_import_packages = 'foo'").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_materialized_files_dir() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.materialized_files_dir == None")?;
+        env.eval("config.materialized_files_dir = '$ORIGIN/materialized'")?;
+        eval_assert(
+            &mut env,
+            "config.materialized_files_dir == '$ORIGIN/materialized'",
+        )?;
+        env.eval("config.materialized_files_dir = None")?;
+        eval_assert(&mut env, "config.materialized_files_dir == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_filesystem_first_packages() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.filesystem_first_packages == []")?;
+        env.eval("config.filesystem_first_packages = ['foo', 'bar.baz']")?;
+        eval_assert(
+            &mut env,
+            "config.filesystem_first_packages == ['foo', 'bar.baz']",
+        )?;
+        assert!(env
+            .eval("config.filesystem_first_packages = 'foo'")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_overrides() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.config_overrides_path == None")?;
+        eval_assert(&mut env, "config.config_overrides_allowed == []")?;
+        env.eval("config.config_overrides_path = '$ORIGIN/app.toml'")?;
+        env.eval("config.config_overrides_allowed = ['verbose', 'run_module']")?;
+        eval_assert(
+            &mut env,
+            "config.config_overrides_path == '$ORIGIN/app.toml'",
+        )?;
+        eval_assert(
+            &mut env,
+            "config.config_overrides_allowed == ['verbose', 'run_module']",
+        )?;
+        assert!(env
+            .eval("config.config_overrides_allowed = 'verbose'")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_environment_overrides() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.environment_overrides_prefix == None")?;
+        eval_assert(&mut env, "config.environment_overrides_allowed == []")?;
+        env.eval("config.environment_overrides_prefix = 'MYAPP'")?;
+        env.eval("config.environment_overrides_allowed = ['verbose']")?;
+        eval_assert(&mut env, "config.environment_overrides_prefix == 'MYAPP'")?;
+        eval_assert(
+            &mut env,
+            "config.environment_overrides_allowed == ['verbose']",
+        )?;
+        assert!(env
+            .eval("config.environment_overrides_allowed = 'verbose'")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_destination() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.log_destination == None")?;
+        eval_assert(&mut env, "config.log_filter == None")?;
+        eval_assert(&mut env, "config.log_filter_env == None")?;
+
+        env.eval("config.log_destination = 'file:$ORIGIN/app.log'")?;
+        env.eval("config.log_filter = 'info,app=debug'")?;
+        env.eval("config.log_filter_env = 'RUST_LOG'")?;
+        eval_assert(&mut env, "config.log_destination == 'file:$ORIGIN/app.log'")?;
+        eval_assert(&mut env, "config.log_filter == 'info,app=debug'")?;
+        eval_assert(&mut env, "config.log_filter_env == 'RUST_LOG'")?;
+
+        env.eval("config.log_destination = 'journald'")?;
+        eval_assert(&mut env, "config.log_destination == 'journald'")?;
+
+        eval_assert(&mut env, "config.log_register_event_source == False")?;
+        env.eval("config.log_destination = 'windows-event-log:My Service'")?;
+        env.eval("config.log_register_event_source = True")?;
+        eval_assert(
+            &mut env,
+            "config.log_destination == 'windows-event-log:My Service'",
+        )?;
+        eval_assert(&mut env, "config.log_register_event_source == True")?;
+
+        env.eval("config.log_destination = 'etw:9e4a3b8c-7d21-4f6a-b0c5-2e8d1f7a6c34'")?;
+        eval_assert(
+            &mut env,
+            "config.log_destination == 'etw:9e4a3b8c-7d21-4f6a-b0c5-2e8d1f7a6c34'",
+        )?;
+        assert!(env
+            .eval("config.log_destination = 'etw:my-provider'")
+            .is_err());
+
+        env.eval("config.log_destination = None")?;
+        eval_assert(&mut env, "config.log_destination == None")?;
+
+        assert!(env.eval("config.log_destination = 'syslog'").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traceback_source_paths() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.traceback_source_paths == False")?;
+
+        env.eval("config.traceback_source_paths = True")?;
+        eval_assert(&mut env, "config.traceback_source_paths == True")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_missing_stdio() -> Result<()> {
+        let mut env = get_env()?;
+
+        eval_assert(&mut env, "config.replace_missing_stdio == False")?;
+
+        env.eval("config.replace_missing_stdio = True")?;
+        eval_assert(&mut env, "config.replace_missing_stdio == True")?;
+
+        Ok(())
+    }
 }
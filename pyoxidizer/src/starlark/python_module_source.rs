@@ -220,7 +220,11 @@ mod tests {
 
         assert_eq!(
             m.get_attr("add_location_fallback").unwrap().get_type(),
-            if dist.supports_in_memory_shared_library_loading() {
+            if dist
+                .create_packaging_policy()?
+                .resources_location_fallback()
+                .is_some()
+            {
                 "string"
             } else {
                 "NoneType"
@@ -311,6 +315,28 @@ mod tests {
             .unwrap()
             .to_bool());
 
+        assert!(m.has_attr("add_compression").unwrap());
+        assert_eq!(m.get_attr("add_compression").unwrap().to_str(), "none");
+        m.set_attr("add_compression", Value::from("zstd:10"))
+            .unwrap();
+        assert_eq!(m.get_attr("add_compression").unwrap().to_str(), "zstd:10");
+        assert!(m.set_attr("add_compression", Value::from("bogus")).is_err());
+
+        assert!(m.has_attr("add_bytecode_stripping").unwrap());
+        assert_eq!(
+            m.get_attr("add_bytecode_stripping").unwrap().to_str(),
+            "none"
+        );
+        m.set_attr("add_bytecode_stripping", Value::from("docstrings"))
+            .unwrap();
+        assert_eq!(
+            m.get_attr("add_bytecode_stripping").unwrap().to_str(),
+            "docstrings"
+        );
+        assert!(m
+            .set_attr("add_bytecode_stripping", Value::from("bogus"))
+            .is_err());
+
         Ok(())
     }
 }
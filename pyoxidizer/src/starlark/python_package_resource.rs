@@ -3,7 +3,7 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    super::python_resource::ResourceCollectionContext,
+    super::{python_resource::ResourceCollectionContext, util::ToValue},
     python_packaging::{
         resource::{PythonPackageResource, PythonResource},
         resource_collection::PythonResourceAddCollectionContext,
@@ -12,6 +12,7 @@ use {
         error::{RuntimeError, UnsupportedOperation, ValueError},
         {Mutable, TypedValue, Value, ValueResult},
     },
+    starlark_dialect_build_targets::optional_str_arg,
     std::sync::{Arc, Mutex, MutexGuard},
 };
 
@@ -115,6 +116,7 @@ impl TypedValue for PythonPackageResourceValue {
             "is_stdlib" => Value::from(inner.r.is_stdlib),
             "package" => Value::new(inner.r.leaf_package.clone()),
             "name" => Value::new(inner.r.relative_name.clone()),
+            "locale" => inner.r.locale.to_value(),
             // TODO expose raw data
             attr => {
                 drop(inner);
@@ -139,13 +141,18 @@ impl TypedValue for PythonPackageResourceValue {
             "is_stdlib" => true,
             "package" => true,
             "name" => true,
+            "locale" => true,
             // TODO expose raw data
             attr => self.add_collection_context_attrs().contains(&attr),
         })
     }
 
     fn set_attr(&mut self, attribute: &str, value: Value) -> Result<(), ValueError> {
-        if self.add_collection_context_attrs().contains(&attribute) {
+        if attribute == "locale" {
+            let locale = optional_str_arg("locale", &value)?;
+            self.inner("PythonPackageResource.locale")?.r.locale = locale;
+            Ok(())
+        } else if self.add_collection_context_attrs().contains(&attribute) {
             self.set_attr_add_collection_context(attribute, value)
         } else {
             Err(ValueError::OperationNotSupported {
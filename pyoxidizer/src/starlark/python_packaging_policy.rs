@@ -6,8 +6,14 @@ use {
     super::python_resource::ResourceCollectionContext,
     linked_hash_map::LinkedHashMap,
     python_packaging::{
+        bytecode::BytecodeStripping,
         location::ConcreteResourceLocation,
-        policy::{ExtensionModuleFilter, PythonPackagingPolicy, ResourceHandlingMode},
+        policy::{
+            CryptoLinkage, ExtensionModuleFilter, FrameworkDataHook, PythonPackagingPolicy,
+            ResourceHandlingMode, ResourceNamePattern, StaticRebuildMode, StdlibProfile,
+            TypeInformationMode,
+        },
+        resource::ResourceCompression,
     },
     starlark::{
         environment::TypeValues,
@@ -20,8 +26,9 @@ use {
             Mutable, TypedValue, Value, ValueResult,
         },
     },
-    starlark_dialect_build_targets::required_type_arg,
+    starlark_dialect_build_targets::{required_list_arg, required_type_arg},
     std::{
+        collections::HashMap,
         ops::Deref,
         sync::{Arc, Mutex, MutexGuard},
     },
@@ -103,6 +110,28 @@ impl PythonPackagingPolicyValue {
 
         Ok(Value::from(NoneType::None))
     }
+
+    /// Parse a list of strings into resource name patterns.
+    fn resource_name_patterns(
+        attribute: &str,
+        value: &Value,
+    ) -> Result<Vec<ResourceNamePattern>, ValueError> {
+        required_list_arg(attribute, "string", value)?;
+
+        value
+            .iter()?
+            .iter()
+            .map(|x| {
+                ResourceNamePattern::try_from(x.to_string().as_str()).map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: e,
+                        label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                    })
+                })
+            })
+            .collect()
+    }
 }
 
 impl TypedValue for PythonPackagingPolicyValue {
@@ -126,9 +155,33 @@ impl TypedValue for PythonPackagingPolicyValue {
             "bytecode_optimize_level_zero" => Value::from(inner.bytecode_optimize_level_zero()),
             "bytecode_optimize_level_one" => Value::from(inner.bytecode_optimize_level_one()),
             "bytecode_optimize_level_two" => Value::from(inner.bytecode_optimize_level_two()),
+            "bytecode_compression" => Value::from(inner.bytecode_compression().to_string()),
+            "crypto_linkage" => Value::from(inner.crypto_linkage().to_string()),
             "extension_module_filter" => Value::from(inner.extension_module_filter().as_ref()),
+            "exclude_patterns" => Value::from(
+                inner
+                    .exclude_patterns()
+                    .iter()
+                    .map(|p| Value::from(p.to_string()))
+                    .collect::<Vec<_>>(),
+            ),
             "file_scanner_classify_files" => Value::from(inner.file_scanner_classify_files()),
             "file_scanner_emit_files" => Value::from(inner.file_scanner_emit_files()),
+            "framework_data_hooks" => Value::from(
+                inner
+                    .framework_data_hooks()
+                    .iter()
+                    .map(|hook| Value::from(hook.as_ref()))
+                    .collect::<Vec<_>>(),
+            ),
+            "framework_data_location" => Value::from(inner.framework_data_location().to_string()),
+            "framework_data_packages" => Value::from(
+                inner
+                    .framework_data_packages()
+                    .iter()
+                    .map(|p| Value::from(p.as_str()))
+                    .collect::<Vec<_>>(),
+            ),
             "include_distribution_sources" => Value::from(inner.include_distribution_sources()),
             "include_distribution_resources" => Value::from(inner.include_distribution_resources()),
             "include_classified_resources" => Value::from(inner.include_classified_resources()),
@@ -136,7 +189,17 @@ impl TypedValue for PythonPackagingPolicyValue {
             "include_non_distribution_sources" => {
                 Value::from(inner.include_non_distribution_sources())
             }
+            "include_patterns" => Value::from(
+                inner
+                    .include_patterns()
+                    .iter()
+                    .map(|p| Value::from(p.to_string()))
+                    .collect::<Vec<_>>(),
+            ),
             "include_test" => Value::from(inner.include_test()),
+            "package_resources_compression" => {
+                Value::from(inner.package_resources_compression().to_string())
+            }
             "preferred_extension_module_variants" => {
                 Value::try_from(inner.preferred_extension_module_variants().clone())?
             }
@@ -145,6 +208,24 @@ impl TypedValue for PythonPackagingPolicyValue {
                 Some(location) => Value::from(location.to_string()),
                 None => Value::from(NoneType::None),
             },
+            "static_rebuild" => Value::from(inner.static_rebuild().as_ref()),
+            "stdlib_profile" => Value::from(inner.stdlib_profile().as_ref()),
+            "type_information" => Value::from(inner.type_information().to_string()),
+            "package_type_information" => Value::try_from(
+                inner
+                    .package_type_information()
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.to_string()))
+                    .collect::<HashMap<String, String>>(),
+            )?,
+            "bytecode_stripping" => Value::from(inner.bytecode_stripping().to_string()),
+            "package_bytecode_stripping" => Value::try_from(
+                inner
+                    .package_bytecode_stripping()
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.to_string()))
+                    .collect::<HashMap<String, String>>(),
+            )?,
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: UnsupportedOperation::GetAttr(attr.to_string()),
@@ -165,18 +246,32 @@ impl TypedValue for PythonPackagingPolicyValue {
                 | "bytecode_optimize_level_zero"
                 | "bytecode_optimize_level_one"
                 | "bytecode_optimize_level_two"
+                | "bytecode_compression"
+                | "crypto_linkage"
+                | "exclude_patterns"
                 | "extension_module_filter"
                 | "file_scanner_classify_files"
                 | "file_scanner_emit_files"
+                | "framework_data_hooks"
+                | "framework_data_location"
+                | "framework_data_packages"
                 | "include_distribution_sources"
                 | "include_distribution_resources"
                 | "include_classified_resources"
                 | "include_file_resources"
                 | "include_non_distribution_sources"
+                | "include_patterns"
                 | "include_test"
+                | "package_resources_compression"
                 | "preferred_extension_module_variants"
                 | "resources_location"
                 | "resources_location_fallback"
+                | "static_rebuild"
+                | "stdlib_profile"
+                | "type_information"
+                | "package_type_information"
+                | "bytecode_stripping"
+                | "package_bytecode_stripping"
         ))
     }
 
@@ -211,12 +306,53 @@ impl TypedValue for PythonPackagingPolicyValue {
 
                 inner.set_extension_module_filter(filter);
             }
+            "exclude_patterns" => {
+                let patterns = Self::resource_name_patterns(attribute, &value)?;
+                inner.set_exclude_patterns(patterns);
+            }
             "file_scanner_classify_files" => {
                 inner.set_file_scanner_classify_files(value.to_bool());
             }
             "file_scanner_emit_files" => {
                 inner.set_file_scanner_emit_files(value.to_bool());
             }
+            "framework_data_hooks" => {
+                required_list_arg(attribute, "string", &value)?;
+
+                let hooks = value
+                    .iter()?
+                    .iter()
+                    .map(|x| {
+                        FrameworkDataHook::try_from(x.to_string().as_str()).map_err(|e| {
+                            ValueError::from(RuntimeError {
+                                code: "PYOXIDIZER_BUILD",
+                                message: e,
+                                label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                            })
+                        })
+                    })
+                    .collect::<Result<Vec<_>, ValueError>>()?;
+
+                inner.set_framework_data_hooks(hooks);
+            }
+            "framework_data_location" => {
+                inner.set_framework_data_location(
+                    ConcreteResourceLocation::try_from(value.to_string().as_str()).map_err(
+                        |e| {
+                            ValueError::from(RuntimeError {
+                                code: "PYOXIDIZER_BUILD",
+                                message: e,
+                                label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                            })
+                        },
+                    )?,
+                );
+            }
+            "framework_data_packages" => {
+                required_list_arg(attribute, "string", &value)?;
+
+                inner.set_framework_data_packages(value.iter()?.iter().map(|x| x.to_string()));
+            }
             "include_classified_resources" => {
                 inner.set_include_classified_resources(value.to_bool());
             }
@@ -232,6 +368,10 @@ impl TypedValue for PythonPackagingPolicyValue {
             "include_non_distribution_sources" => {
                 inner.set_include_non_distribution_sources(value.to_bool());
             }
+            "include_patterns" => {
+                let patterns = Self::resource_name_patterns(attribute, &value)?;
+                inner.set_include_patterns(patterns);
+            }
             "include_test" => {
                 inner.set_include_test(value.to_bool());
             }
@@ -265,6 +405,88 @@ impl TypedValue for PythonPackagingPolicyValue {
                     ));
                 }
             }
+            "bytecode_compression" => {
+                let compression = ResourceCompression::try_from(value.to_string().as_str())
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: e,
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                        })
+                    })?;
+
+                inner.set_bytecode_compression(compression);
+            }
+            "crypto_linkage" => {
+                let linkage = CryptoLinkage::try_from(value.to_string().as_str()).map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: e,
+                        label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                    })
+                })?;
+
+                inner.set_crypto_linkage(linkage);
+            }
+            "package_resources_compression" => {
+                let compression = ResourceCompression::try_from(value.to_string().as_str())
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: e,
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                        })
+                    })?;
+
+                inner.set_package_resources_compression(compression);
+            }
+            "static_rebuild" => {
+                let mode =
+                    StaticRebuildMode::try_from(value.to_string().as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: e,
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                        })
+                    })?;
+
+                inner.set_static_rebuild(mode);
+            }
+            "stdlib_profile" => {
+                let profile = StdlibProfile::try_from(value.to_string().as_str()).map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: e,
+                        label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                    })
+                })?;
+
+                inner.set_stdlib_profile(profile);
+            }
+            "type_information" => {
+                let mode =
+                    TypeInformationMode::try_from(value.to_string().as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: e,
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                        })
+                    })?;
+
+                inner.set_type_information(mode);
+            }
+            "bytecode_stripping" => {
+                let stripping =
+                    BytecodeStripping::try_from(value.to_string().as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: e,
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                        })
+                    })?;
+
+                inner.set_bytecode_stripping(stripping);
+            }
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: UnsupportedOperation::SetAttr(attr.to_string()),
@@ -300,6 +522,48 @@ impl PythonPackagingPolicyValue {
         Ok(Value::from(NoneType::None))
     }
 
+    fn starlark_set_package_type_information(
+        &mut self,
+        package: String,
+        mode: String,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonPackagingPolicy.set_package_type_information()";
+
+        let mode = TypeInformationMode::try_from(mode.as_str()).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYTHON_PACKAGING_POLICY",
+                message: e,
+                label: LABEL.to_string(),
+            })
+        })?;
+
+        self.inner(LABEL)?
+            .set_package_type_information(&package, mode);
+
+        Ok(Value::from(NoneType::None))
+    }
+
+    fn starlark_set_package_bytecode_stripping(
+        &mut self,
+        package: String,
+        value: String,
+    ) -> ValueResult {
+        const LABEL: &str = "PythonPackagingPolicy.set_package_bytecode_stripping()";
+
+        let stripping = BytecodeStripping::try_from(value.as_str()).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYTHON_PACKAGING_POLICY",
+                message: e,
+                label: LABEL.to_string(),
+            })
+        })?;
+
+        self.inner(LABEL)?
+            .set_package_bytecode_stripping(&package, stripping);
+
+        Ok(Value::from(NoneType::None))
+    }
+
     fn starlark_set_resource_handling_mode(&mut self, value: String) -> ValueResult {
         const LABEL: &str = "PythonPackagingPolicy.set_resource_handling_mode()";
 
@@ -332,6 +596,16 @@ starlark_module! { python_packaging_policy_module =>
         this.starlark_set_preferred_extension_module_variant(name, value)
     }
 
+    PythonPackagingPolicy.set_package_type_information(this, package: String, mode: String) {
+        let mut this = this.downcast_mut::<PythonPackagingPolicyValue>().unwrap().unwrap();
+        this.starlark_set_package_type_information(package, mode)
+    }
+
+    PythonPackagingPolicy.set_package_bytecode_stripping(this, package: String, value: String) {
+        let mut this = this.downcast_mut::<PythonPackagingPolicyValue>().unwrap().unwrap();
+        this.starlark_set_package_bytecode_stripping(package, value)
+    }
+
     PythonPackagingPolicy.set_resource_handling_mode(this, mode: String) {
         let mut this = this.downcast_mut::<PythonPackagingPolicyValue>().unwrap().unwrap();
         this.starlark_set_resource_handling_mode(mode)
@@ -487,7 +761,7 @@ mod tests {
         assert_eq!(value.to_string(), "filesystem-relative:lib");
 
         let value = env.eval("policy.resources_location_fallback")?;
-        if dist_ref.supports_in_memory_shared_library_loading() {
+        if policy.resources_location_fallback().is_some() {
             assert_eq!(value.get_type(), "string");
             assert_eq!(value.to_string(), "filesystem-relative:lib");
         } else {
@@ -502,6 +776,66 @@ mod tests {
         )?;
         assert_eq!(value.get_type(), "NoneType");
 
+        let value = env.eval("policy.bytecode_compression")?;
+        assert_eq!(value.to_string(), "none");
+
+        let value =
+            env.eval("policy.bytecode_compression = 'zstd'; policy.bytecode_compression")?;
+        assert_eq!(value.to_string(), "zstd:3");
+
+        let value = env.eval(
+            "policy.package_resources_compression = 'zlib:9'; policy.package_resources_compression",
+        )?;
+        assert_eq!(value.to_string(), "zlib:9");
+
+        assert!(env.eval("policy.bytecode_compression = 'zstd:99'").is_err());
+
+        let value = env.eval("policy.static_rebuild")?;
+        assert_eq!(value.to_string(), "never");
+
+        let value = env.eval("policy.static_rebuild = 'if-available'; policy.static_rebuild")?;
+        assert_eq!(value.to_string(), "if-available");
+
+        assert!(env.eval("policy.static_rebuild = 'invalid'").is_err());
+
+        let value = env.eval("policy.stdlib_profile")?;
+        assert_eq!(value.to_string(), "full");
+
+        let value = env.eval("policy.stdlib_profile = 'no-gui'; policy.stdlib_profile")?;
+        assert_eq!(value.to_string(), "no-gui");
+
+        assert!(env.eval("policy.stdlib_profile = 'invalid'").is_err());
+
+        let value = env.eval("policy.crypto_linkage")?;
+        assert_eq!(value.to_string(), "distribution");
+
+        let value = env.eval("policy.crypto_linkage = 'system'; policy.crypto_linkage")?;
+        assert_eq!(value.to_string(), "system");
+
+        let value =
+            env.eval("policy.crypto_linkage = 'variant:libressl'; policy.crypto_linkage")?;
+        assert_eq!(value.to_string(), "variant:libressl");
+
+        assert!(env.eval("policy.crypto_linkage = 'variant:'").is_err());
+        assert!(env.eval("policy.crypto_linkage = 'invalid'").is_err());
+
+        let value = env.eval("policy.include_patterns")?;
+        assert_eq!(value.get_type(), "list");
+        assert_eq!(value.length().unwrap(), 0);
+
+        let value = env.eval(
+            "policy.include_patterns = ['myapp.*', 're:myapp_[a-z]+']; policy.include_patterns",
+        )?;
+        assert_eq!(value.to_string(), "[\"myapp.*\", \"re:myapp_[a-z]+\"]");
+
+        let value = env.eval(
+            "policy.exclude_patterns = ['*.tests.*', '*__pycache__/*']; policy.exclude_patterns",
+        )?;
+        assert_eq!(value.to_string(), "[\"*.tests.*\", \"*__pycache__/*\"]");
+
+        assert!(env.eval("policy.exclude_patterns = ['re:(']").is_err());
+        assert!(env.eval("policy.exclude_patterns = 'foo'").is_err());
+
         let value = env.eval("policy.allow_files")?;
         assert_eq!(value.get_type(), "bool");
         assert!(!value.to_bool());
@@ -518,6 +852,41 @@ mod tests {
         assert_eq!(value.get_type(), "bool");
         assert!(value.to_bool());
 
+        let value = env.eval("policy.framework_data_hooks")?;
+        assert_eq!(value.get_type(), "list");
+        assert_eq!(value.length().unwrap(), 0);
+
+        let value =
+            env.eval("policy.framework_data_hooks = ['django']; policy.framework_data_hooks")?;
+        assert_eq!(value.length().unwrap(), 1);
+        assert_eq!(value.at(Value::from(0)).unwrap().to_string(), "django");
+
+        assert!(env
+            .eval("policy.framework_data_hooks = ['invalid']")
+            .is_err());
+
+        let value = env.eval("policy.framework_data_location")?;
+        assert_eq!(value.to_string(), "filesystem-relative:lib");
+
+        let value = env.eval(
+            "policy.framework_data_location = 'filesystem-relative:data'; policy.framework_data_location",
+        )?;
+        assert_eq!(value.to_string(), "filesystem-relative:data");
+
+        let value = env.eval("policy.framework_data_packages")?;
+        assert_eq!(value.get_type(), "list");
+        assert_eq!(value.length().unwrap(), 0);
+
+        let value = env.eval(
+            "policy.framework_data_packages = ['myapp', 'blog']; policy.framework_data_packages",
+        )?;
+        assert_eq!(value.length().unwrap(), 2);
+        assert_eq!(value.at(Value::from(0)).unwrap().to_string(), "blog");
+
+        assert!(env
+            .eval("policy.framework_data_packages = 'myapp'")
+            .is_err());
+
         // bytecode_optimize_level_zero
         let value = env.eval("policy.bytecode_optimize_level_zero")?;
         assert_eq!(value.get_type(), "bool");
@@ -587,6 +956,83 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_type_information() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        let value = env.eval("policy.type_information")?;
+        assert_eq!(value.to_string(), "include");
+
+        let value = env.eval("policy.type_information = 'exclude'; policy.type_information")?;
+        assert_eq!(value.to_string(), "exclude");
+
+        let value = env.eval(
+            "policy.type_information = 'filesystem-relative:typed'; policy.type_information",
+        )?;
+        assert_eq!(value.to_string(), "filesystem-relative:typed");
+
+        assert!(env.eval("policy.type_information = 'in-memory'").is_err());
+
+        let value = env.eval("policy.package_type_information")?;
+        assert_eq!(value.get_type(), "dict");
+        assert_eq!(value.length().unwrap(), 0);
+
+        env.eval("policy.set_package_type_information('foo', 'include')")?;
+
+        let value = env.eval("policy.package_type_information")?;
+        assert_eq!(value.length().unwrap(), 1);
+        assert_eq!(
+            value.at(Value::from("foo")).unwrap(),
+            Value::from("include")
+        );
+
+        assert!(env
+            .eval("policy.set_package_type_information('foo', 'invalid')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytecode_stripping() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        let value = env.eval("policy.bytecode_stripping")?;
+        assert_eq!(value.to_string(), "none");
+
+        let value = env.eval(
+            "policy.bytecode_stripping = 'annotations,docstrings'; policy.bytecode_stripping",
+        )?;
+        assert_eq!(value.to_string(), "docstrings,annotations");
+
+        assert!(env.eval("policy.bytecode_stripping = 'asserts'").is_err());
+
+        let value = env.eval("policy.package_bytecode_stripping")?;
+        assert_eq!(value.get_type(), "dict");
+        assert_eq!(value.length().unwrap(), 0);
+
+        env.eval("policy.set_package_bytecode_stripping('numpy', 'docstrings')")?;
+
+        let value = env.eval("policy.package_bytecode_stripping")?;
+        assert_eq!(value.length().unwrap(), 1);
+        assert_eq!(
+            value.at(Value::from("numpy")).unwrap(),
+            Value::from("docstrings")
+        );
+
+        assert!(env
+            .eval("policy.set_package_bytecode_stripping('numpy', 'invalid')")
+            .is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_register_resource_callback() -> Result<()> {
         let mut env = test_evaluation_context_builder()?.into_context()?;
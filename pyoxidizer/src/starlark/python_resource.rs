@@ -11,7 +11,9 @@ use {
         python_packaging_policy::PythonPackagingPolicyValue,
     },
     python_packaging::{
-        location::ConcreteResourceLocation, resource::PythonResource,
+        bytecode::BytecodeStripping,
+        location::ConcreteResourceLocation,
+        resource::{PythonResource, ResourceCompression},
         resource_collection::PythonResourceAddCollectionContext,
     },
     starlark::{
@@ -125,6 +127,8 @@ pub trait ResourceCollectionContext {
             "add_bytecode_optimization_level_zero",
             "add_bytecode_optimization_level_one",
             "add_bytecode_optimization_level_two",
+            "add_compression",
+            "add_bytecode_stripping",
         ]
     }
 
@@ -147,6 +151,8 @@ pub trait ResourceCollectionContext {
                 "add_bytecode_optimization_level_zero" => Value::new(context.optimize_level_zero),
                 "add_bytecode_optimization_level_one" => Value::new(context.optimize_level_one),
                 "add_bytecode_optimization_level_two" => Value::new(context.optimize_level_two),
+                "add_compression" => Value::from(context.compression.to_string()),
+                "add_bytecode_stripping" => Value::from(context.bytecode_stripping.to_string()),
                 "add_include" => Value::new(context.include),
                 "add_location" => Value::new::<String>(context.location.into()),
                 "add_location_fallback" => match context.location_fallback.as_ref() {
@@ -184,6 +190,32 @@ pub trait ResourceCollectionContext {
                         context.optimize_level_two = value.to_bool();
                         Ok(())
                     }
+                    "add_compression" => {
+                        context.compression =
+                            ResourceCompression::try_from(value.to_string().as_str()).map_err(
+                                |e| {
+                                    ValueError::from(RuntimeError {
+                                        code: "PYOXIDIZER_BUILD",
+                                        message: e,
+                                        label: format!("{} = {}", attribute, value),
+                                    })
+                                },
+                            )?;
+                        Ok(())
+                    }
+                    "add_bytecode_stripping" => {
+                        context.bytecode_stripping =
+                            BytecodeStripping::try_from(value.to_string().as_str()).map_err(
+                                |e| {
+                                    ValueError::from(RuntimeError {
+                                        code: "PYOXIDIZER_BUILD",
+                                        message: e,
+                                        label: format!("{} = {}", attribute, value),
+                                    })
+                                },
+                            )?;
+                        Ok(())
+                    }
                     "add_include" => {
                         context.include = value.to_bool();
                         Ok(())
@@ -0,0 +1,296 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Record types: structs whose fields are declared and type checked. */
+
+use {
+    linked_hash_map::LinkedHashMap,
+    starlark::{
+        environment::TypeValues,
+        eval::call_stack::CallStack,
+        values::{
+            error::{RuntimeError, UnsupportedOperation, ValueError},
+            string::rc::RcString,
+            Immutable, TypedValue, Value, ValueResult,
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    std::{fmt, fmt::Write as _, rc::Rc},
+};
+
+fn record_error(message: String, label: &str) -> ValueError {
+    ValueError::from(RuntimeError {
+        code: "PYOXIDIZER_RECORD",
+        message,
+        label: label.to_string(),
+    })
+}
+
+/// Field declarations of a record type.
+///
+/// Keys are field names. Values are the names of the types, as returned by
+/// `type()`, the field accepts. `None` accepts values of any type.
+type RecordFields = LinkedHashMap<String, Option<Vec<String>>>;
+
+/// A record type, created by `record()`.
+///
+/// Calling it constructs a `Record`.
+pub struct RecordType {
+    fields: Rc<RecordFields>,
+}
+
+impl RecordType {
+    fn new(fields: LinkedHashMap<RcString, Value>) -> Result<Self, ValueError> {
+        let fields = fields
+            .into_iter()
+            .map(|(name, types)| {
+                let types = match types.get_type() {
+                    "NoneType" => None,
+                    "string" => Some(vec![types.to_string()]),
+                    "list" => Some(
+                        types
+                            .iter()?
+                            .iter()
+                            .map(|t| {
+                                if t.get_type() == "string" {
+                                    Ok(t.to_string())
+                                } else {
+                                    Err(record_error(
+                                        format!("type of field {} must be a list of strings", name),
+                                        "record()",
+                                    ))
+                                }
+                            })
+                            .collect::<Result<Vec<_>, _>>()?,
+                    ),
+                    t => {
+                        return Err(record_error(
+                            format!("invalid type declaration for field {}: {}", name, t),
+                            "record()",
+                        ))
+                    }
+                };
+
+                Ok((name.to_string(), types))
+            })
+            .collect::<Result<_, ValueError>>()?;
+
+        Ok(Self {
+            fields: Rc::new(fields),
+        })
+    }
+
+    fn construct(&self, values: LinkedHashMap<String, Value>) -> Result<Record, ValueError> {
+        if let Some(name) = values.keys().find(|name| !self.fields.contains_key(*name)) {
+            return Err(record_error(
+                format!("record has no field {}", name),
+                "record",
+            ));
+        }
+
+        let mut fields = LinkedHashMap::new();
+
+        for (name, types) in self.fields.iter() {
+            let value = values
+                .get(name)
+                .ok_or_else(|| record_error(format!("missing field {}", name), "record"))?;
+
+            if let Some(types) = types {
+                if !types.iter().any(|t| t == value.get_type()) {
+                    return Err(record_error(
+                        format!(
+                            "field {} must be of type {}; got {}",
+                            name,
+                            types.join(" or "),
+                            value.get_type()
+                        ),
+                        "record",
+                    ));
+                }
+            }
+
+            fields.insert(name.clone(), value.clone());
+        }
+
+        Ok(Record {
+            record_type: self.fields.clone(),
+            fields,
+        })
+    }
+}
+
+impl TypedValue for RecordType {
+    type Holder = Immutable<RecordType>;
+    const TYPE: &'static str = "record_type";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+
+    fn to_repr_impl(&self, buf: &mut String) -> fmt::Result {
+        write!(buf, "record(")?;
+        for (i, (name, types)) in self.fields.iter().enumerate() {
+            if i != 0 {
+                write!(buf, ", ")?;
+            }
+            match types {
+                Some(types) if types.len() == 1 => write!(buf, "{}={:?}", name, types[0])?,
+                Some(types) => write!(buf, "{}={:?}", name, types)?,
+                None => write!(buf, "{}=None", name)?,
+            }
+        }
+        write!(buf, ")")
+    }
+
+    fn call(
+        &self,
+        _call_stack: &mut CallStack,
+        _type_values: &TypeValues,
+        positional: Vec<Value>,
+        named: LinkedHashMap<RcString, Value>,
+        args: Option<Value>,
+        kwargs: Option<Value>,
+    ) -> ValueResult {
+        if !positional.is_empty() || args.is_some() {
+            return Err(record_error(
+                "record fields must be passed by name".to_string(),
+                "record",
+            ));
+        }
+
+        let mut values = named
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect::<LinkedHashMap<_, _>>();
+
+        if let Some(kwargs) = kwargs {
+            for name in kwargs.iter()?.iter() {
+                let value = kwargs.at(name.clone())?;
+                values.insert(name.to_string(), value);
+            }
+        }
+
+        Ok(Value::new(self.construct(values)?))
+    }
+}
+
+/// An instance of a record type.
+pub struct Record {
+    record_type: Rc<RecordFields>,
+    fields: LinkedHashMap<String, Value>,
+}
+
+impl TypedValue for Record {
+    type Holder = Immutable<Record>;
+    const TYPE: &'static str = "record";
+
+    fn values_for_descendant_check_and_freeze<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = Value> + 'a> {
+        Box::new(self.fields.values().cloned())
+    }
+
+    fn to_repr_impl(&self, buf: &mut String) -> fmt::Result {
+        write!(buf, "record(")?;
+        for (i, (name, value)) in self.fields.iter().enumerate() {
+            if i != 0 {
+                write!(buf, ", ")?;
+            }
+            write!(buf, "{}=", name)?;
+            value.to_repr_impl(buf)?;
+        }
+        write!(buf, ")")
+    }
+
+    fn equals(&self, other: &Record) -> Result<bool, ValueError> {
+        if !Rc::ptr_eq(&self.record_type, &other.record_type) {
+            return Ok(false);
+        }
+
+        for (a, b) in self.fields.values().zip(other.fields.values()) {
+            if !a.equals(b)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        self.fields
+            .get(attribute)
+            .cloned()
+            .ok_or_else(|| ValueError::OperationNotSupported {
+                op: UnsupportedOperation::GetAttr(attribute.to_string()),
+                left: Self::TYPE.to_string(),
+                right: None,
+            })
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(self.fields.contains_key(attribute))
+    }
+
+    fn dir_attr(&self) -> Result<Vec<RcString>, ValueError> {
+        Ok(self
+            .fields
+            .keys()
+            .map(|k| RcString::from(k.as_str()))
+            .collect())
+    }
+}
+
+starlark_module! { record_module =>
+    record(**kwargs) {
+        Ok(Value::new(RecordType::new(kwargs)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testutil::*;
+
+    #[test]
+    fn test_record() {
+        let value = starlark_ok(
+            "Server = record(host='string', port=['int', 'NoneType'], extra=None)\n\
+             s = Server(host='localhost', port=80, extra=[])\n\
+             (s.host, s.port, dir(s), type(s))",
+        );
+        assert_eq!(
+            value.to_repr(),
+            "(\"localhost\", 80, [\"extra\", \"host\", \"port\"], \"record\")"
+        );
+
+        let value =
+            starlark_ok("repr(record(host='string', port=['int', 'NoneType'], extra=None))");
+        assert_eq!(
+            value.to_string(),
+            "record(host=\"string\", port=[\"int\", \"NoneType\"], extra=None)"
+        );
+
+        let value = starlark_ok(
+            "Server = record(host='string', port='int')\n\
+             Other = record(host='string', port='int')\n\
+             a = Server(host='localhost', port=80)\n\
+             (a == Server(port=80, **{'host': 'localhost'}), a == Server(host='localhost', port=81), a == Other(host='localhost', port=80))",
+        );
+        assert_eq!(value.to_repr(), "(True, False, False)");
+    }
+
+    #[test]
+    fn test_record_errors() {
+        starlark_nok("record(host=1)");
+        starlark_nok("record(host=[1])");
+
+        starlark_nok("record(host='string')('localhost')");
+        starlark_nok("record(host='string')()");
+        starlark_nok("record(host='string')(host=80)");
+        starlark_nok("record(host='string')(host='localhost', port=80)");
+        starlark_nok("record(host='string')(host='localhost').port");
+    }
+}
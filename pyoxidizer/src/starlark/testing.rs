@@ -0,0 +1,269 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Assertion functions for testing configuration files.
+
+These power the test files evaluated by `pyoxidizer config-test`. They
+raise an error when an assertion fails, which fails the calling test.
+*/
+
+use {
+    super::{
+        python_embedded_resources::PythonEmbeddedResourcesValue,
+        python_executable::PythonExecutableValue,
+    },
+    starlark::{
+        environment::TypeValues,
+        eval::call_stack::CallStack,
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            {Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{optional_str_arg, starlark_resolve_target},
+};
+
+fn assertion_error(label: &str, message: String, msg: &Value) -> Result<(), ValueError> {
+    let message = match optional_str_arg("msg", msg)? {
+        Some(msg) => format!("{}: {}", msg, message),
+        None => message,
+    };
+
+    Err(ValueError::from(RuntimeError {
+        code: "PYOXIDIZER_ASSERTION",
+        message,
+        label: label.to_string(),
+    }))
+}
+
+/// Obtain the names of resources held by a value.
+///
+/// Strings are treated as the name of a target to resolve.
+fn resource_names(
+    type_values: &TypeValues,
+    call_stack: &mut CallStack,
+    value: &Value,
+    label: &str,
+) -> Result<Vec<String>, ValueError> {
+    match value.get_type() {
+        "string" => {
+            let resolved = starlark_resolve_target(type_values, call_stack, value.to_str())?;
+            resource_names(type_values, call_stack, &resolved, label)
+        }
+        "PythonExecutable" => {
+            let exe = value
+                .downcast_ref::<PythonExecutableValue>()
+                .ok_or(ValueError::IncorrectParameterType)?;
+            let inner = exe.inner(label)?;
+
+            Ok(inner.iter_resources().map(|(name, _)| name.clone()).collect())
+        }
+        "PythonEmbeddedResources" => {
+            let resources = value
+                .downcast_ref::<PythonEmbeddedResourcesValue>()
+                .ok_or(ValueError::IncorrectParameterType)?;
+
+            Ok(resources
+                .exe
+                .iter_resources()
+                .map(|(name, _)| name.clone())
+                .collect())
+        }
+        "list" => value
+            .iter()?
+            .iter()
+            .map(|x| Ok(x.get_attr("name")?.to_str()))
+            .collect(),
+        t => Err(ValueError::from(RuntimeError {
+            code: "PYOXIDIZER_ASSERTION",
+            message: format!(
+                "expected a target name, PythonExecutable, PythonEmbeddedResources, or list of resources; got {}",
+                t
+            ),
+            label: label.to_string(),
+        })),
+    }
+}
+
+/// Whether resource names contain a resource or a member of a package.
+fn names_contain(names: &[String], name: &str) -> bool {
+    let prefix = format!("{}.", name);
+
+    names.iter().any(|x| x == name || x.starts_with(&prefix))
+}
+
+/// assert_eq(actual, expected, msg=None)
+fn starlark_assert_eq(actual: &Value, expected: &Value, msg: &Value) -> ValueResult {
+    if !actual.equals(expected)? {
+        assertion_error(
+            "assert_eq()",
+            format!("{} != {}", actual.to_repr(), expected.to_repr()),
+            msg,
+        )?;
+    }
+
+    Ok(Value::new(NoneType::None))
+}
+
+/// assert_true(value, msg=None)
+fn starlark_assert_true(value: &Value, msg: &Value) -> ValueResult {
+    if !value.to_bool() {
+        assertion_error(
+            "assert_true()",
+            format!("{} is not true", value.to_repr()),
+            msg,
+        )?;
+    }
+
+    Ok(Value::new(NoneType::None))
+}
+
+/// assert_resources_contains(value, name, msg=None)
+fn starlark_assert_resources_contains(
+    type_values: &TypeValues,
+    call_stack: &mut CallStack,
+    value: &Value,
+    name: String,
+    msg: &Value,
+) -> ValueResult {
+    const LABEL: &str = "assert_resources_contains()";
+
+    let names = resource_names(type_values, call_stack, value, LABEL)?;
+
+    if !names_contain(&names, &name) {
+        assertion_error(LABEL, format!("resource {} not found", name), msg)?;
+    }
+
+    Ok(Value::new(NoneType::None))
+}
+
+/// assert_resources_not_contains(value, name, msg=None)
+fn starlark_assert_resources_not_contains(
+    type_values: &TypeValues,
+    call_stack: &mut CallStack,
+    value: &Value,
+    name: String,
+    msg: &Value,
+) -> ValueResult {
+    const LABEL: &str = "assert_resources_not_contains()";
+
+    let names = resource_names(type_values, call_stack, value, LABEL)?;
+
+    if names_contain(&names, &name) {
+        assertion_error(LABEL, format!("resource {} found", name), msg)?;
+    }
+
+    Ok(Value::new(NoneType::None))
+}
+
+starlark_module! { testing_module =>
+    assert_eq(actual, expected, msg=NoneType::None) {
+        starlark_assert_eq(&actual, &expected, &msg)
+    }
+
+    assert_true(value, msg=NoneType::None) {
+        starlark_assert_true(&value, &msg)
+    }
+
+    assert_resources_contains(
+        env env,
+        call_stack cs,
+        value,
+        name: String,
+        msg=NoneType::None
+    ) {
+        starlark_assert_resources_contains(env, cs, &value, name, &msg)
+    }
+
+    assert_resources_not_contains(
+        env env,
+        call_stack cs,
+        value,
+        name: String,
+        msg=NoneType::None
+    ) {
+        starlark_assert_resources_not_contains(env, cs, &value, name, &msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::super::testutil::*, super::*, anyhow::Result};
+
+    #[test]
+    fn test_names_contain() {
+        let names = vec!["foo".to_string(), "bar.baz".to_string()];
+
+        assert!(names_contain(&names, "foo"));
+        assert!(names_contain(&names, "bar"));
+        assert!(names_contain(&names, "bar.baz"));
+        assert!(!names_contain(&names, "ba"));
+        assert!(!names_contain(&names, "foo.bar"));
+    }
+
+    #[test]
+    fn test_assert_eq() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("assert_eq(1 + 1, 2)")?;
+        env.eval("assert_eq(['a'], ['a'], msg = 'lists')")?;
+
+        let err = env
+            .eval("assert_eq('a', 'b', msg = 'strings')")
+            .unwrap_err();
+        assert!(err.to_string().contains("strings: \"a\" != \"b\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_assert_true() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        env.eval("assert_true(True)")?;
+        env.eval("assert_true([1])")?;
+        assert!(env.eval("assert_true(False)").is_err());
+        assert!(env.eval("assert_true([])").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_assert_resources_contains_incorrect_type() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+
+        assert!(env.eval("assert_resources_contains(42, 'foo')").is_err());
+        assert!(env
+            .eval("assert_resources_contains('missing', 'foo')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_assert_resources_contains() -> Result<()> {
+        let mut env = test_evaluation_context_builder()?.into_context()?;
+        add_exe(&mut env)?;
+
+        env.eval("assert_resources_contains(exe, 'email')")?;
+        env.eval("assert_resources_contains(exe, 'email.message')")?;
+        env.eval("assert_resources_not_contains(exe, 'does_not_exist')")?;
+        assert!(env
+            .eval("assert_resources_contains(exe, 'does_not_exist')")
+            .is_err());
+        assert!(env
+            .eval("assert_resources_not_contains(exe, 'email')")
+            .is_err());
+
+        env.eval("register_target('exe', lambda: exe)")?;
+        env.eval("assert_resources_contains('exe', 'email')")?;
+
+        Ok(())
+    }
+}
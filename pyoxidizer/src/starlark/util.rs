@@ -56,6 +56,15 @@ impl ToValue for Option<c_ulong> {
     }
 }
 
+impl ToValue for Option<i64> {
+    fn to_value(&self) -> Value {
+        match self {
+            Some(value) => Value::from(*value),
+            None => Value::from(NoneType::None),
+        }
+    }
+}
+
 impl ToValue for Option<Vec<String>> {
     fn to_value(&self) -> Value {
         match self {
@@ -0,0 +1,48 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! The `oxidized_importer.allocator` module. */
+
+use pyo3::prelude::*;
+
+/// Name of the module holding memory allocator functionality.
+const ALLOCATOR_NAME: &str = "oxidized_importer.allocator";
+
+const ALLOCATOR_DOC: &str = "Information about the memory allocator of the running binary.
+
+stats() returns None unless the binary was built by PyOxidizer with allocator
+statistics enabled.";
+
+/// Return statistics of the memory allocator.
+///
+/// This default implementation is replaced by embedding applications
+/// collecting statistics.
+#[pyfunction]
+fn stats(py: Python) -> PyObject {
+    py.None()
+}
+
+/// Define the function returning memory allocator statistics.
+///
+/// This replaces `stats` in the `allocator` submodule of an
+/// `oxidized_importer` module.
+pub fn set_allocator_stats_function(m: &PyModule, function: &PyAny) -> PyResult<()> {
+    m.getattr("allocator")?.setattr("stats", function)
+}
+
+pub(crate) fn init_module(py: Python, m: &PyModule) -> PyResult<()> {
+    let allocator = PyModule::new(py, ALLOCATOR_NAME)?;
+    allocator.setattr("__doc__", ALLOCATOR_DOC)?;
+    allocator.add_function(wrap_pyfunction!(stats, allocator)?)?;
+
+    m.add("allocator", allocator)?;
+
+    // Allow `import oxidized_importer.allocator` even though the parent
+    // isn't a package.
+    py.import("sys")?
+        .getattr("modules")?
+        .set_item(ALLOCATOR_NAME, allocator)?;
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! The `oxidized_importer.build_info` module. */
+
+use {
+    pyo3::{prelude::*, types::PyTuple},
+    python_packaging::interpreter::BuildInfo,
+};
+
+/// Name of the module holding build information.
+const BUILD_INFO_NAME: &str = "oxidized_importer.build_info";
+
+const BUILD_INFO_DOC: &str = "Information about how the running binary was built.
+
+Attributes are None when not running from a binary built by PyOxidizer.";
+
+/// Populate the attributes of the build info module.
+fn populate(py: Python, m: &PyModule, info: Option<&BuildInfo>) -> PyResult<()> {
+    m.setattr(
+        "target_triple",
+        info.map(|x| x.target_triple.as_str()).into_py(py),
+    )?;
+    m.setattr(
+        "python_version",
+        info.map(|x| x.python_version.as_str()).into_py(py),
+    )?;
+    m.setattr(
+        "python_flavor",
+        info.map(|x| x.python_flavor.as_str()).into_py(py),
+    )?;
+    m.setattr(
+        "resources_location",
+        info.map(|x| x.resources_location.as_str()).into_py(py),
+    )?;
+    m.setattr(
+        "resources_location_fallback",
+        info.and_then(|x| x.resources_location_fallback.as_deref())
+            .into_py(py),
+    )?;
+    m.setattr(
+        "features",
+        PyTuple::new(py, info.map(|x| x.features.as_slice()).unwrap_or_default()),
+    )?;
+
+    Ok(())
+}
+
+/// Define build information exposed by an `oxidized_importer` module.
+///
+/// This populates the `build_info` submodule of the module.
+pub fn set_build_info(py: Python, m: &PyModule, info: &BuildInfo) -> PyResult<()> {
+    let build_info = m.getattr("build_info")?.downcast::<PyModule>()?;
+
+    populate(py, build_info, Some(info))
+}
+
+pub(crate) fn init_module(py: Python, m: &PyModule) -> PyResult<()> {
+    let build_info = PyModule::new(py, BUILD_INFO_NAME)?;
+    build_info.setattr("__doc__", BUILD_INFO_DOC)?;
+    populate(py, build_info, None)?;
+
+    m.add("build_info", build_info)?;
+
+    // Allow `import oxidized_importer.build_info` even though the parent
+    // isn't a package.
+    py.import("sys")?
+        .getattr("modules")?
+        .set_item(BUILD_INFO_NAME, build_info)?;
+
+    Ok(())
+}
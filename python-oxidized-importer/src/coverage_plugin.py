@@ -0,0 +1,102 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+"""coverage.py plugin for modules imported from memory by OxidizedFinder.
+
+This file is compiled into the oxidized_importer extension and is evaluated
+by oxidized_importer.coverage_init() when coverage.py loads the plugin.
+"""
+
+import os
+import sys
+
+from coverage.files import canonical_filename
+from coverage.parser import PythonParser
+from coverage.plugin import CoveragePlugin, FileTracer
+from coverage.python import PythonFileReporter
+
+
+class OxidizedFileTracer(FileTracer):
+    def __init__(self, source_filename):
+        self._source_filename = source_filename
+
+    def source_filename(self):
+        return self._source_filename
+
+
+class OxidizedFileReporter(PythonFileReporter):
+    def __init__(self, filename, source, coverage):
+        super().__init__(filename, coverage)
+        self._source = source
+
+    @property
+    def parser(self):
+        # The stock parser reads the source from the filesystem.
+        if self._parser is None:
+            self._parser = PythonParser(
+                text=self._source,
+                filename=self.filename,
+                exclude=self.coverage._exclude_regex("exclude"),
+            )
+            self._parser.parse_source()
+
+        return self._parser
+
+
+class OxidizedCoveragePlugin(CoveragePlugin):
+    def __init__(self, finder_type):
+        self._finder_type = finder_type
+        self._coverage = None
+
+    def _finders(self):
+        return [f for f in sys.meta_path if isinstance(f, self._finder_type)]
+
+    def configure(self, config):
+        # coverage.py passes its Coverage instance, which file reporters need.
+        self._coverage = config
+
+    def file_tracer(self, filename):
+        # Code compiled by OxidizedFinder uses the module name as its filename,
+        # which coverage.py has resolved against the current directory.
+        if os.path.exists(filename):
+            return None
+
+        name = os.path.basename(filename)
+
+        for finder in self._finders():
+            if finder.get_source(name) is None:
+                continue
+
+            path = os.path.join(finder.path_hook_base_str, *name.split("."))
+            spec = finder.find_spec(name, None)
+            if spec.submodule_search_locations is not None:
+                path = os.path.join(path, "__init__.py")
+            else:
+                path += ".py"
+
+            return OxidizedFileTracer(path)
+
+        return None
+
+    def file_reporter(self, filename):
+        for finder in self._finders():
+            base = canonical_filename(finder.path_hook_base_str) + os.sep
+
+            if not filename.startswith(base) or not filename.endswith(".py"):
+                continue
+
+            parts = filename[len(base) : -len(".py")].split(os.sep)
+            if parts[-1] == "__init__":
+                parts.pop()
+
+            source = finder.get_source(".".join(parts))
+            if source is not None:
+                return OxidizedFileReporter(filename, source, self._coverage)
+
+        return "python"
+
+    def sys_info(self):
+        return [
+            ("finders", [f.path_hook_base_str for f in self._finders()]),
+        ]
@@ -0,0 +1,39 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! coverage.py plugin for modules imported from memory. */
+
+use {crate::importer::OxidizedFinder, pyo3::prelude::*};
+
+/// Python source code of the coverage.py plugin.
+const COVERAGE_PLUGIN_SOURCE: &str = include_str!("coverage_plugin.py");
+
+/// coverage.py plugin entrypoint.
+///
+/// This allows `plugins = oxidized_importer` in coverage.py's configuration.
+#[pyfunction]
+fn coverage_init(py: Python, reg: &PyAny, _options: &PyAny) -> PyResult<()> {
+    // Names starting with `<` aren't traced by coverage.py.
+    let module = PyModule::from_code(
+        py,
+        COVERAGE_PLUGIN_SOURCE,
+        "<oxidized_importer.coverage_plugin>",
+        "oxidized_importer.coverage_plugin",
+    )?;
+
+    let plugin = module
+        .getattr("OxidizedCoveragePlugin")?
+        .call1((py.get_type::<OxidizedFinder>(),))?;
+
+    reg.call_method1("add_file_tracer", (plugin,))?;
+    reg.call_method1("add_configurer", (plugin,))?;
+
+    Ok(())
+}
+
+pub(crate) fn init_module(m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(coverage_init, m)?)?;
+
+    Ok(())
+}
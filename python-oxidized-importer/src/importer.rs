@@ -9,6 +9,12 @@ This module defines a Python meta path importer and associated functionality
 for importing Python modules from memory.
 */
 
+#[cfg(target_os = "linux")]
+use crate::memory_so::{
+    load_shared_library_dependencies, memfd_from_data, memfd_path, retain_memfd,
+};
+#[cfg(feature = "wheel")]
+use crate::resource_scanning::wheel_resources;
 #[cfg(windows)]
 use {
     crate::memory_dll::{free_library_memory, get_proc_address_memory, load_library_memory},
@@ -31,11 +37,18 @@ use {
         exceptions::{PyImportError, PyValueError},
         ffi as pyffi,
         prelude::*,
-        types::{PyBytes, PyDict, PyList, PyString, PyTuple},
+        types::{PyBytes, PyCFunction, PyDict, PyList, PyString, PyTuple},
         AsPyPointer, FromPyPointer, PyNativeType, PyTraverseError, PyVisit,
     },
     python_packaging::resource::BytecodeOptimizationLevel,
-    std::sync::Arc,
+    std::{
+        path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    },
 };
 
 #[cfg(windows)]
@@ -85,14 +98,12 @@ fn extension_module_shared_library_create_module(
     // New module load request. Proceed to _PyImport_LoadDynamicModuleWithSpec()
     // functionality.
 
-    let module = unsafe { load_library_memory(resources_state, library_data) };
-
-    if module.is_null() {
-        return Err(PyImportError::new_err((
-            "unable to load extension module library from memory",
+    let module = unsafe { load_library_memory(resources_state, library_data) }.map_err(|e| {
+        PyImportError::new_err((
+            format!("unable to load extension module library from memory: {}", e),
             name.to_owned(),
-        )));
-    }
+        ))
+    })?;
 
     // Any error past this point should call `MemoryFreeLibrary()` to unload the
     // library.
@@ -105,7 +116,65 @@ fn extension_module_shared_library_create_module(
     })
 }
 
-#[cfg(unix)]
+/// Implementation of `Loader.create_module()` for in-memory extension modules on Linux.
+///
+/// The library data is written to a memory backed file (see `memfd_create(2)`)
+/// and `imp.create_dynamic()` is called with a spec pointing at that file. So
+/// CPython's own logic for loading extension modules is used and nothing is
+/// written to a filesystem. In-memory shared libraries the extension depends on
+/// are loaded first.
+#[cfg(target_os = "linux")]
+fn extension_module_shared_library_create_module(
+    resources_state: &PythonResourcesState<u8>,
+    py: Python,
+    _sys_modules: &PyAny,
+    spec: &PyAny,
+    name_py: &PyAny,
+    name: &str,
+    library_data: &[u8],
+) -> PyResult<Py<PyAny>> {
+    load_shared_library_dependencies(resources_state, name)
+        .map_err(|e| PyImportError::new_err((e, name.to_owned())))?;
+
+    let fh = memfd_from_data(name, library_data).map_err(|e| {
+        PyImportError::new_err((
+            format!("unable to create memory file for extension module: {}", e),
+            name.to_owned(),
+        ))
+    })?;
+    let path = memfd_path(&fh);
+
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("origin", &path)?;
+    let memfd_spec = py
+        .import("importlib.machinery")?
+        .getattr("ModuleSpec")?
+        .call((name_py, spec.getattr("loader")?), Some(kwargs))?;
+
+    let module = py
+        .import("_imp")?
+        .getattr("create_dynamic")?
+        .call1((memfd_spec,));
+    retain_memfd(fh);
+    let module = module?;
+
+    // Single-phase initialization sets `__file__` to the origin. But that path
+    // isn't meaningful to anything else.
+    if module.hasattr("__file__")?
+        && module
+            .getattr("__file__")?
+            .extract::<String>()
+            .ok()
+            .as_ref()
+            == Some(&path)
+    {
+        module.delattr("__file__")?;
+    }
+
+    Ok(module.into_py(py))
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
 fn extension_module_shared_library_create_module(
     _resources_state: &PythonResourcesState<u8>,
     _py: Python,
@@ -115,7 +184,7 @@ fn extension_module_shared_library_create_module(
     _name: &str,
     _library_data: &[u8],
 ) -> PyResult<Py<PyAny>> {
-    panic!("should only be called on Windows");
+    panic!("should only be called on Windows and Linux");
 }
 
 /// Reimplementation of `_PyImport_LoadDynamicModuleWithSpec()`.
@@ -241,6 +310,43 @@ fn load_dynamic_library(
     }
 }
 
+/// Register a lazy `linecache` entry resolving lines via the module's loader.
+///
+/// `linecache` only consults loaders when it is given module globals, which
+/// isn't the case for all callers (e.g. `warnings`). Registering an entry
+/// up front allows lines of modules without a file to be resolved by filename
+/// alone.
+///
+/// This is a no-op if `linecache` hasn't been imported, as importing it
+/// would slow down interpreter startup.
+fn register_linecache_entry(
+    sys_module: &PyModule,
+    filename: &PyAny,
+    module_globals: &PyAny,
+) -> PyResult<()> {
+    let linecache = sys_module
+        .getattr("modules")?
+        .call_method1("get", ("linecache",))?;
+
+    // The module may not be fully initialized if its import triggered ours.
+    if linecache.hasattr("lazycache")? {
+        linecache.call_method1("lazycache", (filename, module_globals))?;
+    }
+
+    Ok(())
+}
+
+/// A function receiving the time spent importing a module.
+///
+/// Arguments are the module name, the import step (`create` for creating an
+/// extension module and `exec` for executing a module), when the step started
+/// and how long it took. Nested imports are reported separately and their time
+/// is included in the time of the importing module.
+pub type ImportTimingFn = fn(&str, &'static str, Instant, Duration);
+
+/// Environment variable enabling diagnostics of finder decisions.
+const DIAGNOSTICS_ENV_VAR: &str = "OXIDIZED_IMPORTER_DIAGNOSTICS";
+
 /// Holds state for the custom MetaPathFinder.
 pub struct ImporterState {
     /// `imp` Python module.
@@ -269,8 +375,30 @@ pub struct ImporterState {
     ///
     /// If `None`, `set_start_method()` will not be called automatically.
     pub(crate) multiprocessing_set_start_method: Option<String>,
+    /// Value to pass to `multiprocessing.set_executable()` on import of `multiprocessing`.
+    ///
+    /// If `None`, `set_executable()` will not be called automatically.
+    pub(crate) multiprocessing_set_executable: Option<PathBuf>,
     /// Whether to automatically register ourself with `pkg_resources` when it is imported.
     pub(crate) pkg_resources_import_auto_register: bool,
+    /// Packages whose modules are loaded lazily via `importlib.util.LazyLoader`.
+    ///
+    /// Entries apply to the named package and all its sub-packages and modules.
+    pub(crate) lazy_import_packages: Vec<String>,
+    /// Packages whose modules are searched for on the filesystem first.
+    ///
+    /// Entries apply to the named package and all its sub-packages and modules.
+    pub(crate) filesystem_first_packages: Vec<String>,
+    /// Directory to materialize in-memory modules to, giving them a `__file__`.
+    ///
+    /// If `None`, modules imported from memory don't have a `__file__`.
+    pub(crate) materialized_files_dir: Option<PathBuf>,
+    /// Whether to describe finder decisions on `sys.stderr`.
+    ///
+    /// Atomic so it can be toggled through a shared finder.
+    pub(crate) diagnostics: AtomicBool,
+    /// Function receiving the time spent importing modules.
+    pub(crate) import_timing_fn: Option<ImportTimingFn>,
     /// Holds state about importable resources.
     ///
     /// This field is a PyCapsule and is a glorified wrapper around
@@ -387,8 +515,18 @@ impl ImporterState {
             exec_fn,
             optimize_level,
             multiprocessing_set_start_method: None,
+            multiprocessing_set_executable: None,
             // TODO value should come from config.
             pkg_resources_import_auto_register: true,
+            lazy_import_packages: vec![],
+            filesystem_first_packages: vec![],
+            materialized_files_dir: None,
+            diagnostics: AtomicBool::new(
+                std::env::var_os(DIAGNOSTICS_ENV_VAR)
+                    .map(|value| !value.is_empty() && value != "0")
+                    .unwrap_or(false),
+            ),
+            import_timing_fn: None,
             resources_state: capsule,
         })
     }
@@ -448,6 +586,87 @@ impl ImporterState {
     pub fn set_multiprocessing_set_start_method(&mut self, value: Option<String>) {
         self.multiprocessing_set_start_method = value;
     }
+
+    /// Set the value to call `multiprocessing.set_executable()` with on import of `multiprocessing`.
+    pub fn set_multiprocessing_set_executable(&mut self, value: Option<PathBuf>) {
+        self.multiprocessing_set_executable = value;
+    }
+
+    /// Set the packages whose modules are loaded lazily.
+    pub fn set_lazy_import_packages(&mut self, value: Vec<String>) {
+        self.lazy_import_packages = value;
+    }
+
+    /// Set the packages whose modules are searched for on the filesystem first.
+    pub fn set_filesystem_first_packages(&mut self, value: Vec<String>) {
+        self.filesystem_first_packages = value;
+    }
+
+    /// Set the directory to materialize in-memory modules to.
+    pub fn set_materialized_files_dir(&mut self, value: Option<PathBuf>) {
+        self.materialized_files_dir = value;
+    }
+
+    /// Set the function receiving the time spent importing modules.
+    pub fn set_import_timing_fn(&mut self, value: Option<ImportTimingFn>) {
+        self.import_timing_fn = value;
+    }
+
+    /// Whether finder decisions are described on `sys.stderr`.
+    pub fn diagnostics(&self) -> bool {
+        self.diagnostics.load(Ordering::Relaxed)
+    }
+
+    /// Set whether finder decisions are described on `sys.stderr`.
+    pub fn set_diagnostics(&self, value: bool) {
+        self.diagnostics.store(value, Ordering::Relaxed);
+    }
+
+    /// Describe a finder decision about a module on `sys.stderr`.
+    ///
+    /// Does nothing unless diagnostics are enabled. Decisions are written as
+    /// `key=value` fields, quoting values containing spaces.
+    fn diagnose(&self, py: Python, fullname: &str, fields: &[(&str, &str)]) -> PyResult<()> {
+        if !self.diagnostics() {
+            return Ok(());
+        }
+
+        let mut line = format!("oxidized_importer: find_spec module={}", fullname);
+        for (key, value) in fields {
+            if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"') {
+                line.push_str(&format!(" {}={:?}", key, value));
+            } else {
+                line.push_str(&format!(" {}={}", key, value));
+            }
+        }
+        line.push('\n');
+
+        let stderr = self.sys_module.getattr(py, "stderr")?;
+        if !stderr.is_none(py) {
+            stderr.call_method1(py, "write", (line,))?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether a module should be loaded lazily.
+    fn is_lazy_import(&self, name: &str) -> bool {
+        name_in_packages(name, &self.lazy_import_packages)
+    }
+
+    /// Whether a module should be searched for on the filesystem first.
+    fn is_filesystem_first(&self, name: &str) -> bool {
+        name_in_packages(name, &self.filesystem_first_packages)
+    }
+}
+
+/// Whether a module name is one of the given packages or is contained in one.
+fn name_in_packages(name: &str, packages: &[String]) -> bool {
+    packages.iter().any(|package| {
+        name == package
+            || (name.starts_with(package.as_str())
+                && name.as_bytes().get(package.len()) == Some(&b'.'))
+    })
 }
 
 impl Drop for ImporterState {
@@ -504,93 +723,40 @@ impl OxidizedFinder {
             state: importer_state,
         })
     }
-}
-
-#[pymethods]
-impl OxidizedFinder {
-    fn __traverse__(&self, visit: PyVisit) -> Result<(), PyTraverseError> {
-        self.state.gc_traverse(visit)
-    }
-
-    // Start of importlib.abc.MetaPathFinder interface.
 
-    #[pyo3(signature=(fullname, path, target=None))]
-    fn find_spec<'p>(
-        slf: &'p PyCell<Self>,
-        fullname: String,
-        path: &PyAny,
-        target: Option<&PyAny>,
-    ) -> PyResult<&'p PyAny> {
-        let py = slf.py();
-        let finder = slf.borrow();
-
-        let module = match finder
-            .state
-            .get_resources_state()
-            .resolve_importable_module(&fullname, finder.state.optimize_level)
-        {
-            Some(module) => module,
-            None => return Ok(py.None().into_ref(py)),
-        };
-
-        match module.flavor {
-            ModuleFlavor::Extension | ModuleFlavor::SourceBytecode => module.resolve_module_spec(
-                py,
-                finder.state.module_spec_type.clone_ref(py).into_ref(py),
-                slf,
-                finder.state.optimize_level,
-            ),
-            ModuleFlavor::Builtin => {
-                // BuiltinImporter.find_spec() always returns None if `path` is defined.
-                // And it doesn't use `target`. So don't proxy these values.
-                Ok(finder
-                    .state
-                    .builtin_importer
-                    .call_method(py, "find_spec", (fullname,), None)?
-                    .into_ref(py))
+    /// Run an import step, reporting its duration to the import timing function.
+    fn time_import(
+        slf: &PyCell<Self>,
+        name: &str,
+        step: &'static str,
+        f: impl FnOnce() -> PyResult<Py<PyAny>>,
+    ) -> PyResult<Py<PyAny>> {
+        let timing_fn = slf.borrow().state.import_timing_fn;
+
+        match timing_fn {
+            Some(timing_fn) => {
+                let start = Instant::now();
+                let res = f();
+                timing_fn(name, step, start, start.elapsed());
+
+                res
             }
-            ModuleFlavor::Frozen => Ok(finder
-                .state
-                .frozen_importer
-                .call_method(py, "find_spec", (fullname, path, target), None)?
-                .into_ref(py)),
+            None => f(),
         }
     }
 
-    fn find_module<'p>(
-        slf: &'p PyCell<Self>,
-        fullname: &PyAny,
-        path: &PyAny,
-    ) -> PyResult<&'p PyAny> {
-        let find_spec = slf.getattr("find_spec")?;
-        let spec = find_spec.call((fullname, path), None)?;
-
-        if spec.is_none() {
-            Ok(slf.py().None().into_ref(slf.py()))
-        } else {
-            spec.getattr("loader")
-        }
-    }
-
-    fn invalidate_caches(&self) -> PyResult<()> {
-        Ok(())
-    }
-
-    // End of importlib.abc.MetaPathFinder interface.
-
-    // Start of importlib.abc.Loader interface.
-
-    fn create_module(slf: &PyCell<Self>, spec: &PyAny) -> PyResult<Py<PyAny>> {
+    fn create_module_impl(
+        slf: &PyCell<Self>,
+        spec: &PyAny,
+        name: &PyAny,
+        key: &str,
+    ) -> PyResult<Py<PyAny>> {
         let py = slf.py();
         let finder = slf.borrow();
         let state = &finder.state;
-
-        let name = spec.getattr("name")?;
-        let key = name.extract::<String>()?;
-
         let module = match state
             .get_resources_state()
-            .resolve_importable_module(&key, state.optimize_level)
+            .resolve_importable_module(key, state.optimize_level)
         {
             Some(module) => module,
             None => return Ok(py.None()),
@@ -615,7 +781,7 @@ impl OxidizedFinder {
                     sys_modules.into_ref(py),
                     spec,
                     name,
-                    &key,
+                    key,
                     library_data,
                 )
             } else {
@@ -631,17 +797,14 @@ impl OxidizedFinder {
         }
     }
 
-    fn exec_module(slf: &PyCell<Self>, module: &PyAny) -> PyResult<Py<PyAny>> {
+    fn exec_module_impl(slf: &PyCell<Self>, module: &PyAny, key: &str) -> PyResult<Py<PyAny>> {
         let py = slf.py();
         let finder = slf.borrow();
         let state = &finder.state;
 
-        let name = module.getattr("__name__")?;
-        let key = name.extract::<String>()?;
-
         let mut entry = match state
             .get_resources_state()
-            .resolve_importable_module(&key, state.optimize_level)
+            .resolve_importable_module(key, state.optimize_level)
         {
             Some(entry) => entry,
             None => {
@@ -651,15 +814,23 @@ impl OxidizedFinder {
             }
         };
 
-        if let Some(bytecode) = entry.resolve_bytecode(
+        if let Some(code) = entry.resolve_code(
             py,
             state.optimize_level,
             state.decode_source.as_ref(py),
             state.io_module.as_ref(py),
+            state.marshal_loads.as_ref(py),
         )? {
-            let code = state.marshal_loads.call(py, (bytecode,), None)?;
             let dict = module.getattr("__dict__")?;
 
+            if entry.has_source() {
+                register_linecache_entry(
+                    state.sys_module.as_ref(py),
+                    code.getattr(py, "co_filename")?.as_ref(py),
+                    dict,
+                )?;
+            }
+
             state
                 .call_with_frames_removed
                 .call(py, (&state.exec_fn, code, dict), None)
@@ -683,7 +854,7 @@ impl OxidizedFinder {
         }?;
 
         // Perform import time side-effects for special modules.
-        match key.as_str() {
+        match key {
             "multiprocessing" => {
                 if let Some(method) = state.multiprocessing_set_start_method.as_ref() {
                     // We pass force=True to ensure the call doesn't fail.
@@ -691,17 +862,283 @@ impl OxidizedFinder {
                     kwargs.set_item("force", true)?;
                     module.call_method("set_start_method", (method,), Some(kwargs))?;
                 }
+
+                if let Some(executable) = state.multiprocessing_set_executable.as_ref() {
+                    module.call_method1("set_executable", (executable,))?;
+                }
             }
             "pkg_resources" => {
                 if state.pkg_resources_import_auto_register {
                     register_pkg_resources_with_module(py, module)?;
                 }
             }
+            "linecache" => {
+                // Modules imported before `linecache` didn't get an entry. Bytecode
+                // compiled by us uses the module name as its filename.
+                let modules = state.sys_module.getattr(py, "modules")?;
+                let modules = modules.as_ref(py).downcast::<PyDict>()?.copy()?;
+
+                for (name, m) in modules.iter() {
+                    if !m.getattr("__loader__").map(|l| l.is(slf)).unwrap_or(false) {
+                        continue;
+                    }
+
+                    let has_source = name
+                        .extract::<String>()
+                        .ok()
+                        .and_then(|name| {
+                            state
+                                .get_resources_state()
+                                .resolve_importable_module(&name, state.optimize_level)
+                        })
+                        .map(|entry| entry.has_source())
+                        .unwrap_or(false);
+
+                    if has_source {
+                        register_linecache_entry(
+                            state.sys_module.as_ref(py),
+                            name,
+                            m.getattr("__dict__")?,
+                        )?;
+                    }
+                }
+            }
             _ => {}
         }
 
         Ok(py.None())
     }
+}
+
+#[pymethods]
+impl OxidizedFinder {
+    fn __traverse__(&self, visit: PyVisit) -> Result<(), PyTraverseError> {
+        self.state.gc_traverse(visit)
+    }
+
+    // Start of importlib.abc.MetaPathFinder interface.
+
+    #[pyo3(signature=(fullname, path, target=None))]
+    fn find_spec<'p>(
+        slf: &'p PyCell<Self>,
+        fullname: String,
+        path: &PyAny,
+        target: Option<&PyAny>,
+    ) -> PyResult<&'p PyAny> {
+        let py = slf.py();
+        let finder = slf.borrow();
+
+        // Modules found by `PathFinder` take precedence over our resources for
+        // these packages. Namespace portions don't, as they would shadow the
+        // rest of the package.
+        if finder.state.is_filesystem_first(&fullname) {
+            let spec = py
+                .import("_frozen_importlib_external")?
+                .getattr("PathFinder")?
+                .call_method1(
+                    "find_spec",
+                    (&fullname, Self::external_search_path(slf, path)?, target),
+                )?;
+
+            let found = !spec.is_none() && !spec.getattr("loader")?.is_none();
+
+            finder.state.diagnose(
+                py,
+                &fullname,
+                &[
+                    ("location", "filesystem"),
+                    ("result", if found { "found" } else { "not_found" }),
+                ],
+            )?;
+
+            if found {
+                return Ok(spec);
+            }
+        }
+
+        let resources_state = finder.state.get_resources_state();
+
+        let mut module = match resources_state
+            .resolve_importable_module(&fullname, finder.state.optimize_level)
+        {
+            Some(module) => module,
+            None => {
+                if finder.state.diagnostics() {
+                    finder.state.diagnose(
+                        py,
+                        &fullname,
+                        &[
+                            ("location", "resources"),
+                            ("result", "not_found"),
+                            (
+                                "reason",
+                                resources_state.unresolved_module_reason(&fullname),
+                            ),
+                        ],
+                    )?;
+                }
+
+                return Ok(py.None().into_ref(py));
+            }
+        };
+
+        if let Err(e) = resources_state.verify_resource(&fullname) {
+            finder.state.diagnose(
+                py,
+                &fullname,
+                &[
+                    ("location", module.location()),
+                    ("result", "error"),
+                    ("reason", "verification_failed"),
+                    ("error", &e),
+                ],
+            )?;
+
+            return Err(PyImportError::new_err(e));
+        }
+
+        if module.is_package {
+            Self::cache_path_entry_finder(slf, &module.package_path())?;
+        }
+
+        let spec = match module.flavor {
+            ModuleFlavor::SourceBytecode => {
+                let spec = module.resolve_module_spec(
+                    py,
+                    finder.state.module_spec_type.clone_ref(py).into_ref(py),
+                    slf,
+                    finder.state.optimize_level,
+                )?;
+
+                if let Some(root) = &finder.state.materialized_files_dir {
+                    if let Some(path) = module.materialize(
+                        py,
+                        root,
+                        finder.state.optimize_level,
+                        finder.state.decode_source.as_ref(py),
+                        finder.state.io_module.as_ref(py),
+                    )? {
+                        spec.setattr("origin", &path)?;
+                        spec.setattr("has_location", true)?;
+
+                        // The virtual package directory is retained so
+                        // `pkgutil` continues to see all modules of the package.
+                        if let Some(package_dir) = path.parent().filter(|_| module.is_package) {
+                            spec.setattr(
+                                "submodule_search_locations",
+                                vec![package_dir.to_path_buf(), module.package_path()],
+                            )?;
+                        }
+                    }
+                }
+
+                // `LazyLoader` defers `exec_module()` until the first attribute
+                // access on the module. Extension modules aren't eligible, as
+                // their initialization can't be deferred.
+                if finder.state.is_lazy_import(&fullname) {
+                    let lazy_loader = py
+                        .import("importlib.util")?
+                        .getattr("LazyLoader")?
+                        .call1((slf,))?;
+                    spec.setattr("loader", lazy_loader)?;
+                }
+
+                spec
+            }
+            ModuleFlavor::Extension => module.resolve_module_spec(
+                py,
+                finder.state.module_spec_type.clone_ref(py).into_ref(py),
+                slf,
+                finder.state.optimize_level,
+            )?,
+            ModuleFlavor::Builtin => {
+                // BuiltinImporter.find_spec() always returns None if `path` is defined.
+                // And it doesn't use `target`. So don't proxy these values.
+                finder
+                    .state
+                    .builtin_importer
+                    .call_method(py, "find_spec", (&fullname,), None)?
+                    .into_ref(py)
+            }
+            ModuleFlavor::Frozen => finder
+                .state
+                .frozen_importer
+                .call_method(py, "find_spec", (&fullname, path, target), None)?
+                .into_ref(py),
+            ModuleFlavor::Namespace => {
+                let spec = Self::find_namespace_spec(slf, &fullname, path)?;
+
+                // `_NamespacePath` recomputes its portions when the parent path
+                // changes, just like namespace packages found by `PathFinder`.
+                if !spec.is_none() {
+                    let namespace_path = py
+                        .import("_frozen_importlib_external")?
+                        .getattr("_NamespacePath")?
+                        .call1((
+                            &fullname,
+                            spec.getattr("submodule_search_locations")?,
+                            Self::namespace_path_finder(slf)?,
+                        ))?;
+                    spec.setattr("submodule_search_locations", namespace_path)?;
+                }
+
+                spec
+            }
+        };
+
+        finder.state.diagnose(
+            py,
+            &fullname,
+            &[
+                ("location", module.location()),
+                ("flavor", module.flavor.as_str()),
+                ("result", if spec.is_none() { "not_found" } else { "found" }),
+            ],
+        )?;
+
+        Ok(spec)
+    }
+
+    fn find_module<'p>(
+        slf: &'p PyCell<Self>,
+        fullname: &PyAny,
+        path: &PyAny,
+    ) -> PyResult<&'p PyAny> {
+        let find_spec = slf.getattr("find_spec")?;
+        let spec = find_spec.call((fullname, path), None)?;
+
+        if spec.is_none() {
+            Ok(slf.py().None().into_ref(slf.py()))
+        } else {
+            spec.getattr("loader")
+        }
+    }
+
+    fn invalidate_caches(&self) -> PyResult<()> {
+        Ok(())
+    }
+
+    // End of importlib.abc.MetaPathFinder interface.
+
+    // Start of importlib.abc.Loader interface.
+
+    fn create_module(slf: &PyCell<Self>, spec: &PyAny) -> PyResult<Py<PyAny>> {
+        let name = spec.getattr("name")?;
+        let key = name.extract::<String>()?;
+
+        Self::time_import(slf, &key, "create", || {
+            Self::create_module_impl(slf, spec, name, &key)
+        })
+    }
+
+    fn exec_module(slf: &PyCell<Self>, module: &PyAny) -> PyResult<Py<PyAny>> {
+        let name = module.getattr("__name__")?;
+        let key = name.extract::<String>()?;
+
+        Self::time_import(slf, &key, "exec", || {
+            Self::exec_module_impl(slf, module, &key)
+        })
+    }
 
     // End of importlib.abc.Loader interface.
 
@@ -740,13 +1177,14 @@ impl OxidizedFinder {
             None => return Ok(py.None()),
         };
 
-        if let Some(bytecode) = module.resolve_bytecode(
+        if let Some(code) = module.resolve_code(
             py,
             state.optimize_level,
             state.decode_source.as_ref(py),
             state.io_module.as_ref(py),
+            state.marshal_loads.as_ref(py),
         )? {
-            state.marshal_loads.call(py, (bytecode,), None)
+            Ok(code)
         } else if module.flavor == ModuleFlavor::Frozen {
             state
                 .imp_module
@@ -804,9 +1242,20 @@ impl OxidizedFinder {
             .resolve_importable_module(&key, state.optimize_level)
             .ok_or_else(|| make_error("unknown module"))?;
 
-        module
+        if let Some(origin) = module
             .resolve_origin(slf.py())
             .map_err(|_| make_error("unable to resolve origin"))?
+        {
+            return Ok(origin);
+        }
+
+        // Materialized modules have a `__file__` once they have been imported.
+        state
+            .materialized_files_dir
+            .as_deref()
+            .and_then(|root| module.materialized_path(root))
+            .filter(|path| path.exists())
+            .map(|path| path.into_py(slf.py()).into_ref(slf.py()))
             .ok_or_else(|| make_error("no origin"))
     }
 
@@ -902,10 +1351,17 @@ impl OxidizedFinder {
 
     // Additional methods provided for convenience.
 
-    /// OxidizedFinder.__new__(relative_path_origin=None))
+    /// OxidizedFinder.__new__(relative_path_origin=None, lazy_import_packages=None,
+    /// materialized_files_dir=None, filesystem_first_packages=None))
     #[new]
-    #[pyo3(signature=(relative_path_origin=None))]
-    fn new(py: Python, relative_path_origin: Option<&PyAny>) -> PyResult<Self> {
+    #[pyo3(signature=(relative_path_origin=None, lazy_import_packages=None, materialized_files_dir=None, filesystem_first_packages=None))]
+    fn new(
+        py: Python,
+        relative_path_origin: Option<&PyAny>,
+        lazy_import_packages: Option<Vec<String>>,
+        materialized_files_dir: Option<&PyAny>,
+        filesystem_first_packages: Option<Vec<String>>,
+    ) -> PyResult<Self> {
         // We need to obtain an ImporterState instance. This requires handles on a
         // few items...
 
@@ -921,16 +1377,48 @@ impl OxidizedFinder {
             resources_state.set_origin(pyobject_to_pathbuf(py, py_origin)?);
         }
 
+        let mut state = ImporterState::new(py, m, bootstrap_module, resources_state)?;
+        state.set_lazy_import_packages(lazy_import_packages.unwrap_or_default());
+        state.set_filesystem_first_packages(filesystem_first_packages.unwrap_or_default());
+        state.set_materialized_files_dir(
+            materialized_files_dir
+                .map(|path| pyobject_to_pathbuf(py, path))
+                .transpose()?,
+        );
+
         Ok(OxidizedFinder {
-            state: Arc::new(ImporterState::new(
-                py,
-                m,
-                bootstrap_module,
-                resources_state,
-            )?),
+            state: Arc::new(state),
         })
     }
 
+    #[getter]
+    fn lazy_import_packages(&self) -> Vec<String> {
+        self.state.lazy_import_packages.clone()
+    }
+
+    #[getter]
+    fn filesystem_first_packages(&self) -> Vec<String> {
+        self.state.filesystem_first_packages.clone()
+    }
+
+    #[getter]
+    fn diagnostics(&self) -> bool {
+        self.state.diagnostics()
+    }
+
+    #[setter]
+    fn set_diagnostics(&self, value: bool) {
+        self.state.set_diagnostics(value);
+    }
+
+    #[getter]
+    fn materialized_files_dir<'p>(&self, py: Python<'p>) -> Option<&'p PyAny> {
+        self.state
+            .materialized_files_dir
+            .as_ref()
+            .map(|path| path.into_py(py).into_ref(py))
+    }
+
     #[getter]
     fn multiprocessing_set_start_method(&self) -> PyResult<Option<String>> {
         if let Some(v) = &self.state.multiprocessing_set_start_method {
@@ -999,6 +1487,31 @@ impl OxidizedFinder {
         Ok(())
     }
 
+    #[pyo3(signature=(path, sha256=None))]
+    fn install_resources_file<'p>(
+        &self,
+        py: Python<'p>,
+        path: &PyAny,
+        sha256: Option<&[u8]>,
+    ) -> PyResult<&'p PyList> {
+        let path = pyobject_to_pathbuf(py, path)?;
+        let sha256 = sha256
+            .map(<[u8; 32]>::try_from)
+            .transpose()
+            .map_err(|_| PyValueError::new_err("sha256 must be 32 bytes"))?;
+        let modules = py.import("sys")?.getattr("modules")?;
+
+        let names = self
+            .state
+            .get_resources_state_mut()
+            .install_path(path, sha256.as_ref(), |name| {
+                modules.contains(name).unwrap_or(true)
+            })
+            .map_err(PyValueError::new_err)?;
+
+        Ok(PyList::new(py, names))
+    }
+
     fn index_interpreter_builtins(&self) -> PyResult<()> {
         self.state
             .get_resources_state_mut()
@@ -1032,6 +1545,16 @@ impl OxidizedFinder {
         resources_state.resources_as_py_list(py)
     }
 
+    fn find_resource<'p>(
+        &self,
+        py: Python<'p>,
+        name: &str,
+    ) -> PyResult<Option<&'p PyCell<OxidizedResource>>> {
+        self.state
+            .get_resources_state()
+            .resource_as_pyobject(py, name)
+    }
+
     fn add_resource(&self, resource: &OxidizedResource) -> PyResult<()> {
         let resources_state = self.state.get_resources_state_mut();
 
@@ -1057,17 +1580,45 @@ impl OxidizedFinder {
         Ok(())
     }
 
-    #[pyo3(signature=(ignore_builtin=true, ignore_frozen=true))]
+    #[cfg(feature = "wheel")]
+    fn add_wheel(&self, py: Python, wheel: &PyAny) -> PyResult<()> {
+        let resources = wheel_resources(py, wheel)?;
+        let collection = if wheel.downcast::<PyBytes>().is_ok() {
+            "wheel".to_string()
+        } else {
+            pyobject_to_pathbuf(py, wheel)?.display().to_string()
+        };
+
+        let resources_state = self.state.get_resources_state_mut();
+
+        for resource in resources {
+            // Don't let namespace packages synthesized for the wheel's parent
+            // packages shadow packages we already know about.
+            if resource.is_python_namespace_package && resources_state.has_resource(&resource.name)
+            {
+                continue;
+            }
+
+            resources_state
+                .add_resource_from(resource, &collection)
+                .map_err(|_| PyValueError::new_err("unable to add resource to finder"))?;
+        }
+
+        Ok(())
+    }
+
+    #[pyo3(signature=(ignore_builtin=true, ignore_frozen=true, format_version=3))]
     fn serialize_indexed_resources<'p>(
         &self,
         py: Python<'p>,
         ignore_builtin: bool,
         ignore_frozen: bool,
+        format_version: u8,
     ) -> PyResult<&'p PyBytes> {
         let resources_state = self.state.get_resources_state();
 
         let data = resources_state
-            .serialize_resources(ignore_builtin, ignore_frozen)
+            .serialize_resources(ignore_builtin, ignore_frozen, format_version)
             .map_err(|e| PyValueError::new_err(format!("error serializing: {}", e)))?;
 
         Ok(PyBytes::new(py, &data))
@@ -1075,6 +1626,164 @@ impl OxidizedFinder {
 }
 
 impl OxidizedFinder {
+    /// Register a path entry finder servicing a package's virtual `__path__`.
+    ///
+    /// Packages not backed by the filesystem have a `__path__` entry under
+    /// `path_hook_base_str`, which only our path hook understands. Seeding
+    /// `sys.path_importer_cache` with an `OxidizedPathEntryFinder` for that
+    /// entry allows `pkgutil.iter_modules()` and `pkgutil.walk_packages()` to
+    /// find the package's modules even if our path hook isn't installed in
+    /// `sys.path_hooks`.
+    fn cache_path_entry_finder(slf: &PyCell<Self>, package_path: &Path) -> PyResult<()> {
+        let py = slf.py();
+        let finder = slf.borrow();
+
+        let current_exe = finder.state.get_resources_state().current_exe();
+        if package_path == current_exe || !package_path.starts_with(current_exe) {
+            return Ok(());
+        }
+
+        let cache = finder
+            .state
+            .sys_module
+            .getattr(py, "path_importer_cache")?
+            .into_ref(py);
+        let path = package_path.into_py(py).into_ref(py);
+
+        if !cache.contains(path)? {
+            cache.set_item(path, Py::new(py, Self::path_hook_inner(slf, path)?)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Resolve the search path entries not serviced by our path hook.
+    ///
+    /// `path` is the `path` argument to `find_spec()`. If `None`, `sys.path`
+    /// is used.
+    fn external_search_path<'p>(slf: &'p PyCell<Self>, path: &PyAny) -> PyResult<&'p PyList> {
+        let py = slf.py();
+        let finder = slf.borrow();
+
+        let search_path = if path.is_none() {
+            finder.state.sys_module.getattr(py, "path")?.into_ref(py)
+        } else {
+            path
+        };
+
+        let path_hook_base = finder.path_hook_base_str(py).downcast::<PyString>()?;
+        let prefixes = PyTuple::new(
+            py,
+            [
+                path_hook_base.call_method1("__add__", ("/",))?,
+                path_hook_base.call_method1("__add__", ("\\",))?,
+            ],
+        );
+
+        let external_path = PyList::empty(py);
+        for entry in search_path.iter()? {
+            let entry = entry?;
+
+            if let Ok(entry) = entry.downcast::<PyString>() {
+                if entry.compare(path_hook_base)? == std::cmp::Ordering::Equal
+                    || entry
+                        .call_method1("startswith", (prefixes,))?
+                        .extract::<bool>()?
+                {
+                    continue;
+                }
+            }
+
+            external_path.append(entry)?;
+        }
+
+        Ok(external_path)
+    }
+
+    /// Resolve the `ModuleSpec` of a namespace package.
+    ///
+    /// Namespace packages can be split into portions across multiple
+    /// locations. The portion we index is combined with portions found by
+    /// `PathFinder` in `path`, or `sys.path` for top-level packages. Entries
+    /// serviced by our path hook are ignored, as they are already covered by
+    /// our portion.
+    ///
+    /// Per PEP 420, a regular package or module found by `PathFinder` takes
+    /// precedence over namespace portions. In this case `None` is returned, so
+    /// the import is serviced by `PathFinder`.
+    fn find_namespace_spec<'p>(
+        slf: &'p PyCell<Self>,
+        fullname: &str,
+        path: &PyAny,
+    ) -> PyResult<&'p PyAny> {
+        let py = slf.py();
+        let finder = slf.borrow();
+        let state = &finder.state;
+
+        let module = match state
+            .get_resources_state()
+            .resolve_importable_module(fullname, state.optimize_level)
+        {
+            Some(module) if module.flavor == ModuleFlavor::Namespace => module,
+            _ => return Ok(py.None().into_ref(py)),
+        };
+
+        let external_path = Self::external_search_path(slf, path)?;
+
+        let locations = PyList::new(py, [module.package_path()]);
+
+        let external_spec = py
+            .import("_frozen_importlib_external")?
+            .getattr("PathFinder")?
+            .call_method1("find_spec", (fullname, external_path))?;
+
+        if !external_spec.is_none() {
+            if !external_spec.getattr("loader")?.is_none() {
+                return Ok(py.None().into_ref(py));
+            }
+
+            let portions = external_spec.getattr("submodule_search_locations")?;
+            if !portions.is_none() {
+                for portion in portions.iter()? {
+                    locations.append(portion?)?;
+                }
+            }
+        }
+
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("is_package", true)?;
+
+        let spec = state
+            .module_spec_type
+            .call(py, (fullname, py.None()), Some(kwargs))?
+            .into_ref(py);
+        spec.setattr("submodule_search_locations", locations)?;
+
+        Ok(spec)
+    }
+
+    /// Obtain the path finder callable used by `_NamespacePath`.
+    ///
+    /// `_NamespacePath` calls it with the package name and parent path to
+    /// recompute portions. `submodule_search_locations` of the returned spec
+    /// is a `list`, as `_NamespacePath` expects.
+    fn namespace_path_finder(slf: &PyCell<Self>) -> PyResult<&PyCFunction> {
+        let finder: Py<Self> = slf.into();
+
+        PyCFunction::new_closure(
+            slf.py(),
+            None,
+            None,
+            move |args, _kwargs| -> PyResult<PyObject> {
+                let py = args.py();
+                let fullname = args.get_item(0)?.extract::<String>()?;
+                let path = args.get_item(1)?;
+
+                Ok(Self::find_namespace_spec(finder.as_ref(py), &fullname, path)?.into_py(py))
+            },
+        )
+    }
+
     fn path_hook_inner(
         slf: &PyCell<Self>,
         path_original: &PyAny,
@@ -1198,80 +1907,6 @@ impl OxidizedFinder {
     }
 }
 
-/// Path-like object facilitating Python resource access.
-///
-/// This implements importlib.abc.Traversable.
-#[pyclass(module = "oxidized_importer")]
-pub(crate) struct PyOxidizerTraversable {
-    state: Arc<ImporterState>,
-    path: String,
-}
-
-#[pymethods]
-impl PyOxidizerTraversable {
-    /// Yield Traversable objects in self.
-    fn iterdir(&self) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Read contents of self as bytes.
-    fn read_bytes(&self) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Read contents of self as text.
-    fn read_text(&self) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Return True if self is a dir.
-    fn is_dir(&self) -> PyResult<bool> {
-        // We are a directory if the current path is a known package.
-        // TODO We may need to expand this definition in the future to cover
-        // virtual subdirectories in addressable resources. But this will require
-        // changes to the resources data format to capture said annotations.
-        if let Some(entry) = self
-            .state
-            .get_resources_state()
-            .resolve_importable_module(&self.path, self.state.optimize_level)
-        {
-            if entry.is_package {
-                return Ok(true);
-            }
-        }
-
-        Ok(false)
-    }
-
-    /// Return True if self is a file.
-    fn is_file(&self) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Return Traversable child in self.
-    #[allow(unused)]
-    fn joinpath(&self, child: &PyAny) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Return Traversable child in self.
-    #[allow(unused)]
-    fn __truediv__(&self, child: &PyAny) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// mode may be 'r' or 'rb' to open as text or binary. Return a handle
-    /// suitable for reading (same as pathlib.Path.open).
-    ///
-    /// When opening as text, accepts encoding parameters such as those
-    /// accepted by io.TextIOWrapper.
-    #[allow(unused)]
-    #[pyo3(signature=(*py_args, **py_kwargs))]
-    fn open(&self, py_args: &PyTuple, py_kwargs: Option<&PyDict>) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-}
-
 /// Replace all meta path importers with an OxidizedFinder instance and return it.
 ///
 /// This is called after PyInit_* to finish the initialization of the
@@ -1376,6 +2011,119 @@ pub fn remove_external_importers(sys_module: &PyModule) -> PyResult<()> {
     Ok(())
 }
 
+/// Install the standard library `zipimport` importer.
+///
+/// `PathFinder` is appended to [`sys.meta_path`] and `zipimport.zipimporter`
+/// to [`sys.path_hooks`] if not already present. This is meant to be called
+/// after [remove_external_importers] to restore importing from zip files
+/// on `sys.path` without restoring filesystem importing.
+///
+/// `sys.path_importer_cache` is cleared, as it may hold path entry finders
+/// for the filesystem.
+///
+/// [`sys.meta_path`]: https://docs.python.org/3/library/sys.html#sys.meta_path
+/// [`sys.path_hooks`]: https://docs.python.org/3/library/sys.html#sys.path_hooks
+pub fn install_zipimport_importer(py: Python, sys_module: &PyModule) -> PyResult<()> {
+    let path_finder = py
+        .import("_frozen_importlib_external")?
+        .getattr("PathFinder")?;
+    let zipimporter = py.import("zipimport")?.getattr("zipimporter")?;
+
+    let meta_path = sys_module.getattr("meta_path")?;
+    if !meta_path.contains(path_finder)? {
+        meta_path.call_method1("append", (path_finder,))?;
+    }
+
+    let path_hooks = sys_module.getattr("path_hooks")?;
+    if !path_hooks.contains(zipimporter)? {
+        path_hooks.call_method1("append", (zipimporter,))?;
+    }
+
+    sys_module
+        .getattr("path_importer_cache")?
+        .call_method0("clear")?;
+
+    Ok(())
+}
+
+/// A path hook only servicing some directories and their subdirectories.
+#[pyclass(module = "oxidized_importer")]
+struct RestrictedPathHook {
+    roots: Vec<PathBuf>,
+    hook: PyObject,
+}
+
+#[pymethods]
+impl RestrictedPathHook {
+    fn __call__(&self, py: Python, path: &PyAny) -> PyResult<PyObject> {
+        let path_buf = pyobject_to_pathbuf(py, path)?;
+
+        if self.roots.iter().any(|root| path_buf.starts_with(root)) {
+            self.hook.call1(py, (path,))
+        } else {
+            Err(PyImportError::new_err(format!(
+                "{} is not an allowed filesystem import location",
+                path_buf.display()
+            )))
+        }
+    }
+}
+
+/// Restore the standard library path-based finder for specific directories.
+///
+/// This is meant to be called after [remove_external_importers]. `PathFinder`
+/// is appended to [`sys.meta_path`] if not present and a path hook creating
+/// `FileFinder` instances for `roots` and their subdirectories is appended to
+/// [`sys.path_hooks`]. Other [`sys.path`] entries remain ignored.
+///
+/// `sys` must be a reference to the [`sys`] module.
+///
+/// [`sys.meta_path`]: https://docs.python.org/3/library/sys.html#sys.meta_path
+/// [`sys.path_hooks`]: https://docs.python.org/3/library/sys.html#sys.path_hooks
+/// [`sys.path`]: https://docs.python.org/3/library/sys.html#sys.path
+/// [`sys`]: https://docs.python.org/3/library/sys.html
+pub fn install_restricted_path_importer(
+    py: Python,
+    sys_module: &PyModule,
+    roots: Vec<PathBuf>,
+) -> PyResult<()> {
+    let external = py.import("_frozen_importlib_external")?;
+    let path_finder = external.getattr("PathFinder")?;
+
+    let loaders = external
+        .getattr("_get_supported_file_loaders")?
+        .call0()?
+        .downcast::<PyList>()?
+        .iter()
+        .collect::<Vec<_>>();
+    let loaders = PyTuple::new(py, loaders);
+    let hook = external
+        .getattr("FileFinder")?
+        .call_method1("path_hook", loaders)?;
+
+    let meta_path = sys_module.getattr("meta_path")?;
+    if !meta_path.contains(path_finder)? {
+        meta_path.call_method1("append", (path_finder,))?;
+    }
+
+    let hook = Py::new(
+        py,
+        RestrictedPathHook {
+            roots,
+            hook: hook.into_py(py),
+        },
+    )?;
+    sys_module
+        .getattr("path_hooks")?
+        .call_method1("append", (hook,))?;
+
+    sys_module
+        .getattr("path_importer_cache")?
+        .call_method0("clear")?;
+
+    Ok(())
+}
+
 /// Prepend a path hook to [`sys.path_hooks`] that works with [OxidizedFinder].
 ///
 /// `sys` must be a reference to the [`sys`] module.
@@ -0,0 +1,95 @@
+# This Source Code Form is subject to the terms of the Mozilla Public
+# License, v. 2.0. If a copy of the MPL was not distributed with this
+# file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+"""Finder for interpreters with their own GIL.
+
+This is executed in the namespace of an isolated ``oxidized_importer``
+module, which defines the ``_find_module()``, ``_get_source()`` and
+``_get_bytecode()`` functions over the resources of the interpreter.
+"""
+
+import marshal
+import sys
+
+import _frozen_importlib as _bootstrap
+import _frozen_importlib_external as _bootstrap_external
+
+
+class OxidizedFinder:
+    """Meta path finder and loader importing from packed resources.
+
+    Modules and packages with source or bytecode are imported. Built-in and
+    frozen modules are left to the standard importers. Extension modules are
+    only imported from the filesystem.
+    """
+
+    def find_spec(self, fullname, path, target=None):
+        found = _find_module(fullname, sys.flags.optimize)
+        if found is None:
+            return None
+
+        flavor, is_package, origin, package_path = found
+
+        if flavor == "extension":
+            if origin is None:
+                return None
+
+            loader = _bootstrap_external.ExtensionFileLoader(fullname, origin)
+            return _bootstrap_external.spec_from_file_location(
+                fullname, origin, loader=loader
+            )
+
+        if flavor != "module":
+            return None
+
+        spec = _bootstrap.ModuleSpec(
+            fullname, self, origin=origin, is_package=is_package
+        )
+
+        if origin is not None:
+            spec.has_location = True
+
+        if is_package:
+            spec.submodule_search_locations = [package_path]
+
+        return spec
+
+    def invalidate_caches(self):
+        pass
+
+    def create_module(self, spec):
+        return None
+
+    def exec_module(self, module):
+        name = module.__spec__.name
+        code = self.get_code(name)
+        if code is None:
+            raise ImportError("cannot load module %s" % name, name=name)
+
+        _bootstrap._call_with_frames_removed(exec, code, module.__dict__)
+
+    def get_code(self, fullname):
+        bytecode = _get_bytecode(fullname, sys.flags.optimize)
+        if bytecode is not None:
+            return marshal.loads(bytecode)
+
+        source = self.get_source(fullname)
+        if source is None:
+            return None
+
+        return compile(source, fullname, "exec", dont_inherit=True)
+
+    def get_source(self, fullname):
+        source = _get_source(fullname, sys.flags.optimize)
+        if source is None:
+            return None
+
+        return _bootstrap_external.decode_source(source)
+
+    def is_package(self, fullname):
+        found = _find_module(fullname, sys.flags.optimize)
+        if found is None:
+            raise ImportError("cannot find module %s" % fullname, name=fullname)
+
+        return found[1]
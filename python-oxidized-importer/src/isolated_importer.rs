@@ -0,0 +1,353 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Importer for interpreters with their own GIL.
+
+PyO3 creates the Python types of our classes once per process. Using them
+from interpreters having their own GIL (PEP 684) isn't sound. So these
+interpreters get an `oxidized_importer` module using multi-phase
+initialization (PEP 489) without any PyO3 class. The module state of each
+instance holds a `PythonResourcesState` indexing the resources data, which
+is shared read-only with other interpreters. Its `OxidizedFinder` is
+implemented in Python on top of functions querying that state.
+*/
+
+use {
+    crate::{
+        python_resources::{memoryview_from_memory, BytecodeData, PythonResourcesState},
+        OXIDIZED_IMPORTER_NAME, OXIDIZED_IMPORTER_NAME_STR,
+    },
+    pyo3::{
+        exceptions::{PyImportError, PyRuntimeError, PyValueError},
+        ffi as pyffi,
+        prelude::*,
+        types::{PyBytes, PyTuple},
+        AsPyPointer,
+    },
+    python_packaging::resource::BytecodeOptimizationLevel,
+    std::{
+        os::raw::{c_int, c_void},
+        panic::{catch_unwind, AssertUnwindSafe},
+    },
+};
+
+/// Python source code of the finder.
+const FINDER_SOURCE: &str = include_str!("isolated_finder.py");
+
+const DOC: &[u8] = b"Importer for interpreters with their own GIL\0";
+
+const FIND_MODULE_NAME: &[u8] = b"_find_module\0";
+const GET_SOURCE_NAME: &[u8] = b"_get_source\0";
+const GET_BYTECODE_NAME: &[u8] = b"_get_bytecode\0";
+
+/// `Py_mod_multiple_interpreters` slot of Python 3.12+.
+const PY_MOD_MULTIPLE_INTERPRETERS: c_int = 3;
+
+/// `Py_MOD_PER_INTERPRETER_GIL_SUPPORTED` value of the `Py_mod_multiple_interpreters` slot.
+const PY_MOD_PER_INTERPRETER_GIL_SUPPORTED: usize = 2;
+
+static mut METHODS: [pyffi::PyMethodDef; 4] = [
+    pyffi::PyMethodDef {
+        ml_name: FIND_MODULE_NAME.as_ptr() as *const _,
+        ml_meth: pyffi::PyMethodDefPointer {
+            PyCFunction: find_module,
+        },
+        ml_flags: pyffi::METH_VARARGS,
+        ml_doc: std::ptr::null(),
+    },
+    pyffi::PyMethodDef {
+        ml_name: GET_SOURCE_NAME.as_ptr() as *const _,
+        ml_meth: pyffi::PyMethodDefPointer {
+            PyCFunction: get_source,
+        },
+        ml_flags: pyffi::METH_VARARGS,
+        ml_doc: std::ptr::null(),
+    },
+    pyffi::PyMethodDef {
+        ml_name: GET_BYTECODE_NAME.as_ptr() as *const _,
+        ml_meth: pyffi::PyMethodDefPointer {
+            PyCFunction: get_bytecode,
+        },
+        ml_flags: pyffi::METH_VARARGS,
+        ml_doc: std::ptr::null(),
+    },
+    pyffi::PyMethodDef::zeroed(),
+];
+
+static mut SLOTS: [pyffi::PyModuleDef_Slot; 2] = [
+    pyffi::PyModuleDef_Slot {
+        slot: pyffi::Py_mod_exec,
+        value: module_exec as *mut c_void,
+    },
+    pyffi::PyModuleDef_Slot {
+        slot: 0,
+        value: std::ptr::null_mut(),
+    },
+];
+
+/// Like [SLOTS], also declaring support for interpreters with their own GIL.
+///
+/// Older Python versions refuse unknown slots.
+static mut SLOTS_PER_INTERPRETER_GIL: [pyffi::PyModuleDef_Slot; 3] = [
+    pyffi::PyModuleDef_Slot {
+        slot: pyffi::Py_mod_exec,
+        value: module_exec as *mut c_void,
+    },
+    pyffi::PyModuleDef_Slot {
+        slot: PY_MOD_MULTIPLE_INTERPRETERS,
+        value: PY_MOD_PER_INTERPRETER_GIL_SUPPORTED as *mut c_void,
+    },
+    pyffi::PyModuleDef_Slot {
+        slot: 0,
+        value: std::ptr::null_mut(),
+    },
+];
+
+static mut MODULE_DEF: pyffi::PyModuleDef = pyffi::PyModuleDef {
+    m_base: pyffi::PyModuleDef_HEAD_INIT,
+    m_name: OXIDIZED_IMPORTER_NAME.as_ptr() as *const _,
+    m_doc: DOC.as_ptr() as *const _,
+    m_size: std::mem::size_of::<ModuleState>() as isize,
+    m_methods: std::ptr::addr_of_mut!(METHODS) as *mut _,
+    m_slots: std::ptr::addr_of_mut!(SLOTS) as *mut _,
+    m_traverse: None,
+    m_clear: None,
+    m_free: Some(module_free),
+};
+
+static mut MODULE_DEF_PER_INTERPRETER_GIL: pyffi::PyModuleDef = pyffi::PyModuleDef {
+    m_base: pyffi::PyModuleDef_HEAD_INIT,
+    m_name: OXIDIZED_IMPORTER_NAME.as_ptr() as *const _,
+    m_doc: DOC.as_ptr() as *const _,
+    m_size: std::mem::size_of::<ModuleState>() as isize,
+    m_methods: std::ptr::addr_of_mut!(METHODS) as *mut _,
+    m_slots: std::ptr::addr_of_mut!(SLOTS_PER_INTERPRETER_GIL) as *mut _,
+    m_traverse: None,
+    m_clear: None,
+    m_free: Some(module_free),
+};
+
+/// State associated with each module instance.
+struct ModuleState {
+    /// Resources state owned by the module.
+    ///
+    /// Data indexed by it is borrowed from the creator of the module.
+    resources_state: *mut PythonResourcesState<'static, u8>,
+}
+
+/// Obtain the resources state of a module instance.
+unsafe fn get_resources_state<'a>(
+    module: *mut pyffi::PyObject,
+) -> PyResult<&'a PythonResourcesState<'a, u8>> {
+    let state = pyffi::PyModule_GetState(module) as *mut ModuleState;
+
+    if state.is_null() || (*state).resources_state.is_null() {
+        return Err(PyValueError::new_err("unable to retrieve module state"));
+    }
+
+    Ok(&*(*state).resources_state)
+}
+
+/// Convert a `sys.flags.optimize` value.
+fn optimize_level(value: i64) -> PyResult<BytecodeOptimizationLevel> {
+    match value {
+        0 => Ok(BytecodeOptimizationLevel::Zero),
+        1 => Ok(BytecodeOptimizationLevel::One),
+        2 => Ok(BytecodeOptimizationLevel::Two),
+        _ => Err(PyValueError::new_err(
+            "unexpected value for sys.flags.optimize",
+        )),
+    }
+}
+
+/// Run the implementation of a module function, handing its result to Python.
+unsafe fn trampoline(f: impl FnOnce(Python) -> PyResult<Py<PyAny>>) -> *mut pyffi::PyObject {
+    let pool = Python::assume_gil_acquired().new_pool();
+    let py = pool.python();
+
+    match catch_unwind(AssertUnwindSafe(|| f(py))) {
+        Ok(Ok(value)) => value.into_ptr(),
+        Ok(Err(e)) => {
+            e.restore(py);
+            std::ptr::null_mut()
+        }
+        Err(_) => {
+            PyRuntimeError::new_err("panic in oxidized_importer").restore(py);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// _find_module(fullname, optimize)
+///
+/// Returns `None` if the module isn't known. Otherwise a tuple of the module
+/// flavor, whether it is a package, its origin and its package path.
+unsafe extern "C" fn find_module(
+    slf: *mut pyffi::PyObject,
+    args: *mut pyffi::PyObject,
+) -> *mut pyffi::PyObject {
+    trampoline(|py| {
+        let (fullname, optimize) = py
+            .from_borrowed_ptr::<PyTuple>(args)
+            .extract::<(&str, i64)>()?;
+        let resources_state = get_resources_state(slf)?;
+
+        let module =
+            match resources_state.resolve_importable_module(fullname, optimize_level(optimize)?) {
+                Some(module) => module,
+                None => return Ok(py.None()),
+            };
+
+        resources_state
+            .verify_resource(fullname)
+            .map_err(PyImportError::new_err)?;
+
+        let package_path = if module.is_package {
+            Some(module.package_path())
+        } else {
+            None
+        };
+
+        Ok((
+            module.flavor.as_str(),
+            module.is_package,
+            module.resolve_origin(py)?,
+            package_path,
+        )
+            .into_py(py))
+    })
+}
+
+/// _get_source(fullname, optimize)
+///
+/// Returns the undecoded source code of a module as `bytes`, or `None`.
+unsafe extern "C" fn get_source(
+    slf: *mut pyffi::PyObject,
+    args: *mut pyffi::PyObject,
+) -> *mut pyffi::PyObject {
+    trampoline(|py| {
+        let (fullname, optimize) = py
+            .from_borrowed_ptr::<PyTuple>(args)
+            .extract::<(&str, i64)>()?;
+
+        let module = match get_resources_state(slf)?
+            .resolve_importable_module(fullname, optimize_level(optimize)?)
+        {
+            Some(module) => module,
+            None => return Ok(py.None()),
+        };
+
+        Ok(module.resolve_source_bytes(py)?.into_py(py))
+    })
+}
+
+/// _get_bytecode(fullname, optimize)
+///
+/// Returns the bytecode of a module as a bytes-like object, or `None`.
+unsafe extern "C" fn get_bytecode(
+    slf: *mut pyffi::PyObject,
+    args: *mut pyffi::PyObject,
+) -> *mut pyffi::PyObject {
+    trampoline(|py| {
+        let (fullname, optimize) = py
+            .from_borrowed_ptr::<PyTuple>(args)
+            .extract::<(&str, i64)>()?;
+        let optimize_level = optimize_level(optimize)?;
+
+        let module =
+            match get_resources_state(slf)?.resolve_importable_module(fullname, optimize_level) {
+                Some(module) => module,
+                None => return Ok(py.None()),
+            };
+
+        // `OwnedBuffer` is a PyO3 class. So owned data is copied into a `bytes`.
+        Ok(match module.resolve_bytecode_data(py, optimize_level)? {
+            Some(BytecodeData::Borrowed(data)) => memoryview_from_memory(py, data)?.into_py(py),
+            Some(BytecodeData::Owned(data, offset)) => {
+                PyBytes::new(py, &data[offset..]).into_py(py)
+            }
+            None => py.None(),
+        })
+    })
+}
+
+/// `Py_mod_exec` slot, defining the finder in the module.
+unsafe extern "C" fn module_exec(module: *mut pyffi::PyObject) -> c_int {
+    let py = Python::assume_gil_acquired();
+    let module = py.from_borrowed_ptr::<PyModule>(module);
+
+    match py.run(FINDER_SOURCE, Some(module.dict()), None) {
+        Ok(()) => 0,
+        Err(e) => {
+            e.restore(py);
+            -1
+        }
+    }
+}
+
+/// `m_free` of the module, dropping the resources state.
+unsafe extern "C" fn module_free(module: *mut c_void) {
+    let state = pyffi::PyModule_GetState(module as *mut _) as *mut ModuleState;
+
+    if !state.is_null() && !(*state).resources_state.is_null() {
+        drop(Box::from_raw((*state).resources_state));
+        (*state).resources_state = std::ptr::null_mut();
+    }
+}
+
+/// Create an `oxidized_importer` module for an interpreter with its own GIL.
+///
+/// The module takes ownership of the resources state. Data it indexes must
+/// outlive the current interpreter. The module is registered in `sys.modules`
+/// and an instance of its `OxidizedFinder` is returned, which isn't yet
+/// registered on `sys.meta_path`.
+///
+/// The finder imports modules and packages with source or bytecode. It
+/// leaves built-in and frozen modules to the standard importers and only
+/// imports extension modules from the filesystem. It doesn't expose package
+/// resources or any of the `OxidizedFinder` APIs beyond the `importlib`
+/// finder and loader interfaces.
+///
+/// Can be called from any interpreter.
+pub fn new_isolated_finder<'a, 'p>(
+    py: Python<'p>,
+    resources_state: Box<PythonResourcesState<'a, u8>>,
+) -> PyResult<&'p PyAny> {
+    let def = if py.version_info() >= (3, 12) {
+        std::ptr::addr_of_mut!(MODULE_DEF_PER_INTERPRETER_GIL)
+    } else {
+        std::ptr::addr_of_mut!(MODULE_DEF)
+    };
+
+    let spec = py
+        .import("_frozen_importlib")?
+        .getattr("ModuleSpec")?
+        .call1((OXIDIZED_IMPORTER_NAME_STR, py.None()))?;
+
+    let module = unsafe {
+        py.from_owned_ptr_or_err::<PyModule>(pyffi::PyModule_FromDefAndSpec(def, spec.as_ptr()))?
+    };
+
+    // Executing the module allocates its state. Module functions aren't called
+    // before the state is populated, as executing only defines the finder.
+    unsafe {
+        if pyffi::PyModule_ExecDef(module.as_ptr(), def) != 0 {
+            return Err(PyErr::fetch(py));
+        }
+
+        let state = pyffi::PyModule_GetState(module.as_ptr()) as *mut ModuleState;
+
+        if state.is_null() {
+            return Err(PyValueError::new_err("unable to retrieve module state"));
+        }
+
+        (*state).resources_state = Box::into_raw(resources_state) as *mut _;
+    }
+
+    py.import("sys")?
+        .getattr("modules")?
+        .set_item(OXIDIZED_IMPORTER_NAME_STR, module)?;
+
+    module.getattr("OxidizedFinder")?.call0()
+}
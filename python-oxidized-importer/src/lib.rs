@@ -4,11 +4,17 @@
 
 //! oxidized_importer Python extension.
 
+mod allocator;
+mod build_info;
 mod conversion;
+mod coverage_plugin;
 #[allow(clippy::needless_option_as_deref)]
 mod importer;
+mod isolated_importer;
 #[cfg(windows)]
 mod memory_dll;
+#[cfg(target_os = "linux")]
+mod memory_so;
 mod package_metadata;
 #[allow(clippy::needless_option_as_deref)]
 mod path_entry_finder;
@@ -19,15 +25,23 @@ mod python_resource_types;
 mod python_resources;
 mod resource_reader;
 mod resource_scanning;
+#[cfg(feature = "static-server")]
+mod static_server;
+#[cfg(feature = "wasm")]
+mod wasm;
 #[cfg(feature = "zipimport")]
 #[allow(clippy::needless_option_as_deref)]
 mod zip_import;
 
 pub use crate::{
+    allocator::set_allocator_stats_function,
+    build_info::set_build_info,
     importer::{
-        install_path_hook, remove_external_importers, replace_meta_path_importers, ImporterState,
+        install_path_hook, install_restricted_path_importer, install_zipimport_importer,
+        remove_external_importers, replace_meta_path_importers, ImportTimingFn, ImporterState,
         OxidizedFinder,
     },
+    isolated_importer::new_isolated_finder,
     python_resource_collector::PyTempDir,
     python_resources::{PackedResourcesSource, PythonResourcesState},
 };
@@ -40,7 +54,7 @@ use {
         path_entry_finder::OxidizedPathEntryFinder,
         pkg_resources::{register_pkg_resources_with_module, OxidizedPkgResourcesProvider},
         python_resources::OxidizedResource,
-        resource_reader::OxidizedResourceReader,
+        resource_reader::{OxidizedResourceReader, OxidizedTraversable},
     },
     pyo3::{
         exceptions::{PyImportError, PyValueError},
@@ -163,6 +177,14 @@ fn register_pkg_resources(py: Python) -> PyResult<()> {
     register_pkg_resources_with_module(py, py.import("pkg_resources")?)
 }
 
+/// Select the locale whose localized package resources are visible.
+///
+/// Passing `None` deselects any previously selected locale.
+#[pyfunction]
+fn select_locale(locale: Option<&str>) {
+    crate::python_resources::select_locale(locale)
+}
+
 /// Initialize the Python module object.
 ///
 /// This is called as part of the PyInit_* function to create the internal
@@ -184,17 +206,22 @@ fn module_init(py: Python, m: &PyModule) -> PyResult<()> {
 
     state.initialized = false;
 
+    crate::allocator::init_module(py, m)?;
+    crate::build_info::init_module(py, m)?;
+    crate::coverage_plugin::init_module(m)?;
     crate::pkg_resources::init_module(m)?;
     crate::resource_scanning::init_module(m)?;
 
     m.add_function(wrap_pyfunction!(decode_source, m)?)?;
     m.add_function(wrap_pyfunction!(register_pkg_resources, m)?)?;
+    m.add_function(wrap_pyfunction!(select_locale, m)?)?;
 
     m.add_class::<crate::package_metadata::OxidizedDistribution>()?;
     m.add_class::<OxidizedFinder>()?;
     m.add_class::<OxidizedResource>()?;
     m.add_class::<crate::python_resource_collector::OxidizedResourceCollector>()?;
     m.add_class::<OxidizedResourceReader>()?;
+    m.add_class::<OxidizedTraversable>()?;
     m.add_class::<OxidizedPathEntryFinder>()?;
     m.add_class::<OxidizedPkgResourcesProvider>()?;
     m.add_class::<crate::python_resource_types::PythonModuleSource>()?;
@@ -203,11 +230,33 @@ fn module_init(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<crate::python_resource_types::PythonPackageDistributionResource>()?;
     m.add_class::<crate::python_resource_types::PythonExtensionModule>()?;
 
+    init_static_server(m)?;
+    init_wasm(py, m)?;
     init_zipimport(m)?;
 
     Ok(())
 }
 
+#[cfg(feature = "static-server")]
+fn init_static_server(m: &PyModule) -> PyResult<()> {
+    crate::static_server::init_module(m)
+}
+
+#[cfg(not(feature = "static-server"))]
+fn init_static_server(_m: &PyModule) -> PyResult<()> {
+    Ok(())
+}
+
+#[cfg(feature = "wasm")]
+fn init_wasm(py: Python, m: &PyModule) -> PyResult<()> {
+    crate::wasm::init_module(py, m)
+}
+
+#[cfg(not(feature = "wasm"))]
+fn init_wasm(_py: Python, _m: &PyModule) -> PyResult<()> {
+    Ok(())
+}
+
 #[cfg(feature = "zipimport")]
 fn init_zipimport(m: &PyModule) -> PyResult<()> {
     m.add_class::<crate::zip_import::OxidizedZipFinder>()?;
@@ -10,13 +10,15 @@ not a Python `module`.
 
 use {
     crate::python_resources::PythonResourcesState,
+    goblin::pe::{options::ParseOptions, utils::find_offset, PE},
     memory_module_sys::{
         MemoryFreeLibrary, MemoryGetProcAddress, MemoryLoadLibraryEx, HCUSTOMMODULE,
     },
     once_cell::sync::Lazy,
     std::{
-        collections::HashMap,
-        ffi::{c_void, CStr},
+        collections::{HashMap, HashSet},
+        ffi::{c_void, CStr, CString},
+        fmt::{Display, Formatter},
         sync::{
             atomic::{AtomicUsize, Ordering},
             Mutex,
@@ -29,8 +31,11 @@ use {
             ntdef::LPCSTR,
         },
         um::{
-            libloaderapi::{FreeLibrary, GetProcAddress, LoadLibraryA},
-            memoryapi::{VirtualAlloc, VirtualFree},
+            libloaderapi::{
+                FreeLibrary, GetProcAddress, LoadLibraryA, LoadLibraryExA, LOAD_LIBRARY_AS_DATAFILE,
+            },
+            memoryapi::{VirtualAlloc, VirtualFree, VirtualProtect},
+            winnt::PAGE_READWRITE,
         },
     },
 };
@@ -78,11 +83,16 @@ static MEMORY_MODULES: Lazy<Mutex<MemoryModules>> = Lazy::new(|| {
 ///
 /// This is the primary interface to use for initiating the load of a library from memory.
 /// It handles setting up user data and dispatching with the appropriate hooks set.
+///
+/// Delay-loaded DLLs available in memory are loaded and bound immediately,
+/// as the delay-load helper can only load DLLs from the filesystem.
+///
+/// On failure, the error describes imports which couldn't be resolved.
 pub(crate) unsafe fn load_library_memory(
     resources_state: &PythonResourcesState<u8>,
     data: &[u8],
-) -> *const c_void {
-    MemoryLoadLibraryEx(
+) -> Result<*const c_void, String> {
+    let module = MemoryLoadLibraryEx(
         data.as_ptr() as *const c_void,
         data.len(),
         default_alloc,
@@ -91,7 +101,31 @@ pub(crate) unsafe fn load_library_memory(
         custom_get_proc_address,
         custom_free_library,
         resources_state as *const PythonResourcesState<u8> as *mut c_void,
-    )
+    );
+
+    if module.is_null() {
+        let mut unresolved = vec![];
+        find_unresolved_imports(
+            resources_state,
+            None,
+            data,
+            &mut HashSet::new(),
+            &mut unresolved,
+        );
+
+        return Err(if unresolved.is_empty() {
+            "unknown error".to_string()
+        } else {
+            format!("unresolved imports: {}", unresolved.join(", "))
+        });
+    }
+
+    if let Err(e) = bind_delay_imports(resources_state, data, module) {
+        MemoryFreeLibrary(module);
+        return Err(e);
+    }
+
+    Ok(module)
 }
 
 /// Free a library that was loaded from memory.
@@ -157,22 +191,23 @@ extern "C" fn custom_load_library(filename: LPCSTR, user_data: *mut c_void) -> H
             .unwrap()
     };
 
-    if let Some(library_data) = resources_state.resolve_in_memory_shared_library_data(&name) {
-        let res = unsafe { load_library_memory(resources_state, library_data) };
+    if let Some(library_data) = resolve_library_data(resources_state, &name) {
+        let res = match unsafe { load_library_memory(resources_state, library_data) } {
+            Ok(res) => res,
+            // Failures are described by the loader of the importing library.
+            Err(_) => return std::ptr::null(),
+        };
 
-        // If we loaded a module, store its state. Otherwise return its failure (NULL).
-        if !res.is_null() {
-            let mut memory_state = MEMORY_MODULES.lock().unwrap();
+        let mut memory_state = MEMORY_MODULES.lock().unwrap();
 
-            memory_state.modules.insert(
-                name.to_string(),
-                MemoryModule {
-                    ptr: res,
-                    ref_count: AtomicUsize::new(1),
-                },
-            );
-            memory_state.module_ptrs.push(res);
-        }
+        memory_state.modules.insert(
+            name.to_string(),
+            MemoryModule {
+                ptr: res,
+                ref_count: AtomicUsize::new(1),
+            },
+        );
+        memory_state.module_ptrs.push(res);
 
         return res;
     }
@@ -213,13 +248,7 @@ extern "C" fn custom_get_proc_address(
 extern "C" fn custom_free_library(module: HCUSTOMMODULE, _user_data: *mut c_void) {
     let mut memory_state = MEMORY_MODULES.lock().unwrap();
 
-    if let Some(index) = memory_state
-        .module_ptrs
-        .iter()
-        .position(|ptr| ptr == &module)
-    {
-        memory_state.module_ptrs.remove(index);
-
+    if memory_state.module_ptrs.contains(&module) {
         let mut free_module = None;
 
         for (name, module_state) in &memory_state.modules {
@@ -231,16 +260,392 @@ extern "C" fn custom_free_library(module: HCUSTOMMODULE, _user_data: *mut c_void
             }
         }
 
+        // The module stays loaded until its last reference is released.
         if let Some(free_module) = free_module {
             memory_state.modules.remove(&free_module);
+            memory_state.module_ptrs.retain(|ptr| ptr != &module);
 
             // Unlock to avoid potential for deadlock due to recursion.
             std::mem::drop(memory_state);
-            return unsafe { MemoryFreeLibrary(module) };
+            unsafe { MemoryFreeLibrary(module) };
         }
+
+        return;
     }
 
     unsafe {
         FreeLibrary(module as *mut HINSTANCE__);
     }
 }
+
+/// Find DLL data for an imported DLL name in resources state.
+///
+/// Import tables refer to DLLs by file name, which is matched case-insensitively
+/// by Windows. Shared libraries are usually indexed by name without the `.dll`
+/// extension. So several variations of the name are tried.
+fn resolve_library_data<'a>(
+    resources_state: &'a PythonResourcesState<u8>,
+    name: &str,
+) -> Option<&'a [u8]> {
+    let stem = name
+        .len()
+        .checked_sub(4)
+        .filter(|index| name.is_char_boundary(*index))
+        .filter(|index| name[*index..].eq_ignore_ascii_case(".dll"))
+        .map(|index| &name[..index]);
+
+    [
+        Some(name.to_string()),
+        stem.map(|stem| stem.to_string()),
+        Some(name.to_lowercase()),
+        stem.map(|stem| stem.to_lowercase()),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|candidate| resources_state.resolve_in_memory_shared_library_data(&candidate))
+}
+
+/// Whether a DLL can be found by the system loader.
+fn system_library_exists(name: &str) -> bool {
+    let name = match CString::new(name) {
+        Ok(name) => name,
+        Err(_) => return false,
+    };
+
+    // Loading as a data file searches for the DLL without executing any of its code.
+    let handle = unsafe {
+        LoadLibraryExA(
+            name.as_ptr(),
+            std::ptr::null_mut(),
+            LOAD_LIBRARY_AS_DATAFILE,
+        )
+    };
+
+    if handle.is_null() {
+        false
+    } else {
+        unsafe { FreeLibrary(handle) };
+        true
+    }
+}
+
+/// Collect descriptions of imports of a library which can't be resolved.
+///
+/// Imported DLLs must be available in memory or to the system loader. Symbols
+/// imported from in-memory DLLs must be exported by them. In-memory DLLs are
+/// searched recursively. Delay-loaded DLLs not in memory are only loaded when
+/// used, so they aren't reported.
+fn find_unresolved_imports(
+    resources_state: &PythonResourcesState<u8>,
+    name: Option<&str>,
+    data: &[u8],
+    seen: &mut HashSet<String>,
+    unresolved: &mut Vec<String>,
+) {
+    let imported_by = name
+        .map(|name| format!(" (imported by {})", name))
+        .unwrap_or_default();
+
+    let pe = match PE::parse(data) {
+        Ok(pe) => pe,
+        Err(e) => {
+            unresolved.push(format!("unable to parse library{}: {}", imported_by, e));
+            return;
+        }
+    };
+
+    if let Err(e) = parse_delay_imports(&pe, data) {
+        unresolved.push(format!("{}{}", e, imported_by));
+    }
+
+    for dll in &pe.libraries {
+        if !seen.insert(dll.to_lowercase()) {
+            continue;
+        }
+
+        let library_data = match resolve_library_data(resources_state, dll) {
+            Some(library_data) => library_data,
+            None => {
+                if !system_library_exists(dll) {
+                    unresolved.push(format!("{}{}", dll, imported_by));
+                }
+                continue;
+            }
+        };
+
+        if let Ok(library) = PE::parse(library_data) {
+            let exports = library
+                .exports
+                .iter()
+                .filter_map(|export| export.name)
+                .collect::<HashSet<_>>();
+
+            for import in pe.imports.iter().filter(|import| import.dll == *dll) {
+                // Symbols imported by ordinal have synthesized names.
+                if import.name.starts_with("ORDINAL ") {
+                    continue;
+                }
+
+                if !exports.contains(import.name.as_ref()) {
+                    unresolved.push(format!("{}!{}{}", dll, import.name, imported_by));
+                }
+            }
+        }
+
+        find_unresolved_imports(resources_state, Some(dll), library_data, seen, unresolved);
+    }
+}
+
+/// A symbol imported from a DLL.
+enum ImportSymbol {
+    Name(CString),
+    Ordinal(u16),
+}
+
+impl Display for ImportSymbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Name(name) => f.write_str(&name.to_string_lossy()),
+            Self::Ordinal(ordinal) => write!(f, "ordinal {}", ordinal),
+        }
+    }
+}
+
+/// A DLL a library loads lazily via the delay-load mechanism.
+struct DelayImport {
+    /// Name of the DLL.
+    dll: String,
+    /// RVA of the variable holding the DLL's module handle.
+    module_rva: usize,
+    /// RVA of the import address table.
+    iat_rva: usize,
+    /// Imported symbols, in import address table order.
+    symbols: Vec<ImportSymbol>,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn read_cstr(data: &[u8], offset: usize) -> Option<&[u8]> {
+    let data = data.get(offset..)?;
+
+    Some(&data[0..data.iter().position(|b| *b == 0)?])
+}
+
+/// Parse the delay import table of a library.
+fn parse_delay_imports(pe: &PE, data: &[u8]) -> Result<Vec<DelayImport>, String> {
+    let optional_header = match &pe.header.optional_header {
+        Some(optional_header) => optional_header,
+        None => return Ok(vec![]),
+    };
+
+    let directory = match optional_header
+        .data_directories
+        .get_delay_import_descriptor()
+    {
+        Some(directory) if directory.virtual_address != 0 => directory,
+        _ => return Ok(vec![]),
+    };
+
+    let options = ParseOptions::default();
+    let offset = |rva: usize| {
+        find_offset(
+            rva,
+            &pe.sections,
+            optional_header.windows_fields.file_alignment,
+            &options,
+        )
+        .ok_or_else(|| format!("invalid address {:#x} in delay import table", rva))
+    };
+    let truncated = || "truncated delay import table".to_string();
+
+    let mut imports = vec![];
+    let mut descriptor = offset(directory.virtual_address as usize)?;
+
+    loop {
+        let field = |index: usize| read_u32(data, descriptor + index * 4).ok_or_else(truncated);
+
+        let attributes = field(0)?;
+        let name = field(1)?;
+
+        if name == 0 {
+            break;
+        }
+
+        // Descriptors without the RVA attribute hold virtual addresses.
+        let rva = |value: u64| {
+            if attributes & 1 != 0 {
+                value as usize
+            } else {
+                (value as usize).wrapping_sub(pe.image_base)
+            }
+        };
+
+        let dll = String::from_utf8_lossy(
+            read_cstr(data, offset(rva(name as u64))?).ok_or_else(truncated)?,
+        )
+        .to_string();
+
+        let mut symbols = vec![];
+        let mut thunk = offset(rva(field(4)? as u64))?;
+
+        loop {
+            let (value, ordinal_flag, size) = if pe.is_64 {
+                (read_u64(data, thunk).ok_or_else(truncated)?, 1 << 63, 8)
+            } else {
+                (
+                    read_u32(data, thunk).ok_or_else(truncated)? as u64,
+                    1 << 31,
+                    4,
+                )
+            };
+
+            if value == 0 {
+                break;
+            }
+
+            if value & ordinal_flag != 0 {
+                symbols.push(ImportSymbol::Ordinal(value as u16));
+            } else {
+                // Names are preceded by a 2 byte hint.
+                let name = read_cstr(data, offset(rva(value))? + 2).ok_or_else(truncated)?;
+
+                symbols.push(ImportSymbol::Name(
+                    CString::new(name).map_err(|e| e.to_string())?,
+                ));
+            }
+
+            thunk += size;
+        }
+
+        imports.push(DelayImport {
+            dll,
+            module_rva: rva(field(2)? as u64),
+            iat_rva: rva(field(3)? as u64),
+            symbols,
+        });
+
+        descriptor += 32;
+    }
+
+    Ok(imports)
+}
+
+/// Resolve the address a library loaded from memory is mapped at.
+///
+/// MemoryModule doesn't expose it. But it is the address of any exported
+/// symbol minus that symbol's RVA.
+unsafe fn memory_module_base(pe: &PE, module: *const c_void) -> Option<usize> {
+    pe.exports.iter().find_map(|export| {
+        let name = CString::new(export.name?).ok()?;
+        let address = MemoryGetProcAddress(module, name.as_ptr());
+
+        if address.is_null() {
+            None
+        } else {
+            Some(address as usize - export.rva)
+        }
+    })
+}
+
+/// Write values to memory which may be read-only.
+unsafe fn write_protected_memory(address: usize, values: &[usize]) -> Result<(), String> {
+    let size = std::mem::size_of_val(values);
+    let mut protect = 0;
+
+    if VirtualProtect(address as LPVOID, size, PAGE_READWRITE, &mut protect) == 0 {
+        return Err("unable to make delay import table writable".to_string());
+    }
+
+    std::ptr::copy_nonoverlapping(values.as_ptr(), address as *mut usize, values.len());
+
+    VirtualProtect(address as LPVOID, size, protect, &mut protect);
+
+    Ok(())
+}
+
+/// Load and bind delay-loaded DLLs of a library that are available in memory.
+///
+/// The delay-load helper only calls `LoadLibrary()`, which can't find DLLs
+/// that only exist in memory. So these DLLs are loaded now and the import
+/// address table is populated so the helper is never called for them. Other
+/// delay-loaded DLLs are left to the helper.
+unsafe fn bind_delay_imports(
+    resources_state: &PythonResourcesState<u8>,
+    data: &[u8],
+    module: *const c_void,
+) -> Result<(), String> {
+    let pe = PE::parse(data).map_err(|e| e.to_string())?;
+    let mut module_base = None;
+
+    for import in parse_delay_imports(&pe, data)? {
+        if resolve_library_data(resources_state, &import.dll).is_none() {
+            continue;
+        }
+
+        let base = match module_base {
+            Some(base) => base,
+            None => *module_base.insert(memory_module_base(&pe, module).ok_or_else(|| {
+                "unable to resolve address of library loaded from memory".to_string()
+            })?),
+        };
+
+        let dll = CString::new(import.dll.as_str()).map_err(|e| e.to_string())?;
+        let handle = custom_load_library(
+            dll.as_ptr(),
+            resources_state as *const PythonResourcesState<u8> as *mut c_void,
+        );
+
+        if handle.is_null() {
+            let mut unresolved = vec![];
+            find_unresolved_imports(
+                resources_state,
+                Some(&import.dll),
+                resolve_library_data(resources_state, &import.dll).unwrap(),
+                &mut HashSet::new(),
+                &mut unresolved,
+            );
+
+            return Err(format!(
+                "unable to load delay-loaded {} from memory; unresolved imports: {}",
+                import.dll,
+                unresolved.join(", ")
+            ));
+        }
+
+        let mut addresses = vec![];
+
+        for symbol in &import.symbols {
+            let name = match symbol {
+                ImportSymbol::Name(name) => name.as_ptr(),
+                ImportSymbol::Ordinal(ordinal) => *ordinal as usize as LPCSTR,
+            };
+
+            let address = custom_get_proc_address(handle, name, std::ptr::null_mut());
+
+            if address.is_null() {
+                return Err(format!(
+                    "unresolved imports: {}!{} (delay-loaded)",
+                    import.dll, symbol
+                ));
+            }
+
+            addresses.push(address as usize);
+        }
+
+        write_protected_memory(base + import.iat_rva, &addresses)?;
+
+        if import.module_rva != 0 {
+            write_protected_memory(base + import.module_rva, &[handle as usize])?;
+        }
+    }
+
+    Ok(())
+}
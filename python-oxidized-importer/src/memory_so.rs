@@ -0,0 +1,133 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Functionality for loading Linux shared libraries from memory.
+
+The dynamic loader can only load libraries from files. So library data is
+written to an anonymous, memory backed file created with `memfd_create()`
+and the library is loaded from its `/proc/self/fd/` path. Nothing is written
+to a filesystem.
+*/
+
+use {
+    crate::python_resources::PythonResourcesState,
+    once_cell::sync::Lazy,
+    std::{
+        collections::HashSet,
+        ffi::{CStr, CString},
+        fs::File,
+        io::Write,
+        os::unix::io::{AsRawFd, FromRawFd},
+        sync::Mutex,
+    },
+};
+
+/// Names of shared libraries that were loaded from memory.
+static LOADED_LIBRARIES: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Memory backed files libraries were loaded from.
+///
+/// The dynamic loader identifies loaded libraries by path. If a file were
+/// closed, its descriptor number and therefore its `/proc/self/fd/` path
+/// could be reused by another library, which the loader would mistake for
+/// the already loaded one. So files are kept open for the life of the process.
+static MEMORY_FILES: Lazy<Mutex<Vec<File>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Keep a memory backed file open for the life of the process.
+pub(crate) fn retain_memfd(fh: File) {
+    MEMORY_FILES.lock().unwrap().push(fh);
+}
+
+/// Write data to a new memory backed file.
+///
+/// `name` is only used for display purposes, e.g. in `/proc/self/maps`.
+pub(crate) fn memfd_from_data(name: &str, data: &[u8]) -> std::io::Result<File> {
+    let name =
+        CString::new(name).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+    // The libc wrapper for memfd_create() requires glibc 2.27. But binaries
+    // may target older glibc versions. So we call the system call directly.
+    let fd = unsafe {
+        libc::syscall(
+            libc::SYS_memfd_create,
+            name.as_ptr(),
+            libc::MFD_CLOEXEC as libc::c_uint,
+        )
+    };
+
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let mut fh = unsafe { File::from_raw_fd(fd as libc::c_int) };
+    fh.write_all(data)?;
+
+    Ok(fh)
+}
+
+/// Obtain the path the dynamic loader can open a memory backed file from.
+pub(crate) fn memfd_path(fh: &File) -> String {
+    format!("/proc/self/fd/{}", fh.as_raw_fd())
+}
+
+fn dlerror() -> String {
+    let message = unsafe { libc::dlerror() };
+
+    if message.is_null() {
+        "unknown error".to_string()
+    } else {
+        unsafe { CStr::from_ptr(message) }
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+/// Load the in-memory shared libraries a resource depends on.
+///
+/// Libraries are loaded before the resource depending on them so the dynamic
+/// loader finds them by their soname instead of searching the filesystem.
+/// Dependencies not available in memory are left for the dynamic loader to
+/// resolve. Loaded libraries are never unloaded.
+pub(crate) fn load_shared_library_dependencies(
+    resources_state: &PythonResourcesState<u8>,
+    name: &str,
+) -> Result<(), String> {
+    for dependency in resources_state.resolve_shared_library_dependency_names(name) {
+        let data = match resources_state.resolve_in_memory_shared_library_data(dependency) {
+            Some(data) => data,
+            None => continue,
+        };
+
+        // Recording the library before loading it prevents infinite recursion
+        // on dependency cycles. The lock is released since we may recurse.
+        if !LOADED_LIBRARIES
+            .lock()
+            .unwrap()
+            .insert(dependency.to_string())
+        {
+            continue;
+        }
+
+        load_shared_library_dependencies(resources_state, dependency)?;
+
+        let fh = memfd_from_data(dependency, data)
+            .map_err(|e| format!("unable to create memory file for {}: {}", dependency, e))?;
+        let path = CString::new(memfd_path(&fh)).unwrap();
+
+        let handle = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_GLOBAL) };
+        retain_memfd(fh);
+
+        if handle.is_null() {
+            LOADED_LIBRARIES.lock().unwrap().remove(dependency);
+
+            return Err(format!(
+                "unable to load shared library {} from memory: {}",
+                dependency,
+                dlerror()
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -9,7 +9,7 @@ use {
         python_resources::{name_at_package_hierarchy, name_within_package_hierarchy},
     },
     pyo3::{
-        exceptions::{PyIOError, PyNotImplementedError, PyValueError},
+        exceptions::{PyIOError, PyValueError},
         prelude::*,
         types::{PyBytes, PyDict, PyList, PyString, PyTuple, PyType},
     },
@@ -34,6 +34,26 @@ fn discover_resolvers(py: Python) -> PyResult<&PyList> {
     Ok(PyList::new(py, resolvers))
 }
 
+/// Normalize a distribution name for comparison.
+///
+/// Emulates importlib.metadata.Prepared.normalize(): runs of `-`, `_`, and `.`
+/// are equivalent and comparison is case insensitive.
+fn normalize_distribution_name(name: &str) -> String {
+    let mut res = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !res.ends_with('_') {
+                res.push('_');
+            }
+        } else {
+            res.extend(c.to_lowercase());
+        }
+    }
+
+    res
+}
+
 /// A importlib.metadata.Distribution allowing access to package distribution data.
 #[pyclass(module = "oxidized_importer")]
 pub(crate) struct OxidizedDistribution {
@@ -220,6 +240,9 @@ impl OxidizedDistribution {
 
         if let Ok(entry_points) = importlib_metadata.getattr("EntryPoints") {
             entry_points.call_method("_from_text_for", (text, self_), None)
+        } else if text.is_none() {
+            // Python 3.9 and older can't parse missing text.
+            Ok(PyList::empty(py).into())
         } else {
             let entry_point = importlib_metadata.getattr("EntryPoint")?;
 
@@ -227,9 +250,81 @@ impl OxidizedDistribution {
         }
     }
 
+    /// Files in this distribution.
+    ///
+    /// Returns a list of importlib.metadata.PackagePath resolved from the
+    /// `RECORD` file or the `SOURCES.txt` file of legacy egg metadata. Returns
+    /// None if neither exists.
     #[getter]
-    fn files(&self) -> PyResult<()> {
-        Err(PyNotImplementedError::new_err(()))
+    fn files<'p>(slf: &'p PyCell<Self>, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let this = slf.borrow();
+
+        let importlib_metadata = py.import("importlib.metadata")?;
+        let package_path = importlib_metadata.getattr("PackagePath")?;
+        let file_hash = importlib_metadata.getattr("FileHash")?;
+
+        let mut entries = vec![];
+
+        let record = this.read_text(py, "RECORD".into())?;
+
+        if !record.is_none() {
+            let lines = record.call_method0("splitlines")?;
+
+            for row in py.import("csv")?.call_method1("reader", (lines,))?.iter()? {
+                let row = row?.extract::<Vec<String>>()?;
+
+                if let Some(name) = row.first() {
+                    entries.push((name.clone(), row.get(1).cloned(), row.get(2).cloned()));
+                }
+            }
+        } else {
+            let sources = this.read_text(py, "SOURCES.txt".into())?;
+
+            if sources.is_none() {
+                return Ok(py.None().into_ref(py));
+            }
+
+            for line in sources.extract::<String>()?.lines() {
+                entries.push((line.to_string(), None, None));
+            }
+        }
+
+        let res = PyList::empty(py);
+
+        for (name, hash, size) in entries {
+            let path = package_path.call1((name,))?;
+
+            let hash = match hash.filter(|hash| !hash.is_empty()) {
+                Some(hash) => file_hash.call1((hash,))?,
+                None => py.None().into_ref(py),
+            };
+            let size = size.and_then(|size| size.parse::<u64>().ok());
+
+            path.setattr("hash", hash)?;
+            path.setattr("size", size)?;
+            path.setattr("dist", slf)?;
+
+            res.append(path)?;
+        }
+
+        Ok(res.into())
+    }
+
+    /// Return a path to a file in this distribution.
+    ///
+    /// Paths are relative to the directory holding the distribution's
+    /// metadata directory. Files are not guaranteed to exist on the
+    /// filesystem, as resources may be loaded from memory.
+    fn locate_file<'p>(&self, py: Python<'p>, path: &PyAny) -> PyResult<&'p PyAny> {
+        let root = self
+            .state
+            .get_resources_state()
+            .package_distribution_root(&self.package);
+
+        py.import("pathlib")?
+            .getattr("Path")?
+            .call1((root,))?
+            .call_method1("joinpath", (path,))
     }
 
     #[getter]
@@ -277,12 +372,8 @@ pub(crate) fn find_distributions<'p>(
         .package_distribution_names(|match_name| {
             if let Some(name) = name {
                 // Python normalizes the name. We do the same.
-                let name = name.to_string();
-                let name = name.to_lowercase().replace('-', "_");
-
-                let match_name = match_name.to_lowercase().replace('-', "_");
-
-                match_name == name
+                normalize_distribution_name(match_name)
+                    == normalize_distribution_name(&name.to_string())
             } else {
                 true
             }
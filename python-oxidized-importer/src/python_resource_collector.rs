@@ -290,7 +290,7 @@ impl OxidizedResourceCollector {
         let mut resources = Vec::new();
 
         for resource in prepared.resources.values() {
-            resources.push(resource_to_pyobject(py, resource)?);
+            resources.push(resource_to_pyobject(py, resource, None)?);
         }
 
         let mut file_installs = Vec::new();
@@ -296,6 +296,21 @@ impl PythonPackageResource {
             Err(PyTypeError::new_err("cannot delete data"))
         }
     }
+
+    #[getter]
+    fn get_locale(&self) -> Option<String> {
+        self.resource.borrow().locale.clone()
+    }
+
+    #[setter]
+    fn set_locale(&self, value: Option<&PyAny>) -> PyResult<()> {
+        self.resource.borrow_mut().locale = match value {
+            Some(value) if !value.is_none() => Some(value.extract::<String>()?),
+            _ => None,
+        };
+
+        Ok(())
+    }
 }
 
 #[pyclass(module = "oxidized_importer")]
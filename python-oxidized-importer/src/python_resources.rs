@@ -19,22 +19,246 @@ use {
         ffi as pyffi,
         prelude::*,
         types::{PyBytes, PyDict, PyList, PyString, PyTuple},
-        PyTypeInfo,
+        AsPyPointer, PyTypeInfo,
     },
-    python_packaging::resource::BytecodeOptimizationLevel,
-    python_packed_resources::Resource,
+    python_packaging::{
+        interpreter::PackedResourcesVerification,
+        resource::{
+            decompress_data, decompress_data_with_dictionary, zstd_dictionary_id,
+            zstd_frame_dictionary_id, BytecodeOptimizationLevel,
+        },
+    },
+    python_packed_resources::{DataCompression, Resource},
+    sha2::{Digest, Sha256},
     std::{
         borrow::Cow,
         cell::RefCell,
-        collections::{hash_map::Entry, BTreeSet, HashMap},
+        collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet},
         ffi::CStr,
         os::raw::c_int,
         path::{Path, PathBuf},
+        sync::{Arc, RwLock},
     },
 };
 
 const ENOENT: c_int = 2;
 
+/// Locale whose localized package resources are exposed to `importlib.resources`.
+static SELECTED_LOCALE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Select the locale whose localized package resources should be visible.
+///
+/// The encoding and modifier of POSIX locale names (e.g. `.UTF-8` in
+/// `de_DE.UTF-8`) are ignored.
+pub(crate) fn select_locale(locale: Option<&str>) {
+    let locale = locale
+        .map(|locale| locale.split(['.', '@']).next().unwrap_or_default())
+        .filter(|locale| !locale.is_empty())
+        .map(|locale| locale.replace('-', "_"));
+
+    *SELECTED_LOCALE.write().unwrap() = locale;
+}
+
+/// Obtain the localized in-memory package resources for the selected locale.
+///
+/// Resources of an exact match of the selected locale come first, followed by
+/// those of the language part of the locale (e.g. `de` for `de_DE`).
+fn localized_package_resources<'r, 'a>(
+    entry: &'r Resource<'a, u8>,
+) -> Vec<&'r HashMap<Cow<'a, str>, Cow<'a, [u8]>>> {
+    let locales = match &entry.in_memory_localized_package_resources {
+        Some(locales) => locales,
+        None => return vec![],
+    };
+
+    let selected = SELECTED_LOCALE.read().unwrap();
+    let locale = match selected.as_deref() {
+        Some(locale) => locale,
+        None => return vec![],
+    };
+
+    let mut res = Vec::with_capacity(2);
+    res.extend(locales.get(locale));
+
+    if let Some((language, _)) = locale.split_once('_') {
+        res.extend(locales.get(language));
+    }
+
+    res
+}
+
+/// Obtain all in-memory package resources visible for a resource.
+///
+/// Resources of the selected locale come first so they take precedence over
+/// unlocalized resources having the same name.
+fn in_memory_package_resources<'r, 'a>(
+    entry: &'r Resource<'a, u8>,
+) -> impl Iterator<Item = &'r HashMap<Cow<'a, str>, Cow<'a, [u8]>>> {
+    localized_package_resources(entry)
+        .into_iter()
+        .chain(entry.in_memory_package_resources.as_ref())
+}
+
+/// Decompress resource data, resolving shared zstd dictionaries as needed.
+///
+/// The GIL is released during decompression so other threads can import
+/// concurrently.
+fn decompress_resource_data(
+    py: Python,
+    compression: DataCompression,
+    data: &[u8],
+    dictionaries: &HashMap<u32, Cow<[u8]>>,
+) -> anyhow::Result<Vec<u8>> {
+    let dictionary = if compression == DataCompression::ZstdDictionary {
+        Some(
+            zstd_frame_dictionary_id(data)
+                .and_then(|id| dictionaries.get(&id))
+                .ok_or_else(|| anyhow::anyhow!("zstd compression dictionary not found"))?,
+        )
+    } else {
+        None
+    };
+
+    py.allow_threads(|| match dictionary {
+        Some(dictionary) => decompress_data_with_dictionary(data, dictionary),
+        None => decompress_data(compression, data),
+    })
+}
+
+/// Obtain a `memoryview` of memory living as long as the resources state.
+pub(crate) fn memoryview_from_memory(py: Python, data: &[u8]) -> PyResult<Option<Py<PyAny>>> {
+    let ptr = unsafe {
+        pyffi::PyMemoryView_FromMemory(data.as_ptr() as _, data.len() as _, pyffi::PyBUF_READ)
+    };
+
+    if ptr.is_null() {
+        Ok(None)
+    } else {
+        Ok(Some(unsafe { PyObject::from_owned_ptr(py, ptr) }))
+    }
+}
+
+/// Read a file without holding the GIL.
+fn read_file_without_gil(py: Python, path: &Path) -> std::io::Result<Vec<u8>> {
+    py.allow_threads(|| std::fs::read(path))
+}
+
+/// Data owned by Rust exposed to Python through the buffer protocol.
+///
+/// This allows handing large buffers to Python without copying them into a `bytes`.
+#[pyclass(module = "oxidized_importer")]
+pub(crate) struct OwnedBuffer {
+    data: Vec<u8>,
+    /// Offset into `data` where exposed data starts.
+    offset: usize,
+}
+
+#[pymethods]
+impl OwnedBuffer {
+    unsafe fn __getbuffer__(
+        slf: PyRefMut<Self>,
+        view: *mut pyffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        let data = &slf.data[slf.offset..];
+
+        if pyffi::PyBuffer_FillInfo(
+            view,
+            slf.as_ptr(),
+            data.as_ptr() as *mut _,
+            data.len() as _,
+            1,
+            flags,
+        ) != 0
+        {
+            return Err(PyErr::fetch(slf.py()));
+        }
+
+        Ok(())
+    }
+}
+
+impl OwnedBuffer {
+    /// Obtain a `memoryview` of data starting at an offset, taking ownership of data.
+    ///
+    /// `offset` must not be greater than the length of data.
+    pub(crate) fn memoryview_at(py: Python, data: Vec<u8>, offset: usize) -> PyResult<Py<PyAny>> {
+        assert!(offset <= data.len());
+        let buffer = Py::new(py, Self { data, offset })?;
+
+        unsafe {
+            PyObject::from_owned_ptr_or_err(py, pyffi::PyMemoryView_FromObject(buffer.as_ptr()))
+        }
+    }
+}
+
+/// Bytecode data of a module.
+pub(crate) enum BytecodeData<'a> {
+    /// Data borrowed from memory living as long as the resources state.
+    Borrowed(&'a [u8]),
+    /// Data owned by us and the offset where bytecode starts.
+    Owned(Vec<u8>, usize),
+}
+
+/// Obtain a `bytes` for in-memory package resource data, decompressing it if necessary.
+#[allow(clippy::ptr_arg)]
+fn package_resource_bytes<'p>(
+    py: Python<'p>,
+    entry: &Resource<u8>,
+    data: &Cow<[u8]>,
+    dictionaries: &HashMap<u32, Cow<[u8]>>,
+) -> PyResult<&'p PyBytes> {
+    if let Some(compression) = entry.in_memory_package_resources_compression {
+        let data = decompress_resource_data(py, compression, data, dictionaries).map_err(|e| {
+            PyOSError::new_err(format!(
+                "error decompressing resource data in {}: {}",
+                entry.name, e
+            ))
+        })?;
+
+        Ok(PyBytes::new(py, &data))
+    } else {
+        Ok(PyBytes::new(py, data))
+    }
+}
+
+/// Content of a package resource not tied to the lifetime of resources state.
+#[cfg_attr(not(feature = "static-server"), allow(unused))]
+pub(crate) enum PackageResourceContent {
+    /// Resource data, decompressed.
+    Memory(Vec<u8>),
+    /// Path of a file holding resource data.
+    Path(PathBuf),
+}
+
+/// Write a materialized file, leaving it alone if it already has the content.
+///
+/// Data is written to a temporary file which is then renamed, so concurrent
+/// processes never observe partially written files.
+fn write_materialized_file(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Ok(existing) = std::fs::read(path) {
+        if existing == data {
+            return Ok(());
+        }
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = PathBuf::from(temp_name);
+
+    std::fs::write(&temp_path, data)?;
+    if let Err(e) = std::fs::rename(&temp_path, path) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    Ok(())
+}
+
 /// Determines whether an entry represents an importable Python module.
 ///
 /// Should only be called on module flavors.
@@ -42,7 +266,8 @@ fn is_module_importable<X>(entry: &Resource<X>, optimize_level: BytecodeOptimiza
 where
     [X]: ToOwned<Owned = Vec<X>>,
 {
-    entry.in_memory_source.is_some()
+    entry.is_python_namespace_package
+        || entry.in_memory_source.is_some()
         || entry.relative_path_module_source.is_some()
         || match optimize_level {
             BytecodeOptimizationLevel::Zero => {
@@ -101,6 +326,21 @@ pub enum ModuleFlavor {
     Frozen,
     Extension,
     SourceBytecode,
+    /// A PEP 420 namespace package, which has no code.
+    Namespace,
+}
+
+impl ModuleFlavor {
+    /// A short name of the flavor, used in diagnostics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Builtin => "builtin",
+            Self::Frozen => "frozen",
+            Self::Extension => "extension",
+            Self::SourceBytecode => "module",
+            Self::Namespace => "namespace",
+        }
+    }
 }
 
 /// Holds state for an importable Python module.
@@ -112,7 +352,10 @@ where
     [X]: ToOwned<Owned = Vec<X>>,
 {
     /// The raw resource backing this importable module.
-    resource: &'a Resource<'a, X>,
+    ///
+    /// This is shared with the resources state instead of borrowed from it, so it
+    /// remains valid if the state is mutated while Python code runs.
+    resource: Arc<Resource<'a, X>>,
 
     /// Path to current executable.
     current_exe: &'a Path,
@@ -120,6 +363,9 @@ where
     /// Path from which relative paths should be interpreted.
     origin: &'a Path,
 
+    /// zstd dictionaries used to decompress data, keyed by dictionary ID.
+    compression_dictionaries: Arc<HashMap<u32, Cow<'a, [X]>>>,
+
     /// The type of importable module.
     pub flavor: ModuleFlavor,
     /// Whether this module is a package.
@@ -127,6 +373,39 @@ where
 }
 
 impl<'a> ImportablePythonModule<'a, u8> {
+    /// Where the code of this module is loaded from, used in diagnostics.
+    ///
+    /// `memory` if loaded from packed resources data and `filesystem-relative`
+    /// if loaded from a file relative to the origin. Built-in, frozen and
+    /// namespace modules have no location of their own and use their flavor.
+    pub fn location(&self) -> &'static str {
+        let in_memory = match self.flavor {
+            ModuleFlavor::Extension => self
+                .resource
+                .in_memory_extension_module_shared_library
+                .is_some(),
+            ModuleFlavor::SourceBytecode => {
+                self.resource.in_memory_source.is_some()
+                    || self.resource.in_memory_bytecode.is_some()
+                    || self.resource.in_memory_bytecode_opt1.is_some()
+                    || self.resource.in_memory_bytecode_opt2.is_some()
+            }
+            _ => return self.flavor.as_str(),
+        };
+
+        if in_memory {
+            "memory"
+        } else {
+            "filesystem-relative"
+        }
+    }
+
+    /// Whether source code is available for this module.
+    pub fn has_source(&self) -> bool {
+        self.resource.in_memory_source.is_some()
+            || self.resource.relative_path_module_source.is_some()
+    }
+
     /// Attempt to resolve a Python `bytes` for the source code behind this module.
     ///
     /// Will return a PyErr if an error occurs resolving source. If there is no source,
@@ -138,12 +417,21 @@ impl<'a> ImportablePythonModule<'a, u8> {
         decode_source: &'p PyAny,
         io_module: &PyAny,
     ) -> PyResult<Option<&'p PyAny>> {
-        let bytes = if let Some(data) = &self.resource.in_memory_source {
-            Some(PyBytes::new(py, data))
+        if let Some(bytes) = self.resolve_source_bytes(py)? {
+            Ok(Some(decode_source.call((io_module, bytes), None)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Attempt to resolve a Python `bytes` holding the undecoded source code of this module.
+    pub(crate) fn resolve_source_bytes<'p>(&self, py: Python<'p>) -> PyResult<Option<&'p PyBytes>> {
+        if let Some(data) = &self.resource.in_memory_source {
+            Ok(Some(PyBytes::new(py, data)))
         } else if let Some(relative_path) = &self.resource.relative_path_module_source {
             let path = self.origin.join(relative_path);
 
-            let source = std::fs::read(&path).map_err(|e| {
+            let source = read_file_without_gil(py, &path).map_err(|e| {
                 PyErr::from_type(
                     PyImportError::type_object(py),
                     (
@@ -153,13 +441,7 @@ impl<'a> ImportablePythonModule<'a, u8> {
                 )
             })?;
 
-            Some(PyBytes::new(py, &source))
-        } else {
-            None
-        };
-
-        if let Some(bytes) = bytes {
-            Ok(Some(decode_source.call((io_module, bytes), None)?))
+            Ok(Some(PyBytes::new(py, &source)))
         } else {
             Ok(None)
         }
@@ -180,28 +462,95 @@ impl<'a> ImportablePythonModule<'a, u8> {
         decode_source: &PyAny,
         io_module: &PyModule,
     ) -> PyResult<Option<Py<PyAny>>> {
+        if let Some(bytecode) = self.resolve_bytecode_buffer(py, optimize_level)? {
+            Ok(Some(bytecode))
+        } else if let Some(code) = self.compile_source(py, decode_source, io_module)? {
+            let marshal = py.import("marshal")?;
+
+            Ok(Some(
+                marshal.getattr("dumps")?.call((code,), None)?.into_py(py),
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Attempt to resolve the code object for this module.
+    ///
+    /// Like [Self::resolve_bytecode()] except source is compiled directly into a
+    /// code object instead of round-tripping through marshaled bytecode.
+    pub fn resolve_code(
+        &mut self,
+        py: Python,
+        optimize_level: BytecodeOptimizationLevel,
+        decode_source: &PyAny,
+        io_module: &PyModule,
+        marshal_loads: &PyAny,
+    ) -> PyResult<Option<Py<PyAny>>> {
+        if let Some(bytecode) = self.resolve_bytecode_buffer(py, optimize_level)? {
+            Ok(Some(marshal_loads.call((bytecode,), None)?.into_py(py)))
+        } else {
+            Ok(self
+                .compile_source(py, decode_source, io_module)?
+                .map(|code| code.into_py(py)))
+        }
+    }
+
+    /// Resolve a `memoryview` of the bytecode data for this module.
+    ///
+    /// In-memory bytecode is exposed without copying. Decompressed bytecode and
+    /// bytecode read from the filesystem is handed to Python without copying it
+    /// into a `bytes`.
+    fn resolve_bytecode_buffer(
+        &self,
+        py: Python,
+        optimize_level: BytecodeOptimizationLevel,
+    ) -> PyResult<Option<Py<PyAny>>> {
+        match self.resolve_bytecode_data(py, optimize_level)? {
+            Some(BytecodeData::Borrowed(data)) => memoryview_from_memory(py, data),
+            Some(BytecodeData::Owned(data, offset)) => {
+                Ok(Some(OwnedBuffer::memoryview_at(py, data, offset)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Resolve the bytecode data for this module.
+    ///
+    /// In-memory bytecode is borrowed unless it has to be decompressed or is
+    /// owned by the resource, which may be freed while the data is in use.
+    pub(crate) fn resolve_bytecode_data(
+        &self,
+        py: Python,
+        optimize_level: BytecodeOptimizationLevel,
+    ) -> PyResult<Option<BytecodeData<'a>>> {
         if let Some(data) = match optimize_level {
             BytecodeOptimizationLevel::Zero => &self.resource.in_memory_bytecode,
             BytecodeOptimizationLevel::One => &self.resource.in_memory_bytecode_opt1,
             BytecodeOptimizationLevel::Two => &self.resource.in_memory_bytecode_opt2,
         } {
-            let ptr = unsafe {
-                pyffi::PyMemoryView_FromMemory(
-                    data.as_ptr() as _,
-                    data.len() as _,
-                    pyffi::PyBUF_READ,
-                )
-            };
-
-            if ptr.is_null() {
-                Ok(None)
-            } else {
-                Ok(Some(unsafe { PyObject::from_owned_ptr(py, ptr) }))
+            if let Some(compression) = self.resource.in_memory_bytecode_compression {
+                let bytecode =
+                    decompress_resource_data(py, compression, data, &self.compression_dictionaries)
+                        .map_err(|e| {
+                            PyImportError::new_err(format!(
+                                "error decompressing bytecode for {}: {}",
+                                self.resource.name, e
+                            ))
+                        })?;
+
+                return Ok(Some(BytecodeData::Owned(bytecode, 0)));
             }
+
+            // Owned data is freed with the resource, which may happen while the
+            // data is in use. Only data borrowed from memory living as long as
+            // the resources state is exposed without copying.
+            Ok(Some(match data {
+                Cow::Borrowed(data) => BytecodeData::Borrowed(data),
+                Cow::Owned(data) => BytecodeData::Owned(data.clone(), 0),
+            }))
         } else if let Some(path) = self.bytecode_path(optimize_level) {
-            // TODO we could potentially avoid the double allocation for bytecode
-            // by reading directly into a buffer transferred to Python.
-            let bytecode = std::fs::read(&path).map_err(|e| {
+            let bytecode = read_file_without_gil(py, &path).map_err(|e| {
                 PyErr::from_type(
                     PyImportError::type_object(py),
                     (
@@ -219,17 +568,26 @@ impl<'a> ImportablePythonModule<'a, u8> {
             }
 
             // First 16 bytes of .pyc files are a header.
-            Ok(Some(PyBytes::new(py, &bytecode[16..]).into_py(py)))
-        } else if let Some(source) = self.resolve_source(py, decode_source, io_module)? {
-            let builtins = py.import("builtins")?;
-            let marshal = py.import("marshal")?;
+            Ok(Some(BytecodeData::Owned(bytecode, 16)))
+        } else {
+            Ok(None)
+        }
+    }
 
-            let code = builtins
-                .getattr("compile")?
-                .call((source, self.resource.name.as_ref(), "exec"), None)?;
-            let bytecode = marshal.getattr("dumps")?.call((code,), None)?;
+    /// Compile the source of this module into a code object, if source is available.
+    fn compile_source<'p>(
+        &self,
+        py: Python<'p>,
+        decode_source: &'p PyAny,
+        io_module: &PyModule,
+    ) -> PyResult<Option<&'p PyAny>> {
+        if let Some(source) = self.resolve_source(py, decode_source, io_module)? {
+            let builtins = py.import("builtins")?;
 
-            Ok(Some(bytecode.into_py(py)))
+            Ok(Some(builtins.getattr("compile")?.call(
+                (source, self.resource.name.as_ref(), "exec"),
+                None,
+            )?))
         } else {
             Ok(None)
         }
@@ -296,27 +654,29 @@ impl<'a> ImportablePythonModule<'a, u8> {
             // library sets `__path__` to the path to the zip file with the package
             // names `os.path.join()`d to the end. e.g.
             // `/path/to/myapp.zip/mypackage/subpackage`.
-            let mut locations = if let Some(origin_path) = self.origin_path() {
-                if let Some(parent_path) = origin_path.parent() {
-                    vec![parent_path.into_py(py).into_ref(py)]
-                } else {
-                    vec![]
-                }
-            } else {
-                vec![]
-            };
+            spec.setattr(
+                "submodule_search_locations",
+                vec![self.package_path().into_py(py)],
+            )?;
+        }
 
-            if locations.is_empty() {
-                let mut path = self.current_exe.to_path_buf();
-                path.extend(self.resource.name.split('.'));
+        Ok(spec)
+    }
 
-                locations.push(path.into_py(py).into_ref(py));
-            }
+    /// Resolve the path of the directory holding this package's modules.
+    ///
+    /// This is the parent directory of the module file, if the package is
+    /// filesystem based. Otherwise it is a virtual directory constructed
+    /// from the current executable and package name.
+    pub fn package_path(&self) -> PathBuf {
+        if let Some(parent_path) = self.origin_path().as_deref().and_then(Path::parent) {
+            parent_path.to_path_buf()
+        } else {
+            let mut path = self.current_exe.to_path_buf();
+            path.extend(self.resource.name.split('.'));
 
-            spec.setattr("submodule_search_locations", locations)?;
+            path
         }
-
-        Ok(spec)
     }
 
     /// Resolve the value of a `ModuleSpec` origin.
@@ -367,6 +727,127 @@ impl<'a> ImportablePythonModule<'a, u8> {
         }
     }
 
+    /// Resolve the path the code of this module is materialized to under `root`.
+    ///
+    /// Modules with source are materialized to a `.py` file. Otherwise their
+    /// bytecode is materialized to a `.pyc` file. Returns `None` if the module
+    /// isn't loaded from memory.
+    pub fn materialized_path(&self, root: &Path) -> Option<PathBuf> {
+        if self.flavor != ModuleFlavor::SourceBytecode || self.origin_path().is_some() {
+            return None;
+        }
+
+        let mut path = root.to_path_buf();
+        path.extend(self.resource.name.split('.'));
+        if self.is_package {
+            path.push("__init__");
+        }
+        path.set_extension(if self.resource.in_memory_source.is_some() {
+            "py"
+        } else {
+            "pyc"
+        });
+
+        Some(path)
+    }
+
+    /// Write the code of this in-memory module to a file under `root`.
+    ///
+    /// The in-memory package resources of packages are written next to it, as
+    /// if the package were installed in `root`. Existing files with the same
+    /// content are left alone.
+    ///
+    /// Returns the path of the file holding the module's code or `None` if the
+    /// module isn't loaded from memory.
+    pub fn materialize(
+        &mut self,
+        py: Python,
+        root: &Path,
+        optimize_level: BytecodeOptimizationLevel,
+        decode_source: &PyAny,
+        io_module: &PyModule,
+    ) -> PyResult<Option<PathBuf>> {
+        let path = match self.materialized_path(root) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        let data = if let Some(source) = &self.resource.in_memory_source {
+            source.to_vec()
+        } else if let Some(bytecode) =
+            self.resolve_bytecode(py, optimize_level, decode_source, io_module)?
+        {
+            let magic_number = py
+                .import("_frozen_importlib_external")?
+                .getattr("MAGIC_NUMBER")?
+                .downcast::<PyBytes>()?
+                .as_bytes();
+
+            // The header of unchecked .pyc files: magic, flags, mtime, and size.
+            let mut data = magic_number.to_vec();
+            data.extend_from_slice(&[0; 12]);
+            data.extend(PyBuffer::<u8>::get(bytecode.as_ref(py))?.to_vec(py)?);
+
+            data
+        } else {
+            return Ok(None);
+        };
+
+        let make_error = |path: &Path, e: std::io::Error| -> PyErr {
+            PyErr::from_type(
+                PyImportError::type_object(py),
+                (
+                    format!("error materializing {}: {}", path.display(), e),
+                    self.resource.name.clone().into_py(py),
+                ),
+            )
+        };
+
+        write_materialized_file(&path, &data).map_err(|e| make_error(&path, e))?;
+
+        if self.is_package {
+            if let (Some(resources), Some(package_dir)) =
+                (&self.resource.in_memory_package_resources, path.parent())
+            {
+                for (name, data) in resources {
+                    // Resource names are normalized to POSIX separators. Refuse
+                    // to write outside the package directory.
+                    if name.split('/').any(|part| part.is_empty() || part == "..") {
+                        continue;
+                    }
+
+                    let resource_path = package_dir.join(name.as_ref());
+
+                    let data = if let Some(compression) =
+                        self.resource.in_memory_package_resources_compression
+                    {
+                        Cow::Owned(
+                            decompress_resource_data(
+                                py,
+                                compression,
+                                data,
+                                &self.compression_dictionaries,
+                            )
+                            .map_err(|e| {
+                                PyImportError::new_err(format!(
+                                    "error decompressing resource data in {}: {}",
+                                    self.resource.name, e
+                                ))
+                            })?,
+                        )
+                    } else {
+                        Cow::Borrowed(data.as_ref())
+                    };
+
+                    write_materialized_file(&resource_path, &data)
+                        .map_err(|e| make_error(&resource_path, e))?;
+                }
+            }
+        }
+
+        Ok(Some(path))
+    }
+
     /// Obtain the filesystem path to bytecode for this module.
     fn bytecode_path(&self, optimize_level: BytecodeOptimizationLevel) -> Option<PathBuf> {
         let bytecode_path = match optimize_level {
@@ -380,7 +861,7 @@ impl<'a> ImportablePythonModule<'a, u8> {
             .map(|bytecode_path| self.origin.join(bytecode_path))
     }
 
-    pub fn in_memory_extension_module_shared_library(&self) -> &'a Option<Cow<'a, [u8]>> {
+    pub fn in_memory_extension_module_shared_library(&self) -> &Option<Cow<'a, [u8]>> {
         &self.resource.in_memory_extension_module_shared_library
     }
 }
@@ -394,6 +875,17 @@ pub enum PackedResourcesSource<'a> {
     /// Load resources data from a filesystem path using memory mapped I/O.
     #[allow(unused)]
     MemoryMappedPath(PathBuf),
+
+    /// Load resources data from a filesystem path using memory mapped I/O
+    /// after verifying its SHA-256 digest.
+    ///
+    /// Loading fails if the content of the file doesn't match the digest.
+    #[allow(unused)]
+    MemoryMappedPathVerified(PathBuf, [u8; 32]),
+
+    /// Load resources data appended to the current executable using memory mapped I/O.
+    #[allow(unused)]
+    ExecutableOverlay,
 }
 
 impl<'a> From<&'a [u8]> for PackedResourcesSource<'a> {
@@ -417,7 +909,16 @@ where
     origin: PathBuf,
 
     /// Named resources available for loading.
-    resources: HashMap<Cow<'a, str>, Resource<'a, X>>,
+    ///
+    /// Resources are shared so they can be used while Python code runs, which
+    /// may mutate this instance from another thread. Mutating a resource that
+    /// is in use copies it.
+    resources: HashMap<Cow<'a, str>, Arc<Resource<'a, X>>>,
+
+    /// zstd dictionaries used to decompress resource data, keyed by dictionary ID.
+    ///
+    /// Shared like `resources`.
+    compression_dictionaries: Arc<HashMap<u32, Cow<'a, [X]>>>,
 
     /// List of `PyObject` that back indexed data.
     ///
@@ -427,6 +928,27 @@ where
 
     /// Holds memory mapped file instances that resources data came from.
     backing_mmaps: Vec<memmap2::Mmap>,
+
+    /// Holds decrypted resources data.
+    backing_buffers: Vec<Vec<u8>>,
+
+    /// Key used to decrypt encrypted resources data.
+    decryption_key: Option<[u8; 32]>,
+
+    /// When content digests of resources are verified.
+    verification: PackedResourcesVerification,
+
+    /// Names of resources whose content digests were verified on load.
+    ///
+    /// Once all resources in use are verified, this is only read. So a read-write
+    /// lock allows threads to import concurrently.
+    verified_resources: RwLock<HashSet<String>>,
+
+    /// Names of the collections resources were indexed from.
+    collections: Vec<String>,
+
+    /// Index into `collections` of the collection that last provided each resource.
+    resource_collections: HashMap<Cow<'a, str>, usize>,
 }
 
 impl<'a> Default for PythonResourcesState<'a, u8> {
@@ -435,8 +957,15 @@ impl<'a> Default for PythonResourcesState<'a, u8> {
             current_exe: PathBuf::new(),
             origin: PathBuf::new(),
             resources: HashMap::new(),
+            compression_dictionaries: Arc::new(HashMap::new()),
             backing_py_objects: vec![],
             backing_mmaps: vec![],
+            backing_buffers: vec![],
+            decryption_key: None,
+            verification: PackedResourcesVerification::None,
+            verified_resources: RwLock::new(HashSet::new()),
+            collections: vec![],
+            resource_collections: HashMap::new(),
         }
     }
 }
@@ -477,14 +1006,123 @@ impl<'a> PythonResourcesState<'a, u8> {
         self.origin = path;
     }
 
+    /// Set the key used to decrypt encrypted resources data.
+    ///
+    /// Must be called before indexing encrypted data.
+    pub fn set_decryption_key(&mut self, key: Option<[u8; 32]>) {
+        self.decryption_key = key;
+    }
+
+    /// Set when content digests of resources are verified.
+    ///
+    /// Must be called before indexing data for [PackedResourcesVerification::Startup]
+    /// to have an effect.
+    pub fn set_verification(&mut self, verification: PackedResourcesVerification) {
+        self.verification = verification;
+    }
+
+    /// Verify the content digest of a resource if verification on load is enabled.
+    ///
+    /// Each resource is only verified once. Resources without a content digest
+    /// are not verified.
+    pub fn verify_resource(&self, name: &str) -> Result<(), String> {
+        if self.verification != PackedResourcesVerification::OnLoad {
+            return Ok(());
+        }
+
+        let name = name.strip_suffix(".__init__").unwrap_or(name);
+
+        let resource = match self.resources.get(name) {
+            Some(resource) => resource,
+            None => return Ok(()),
+        };
+
+        let expected = match &resource.content_digest {
+            Some(digest) => digest,
+            None => return Ok(()),
+        };
+
+        if self
+            .verified_resources
+            .read()
+            .map_err(|_| "unable to lock verified resources".to_string())?
+            .contains(name)
+        {
+            return Ok(());
+        }
+
+        // The lock isn't held while computing the digest so threads verifying
+        // other resources don't wait on us. Threads racing to verify the same
+        // resource both compute its digest, which is harmless.
+        let digest = resource
+            .compute_content_digest()
+            .map_err(|e| format!("computing content digest of {}: {}", name, e))?;
+
+        if &digest != expected {
+            return Err(format!(
+                "content digest of resource {} does not match; resources data is corrupt or was modified",
+                name
+            ));
+        }
+
+        self.verified_resources
+            .write()
+            .map_err(|_| "unable to lock verified resources".to_string())?
+            .insert(name.to_string());
+
+        Ok(())
+    }
+
+    /// Decrypt encrypted resources data with the key from [Self::set_decryption_key()].
+    #[cfg(feature = "encryption")]
+    fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, &'static str> {
+        let key = self
+            .decryption_key
+            .as_ref()
+            .ok_or("resources data is encrypted but no decryption key is set")?;
+
+        python_packed_resources::decrypt_packed_resources(data, key)
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn decrypt(&self, _data: &[u8]) -> Result<Vec<u8>, &'static str> {
+        Err("resources data is encrypted but encryption support is not enabled")
+    }
+
     /// Load resources by parsing a blob.
     ///
+    /// Encrypted data is decrypted with the key from [Self::set_decryption_key()].
+    ///
     /// If an existing entry exists, the new entry will be merged into it. Set fields
     /// on the incoming entry will overwrite fields on the existing entry.
     ///
     /// If an entry doesn't exist, the resource will be inserted as-is.
     pub fn index_data(&mut self, data: &'a [u8]) -> Result<(), &'static str> {
-        let resources = python_packed_resources::load_resources(data)?;
+        self.index_data_from(data, "memory")
+    }
+
+    /// Load resources by parsing a blob, recording them as part of a named collection.
+    fn index_data_from(&mut self, data: &'a [u8], collection: &str) -> Result<(), &'static str> {
+        let data = if python_packed_resources::is_encrypted(data) {
+            let decrypted = self.decrypt(data)?;
+
+            // The heap allocation doesn't move when the Vec is moved into
+            // self.backing_buffers, so references into it live as long as self.
+            let data =
+                unsafe { std::slice::from_raw_parts::<u8>(decrypted.as_ptr(), decrypted.len()) };
+            self.backing_buffers.push(decrypted);
+
+            data
+        } else {
+            data
+        };
+
+        let mut resources = python_packed_resources::load_resources(data)?;
+        if self.verification == PackedResourcesVerification::Startup {
+            resources.verify_content_digests(true)?;
+        }
+        let collection = self.collection_index(collection);
+        self.index_compression_dictionaries(resources.compression_dictionaries()?);
 
         // Reserve space for expected number of incoming items so we can avoid extra
         // allocations.
@@ -493,12 +1131,15 @@ impl<'a> PythonResourcesState<'a, u8> {
         for resource in resources {
             let resource = resource?;
 
+            self.resource_collections
+                .insert(resource.name.clone(), collection);
+
             match self.resources.entry(resource.name.clone()) {
                 Entry::Occupied(existing) => {
-                    existing.into_mut().merge_from(resource)?;
+                    Arc::make_mut(existing.into_mut()).merge_from(resource)?;
                 }
                 Entry::Vacant(vacant) => {
-                    vacant.insert(resource);
+                    vacant.insert(Arc::new(resource));
                 }
             }
         }
@@ -506,6 +1147,33 @@ impl<'a> PythonResourcesState<'a, u8> {
         Ok(())
     }
 
+    /// Obtain the index of a named collection in `self.collections`, registering it if needed.
+    fn collection_index(&mut self, name: &str) -> usize {
+        if let Some(index) = self.collections.iter().position(|c| c == name) {
+            index
+        } else {
+            self.collections.push(name.to_string());
+            self.collections.len() - 1
+        }
+    }
+
+    /// Obtain the name of the collection a resource was last indexed from.
+    pub fn resource_collection(&self, name: &str) -> Option<&str> {
+        self.resource_collections
+            .get(name)
+            .map(|index| self.collections[*index].as_str())
+    }
+
+    /// Record zstd compression dictionaries by their dictionary ID.
+    fn index_compression_dictionaries(&mut self, dictionaries: Vec<&'a [u8]>) {
+        for dictionary in dictionaries {
+            if let Some(id) = zstd_dictionary_id(dictionary) {
+                Arc::make_mut(&mut self.compression_dictionaries)
+                    .insert(id, Cow::Borrowed(dictionary));
+            }
+        }
+    }
+
     /// Load resources data from a filesystem path using memory mapped I/O.
     pub fn index_path_memory_mapped(&mut self, path: impl AsRef<Path>) -> Result<(), String> {
         let path = path.as_ref();
@@ -515,10 +1183,158 @@ impl<'a> PythonResourcesState<'a, u8> {
 
         let data = unsafe { std::slice::from_raw_parts::<u8>(mapped.as_ptr(), mapped.len()) };
 
-        self.index_data(data)?;
-        self.backing_mmaps.push(mapped);
+        self.index_data_from(data, &path.display().to_string())?;
+        self.backing_mmaps.push(mapped);
+
+        Ok(())
+    }
+
+    /// Load resources by parsing a memory mapped file after verifying its content.
+    ///
+    /// The SHA-256 digest of the file content must equal `expected_sha256`.
+    /// Resources are only indexed if the digest matches.
+    pub fn index_path_memory_mapped_verified(
+        &mut self,
+        path: impl AsRef<Path>,
+        expected_sha256: &[u8; 32],
+    ) -> Result<(), String> {
+        let path = path.as_ref();
+        let f = std::fs::File::open(path).map_err(|e| e.to_string())?;
+
+        let mapped = unsafe { memmap2::Mmap::map(&f) }.map_err(|e| e.to_string())?;
+
+        let data = unsafe { std::slice::from_raw_parts::<u8>(mapped.as_ptr(), mapped.len()) };
+
+        if Sha256::digest(data).as_slice() != expected_sha256 {
+            return Err(format!(
+                "packed resources file {} does not match expected SHA-256 digest",
+                path.display()
+            ));
+        }
+
+        self.index_data_from(data, &path.display().to_string())?;
+        self.backing_mmaps.push(mapped);
+
+        Ok(())
+    }
+
+    /// Load resources data appended to the current executable.
+    ///
+    /// The executable is read using memory mapped I/O.
+    pub fn index_executable_overlay(&mut self) -> Result<(), String> {
+        let path = self.current_exe.clone();
+        let f = std::fs::File::open(&path).map_err(|e| e.to_string())?;
+
+        let mapped = unsafe { memmap2::Mmap::map(&f) }.map_err(|e| e.to_string())?;
+
+        let data = unsafe { std::slice::from_raw_parts::<u8>(mapped.as_ptr(), mapped.len()) };
+        let data = python_packed_resources::find_executable_overlay(data)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        self.index_data_from(data, &path.display().to_string())?;
+        self.backing_mmaps.push(mapped);
+
+        Ok(())
+    }
+
+    /// Atomically add the resources in a packed resources file to an instance in use.
+    ///
+    /// This is meant for installing resources, such as plugins, into a running
+    /// application. The file is read into memory and fully parsed before any
+    /// resource is added. If `expected_sha256` is set, the SHA-256 digest of the
+    /// file content must equal it. Content digests of resources are always
+    /// verified if the data format supports them.
+    ///
+    /// Incoming resources replace existing resources of the same name, except
+    /// that namespace packages don't replace existing resources. `is_imported` is
+    /// called with the name of every resource to be added. If it returns `true`
+    /// for any of them, no resources are added, as modules which have already
+    /// been imported can't be replaced.
+    ///
+    /// Returns the names of the added resources.
+    pub fn install_path(
+        &mut self,
+        path: impl AsRef<Path>,
+        expected_sha256: Option<&[u8; 32]>,
+        is_imported: impl Fn(&str) -> bool,
+    ) -> Result<Vec<String>, String> {
+        let path = path.as_ref();
+        let buffer = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        if let Some(expected) = expected_sha256 {
+            if Sha256::digest(&buffer).as_slice() != expected {
+                return Err(format!(
+                    "packed resources file {} does not match expected SHA-256 digest",
+                    path.display()
+                ));
+            }
+        }
+
+        let buffer = if python_packed_resources::is_encrypted(&buffer) {
+            self.decrypt(&buffer)?
+        } else {
+            buffer
+        };
+
+        // The heap allocation doesn't move when the Vec is moved into
+        // self.backing_buffers, so references into it live as long as self.
+        let data = unsafe { std::slice::from_raw_parts::<u8>(buffer.as_ptr(), buffer.len()) };
+
+        let mut parser = python_packed_resources::load_resources(data)?;
+        if data.starts_with(python_packed_resources::HEADER_V4) {
+            parser.verify_content_digests(true)?;
+        }
+
+        let dictionaries = parser.compression_dictionaries()?;
+
+        let mut resources = vec![];
+        for resource in parser {
+            let resource = resource?;
+
+            if resource.is_python_namespace_package && self.resources.contains_key(&resource.name) {
+                continue;
+            }
+
+            resources.push(resource);
+        }
+
+        let imported = resources
+            .iter()
+            .filter(|resource| is_imported(&resource.name))
+            .map(|resource| resource.name.to_string())
+            .collect::<Vec<_>>();
+        if !imported.is_empty() {
+            return Err(format!(
+                "packed resources file {} replaces already imported modules: {}",
+                path.display(),
+                imported.join(", ")
+            ));
+        }
+
+        let collection = self.collection_index(&path.display().to_string());
+        let verified_resources = self
+            .verified_resources
+            .get_mut()
+            .map_err(|_| "unable to lock verified resources".to_string())?;
+        let mut names = vec![];
+
+        for resource in &resources {
+            // Replaced resources need verifying again.
+            verified_resources.remove(resource.name.as_ref());
+            names.push(resource.name.to_string());
+        }
+
+        self.index_compression_dictionaries(dictionaries);
+        for resource in resources {
+            self.resource_collections
+                .insert(resource.name.clone(), collection);
+            self.resources
+                .insert(resource.name.clone(), Arc::new(resource));
+        }
+
+        self.backing_buffers.push(buffer);
 
-        Ok(())
+        Ok(names)
     }
 
     /// Load resources from packed data stored in a PyObject.
@@ -539,6 +1355,8 @@ impl<'a> PythonResourcesState<'a, u8> {
 
     /// Load `builtin` modules from the Python interpreter.
     pub fn index_interpreter_builtin_extension_modules(&mut self) -> Result<(), &'static str> {
+        let collection = self.collection_index("builtin");
+
         for i in 0.. {
             let record = unsafe { pyffi::PyImport_Inittab.offset(i) };
 
@@ -554,16 +1372,20 @@ impl<'a> PythonResourcesState<'a, u8> {
                 }
             };
 
-            self.resources
-                .entry(name_str.into())
-                .and_modify(|r| {
-                    r.is_python_builtin_extension_module = true;
-                })
-                .or_insert_with(|| Resource {
-                    is_python_builtin_extension_module: true,
-                    name: Cow::Owned(name_str.to_string()),
-                    ..Resource::default()
-                });
+            match self.resources.entry(name_str.into()) {
+                Entry::Occupied(existing) => {
+                    Arc::make_mut(existing.into_mut()).is_python_builtin_extension_module = true;
+                }
+                Entry::Vacant(vacant) => {
+                    self.resource_collections
+                        .insert(vacant.key().clone(), collection);
+                    vacant.insert(Arc::new(Resource {
+                        is_python_builtin_extension_module: true,
+                        name: Cow::Owned(name_str.to_string()),
+                        ..Resource::default()
+                    }));
+                }
+            }
         }
 
         Ok(())
@@ -571,6 +1393,8 @@ impl<'a> PythonResourcesState<'a, u8> {
 
     /// Load `frozen` modules from the Python interpreter.
     pub fn index_interpreter_frozen_modules(&mut self) -> Result<(), &'static str> {
+        let collection = self.collection_index("frozen");
+
         for i in 0.. {
             let record = unsafe { pyffi::PyImport_FrozenModules.offset(i) };
 
@@ -586,16 +1410,20 @@ impl<'a> PythonResourcesState<'a, u8> {
                 }
             };
 
-            self.resources
-                .entry(name_str.into())
-                .and_modify(|r| {
-                    r.is_python_frozen_module = true;
-                })
-                .or_insert_with(|| Resource {
-                    is_python_frozen_module: true,
-                    name: Cow::Owned(name_str.to_string()),
-                    ..Resource::default()
-                });
+            match self.resources.entry(name_str.into()) {
+                Entry::Occupied(existing) => {
+                    Arc::make_mut(existing.into_mut()).is_python_frozen_module = true;
+                }
+                Entry::Vacant(vacant) => {
+                    self.resource_collections
+                        .insert(vacant.key().clone(), collection);
+                    vacant.insert(Arc::new(Resource {
+                        is_python_frozen_module: true,
+                        name: Cow::Owned(name_str.to_string()),
+                        ..Resource::default()
+                    }));
+                }
+            }
         }
 
         Ok(())
@@ -626,7 +1454,21 @@ impl<'a> PythonResourcesState<'a, u8> {
         &mut self,
         resource: Resource<'resource, u8>,
     ) -> Result<(), &'static str> {
-        self.resources.insert(resource.name.clone(), resource);
+        self.add_resource_from(resource, "added")
+    }
+
+    /// Add a resource to the instance, recording it as part of a named collection.
+    pub fn add_resource_from<'resource: 'a>(
+        &mut self,
+        resource: Resource<'resource, u8>,
+        collection: &str,
+    ) -> Result<(), &'static str> {
+        let collection = self.collection_index(collection);
+
+        self.resource_collections
+            .insert(resource.name.clone(), collection);
+        self.resources
+            .insert(resource.name.clone(), Arc::new(resource));
 
         Ok(())
     }
@@ -695,34 +1537,47 @@ impl<'a> PythonResourcesState<'a, u8> {
 
         if resource.is_python_builtin_extension_module {
             Some(ImportablePythonModule {
-                resource,
+                resource: resource.clone(),
                 current_exe: &self.current_exe,
                 origin: &self.origin,
+                compression_dictionaries: self.compression_dictionaries.clone(),
                 flavor: ModuleFlavor::Builtin,
                 is_package: resource.is_python_package,
             })
         } else if resource.is_python_frozen_module {
             Some(ImportablePythonModule {
-                resource,
+                resource: resource.clone(),
                 current_exe: &self.current_exe,
                 origin: &self.origin,
+                compression_dictionaries: self.compression_dictionaries.clone(),
                 flavor: ModuleFlavor::Frozen,
                 is_package: resource.is_python_package,
             })
         } else if resource.is_python_extension_module {
             Some(ImportablePythonModule {
-                resource,
+                resource: resource.clone(),
                 current_exe: &self.current_exe,
                 origin: &self.origin,
+                compression_dictionaries: self.compression_dictionaries.clone(),
                 flavor: ModuleFlavor::Extension,
                 is_package: resource.is_python_package,
             })
+        } else if resource.is_python_namespace_package {
+            Some(ImportablePythonModule {
+                resource: resource.clone(),
+                current_exe: &self.current_exe,
+                origin: &self.origin,
+                compression_dictionaries: self.compression_dictionaries.clone(),
+                flavor: ModuleFlavor::Namespace,
+                is_package: true,
+            })
         } else if resource.is_python_module {
             if is_module_importable(resource, optimize_level) {
                 Some(ImportablePythonModule {
-                    resource,
+                    resource: resource.clone(),
                     current_exe: &self.current_exe,
                     origin: &self.origin,
+                    compression_dictionaries: self.compression_dictionaries.clone(),
                     flavor: ModuleFlavor::SourceBytecode,
                     is_package: resource.is_python_package,
                 })
@@ -734,6 +1589,21 @@ impl<'a> PythonResourcesState<'a, u8> {
         }
     }
 
+    /// Describe why [Self::resolve_importable_module] doesn't resolve a module.
+    ///
+    /// Returns a short reason used in diagnostics.
+    pub fn unresolved_module_reason(&self, name: &str) -> &'static str {
+        let name = name.strip_suffix(".__init__").unwrap_or(name);
+
+        match self.resources.get(name) {
+            None => "not_indexed",
+            Some(resource) if !resource.is_python_module => "not_a_module",
+            // Modules without source are only importable at the optimization
+            // levels they have bytecode for.
+            Some(_) => "no_bytecode_for_optimization_level",
+        }
+    }
+
     /// Obtain a single named resource in a package.
     ///
     /// Err occurs if loading the resource data fails. `Ok(None)` is returned
@@ -746,16 +1616,19 @@ impl<'a> PythonResourcesState<'a, u8> {
         resource_name: &str,
     ) -> PyResult<Option<&'p PyAny>> {
         let entry = match self.resources.get(package) {
-            Some(entry) => entry,
+            Some(entry) => entry.clone(),
             None => return Ok(None),
         };
+        let dictionaries = self.compression_dictionaries.clone();
 
-        if let Some(resources) = &entry.in_memory_package_resources {
+        self.verify_resource(package).map_err(PyOSError::new_err)?;
+
+        for resources in in_memory_package_resources(&entry) {
             if let Some(data) = resources.get(resource_name) {
                 let io_module = py.import("io")?;
                 let bytes_io = io_module.getattr("BytesIO")?;
 
-                let data = PyBytes::new(py, data);
+                let data = package_resource_bytes(py, &entry, data, &dictionaries)?;
                 return Ok(Some(bytes_io.call((data,), None)?));
             }
         }
@@ -776,13 +1649,66 @@ impl<'a> PythonResourcesState<'a, u8> {
         Ok(None)
     }
 
+    /// Obtain the content of all resources in a package.
+    ///
+    /// In-memory resources are decompressed. Keys are resource names.
+    #[cfg_attr(not(feature = "static-server"), allow(unused))]
+    pub(crate) fn package_resources_content(
+        &self,
+        py: Python,
+        package: &str,
+    ) -> PyResult<BTreeMap<String, PackageResourceContent>> {
+        let mut res = BTreeMap::new();
+
+        let entry = match self.resources.get(package) {
+            Some(entry) => entry.clone(),
+            None => return Ok(res),
+        };
+        let dictionaries = self.compression_dictionaries.clone();
+
+        self.verify_resource(package).map_err(PyOSError::new_err)?;
+
+        for resources in in_memory_package_resources(&entry) {
+            for (name, data) in resources {
+                if res.contains_key(name.as_ref()) {
+                    continue;
+                }
+
+                let data = match entry.in_memory_package_resources_compression {
+                    Some(compression) => {
+                        decompress_resource_data(py, compression, data, &dictionaries).map_err(
+                            |e| {
+                                PyOSError::new_err(format!(
+                                    "error decompressing resource data in {}: {}",
+                                    entry.name, e
+                                ))
+                            },
+                        )?
+                    }
+                    None => data.to_vec(),
+                };
+
+                res.insert(name.to_string(), PackageResourceContent::Memory(data));
+            }
+        }
+
+        if let Some(resources) = &entry.relative_path_package_resources {
+            for (name, path) in resources {
+                res.entry(name.to_string())
+                    .or_insert_with(|| PackageResourceContent::Path(self.origin.join(path)));
+            }
+        }
+
+        Ok(res)
+    }
+
     /// Determines whether a specific package + name pair is a known Python package resource.
     pub fn is_package_resource(&self, package: &str, resource_name: &str) -> bool {
         if let Some(entry) = self.resources.get(package) {
-            if let Some(resources) = &entry.in_memory_package_resources {
-                if resources.contains_key(resource_name) {
-                    return true;
-                }
+            if in_memory_package_resources(entry)
+                .any(|resources| resources.contains_key(resource_name))
+            {
+                return true;
             }
 
             if let Some(resources) = &entry.relative_path_package_resources {
@@ -800,19 +1726,21 @@ impl<'a> PythonResourcesState<'a, u8> {
     /// The names are returned in sorted order.
     pub fn package_resource_names<'p>(&self, py: Python<'p>, package: &str) -> PyResult<&'p PyAny> {
         let entry = match self.resources.get(package) {
-            Some(entry) => entry,
+            Some(entry) => entry.clone(),
             None => return Ok(PyList::empty(py).into()),
         };
 
-        let mut names = if let Some(resources) = &entry.in_memory_package_resources {
-            resources.keys().collect()
-        } else if let Some(resources) = &entry.relative_path_package_resources {
-            resources.keys().collect()
-        } else {
-            vec![]
-        };
+        let mut names = BTreeSet::new();
 
-        names.sort();
+        for resources in localized_package_resources(&entry) {
+            names.extend(resources.keys());
+        }
+
+        if let Some(resources) = &entry.in_memory_package_resources {
+            names.extend(resources.keys());
+        } else if let Some(resources) = &entry.relative_path_package_resources {
+            names.extend(resources.keys());
+        }
 
         let names = names
             .iter()
@@ -834,10 +1762,10 @@ impl<'a> PythonResourcesState<'a, u8> {
         };
 
         if let Some(entry) = self.resources.get(package) {
-            if let Some(resources) = &entry.in_memory_package_resources {
-                if resources.keys().any(|path| path.starts_with(&prefix)) {
-                    return true;
-                }
+            if in_memory_package_resources(entry)
+                .any(|resources| resources.keys().any(|path| path.starts_with(&prefix)))
+            {
+                return true;
             }
 
             if let Some(resources) = &entry.relative_path_package_resources {
@@ -891,7 +1819,40 @@ impl<'a> PythonResourcesState<'a, u8> {
         let mut entries = BTreeSet::new();
 
         if let Some(entry) = self.resources.get(package) {
-            if let Some(resources) = &entry.in_memory_package_resources {
+            for resources in in_memory_package_resources(entry) {
+                entries.extend(resources.keys().filter_map(filter_map_resource));
+            }
+
+            if let Some(resources) = &entry.relative_path_package_resources {
+                entries.extend(resources.keys().filter_map(filter_map_resource));
+            }
+        }
+
+        entries.into_iter().collect::<Vec<_>>()
+    }
+
+    /// Resolve directories of package resources in a directory.
+    ///
+    /// Directories are implied by the paths of resources within them.
+    pub fn package_resources_list_subdirectories(&self, package: &str, name: &str) -> Vec<String> {
+        let name = name.replace('\\', "/");
+
+        let prefix = if name.is_empty() || name.ends_with('/') {
+            name
+        } else {
+            format!("{}/", name)
+        };
+
+        let filter_map_resource = |path: &'_ Cow<'_, str>| -> Option<String> {
+            path.strip_prefix(&prefix)
+                .and_then(|name| name.split_once('/'))
+                .map(|(directory, _)| directory.to_string())
+        };
+
+        let mut entries = BTreeSet::new();
+
+        if let Some(entry) = self.resources.get(package) {
+            for resources in in_memory_package_resources(entry) {
                 entries.extend(resources.keys().filter_map(filter_map_resource));
             }
 
@@ -903,6 +1864,34 @@ impl<'a> PythonResourcesState<'a, u8> {
         entries.into_iter().collect::<Vec<_>>()
     }
 
+    /// Whether the given name is a known Python package, including namespace packages.
+    pub fn is_package(&self, name: &str) -> bool {
+        self.resources
+            .get(name)
+            .map(|r| r.is_python_package || r.is_python_namespace_package)
+            .unwrap_or(false)
+    }
+
+    /// Resolve the names of packages directly within a package.
+    ///
+    /// Names are relative to the package.
+    pub fn list_subpackages(&self, package: &str) -> Vec<String> {
+        let prefix = format!("{}.", package);
+
+        let mut names = self
+            .resources
+            .iter()
+            .filter(|(_, r)| r.is_python_package || r.is_python_namespace_package)
+            .filter_map(|(name, _)| name.strip_prefix(&prefix))
+            .filter(|name| !name.contains('.'))
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+
+        names.sort();
+
+        names
+    }
+
     /// Attempt to resolve a PyBytes for resource data given a relative path.
     ///
     /// Raises OSerror on failure.
@@ -1008,11 +1997,15 @@ impl<'a> PythonResourcesState<'a, u8> {
             let resource_name = name_parts.join("/");
             let resource_name_ref: &str = &resource_name;
 
-            if let Some(entry) = self.resources.get(package_name_ref) {
+            if let Some(entry) = self.resources.get(package_name_ref).cloned() {
                 if check_in_memory {
-                    if let Some(resources) = &entry.in_memory_package_resources {
+                    for resources in in_memory_package_resources(&entry) {
                         if let Some(data) = resources.get(resource_name_ref) {
-                            return Ok(PyBytes::new(py, data).into());
+                            let dictionaries = self.compression_dictionaries.clone();
+
+                            return Ok(
+                                package_resource_bytes(py, &entry, data, &dictionaries)?.into()
+                            );
                         }
                     }
                 }
@@ -1062,7 +2055,9 @@ impl<'a> PythonResourcesState<'a, u8> {
         prefix: Option<String>,
         optimize_level: BytecodeOptimizationLevel,
     ) -> PyResult<&'p PyList> {
-        let infos: PyResult<Vec<_>> = self
+        // Creating Python objects can run Python code. So resolve everything
+        // needed from resources before doing so.
+        let modules = self
             .resources
             .values()
             .filter(|r| {
@@ -1080,20 +2075,22 @@ impl<'a> PythonResourcesState<'a, u8> {
                     name.to_string()
                 };
 
-                let name = name.to_object(py);
-                let is_package = r.is_python_package.to_object(py);
-
-                Ok(PyTuple::new(py, &[name, is_package]))
+                (name, r.is_python_package)
             })
-            .collect();
+            .collect::<Vec<_>>();
 
-        let infos = infos?;
+        let infos = modules
+            .into_iter()
+            .map(|(name, is_package)| {
+                PyTuple::new(py, &[name.to_object(py), is_package.to_object(py)])
+            })
+            .collect::<Vec<_>>();
 
         Ok(PyList::new(py, &infos))
     }
 
     /// Resolve the names of package distributions matching a name filter.
-    pub fn package_distribution_names(&self, filter: impl Fn(&str) -> bool) -> Vec<&'_ str> {
+    pub fn package_distribution_names(&self, filter: impl Fn(&str) -> bool) -> Vec<String> {
         self.resources
             .values()
             .filter(|r| {
@@ -1102,7 +2099,7 @@ impl<'a> PythonResourcesState<'a, u8> {
                         || r.relative_path_distribution_resources.is_some())
             })
             .filter(|r| filter(r.name.as_ref()))
-            .map(|r| r.name.as_ref())
+            .map(|r| r.name.to_string())
             .collect::<Vec<_>>()
     }
 
@@ -1111,20 +2108,20 @@ impl<'a> PythonResourcesState<'a, u8> {
         &self,
         package: &str,
         name: &str,
-    ) -> Result<Option<Cow<'_, [u8]>>> {
+    ) -> Result<Option<Vec<u8>>> {
         if let Some(entry) = self.resources.get(package) {
+            self.verify_resource(package).map_err(anyhow::Error::msg)?;
+
             if let Some(resources) = &entry.in_memory_distribution_resources {
                 if let Some(data) = resources.get(name) {
-                    return Ok(Some(Cow::Borrowed(data.as_ref())));
+                    return Ok(Some(data.to_vec()));
                 }
             }
 
             if let Some(resources) = &entry.relative_path_distribution_resources {
                 if let Some(path) = resources.get(name) {
                     let path = &self.origin.join(path);
-                    let data = std::fs::read(path)?;
-
-                    return Ok(Some(Cow::Owned(data)));
+                    return Ok(Some(std::fs::read(path)?));
                 }
             }
 
@@ -1134,6 +2131,31 @@ impl<'a> PythonResourcesState<'a, u8> {
         }
     }
 
+    /// Resolve the directory paths in a package distribution are relative to.
+    ///
+    /// This is the equivalent of the `site-packages` directory holding the
+    /// distribution's metadata directory. For distributions with metadata in
+    /// memory, this is the current executable, as it is for the `__path__` of
+    /// in-memory packages.
+    pub fn package_distribution_root(&self, package: &str) -> PathBuf {
+        if let Some(resources) = self
+            .resources
+            .get(package)
+            .and_then(|entry| entry.relative_path_distribution_resources.as_ref())
+        {
+            if let Some((name, path)) = resources.iter().next() {
+                // Strip the resource name and the metadata directory.
+                let depth = name.split('/').count() + 1;
+
+                if let Some(root) = path.ancestors().nth(depth) {
+                    return self.origin.join(root);
+                }
+            }
+        }
+
+        self.current_exe.clone()
+    }
+
     /// Whether a package distribution resource name is a directory.
     pub fn package_distribution_resource_name_is_directory(
         &self,
@@ -1227,27 +2249,65 @@ impl<'a> PythonResourcesState<'a, u8> {
         }
     }
 
+    /// Resolve names of shared libraries a resource depends on.
+    #[allow(unused)]
+    pub fn resolve_shared_library_dependency_names(&self, name: &str) -> Vec<&str> {
+        self.resources
+            .get(name)
+            .and_then(|entry| entry.shared_library_dependency_names.as_ref())
+            .map(|names| names.iter().map(|name| name.as_ref()).collect())
+            .unwrap_or_default()
+    }
+
     /// Convert indexed resources to a [PyList].
     pub fn resources_as_py_list<'p>(&self, py: Python<'p>) -> PyResult<&'p PyList> {
-        let mut resources = self.resources.values().collect::<Vec<_>>();
-        resources.sort_by_key(|r| &r.name);
+        let mut resources = self
+            .resources
+            .values()
+            .map(|r| {
+                (
+                    r.clone(),
+                    self.resource_collection(&r.name).map(String::from),
+                )
+            })
+            .collect::<Vec<_>>();
+        resources.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
 
         let objects = resources
             .iter()
-            .map(|r| resource_to_pyobject(py, r))
+            .map(|(r, collection)| resource_to_pyobject(py, r, collection.as_deref()))
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(PyList::new(py, objects))
     }
 
+    /// Obtain a named resource as an [OxidizedResource].
+    pub(crate) fn resource_as_pyobject<'p>(
+        &self,
+        py: Python<'p>,
+        name: &str,
+    ) -> PyResult<Option<&'p PyCell<OxidizedResource>>> {
+        let resource = match self.resources.get(name) {
+            Some(resource) => resource.clone(),
+            None => return Ok(None),
+        };
+        let collection = self.resource_collection(name).map(String::from);
+
+        resource_to_pyobject(py, &resource, collection.as_deref()).map(Some)
+    }
+
     /// Serialize resources contained in this data structure.
     ///
     /// `ignore_built` and `ignore_frozen` specify whether to ignore built-in
     /// extension modules and frozen modules, respectively.
+    ///
+    /// `format_version` is the version of the packed resources format to write,
+    /// 3 or 4. Version 4 records content digests of resources.
     pub fn serialize_resources(
         &self,
         ignore_builtin: bool,
         ignore_frozen: bool,
+        format_version: u8,
     ) -> Result<Vec<u8>> {
         let mut resources = self
             .resources
@@ -1257,14 +2317,41 @@ impl<'a> PythonResourcesState<'a, u8> {
                 !((resource.is_python_builtin_extension_module && ignore_builtin)
                     || (resource.is_python_frozen_module && ignore_frozen))
             })
+            .map(|resource| resource.as_ref())
             .collect::<Vec<&Resource<u8>>>();
 
         // Sort so behavior is deterministic.
         resources.sort_by_key(|v| &v.name);
 
+        let dictionaries = self
+            .compression_dictionaries
+            .iter()
+            .collect::<BTreeMap<_, _>>()
+            .into_values()
+            .map(|dictionary| dictionary.as_ref())
+            .collect::<Vec<_>>();
+
         let mut buffer = Vec::new();
 
-        python_packed_resources::write_packed_resources_v3(&resources, &mut buffer, None)?;
+        match format_version {
+            3 => python_packed_resources::write_packed_resources_v3_with_dictionaries(
+                &resources,
+                &dictionaries,
+                &mut buffer,
+                None,
+            )?,
+            4 => python_packed_resources::write_packed_resources_v4_with_dictionaries(
+                &resources,
+                &dictionaries,
+                &mut buffer,
+            )?,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "unsupported packed resources format version: {}",
+                    format_version
+                ))
+            }
+        }
 
         Ok(buffer)
     }
@@ -1273,6 +2360,9 @@ impl<'a> PythonResourcesState<'a, u8> {
 #[pyclass(module = "oxidized_importer")]
 pub(crate) struct OxidizedResource {
     resource: RefCell<Resource<'static, u8>>,
+
+    /// Name of the collection the resource was indexed from.
+    origin_collection: Option<String>,
 }
 
 #[pymethods]
@@ -1288,9 +2378,61 @@ impl OxidizedResource {
     fn new() -> PyResult<Self> {
         Ok(Self {
             resource: RefCell::new(Resource::<u8>::default()),
+            origin_collection: None,
         })
     }
 
+    #[getter]
+    fn get_origin_collection(&self) -> Option<String> {
+        self.origin_collection.clone()
+    }
+
+    #[getter]
+    fn get_locations(&self) -> Vec<&'static str> {
+        let resource = self.resource.borrow();
+
+        let mut locations = vec![];
+        if !resource_in_memory_data(&resource).is_empty() {
+            locations.push("in-memory");
+        }
+        if resource_has_relative_path_data(&resource) {
+            locations.push("filesystem-relative");
+        }
+
+        locations
+    }
+
+    #[getter]
+    fn get_in_memory_size(&self) -> usize {
+        resource_in_memory_data(&self.resource.borrow())
+            .iter()
+            .map(|data| data.len())
+            .sum()
+    }
+
+    #[getter]
+    fn get_sha256(&self) -> Option<String> {
+        let resource = self.resource.borrow();
+        let data = resource_in_memory_data(&resource);
+
+        if data.is_empty() {
+            return None;
+        }
+
+        let mut hasher = Sha256::new();
+        for data in data {
+            hasher.update(data);
+        }
+
+        Some(
+            hasher
+                .finalize()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect(),
+        )
+    }
+
     #[getter]
     fn get_is_module(&self) -> bool {
         self.resource.borrow().is_python_module
@@ -1457,6 +2599,22 @@ impl OxidizedResource {
         Ok(())
     }
 
+    #[getter]
+    fn get_in_memory_bytecode_compression(&self) -> Option<&'static str> {
+        self.resource
+            .borrow()
+            .in_memory_bytecode_compression
+            .map(data_compression_name)
+    }
+
+    #[setter]
+    fn set_in_memory_bytecode_compression(&self, value: Option<&str>) -> PyResult<()> {
+        self.resource.borrow_mut().in_memory_bytecode_compression =
+            value.map(data_compression_from_name).transpose()?;
+
+        Ok(())
+    }
+
     #[getter]
     fn get_in_memory_extension_module_shared_library<'p>(
         &self,
@@ -1507,6 +2665,74 @@ impl OxidizedResource {
         Ok(())
     }
 
+    #[getter]
+    fn get_in_memory_package_resources_compression(&self) -> Option<&'static str> {
+        self.resource
+            .borrow()
+            .in_memory_package_resources_compression
+            .map(data_compression_name)
+    }
+
+    #[setter]
+    fn set_in_memory_package_resources_compression(&self, value: Option<&str>) -> PyResult<()> {
+        self.resource
+            .borrow_mut()
+            .in_memory_package_resources_compression =
+            value.map(data_compression_from_name).transpose()?;
+
+        Ok(())
+    }
+
+    #[getter]
+    fn get_in_memory_localized_package_resources<'p>(
+        &self,
+        py: Python<'p>,
+    ) -> Option<HashMap<String, HashMap<String, &'p PyBytes>>> {
+        self.resource
+            .borrow()
+            .in_memory_localized_package_resources
+            .as_ref()
+            .map(|x| {
+                x.iter()
+                    .map(|(locale, resources)| {
+                        (
+                            locale.to_string(),
+                            resources
+                                .iter()
+                                .map(|(k, v)| (k.to_string(), PyBytes::new(py, v)))
+                                .collect(),
+                        )
+                    })
+                    .collect()
+            })
+    }
+
+    #[setter]
+    fn set_in_memory_localized_package_resources(&self, value: &PyAny) -> PyResult<()> {
+        self.resource
+            .borrow_mut()
+            .in_memory_localized_package_resources = if value.is_none() {
+            None
+        } else {
+            let mut res = HashMap::new();
+
+            for (locale, resources) in value.downcast::<PyDict>()?.iter() {
+                res.insert(
+                    Cow::Owned(locale.extract::<String>()?),
+                    pyobject_optional_resources_map_to_owned_bytes(resources)?
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|(k, v)| (Cow::Owned(k), Cow::Owned(v)))
+                        .collect(),
+                );
+            }
+
+            Some(res)
+        };
+
+        Ok(())
+    }
+
     #[getter]
     fn get_in_memory_distribution_resources<'p>(
         &self,
@@ -1747,21 +2973,135 @@ impl OxidizedResource {
 
         Ok(())
     }
+
+    #[getter]
+    fn get_source_map_path(&self) -> Option<String> {
+        self.resource
+            .borrow()
+            .source_map_path
+            .as_ref()
+            .map(|x| x.to_string())
+    }
+
+    #[setter]
+    fn set_source_map_path(&self, value: Option<&str>) -> PyResult<()> {
+        self.resource.borrow_mut().source_map_path = value.map(|x| Cow::Owned(x.to_owned()));
+
+        Ok(())
+    }
 }
 
 /// Convert a Resource to an OxidizedResource.
 pub(crate) fn resource_to_pyobject<'p>(
     py: Python<'p>,
     resource: &Resource<u8>,
+    origin_collection: Option<&str>,
 ) -> PyResult<&'p PyCell<OxidizedResource>> {
     PyCell::new(
         py,
         OxidizedResource {
             resource: RefCell::new(resource.to_owned()),
+            origin_collection: origin_collection.map(|s| s.to_string()),
         },
     )
 }
 
+/// Obtain the name of a compression algorithm exposed to Python.
+fn data_compression_name(compression: DataCompression) -> &'static str {
+    match compression {
+        DataCompression::Zlib => "zlib",
+        DataCompression::Zstd => "zstd",
+        DataCompression::ZstdDictionary => "zstd-dict",
+    }
+}
+
+/// Resolve a compression algorithm from its name exposed to Python.
+fn data_compression_from_name(name: &str) -> PyResult<DataCompression> {
+    match name {
+        "zlib" => Ok(DataCompression::Zlib),
+        "zstd" => Ok(DataCompression::Zstd),
+        "zstd-dict" => Ok(DataCompression::ZstdDictionary),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown compression: {}; must be zlib, zstd, or zstd-dict",
+            name
+        ))),
+    }
+}
+
+/// Obtain the in-memory data of a resource, as stored.
+///
+/// Data is ordered by field and then by the keys of mapping fields.
+fn resource_in_memory_data<'r>(resource: &'r Resource<u8>) -> Vec<&'r [u8]> {
+    let mut res = vec![];
+
+    for data in [
+        &resource.in_memory_source,
+        &resource.in_memory_bytecode,
+        &resource.in_memory_bytecode_opt1,
+        &resource.in_memory_bytecode_opt2,
+        &resource.in_memory_extension_module_shared_library,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        res.push(data.as_ref());
+    }
+
+    for resources in [
+        &resource.in_memory_package_resources,
+        &resource.in_memory_distribution_resources,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        res.extend(
+            resources
+                .iter()
+                .collect::<BTreeMap<_, _>>()
+                .into_values()
+                .map(|data| data.as_ref()),
+        );
+    }
+
+    if let Some(locales) = &resource.in_memory_localized_package_resources {
+        for resources in locales.iter().collect::<BTreeMap<_, _>>().into_values() {
+            res.extend(
+                resources
+                    .iter()
+                    .collect::<BTreeMap<_, _>>()
+                    .into_values()
+                    .map(|data| data.as_ref()),
+            );
+        }
+    }
+
+    for data in [
+        &resource.in_memory_shared_library,
+        &resource.file_data_embedded,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        res.push(data.as_ref());
+    }
+
+    res
+}
+
+/// Whether a resource references data at filesystem relative paths.
+fn resource_has_relative_path_data(resource: &Resource<u8>) -> bool {
+    resource.relative_path_module_source.is_some()
+        || resource.relative_path_module_bytecode.is_some()
+        || resource.relative_path_module_bytecode_opt1.is_some()
+        || resource.relative_path_module_bytecode_opt2.is_some()
+        || resource
+            .relative_path_extension_module_shared_library
+            .is_some()
+        || resource.relative_path_package_resources.is_some()
+        || resource.relative_path_distribution_resources.is_some()
+        || resource.file_data_utf8_relative_path.is_some()
+}
+
 #[inline]
 pub(crate) fn pyobject_to_resource(resource: &OxidizedResource) -> Resource<'static, u8> {
     resource.resource.borrow().clone()
@@ -4,7 +4,14 @@
 
 use {
     crate::importer::ImporterState,
-    pyo3::{exceptions::PyFileNotFoundError, prelude::*},
+    pyo3::{
+        exceptions::{
+            PyFileNotFoundError, PyIsADirectoryError, PyNotADirectoryError, PyValueError,
+        },
+        prelude::*,
+        pyclass::CompareOp,
+        types::{PyDict, PyList, PyTuple},
+    },
     std::sync::Arc,
 };
 
@@ -80,4 +87,268 @@ impl OxidizedResourceReader {
             .get_resources_state()
             .package_resource_names(py, &self.package)
     }
+
+    /// Returns a Traversable object for the package.
+    ///
+    /// Implements importlib.resources.abc.TraversableResources.
+    fn files(&self) -> OxidizedTraversable {
+        OxidizedTraversable {
+            state: self.state.clone(),
+            package: self.package.clone(),
+            path: "".to_string(),
+        }
+    }
+}
+
+/// Path-like object facilitating Python resource access.
+///
+/// Implements importlib.resources.abc.Traversable.
+///
+/// Instances refer to a package or to a file or directory of resources within
+/// a package. Resources in sub-packages are exposed as if sub-packages were
+/// directories.
+#[pyclass(module = "oxidized_importer")]
+pub(crate) struct OxidizedTraversable {
+    state: Arc<ImporterState>,
+    /// Name of the package resources are relative to.
+    package: String,
+    /// Path of the resource within the package. Empty for the package itself.
+    path: String,
+}
+
+impl OxidizedTraversable {
+    fn with_path(&self, package: String, path: String) -> Self {
+        Self {
+            state: self.state.clone(),
+            package,
+            path,
+        }
+    }
+
+    /// Resolve a path relative to this instance.
+    ///
+    /// Both `/` and `\` are accepted as directory separators.
+    fn join(&self, child: &str) -> PyResult<Self> {
+        let resources_state = self.state.get_resources_state();
+
+        let mut package = self.package.clone();
+        let mut path = self.path.clone();
+
+        for component in child.split(['/', '\\']) {
+            match component {
+                "" | "." => {}
+                ".." => {
+                    return Err(PyValueError::new_err(
+                        "traversing to parent directories is not supported",
+                    ));
+                }
+                _ if path.is_empty() => {
+                    let subpackage = format!("{}.{}", package, component);
+
+                    if !resources_state.is_package_resource_directory(&package, component)
+                        && resources_state.is_package(&subpackage)
+                    {
+                        package = subpackage;
+                    } else {
+                        path = component.to_string();
+                    }
+                }
+                _ => {
+                    path = format!("{}/{}", path, component);
+                }
+            }
+        }
+
+        Ok(self.with_path(package, path))
+    }
+
+    /// Obtain a binary file object for reading the resource.
+    fn open_binary<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        if let Some(file) = self.state.get_resources_state().get_package_resource_file(
+            py,
+            &self.package,
+            &self.path,
+        )? {
+            Ok(file)
+        } else if self.is_dir() {
+            Err(PyIsADirectoryError::new_err(self.__str__()))
+        } else {
+            Err(PyFileNotFoundError::new_err(self.__str__()))
+        }
+    }
+}
+
+#[pymethods]
+impl OxidizedTraversable {
+    /// The base name of this object without any parent references.
+    #[getter]
+    fn name(&self) -> String {
+        if self.path.is_empty() {
+            self.package.rsplit('.').next().unwrap_or_default()
+        } else {
+            self.path.rsplit('/').next().unwrap_or_default()
+        }
+        .to_string()
+    }
+
+    /// Yield Traversable objects in self.
+    fn iterdir<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        if !self.is_dir() {
+            return Err(if self.is_file() {
+                PyNotADirectoryError::new_err(self.__str__())
+            } else {
+                PyFileNotFoundError::new_err(self.__str__())
+            });
+        }
+
+        let resources_state = self.state.get_resources_state();
+
+        let mut entries = vec![];
+
+        if self.path.is_empty() {
+            for name in resources_state.list_subpackages(&self.package) {
+                entries.push(self.with_path(format!("{}.{}", self.package, name), "".into()));
+            }
+        }
+
+        for name in resources_state
+            .package_resources_list_subdirectories(&self.package, &self.path)
+            .into_iter()
+            .chain(resources_state.package_resources_list_directory(&self.package, &self.path))
+        {
+            let path = if self.path.is_empty() {
+                name
+            } else {
+                format!("{}/{}", self.path, name)
+            };
+
+            entries.push(self.with_path(self.package.clone(), path));
+        }
+
+        let entries = entries
+            .into_iter()
+            .map(|entry| Py::new(py, entry))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        PyList::new(py, entries).call_method0("__iter__")
+    }
+
+    /// Read contents of self as bytes.
+    fn read_bytes<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        self.open_binary(py)?.call_method0("read")
+    }
+
+    /// Read contents of self as text.
+    #[pyo3(signature = (encoding=None))]
+    fn read_text<'p>(&self, py: Python<'p>, encoding: Option<&str>) -> PyResult<&'p PyAny> {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("encoding", encoding)?;
+
+        self.open(py, "r", PyTuple::empty(py), Some(kwargs))?
+            .call_method0("read")
+    }
+
+    /// Return True if self is a dir.
+    fn is_dir(&self) -> bool {
+        self.path.is_empty()
+            || self
+                .state
+                .get_resources_state()
+                .is_package_resource_directory(&self.package, &self.path)
+    }
+
+    /// Return True if self is a file.
+    fn is_file(&self) -> bool {
+        !self.path.is_empty()
+            && self
+                .state
+                .get_resources_state()
+                .is_package_resource(&self.package, &self.path)
+    }
+
+    /// Return Traversable resolved with any descendants applied.
+    #[pyo3(signature = (*descendants))]
+    fn joinpath(&self, descendants: &PyTuple) -> PyResult<Self> {
+        let mut res = self.with_path(self.package.clone(), self.path.clone());
+
+        for child in descendants {
+            res = res.join(child.extract::<&str>()?)?;
+        }
+
+        Ok(res)
+    }
+
+    /// Return Traversable child in self.
+    fn __truediv__(&self, child: &str) -> PyResult<Self> {
+        self.join(child)
+    }
+
+    /// mode may be 'r' or 'rb' to open as text or binary. Return a handle
+    /// suitable for reading (same as pathlib.Path.open).
+    ///
+    /// When opening as text, accepts encoding parameters such as those
+    /// accepted by io.TextIOWrapper.
+    #[pyo3(signature = (mode="r", *args, **kwargs))]
+    fn open<'p>(
+        &self,
+        py: Python<'p>,
+        mode: &str,
+        args: &PyTuple,
+        kwargs: Option<&PyDict>,
+    ) -> PyResult<&'p PyAny> {
+        match mode {
+            "r" => {
+                let args = PyTuple::new(
+                    py,
+                    std::iter::once(self.open_binary(py)?)
+                        .chain(args.iter())
+                        .collect::<Vec<_>>(),
+                );
+
+                py.import("io")?
+                    .getattr("TextIOWrapper")?
+                    .call(args, kwargs)
+            }
+            "rb" => self.open_binary(py),
+            _ => Err(PyValueError::new_err(format!(
+                "invalid mode: {}; must be 'r' or 'rb'",
+                mode
+            ))),
+        }
+    }
+
+    fn __str__(&self) -> String {
+        let package = self.package.replace('.', "/");
+
+        if self.path.is_empty() {
+            package
+        } else {
+            format!("{}/{}", package, self.path)
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<OxidizedTraversable {}>", self.__str__())
+    }
+
+    fn __richcmp__(&self, py: Python, other: &PyAny, op: CompareOp) -> PyObject {
+        let other = match other.extract::<PyRef<Self>>() {
+            Ok(other) => other,
+            Err(_) => return py.NotImplemented(),
+        };
+
+        let equal = self.package == other.package && self.path == other.path;
+
+        match op {
+            CompareOp::Eq => equal.into_py(py),
+            CompareOp::Ne => (!equal).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        std::hash::Hash::hash(&(&self.package, &self.path), &mut hasher);
+        std::hash::Hasher::finish(&hasher)
+    }
 }
@@ -19,18 +19,21 @@ use {
     },
 };
 
-/// Scans a filesystem path for Python resources and turns them into Python types.
-#[pyfunction]
-pub(crate) fn find_resources_in_path<'p>(py: Python<'p>, path: &PyAny) -> PyResult<&'p PyList> {
-    let path = pyobject_to_pathbuf(py, path)?;
-
-    if !path.is_dir() {
-        return Err(PyValueError::new_err(format!(
-            "path is not a directory: {}",
-            path.display()
-        )));
-    }
+#[cfg(feature = "wheel")]
+use {
+    pyo3::types::PyBytes,
+    python_packaging::{
+        bytecode::{CompileMode, PythonBytecodeCompiler},
+        location::{AbstractResourceLocation, ConcreteResourceLocation},
+        resource::BytecodeOptimizationLevel,
+        resource_collection::PythonResourceCollector,
+        wheel::WheelArchive,
+    },
+    python_packed_resources::Resource,
+};
 
+/// Resolve the bytecode cache tag and module suffixes of the running interpreter.
+fn interpreter_module_suffixes(py: Python) -> PyResult<(String, PythonModuleSuffixes)> {
     let sys_module = py.import("sys")?;
     let implementation = sys_module.getattr("implementation")?;
     let cache_tag = implementation.getattr("cache_tag")?.extract::<String>()?;
@@ -61,6 +64,23 @@ pub(crate) fn find_resources_in_path<'p>(py: Python<'p>, path: &PyAny) -> PyResu
         extension,
     };
 
+    Ok((cache_tag, suffixes))
+}
+
+/// Scans a filesystem path for Python resources and turns them into Python types.
+#[pyfunction]
+pub(crate) fn find_resources_in_path<'p>(py: Python<'p>, path: &PyAny) -> PyResult<&'p PyList> {
+    let path = pyobject_to_pathbuf(py, path)?;
+
+    if !path.is_dir() {
+        return Err(PyValueError::new_err(format!(
+            "path is not a directory: {}",
+            path.display()
+        )));
+    }
+
+    let (cache_tag, suffixes) = interpreter_module_suffixes(py)?;
+
     let mut res: Vec<Py<PyAny>> = Vec::new();
 
     let iter = find_python_resources(&path, &cache_tag, &suffixes, false, true)
@@ -96,6 +116,91 @@ pub(crate) fn find_resources_in_path<'p>(py: Python<'p>, path: &PyAny) -> PyResu
     Ok(PyList::new(py, &res))
 }
 
+/// A bytecode compiler for resources that don't request bytecode.
+///
+/// Resources derived at run-time only carry module source, which is compiled
+/// by the importer when the module is loaded.
+#[cfg(feature = "wheel")]
+struct SourceOnlyCompiler;
+
+#[cfg(feature = "wheel")]
+impl PythonBytecodeCompiler for SourceOnlyCompiler {
+    fn get_magic_number(&self) -> u32 {
+        0
+    }
+
+    fn compile(
+        &mut self,
+        _source: &[u8],
+        filename: &str,
+        _optimize: BytecodeOptimizationLevel,
+        _output_mode: CompileMode,
+    ) -> anyhow::Result<Vec<u8>> {
+        Err(anyhow::anyhow!(
+            "bytecode compilation of {} is not supported",
+            filename
+        ))
+    }
+}
+
+/// Obtain indexable resources from a wheel.
+///
+/// `wheel` is a path-like object or `bytes` holding the wheel's content.
+/// Modules are indexed as in-memory source. Wheels containing extension
+/// modules are rejected, as those can't be loaded from memory.
+#[cfg(feature = "wheel")]
+pub(crate) fn wheel_resources(py: Python, wheel: &PyAny) -> PyResult<Vec<Resource<'static, u8>>> {
+    let archive = if let Ok(data) = wheel.downcast::<PyBytes>() {
+        WheelArchive::from_reader_unnamed(std::io::Cursor::new(data.as_bytes()))
+    } else {
+        WheelArchive::from_path(&pyobject_to_pathbuf(py, wheel)?)
+    }
+    .map_err(|e| PyValueError::new_err(format!("error reading wheel: {}", e)))?;
+
+    let (cache_tag, suffixes) = interpreter_module_suffixes(py)?;
+
+    let resources = archive
+        .python_resources(&cache_tag, &suffixes, false, true)
+        .map_err(|e| PyValueError::new_err(format!("error reading wheel: {}", e)))?;
+
+    let mut collector = PythonResourceCollector::new(
+        vec![AbstractResourceLocation::InMemory],
+        vec![],
+        false,
+        false,
+    );
+
+    let location = ConcreteResourceLocation::InMemory;
+
+    for resource in resources {
+        match resource {
+            PythonResource::ModuleSource(module) => {
+                collector.add_python_module_source(&module, &location)
+            }
+            PythonResource::PackageResource(resource) => {
+                collector.add_python_package_resource(&resource, &location)
+            }
+            PythonResource::PackageDistributionResource(resource) => {
+                collector.add_python_package_distribution_resource(&resource, &location)
+            }
+            PythonResource::ExtensionModule(module) => {
+                return Err(PyValueError::new_err(format!(
+                    "wheel contains extension module {}; only pure Python wheels are supported",
+                    module.name
+                )));
+            }
+            _ => continue,
+        }
+        .map_err(|e| PyValueError::new_err(format!("error indexing wheel: {}", e)))?;
+    }
+
+    let compiled = collector
+        .compile_resources(&mut SourceOnlyCompiler)
+        .map_err(|e| PyValueError::new_err(format!("error indexing wheel: {}", e)))?;
+
+    Ok(compiled.resources.into_values().collect())
+}
+
 pub(crate) fn init_module(m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(find_resources_in_path, m)?)?;
 
@@ -0,0 +1,387 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Serving package resources as static files over HTTP.
+
+`oxidized_importer.serve_static()` serves the resources of a package, such as
+web assets added with `PythonExecutable.read_static_assets()`, on a loopback
+address. This allows applications to ship a web interface without depending
+on a Python HTTP server.
+
+Resources are snapshotted when the server is started. Requests are served by
+Rust threads which never acquire the GIL.
+*/
+
+use {
+    crate::{importer::OxidizedFinder, python_resources::PackageResourceContent},
+    pyo3::{exceptions::PyValueError, prelude::*, types::PyList},
+    std::{
+        borrow::Cow,
+        collections::BTreeMap,
+        io::{BufRead, BufReader, Read, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread::JoinHandle,
+        time::Duration,
+    },
+};
+
+/// Maximum size of the request line and headers we read.
+const MAX_REQUEST_SIZE: u64 = 16384;
+
+/// How long to wait for a client to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Resource served for directories and unknown extensionless paths.
+const INDEX: &str = "index.html";
+
+/// Obtain the `Content-Type` of a file from its name.
+fn content_type(name: &str) -> &'static str {
+    let extension = match name.rsplit_once('.') {
+        Some((_, extension)) => extension.to_ascii_lowercase(),
+        None => return "application/octet-stream",
+    };
+
+    match extension.as_str() {
+        "css" => "text/css; charset=utf-8",
+        "gif" => "image/gif",
+        "htm" | "html" => "text/html; charset=utf-8",
+        "ico" => "image/x-icon",
+        "jpeg" | "jpg" => "image/jpeg",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "png" => "image/png",
+        "svg" => "image/svg+xml",
+        "ttf" => "font/ttf",
+        "txt" => "text/plain; charset=utf-8",
+        "wasm" => "application/wasm",
+        "webp" => "image/webp",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "xml" => "application/xml",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Decode `%XX` escapes in a URL path.
+///
+/// Returns `None` if an escape is malformed or the result isn't UTF-8.
+fn percent_decode(path: &str) -> Option<String> {
+    let mut res = Vec::with_capacity(path.len());
+    let mut bytes = path.bytes();
+
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            res.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            res.push(b);
+        }
+    }
+
+    String::from_utf8(res).ok()
+}
+
+/// Files being served, keyed by their path relative to the server root.
+struct Assets {
+    files: BTreeMap<String, PackageResourceContent>,
+}
+
+impl Assets {
+    /// Resolve the file to serve for a request target.
+    fn resolve(&self, target: &str) -> Option<(&str, &PackageResourceContent)> {
+        let path = target.split(['?', '#']).next()?;
+        let path = percent_decode(path.strip_prefix('/')?)?;
+
+        if path
+            .split('/')
+            .any(|component| component == "." || component == ".." || component.contains('\\'))
+        {
+            return None;
+        }
+
+        let mut candidates = vec![];
+        if path.is_empty() || path.ends_with('/') {
+            candidates.push(format!("{}{}", path, INDEX));
+        } else {
+            candidates.push(path.clone());
+            candidates.push(format!("{}/{}", path, INDEX));
+        }
+
+        // Let single page applications route extensionless paths themselves.
+        if !path.rsplit('/').next().unwrap_or_default().contains('.') {
+            candidates.push(INDEX.to_string());
+        }
+
+        candidates.iter().find_map(|name| {
+            self.files
+                .get_key_value(name.as_str())
+                .map(|(name, content)| (name.as_str(), content))
+        })
+    }
+}
+
+/// An HTTP response.
+struct Response<'a> {
+    status: &'static str,
+    content_type: &'static str,
+    body: Cow<'a, [u8]>,
+}
+
+impl<'a> Response<'a> {
+    fn error(status: &'static str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: Cow::Owned(format!("{}\n", status).into_bytes()),
+        }
+    }
+}
+
+/// Produce the response to a request.
+fn respond<'a>(assets: &'a Assets, method: &str, target: &str) -> Response<'a> {
+    if method != "GET" && method != "HEAD" {
+        return Response::error("405 Method Not Allowed");
+    }
+
+    let (name, content) = match assets.resolve(target) {
+        Some(res) => res,
+        None => return Response::error("404 Not Found"),
+    };
+
+    let body = match content {
+        PackageResourceContent::Memory(data) => Cow::Borrowed(data.as_slice()),
+        PackageResourceContent::Path(path) => match std::fs::read(path) {
+            Ok(data) => Cow::Owned(data),
+            Err(_) => return Response::error("500 Internal Server Error"),
+        },
+    };
+
+    Response {
+        status: "200 OK",
+        content_type: content_type(name),
+        body,
+    }
+}
+
+/// Serve the request of a connection.
+fn handle_connection(stream: TcpStream, assets: &Assets) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let mut reader = BufReader::new((&stream).take(MAX_REQUEST_SIZE));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Headers don't influence the response. But they must be consumed before
+    // closing the connection, lest the client sees a reset.
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method, target),
+        _ => ("", ""),
+    };
+
+    let response = if method.is_empty() {
+        Response::error("400 Bad Request")
+    } else {
+        respond(assets, method, target)
+    };
+
+    let mut writer = &stream;
+    write!(
+        writer,
+        "HTTP/1.1 {}\r\n\
+        Content-Type: {}\r\n\
+        Content-Length: {}\r\n\
+        X-Content-Type-Options: nosniff\r\n\
+        Connection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    )?;
+
+    if method != "HEAD" {
+        writer.write_all(&response.body)?;
+    }
+
+    writer.flush()
+}
+
+/// Obtain the first `OxidizedFinder` on `sys.meta_path`.
+fn meta_path_finder<'p>(py: Python<'p>) -> PyResult<&'p PyCell<OxidizedFinder>> {
+    let meta_path = py
+        .import("sys")?
+        .getattr("meta_path")?
+        .downcast::<PyList>()?;
+
+    meta_path
+        .iter()
+        .find_map(|finder| finder.downcast::<PyCell<OxidizedFinder>>().ok())
+        .ok_or_else(|| PyValueError::new_err("no OxidizedFinder on sys.meta_path"))
+}
+
+/// A running static file server.
+#[pyclass(module = "oxidized_importer")]
+pub(crate) struct StaticServer {
+    address: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StaticServer {
+    fn start(assets: Assets, port: u16) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let address = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let assets = Arc::new(assets);
+
+        let thread = {
+            let stop = stop.clone();
+
+            std::thread::Builder::new()
+                .name("static-server".to_string())
+                .spawn(move || {
+                    for stream in listener.incoming() {
+                        if stop.load(Ordering::SeqCst) {
+                            break;
+                        }
+
+                        if let Ok(stream) = stream {
+                            let assets = assets.clone();
+                            std::thread::spawn(move || {
+                                // Errors only affect the client of the connection.
+                                let _ = handle_connection(stream, &assets);
+                            });
+                        }
+                    }
+                })?
+        };
+
+        Ok(Self {
+            address,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    fn stop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.stop.store(true, Ordering::SeqCst);
+            // Wake up the thread blocked accepting connections.
+            let _ = TcpStream::connect(self.address);
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for StaticServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[pymethods]
+impl StaticServer {
+    /// The port the server listens on.
+    #[getter]
+    fn port(&self) -> u16 {
+        self.address.port()
+    }
+
+    /// The URL of the server root.
+    #[getter]
+    fn url(&self) -> String {
+        format!("http://{}/", self.address)
+    }
+
+    /// Stop serving requests.
+    fn shutdown(&mut self, py: Python) {
+        py.allow_threads(|| self.stop());
+    }
+
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        py: Python,
+        _exc_type: &PyAny,
+        _exc_value: &PyAny,
+        _traceback: &PyAny,
+    ) -> bool {
+        self.shutdown(py);
+        false
+    }
+}
+
+/// serve_static(prefix, port=0, finder=None)
+/// --
+///
+/// Serve package resources over HTTP on 127.0.0.1.
+#[pyfunction]
+#[pyo3(signature = (prefix, port=0, finder=None))]
+fn serve_static(
+    py: Python,
+    prefix: &str,
+    port: u16,
+    finder: Option<&PyCell<OxidizedFinder>>,
+) -> PyResult<StaticServer> {
+    let (package, directory) = match prefix.split_once('/') {
+        Some((package, directory)) => (package, directory.trim_matches('/')),
+        None => (prefix, ""),
+    };
+
+    let finder = match finder {
+        Some(finder) => finder,
+        None => meta_path_finder(py)?,
+    };
+
+    let resources = finder
+        .borrow()
+        .state
+        .get_resources_state()
+        .package_resources_content(py, package)?;
+
+    let files = if directory.is_empty() {
+        resources
+    } else {
+        let directory = format!("{}/", directory);
+
+        resources
+            .into_iter()
+            .filter_map(|(name, content)| {
+                name.strip_prefix(&directory)
+                    .map(|name| (name.to_string(), content))
+            })
+            .collect()
+    };
+
+    if files.is_empty() {
+        return Err(PyValueError::new_err(format!(
+            "no resources to serve in {}",
+            prefix
+        )));
+    }
+
+    Ok(StaticServer::start(Assets { files }, port)?)
+}
+
+pub(crate) fn init_module(m: &PyModule) -> PyResult<()> {
+    m.add_class::<StaticServer>()?;
+    m.add_function(wrap_pyfunction!(serve_static, m)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,262 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! The experimental `oxidized_importer.wasm` module.
+
+This module runs extension logic compiled to WebAssembly with wasmtime.
+A thin Python module, typically shipped alongside the `.wasm` file as a
+package resource, instantiates a `WasmModule` and exposes its exported
+functions to Python code.
+
+Modules run sandboxed: they can't import host functions, so they can
+only compute on their arguments and their own linear memory.
+*/
+
+use {
+    pyo3::{
+        exceptions::{PyRuntimeError, PyTypeError, PyValueError},
+        prelude::*,
+        types::{PyBytes, PyTuple},
+    },
+    wasmtime::{
+        Config, Engine, ExternType, Instance, Memory, Module, Store, StoreLimits,
+        StoreLimitsBuilder, Val, ValType,
+    },
+};
+
+/// Name of the module holding WebAssembly functionality.
+const WASM_NAME: &str = "oxidized_importer.wasm";
+
+const WASM_DOC: &str = "Experimental support for extension logic compiled to WebAssembly.
+
+WasmModule(data) instantiates a WebAssembly module in a sandbox without
+access to host functions. Its exported functions are called with
+WasmModule.call().";
+
+/// Name of the export holding the linear memory of a module.
+const MEMORY_EXPORT: &str = "memory";
+
+fn wasm_error(e: impl std::fmt::Display) -> PyErr {
+    PyRuntimeError::new_err(format!("WebAssembly error: {:#}", e))
+}
+
+/// Convert a Python value to a WebAssembly value of a type.
+fn py_to_val(value: &PyAny, ty: &ValType) -> PyResult<Val> {
+    Ok(match ty {
+        ValType::I32 => Val::I32(value.extract()?),
+        ValType::I64 => Val::I64(value.extract()?),
+        ValType::F32 => Val::F32(value.extract::<f32>()?.to_bits()),
+        ValType::F64 => Val::F64(value.extract::<f64>()?.to_bits()),
+        _ => {
+            return Err(PyTypeError::new_err(format!(
+                "WebAssembly parameters of type {} are not supported",
+                ty
+            )))
+        }
+    })
+}
+
+/// Convert a WebAssembly value to a Python value.
+fn val_to_py(py: Python, value: &Val) -> PyResult<PyObject> {
+    Ok(match value {
+        Val::I32(v) => v.into_py(py),
+        Val::I64(v) => v.into_py(py),
+        Val::F32(bits) => f32::from_bits(*bits).into_py(py),
+        Val::F64(bits) => f64::from_bits(*bits).into_py(py),
+        _ => {
+            return Err(PyTypeError::new_err(format!(
+                "WebAssembly results of type {} are not supported",
+                value.ty()
+            )))
+        }
+    })
+}
+
+/// An instantiated WebAssembly module.
+#[pyclass(module = "oxidized_importer.wasm")]
+pub(crate) struct WasmModule {
+    store: Store<StoreLimits>,
+    instance: Instance,
+    memory: Option<Memory>,
+    /// Fuel each call may consume, if limited.
+    fuel: Option<u64>,
+    exports: Vec<String>,
+}
+
+impl WasmModule {
+    /// Refill fuel to the per-call limit.
+    fn refill_fuel(&mut self) -> PyResult<()> {
+        if let Some(fuel) = self.fuel {
+            let remaining = self.store.consume_fuel(0).map_err(wasm_error)?;
+            self.store
+                .add_fuel(fuel.saturating_sub(remaining))
+                .map_err(wasm_error)?;
+        }
+
+        Ok(())
+    }
+
+    fn memory(&self) -> PyResult<Memory> {
+        self.memory.ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "WebAssembly module does not export {}",
+                MEMORY_EXPORT
+            ))
+        })
+    }
+}
+
+#[pymethods]
+impl WasmModule {
+    #[new]
+    #[pyo3(signature = (data, fuel=None, max_memory=None))]
+    fn new(data: &[u8], fuel: Option<u64>, max_memory: Option<usize>) -> PyResult<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(fuel.is_some());
+
+        let engine = Engine::new(&config).map_err(wasm_error)?;
+        let module = Module::new(&engine, data).map_err(wasm_error)?;
+
+        if let Some(import) = module.imports().next() {
+            return Err(PyValueError::new_err(format!(
+                "WebAssembly module imports {}.{}; imports are not supported",
+                import.module(),
+                import.name()
+            )));
+        }
+
+        let mut limits = StoreLimitsBuilder::new();
+        if let Some(max_memory) = max_memory {
+            limits = limits.memory_size(max_memory);
+        }
+
+        let mut store = Store::new(&engine, limits.build());
+        store.limiter(|limits| limits);
+
+        if let Some(fuel) = fuel {
+            store.add_fuel(fuel).map_err(wasm_error)?;
+        }
+
+        let instance = Instance::new(&mut store, &module, &[]).map_err(wasm_error)?;
+        let memory = instance.get_memory(&mut store, MEMORY_EXPORT);
+
+        let exports = module
+            .exports()
+            .filter(|export| matches!(export.ty(), ExternType::Func(_)))
+            .map(|export| export.name().to_string())
+            .collect();
+
+        Ok(Self {
+            store,
+            instance,
+            memory,
+            fuel,
+            exports,
+        })
+    }
+
+    /// Names of exported functions.
+    #[getter]
+    fn exports<'p>(&self, py: Python<'p>) -> &'p PyTuple {
+        PyTuple::new(py, &self.exports)
+    }
+
+    /// Call an exported function.
+    ///
+    /// Returns None, a single value or a tuple of values depending on the
+    /// number of results of the function.
+    #[pyo3(signature = (name, *args))]
+    fn call(&mut self, py: Python, name: &str, args: &PyTuple) -> PyResult<PyObject> {
+        let func = self
+            .instance
+            .get_func(&mut self.store, name)
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "WebAssembly module does not export function {}",
+                    name
+                ))
+            })?;
+        let ty = func.ty(&self.store);
+
+        if ty.params().len() != args.len() {
+            return Err(PyTypeError::new_err(format!(
+                "{}() takes {} arguments ({} given)",
+                name,
+                ty.params().len(),
+                args.len()
+            )));
+        }
+
+        let params = ty
+            .params()
+            .zip(args.iter())
+            .map(|(ty, arg)| py_to_val(arg, &ty))
+            .collect::<PyResult<Vec<_>>>()?;
+        let mut results = vec![Val::I32(0); ty.results().len()];
+
+        self.refill_fuel()?;
+
+        let store = &mut self.store;
+        py.allow_threads(|| func.call(store, &params, &mut results))
+            .map_err(wasm_error)?;
+
+        match results.as_slice() {
+            [] => Ok(py.None()),
+            [value] => val_to_py(py, value),
+            values => Ok(PyTuple::new(
+                py,
+                values
+                    .iter()
+                    .map(|value| val_to_py(py, value))
+                    .collect::<PyResult<Vec<_>>>()?,
+            )
+            .into_py(py)),
+        }
+    }
+
+    /// Read bytes from the exported linear memory.
+    fn read_memory<'p>(
+        &self,
+        py: Python<'p>,
+        offset: usize,
+        length: usize,
+    ) -> PyResult<&'p PyBytes> {
+        let mut data = vec![0; length];
+
+        self.memory()?
+            .read(&self.store, offset, &mut data)
+            .map_err(wasm_error)?;
+
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Write bytes to the exported linear memory.
+    fn write_memory(&mut self, offset: usize, data: &[u8]) -> PyResult<()> {
+        self.memory()?
+            .write(&mut self.store, offset, data)
+            .map_err(wasm_error)
+    }
+
+    /// Size of the exported linear memory in bytes.
+    #[getter]
+    fn memory_size(&self) -> PyResult<usize> {
+        Ok(self.memory()?.data_size(&self.store))
+    }
+}
+
+pub(crate) fn init_module(py: Python, m: &PyModule) -> PyResult<()> {
+    let wasm = PyModule::new(py, WASM_NAME)?;
+    wasm.setattr("__doc__", WASM_DOC)?;
+    wasm.add_class::<WasmModule>()?;
+
+    m.add("wasm", wasm)?;
+
+    // Allow `import oxidized_importer.wasm` even though the parent
+    // isn't a package.
+    py.import("sys")?
+        .getattr("modules")?
+        .set_item(WASM_NAME, wasm)?;
+
+    Ok(())
+}
@@ -21,6 +21,72 @@ use {
 
 pub const BYTECODE_COMPILER: &[u8] = include_bytes!("bytecodecompiler.py");
 
+/// Describes content to strip from Python source before compiling bytecode.
+///
+/// Stripping is independent of the bytecode optimization level, so e.g.
+/// docstrings can be removed from optimization level 0 bytecode without
+/// also removing `assert` statements.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BytecodeStripping {
+    /// Remove module, class, and function docstrings.
+    ///
+    /// This is equivalent to what `python -OO` does. `__doc__` will be `None`.
+    pub docstrings: bool,
+
+    /// Remove annotations only used to populate `__annotations__`.
+    ///
+    /// Function parameter and return annotations and module-level variable
+    /// annotations are removed. Annotated module-level assignments become plain
+    /// assignments. Annotations in class bodies are preserved, as constructs like
+    /// dataclasses and `typing.NamedTuple` rely on them.
+    pub annotations: bool,
+}
+
+impl TryFrom<&str> for BytecodeStripping {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut res = Self::default();
+
+        if value == "none" {
+            return Ok(res);
+        }
+
+        for part in value.split(',') {
+            match part {
+                "docstrings" => res.docstrings = true,
+                "annotations" => res.annotations = true,
+                _ => {
+                    return Err(format!(
+                        "{} is not a valid bytecode stripping value; use \"none\" or a comma delimited list of \"docstrings\" and \"annotations\"",
+                        value
+                    ))
+                }
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+impl std::fmt::Display for BytecodeStripping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.docstrings, self.annotations) {
+            (false, false) => f.write_str("none"),
+            (true, false) => f.write_str("docstrings"),
+            (false, true) => f.write_str("annotations"),
+            (true, true) => f.write_str("docstrings,annotations"),
+        }
+    }
+}
+
+impl BytecodeStripping {
+    /// Whether nothing is stripped.
+    pub fn is_none(&self) -> bool {
+        !self.docstrings && !self.annotations
+    }
+}
+
 /// An entity that can compile Python bytecode.
 pub trait PythonBytecodeCompiler {
     /// Obtain the magic number to use in the bytecode header.
@@ -34,6 +100,27 @@ pub trait PythonBytecodeCompiler {
         optimize: BytecodeOptimizationLevel,
         output_mode: CompileMode,
     ) -> Result<Vec<u8>>;
+
+    /// Compile Python source into bytecode after stripping content from it.
+    ///
+    /// The default implementation only supports not stripping anything.
+    fn compile_stripped(
+        &mut self,
+        source: &[u8],
+        filename: &str,
+        optimize: BytecodeOptimizationLevel,
+        strip: BytecodeStripping,
+        output_mode: CompileMode,
+    ) -> Result<Vec<u8>> {
+        if strip.is_none() {
+            self.compile(source, filename, optimize, output_mode)
+        } else {
+            Err(anyhow!(
+                "bytecode compiler does not support stripping {}",
+                strip
+            ))
+        }
+    }
 }
 
 /// An entity to perform Python bytecode compilation.
@@ -71,11 +158,33 @@ impl BytecodeCompiler {
     /// a proper temporary file internally. The reason this isn't done is to avoid
     /// an extra crate dependency.
     pub fn new(python: &Path, script_dir: impl AsRef<Path>) -> Result<BytecodeCompiler> {
+        Self::new_with_emulator(None, python, script_dir)
+    }
+
+    /// Create a bytecode compiler using a Python executable run through an emulator.
+    ///
+    /// This is like [Self::new] except the Python executable is launched as an
+    /// argument to `emulator` (e.g. `qemu-aarch64` or `wine`), if defined. This
+    /// allows compiling bytecode with an interpreter that can't run natively
+    /// on the current machine.
+    pub fn new_with_emulator(
+        emulator: Option<&Path>,
+        python: &Path,
+        script_dir: impl AsRef<Path>,
+    ) -> Result<BytecodeCompiler> {
         let script_path = script_dir.as_ref().join("bytecode-compiler.py");
         std::fs::write(&script_path, BYTECODE_COMPILER)
             .with_context(|| format!("writing Python script to {}", script_path.display()))?;
 
-        let mut command = process::Command::new(python)
+        let mut command = if let Some(emulator) = emulator {
+            let mut command = process::Command::new(emulator);
+            command.arg(python);
+            command
+        } else {
+            process::Command::new(python)
+        };
+
+        let mut command = command
             .arg(&script_path)
             .stdin(process::Stdio::piped())
             .stdout(process::Stdio::piped())
@@ -130,6 +239,23 @@ impl PythonBytecodeCompiler for BytecodeCompiler {
         filename: &str,
         optimize: BytecodeOptimizationLevel,
         output_mode: CompileMode,
+    ) -> Result<Vec<u8>> {
+        self.compile_stripped(
+            source,
+            filename,
+            optimize,
+            BytecodeStripping::default(),
+            output_mode,
+        )
+    }
+
+    fn compile_stripped(
+        self: &mut BytecodeCompiler,
+        source: &[u8],
+        filename: &str,
+        optimize: BytecodeOptimizationLevel,
+        strip: BytecodeStripping,
+        output_mode: CompileMode,
     ) -> Result<Vec<u8>> {
         let stdin = self.command.stdin.as_mut().expect("failed to get stdin");
         let stdout = self.command.stdout.as_mut().expect("failed to get stdout");
@@ -157,6 +283,10 @@ impl PythonBytecodeCompiler for BytecodeCompiler {
             })
             .context("writing format")?;
         stdin.write_all(b"\n")?;
+        stdin
+            .write_all(strip.to_string().as_bytes())
+            .context("writing strip mode")?;
+        stdin.write_all(b"\n")?;
         stdin
             .write_all(filename.as_bytes())
             .context("writing filename")?;
@@ -288,4 +418,33 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_bytecode_stripping() {
+        assert_eq!(
+            BytecodeStripping::try_from("none"),
+            Ok(BytecodeStripping::default())
+        );
+        assert_eq!(
+            BytecodeStripping::try_from("annotations,docstrings"),
+            Ok(BytecodeStripping {
+                docstrings: true,
+                annotations: true,
+            })
+        );
+        assert!(BytecodeStripping::try_from("asserts").is_err());
+        assert!(BytecodeStripping::try_from("").is_err());
+
+        for value in [
+            "none",
+            "docstrings",
+            "annotations",
+            "docstrings,annotations",
+        ] {
+            assert_eq!(
+                BytecodeStripping::try_from(value).unwrap().to_string(),
+                value
+            );
+        }
+    }
 }
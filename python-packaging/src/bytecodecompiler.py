@@ -11,6 +11,7 @@
 # When invoked, we start a server that listens for commands. We then
 # react to those commands and send results to the caller.
 
+import ast
 import importlib._bootstrap_external
 import importlib.util
 import marshal
@@ -25,6 +26,71 @@ RE_CODING = re.compile(b"^[ \t\f]*#.*?coding[:=][ \t]*([-_.a-zA-Z0-9]+)")
 if marshal.version != 4:
     raise Exception("unexpected marshal version: %d" % marshal.version)
 
+
+def strip_docstring(node):
+    body = node.body
+
+    if (
+        body
+        and isinstance(body[0], ast.Expr)
+        and isinstance(body[0].value, ast.Constant)
+        and isinstance(body[0].value.value, str)
+    ):
+        # Bodies can't be empty. So replace a lone docstring with `pass`.
+        node.body = body[1:] or [ast.copy_location(ast.Pass(), body[0])]
+
+
+def strip_arguments_annotations(args):
+    for arg in args.posonlyargs + args.args + args.kwonlyargs:
+        arg.annotation = None
+
+    if args.vararg:
+        args.vararg.annotation = None
+    if args.kwarg:
+        args.kwarg.annotation = None
+
+
+def strip_module_annotations(body):
+    res = []
+
+    for node in body:
+        if isinstance(node, ast.AnnAssign):
+            if node.value is not None:
+                res.append(
+                    ast.copy_location(
+                        ast.Assign(targets=[node.target], value=node.value),
+                        node,
+                    )
+                )
+            elif not node.simple:
+                # Non-simple targets (e.g. `a.b: int`) evaluate the target
+                # expression. Preserve that behavior.
+                res.append(node)
+        else:
+            res.append(node)
+
+    return res or [ast.copy_location(ast.Pass(), body[0])]
+
+
+def strip_source(source, name, docstrings, annotations):
+    tree = ast.parse(source, name, "exec")
+
+    if annotations and tree.body:
+        tree.body = strip_module_annotations(tree.body)
+
+    for node in ast.walk(tree):
+        if docstrings and isinstance(
+            node, (ast.Module, ast.ClassDef, ast.FunctionDef, ast.AsyncFunctionDef)
+        ):
+            strip_docstring(node)
+
+        if annotations and isinstance(node, (ast.FunctionDef, ast.AsyncFunctionDef)):
+            strip_arguments_annotations(node.args)
+            node.returns = None
+
+    return ast.fix_missing_locations(tree)
+
+
 stdin = sys.__stdin__.buffer
 stdout = sys.__stdout__.buffer
 
@@ -43,6 +109,7 @@ while True:
             source_len = stdin.readline().rstrip()
             optimize_level = stdin.readline().rstrip()
             output_mode = stdin.readline().rstrip()
+            strip = stdin.readline().rstrip()
 
             name_len = int(name_len)
             source_len = int(source_len)
@@ -74,6 +141,22 @@ while True:
             source_bytes = source
             source = source.decode(encoding)
 
+            strip = set(strip.split(b",")) - {b"none"}
+            unknown_strip = strip - {b"docstrings", b"annotations"}
+            if unknown_strip:
+                raise Exception(
+                    "unknown strip mode: %s"
+                    % b",".join(sorted(unknown_strip)).decode("utf-8", "replace")
+                )
+
+            if strip:
+                source = strip_source(
+                    source,
+                    name,
+                    docstrings=b"docstrings" in strip,
+                    annotations=b"annotations" in strip,
+                )
+
             code = compile(source, name, "exec", optimize=optimize_level)
             bytecode = marshal.dumps(code)
 
@@ -24,9 +24,10 @@ use {
     anyhow::{Context, Result},
     simple_file_manifest::{File, FileData, FileEntry, FileManifest},
     std::{
-        collections::HashSet,
+        collections::{HashSet, VecDeque},
         ffi::OsStr,
         path::{Path, PathBuf},
+        sync::{Arc, RwLock},
     },
 };
 
@@ -44,6 +45,45 @@ fn is_executable(_metadata: &std::fs::Metadata) -> bool {
     false
 }
 
+/// Converts files into Python resources during filesystem scanning.
+///
+/// Scanners allow supporting file formats the built-in scanning doesn't know
+/// about, such as proprietary bundles of modules and data files. Scanners are
+/// consulted in order for every file before it is classified. The first
+/// scanner returning `Some` claims the file and the built-in classification
+/// is skipped.
+///
+/// `PythonResource::File` resources returned by a scanner are classified as
+/// if they were found on the filesystem, at their path relative to the
+/// scanned directory. Other resources are emitted as-is. Files returned by
+/// scanners aren't passed to scanners again.
+pub trait ResourceScanner: Send + Sync {
+    /// Name of the scanner, used in error messages.
+    fn name(&self) -> &str;
+
+    /// Scan a file.
+    ///
+    /// `relative_path` is the path of the file relative to the scanned
+    /// directory. Returns `None` if the scanner doesn't handle the file.
+    fn scan_file(
+        &self,
+        relative_path: &Path,
+        data: &FileData,
+    ) -> Result<Option<Vec<PythonResource<'static>>>>;
+}
+
+static RESOURCE_SCANNERS: RwLock<Vec<Arc<dyn ResourceScanner>>> = RwLock::new(Vec::new());
+
+/// Register a [ResourceScanner] used by all future scans in this process.
+pub fn register_resource_scanner(scanner: Arc<dyn ResourceScanner>) {
+    RESOURCE_SCANNERS.write().unwrap().push(scanner);
+}
+
+/// Obtain the registered [ResourceScanner]s.
+pub fn resource_scanners() -> Vec<Arc<dyn ResourceScanner>> {
+    RESOURCE_SCANNERS.read().unwrap().clone()
+}
+
 pub fn walk_tree_files(path: &Path) -> Box<dyn Iterator<Item = walkdir::DirEntry>> {
     let res = walkdir::WalkDir::new(path).sort_by(|a, b| a.file_name().cmp(b.file_name()));
 
@@ -84,6 +124,8 @@ struct PathEntry {
     file_emitted: bool,
     /// Whether we emitted a non-`PythonResource::File` instance.
     non_file_emitted: bool,
+    /// Whether this path was produced by a `ResourceScanner`.
+    from_scanner: bool,
 }
 
 /// An iterator of `PythonResource`.
@@ -102,6 +144,9 @@ pub struct PythonResourceIterator<'a> {
     emit_files: bool,
     // Whether to emit non-`PythonResource::File` entries.
     emit_non_files: bool,
+    scanners: Vec<Arc<dyn ResourceScanner>>,
+    // Resources emitted by scanners which haven't been returned yet.
+    scanned_resources: VecDeque<PythonResource<'a>>,
     _phantom: std::marker::PhantomData<&'a ()>,
 }
 
@@ -129,6 +174,7 @@ impl<'a> PythonResourceIterator<'a> {
                         path: path.to_path_buf(),
                         file_emitted: false,
                         non_file_emitted: false,
+                        from_scanner: false,
                     })
                 })
             })
@@ -147,6 +193,8 @@ impl<'a> PythonResourceIterator<'a> {
             resources: Vec::new(),
             emit_files,
             emit_non_files,
+            scanners: resource_scanners(),
+            scanned_resources: VecDeque::new(),
             _phantom: std::marker::PhantomData,
         })
     }
@@ -165,6 +213,7 @@ impl<'a> PythonResourceIterator<'a> {
                 path: file.path().to_path_buf(),
                 file_emitted: false,
                 non_file_emitted: false,
+                from_scanner: false,
             })
             .collect::<Vec<_>>();
         paths.sort_by(|a, b| a.path.cmp(&b.path));
@@ -184,12 +233,82 @@ impl<'a> PythonResourceIterator<'a> {
             resources: Vec::new(),
             emit_files,
             emit_non_files,
+            scanners: resource_scanners(),
+            scanned_resources: VecDeque::new(),
             _phantom: std::marker::PhantomData,
         })
     }
 
+    /// Use the given [ResourceScanner]s instead of the registered ones.
+    pub fn with_resource_scanners(mut self, scanners: Vec<Arc<dyn ResourceScanner>>) -> Self {
+        self.scanners = scanners;
+        self
+    }
+
+    /// Pass the first path to scanners.
+    ///
+    /// Returns whether a scanner claimed the path.
+    fn scan_path(&mut self) -> Result<bool> {
+        if self.scanners.is_empty() || self.paths[0].from_scanner {
+            return Ok(false);
+        }
+
+        let path = self.paths[0].path.clone();
+        let rel_path = path
+            .strip_prefix(&self.root_path)
+            .expect("unable to strip path prefix");
+        let data = self.resolve_file_data(&path);
+
+        for scanner in self.scanners.clone() {
+            let resources = match scanner
+                .scan_file(rel_path, &data)
+                .with_context(|| format!("scanning {} with {}", path.display(), scanner.name()))?
+            {
+                Some(resources) => resources,
+                None => continue,
+            };
+
+            // Produced files are processed right after the scanned path.
+            let mut index = 1;
+
+            for resource in resources {
+                match resource {
+                    PythonResource::File(file) => {
+                        let full_path = self.root_path.join(file.path());
+
+                        self.path_content_overrides
+                            .add_file_entry(file.path(), file.entry().clone())?;
+                        self.paths.insert(
+                            index,
+                            PathEntry {
+                                path: full_path,
+                                file_emitted: false,
+                                non_file_emitted: false,
+                                from_scanner: true,
+                            },
+                        );
+                        index += 1;
+                    }
+                    resource => self.scanned_resources.push_back(resource),
+                }
+            }
+
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Obtain the overridden content of a path.
+    ///
+    /// Overrides are keyed by the path relative to the root path.
+    fn content_override(&self, path: &Path) -> Option<&FileEntry> {
+        self.path_content_overrides
+            .get(path.strip_prefix(&self.root_path).unwrap_or(path))
+    }
+
     fn resolve_is_executable(&self, path: &Path) -> bool {
-        match self.path_content_overrides.get(path) {
+        match self.content_override(path) {
             Some(file) => file.is_executable(),
             None => {
                 if let Ok(metadata) = path.metadata() {
@@ -202,7 +321,7 @@ impl<'a> PythonResourceIterator<'a> {
     }
 
     fn resolve_file_data(&self, path: &Path) -> FileData {
-        match self.path_content_overrides.get(path) {
+        match self.content_override(path) {
             Some(file) => file.file_data().clone(),
             None => FileData::Path(path.to_path_buf()),
         }
@@ -240,7 +359,7 @@ impl<'a> PythonResourceIterator<'a> {
         };
 
         if let Some((metadata_path, location)) = distribution_info {
-            let data = if let Some(file) = self.path_content_overrides.get(&metadata_path) {
+            let data = if let Some(file) = self.content_override(&metadata_path) {
                 file.resolve_content().ok()?
             } else {
                 std::fs::read(&metadata_path).ok()?
@@ -533,6 +652,10 @@ impl<'a> Iterator for PythonResourceIterator<'a> {
         // We then emit those at the end, perhaps doing some post-processing along the
         // way.
         loop {
+            if let Some(resource) = self.scanned_resources.pop_front() {
+                return Some(Ok(resource));
+            }
+
             if self.paths.is_empty() {
                 break;
             }
@@ -562,6 +685,15 @@ impl<'a> Iterator for PythonResourceIterator<'a> {
             if self.emit_non_files && !self.paths[0].non_file_emitted {
                 self.paths[0].non_file_emitted = true;
 
+                match self.scan_path() {
+                    Ok(true) => {
+                        self.paths.remove(0);
+                        continue;
+                    }
+                    Ok(false) => {}
+                    Err(e) => return Some(Err(e)),
+                }
+
                 // Because resolve_path is a mutable borrow.
                 let path_temp = self.paths[0].path.clone();
 
@@ -669,6 +801,7 @@ impl<'a> Iterator for PythonResourceIterator<'a> {
                 data: self.resolve_file_data(&resource.full_path),
                 is_stdlib: false,
                 is_test: false,
+                locale: None,
             }
             .into()));
         }
@@ -694,6 +827,47 @@ pub fn find_python_resources<'a>(
     PythonResourceIterator::new(root_path, cache_tag, suffixes, emit_files, emit_non_files)
 }
 
+/// Find files in a directory as resources of a Python package.
+///
+/// Every file under `root_path` becomes a [PythonPackageResource] of `package`
+/// named by its path relative to `root_path`, using `/` as the separator.
+///
+/// Unlike [find_python_resources()], files aren't classified: Python source
+/// files are package resources too. This is meant for data files looked up by
+/// name, such as the assets of a web frontend.
+pub fn find_package_resources_in_directory(
+    root_path: &Path,
+    package: &str,
+) -> Result<Vec<PythonPackageResource>> {
+    walk_tree_files(root_path)
+        .map(|entry| {
+            let path = entry.path();
+            let relative_path = path
+                .strip_prefix(root_path)
+                .expect("walked path should be under root");
+
+            let relative_name = relative_path
+                .components()
+                .map(|c| {
+                    c.as_os_str().to_str().with_context(|| {
+                        format!("{} is not a valid UTF-8 path", relative_path.display())
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?
+                .join("/");
+
+            Ok(PythonPackageResource {
+                leaf_package: package.to_string(),
+                relative_name,
+                data: FileData::Path(path.to_path_buf()),
+                is_stdlib: false,
+                is_test: false,
+                locale: None,
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use {
@@ -1493,6 +1667,7 @@ mod tests {
                 data: FileData::Path(resource_path),
                 is_stdlib: false,
                 is_test: false,
+                locale: None,
             }
             .into()
         );
@@ -1542,6 +1717,7 @@ mod tests {
                 data: FileData::Path(resource_path),
                 is_stdlib: false,
                 is_test: false,
+                locale: None,
             }
             .into()
         );
@@ -1884,4 +2060,144 @@ mod tests {
 
         Ok(())
     }
+
+    struct BundleScanner;
+
+    impl ResourceScanner for BundleScanner {
+        fn name(&self) -> &str {
+            "bundle"
+        }
+
+        // Bundles hold `<name>=<content>` lines, one per file.
+        fn scan_file(
+            &self,
+            relative_path: &Path,
+            data: &FileData,
+        ) -> Result<Option<Vec<PythonResource<'static>>>> {
+            if relative_path.extension() != Some(OsStr::new("bundle")) {
+                return Ok(None);
+            }
+
+            let parent = relative_path.parent().unwrap();
+            let content = String::from_utf8(data.resolve_content()?)?;
+
+            Ok(Some(
+                content
+                    .lines()
+                    .map(|line| {
+                        let (name, content) = line.split_once('=').unwrap();
+
+                        File::new(
+                            parent.join(name),
+                            FileEntry::new_from_data(content.as_bytes().to_vec(), false),
+                        )
+                        .into()
+                    })
+                    .collect(),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_resource_scanner() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("python-packaging-test")
+            .tempdir()?;
+        let tp = td.path();
+
+        let acme_path = tp.join("acme");
+        create_dir_all(&acme_path)?;
+        write(acme_path.join("__init__.py"), "")?;
+        write(
+            acme_path.join("assets.bundle"),
+            "foo.py=# acme.foo\nassets.bundle=nested\ndata.txt=data",
+        )?;
+
+        let resources =
+            PythonResourceIterator::new(tp, DEFAULT_CACHE_TAG, &DEFAULT_SUFFIXES, false, true)?
+                .with_resource_scanners(vec![Arc::new(BundleScanner)])
+                .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            resources,
+            vec![
+                PythonModuleSource {
+                    name: "acme".to_string(),
+                    source: FileData::Path(acme_path.join("__init__.py")),
+                    is_package: true,
+                    cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                }
+                .into(),
+                PythonModuleSource {
+                    name: "acme.foo".to_string(),
+                    source: FileData::Memory(b"# acme.foo".to_vec()),
+                    is_package: false,
+                    cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                }
+                .into(),
+                PythonPackageResource {
+                    leaf_package: "acme".to_string(),
+                    relative_name: "assets.bundle".to_string(),
+                    data: FileData::Memory(b"nested".to_vec()),
+                    is_stdlib: false,
+                    is_test: false,
+                    locale: None,
+                }
+                .into(),
+                PythonPackageResource {
+                    leaf_package: "acme".to_string(),
+                    relative_name: "data.txt".to_string(),
+                    data: FileData::Memory(b"data".to_vec()),
+                    is_stdlib: false,
+                    is_test: false,
+                    locale: None,
+                }
+                .into(),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_package_resources_in_directory() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("python-packaging-test")
+            .tempdir()?;
+        let tp = td.path();
+
+        create_dir_all(tp.join("assets").join("img"))?;
+        write(tp.join("index.html"), "")?;
+        write(tp.join("app.py"), "")?;
+        write(tp.join("assets").join("app.js"), "")?;
+        write(tp.join("assets").join("img").join("logo.svg"), "")?;
+
+        let resources = find_package_resources_in_directory(tp, "myapp.ui")?;
+
+        assert_eq!(
+            resources,
+            [
+                "app.py",
+                "assets/app.js",
+                "assets/img/logo.svg",
+                "index.html"
+            ]
+            .iter()
+            .map(|name| PythonPackageResource {
+                leaf_package: "myapp.ui".to_string(),
+                relative_name: name.to_string(),
+                data: FileData::Path(tp.join(name)),
+                is_stdlib: false,
+                is_test: false,
+                locale: None,
+            })
+            .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
 }
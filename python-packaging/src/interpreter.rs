@@ -610,7 +610,7 @@ pub enum MultiprocessingStartMethod {
 
     /// Call with a valid appropriate for the given environment.
     ///
-    /// This likely maps to `spawn` on Windows and `fork` on non-Windows.
+    /// This maps to `spawn` on Windows and macOS and `fork` elsewhere.
     ///
     /// Serialized value: `auto`
     Auto,
@@ -666,6 +666,79 @@ impl TryFrom<String> for MultiprocessingStartMethod {
     }
 }
 
+/// Defines when content digests of packed resources are verified.
+///
+/// Only resources from version 4 packed resources data have content digests.
+///
+/// Serialization type: `string`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialization", serde(try_from = "String", into = "String"))]
+pub enum PackedResourcesVerification {
+    /// Do not verify content digests.
+    ///
+    /// Serialized value: `none`
+    None,
+
+    /// Verify the digest of a resource the first time it is loaded.
+    ///
+    /// Serialized value: `on-load`
+    OnLoad,
+
+    /// Verify the digests of all resources when they are indexed.
+    ///
+    /// Serialized value: `startup`
+    Startup,
+}
+
+impl std::fmt::Display for PackedResourcesVerification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::OnLoad => "on-load",
+            Self::Startup => "startup",
+        })
+    }
+}
+
+impl From<PackedResourcesVerification> for String {
+    fn from(v: PackedResourcesVerification) -> Self {
+        v.to_string()
+    }
+}
+
+impl FromStr for PackedResourcesVerification {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "on-load" => Ok(Self::OnLoad),
+            "startup" => Ok(Self::Startup),
+            _ => Err(format!(
+                "{} is not a valid packed resources verification mode; must be 'none', 'on-load', or 'startup'",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<&str> for PackedResourcesVerification {
+    type Error = String;
+
+    fn try_from(v: &str) -> Result<Self, Self::Error> {
+        Self::from_str(v)
+    }
+}
+
+impl TryFrom<String> for PackedResourcesVerification {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_str())
+    }
+}
+
 /// Holds configuration of a Python interpreter.
 ///
 /// This struct holds fields that are exposed by `PyPreConfig` and
@@ -769,6 +842,13 @@ pub struct PythonInterpreterConfig {
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.check_hash_pycs_mode>.
     pub check_hash_pycs_mode: Option<CheckHashPycsMode>,
 
+    /// Whether to include fine grained source locations in code objects.
+    ///
+    /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.code_debug_ranges>.
+    ///
+    /// Requires Python 3.11+.
+    pub code_debug_ranges: Option<bool>,
+
     /// Controls binary mode and buffering on C standard streams.
     ///
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.configure_c_stdio>.
@@ -829,6 +909,13 @@ pub struct PythonInterpreterConfig {
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.install_signal_handlers>.
     pub install_signal_handlers: Option<bool>,
 
+    /// Limit on the number of digits when converting between `int` and `str`.
+    ///
+    /// See <https://docs.python.org/3/library/stdtypes.html#int-max-str-digits>.
+    ///
+    /// `0` disables the limitation. Other values must be at least `640`.
+    pub int_max_str_digits: Option<i64>,
+
     /// Whether to enable the interactive REPL mode.
     ///
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.interactive>.
@@ -874,6 +961,13 @@ pub struct PythonInterpreterConfig {
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.pathconfig_warnings>.
     pub pathconfig_warnings: Option<bool>,
 
+    /// Whether to enable the Linux `perf` profiler trampoline.
+    ///
+    /// See <https://docs.python.org/3/howto/perf_profiling.html>.
+    ///
+    /// Requires Python 3.12+.
+    pub perf_profiling: Option<bool>,
+
     /// Defines `sys.prefix`.
     ///
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.prefix>.
@@ -918,6 +1012,13 @@ pub struct PythonInterpreterConfig {
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.run_module>.
     pub run_module: Option<String>,
 
+    /// Whether to not prepend a potentially unsafe path to `sys.path`.
+    ///
+    /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.safe_path>.
+    ///
+    /// Requires Python 3.11+.
+    pub safe_path: Option<bool>,
+
     /// Whether to show the total reference count at exit.
     ///
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.show_ref_count>.
@@ -976,3 +1077,685 @@ pub struct PythonInterpreterConfig {
     /// See <https://docs.python.org/3/c-api/init_config.html#c.PyConfig.xoptions>.
     pub x_options: Option<Vec<String>>,
 }
+
+/// Describes requirements the host environment must satisfy to run a binary.
+///
+/// Instances are typically derived at build time from the properties of the
+/// Python distribution being embedded. At run-time, the host environment
+/// can be validated against these requirements so unmet requirements are
+/// reported with an actionable error message instead of a cryptic loader
+/// failure or illegal instruction crash.
+///
+/// All requirements are optional. Requirements that aren't relevant to the
+/// current platform are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialization", serde(default))]
+pub struct HostRequirements {
+    /// Minimum version of glibc required.
+    ///
+    /// e.g. `2.17`. Only checked on Linux targets linking against glibc.
+    ///
+    /// The dynamic loader refuses to start an executable requiring a newer
+    /// glibc before this can be checked. So this is only meaningful for
+    /// shared libraries loaded at run-time, such as extension modules.
+    pub glibc_version: Option<String>,
+
+    /// Minimum version of macOS required.
+    ///
+    /// e.g. `10.9`. Only checked on macOS.
+    pub macos_version: Option<String>,
+
+    /// Minimum Windows build number required.
+    ///
+    /// e.g. `9600` for Windows 8.1. Only checked on Windows.
+    pub windows_build: Option<u32>,
+
+    /// CPU features that must be supported by the host.
+    ///
+    /// Values are feature names as accepted by Rust's
+    /// `is_x86_feature_detected!` and `is_aarch64_feature_detected!` macros.
+    /// e.g. `sse4.2` or `avx2`.
+    pub cpu_features: Vec<String>,
+
+    /// Additional message to print when a requirement isn't met.
+    ///
+    /// This can be used to tell end-users where to obtain a build of the
+    /// application compatible with their machine.
+    pub message: Option<String>,
+}
+
+/// System paths that must remain readable for a macOS process to function.
+const MACOS_SANDBOX_SYSTEM_READ_PATHS: &[&str] = &[
+    "/System",
+    "/usr/lib",
+    "/usr/share",
+    "/private/var/db/dyld",
+    "/private/var/db/timezone",
+    "/dev",
+];
+
+/// Describes the capabilities an application needs from the operating system.
+///
+/// Instances are used to derive operating system sandbox profiles which deny
+/// everything the application doesn't declare a need for. This provides
+/// defense-in-depth for applications processing untrusted input.
+///
+/// Paths may begin with the token `$ORIGIN`, which refers to the directory
+/// containing the application's executable.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialization", serde(default))]
+pub struct SandboxPolicy {
+    /// Whether the application may use IP networking.
+    ///
+    /// Unix domain sockets are always allowed.
+    pub allow_network: bool,
+
+    /// Whether the application may execute other programs.
+    ///
+    /// Note that the `spawn` and `forkserver` start methods of `multiprocessing`
+    /// execute a new process.
+    pub allow_subprocess: bool,
+
+    /// Paths whose content the application may read.
+    ///
+    /// `None` means reads aren't restricted. The directory containing the
+    /// executable and paths required by the operating system are always
+    /// readable.
+    pub read_paths: Option<Vec<String>>,
+
+    /// Paths the application may write to.
+    ///
+    /// `None` means writes aren't restricted. Paths that are writable are
+    /// also readable.
+    pub write_paths: Option<Vec<String>>,
+}
+
+impl SandboxPolicy {
+    /// Obtain a macOS sandbox profile (SBPL) enforcing this policy.
+    ///
+    /// If `origin` is defined, the `$ORIGIN` token in paths is replaced by it.
+    /// Otherwise it is replaced by the `ORIGIN` parameter of the profile, which
+    /// can be defined via `sandbox-exec -D ORIGIN=<path>`.
+    pub fn to_macos_profile(&self, origin: Option<&str>) -> String {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        let path = |p: &str| {
+            if let Some(rest) = p.strip_prefix("$ORIGIN") {
+                if let Some(origin) = origin {
+                    format!("(subpath {})", quote(&format!("{}{}", origin, rest)))
+                } else {
+                    format!(
+                        "(subpath (string-append (param \"ORIGIN\") {}))",
+                        quote(rest)
+                    )
+                }
+            } else {
+                format!("(subpath {})", quote(p))
+            }
+        };
+
+        let mut lines = vec!["(version 1)".to_string(), "(allow default)".to_string()];
+
+        if !self.allow_network {
+            lines.push("(deny network-outbound (remote ip))".to_string());
+            lines.push("(deny network-inbound (local ip))".to_string());
+            lines.push("(deny network-bind (local ip))".to_string());
+        }
+
+        if !self.allow_subprocess {
+            lines.push("(deny process-exec)".to_string());
+        }
+
+        if let Some(read_paths) = &self.read_paths {
+            let allowed = MACOS_SANDBOX_SYSTEM_READ_PATHS
+                .iter()
+                .copied()
+                .chain(std::iter::once("$ORIGIN"))
+                .chain(read_paths.iter().map(|p| p.as_str()))
+                .chain(self.write_paths.iter().flatten().map(|p| p.as_str()))
+                .map(path)
+                .collect::<Vec<_>>();
+
+            lines.push("(deny file-read-data)".to_string());
+            lines.push(format!(
+                "(allow file-read-data\n  {})",
+                allowed.join("\n  ")
+            ));
+        }
+
+        if let Some(write_paths) = &self.write_paths {
+            let allowed = [
+                "(literal \"/dev/null\")".to_string(),
+                "(regex #\"^/dev/tty\")".to_string(),
+                "(regex #\"^/dev/fd/\")".to_string(),
+            ]
+            .into_iter()
+            .chain(write_paths.iter().map(|p| path(p)))
+            .collect::<Vec<_>>();
+
+            lines.push("(deny file-write*)".to_string());
+            lines.push(format!("(allow file-write*\n  {})", allowed.join("\n  ")));
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    /// Obtain an AppArmor profile enforcing this policy for an executable.
+    ///
+    /// The `$ORIGIN` token in paths is replaced by the `@{ORIGIN}` variable,
+    /// which must be set to the directory the executable is installed to.
+    pub fn to_apparmor_profile(&self, exe_name: &str) -> String {
+        let path = |p: &str| {
+            let p = if let Some(rest) = p.strip_prefix("$ORIGIN") {
+                format!("@{{ORIGIN}}{}", rest)
+            } else {
+                p.to_string()
+            };
+
+            if p.contains(char::is_whitespace) {
+                format!("\"{}\"", p)
+            } else {
+                p
+            }
+        };
+        let tree = |p: &str| path(&format!("{}/**", p.trim_end_matches('/')));
+
+        let mut rules = vec!["@{ORIGIN}/ r,".to_string(), "@{ORIGIN}/** mr,".to_string()];
+
+        if let Some(read_paths) = &self.read_paths {
+            for p in read_paths {
+                rules.push(format!("{} r,", path(p)));
+                rules.push(format!("{} r,", tree(p)));
+            }
+        } else {
+            rules.push("/** r,".to_string());
+        }
+
+        if let Some(write_paths) = &self.write_paths {
+            for p in write_paths {
+                rules.push(format!("{} rwk,", path(p)));
+                rules.push(format!("{} rwk,", tree(p)));
+            }
+        } else {
+            rules.push("/** wk,".to_string());
+        }
+
+        rules.push(if self.allow_network {
+            "network,".to_string()
+        } else {
+            "network unix,".to_string()
+        });
+
+        if self.allow_subprocess {
+            rules.push("/** ix,".to_string());
+        }
+
+        format!(
+            "# AppArmor profile for {name}.\n\
+             #\n\
+             # Set @{{ORIGIN}} to the directory {name} is installed to, copy this file\n\
+             # to /etc/apparmor.d/ and load it with `apparmor_parser -r`.\n\
+             \n\
+             #include <tunables/global>\n\
+             \n\
+             @{{ORIGIN}}=/opt/{name}\n\
+             \n\
+             profile {name} @{{ORIGIN}}/{name} {{\n  \
+             #include <abstractions/base>\n\
+             \n  \
+             {rules}\n\
+             }}\n",
+            name = exe_name,
+            rules = rules.join("\n  ")
+        )
+    }
+}
+
+/// Defines where log records of Rust and Python code are written.
+///
+/// Serialization type: `string`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialization", serde(try_from = "String", into = "String"))]
+pub enum LogDestination {
+    /// Forward records to the `log` crate logger installed by the application.
+    ///
+    /// Serialized value: `host`
+    Host,
+
+    /// Write records to standard error.
+    ///
+    /// Serialized value: `stderr`
+    Stderr,
+
+    /// Append records to a file.
+    ///
+    /// Serialized value: `file:<path>`
+    ///
+    /// e.g. `file:$ORIGIN/app.log`.
+    File(String),
+
+    /// Send records to the systemd journal.
+    ///
+    /// Only available on Linux.
+    ///
+    /// Serialized value: `journald`
+    Journald,
+
+    /// Report records to the Windows Event Log.
+    ///
+    /// Records are reported by the named event source or, if no name is
+    /// given, by an event source named after the executable. Only available
+    /// on Windows.
+    ///
+    /// Serialized value: `windows-event-log` or `windows-event-log:<source>`
+    ///
+    /// e.g. `windows-event-log:My Service`.
+    WindowsEventLog(Option<String>),
+
+    /// Write records as string events of an Event Tracing for Windows provider.
+    ///
+    /// The provider is identified by a GUID. Only available on Windows.
+    ///
+    /// Serialized value: `etw:<provider GUID>`
+    ///
+    /// e.g. `etw:9e4a3b8c-7d21-4f6a-b0c5-2e8d1f7a6c34`.
+    Etw(String),
+}
+
+/// Whether a value is a GUID of the form `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`.
+fn is_guid(value: &str) -> bool {
+    value.len() == 36
+        && value.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+impl ToString for LogDestination {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Host => "host".to_string(),
+            Self::Stderr => "stderr".to_string(),
+            Self::File(path) => format!("file:{}", path),
+            Self::Journald => "journald".to_string(),
+            Self::WindowsEventLog(None) => "windows-event-log".to_string(),
+            Self::WindowsEventLog(Some(source)) => format!("windows-event-log:{}", source),
+            Self::Etw(guid) => format!("etw:{}", guid),
+        }
+    }
+}
+
+impl From<LogDestination> for String {
+    fn from(v: LogDestination) -> Self {
+        v.to_string()
+    }
+}
+
+impl TryFrom<&str> for LogDestination {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "host" => Ok(Self::Host),
+            "stderr" => Ok(Self::Stderr),
+            "journald" => Ok(Self::Journald),
+            "windows-event-log" => Ok(Self::WindowsEventLog(None)),
+            _ => {
+                if let Some(path) = value.strip_prefix("file:") {
+                    if !path.is_empty() {
+                        return Ok(Self::File(path.to_string()));
+                    }
+                } else if let Some(source) = value.strip_prefix("windows-event-log:") {
+                    if !source.is_empty() {
+                        return Ok(Self::WindowsEventLog(Some(source.to_string())));
+                    }
+                } else if let Some(guid) = value.strip_prefix("etw:") {
+                    if is_guid(guid) {
+                        return Ok(Self::Etw(guid.to_string()));
+                    }
+                }
+
+                Err(format!("{} is not a valid log destination", value))
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for LogDestination {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_str())
+    }
+}
+
+/// Defines the path reported for the running program.
+///
+/// Used to control `sys.executable`, `sys.argv[0]` and the executable
+/// `multiprocessing` starts processes with.
+///
+/// Serialization type: `string`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialization", serde(try_from = "String", into = "String"))]
+pub enum ProgramPath {
+    /// Keep the value set by Python.
+    ///
+    /// Serialized value: `default`
+    #[default]
+    Default,
+
+    /// The path of the executable, with symlinks resolved.
+    ///
+    /// Serialized value: `exe`
+    Exe,
+
+    /// The path the executable was invoked through.
+    ///
+    /// Unlike [Self::Exe], symlinks to the executable are preserved. The
+    /// path is derived from the first process argument, searching `PATH`
+    /// if it doesn't contain a directory.
+    ///
+    /// Serialized value: `invoked`
+    Invoked,
+
+    /// An empty string.
+    ///
+    /// Serialized value: `empty`
+    Empty,
+
+    /// A fixed value.
+    ///
+    /// Serialized value: `static:<value>`
+    ///
+    /// e.g. `static:$ORIGIN/python3`.
+    Static(String),
+}
+
+impl std::fmt::Display for ProgramPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default => f.write_str("default"),
+            Self::Exe => f.write_str("exe"),
+            Self::Invoked => f.write_str("invoked"),
+            Self::Empty => f.write_str("empty"),
+            Self::Static(value) => write!(f, "static:{}", value),
+        }
+    }
+}
+
+impl From<ProgramPath> for String {
+    fn from(v: ProgramPath) -> Self {
+        v.to_string()
+    }
+}
+
+impl TryFrom<&str> for ProgramPath {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "default" => Ok(Self::Default),
+            "exe" => Ok(Self::Exe),
+            "invoked" => Ok(Self::Invoked),
+            "empty" => Ok(Self::Empty),
+            _ => match value.strip_prefix("static:") {
+                Some(value) => Ok(Self::Static(value.to_string())),
+                None => Err(format!(
+                    "{} is not a valid program path; use 'default', 'exe', 'invoked', 'empty' or 'static:<value>'",
+                    value
+                )),
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for ProgramPath {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_str())
+    }
+}
+
+/// A versioned migration run once per user after an application is upgraded.
+///
+/// Migrations convert state left behind by older versions of an application,
+/// such as files in per-user data directories, to what the current version
+/// expects.
+///
+/// Serialization type: `string`
+///
+/// Serialized value: `<version>=<module>:<function>`. e.g.
+/// `2.0=myapp.migrations:move_cache`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialization", serde(try_from = "String", into = "String"))]
+pub struct Migration {
+    /// Identifier of the migration.
+    ///
+    /// Recorded once the migration succeeded so it never runs again. Must be
+    /// unique among the migrations of an application.
+    pub version: String,
+
+    /// Name of the module defining the function performing the migration.
+    pub module: String,
+
+    /// Name of the function performing the migration.
+    ///
+    /// The function is called without arguments.
+    pub function: String,
+}
+
+impl std::fmt::Display for Migration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}:{}", self.version, self.module, self.function)
+    }
+}
+
+impl From<Migration> for String {
+    fn from(v: Migration) -> Self {
+        v.to_string()
+    }
+}
+
+impl TryFrom<&str> for Migration {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let error = || {
+            format!(
+                "{} is not a valid migration; use '<version>=<module>:<function>'",
+                value
+            )
+        };
+
+        let (version, callable) = value.split_once('=').ok_or_else(error)?;
+        let (module, function) = callable.split_once(':').ok_or_else(error)?;
+
+        // Versions are recorded one per line in the migrations state file.
+        if version.is_empty()
+            || version.contains(char::is_whitespace)
+            || module.is_empty()
+            || function.is_empty()
+        {
+            return Err(error());
+        }
+
+        Ok(Self {
+            version: version.to_string(),
+            module: module.to_string(),
+            function: function.to_string(),
+        })
+    }
+}
+
+impl TryFrom<String> for Migration {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_str())
+    }
+}
+
+/// Describes how a binary embedding Python was built.
+///
+/// Instances are derived at build time and exposed to Python code at run-time
+/// so applications can adapt to how they were packaged.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialization", serde(default))]
+pub struct BuildInfo {
+    /// Rust target triple the binary was built for.
+    pub target_triple: String,
+
+    /// Version of the embedded Python distribution.
+    ///
+    /// e.g. `3.10.9`.
+    pub python_version: String,
+
+    /// Flavor of the embedded Python distribution.
+    ///
+    /// e.g. `standalone-static` or `standalone-dynamic`.
+    pub python_flavor: String,
+
+    /// Where resources are installed by default.
+    ///
+    /// e.g. `in-memory` or `filesystem-relative:lib`.
+    pub resources_location: String,
+
+    /// Where resources are installed if they can't be installed in the default location.
+    pub resources_location_fallback: Option<String>,
+
+    /// Cargo features the binary was built with.
+    pub features: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sandbox_policy_macos_profile() {
+        let policy = SandboxPolicy::default();
+        assert_eq!(
+            policy.to_macos_profile(None),
+            "(version 1)\n\
+             (allow default)\n\
+             (deny network-outbound (remote ip))\n\
+             (deny network-inbound (local ip))\n\
+             (deny network-bind (local ip))\n\
+             (deny process-exec)\n"
+        );
+
+        let policy = SandboxPolicy {
+            allow_network: true,
+            allow_subprocess: true,
+            read_paths: Some(vec!["/etc/app".to_string()]),
+            write_paths: Some(vec!["$ORIGIN/data".to_string()]),
+        };
+        let profile = policy.to_macos_profile(None);
+        assert!(profile.contains("(deny file-read-data)"));
+        assert!(profile.contains("  (subpath \"/etc/app\")"));
+        assert!(profile.contains("  (subpath (string-append (param \"ORIGIN\") \"/data\"))"));
+        assert!(profile.contains("(deny file-write*)"));
+        assert!(!profile.contains("network"));
+        assert!(!profile.contains("process-exec"));
+
+        let profile = policy.to_macos_profile(Some("/Applications/app"));
+        assert!(profile.contains("  (subpath \"/Applications/app\")"));
+        assert!(profile.contains("  (subpath \"/Applications/app/data\")"));
+        assert!(!profile.contains("param"));
+    }
+
+    #[test]
+    fn test_sandbox_policy_apparmor_profile() {
+        let profile = SandboxPolicy::default().to_apparmor_profile("myapp");
+        assert!(profile.contains("@{ORIGIN}=/opt/myapp\n"));
+        assert!(profile.contains("profile myapp @{ORIGIN}/myapp {\n"));
+        assert!(profile.contains("\n  /** r,\n  /** wk,\n  network unix,\n}\n"));
+
+        let policy = SandboxPolicy {
+            allow_network: true,
+            allow_subprocess: true,
+            read_paths: Some(vec!["/etc/my app".to_string()]),
+            write_paths: Some(vec!["$ORIGIN/data/".to_string()]),
+        };
+        let profile = policy.to_apparmor_profile("myapp");
+        assert!(profile.contains("\n  \"/etc/my app\" r,\n  \"/etc/my app/**\" r,\n"));
+        assert!(profile.contains("\n  @{ORIGIN}/data/ rwk,\n  @{ORIGIN}/data/** rwk,\n"));
+        assert!(profile.contains("\n  network,\n  /** ix,\n}\n"));
+    }
+
+    #[test]
+    fn test_log_destination() {
+        for value in [
+            "host",
+            "stderr",
+            "journald",
+            "windows-event-log",
+            "windows-event-log:My Service",
+            "etw:9e4a3b8c-7d21-4f6a-b0c5-2e8d1f7a6c34",
+            "file:/var/log/app.log",
+        ] {
+            assert_eq!(LogDestination::try_from(value).unwrap().to_string(), value);
+        }
+
+        assert_eq!(
+            LogDestination::try_from("file:$ORIGIN/app.log"),
+            Ok(LogDestination::File("$ORIGIN/app.log".to_string()))
+        );
+        assert!(LogDestination::try_from("file:").is_err());
+        assert!(LogDestination::try_from("windows-event-log:").is_err());
+        assert!(LogDestination::try_from("etw:").is_err());
+        assert!(LogDestination::try_from("etw:9e4a3b8c7d214f6ab0c52e8d1f7a6c34").is_err());
+        assert!(LogDestination::try_from("etw:9e4a3b8c-7d21-4f6a-b0c5-2e8d1f7a6c3z").is_err());
+        assert!(LogDestination::try_from("syslog").is_err());
+    }
+
+    #[test]
+    fn test_program_path() {
+        for value in [
+            "default",
+            "exe",
+            "invoked",
+            "empty",
+            "static:/usr/bin/python3",
+        ] {
+            assert_eq!(ProgramPath::try_from(value).unwrap().to_string(), value);
+        }
+
+        assert_eq!(
+            ProgramPath::try_from("static:"),
+            Ok(ProgramPath::Static(String::new()))
+        );
+        assert!(ProgramPath::try_from("real").is_err());
+    }
+
+    #[test]
+    fn test_migration() {
+        let migration = Migration::try_from("2.0=myapp.migrations:move_cache").unwrap();
+        assert_eq!(
+            migration,
+            Migration {
+                version: "2.0".to_string(),
+                module: "myapp.migrations".to_string(),
+                function: "move_cache".to_string(),
+            }
+        );
+        assert_eq!(migration.to_string(), "2.0=myapp.migrations:move_cache");
+
+        for value in [
+            "myapp.migrations:move_cache",
+            "2.0=myapp.migrations",
+            "=myapp.migrations:move_cache",
+            "2.0 beta=myapp.migrations:move_cache",
+            "2.0=:move_cache",
+            "2.0=myapp.migrations:",
+        ] {
+            assert!(Migration::try_from(value).is_err(), "{}", value);
+        }
+    }
+}
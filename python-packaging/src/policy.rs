@@ -12,13 +12,17 @@ Functionality for defining how Python resources should be packaged.
 
 use {
     crate::{
+        bytecode::BytecodeStripping,
         licensing::{LicenseFlavor, SAFE_SYSTEM_LIBRARIES},
         location::ConcreteResourceLocation,
-        resource::{PythonExtensionModule, PythonExtensionModuleVariants, PythonResource},
+        resource::{
+            PythonExtensionModule, PythonExtensionModuleVariants, PythonResource,
+            ResourceCompression,
+        },
         resource_collection::PythonResourceAddCollectionContext,
     },
-    anyhow::Result,
-    std::collections::{HashMap, HashSet},
+    anyhow::{anyhow, Result},
+    std::collections::{BTreeSet, HashMap, HashSet},
 };
 
 /// Denotes methods to filter extension modules.
@@ -92,6 +96,512 @@ impl AsRef<str> for ResourceHandlingMode {
     }
 }
 
+/// Describes whether extension modules should be statically relinked.
+///
+/// Binary wheels typically ship extension modules as shared libraries. Some
+/// also ship a static archive of the extension. Static relinking takes
+/// the object files from that archive and links them into the produced
+/// binary as a built-in extension module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StaticRebuildMode {
+    /// Never statically relink extension modules.
+    Never,
+    /// Statically relink extension modules having a static archive.
+    IfAvailable,
+    /// Statically relink all extension modules and error if that isn't possible.
+    Required,
+}
+
+impl TryFrom<&str> for StaticRebuildMode {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "never" => Ok(Self::Never),
+            "if-available" => Ok(Self::IfAvailable),
+            "required" => Ok(Self::Required),
+            _ => Err(format!(
+                "{} is not a valid static rebuild mode; use \"never\", \"if-available\", or \"required\"",
+                value
+            )),
+        }
+    }
+}
+
+impl AsRef<str> for StaticRebuildMode {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Never => "never",
+            Self::IfAvailable => "if-available",
+            Self::Required => "required",
+        }
+    }
+}
+
+/// Describes how Python type information files should be packaged.
+///
+/// Type information consists of `.pyi` stub files and `py.typed` marker
+/// files (see PEP 561). These files are consumed by type checkers and
+/// are never used by the Python interpreter at run-time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeInformationMode {
+    /// Type information is packaged like any other package resource.
+    Include,
+    /// Type information is not packaged.
+    Exclude,
+    /// Type information is only installed in a directory relative to the binary.
+    ///
+    /// This preserves the package layout on the filesystem so tools like
+    /// type checkers can be pointed at the installed files without
+    /// type information being embedded in memory.
+    FilesystemRelative(String),
+}
+
+impl TryFrom<&str> for TypeInformationMode {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "include" => Ok(Self::Include),
+            "exclude" => Ok(Self::Exclude),
+            _ => match ConcreteResourceLocation::try_from(value) {
+                Ok(ConcreteResourceLocation::RelativePath(prefix)) => {
+                    Ok(Self::FilesystemRelative(prefix))
+                }
+                _ => Err(format!(
+                    "{} is not a valid type information mode; use \"include\", \"exclude\", or \"filesystem-relative:<prefix>\"",
+                    value
+                )),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for TypeInformationMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Include => f.write_str("include"),
+            Self::Exclude => f.write_str("exclude"),
+            Self::FilesystemRelative(prefix) => write!(f, "filesystem-relative:{}", prefix),
+        }
+    }
+}
+
+impl TypeInformationMode {
+    /// Whether a resource is a type information file.
+    pub fn is_type_information(resource: &PythonResource) -> bool {
+        match resource {
+            PythonResource::PackageResource(r) => {
+                let filename = r.relative_name.rsplit('/').next().unwrap_or_default();
+
+                filename == "py.typed" || filename.ends_with(".pyi")
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Python frameworks whose package data can be discovered automatically.
+///
+/// Frameworks like these locate their data files (templates, static assets,
+/// migrations, etc) relative to the filesystem path of the package defining
+/// them. So these files need to be materialized on the filesystem using the
+/// same relative layout as the original package.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FrameworkDataHook {
+    /// Django app `templates`, `static`, `locale`, and `fixtures` directories.
+    Django,
+    /// Flask application and blueprint `templates` and `static` directories.
+    Flask,
+    /// Jinja templates.
+    Jinja,
+    /// Alembic migration environments and their version scripts.
+    Alembic,
+}
+
+impl TryFrom<&str> for FrameworkDataHook {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "django" => Ok(Self::Django),
+            "flask" => Ok(Self::Flask),
+            "jinja" => Ok(Self::Jinja),
+            "alembic" => Ok(Self::Alembic),
+            _ => Err(format!(
+                "{} is not a valid framework data hook; use \"django\", \"flask\", \"jinja\", or \"alembic\"",
+                value
+            )),
+        }
+    }
+}
+
+impl AsRef<str> for FrameworkDataHook {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Django => "django",
+            Self::Flask => "flask",
+            Self::Jinja => "jinja",
+            Self::Alembic => "alembic",
+        }
+    }
+}
+
+impl FrameworkDataHook {
+    /// All known hooks.
+    pub fn all() -> [Self; 4] {
+        [Self::Django, Self::Flask, Self::Jinja, Self::Alembic]
+    }
+
+    /// Whether a resource is data belonging to this framework.
+    pub fn matches_resource(&self, resource: &PythonResource) -> bool {
+        match resource {
+            PythonResource::PackageResource(r) => {
+                let mut components = r.relative_name.split('/');
+                let first = components.next().unwrap_or_default();
+                let filename = r.relative_name.rsplit('/').next().unwrap_or_default();
+                let is_nested = r.relative_name.contains('/');
+
+                match self {
+                    Self::Django => {
+                        is_nested && matches!(first, "templates" | "static" | "locale" | "fixtures")
+                    }
+                    Self::Flask => is_nested && matches!(first, "templates" | "static"),
+                    Self::Jinja => {
+                        (is_nested && first == "templates")
+                            || filename.ends_with(".j2")
+                            || filename.ends_with(".jinja")
+                            || filename.ends_with(".jinja2")
+                    }
+                    Self::Alembic => matches!(filename, "script.py.mako" | "alembic.ini"),
+                }
+            }
+            // Alembic loads its environment script and version scripts from
+            // the filesystem by path rather than importing them.
+            PythonResource::ModuleSource(m) if *self == Self::Alembic => {
+                let parts = m.name.split('.').collect::<Vec<_>>();
+
+                parts.windows(2).any(|w| {
+                    matches!(w[0], "alembic" | "migrations") && matches!(w[1], "env" | "versions")
+                }) && parts.len() > 2
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Named subsets of the Python standard library.
+///
+/// Each profile excludes a curated set of standard library packages and
+/// extension modules that are rarely needed by applications of a given kind.
+/// Exclusions only apply to resources from the Python distribution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StdlibProfile {
+    /// The entire standard library.
+    Full,
+    /// Everything except GUI toolkits, IDLE, and the test suite.
+    NoGui,
+    /// Excludes everything `no-gui` does plus packaging tools and modules
+    /// for serving or storing data.
+    MinimalCli,
+    /// Excludes everything `no-gui` does plus packaging tools and terminal
+    /// user interface modules.
+    NetworkService,
+}
+
+/// Standard library packages excluded by the `no-gui` profile.
+const STDLIB_EXCLUDES_NO_GUI: &[&str] = &[
+    "_tkinter",
+    "idlelib",
+    "test",
+    "tkinter",
+    "turtle",
+    "turtledemo",
+];
+
+/// Standard library packages for building and installing packages.
+const STDLIB_EXCLUDES_PACKAGING: &[&str] = &["distutils", "ensurepip", "lib2to3", "venv"];
+
+/// Standard library packages excluded by the `minimal-cli` profile in
+/// addition to `no-gui` and packaging exclusions.
+const STDLIB_EXCLUDES_MINIMAL_CLI: &[&str] = &[
+    "_msi",
+    "_sqlite3",
+    "dbm",
+    "msilib",
+    "pydoc_data",
+    "shelve",
+    "sqlite3",
+    "wsgiref",
+    "xmlrpc",
+];
+
+/// Standard library packages excluded by the `network-service` profile in
+/// addition to `no-gui` and packaging exclusions.
+const STDLIB_EXCLUDES_NETWORK_SERVICE: &[&str] = &[
+    "_curses",
+    "_curses_panel",
+    "_msi",
+    "curses",
+    "msilib",
+    "pydoc_data",
+];
+
+impl TryFrom<&str> for StdlibProfile {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "full" => Ok(Self::Full),
+            "no-gui" => Ok(Self::NoGui),
+            "minimal-cli" => Ok(Self::MinimalCli),
+            "network-service" => Ok(Self::NetworkService),
+            _ => Err(format!(
+                "{} is not a valid stdlib profile; use \"full\", \"no-gui\", \"minimal-cli\", or \"network-service\"",
+                value
+            )),
+        }
+    }
+}
+
+impl AsRef<str> for StdlibProfile {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Full => "full",
+            Self::NoGui => "no-gui",
+            Self::MinimalCli => "minimal-cli",
+            Self::NetworkService => "network-service",
+        }
+    }
+}
+
+impl StdlibProfile {
+    /// Names of top-level packages and modules excluded by this profile.
+    pub fn excluded_packages(&self) -> Vec<&'static str> {
+        let mut res = match self {
+            Self::Full => vec![],
+            Self::NoGui => STDLIB_EXCLUDES_NO_GUI.to_vec(),
+            Self::MinimalCli => [
+                STDLIB_EXCLUDES_NO_GUI,
+                STDLIB_EXCLUDES_PACKAGING,
+                STDLIB_EXCLUDES_MINIMAL_CLI,
+            ]
+            .concat(),
+            Self::NetworkService => [
+                STDLIB_EXCLUDES_NO_GUI,
+                STDLIB_EXCLUDES_PACKAGING,
+                STDLIB_EXCLUDES_NETWORK_SERVICE,
+            ]
+            .concat(),
+        };
+
+        res.sort_unstable();
+
+        res
+    }
+
+    /// Whether a module or package name is excluded by this profile.
+    pub fn excludes(&self, name: &str) -> bool {
+        let top_level = name.split('.').next().unwrap_or_default();
+
+        self.excluded_packages().contains(&top_level)
+    }
+
+    /// Whether a resource is excluded by this profile.
+    ///
+    /// Only resources belonging to the standard library are excluded.
+    pub fn excludes_resource(&self, resource: &PythonResource) -> bool {
+        if *self == Self::Full {
+            return false;
+        }
+
+        match resource {
+            PythonResource::ModuleSource(m) => m.is_stdlib && self.excludes(&m.name),
+            PythonResource::ModuleBytecodeRequest(m) => m.is_stdlib && self.excludes(&m.name),
+            PythonResource::ModuleBytecode(m) => m.is_stdlib && self.excludes(&m.name),
+            PythonResource::PackageResource(r) => r.is_stdlib && self.excludes(&r.leaf_package),
+            PythonResource::ExtensionModule(em) => em.is_stdlib && self.excludes(&em.name),
+            _ => false,
+        }
+    }
+}
+
+/// Extension modules linking a crypto library.
+pub const CRYPTO_EXTENSION_MODULES: &[&str] = &["_hashlib", "_ssl"];
+
+/// Names of OpenSSL libraries extension modules link against.
+///
+/// Windows distributions use the `lib` prefixed names.
+pub const OPENSSL_LIBRARIES: &[&str] = &["crypto", "libcrypto", "libssl", "ssl"];
+
+/// Describes how the `_ssl` and `_hashlib` extension modules obtain a crypto library.
+///
+/// Python distributions statically link OpenSSL into these extension
+/// modules. FIPS and distribution policies may instead require the crypto
+/// library provided by the operating system or an alternative build.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CryptoLinkage {
+    /// Use the OpenSSL libraries provided by the Python distribution.
+    Distribution,
+    /// Dynamically link the OpenSSL libraries of the target system.
+    ///
+    /// Only supported for glibc based Linux targets.
+    System,
+    /// Use a named variant of the extension modules provided by the Python distribution.
+    ///
+    /// This allows choosing builds against other crypto libraries, such as
+    /// LibreSSL or BoringSSL, if the distribution provides them.
+    Variant(String),
+}
+
+impl TryFrom<&str> for CryptoLinkage {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "distribution" => Ok(Self::Distribution),
+            "system" => Ok(Self::System),
+            _ => match value.strip_prefix("variant:") {
+                Some(variant) if !variant.is_empty() => Ok(Self::Variant(variant.to_string())),
+                _ => Err(format!(
+                    "{} is not a valid crypto linkage; use \"distribution\", \"system\", or \"variant:<name>\"",
+                    value
+                )),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for CryptoLinkage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Distribution => f.write_str("distribution"),
+            Self::System => f.write_str("system"),
+            Self::Variant(variant) => write!(f, "variant:{}", variant),
+        }
+    }
+}
+
+impl CryptoLinkage {
+    /// Ensure this linkage can be used for a target triple.
+    pub fn validate_target(&self, target_triple: &str) -> Result<()> {
+        let glibc_linux = target_triple.contains("-linux-") && !target_triple.contains("musl");
+
+        if *self == Self::System && !glibc_linux {
+            Err(anyhow!(
+                "system crypto linkage is only supported on glibc based Linux targets, not {}",
+                target_triple
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A pattern matched against fully qualified resource names.
+///
+/// Patterns prefixed with `re:` are regular expressions. All other patterns
+/// are globs, where `*` matches any sequence of characters (including `.`
+/// and `/`), `?` matches a single character, and `[...]` matches a character
+/// class. Either form must match the entire resource name.
+#[derive(Clone, Debug)]
+pub struct ResourceNamePattern {
+    pattern: String,
+    regex: regex::Regex,
+}
+
+impl PartialEq for ResourceNamePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for ResourceNamePattern {}
+
+impl TryFrom<&str> for ResourceNamePattern {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let expression = if let Some(expression) = value.strip_prefix("re:") {
+            expression.to_string()
+        } else {
+            glob_to_regex(value)?
+        };
+
+        let regex = regex::Regex::new(&format!("^(?:{})$", expression))
+            .map_err(|e| format!("{} is not a valid resource name pattern: {}", value, e))?;
+
+        Ok(Self {
+            pattern: value.to_string(),
+            regex,
+        })
+    }
+}
+
+impl std::fmt::Display for ResourceNamePattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+impl ResourceNamePattern {
+    /// Whether a fully qualified resource name matches this pattern.
+    pub fn matches(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+}
+
+/// Convert a glob pattern to an equivalent regular expression.
+fn glob_to_regex(pattern: &str) -> Result<String, String> {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut res = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' => res.push_str(".*"),
+            '?' => res.push('.'),
+            '[' => {
+                res.push('[');
+                i += 1;
+
+                if chars.get(i) == Some(&'!') {
+                    res.push('^');
+                    i += 1;
+                }
+
+                let start = i;
+                loop {
+                    match chars.get(i) {
+                        None => {
+                            return Err(format!(
+                                "{} is not a valid resource name pattern: unterminated character class",
+                                pattern
+                            ));
+                        }
+                        // A leading `]` is a literal member of the class.
+                        Some(']') if i > start => break,
+                        Some(c @ ('\\' | '[' | ']' | '^' | '&' | '~')) => {
+                            res.push('\\');
+                            res.push(*c);
+                        }
+                        Some(c) => res.push(*c),
+                    }
+
+                    i += 1;
+                }
+
+                res.push(']');
+            }
+            c => res.push_str(&regex::escape(&c.to_string())),
+        }
+
+        i += 1;
+    }
+
+    Ok(res)
+}
+
 /// Defines how Python resources should be packaged.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PythonPackagingPolicy {
@@ -178,6 +688,51 @@ pub struct PythonPackagingPolicy {
 
     /// Python modules for which bytecode should not be generated by default.
     no_bytecode_modules: HashSet<String>,
+
+    /// Framework data hooks that are active.
+    ///
+    /// Included resources of `framework_data_packages` matched by an active
+    /// hook are placed in `framework_data_location`.
+    framework_data_hooks: BTreeSet<FrameworkDataHook>,
+
+    /// Packages framework data hooks apply to.
+    framework_data_packages: BTreeSet<String>,
+
+    /// Location for resources matched by framework data hooks.
+    framework_data_location: ConcreteResourceLocation,
+
+    /// Whether to statically relink non-distribution extension modules.
+    static_rebuild: StaticRebuildMode,
+
+    /// Compression to apply to in-memory Python module bytecode.
+    bytecode_compression: ResourceCompression,
+
+    /// Compression to apply to in-memory Python package resources.
+    package_resources_compression: ResourceCompression,
+
+    /// How to package type information files.
+    type_information: TypeInformationMode,
+
+    /// Per-package overrides of `type_information`.
+    package_type_information: HashMap<String, TypeInformationMode>,
+
+    /// Content to strip from Python source when compiling bytecode.
+    bytecode_stripping: BytecodeStripping,
+
+    /// Per-package overrides of `bytecode_stripping`.
+    package_bytecode_stripping: HashMap<String, BytecodeStripping>,
+
+    /// Subset of the standard library to include.
+    stdlib_profile: StdlibProfile,
+
+    /// Patterns of resource names to include. Empty includes everything.
+    include_patterns: Vec<ResourceNamePattern>,
+
+    /// Patterns of resource names to exclude.
+    exclude_patterns: Vec<ResourceNamePattern>,
+
+    /// How crypto extension modules obtain their crypto library.
+    crypto_linkage: CryptoLinkage,
 }
 
 impl Default for PythonPackagingPolicy {
@@ -202,6 +757,20 @@ impl Default for PythonPackagingPolicy {
             bytecode_optimize_level_one: false,
             bytecode_optimize_level_two: false,
             no_bytecode_modules: HashSet::new(),
+            framework_data_hooks: BTreeSet::new(),
+            framework_data_packages: BTreeSet::new(),
+            framework_data_location: ConcreteResourceLocation::RelativePath("lib".to_string()),
+            static_rebuild: StaticRebuildMode::Never,
+            bytecode_compression: ResourceCompression::None,
+            package_resources_compression: ResourceCompression::None,
+            type_information: TypeInformationMode::Include,
+            package_type_information: HashMap::new(),
+            bytecode_stripping: BytecodeStripping::default(),
+            package_bytecode_stripping: HashMap::new(),
+            stdlib_profile: StdlibProfile::Full,
+            include_patterns: vec![],
+            exclude_patterns: vec![],
+            crypto_linkage: CryptoLinkage::Distribution,
         }
     }
 }
@@ -383,6 +952,236 @@ impl PythonPackagingPolicy {
         self.bytecode_optimize_level_two = value;
     }
 
+    /// Obtain the active framework data hooks.
+    pub fn framework_data_hooks(&self) -> &BTreeSet<FrameworkDataHook> {
+        &self.framework_data_hooks
+    }
+
+    /// Set the active framework data hooks.
+    pub fn set_framework_data_hooks(&mut self, hooks: impl IntoIterator<Item = FrameworkDataHook>) {
+        self.framework_data_hooks = hooks.into_iter().collect();
+    }
+
+    /// Obtain the packages framework data hooks apply to.
+    pub fn framework_data_packages(&self) -> &BTreeSet<String> {
+        &self.framework_data_packages
+    }
+
+    /// Set the packages framework data hooks apply to.
+    ///
+    /// Hooks apply to these packages and their sub-packages.
+    pub fn set_framework_data_packages(
+        &mut self,
+        packages: impl IntoIterator<Item = impl ToString>,
+    ) {
+        self.framework_data_packages = packages.into_iter().map(|p| p.to_string()).collect();
+    }
+
+    /// Whether framework data hooks apply to a resource.
+    fn is_framework_data(&self, resource: &PythonResource) -> bool {
+        let package = match resource {
+            PythonResource::PackageResource(r) => &r.leaf_package,
+            PythonResource::ModuleSource(m) => &m.name,
+            _ => return false,
+        };
+
+        let in_packages = self.framework_data_packages.iter().any(|p| {
+            matches!(package.strip_prefix(p.as_str()), Some(rest) if rest.is_empty() || rest.starts_with('.'))
+        });
+
+        in_packages
+            && self
+                .framework_data_hooks
+                .iter()
+                .any(|hook| hook.matches_resource(resource))
+    }
+
+    /// Obtain the location for resources matched by framework data hooks.
+    pub fn framework_data_location(&self) -> &ConcreteResourceLocation {
+        &self.framework_data_location
+    }
+
+    /// Set the location for resources matched by framework data hooks.
+    pub fn set_framework_data_location(&mut self, location: ConcreteResourceLocation) {
+        self.framework_data_location = location;
+    }
+
+    /// Obtain the static relinking mode for extension modules.
+    pub fn static_rebuild(&self) -> StaticRebuildMode {
+        self.static_rebuild
+    }
+
+    /// Set the static relinking mode for extension modules.
+    pub fn set_static_rebuild(&mut self, mode: StaticRebuildMode) {
+        self.static_rebuild = mode;
+    }
+
+    /// Obtain the compression applied to in-memory Python module bytecode.
+    pub fn bytecode_compression(&self) -> ResourceCompression {
+        self.bytecode_compression
+    }
+
+    /// Set the compression applied to in-memory Python module bytecode.
+    pub fn set_bytecode_compression(&mut self, compression: ResourceCompression) {
+        self.bytecode_compression = compression;
+    }
+
+    /// Obtain the compression applied to in-memory Python package resources.
+    pub fn package_resources_compression(&self) -> ResourceCompression {
+        self.package_resources_compression
+    }
+
+    /// Set the compression applied to in-memory Python package resources.
+    pub fn set_package_resources_compression(&mut self, compression: ResourceCompression) {
+        self.package_resources_compression = compression;
+    }
+
+    /// Obtain how type information files are packaged.
+    pub fn type_information(&self) -> &TypeInformationMode {
+        &self.type_information
+    }
+
+    /// Set how type information files are packaged.
+    pub fn set_type_information(&mut self, mode: TypeInformationMode) {
+        self.type_information = mode;
+    }
+
+    /// Obtain per-package overrides of how type information files are packaged.
+    pub fn package_type_information(&self) -> &HashMap<String, TypeInformationMode> {
+        &self.package_type_information
+    }
+
+    /// Set how type information files are packaged for a specific package.
+    ///
+    /// The setting applies to the package and all its sub-packages, unless a
+    /// sub-package has its own setting.
+    pub fn set_package_type_information(&mut self, package: &str, mode: TypeInformationMode) {
+        self.package_type_information
+            .insert(package.to_string(), mode);
+    }
+
+    /// Resolve how type information files in a package are packaged.
+    pub fn resolve_type_information(&self, package: &str) -> &TypeInformationMode {
+        let mut candidate = package;
+
+        loop {
+            if let Some(mode) = self.package_type_information.get(candidate) {
+                return mode;
+            }
+
+            match candidate.rsplit_once('.') {
+                Some((parent, _)) => candidate = parent,
+                None => return &self.type_information,
+            }
+        }
+    }
+
+    /// Obtain what is stripped from Python source when compiling bytecode.
+    pub fn bytecode_stripping(&self) -> BytecodeStripping {
+        self.bytecode_stripping
+    }
+
+    /// Set what is stripped from Python source when compiling bytecode.
+    pub fn set_bytecode_stripping(&mut self, value: BytecodeStripping) {
+        self.bytecode_stripping = value;
+    }
+
+    /// Obtain per-package overrides of what is stripped when compiling bytecode.
+    pub fn package_bytecode_stripping(&self) -> &HashMap<String, BytecodeStripping> {
+        &self.package_bytecode_stripping
+    }
+
+    /// Set what is stripped when compiling bytecode for a specific package.
+    ///
+    /// The setting applies to the package and all its sub-packages and modules,
+    /// unless one of them has its own setting.
+    pub fn set_package_bytecode_stripping(&mut self, package: &str, value: BytecodeStripping) {
+        self.package_bytecode_stripping
+            .insert(package.to_string(), value);
+    }
+
+    /// Resolve what is stripped when compiling bytecode for a named module.
+    pub fn resolve_bytecode_stripping(&self, module: &str) -> BytecodeStripping {
+        let mut candidate = module;
+
+        loop {
+            if let Some(value) = self.package_bytecode_stripping.get(candidate) {
+                return *value;
+            }
+
+            match candidate.rsplit_once('.') {
+                Some((parent, _)) => candidate = parent,
+                None => return self.bytecode_stripping,
+            }
+        }
+    }
+
+    /// Obtain the subset of the standard library to include.
+    pub fn stdlib_profile(&self) -> StdlibProfile {
+        self.stdlib_profile
+    }
+
+    /// Set the subset of the standard library to include.
+    pub fn set_stdlib_profile(&mut self, profile: StdlibProfile) {
+        self.stdlib_profile = profile;
+    }
+
+    /// Obtain how crypto extension modules obtain their crypto library.
+    pub fn crypto_linkage(&self) -> &CryptoLinkage {
+        &self.crypto_linkage
+    }
+
+    /// Set how crypto extension modules obtain their crypto library.
+    pub fn set_crypto_linkage(&mut self, linkage: CryptoLinkage) {
+        self.crypto_linkage = linkage;
+    }
+
+    /// Obtain patterns of resource names to include.
+    pub fn include_patterns(&self) -> &[ResourceNamePattern] {
+        &self.include_patterns
+    }
+
+    /// Set patterns of resource names to include.
+    ///
+    /// If non-empty, resources whose name doesn't match any pattern are
+    /// excluded.
+    pub fn set_include_patterns(
+        &mut self,
+        patterns: impl IntoIterator<Item = ResourceNamePattern>,
+    ) {
+        self.include_patterns = patterns.into_iter().collect();
+    }
+
+    /// Obtain patterns of resource names to exclude.
+    pub fn exclude_patterns(&self) -> &[ResourceNamePattern] {
+        &self.exclude_patterns
+    }
+
+    /// Set patterns of resource names to exclude.
+    ///
+    /// Exclusion takes precedence over `include_patterns`.
+    pub fn set_exclude_patterns(
+        &mut self,
+        patterns: impl IntoIterator<Item = ResourceNamePattern>,
+    ) {
+        self.exclude_patterns = patterns.into_iter().collect();
+    }
+
+    /// Whether a resource name passes the include and exclude patterns.
+    ///
+    /// Resources without a name (e.g. path extensions) always pass.
+    pub fn filter_resource_name(&self, name: &str) -> bool {
+        if name.is_empty() {
+            return true;
+        }
+
+        if self.exclude_patterns.iter().any(|p| p.matches(name)) {
+            return false;
+        }
+
+        self.include_patterns.is_empty() || self.include_patterns.iter().any(|p| p.matches(name))
+    }
+
     /// Set the resource handling mode of the policy.
     ///
     /// This is a convenience function for mapping a `ResourceHandlingMode`
@@ -490,7 +1289,23 @@ impl PythonPackagingPolicy {
             _ => self.bytecode_optimize_level_two,
         };
 
-        PythonResourceAddCollectionContext {
+        let compression = match resource {
+            PythonResource::ModuleSource(_)
+            | PythonResource::ModuleBytecodeRequest(_)
+            | PythonResource::ModuleBytecode(_) => self.bytecode_compression,
+            PythonResource::PackageResource(_) => self.package_resources_compression,
+            _ => ResourceCompression::None,
+        };
+
+        let bytecode_stripping = match resource {
+            PythonResource::ModuleSource(module) => self.resolve_bytecode_stripping(&module.name),
+            PythonResource::ModuleBytecodeRequest(module) => {
+                self.resolve_bytecode_stripping(&module.name)
+            }
+            _ => BytecodeStripping::default(),
+        };
+
+        let mut context = PythonResourceAddCollectionContext {
             include,
             location,
             location_fallback,
@@ -498,7 +1313,37 @@ impl PythonPackagingPolicy {
             optimize_level_zero,
             optimize_level_one,
             optimize_level_two,
+            compression,
+            bytecode_stripping,
+        };
+
+        // Hooks only relocate resources the policy already includes. So they
+        // never override exclusions by other settings.
+        if context.include && self.is_framework_data(resource) {
+            context.location = self.framework_data_location.clone();
+            context.location_fallback = None;
+
+            if matches!(resource, PythonResource::ModuleSource(_)) {
+                context.store_source = true;
+            }
         }
+
+        if let PythonResource::PackageResource(r) = resource {
+            if TypeInformationMode::is_type_information(resource) {
+                match self.resolve_type_information(&r.leaf_package) {
+                    TypeInformationMode::Include => {}
+                    TypeInformationMode::Exclude => {
+                        context.include = false;
+                    }
+                    TypeInformationMode::FilesystemRelative(prefix) => {
+                        context.location = ConcreteResourceLocation::RelativePath(prefix.clone());
+                        context.location_fallback = None;
+                    }
+                }
+            }
+        }
+
+        context
     }
 
     /// Determine if a Python resource is applicable to the current policy.
@@ -508,6 +1353,14 @@ impl PythonPackagingPolicy {
     ///
     /// Returns true if the resource should be included, false otherwise.
     fn filter_python_resource(&self, resource: &PythonResource) -> bool {
+        if self.stdlib_profile.excludes_resource(resource) {
+            return false;
+        }
+
+        if !self.filter_resource_name(&resource.full_name()) {
+            return false;
+        }
+
         match resource {
             PythonResource::File(_) => {
                 if !self.include_file_resources {
@@ -559,6 +1412,13 @@ impl PythonPackagingPolicy {
     ) -> Result<Vec<PythonExtensionModule>> {
         let mut res = vec![];
 
+        let mut preferred_variants = self.preferred_extension_module_variants.clone();
+        if let CryptoLinkage::Variant(variant) = &self.crypto_linkage {
+            for name in CRYPTO_EXTENSION_MODULES {
+                preferred_variants.insert(name.to_string(), variant.clone());
+            }
+        }
+
         for variants in extensions_variants {
             let name = &variants.default_variant().name;
 
@@ -572,6 +1432,14 @@ impl PythonPackagingPolicy {
                 continue;
             }
 
+            // This extension is excluded by the standard library profile.
+            if self
+                .stdlib_profile
+                .excludes_resource(&PythonResource::from(variants.default_variant()))
+            {
+                continue;
+            }
+
             // Always add minimally required extension modules, because things don't
             // work if we don't do this.
             let ext_variants: PythonExtensionModuleVariants = variants
@@ -586,11 +1454,32 @@ impl PythonPackagingPolicy {
                 .collect();
 
             if !ext_variants.is_empty() {
-                res.push(
-                    ext_variants
-                        .choose_variant(&self.preferred_extension_module_variants)
-                        .clone(),
-                );
+                res.push(ext_variants.choose_variant(&preferred_variants).clone());
+            }
+
+            // Patterns can't exclude minimally required extensions added above.
+            if !self.filter_resource_name(name) {
+                continue;
+            }
+
+            // Requested crypto library builds must exist.
+            if let CryptoLinkage::Variant(variant) = &self.crypto_linkage {
+                if CRYPTO_EXTENSION_MODULES.contains(&name.as_str())
+                    && !variants
+                        .iter()
+                        .any(|em| em.variant.as_deref() == Some(variant.as_str()))
+                {
+                    return Err(anyhow!(
+                        "Python distribution does not provide a {} variant of extension module {}; available variants: {}",
+                        variant,
+                        name,
+                        variants
+                            .iter()
+                            .filter_map(|em| em.variant.clone())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+                }
             }
 
             match self.extension_module_filter {
@@ -598,11 +1487,7 @@ impl PythonPackagingPolicy {
                 ExtensionModuleFilter::Minimal => {}
 
                 ExtensionModuleFilter::All => {
-                    res.push(
-                        variants
-                            .choose_variant(&self.preferred_extension_module_variants)
-                            .clone(),
-                    );
+                    res.push(variants.choose_variant(&preferred_variants).clone());
                 }
 
                 ExtensionModuleFilter::NoLibraries => {
@@ -618,11 +1503,7 @@ impl PythonPackagingPolicy {
                         .collect();
 
                     if !ext_variants.is_empty() {
-                        res.push(
-                            ext_variants
-                                .choose_variant(&self.preferred_extension_module_variants)
-                                .clone(),
-                        );
+                        res.push(ext_variants.choose_variant(&preferred_variants).clone());
                     }
                 }
 
@@ -667,11 +1548,7 @@ impl PythonPackagingPolicy {
                         .collect();
 
                     if !ext_variants.is_empty() {
-                        res.push(
-                            ext_variants
-                                .choose_variant(&self.preferred_extension_module_variants)
-                                .clone(),
-                        );
+                        res.push(ext_variants.choose_variant(&preferred_variants).clone());
                     }
                 }
             }
@@ -683,7 +1560,11 @@ impl PythonPackagingPolicy {
 
 #[cfg(test)]
 mod tests {
-    use {super::*, simple_file_manifest::File};
+    use {
+        super::*,
+        crate::resource::{PythonModuleSource, PythonPackageResource},
+        simple_file_manifest::File,
+    };
 
     #[test]
     fn test_add_collection_context_file() -> Result<()> {
@@ -703,4 +1584,507 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_framework_data_hooks() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_resources_location_fallback(None);
+        assert!(policy.framework_data_hooks().is_empty());
+
+        let template = PythonPackageResource {
+            leaf_package: "myapp".to_string(),
+            relative_name: "templates/myapp/index.html".to_string(),
+            data: vec![42].into(),
+            is_stdlib: false,
+            is_test: false,
+            locale: None,
+        };
+        let data = PythonPackageResource {
+            relative_name: "data.json".to_string(),
+            ..template.clone()
+        };
+        let alembic_version = PythonModuleSource {
+            name: "myapp.migrations.versions.abc123_initial".to_string(),
+            source: vec![42].into(),
+            is_package: false,
+            cache_tag: "cpython-39".to_string(),
+            is_stdlib: false,
+            is_test: false,
+        };
+
+        let lib = ConcreteResourceLocation::RelativePath("lib".to_string());
+
+        // Hooks are disabled by default.
+        let add_context = policy.derive_add_collection_context(&template.clone().into());
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        // Hooks only apply to named packages.
+        policy.set_framework_data_hooks(FrameworkDataHook::all());
+        let add_context = policy.derive_add_collection_context(&template.clone().into());
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        policy.set_framework_data_packages(["myapp"]);
+        let add_context = policy.derive_add_collection_context(&template.clone().into());
+        assert!(add_context.include);
+        assert_eq!(add_context.location, lib);
+
+        let other = PythonPackageResource {
+            leaf_package: "myapplication".to_string(),
+            ..template.clone()
+        };
+        let add_context = policy.derive_add_collection_context(&other.into());
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        // Resources excluded by other settings stay excluded.
+        let test_template = PythonPackageResource {
+            leaf_package: "myapp.tests".to_string(),
+            is_test: true,
+            is_stdlib: true,
+            ..template.clone()
+        };
+        let add_context = policy.derive_add_collection_context(&test_template.into());
+        assert!(!add_context.include);
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        let add_context = policy.derive_add_collection_context(&data.into());
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        let add_context = policy.derive_add_collection_context(&alembic_version.clone().into());
+        assert_eq!(add_context.location, lib);
+        assert!(add_context.store_source);
+
+        policy.set_framework_data_hooks([FrameworkDataHook::Alembic]);
+        let add_context = policy.derive_add_collection_context(&template.into());
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+        let add_context = policy.derive_add_collection_context(&alembic_version.into());
+        assert_eq!(add_context.location, lib);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compression() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_bytecode_compression(ResourceCompression::Zstd(3));
+        policy.set_package_resources_compression(ResourceCompression::Zlib(9));
+
+        let module = PythonModuleSource {
+            name: "foo".to_string(),
+            source: vec![42].into(),
+            is_package: false,
+            cache_tag: "cpython-39".to_string(),
+            is_stdlib: false,
+            is_test: false,
+        };
+        let resource = PythonPackageResource {
+            leaf_package: "foo".to_string(),
+            relative_name: "data.txt".to_string(),
+            data: vec![42].into(),
+            is_stdlib: false,
+            is_test: false,
+            locale: None,
+        };
+
+        let add_context = policy.derive_add_collection_context(&module.into());
+        assert_eq!(add_context.compression, ResourceCompression::Zstd(3));
+        let add_context = policy.derive_add_collection_context(&resource.into());
+        assert_eq!(add_context.compression, ResourceCompression::Zlib(9));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_type_information() -> Result<()> {
+        assert_eq!(
+            TypeInformationMode::try_from("filesystem-relative:lib"),
+            Ok(TypeInformationMode::FilesystemRelative("lib".to_string()))
+        );
+        assert!(TypeInformationMode::try_from("in-memory").is_err());
+
+        let resource = |package: &str, name: &str| -> PythonResource {
+            PythonPackageResource {
+                leaf_package: package.to_string(),
+                relative_name: name.to_string(),
+                data: vec![42].into(),
+                is_stdlib: false,
+                is_test: false,
+                locale: None,
+            }
+            .into()
+        };
+
+        let mut policy = PythonPackagingPolicy::default();
+
+        let add_context = policy.derive_add_collection_context(&resource("foo", "__init__.pyi"));
+        assert!(add_context.include);
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        policy.set_type_information(TypeInformationMode::Exclude);
+        policy.set_package_type_information(
+            "foo",
+            TypeInformationMode::FilesystemRelative("typed".to_string()),
+        );
+        policy.set_package_type_information("foo.bar", TypeInformationMode::Include);
+
+        let add_context = policy.derive_add_collection_context(&resource("other", "py.typed"));
+        assert!(!add_context.include);
+        let add_context = policy.derive_add_collection_context(&resource("other", "data.txt"));
+        assert!(add_context.include);
+
+        let add_context = policy.derive_add_collection_context(&resource("foo.baz", "sub/x.pyi"));
+        assert!(add_context.include);
+        assert_eq!(
+            add_context.location,
+            ConcreteResourceLocation::RelativePath("typed".to_string())
+        );
+
+        let add_context = policy.derive_add_collection_context(&resource("foo.bar", "py.typed"));
+        assert!(add_context.include);
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytecode_stripping() -> Result<()> {
+        let module = |name: &str| -> PythonResource {
+            PythonModuleSource {
+                name: name.to_string(),
+                source: vec![42].into(),
+                is_package: false,
+                cache_tag: "cpython-39".to_string(),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into()
+        };
+
+        let docstrings = BytecodeStripping {
+            docstrings: true,
+            annotations: false,
+        };
+        let all = BytecodeStripping {
+            docstrings: true,
+            annotations: true,
+        };
+
+        let mut policy = PythonPackagingPolicy::default();
+        assert!(policy
+            .derive_add_collection_context(&module("numpy"))
+            .bytecode_stripping
+            .is_none());
+
+        policy.set_bytecode_stripping(docstrings);
+        policy.set_package_bytecode_stripping("numpy", all);
+        policy.set_package_bytecode_stripping("numpy.typing", BytecodeStripping::default());
+
+        assert_eq!(
+            policy
+                .derive_add_collection_context(&module("foo"))
+                .bytecode_stripping,
+            docstrings
+        );
+        assert_eq!(
+            policy
+                .derive_add_collection_context(&module("numpy.linalg.linalg"))
+                .bytecode_stripping,
+            all
+        );
+        assert!(policy
+            .derive_add_collection_context(&module("numpy.typing.mypy_plugin"))
+            .bytecode_stripping
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_crypto_linkage() -> Result<()> {
+        assert_eq!(CryptoLinkage::try_from("system"), Ok(CryptoLinkage::System));
+        assert_eq!(
+            CryptoLinkage::try_from("variant:libressl"),
+            Ok(CryptoLinkage::Variant("libressl".to_string()))
+        );
+        assert_eq!(
+            CryptoLinkage::Variant("libressl".to_string()).to_string(),
+            "variant:libressl"
+        );
+        assert!(CryptoLinkage::try_from("variant:").is_err());
+        assert!(CryptoLinkage::try_from("boringssl").is_err());
+
+        assert!(CryptoLinkage::System
+            .validate_target("x86_64-unknown-linux-gnu")
+            .is_ok());
+        assert!(CryptoLinkage::System
+            .validate_target("x86_64-unknown-linux-musl")
+            .is_err());
+        assert!(CryptoLinkage::System
+            .validate_target("x86_64-pc-windows-msvc")
+            .is_err());
+        assert!(CryptoLinkage::Distribution
+            .validate_target("x86_64-pc-windows-msvc")
+            .is_ok());
+
+        let extension = |name: &str, variant: &str| PythonExtensionModule {
+            name: name.to_string(),
+            init_fn: Some(format!("PyInit_{}", name)),
+            extension_file_suffix: "".to_string(),
+            shared_library: None,
+            object_file_data: vec![],
+            is_package: false,
+            link_libraries: vec![],
+            is_stdlib: true,
+            builtin_default: false,
+            required: false,
+            variant: Some(variant.to_string()),
+            license: None,
+        };
+
+        let extensions = [
+            [extension("_ssl", "openssl"), extension("_ssl", "libressl")]
+                .into_iter()
+                .collect::<PythonExtensionModuleVariants>(),
+            [extension("_hashlib", "openssl")]
+                .into_iter()
+                .collect::<PythonExtensionModuleVariants>(),
+            [extension("zlib", "default")]
+                .into_iter()
+                .collect::<PythonExtensionModuleVariants>(),
+        ];
+
+        let resolve = |policy: &PythonPackagingPolicy| -> Result<Vec<(String, String)>> {
+            Ok(policy
+                .resolve_python_extension_modules(extensions.iter(), "x86_64-unknown-linux-gnu")?
+                .into_iter()
+                .map(|em| (em.name, em.variant.unwrap_or_default()))
+                .collect())
+        };
+
+        let mut policy = PythonPackagingPolicy::default();
+        assert_eq!(
+            resolve(&policy)?,
+            vec![
+                ("_ssl".to_string(), "openssl".to_string()),
+                ("_hashlib".to_string(), "openssl".to_string()),
+                ("zlib".to_string(), "default".to_string()),
+            ]
+        );
+
+        // The distribution has no LibreSSL build of _hashlib.
+        policy.set_crypto_linkage(CryptoLinkage::Variant("libressl".to_string()));
+        assert!(resolve(&policy).is_err());
+
+        policy.set_exclude_patterns([ResourceNamePattern::try_from("_hashlib").unwrap()]);
+        assert_eq!(
+            resolve(&policy)?,
+            vec![
+                ("_ssl".to_string(), "libressl".to_string()),
+                ("zlib".to_string(), "default".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stdlib_profile() -> Result<()> {
+        assert_eq!(StdlibProfile::try_from("no-gui"), Ok(StdlibProfile::NoGui));
+        assert!(StdlibProfile::try_from("tiny").is_err());
+
+        let module = |name: &str, is_stdlib: bool| -> PythonResource {
+            PythonModuleSource {
+                name: name.to_string(),
+                source: vec![42].into(),
+                is_package: false,
+                cache_tag: "cpython-39".to_string(),
+                is_stdlib,
+                is_test: false,
+            }
+            .into()
+        };
+        let resource = |package: &str| -> PythonResource {
+            PythonPackageResource {
+                leaf_package: package.to_string(),
+                relative_name: "data.bin".to_string(),
+                data: vec![42].into(),
+                is_stdlib: true,
+                is_test: false,
+                locale: None,
+            }
+            .into()
+        };
+
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_include_distribution_resources(true);
+        assert_eq!(policy.stdlib_profile(), StdlibProfile::Full);
+        assert!(
+            policy
+                .derive_add_collection_context(&module("tkinter.ttk", true))
+                .include
+        );
+
+        policy.set_stdlib_profile(StdlibProfile::NoGui);
+        assert!(
+            !policy
+                .derive_add_collection_context(&module("tkinter.ttk", true))
+                .include
+        );
+        assert!(
+            !policy
+                .derive_add_collection_context(&module("turtle", true))
+                .include
+        );
+        assert!(
+            policy
+                .derive_add_collection_context(&module("turtles", true))
+                .include
+        );
+        assert!(
+            policy
+                .derive_add_collection_context(&module("tkinter", false))
+                .include
+        );
+        assert!(
+            policy
+                .derive_add_collection_context(&module("sqlite3", true))
+                .include
+        );
+
+        policy.set_stdlib_profile(StdlibProfile::MinimalCli);
+        assert!(
+            !policy
+                .derive_add_collection_context(&module("sqlite3.dbapi2", true))
+                .include
+        );
+        assert!(
+            !policy
+                .derive_add_collection_context(&resource("ensurepip._bundled"))
+                .include
+        );
+        assert!(
+            policy
+                .derive_add_collection_context(&module("curses", true))
+                .include
+        );
+
+        policy.set_stdlib_profile(StdlibProfile::NetworkService);
+        assert!(
+            policy
+                .derive_add_collection_context(&module("sqlite3", true))
+                .include
+        );
+        assert!(
+            !policy
+                .derive_add_collection_context(&module("curses.ascii", true))
+                .include
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resource_name_patterns() -> Result<()> {
+        let pattern = |value: &str| ResourceNamePattern::try_from(value).unwrap();
+
+        assert!(pattern("*.tests.*").matches("foo.tests.test_bar"));
+        assert!(!pattern("*.tests.*").matches("foo.tests"));
+        assert!(pattern("*/__pycache__/*").matches("foo/__pycache__/bar.pyc"));
+        // Package resource names join the package and path with a `.`.
+        assert!(!pattern("*/__pycache__/*").matches("foo.__pycache__/bar.pyc"));
+        assert!(pattern("*__pycache__/*").matches("foo.__pycache__/bar.pyc"));
+        assert!(pattern("*__pycache__/*").matches("foo.data/__pycache__/bar.pyc"));
+        assert!(pattern("*__pycache__/*").matches("foo/__pycache__/bar.pyc"));
+        assert!(pattern("foo.?ar").matches("foo.bar"));
+        assert!(!pattern("foo.bar").matches("fooxbar"));
+        assert!(pattern("foo.[!b]ar").matches("foo.car"));
+        assert!(!pattern("foo.[!b]ar").matches("foo.bar"));
+        assert!(pattern("re:foo\\.(bar|baz)").matches("foo.baz"));
+        assert!(!pattern("re:foo").matches("foo.bar"));
+        assert!(ResourceNamePattern::try_from("foo[").is_err());
+        assert!(ResourceNamePattern::try_from("re:foo(").is_err());
+
+        let module = |name: &str| -> PythonResource {
+            PythonModuleSource {
+                name: name.to_string(),
+                source: vec![42].into(),
+                is_package: false,
+                cache_tag: "cpython-39".to_string(),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into()
+        };
+
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_exclude_patterns([pattern("*.tests.*")]);
+        assert!(
+            policy
+                .derive_add_collection_context(&module("foo.bar"))
+                .include
+        );
+        assert!(
+            !policy
+                .derive_add_collection_context(&module("foo.tests.test_bar"))
+                .include
+        );
+
+        policy.set_include_patterns([pattern("foo"), pattern("foo.*")]);
+        assert!(policy.derive_add_collection_context(&module("foo")).include);
+        assert!(!policy.derive_add_collection_context(&module("bar")).include);
+        assert!(
+            !policy
+                .derive_add_collection_context(&module("foo.tests.test_bar"))
+                .include
+        );
+
+        let file = File::new("foo/__pycache__/bar.pyc", vec![42]);
+        policy.set_include_file_resources(true);
+        policy.set_include_patterns([]);
+        policy.set_exclude_patterns([pattern("*__pycache__/*")]);
+        assert!(!policy.derive_add_collection_context(&file.into()).include);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_patterns_framework_data_hooks() -> Result<()> {
+        let resource = |relative_name: &str| -> PythonResource {
+            PythonPackageResource {
+                leaf_package: "myapp".to_string(),
+                relative_name: relative_name.to_string(),
+                data: vec![42].into(),
+                is_stdlib: false,
+                is_test: false,
+                locale: None,
+            }
+            .into()
+        };
+
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_resources_location_fallback(None);
+        policy.set_framework_data_hooks(FrameworkDataHook::all());
+        policy.set_framework_data_packages(["myapp"]);
+        policy.set_exclude_patterns([
+            ResourceNamePattern::try_from("*__pycache__/*").unwrap(),
+            ResourceNamePattern::try_from("myapp.templates/myapp/drafts/*").unwrap(),
+        ]);
+
+        let lib = ConcreteResourceLocation::RelativePath("lib".to_string());
+
+        let add_context =
+            policy.derive_add_collection_context(&resource("templates/myapp/index.html"));
+        assert!(add_context.include);
+        assert_eq!(add_context.location, lib);
+
+        for name in [
+            "templates/myapp/drafts/index.html",
+            "templates/__pycache__/index.cpython-39.pyc",
+        ] {
+            let add_context = policy.derive_add_collection_context(&resource(name));
+            assert!(!add_context.include, "{} is excluded", name);
+            assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+        }
+
+        Ok(())
+    }
 }
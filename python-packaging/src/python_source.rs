@@ -8,7 +8,7 @@
 
 /*! Utility functions related to Python source code. */
 
-use {anyhow::Result, once_cell::sync::Lazy};
+use {anyhow::Result, once_cell::sync::Lazy, std::collections::BTreeSet};
 
 static RE_CODING: Lazy<regex::bytes::Regex> = Lazy::new(|| {
     regex::bytes::Regex::new(r"^[ \t\f]*#.*?coding[:=][ \t]*([-_.a-zA-Z0-9]+)").unwrap()
@@ -34,11 +34,8 @@ pub fn python_source_encoding(source: &[u8]) -> Vec<u8> {
     b"utf-8".to_vec()
 }
 
-/// Whether __file__ occurs in Python source code.
-pub fn has_dunder_file(source: &[u8]) -> Result<bool> {
-    // We can't just look for b"__file__ because the source file may be in
-    // encodings like UTF-16. So we need to decode to Unicode first then look for
-    // the code points.
+/// Decode Python source code to a string using its declared encoding.
+fn decode_source(source: &[u8]) -> std::borrow::Cow<'_, str> {
     let encoding = python_source_encoding(source);
 
     let encoder = match encoding_rs::Encoding::for_label(&encoding) {
@@ -48,5 +45,734 @@ pub fn has_dunder_file(source: &[u8]) -> Result<bool> {
 
     let (source, ..) = encoder.decode(source);
 
-    Ok(source.contains("__file__"))
+    source
+}
+
+/// Whether __file__ occurs in Python source code.
+pub fn has_dunder_file(source: &[u8]) -> Result<bool> {
+    // We can't just look for b"__file__ because the source file may be in
+    // encodings like UTF-16. So we need to decode to Unicode first then look for
+    // the code points.
+    Ok(decode_source(source).contains("__file__"))
+}
+
+/// A lexical token in Python source code.
+///
+/// Only the tokens needed to recognize import statements are distinguished.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    /// An identifier or keyword.
+    Name(String),
+    /// A string literal. Holds its value if it is a plain literal without escapes.
+    Str(Option<String>),
+    /// Any other character. Numbers are represented as `0`.
+    Op(char),
+    /// The end of a logical line.
+    Newline,
+}
+
+/// Read a string literal whose opening quote is at `start`.
+///
+/// `formatted` denotes an f-string. String literals in its replacement fields
+/// may use the same quotes as the f-string itself (PEP 701), so they are
+/// skipped over.
+///
+/// Returns the literal's content and the index after its closing quote.
+fn read_string(chars: &[char], start: usize, formatted: bool) -> (String, usize) {
+    let quote = chars[start];
+    let triple = chars.get(start + 1) == Some(&quote) && chars.get(start + 2) == Some(&quote);
+
+    let mut i = if triple { start + 3 } else { start + 1 };
+    let content_start = i;
+    // Nesting level of replacement fields in f-strings.
+    let mut depth = 0usize;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '\\' {
+            i += 2;
+        } else if !triple && c == '\n' {
+            // Unterminated literal.
+            break;
+        } else if formatted && c == '{' {
+            if depth == 0 && chars.get(i + 1) == Some(&'{') {
+                i += 2;
+            } else {
+                depth += 1;
+                i += 1;
+            }
+        } else if formatted && depth > 0 && c == '}' {
+            depth -= 1;
+            i += 1;
+        } else if depth > 0 && (c == '\'' || c == '"') {
+            let (_, end) = read_string(chars, i, false);
+            i = end;
+        } else if c == quote
+            && (!triple || (chars.get(i + 1) == Some(&quote) && chars.get(i + 2) == Some(&quote)))
+        {
+            let content = chars[content_start..i].iter().collect();
+            return (content, if triple { i + 3 } else { i + 1 });
+        } else {
+            i += 1;
+        }
+    }
+
+    let end = i.min(chars.len());
+
+    (chars[content_start..end].iter().collect(), end)
+}
+
+/// Split Python source code into tokens.
+fn tokenize(source: &str) -> Vec<Token> {
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    // Newlines within brackets don't end logical lines.
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        match c {
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '\\' => {
+                // Explicit line joining.
+                i += 1;
+                if chars.get(i) == Some(&'\r') {
+                    i += 1;
+                }
+                if chars.get(i) == Some(&'\n') {
+                    i += 1;
+                }
+            }
+            '\n' => {
+                if depth == 0 {
+                    tokens.push(Token::Newline);
+                }
+                i += 1;
+            }
+            '(' | '[' | '{' => {
+                depth += 1;
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+            ')' | ']' | '}' => {
+                depth = depth.saturating_sub(1);
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+            '\'' | '"' => {
+                let (content, end) = read_string(&chars, i, false);
+                let value = if content.contains('\\') {
+                    None
+                } else {
+                    Some(content)
+                };
+                tokens.push(Token::Str(value));
+                i = end;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let name = chars[start..i].iter().collect::<String>();
+
+                let is_string_prefix = matches!(
+                    name.to_lowercase().as_str(),
+                    "r" | "u" | "b" | "f" | "br" | "rb" | "fr" | "rf"
+                );
+
+                if is_string_prefix && matches!(chars.get(i), Some('\'') | Some('"')) {
+                    let lower = name.to_lowercase();
+                    let (content, end) = read_string(&chars, i, lower.contains('f'));
+                    let value =
+                        if lower.contains('b') || lower.contains('f') || content.contains('\\') {
+                            None
+                        } else {
+                            Some(content)
+                        };
+                    tokens.push(Token::Str(value));
+                    i = end;
+                } else {
+                    tokens.push(Token::Name(name));
+                }
+            }
+            c if c.is_ascii_digit() => {
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.'))
+                {
+                    i += 1;
+                }
+                tokens.push(Token::Op('0'));
+            }
+            c if c.is_whitespace() => {
+                i += 1;
+            }
+            _ => {
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Read a dotted name starting at `i`, advancing `i` past it.
+fn read_dotted_name(tokens: &[Token], i: &mut usize) -> Option<String> {
+    let mut parts = vec![];
+
+    while let Some(Token::Name(name)) = tokens.get(*i) {
+        // `from . import x` has no module name.
+        if name == "import" {
+            break;
+        }
+
+        parts.push(name.clone());
+        *i += 1;
+
+        if tokens.get(*i) == Some(&Token::Op('.'))
+            && matches!(tokens.get(*i + 1), Some(Token::Name(_)))
+        {
+            *i += 1;
+        } else {
+            break;
+        }
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("."))
+    }
+}
+
+/// Skip an `as <name>` clause at `i`, if present.
+fn skip_alias(tokens: &[Token], i: &mut usize) {
+    if tokens.get(*i) == Some(&Token::Name("as".to_string())) {
+        *i += 2;
+    }
+}
+
+/// Resolve the package a relative import at `level` is relative to.
+fn resolve_relative_package(module: &str, is_package: bool, level: usize) -> Option<String> {
+    let mut parts = module.split('.').collect::<Vec<_>>();
+
+    if !is_package {
+        parts.pop();
+    }
+
+    for _ in 1..level {
+        parts.pop()?;
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("."))
+    }
+}
+
+/// A call to a function importing a module by name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DynamicImport {
+    /// The imported module could be resolved from string literals.
+    Module(String),
+    /// The imported module is computed at run-time.
+    ///
+    /// Holds the name of the called function.
+    Computed(String),
+}
+
+/// Resolve the package argument of an `importlib.import_module()` call.
+fn resolve_package_argument(token: &Token, module: &str, is_package: bool) -> Option<String> {
+    match token {
+        Token::Name(name) if name == "__name__" => Some(module.to_string()),
+        Token::Name(name) if name == "__package__" => {
+            if is_package {
+                Some(module.to_string())
+            } else {
+                resolve_relative_package(module, false, 1)
+            }
+        }
+        Token::Str(Some(value)) if !value.is_empty() => Some(value.clone()),
+        _ => None,
+    }
+}
+
+/// Read a call to `import_module()` or `__import__()` at `i`.
+///
+/// Function definitions and references to these functions that aren't calls
+/// are ignored.
+fn read_dynamic_import(
+    tokens: &[Token],
+    i: usize,
+    module: &str,
+    is_package: bool,
+) -> Option<DynamicImport> {
+    let function = match &tokens[i] {
+        Token::Name(name) if name == "import_module" || name == "__import__" => name,
+        _ => return None,
+    };
+
+    if tokens.get(i + 1) != Some(&Token::Op('('))
+        || (i > 0 && tokens[i - 1] == Token::Name("def".to_string()))
+    {
+        return None;
+    }
+
+    let computed = || Some(DynamicImport::Computed(function.clone()));
+
+    // Arguments must be a lone literal, not e.g. an expression starting with one.
+    let value = match (tokens.get(i + 2), tokens.get(i + 3)) {
+        (Some(Token::Str(Some(value))), Some(Token::Op(')')) | Some(Token::Op(','))) => value,
+        _ => return computed(),
+    };
+
+    let relative = value.trim_start_matches('.');
+    let level = value.len() - relative.len();
+
+    if level == 0 {
+        return if value.is_empty() {
+            computed()
+        } else {
+            Some(DynamicImport::Module(value.clone()))
+        };
+    }
+
+    // Only `import_module()` resolves relative names, using its `package` argument.
+    if function != "import_module" || tokens.get(i + 3) != Some(&Token::Op(',')) {
+        return computed();
+    }
+
+    let mut package_index = i + 4;
+    if tokens.get(package_index) == Some(&Token::Name("package".to_string()))
+        && tokens.get(package_index + 1) == Some(&Token::Op('='))
+    {
+        package_index += 2;
+    }
+
+    let package = match tokens
+        .get(package_index)
+        .and_then(|token| resolve_package_argument(token, module, is_package))
+    {
+        Some(package) => package,
+        None => return computed(),
+    };
+
+    let mut parts = package.split('.').collect::<Vec<_>>();
+    if level > parts.len() {
+        return computed();
+    }
+    parts.truncate(parts.len() + 1 - level);
+
+    if !relative.is_empty() {
+        parts.push(relative);
+    }
+
+    Some(DynamicImport::Module(parts.join(".")))
+}
+
+/// Find calls to functions importing modules by name in Python source code.
+///
+/// `module` and `is_package` describe the module the source belongs to and
+/// are used to resolve relative names passed to `importlib.import_module()`.
+///
+/// Calls to `importlib.import_module()` and `__import__()` are recognized.
+/// A call whose module name is a string literal yields the name of the
+/// imported module. Calls with any other argument are reported as computed.
+pub fn find_dynamic_imports(source: &[u8], module: &str, is_package: bool) -> Vec<DynamicImport> {
+    let tokens = tokenize(&decode_source(source));
+
+    (0..tokens.len())
+        .filter_map(|i| read_dynamic_import(&tokens, i, module, is_package))
+        .collect()
+}
+
+/// Find the names of modules imported by Python source code.
+///
+/// `module` and `is_package` describe the module the source belongs to and
+/// are used to resolve relative imports.
+///
+/// This is a static analysis of `import` and `from ... import` statements,
+/// wherever they occur, plus calls to `importlib.import_module()` and
+/// `__import__()` with a string literal argument. Since `from a import b`
+/// may import either the module `a.b` or the attribute `b` of `a`, both `a`
+/// and `a.b` are emitted. Imports performed with computed names can't be
+/// detected.
+pub fn find_imports(source: &[u8], module: &str, is_package: bool) -> BTreeSet<String> {
+    let tokens = tokenize(&decode_source(source));
+    let mut res = BTreeSet::new();
+
+    let mut statement_start = true;
+    let mut i = 0;
+
+    while i < tokens.len() {
+        match &tokens[i] {
+            Token::Name(name) if statement_start && name == "import" => {
+                i += 1;
+
+                loop {
+                    if let Some(name) = read_dotted_name(&tokens, &mut i) {
+                        res.insert(name);
+                    }
+                    skip_alias(&tokens, &mut i);
+
+                    if tokens.get(i) == Some(&Token::Op(',')) {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+
+                statement_start = false;
+                continue;
+            }
+            Token::Name(name) if statement_start && name == "from" => {
+                i += 1;
+
+                let mut level = 0;
+                while tokens.get(i) == Some(&Token::Op('.')) {
+                    level += 1;
+                    i += 1;
+                }
+
+                let name = read_dotted_name(&tokens, &mut i);
+
+                if tokens.get(i) != Some(&Token::Name("import".to_string())) {
+                    statement_start = false;
+                    continue;
+                }
+                i += 1;
+
+                let base = if level > 0 {
+                    match (resolve_relative_package(module, is_package, level), name) {
+                        (Some(package), Some(name)) => Some(format!("{}.{}", package, name)),
+                        (Some(package), None) => Some(package),
+                        (None, _) => None,
+                    }
+                } else {
+                    name
+                };
+
+                if tokens.get(i) == Some(&Token::Op('(')) {
+                    i += 1;
+                }
+
+                while let Some(token) = tokens.get(i) {
+                    match token {
+                        Token::Name(attr) => {
+                            if let Some(base) = &base {
+                                res.insert(format!("{}.{}", base, attr));
+                            }
+                            i += 1;
+                            skip_alias(&tokens, &mut i);
+                        }
+                        Token::Op(',') | Token::Op('*') => {
+                            i += 1;
+                        }
+                        Token::Op(')') => {
+                            i += 1;
+                            break;
+                        }
+                        _ => break,
+                    }
+                }
+
+                if let Some(base) = base {
+                    res.insert(base);
+                }
+
+                statement_start = false;
+                continue;
+            }
+            Token::Name(_) => {
+                if let Some(DynamicImport::Module(name)) =
+                    read_dynamic_import(&tokens, i, module, is_package)
+                {
+                    res.insert(name);
+                }
+
+                statement_start = false;
+            }
+            Token::Newline | Token::Op(';') | Token::Op(':') => {
+                statement_start = true;
+            }
+            _ => {
+                statement_start = false;
+            }
+        }
+
+        i += 1;
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn imports(source: &str, module: &str, is_package: bool) -> Vec<String> {
+        find_imports(source.as_bytes(), module, is_package)
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_find_imports_absolute() {
+        assert_eq!(
+            imports(
+                "import os, sys as system\nimport xml.etree.ElementTree as ET\n",
+                "foo",
+                false
+            ),
+            vec!["os", "sys", "xml.etree.ElementTree"]
+        );
+        assert_eq!(
+            imports(
+                "from json import (\n    dumps,\n    loads as l,\n)\n",
+                "foo",
+                false
+            ),
+            vec!["json", "json.dumps", "json.loads"]
+        );
+        assert_eq!(
+            imports("from os.path import *\n", "foo", false),
+            vec!["os.path"]
+        );
+    }
+
+    #[test]
+    fn test_find_imports_relative() {
+        assert_eq!(
+            imports(
+                "from . import a\nfrom .b import c\nfrom .. import d\n",
+                "pkg.sub.mod",
+                false
+            ),
+            vec![
+                "pkg",
+                "pkg.d",
+                "pkg.sub",
+                "pkg.sub.a",
+                "pkg.sub.b",
+                "pkg.sub.b.c"
+            ]
+        );
+        assert_eq!(
+            imports("from .mod import x\n", "pkg", true),
+            vec!["pkg.mod", "pkg.mod.x"]
+        );
+        // Relative imports beyond the top-level package are ignored.
+        assert!(imports("from ... import x\n", "pkg.mod", false).is_empty());
+    }
+
+    #[test]
+    fn test_find_imports_nested() {
+        let source = "\
+try:
+    import json
+except ImportError:
+    json = None
+
+def f():
+    if True: import csv; import array
+    x = {'a': 1}
+    return x
+
+class C:
+    from sqlite3 import connect
+";
+
+        assert_eq!(
+            imports(source, "foo", false),
+            vec!["array", "csv", "json", "sqlite3", "sqlite3.connect"]
+        );
+    }
+
+    #[test]
+    fn test_find_imports_ignored() {
+        let source = "\
+# import comment
+s = 'import string'
+d = \"\"\"
+import docstring
+\"\"\"
+print(f'{x}', 'from x import y')
+imported = 1
+x.import_ = 2
+";
+
+        assert!(imports(source, "foo", false).is_empty());
+    }
+
+    #[test]
+    fn test_tokenize_string_prefixes() {
+        let name = |s: &str| Token::Name(s.to_string());
+        let string = |s: &str| Token::Str(Some(s.to_string()));
+
+        assert_eq!(
+            tokenize("x = r'a' + U\"b\" + Rb'c' + bR\"d\" + u'e\\n' + rf'{x}'\n"),
+            vec![
+                name("x"),
+                Token::Op('='),
+                string("a"),
+                Token::Op('+'),
+                string("b"),
+                Token::Op('+'),
+                Token::Str(None),
+                Token::Op('+'),
+                Token::Str(None),
+                Token::Op('+'),
+                Token::Str(None),
+                Token::Op('+'),
+                Token::Str(None),
+                Token::Newline,
+            ]
+        );
+
+        // Names spelled like prefixes are only prefixes when directly followed by a quote.
+        assert_eq!(
+            tokenize("rb = f (b)\nbr 'x'\n"),
+            vec![
+                name("rb"),
+                Token::Op('='),
+                name("f"),
+                Token::Op('('),
+                name("b"),
+                Token::Op(')'),
+                Token::Newline,
+                name("br"),
+                string("x"),
+                Token::Newline,
+            ]
+        );
+
+        // Quotes preceded by a backslash don't end raw strings either.
+        assert_eq!(
+            tokenize("r'\\'' + x\n"),
+            vec![Token::Str(None), Token::Op('+'), name("x"), Token::Newline]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_f_strings() {
+        // Replacement fields may contain strings using the same quotes.
+        assert_eq!(
+            tokenize("f\"{\"import os\"}\" + F'{x!r:>{width}}' + f'{{x}}'\n"),
+            vec![
+                Token::Str(None),
+                Token::Op('+'),
+                Token::Str(None),
+                Token::Op('+'),
+                Token::Str(None),
+                Token::Newline,
+            ]
+        );
+
+        let source = "\
+message = f\"\"\"
+import {name}
+{'''
+import nested
+'''}
+\"\"\"
+value = f'{d[\"key\"]}' + f\"{ {'a': 1}['a'] }\"
+import json
+";
+
+        assert_eq!(imports(source, "foo", false), vec!["json"]);
+    }
+
+    #[test]
+    fn test_find_imports_dynamic() {
+        assert_eq!(
+            imports(
+                "mod = importlib.import_module('decimal')\n__import__(\"fractions\")\nimport_module(name)\n",
+                "foo",
+                false
+            ),
+            vec!["decimal", "fractions"]
+        );
+    }
+
+    #[test]
+    fn test_find_dynamic_imports() {
+        let module = |name: &str| DynamicImport::Module(name.to_string());
+        let computed = |name: &str| DynamicImport::Computed(name.to_string());
+
+        assert_eq!(
+            find_dynamic_imports(
+                b"importlib.import_module('decimal')\n\
+                __import__(\"xml.dom\", globals(), locals(), ['minidom'])\n\
+                import_module(name)\n\
+                import_module('plugins.' + name)\n\
+                __import__(f'{name}')\n",
+                "foo",
+                false
+            ),
+            vec![
+                module("decimal"),
+                module("xml.dom"),
+                computed("import_module"),
+                computed("import_module"),
+                computed("__import__"),
+            ]
+        );
+
+        // Definitions and references that aren't calls are ignored.
+        assert_eq!(
+            find_dynamic_imports(
+                b"def import_module(name, package=None):\n    pass\n\
+                builtins.__import__ = hook\n",
+                "importlib",
+                true
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_find_dynamic_imports_relative() {
+        let module = |name: &str| DynamicImport::Module(name.to_string());
+        let computed = |name: &str| DynamicImport::Computed(name.to_string());
+
+        assert_eq!(
+            find_dynamic_imports(
+                b"import_module('.backends', __package__)\n\
+                import_module('..util', package=__package__)\n\
+                import_module('.', __name__)\n\
+                import_module('.sub', 'other.pkg')\n\
+                import_module('.sub')\n\
+                import_module('....sub', __package__)\n",
+                "pkg.mod",
+                false
+            ),
+            vec![
+                module("pkg.backends"),
+                computed("import_module"),
+                module("pkg.mod"),
+                module("other.pkg.sub"),
+                computed("import_module"),
+                computed("import_module"),
+            ]
+        );
+
+        assert_eq!(
+            find_dynamic_imports(
+                b"import_module('.backends', __package__)\n\
+                import_module('..util', package=__package__)\n",
+                "pkg.sub",
+                true
+            ),
+            vec![module("pkg.sub.backends"), module("pkg.util")]
+        );
+    }
 }
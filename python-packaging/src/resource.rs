@@ -15,12 +15,15 @@ use {
         module_util::{is_package_from_path, packages_from_module_name, resolve_path_for_module},
         python_source::has_dunder_file,
     },
-    anyhow::{anyhow, Result},
+    anyhow::{anyhow, Context, Result},
+    python_packed_resources::DataCompression,
     simple_file_manifest::{File, FileData},
     std::{
         borrow::Cow,
         collections::HashMap,
+        fmt::{Display, Formatter},
         hash::BuildHasher,
+        io::{Read, Write},
         path::{Path, PathBuf},
     },
 };
@@ -87,6 +90,164 @@ impl From<BytecodeOptimizationLevel> for i32 {
     }
 }
 
+/// Describes compression to apply to in-memory resource data.
+///
+/// String values are `none`, `zlib`, `zstd`, and `zstd-dict`. A compression
+/// level can be specified after a colon. e.g. `zlib:9` or `zstd:19`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResourceCompression {
+    /// Data is stored uncompressed.
+    #[default]
+    None,
+
+    /// Data is compressed with zlib at the given level (0-9).
+    Zlib(u32),
+
+    /// Data is compressed with zstd at the given level (1-22).
+    Zstd(i32),
+
+    /// Data is compressed with zstd at the given level (1-22) against a shared dictionary.
+    ///
+    /// The dictionary is trained over all data using this compression when
+    /// resources are compiled. This is much more effective than [Self::Zstd]
+    /// for large numbers of small payloads, like bytecode of small modules.
+    ZstdDictionary(i32),
+}
+
+impl Display for ResourceCompression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => f.write_str("none"),
+            Self::Zlib(level) => write!(f, "zlib:{}", level),
+            Self::Zstd(level) => write!(f, "zstd:{}", level),
+            Self::ZstdDictionary(level) => write!(f, "zstd-dict:{}", level),
+        }
+    }
+}
+
+impl TryFrom<&str> for ResourceCompression {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let (algorithm, level) = match value.split_once(':') {
+            Some((algorithm, level)) => (algorithm, Some(level)),
+            None => (value, None),
+        };
+
+        let level_error = || format!("invalid compression level in {}", value);
+
+        match (algorithm, level) {
+            ("none", None) => Ok(Self::None),
+            ("zlib", None) => Ok(Self::Zlib(6)),
+            ("zlib", Some(level)) => match level.parse::<u32>() {
+                Ok(level) if level <= 9 => Ok(Self::Zlib(level)),
+                _ => Err(level_error()),
+            },
+            ("zstd", None) => Ok(Self::Zstd(3)),
+            ("zstd", Some(level)) => match level.parse::<i32>() {
+                Ok(level) if (1..=22).contains(&level) => Ok(Self::Zstd(level)),
+                _ => Err(level_error()),
+            },
+            ("zstd-dict", None) => Ok(Self::ZstdDictionary(3)),
+            ("zstd-dict", Some(level)) => match level.parse::<i32>() {
+                Ok(level) if (1..=22).contains(&level) => Ok(Self::ZstdDictionary(level)),
+                _ => Err(level_error()),
+            },
+            _ => Err(format!(
+                "{} is not a valid compression; use none, zlib[:level], zstd[:level], or zstd-dict[:level]",
+                value
+            )),
+        }
+    }
+}
+
+impl ResourceCompression {
+    /// The compression algorithm recorded in packed resources data.
+    pub fn data_compression(&self) -> Option<DataCompression> {
+        match self {
+            Self::None => None,
+            Self::Zlib(_) => Some(DataCompression::Zlib),
+            Self::Zstd(_) => Some(DataCompression::Zstd),
+            Self::ZstdDictionary(_) => Some(DataCompression::ZstdDictionary),
+        }
+    }
+
+    /// Compress data using these settings.
+    ///
+    /// Data is returned as-is if no compression is configured. Data is also
+    /// returned as-is for [Self::ZstdDictionary], as the dictionary can only be
+    /// trained once all data is known. See [train_zstd_dictionary()].
+    pub fn compress(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(data),
+            Self::Zlib(level) => {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(vec![], flate2::Compression::new(*level));
+                encoder.write_all(&data).context("zlib compressing data")?;
+                Ok(encoder.finish()?)
+            }
+            Self::Zstd(level) => {
+                zstd::bulk::compress(&data, *level).context("zstd compressing data")
+            }
+            Self::ZstdDictionary(_) => Ok(data),
+        }
+    }
+}
+
+/// Train a zstd dictionary over samples of data.
+///
+/// Returns `None` if a dictionary could not be trained. This can happen
+/// if there isn't enough sample data.
+pub fn train_zstd_dictionary<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> Option<Vec<u8>> {
+    zstd::dict::from_samples(samples, max_size).ok()
+}
+
+/// Obtain the ID of the dictionary a zstd frame was compressed against, if any.
+pub fn zstd_frame_dictionary_id(data: &[u8]) -> Option<u32> {
+    zstd::zstd_safe::get_dict_id_from_frame(data).map(|id| id.get())
+}
+
+/// Obtain the ID of a zstd dictionary.
+pub fn zstd_dictionary_id(dictionary: &[u8]) -> Option<u32> {
+    zstd::zstd_safe::get_dict_id_from_dict(dictionary).map(|id| id.get())
+}
+
+/// Decompress zstd data compressed against a dictionary.
+pub fn decompress_data_with_dictionary(data: &[u8], dictionary: &[u8]) -> Result<Vec<u8>> {
+    let mut res = vec![];
+
+    zstd::stream::read::Decoder::with_dictionary(data, dictionary)?
+        .read_to_end(&mut res)
+        .context("zstd decompressing data with dictionary")?;
+
+    Ok(res)
+}
+
+/// Decompress data stored in packed resources with a compression algorithm.
+pub fn decompress_data(compression: DataCompression, data: &[u8]) -> Result<Vec<u8>> {
+    let mut res = vec![];
+
+    match compression {
+        DataCompression::Zlib => {
+            flate2::read::ZlibDecoder::new(data)
+                .read_to_end(&mut res)
+                .context("zlib decompressing data")?;
+        }
+        DataCompression::Zstd => {
+            zstd::stream::read::Decoder::new(data)?
+                .read_to_end(&mut res)
+                .context("zstd decompressing data")?;
+        }
+        DataCompression::ZstdDictionary => {
+            return Err(anyhow!(
+                "decompressing zstd data compressed against a dictionary requires the dictionary"
+            ));
+        }
+    }
+
+    Ok(res)
+}
+
 /// A Python module defined via source code.
 #[derive(Clone, Debug, PartialEq)]
 pub struct PythonModuleSource {
@@ -377,6 +538,11 @@ pub struct PythonPackageResource {
     pub is_stdlib: bool,
     /// Whether this resource belongs to a package that is a test.
     pub is_test: bool,
+    /// Locale this resource provides data for.
+    ///
+    /// Localized resources are only visible to `importlib.resources` when
+    /// their locale is selected at run-time.
+    pub locale: Option<String>,
 }
 
 impl PythonPackageResource {
@@ -391,11 +557,16 @@ impl PythonPackageResource {
             data: self.data.to_memory()?,
             is_stdlib: self.is_stdlib,
             is_test: self.is_test,
+            locale: self.locale.clone(),
         })
     }
 
     pub fn symbolic_name(&self) -> String {
-        format!("{}:{}", self.leaf_package, self.relative_name)
+        if let Some(locale) = &self.locale {
+            format!("{}:{}[{}]", self.leaf_package, self.relative_name, locale)
+        } else {
+            format!("{}:{}", self.leaf_package, self.relative_name)
+        }
     }
 
     /// Resolve filesystem path to this bytecode.
@@ -792,6 +963,45 @@ impl PythonPathExtension {
             data: self.data.to_memory()?,
         })
     }
+
+    /// Parse the entries defined by this .pth file.
+    ///
+    /// Parsing follows the semantics of `site.addpackage()`: blank lines and
+    /// lines beginning with `#` are ignored, lines beginning with `import`
+    /// followed by a space or tab are executed as Python code, and every other
+    /// line denotes a path to add to `sys.path`.
+    pub fn entries(&self) -> Result<Vec<PythonPathExtensionEntry>> {
+        let data = self.data.resolve_content()?;
+        let text = String::from_utf8_lossy(&data);
+
+        Ok(text
+            .lines()
+            .filter_map(|line| {
+                if line.starts_with('#') || line.trim().is_empty() {
+                    None
+                } else if line.starts_with("import ") || line.starts_with("import\t") {
+                    Some(PythonPathExtensionEntry::Import(line.to_string()))
+                } else {
+                    Some(PythonPathExtensionEntry::Path(line.trim_end().to_string()))
+                }
+            })
+            .collect::<Vec<_>>())
+    }
+}
+
+/// Represents an entry in a .pth file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PythonPathExtensionEntry {
+    /// A path to add to `sys.path`.
+    ///
+    /// Relative paths are relative to the directory holding the .pth file.
+    Path(String),
+
+    /// A line of Python code executed during `site` initialization.
+    ///
+    /// These are commonly used by editable installs to register custom
+    /// finders and by namespace package shims.
+    Import(String),
 }
 
 /// Represents a resource that can be read by Python somehow.
@@ -994,6 +1204,48 @@ mod tests {
 
     const DEFAULT_CACHE_TAG: &str = "cpython-39";
 
+    #[test]
+    fn test_resource_compression() -> Result<()> {
+        assert_eq!(
+            ResourceCompression::try_from("none"),
+            Ok(ResourceCompression::None)
+        );
+        assert_eq!(
+            ResourceCompression::try_from("zlib"),
+            Ok(ResourceCompression::Zlib(6))
+        );
+        assert_eq!(
+            ResourceCompression::try_from("zstd:19"),
+            Ok(ResourceCompression::Zstd(19))
+        );
+        assert!(ResourceCompression::try_from("zlib:10").is_err());
+        assert!(ResourceCompression::try_from("none:1").is_err());
+        assert!(ResourceCompression::try_from("lzma").is_err());
+        assert_eq!(ResourceCompression::Zstd(3).to_string(), "zstd:3");
+        assert_eq!(
+            ResourceCompression::try_from("zstd-dict"),
+            Ok(ResourceCompression::ZstdDictionary(3))
+        );
+        assert_eq!(
+            ResourceCompression::ZstdDictionary(19).to_string(),
+            "zstd-dict:19"
+        );
+        assert!(ResourceCompression::try_from("zstd-dict:23").is_err());
+
+        let data = b"foo bar baz foo bar baz foo bar baz".to_vec();
+
+        for compression in [ResourceCompression::Zlib(9), ResourceCompression::Zstd(3)] {
+            let compressed = compression.compress(data.clone())?;
+            assert_ne!(compressed, data);
+            assert_eq!(
+                decompress_data(compression.data_compression().unwrap(), &compressed)?,
+                data
+            );
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_is_in_packages() {
         let source = PythonResource::ModuleSource(Cow::Owned(PythonModuleSource {
@@ -1022,6 +1274,30 @@ mod tests {
         assert!(!bytecode.is_in_packages(&["bar".to_string()]));
     }
 
+    #[test]
+    fn path_extension_entries() -> Result<()> {
+        let pe = PythonPathExtension {
+            data: FileData::Memory(
+                b"# comment\n\n../src\nimport __editable___foo_finder; __editable___foo_finder.install()\nimport\tsys\nimported  \n"
+                    .to_vec(),
+            ),
+        };
+
+        assert_eq!(
+            pe.entries()?,
+            vec![
+                PythonPathExtensionEntry::Path("../src".to_string()),
+                PythonPathExtensionEntry::Import(
+                    "import __editable___foo_finder; __editable___foo_finder.install()".to_string()
+                ),
+                PythonPathExtensionEntry::Import("import\tsys".to_string()),
+                PythonPathExtensionEntry::Path("imported".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn package_distribution_resources_path_normalization() {
         // Package names are normalized to lowercase and have hyphens replaced
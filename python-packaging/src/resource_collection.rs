@@ -11,26 +11,28 @@
 use {
     crate::{
         bytecode::{
-            compute_bytecode_header, BytecodeHeaderMode, CompileMode, PythonBytecodeCompiler,
+            compute_bytecode_header, BytecodeHeaderMode, BytecodeStripping, CompileMode,
+            PythonBytecodeCompiler,
         },
         libpython::LibPythonBuildContext,
         licensing::{LicensedComponent, LicensedComponents},
         location::{AbstractResourceLocation, ConcreteResourceLocation},
         module_util::{packages_from_module_name, resolve_path_for_module},
-        python_source::has_dunder_file,
+        python_source::{find_dynamic_imports, find_imports, has_dunder_file, DynamicImport},
         resource::{
-            BytecodeOptimizationLevel, PythonExtensionModule, PythonModuleBytecode,
-            PythonModuleBytecodeFromSource, PythonModuleSource, PythonPackageDistributionResource,
-            PythonPackageResource, PythonResource, SharedLibrary,
+            train_zstd_dictionary, BytecodeOptimizationLevel, PythonExtensionModule,
+            PythonModuleBytecode, PythonModuleBytecodeFromSource, PythonModuleSource,
+            PythonPackageDistributionResource, PythonPackageResource, PythonResource,
+            ResourceCompression, SharedLibrary,
         },
     },
     anyhow::{anyhow, Context, Result},
-    python_packed_resources::Resource,
+    python_packed_resources::{DataCompression, Resource},
     simple_file_manifest::{File, FileData, FileEntry, FileManifest},
     std::{
         borrow::Cow,
         collections::{BTreeMap, BTreeSet, HashMap},
-        path::PathBuf,
+        path::{Path, PathBuf},
     },
 };
 
@@ -49,6 +51,21 @@ pub enum PythonModuleBytecodeProvider {
     FromSource(FileData),
 }
 
+/// A file replacing the data of a collected resource.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceOverride {
+    /// Path of the overridden file relative to the root of the package tree.
+    ///
+    /// e.g. `pkg/data/config.json` or `pkg/module.py`.
+    pub path: String,
+
+    /// Data replacing the resource data.
+    pub data: FileData,
+
+    /// Whether the override was applied to a collected resource.
+    pub applied: bool,
+}
+
 /// Represents a Python resource entry before it is packaged.
 ///
 /// Instances hold the same fields as `Resource` except fields holding
@@ -66,8 +83,11 @@ pub struct PrePackagedResource {
     pub in_memory_bytecode_opt2: Option<PythonModuleBytecodeProvider>,
     pub in_memory_extension_module_shared_library: Option<FileData>,
     pub in_memory_resources: Option<BTreeMap<String, FileData>>,
+    // locale -> (name, data)
+    pub in_memory_localized_resources: Option<BTreeMap<String, BTreeMap<String, FileData>>>,
     pub in_memory_distribution_resources: Option<BTreeMap<String, FileData>>,
     pub in_memory_shared_library: Option<FileData>,
+    pub in_memory_shared_library_filename: Option<PathBuf>,
     pub shared_library_dependency_names: Option<Vec<String>>,
     // (prefix, source code)
     pub relative_path_module_source: Option<(String, FileData)>,
@@ -89,9 +109,92 @@ pub struct PrePackagedResource {
     pub file_executable: bool,
     pub file_data_embedded: Option<FileData>,
     pub file_data_utf8_relative_path: Option<(PathBuf, FileData)>,
+    pub in_memory_bytecode_compression: ResourceCompression,
+    pub in_memory_resources_compression: ResourceCompression,
+    pub bytecode_stripping: BytecodeStripping,
+    /// Filesystem path of the module source this resource was collected from.
+    pub source_path: Option<PathBuf>,
 }
 
 impl PrePackagedResource {
+    /// Replace data of this resource belonging to a file path.
+    ///
+    /// `path` is relative to the root of the package tree. Module source is
+    /// replaced if `path` is the module's `.py` file. Bytecode is then compiled
+    /// from the replaced source. Package resources are replaced if `path`
+    /// refers to them.
+    ///
+    /// Returns whether any data was replaced.
+    pub fn apply_override(&mut self, path: &str, data: &FileData) -> bool {
+        let name_path = self.name.replace('.', "/");
+        let mut applied = false;
+
+        let module_path = if self.is_package {
+            format!("{}/__init__.py", name_path)
+        } else {
+            format!("{}.py", name_path)
+        };
+
+        if self.is_module && path == module_path {
+            if let Some(source) = &mut self.in_memory_source {
+                *source = data.clone();
+                applied = true;
+            }
+            if let Some((_, source)) = &mut self.relative_path_module_source {
+                *source = data.clone();
+                applied = true;
+            }
+
+            for bytecode in [
+                &mut self.in_memory_bytecode,
+                &mut self.in_memory_bytecode_opt1,
+                &mut self.in_memory_bytecode_opt2,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                *bytecode = PythonModuleBytecodeProvider::FromSource(data.clone());
+                applied = true;
+            }
+
+            for (_, _, bytecode) in [
+                &mut self.relative_path_bytecode,
+                &mut self.relative_path_bytecode_opt1,
+                &mut self.relative_path_bytecode_opt2,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                *bytecode = PythonModuleBytecodeProvider::FromSource(data.clone());
+                applied = true;
+            }
+        }
+
+        if let Some(name) = path
+            .strip_prefix(&name_path)
+            .and_then(|x| x.strip_prefix('/'))
+        {
+            if let Some(resource) = self
+                .in_memory_resources
+                .as_mut()
+                .and_then(|x| x.get_mut(name))
+            {
+                *resource = data.clone();
+                applied = true;
+            }
+            if let Some((_, resource)) = self
+                .relative_path_package_resources
+                .as_mut()
+                .and_then(|x| x.get_mut(name))
+            {
+                *resource = data.clone();
+                applied = true;
+            }
+        }
+
+        applied
+    }
+
     /// Whether this resource represents a Python resource.
     pub fn is_python_resource(&self) -> bool {
         self.is_module
@@ -100,6 +203,65 @@ impl PrePackagedResource {
             || self.is_extension_module
     }
 
+    /// Obtain Python source code for this module, if available.
+    ///
+    /// Source code is obtained from any location it is stored in or from
+    /// requests to compile bytecode from source.
+    pub fn module_source(&self) -> Option<&FileData> {
+        fn bytecode_source(provider: &Option<PythonModuleBytecodeProvider>) -> Option<&FileData> {
+            match provider {
+                Some(PythonModuleBytecodeProvider::FromSource(source)) => Some(source),
+                _ => None,
+            }
+        }
+
+        self.in_memory_source
+            .as_ref()
+            .or_else(|| bytecode_source(&self.in_memory_bytecode))
+            .or_else(|| bytecode_source(&self.in_memory_bytecode_opt1))
+            .or_else(|| bytecode_source(&self.in_memory_bytecode_opt2))
+            .or_else(|| {
+                self.relative_path_module_source
+                    .as_ref()
+                    .map(|(_, source)| source)
+            })
+            .or_else(|| {
+                [
+                    &self.relative_path_bytecode,
+                    &self.relative_path_bytecode_opt1,
+                    &self.relative_path_bytecode_opt2,
+                ]
+                .into_iter()
+                .find_map(|entry| match entry {
+                    Some((_, _, PythonModuleBytecodeProvider::FromSource(source))) => Some(source),
+                    _ => None,
+                })
+            })
+    }
+
+    /// Whether this module has bytecode without corresponding source code.
+    pub fn has_provided_bytecode(&self) -> bool {
+        [
+            &self.in_memory_bytecode,
+            &self.in_memory_bytecode_opt1,
+            &self.in_memory_bytecode_opt2,
+        ]
+        .into_iter()
+        .any(|provider| matches!(provider, Some(PythonModuleBytecodeProvider::Provided(_))))
+            || [
+                &self.relative_path_bytecode,
+                &self.relative_path_bytecode_opt1,
+                &self.relative_path_bytecode_opt2,
+            ]
+            .into_iter()
+            .any(|entry| {
+                matches!(
+                    entry,
+                    Some((_, _, PythonModuleBytecodeProvider::Provided(_)))
+                )
+            })
+    }
+
     /// Convert the instance to a `Resource`.
     ///
     /// This will compile bytecode from source code using the specified compiler.
@@ -121,50 +283,62 @@ impl PrePackagedResource {
                 None
             },
             in_memory_bytecode: match &self.in_memory_bytecode {
-                Some(PythonModuleBytecodeProvider::Provided(location)) => {
-                    Some(Cow::Owned(location.resolve_content()?))
-                }
+                Some(PythonModuleBytecodeProvider::Provided(location)) => Some(Cow::Owned(
+                    self.in_memory_bytecode_compression
+                        .compress(location.resolve_content()?)?,
+                )),
                 Some(PythonModuleBytecodeProvider::FromSource(location)) => Some(Cow::Owned(
-                    compiler
-                        .compile(
-                            &location.resolve_content()?,
-                            &self.name,
-                            BytecodeOptimizationLevel::Zero,
-                            CompileMode::Bytecode,
-                        )
-                        .context("compiling in-memory bytecode")?,
+                    self.in_memory_bytecode_compression.compress(
+                        compiler
+                            .compile_stripped(
+                                &location.resolve_content()?,
+                                &self.name,
+                                BytecodeOptimizationLevel::Zero,
+                                self.bytecode_stripping,
+                                CompileMode::Bytecode,
+                            )
+                            .context("compiling in-memory bytecode")?,
+                    )?,
                 )),
                 None => None,
             },
             in_memory_bytecode_opt1: match &self.in_memory_bytecode_opt1 {
-                Some(PythonModuleBytecodeProvider::Provided(location)) => {
-                    Some(Cow::Owned(location.resolve_content()?))
-                }
+                Some(PythonModuleBytecodeProvider::Provided(location)) => Some(Cow::Owned(
+                    self.in_memory_bytecode_compression
+                        .compress(location.resolve_content()?)?,
+                )),
                 Some(PythonModuleBytecodeProvider::FromSource(location)) => Some(Cow::Owned(
-                    compiler
-                        .compile(
-                            &location.resolve_content()?,
-                            &self.name,
-                            BytecodeOptimizationLevel::One,
-                            CompileMode::Bytecode,
-                        )
-                        .context("compiling in-memory bytecode opt-1")?,
+                    self.in_memory_bytecode_compression.compress(
+                        compiler
+                            .compile_stripped(
+                                &location.resolve_content()?,
+                                &self.name,
+                                BytecodeOptimizationLevel::One,
+                                self.bytecode_stripping,
+                                CompileMode::Bytecode,
+                            )
+                            .context("compiling in-memory bytecode opt-1")?,
+                    )?,
                 )),
                 None => None,
             },
             in_memory_bytecode_opt2: match &self.in_memory_bytecode_opt2 {
-                Some(PythonModuleBytecodeProvider::Provided(location)) => {
-                    Some(Cow::Owned(location.resolve_content()?))
-                }
+                Some(PythonModuleBytecodeProvider::Provided(location)) => Some(Cow::Owned(
+                    self.in_memory_bytecode_compression
+                        .compress(location.resolve_content()?)?,
+                )),
                 Some(PythonModuleBytecodeProvider::FromSource(location)) => Some(Cow::Owned(
-                    compiler
-                        .compile(
-                            &location.resolve_content()?,
-                            &self.name,
-                            BytecodeOptimizationLevel::Two,
-                            CompileMode::Bytecode,
-                        )
-                        .context("compiling in-memory bytecode opt2")?,
+                    self.in_memory_bytecode_compression.compress(
+                        compiler
+                            .compile_stripped(
+                                &location.resolve_content()?,
+                                &self.name,
+                                BytecodeOptimizationLevel::Two,
+                                self.bytecode_stripping,
+                                CompileMode::Bytecode,
+                            )
+                            .context("compiling in-memory bytecode opt2")?,
+                    )?,
                 )),
                 None => None,
             },
@@ -180,13 +354,37 @@ impl PrePackagedResource {
                 for (key, location) in resources {
                     res.insert(
                         Cow::Owned(key.clone()),
-                        Cow::Owned(location.resolve_content()?),
+                        Cow::Owned(
+                            self.in_memory_resources_compression
+                                .compress(location.resolve_content()?)?,
+                        ),
                     );
                 }
                 Some(res)
             } else {
                 None
             },
+            in_memory_localized_package_resources: if let Some(locales) =
+                &self.in_memory_localized_resources
+            {
+                let mut res = HashMap::new();
+                for (locale, resources) in locales {
+                    let mut group = HashMap::new();
+                    for (key, location) in resources {
+                        group.insert(
+                            Cow::Owned(key.clone()),
+                            Cow::Owned(
+                                self.in_memory_resources_compression
+                                    .compress(location.resolve_content()?)?,
+                            ),
+                        );
+                    }
+                    res.insert(Cow::Owned(locale.clone()), group);
+                }
+                Some(res)
+            } else {
+                None
+            },
             in_memory_distribution_resources: if let Some(resources) =
                 &self.in_memory_distribution_resources
             {
@@ -239,10 +437,11 @@ impl PrePackagedResource {
                     path.clone(),
                     FileData::Memory(match provider {
                         PythonModuleBytecodeProvider::FromSource(location) => compiler
-                            .compile(
+                            .compile_stripped(
                                 &location.resolve_content()?,
                                 &self.name,
                                 BytecodeOptimizationLevel::Zero,
+                                self.bytecode_stripping,
                                 CompileMode::PycUncheckedHash,
                             )
                             .context("compiling relative path module bytecode")?,
@@ -281,10 +480,11 @@ impl PrePackagedResource {
                     path.clone(),
                     FileData::Memory(match provider {
                         PythonModuleBytecodeProvider::FromSource(location) => compiler
-                            .compile(
+                            .compile_stripped(
                                 &location.resolve_content()?,
                                 &self.name,
                                 BytecodeOptimizationLevel::One,
+                                self.bytecode_stripping,
                                 CompileMode::PycUncheckedHash,
                             )
                             .context("compiling relative path module bytecode opt-1")?,
@@ -322,12 +522,14 @@ impl PrePackagedResource {
                 installs.push((
                     path.clone(),
                     FileData::Memory(match provider {
-                        PythonModuleBytecodeProvider::FromSource(location) => compiler.compile(
-                            &location.resolve_content()?,
-                            &self.name,
-                            BytecodeOptimizationLevel::Two,
-                            CompileMode::PycUncheckedHash,
-                        )?,
+                        PythonModuleBytecodeProvider::FromSource(location) => compiler
+                            .compile_stripped(
+                                &location.resolve_content()?,
+                                &self.name,
+                                BytecodeOptimizationLevel::Two,
+                                self.bytecode_stripping,
+                                CompileMode::PycUncheckedHash,
+                            )?,
                         PythonModuleBytecodeProvider::Provided(location) => {
                             let mut data = compute_bytecode_header(
                                 compiler.get_magic_number(),
@@ -402,6 +604,24 @@ impl PrePackagedResource {
             } else {
                 None
             },
+            in_memory_bytecode_compression: if self.in_memory_bytecode.is_some()
+                || self.in_memory_bytecode_opt1.is_some()
+                || self.in_memory_bytecode_opt2.is_some()
+            {
+                self.in_memory_bytecode_compression.data_compression()
+            } else {
+                None
+            },
+            in_memory_package_resources_compression: if self.in_memory_resources.is_some()
+                || self.in_memory_localized_resources.is_some()
+            {
+                self.in_memory_resources_compression.data_compression()
+            } else {
+                None
+            },
+            content_digest: None,
+            // Resolved by the collector, which knows the source map root.
+            source_map_path: None,
         };
 
         if let Some((prefix, filename, location)) = &self.relative_path_shared_library {
@@ -410,6 +630,217 @@ impl PrePackagedResource {
 
         Ok((resource, installs))
     }
+
+    /// Obtain a copy of this resource with all data stored in files.
+    ///
+    /// In-memory data is converted to the files a conventional Python
+    /// installation would have and all Python files are installed relative to
+    /// `prefix`. Bytecode files are named with `cache_tag` and extension
+    /// modules only available in memory with `extension_module_suffix`.
+    ///
+    /// Built-in and frozen modules are not backed by files and are retained
+    /// as-is.
+    pub fn to_relative_path(
+        &self,
+        prefix: &str,
+        cache_tag: &str,
+        extension_module_suffix: &str,
+    ) -> Result<Self> {
+        if self.in_memory_localized_resources.is_some() {
+            return Err(anyhow!(
+                "{} has localized resources, which can only be loaded from memory",
+                self.name
+            ));
+        }
+
+        let bytecode =
+            |in_memory: &Option<PythonModuleBytecodeProvider>,
+             relative_path: &Option<(String, String, PythonModuleBytecodeProvider)>| {
+                in_memory
+                    .clone()
+                    .or_else(|| {
+                        relative_path
+                            .as_ref()
+                            .map(|(_, _, provider)| provider.clone())
+                    })
+                    .map(|provider| (prefix.to_string(), cache_tag.to_string(), provider))
+            };
+
+        let mut package_path = PathBuf::from(prefix);
+        package_path.extend(self.name.split('.'));
+
+        let relative_path_extension_module_shared_library =
+            if let Some(data) = &self.in_memory_extension_module_shared_library {
+                let file_name = format!(
+                    "{}{}",
+                    self.name.rsplit('.').next().unwrap_or(&self.name),
+                    extension_module_suffix
+                );
+
+                Some((package_path.with_file_name(file_name), data.clone()))
+            } else if let Some((path, data)) = &self.relative_path_extension_module_shared_library {
+                let file_name = path
+                    .file_name()
+                    .ok_or_else(|| anyhow!("unable to resolve extension module file name"))?;
+
+                Some((package_path.with_file_name(file_name), data.clone()))
+            } else {
+                None
+            };
+
+        let mut relative_path_package_resources = BTreeMap::new();
+        for (name, data) in self.in_memory_resources.iter().flatten() {
+            relative_path_package_resources
+                .insert(name.clone(), (package_path.join(name), data.clone()));
+        }
+        for (name, (_, data)) in self.relative_path_package_resources.iter().flatten() {
+            relative_path_package_resources
+                .insert(name.clone(), (package_path.join(name), data.clone()));
+        }
+
+        let mut relative_path_distribution_resources = BTreeMap::new();
+        if let Some(resources) = &self.in_memory_distribution_resources {
+            let directory = distribution_directory_name(&self.name, resources)?;
+
+            for (name, data) in resources {
+                relative_path_distribution_resources.insert(
+                    name.clone(),
+                    (
+                        PathBuf::from(prefix).join(&directory).join(name),
+                        data.clone(),
+                    ),
+                );
+            }
+        }
+        for (name, (path, data)) in self.relative_path_distribution_resources.iter().flatten() {
+            // The distribution directory is the path component preceding the
+            // resource name.
+            let directory = path
+                .ancestors()
+                .nth(Path::new(name).components().count())
+                .and_then(|p| p.file_name())
+                .ok_or_else(|| anyhow!("unable to resolve distribution directory of {}", name))?;
+
+            relative_path_distribution_resources.insert(
+                name.clone(),
+                (
+                    PathBuf::from(prefix).join(directory).join(name),
+                    data.clone(),
+                ),
+            );
+        }
+
+        let relative_path_shared_library = match (
+            &self.in_memory_shared_library,
+            &self.relative_path_shared_library,
+        ) {
+            (Some(data), _) => {
+                let filename = self
+                    .in_memory_shared_library_filename
+                    .clone()
+                    .ok_or_else(|| {
+                        anyhow!("file name of shared library {} is not known", self.name)
+                    })?;
+
+                Some((prefix.to_string(), filename, data.clone()))
+            }
+            (None, Some((_, filename, data))) => {
+                Some((prefix.to_string(), filename.clone(), data.clone()))
+            }
+            (None, None) => None,
+        };
+
+        let relative_path_module_source = self
+            .in_memory_source
+            .clone()
+            .or_else(|| {
+                self.relative_path_module_source
+                    .as_ref()
+                    .map(|(_, data)| data.clone())
+            })
+            .map(|data| (prefix.to_string(), data));
+
+        let file_data_utf8_relative_path = if let Some(data) = &self.file_data_embedded {
+            Some((PathBuf::from(&self.name), data.clone()))
+        } else {
+            self.file_data_utf8_relative_path.clone()
+        };
+
+        Ok(Self {
+            name: self.name.clone(),
+            is_package: self.is_package,
+            is_namespace_package: self.is_namespace_package,
+            shared_library_dependency_names: self.shared_library_dependency_names.clone(),
+            relative_path_module_source,
+            relative_path_bytecode: bytecode(
+                &self.in_memory_bytecode,
+                &self.relative_path_bytecode,
+            ),
+            relative_path_bytecode_opt1: bytecode(
+                &self.in_memory_bytecode_opt1,
+                &self.relative_path_bytecode_opt1,
+            ),
+            relative_path_bytecode_opt2: bytecode(
+                &self.in_memory_bytecode_opt2,
+                &self.relative_path_bytecode_opt2,
+            ),
+            relative_path_extension_module_shared_library,
+            relative_path_package_resources: if relative_path_package_resources.is_empty() {
+                None
+            } else {
+                Some(relative_path_package_resources)
+            },
+            relative_path_distribution_resources: if relative_path_distribution_resources.is_empty()
+            {
+                None
+            } else {
+                Some(relative_path_distribution_resources)
+            },
+            relative_path_shared_library,
+            is_module: self.is_module,
+            is_builtin_extension_module: self.is_builtin_extension_module,
+            is_frozen_module: self.is_frozen_module,
+            is_extension_module: self.is_extension_module,
+            is_shared_library: self.is_shared_library,
+            is_utf8_filename_data: self.is_utf8_filename_data,
+            file_executable: self.file_executable,
+            file_data_utf8_relative_path,
+            bytecode_stripping: self.bytecode_stripping,
+            source_path: self.source_path.clone(),
+            ..Self::default()
+        })
+    }
+}
+
+/// Resolve the name of the metadata directory for a package distribution.
+///
+/// In-memory distribution resources don't record the package version. So it
+/// is obtained from the `Version` field of the distribution metadata.
+fn distribution_directory_name(
+    package: &str,
+    resources: &BTreeMap<String, FileData>,
+) -> Result<String> {
+    let (metadata, suffix) = if let Some(data) = resources.get("METADATA") {
+        (data, "dist-info")
+    } else if let Some(data) = resources.get("PKG-INFO") {
+        (data, "egg-info")
+    } else {
+        return Err(anyhow!("distribution metadata of {} not found", package));
+    };
+
+    let metadata = metadata.resolve_content()?;
+    let version = String::from_utf8_lossy(&metadata)
+        .lines()
+        .find_map(|line| line.strip_prefix("Version:").map(|v| v.trim().to_string()))
+        .ok_or_else(|| anyhow!("distribution metadata of {} has no version", package))?;
+
+    // This matches PythonPackageDistributionResource::resolve_path().
+    Ok(format!(
+        "{}-{}.{}",
+        package.to_lowercase().replace('-', "_"),
+        version,
+        suffix
+    ))
 }
 
 /// Fill in missing data on parent packages.
@@ -425,9 +856,20 @@ impl PrePackagedResource {
 /// a particular field, we populate that field in all its parent
 /// packages. If a corresponding fields is already populated, we
 /// copy its data as well.
+///
+/// Parent packages that aren't Python resources themselves (e.g. a directory
+/// without an `__init__.py`) are PEP 420 namespace packages. These are
+/// marked as such and don't have any code materialized, as their content may
+/// be split across multiple locations.
 pub fn populate_parent_packages(
     resources: &mut BTreeMap<String, PrePackagedResource>,
 ) -> Result<()> {
+    let module_names = resources
+        .iter()
+        .filter(|(_, v)| v.is_python_resource())
+        .map(|(k, _)| k.to_owned())
+        .collect::<BTreeSet<_>>();
+
     let original_resources = resources
         .iter()
         .filter_map(|(k, v)| {
@@ -452,6 +894,11 @@ pub fn populate_parent_packages(
             entry.is_module = true;
             entry.is_package = true;
 
+            if !module_names.contains(&entry.name) {
+                entry.is_namespace_package = true;
+                continue;
+            }
+
             // We want to materialize bytecode on parent packages no matter
             // what. If the original resource has a variant of bytecode in a
             // location, we materialize that variant on parents. We take
@@ -584,6 +1031,12 @@ pub struct PythonResourceAddCollectionContext {
 
     /// Whether to store Python bytecode for optimization level 2.
     pub optimize_level_two: bool,
+
+    /// Compression to apply to the resource's data when stored in memory.
+    pub compression: ResourceCompression,
+
+    /// Content to strip from Python source when compiling bytecode from it.
+    pub bytecode_stripping: BytecodeStripping,
 }
 
 impl PythonResourceAddCollectionContext {
@@ -596,6 +1049,8 @@ impl PythonResourceAddCollectionContext {
         self.optimize_level_zero = other.optimize_level_zero;
         self.optimize_level_one = other.optimize_level_one;
         self.optimize_level_two = other.optimize_level_two;
+        self.compression = other.compression;
+        self.bytecode_stripping = other.bytecode_stripping;
     }
 }
 
@@ -631,6 +1086,140 @@ impl ToString for AddResourceAction {
     }
 }
 
+/// Maximum size in bytes of trained zstd compression dictionaries.
+const ZSTD_DICTIONARY_MAX_SIZE: usize = 112_640;
+
+/// Compress resource data marked for zstd dictionary compression.
+///
+/// A single dictionary is trained over all data marked with
+/// [DataCompression::ZstdDictionary]. Then each payload is compressed
+/// against it and the dictionary is returned.
+///
+/// If a dictionary can't be trained (e.g. because there is too little data),
+/// data is compressed with regular zstd compression instead.
+fn apply_zstd_dictionary_compression(
+    resources: &mut BTreeMap<String, Resource<u8>>,
+    level: i32,
+) -> Result<Option<Vec<u8>>> {
+    let mut samples: Vec<&[u8]> = vec![];
+
+    for resource in resources.values() {
+        if resource.in_memory_bytecode_compression == Some(DataCompression::ZstdDictionary) {
+            for data in [
+                &resource.in_memory_bytecode,
+                &resource.in_memory_bytecode_opt1,
+                &resource.in_memory_bytecode_opt2,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                samples.push(data);
+            }
+        }
+
+        if resource.in_memory_package_resources_compression == Some(DataCompression::ZstdDictionary)
+        {
+            if let Some(data) = &resource.in_memory_package_resources {
+                samples.extend(data.values().map(|v| v.as_ref()));
+            }
+            if let Some(locales) = &resource.in_memory_localized_package_resources {
+                for data in locales.values() {
+                    samples.extend(data.values().map(|v| v.as_ref()));
+                }
+            }
+        }
+    }
+
+    if samples.is_empty() {
+        return Ok(None);
+    }
+
+    let dictionary = train_zstd_dictionary(&samples, ZSTD_DICTIONARY_MAX_SIZE);
+
+    let (mut compressor, compression) = if let Some(dictionary) = &dictionary {
+        (
+            zstd::bulk::Compressor::with_dictionary(level, dictionary)
+                .context("creating zstd dictionary compressor")?,
+            DataCompression::ZstdDictionary,
+        )
+    } else {
+        (
+            zstd::bulk::Compressor::new(level).context("creating zstd compressor")?,
+            DataCompression::Zstd,
+        )
+    };
+
+    let mut compress = |data: &mut Cow<[u8]>| -> Result<()> {
+        *data = Cow::Owned(compressor.compress(data).context("zstd compressing data")?);
+        Ok(())
+    };
+
+    for resource in resources.values_mut() {
+        if resource.in_memory_bytecode_compression == Some(DataCompression::ZstdDictionary) {
+            for data in [
+                &mut resource.in_memory_bytecode,
+                &mut resource.in_memory_bytecode_opt1,
+                &mut resource.in_memory_bytecode_opt2,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                compress(data)?;
+            }
+
+            resource.in_memory_bytecode_compression = Some(compression);
+        }
+
+        if resource.in_memory_package_resources_compression == Some(DataCompression::ZstdDictionary)
+        {
+            if let Some(data) = &mut resource.in_memory_package_resources {
+                for value in data.values_mut() {
+                    compress(value)?;
+                }
+            }
+            if let Some(locales) = &mut resource.in_memory_localized_package_resources {
+                for value in locales.values_mut().flat_map(|data| data.values_mut()) {
+                    compress(value)?;
+                }
+            }
+
+            resource.in_memory_package_resources_compression = Some(compression);
+        }
+    }
+
+    Ok(dictionary)
+}
+
+/// Python modules imported by standard library extension modules.
+///
+/// Imports performed by native code can't be discovered by analyzing Python
+/// source code. This records the ones from extension modules that are part
+/// of the standard library.
+const EXTENSION_MODULE_IMPORTS: &[(&str, &[&str])] = &[
+    ("_asyncio", &["asyncio"]),
+    ("_datetime", &["_strptime", "time"]),
+    ("_decimal", &["collections", "contextvars", "numbers"]),
+    ("_elementtree", &["copy", "xml.etree.ElementPath"]),
+    ("_pickle", &["_compat_pickle", "copyreg"]),
+    ("time", &["_strptime"]),
+];
+
+/// The outcome of analyzing imports between collected Python modules.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportAnalysis {
+    /// Modules reachable from the analysis roots.
+    pub reachable: BTreeSet<String>,
+
+    /// Modules not reachable from the analysis roots.
+    pub unreachable: BTreeSet<String>,
+
+    /// Reachable modules whose imports couldn't be analyzed.
+    ///
+    /// These are extension modules and modules having bytecode without
+    /// source code. All modules in their top-level package are reachable.
+    pub opaque: BTreeSet<String>,
+}
+
 /// Represents a finalized collection of Python resources.
 ///
 /// Instances are produced from a `PythonResourceCollector` and a
@@ -642,22 +1231,78 @@ pub struct CompiledResourcesCollection<'a> {
 
     /// Extra file installs that must be performed so referenced files are available.
     pub extra_files: Vec<FileInstall>,
+
+    /// zstd dictionaries needed to decompress resource data.
+    pub compression_dictionaries: Vec<Vec<u8>>,
 }
 
 impl<'a> CompiledResourcesCollection<'a> {
-    /// Write resources to packed resources data, version 1.
+    /// Write resources to packed resources data, version 3.
     pub fn write_packed_resources<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
-        python_packed_resources::write_packed_resources_v3(
+        python_packed_resources::write_packed_resources_v3_with_dictionaries(
             &self
                 .resources
                 .values()
                 .cloned()
                 .collect::<Vec<Resource<'a, u8>>>(),
+            &self
+                .compression_dictionaries
+                .iter()
+                .map(|d| d.as_slice())
+                .collect::<Vec<_>>(),
             writer,
             None,
         )
     }
 
+    /// Write resources to packed resources data, version 4.
+    ///
+    /// Version 4 records content digests of resources, allowing them to be verified
+    /// at run-time. Readers predating it can't parse it.
+    pub fn write_packed_resources_v4<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+        python_packed_resources::write_packed_resources_v4_with_dictionaries(
+            &self
+                .resources
+                .values()
+                .cloned()
+                .collect::<Vec<Resource<'a, u8>>>(),
+            &self
+                .compression_dictionaries
+                .iter()
+                .map(|d| d.as_slice())
+                .collect::<Vec<_>>(),
+            writer,
+        )
+    }
+
+    /// Move resources belonging to any of the named packages to a new collection.
+    ///
+    /// A resource belongs to a package if its name is the package name or
+    /// starts with the package name followed by `.`. Extra files remain in
+    /// this collection.
+    pub fn split_packages(&mut self, packages: &[String]) -> CompiledResourcesCollection<'a> {
+        let names = self
+            .resources
+            .keys()
+            .filter(|name| {
+                packages.iter().any(|package| {
+                    name.as_str() == package
+                        || matches!(name.strip_prefix(package.as_str()), Some(rest) if rest.starts_with('.'))
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        CompiledResourcesCollection {
+            resources: names
+                .into_iter()
+                .filter_map(|name| self.resources.remove_entry(&name))
+                .collect(),
+            extra_files: vec![],
+            compression_dictionaries: self.compression_dictionaries.clone(),
+        }
+    }
+
     /// Convert the file installs to a [FileManifest].
     pub fn extra_files_manifest(&self) -> Result<FileManifest> {
         let mut m = FileManifest::default();
@@ -712,6 +1357,12 @@ pub struct PythonResourceCollector {
 
     /// Collection of software components which are licensed.
     licensed_components: LicensedComponents,
+
+    /// Files replacing the data of collected resources.
+    resource_overrides: Vec<ResourceOverride>,
+
+    /// Directory that source map paths of modules are relative to.
+    source_map_root: Option<PathBuf>,
 }
 
 impl PythonResourceCollector {
@@ -735,6 +1386,8 @@ impl PythonResourceCollector {
             allow_files,
             resources: BTreeMap::new(),
             licensed_components: LicensedComponents::default(),
+            resource_overrides: vec![],
+            source_map_root: None,
         }
     }
 
@@ -800,11 +1453,207 @@ impl PythonResourceCollector {
         Ok(())
     }
 
+    /// Analyze imports between collected Python modules.
+    ///
+    /// Starting from `roots`, this follows imports statically found in the
+    /// source code of modules to determine which collected modules are
+    /// reachable. A root naming a package makes all modules in that package
+    /// roots. Importing a module also imports its parent packages.
+    ///
+    /// Built-in extension modules and resources holding package distribution
+    /// metadata are never considered unreachable.
+    pub fn analyze_imports(&self, roots: &BTreeSet<String>) -> Result<ImportAnalysis> {
+        let candidates = self
+            .resources
+            .iter()
+            .filter(|(_, r)| {
+                (r.is_module || r.is_extension_module)
+                    && !r.is_builtin_extension_module
+                    && r.in_memory_distribution_resources.is_none()
+                    && r.relative_path_distribution_resources.is_none()
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let package_members = |package: &str| {
+            let prefix = format!("{}.", package);
+
+            candidates
+                .keys()
+                .filter(|name| **name == package || name.starts_with(&prefix))
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let mut queue = vec![];
+        for root in roots {
+            queue.push(root.clone());
+            queue.extend(package_members(root));
+        }
+
+        let mut seen = BTreeSet::new();
+        let mut opaque = BTreeSet::new();
+
+        while let Some(name) = queue.pop() {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+
+            queue.extend(packages_from_module_name(&name));
+
+            if let Some((_, imports)) = EXTENSION_MODULE_IMPORTS.iter().find(|(n, _)| *n == name) {
+                queue.extend(imports.iter().map(|s| s.to_string()));
+            }
+
+            let resource = if let Some(resource) = candidates.get(&name) {
+                resource
+            } else {
+                continue;
+            };
+
+            if let Some(source) = resource.module_source() {
+                let source = source
+                    .resolve_content()
+                    .with_context(|| format!("resolving source of {}", name))?;
+
+                queue.extend(find_imports(&source, &name, resource.is_package));
+            }
+
+            if resource.is_extension_module || resource.has_provided_bytecode() {
+                let top_level = name.split('.').next().unwrap_or_default();
+                queue.extend(package_members(top_level));
+                opaque.insert(name);
+            }
+        }
+
+        let (reachable, unreachable) = candidates
+            .keys()
+            .map(|name| name.to_string())
+            .partition(|name| seen.contains(name));
+
+        Ok(ImportAnalysis {
+            reachable,
+            unreachable,
+            opaque,
+        })
+    }
+
+    /// Remove modules found to be unreachable by an import analysis.
+    ///
+    /// Shared libraries only used by removed extension modules are also removed.
+    pub fn remove_unreachable_modules(&mut self, analysis: &ImportAnalysis) {
+        let removed_libraries = self
+            .resources
+            .iter()
+            .filter(|(name, _)| analysis.unreachable.contains(*name))
+            .flat_map(|(_, r)| r.shared_library_dependency_names.iter().flatten())
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        self.resources
+            .retain(|name, _| !analysis.unreachable.contains(name));
+
+        let used_libraries = self
+            .resources
+            .values()
+            .flat_map(|r| r.shared_library_dependency_names.iter().flatten())
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        self.resources.retain(|name, r| {
+            !r.is_shared_library
+                || !removed_libraries.contains(name)
+                || used_libraries.contains(name)
+        });
+    }
+
     /// Obtain an iterator over the resources in this collector.
     pub fn iter_resources(&self) -> impl Iterator<Item = (&String, &PrePackagedResource)> {
         Box::new(self.resources.iter())
     }
 
+    /// Replace the data of a resource with other data.
+    ///
+    /// `path` is the path of a file relative to the root of the package tree,
+    /// such as `pkg/data/config.json`. It can refer to a package resource or
+    /// to the source of a module, such as `pkg/module.py`.
+    ///
+    /// The override applies to resources already collected and to resources
+    /// collected later.
+    pub fn override_resource(&mut self, path: &str, data: FileData) -> Result<()> {
+        let path = path.replace('\\', "/");
+        let path = path.trim_start_matches('/');
+
+        if path.is_empty() || path.split('/').any(|part| part.is_empty() || part == "..") {
+            return Err(anyhow!("invalid resource path: {}", path));
+        }
+
+        if self.resource_overrides.iter().any(|o| o.path == path) {
+            return Err(anyhow!("resource {} is already overridden", path));
+        }
+
+        let mut applied = false;
+        for resource in self.resources.values_mut() {
+            applied |= resource.apply_override(path, &data);
+        }
+
+        self.resource_overrides.push(ResourceOverride {
+            path: path.to_string(),
+            data,
+            applied,
+        });
+
+        Ok(())
+    }
+
+    /// Obtain registered resource overrides.
+    pub fn resource_overrides(&self) -> &[ResourceOverride] {
+        &self.resource_overrides
+    }
+
+    /// Ensure every resource override was applied to a collected resource.
+    ///
+    /// An override not applied likely refers to a file that doesn't exist.
+    pub fn check_resource_overrides(&self) -> Result<()> {
+        let unapplied = self
+            .resource_overrides
+            .iter()
+            .filter(|o| !o.applied)
+            .map(|o| o.path.as_str())
+            .collect::<Vec<_>>();
+
+        if unapplied.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "resource overrides did not match any collected resource: {}",
+                unapplied.join(", ")
+            ))
+        }
+    }
+
+    /// Apply registered resource overrides to a collected resource.
+    fn apply_resource_overrides(&mut self, name: &str) {
+        if let Some(resource) = self.resources.get_mut(name) {
+            for o in self.resource_overrides.iter_mut() {
+                o.applied |= resource.apply_override(&o.path, &o.data);
+            }
+        }
+    }
+
+    /// Obtain the directory that source map paths of modules are relative to.
+    pub fn source_map_root(&self) -> Option<&Path> {
+        self.source_map_root.as_deref()
+    }
+
+    /// Set the directory that source map paths of modules are relative to.
+    ///
+    /// When set, compiled resources of modules whose source was collected
+    /// from a file under this directory record the path of that file relative
+    /// to it. This allows rendering tracebacks with the original source paths.
+    pub fn set_source_map_root(&mut self, path: Option<PathBuf>) {
+        self.source_map_root = path;
+    }
+
     /// Register a licensed software component to this collection.
     pub fn add_licensed_component(&mut self, component: LicensedComponent) -> Result<()> {
         self.licensed_components.add_component(component);
@@ -839,6 +1688,10 @@ impl PythonResourceCollector {
         entry.is_module = true;
         entry.is_package = module.is_package;
 
+        if let FileData::Path(path) = &module.source {
+            entry.source_path = Some(path.clone());
+        }
+
         match location {
             ConcreteResourceLocation::InMemory => {
                 entry.in_memory_source = Some(module.source.clone());
@@ -849,6 +1702,8 @@ impl PythonResourceCollector {
             }
         }
 
+        self.apply_resource_overrides(&module.name);
+
         Ok(vec![AddResourceAction::Added(
             module.description(),
             location.clone(),
@@ -921,6 +1776,8 @@ impl PythonResourceCollector {
             );
         }
 
+        self.record_bytecode_settings(&module.name, &actions, add_context);
+
         Ok(actions)
     }
 
@@ -976,6 +1833,8 @@ impl PythonResourceCollector {
             },
         }
 
+        self.apply_resource_overrides(&module.name);
+
         Ok(vec![AddResourceAction::Added(
             module.description(),
             location.clone(),
@@ -997,7 +1856,7 @@ impl PythonResourceCollector {
             return Ok(vec![AddResourceAction::NoInclude(module.description())]);
         }
 
-        match module.optimize_level {
+        let actions = match module.optimize_level {
             BytecodeOptimizationLevel::Zero => {
                 if add_context.optimize_level_zero {
                     self.add_python_resource_with_locations(
@@ -1037,7 +1896,11 @@ impl PythonResourceCollector {
                     )])
                 }
             }
-        }
+        }?;
+
+        self.record_bytecode_settings(&module.name, &actions, add_context);
+
+        Ok(actions)
     }
 
     /// Add Python module bytecode derived from source code to the collection.
@@ -1059,6 +1922,10 @@ impl PythonResourceCollector {
         entry.is_module = true;
         entry.is_package = module.is_package;
 
+        if let FileData::Path(path) = &module.source {
+            entry.source_path = Some(path.clone());
+        }
+
         let bytecode = PythonModuleBytecodeProvider::FromSource(module.source.clone());
 
         match location {
@@ -1089,6 +1956,8 @@ impl PythonResourceCollector {
             },
         }
 
+        self.apply_resource_overrides(&module.name);
+
         Ok(vec![AddResourceAction::Added(
             module.description(),
             location.clone(),
@@ -1112,7 +1981,7 @@ impl PythonResourceCollector {
             return Ok(vec![AddResourceAction::NoInclude(module.description())]);
         }
 
-        match module.optimize_level {
+        let actions = match module.optimize_level {
             BytecodeOptimizationLevel::Zero => {
                 if add_context.optimize_level_zero {
                     self.add_python_resource_with_locations(
@@ -1152,7 +2021,11 @@ impl PythonResourceCollector {
                     )])
                 }
             }
-        }
+        }?;
+
+        self.record_bytecode_settings(&module.name, &actions, add_context);
+
+        Ok(actions)
     }
 
     /// Add resource data to a given location.
@@ -1177,8 +2050,22 @@ impl PythonResourceCollector {
         entry.is_module = true;
         entry.is_package = true;
 
-        match location {
-            ConcreteResourceLocation::InMemory => {
+        match (location, &resource.locale) {
+            (ConcreteResourceLocation::InMemory, Some(locale)) => {
+                entry
+                    .in_memory_localized_resources
+                    .get_or_insert_with(BTreeMap::new)
+                    .entry(locale.clone())
+                    .or_default()
+                    .insert(resource.relative_name.clone(), resource.data.clone());
+            }
+            (ConcreteResourceLocation::RelativePath(_), Some(_)) => {
+                return Err(anyhow!(
+                    "{} is localized and can only be loaded from memory",
+                    resource.description()
+                ));
+            }
+            (ConcreteResourceLocation::InMemory, None) => {
                 if entry.in_memory_resources.is_none() {
                     entry.in_memory_resources = Some(BTreeMap::new());
                 }
@@ -1188,7 +2075,7 @@ impl PythonResourceCollector {
                     .unwrap()
                     .insert(resource.relative_name.clone(), resource.data.clone());
             }
-            ConcreteResourceLocation::RelativePath(prefix) => {
+            (ConcreteResourceLocation::RelativePath(prefix), None) => {
                 if entry.relative_path_package_resources.is_none() {
                     entry.relative_path_package_resources = Some(BTreeMap::new());
                 }
@@ -1204,6 +2091,8 @@ impl PythonResourceCollector {
             }
         }
 
+        self.apply_resource_overrides(&resource.leaf_package);
+
         Ok(vec![AddResourceAction::Added(
             resource.description(),
             location.clone(),
@@ -1223,11 +2112,18 @@ impl PythonResourceCollector {
             return Ok(vec![AddResourceAction::NoInclude(resource.description())]);
         }
 
-        self.add_python_resource_with_locations(
+        let actions = self.add_python_resource_with_locations(
             &resource.into(),
             &add_context.location,
             &add_context.location_fallback,
-        )
+        )?;
+
+        // Compression applies to all in-memory resources of a package.
+        if let Some(entry) = self.resources.get_mut(&resource.leaf_package) {
+            entry.in_memory_resources_compression = add_context.compression;
+        }
+
+        Ok(actions)
     }
 
     /// Add a Python package distribution resource to a given location.
@@ -1584,6 +2480,24 @@ impl PythonResourceCollector {
         Ok(actions)
     }
 
+    /// Record the bytecode compression and stripping of a module if bytecode was added.
+    fn record_bytecode_settings(
+        &mut self,
+        name: &str,
+        actions: &[AddResourceAction],
+        add_context: &PythonResourceAddCollectionContext,
+    ) {
+        if actions
+            .iter()
+            .any(|action| matches!(action, AddResourceAction::Added(..)))
+        {
+            if let Some(entry) = self.resources.get_mut(name) {
+                entry.in_memory_bytecode_compression = add_context.compression;
+                entry.bytecode_stripping = add_context.bytecode_stripping;
+            }
+        }
+    }
+
     /// Add a shared library to be loaded from a location.
     pub fn add_shared_library(
         &mut self,
@@ -1605,6 +2519,7 @@ impl PythonResourceCollector {
         match location {
             ConcreteResourceLocation::InMemory => {
                 entry.in_memory_shared_library = Some(library.data.clone());
+                entry.in_memory_shared_library_filename = library.filename.clone();
             }
             ConcreteResourceLocation::RelativePath(prefix) => match &library.filename {
                 Some(filename) => {
@@ -1834,6 +2749,33 @@ impl PythonResourceCollector {
         Ok(res)
     }
 
+    /// Find calls importing modules by name in the source code of Python modules.
+    ///
+    /// Returns a mapping of module names to the dynamic imports found in their
+    /// source code. Modules without dynamic imports aren't present.
+    pub fn find_dynamic_imports(&self) -> Result<BTreeMap<String, Vec<DynamicImport>>> {
+        let mut res = BTreeMap::new();
+
+        for (name, module) in &self.resources {
+            if !module.is_module {
+                continue;
+            }
+
+            if let Some(source) = module.module_source() {
+                let source = source
+                    .resolve_content()
+                    .with_context(|| format!("resolving source of {}", name))?;
+
+                let imports = find_dynamic_imports(&source, name, module.is_package);
+                if !imports.is_empty() {
+                    res.insert(name.clone(), imports);
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
     /// Compiles resources into a finalized collection.
     ///
     /// This will take all resources collected so far and convert them into
@@ -1843,7 +2785,7 @@ impl PythonResourceCollector {
     pub fn compile_resources(
         &self,
         compiler: &mut dyn PythonBytecodeCompiler,
-    ) -> Result<CompiledResourcesCollection> {
+    ) -> Result<CompiledResourcesCollection<'static>> {
         let mut input_resources = self.resources.clone();
         populate_parent_packages(&mut input_resources).context("populating parent packages")?;
 
@@ -1851,20 +2793,116 @@ impl PythonResourceCollector {
         let mut extra_files = Vec::new();
 
         for (name, resource) in &input_resources {
-            let (entry, installs) = resource
+            let (mut entry, installs) = resource
                 .to_resource(compiler)
                 .with_context(|| format!("converting {} to resource", name))?;
 
-            for install in installs {
-                extra_files.push(install);
+            if let (Some(root), Some(path)) = (&self.source_map_root, &resource.source_path) {
+                if let Ok(relative) = path.strip_prefix(root) {
+                    let relative = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+
+                    entry.source_map_path = Some(Cow::Owned(relative));
+                }
+            }
+
+            for install in installs {
+                extra_files.push(install);
+            }
+
+            resources.insert(name.clone(), entry);
+        }
+
+        let dictionary_level = input_resources
+            .values()
+            .flat_map(|r| {
+                [
+                    r.in_memory_bytecode_compression,
+                    r.in_memory_resources_compression,
+                ]
+            })
+            .filter_map(|c| match c {
+                ResourceCompression::ZstdDictionary(level) => Some(level),
+                _ => None,
+            })
+            .max();
+
+        let compression_dictionaries = if let Some(level) = dictionary_level {
+            apply_zstd_dictionary_compression(&mut resources, level)
+                .context("applying zstd dictionary compression")?
+                .into_iter()
+                .collect()
+        } else {
+            vec![]
+        };
+
+        Ok(CompiledResourcesCollection {
+            resources,
+            extra_files,
+            compression_dictionaries,
+        })
+    }
+
+    /// Obtain a copy of this collector with all resources stored in files.
+    ///
+    /// Resources are converted with [PrePackagedResource::to_relative_path].
+    /// Shared libraries are installed next to the first extension module
+    /// depending on them so the dynamic loader can find them.
+    pub fn to_relative_path(
+        &self,
+        prefix: &str,
+        cache_tag: &str,
+        extension_module_suffix: &str,
+    ) -> Result<Self> {
+        let mut resources = self
+            .resources
+            .iter()
+            .map(|(name, resource)| {
+                let resource = resource
+                    .to_relative_path(prefix, cache_tag, extension_module_suffix)
+                    .with_context(|| format!("converting {} to relative path", name))?;
+
+                Ok((name.clone(), resource))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        let mut library_dirs = BTreeMap::new();
+        for resource in resources.values() {
+            if let (Some((path, _)), Some(depends)) = (
+                &resource.relative_path_extension_module_shared_library,
+                &resource.shared_library_dependency_names,
+            ) {
+                if let Some(parent) = path.parent() {
+                    for name in depends {
+                        library_dirs
+                            .entry(name.clone())
+                            .or_insert_with(|| parent.display().to_string().replace('\\', "/"));
+                    }
+                }
             }
+        }
 
-            resources.insert(name.clone(), entry);
+        for (name, dir) in library_dirs {
+            if let Some((prefix, _, _)) = resources
+                .get_mut(&name)
+                .and_then(|entry| entry.relative_path_shared_library.as_mut())
+            {
+                *prefix = dir;
+            }
         }
 
-        Ok(CompiledResourcesCollection {
+        Ok(Self {
+            allowed_locations: vec![AbstractResourceLocation::RelativePath],
+            allowed_extension_module_locations: vec![AbstractResourceLocation::RelativePath],
+            allow_new_builtin_extension_modules: self.allow_new_builtin_extension_modules,
+            allow_files: self.allow_files,
             resources,
-            extra_files,
+            licensed_components: self.licensed_components.clone(),
+            resource_overrides: self.resource_overrides.clone(),
+            source_map_root: self.source_map_root.clone(),
         })
     }
 }
@@ -1874,7 +2912,10 @@ mod tests {
     use {
         super::*,
         crate::{
-            resource::{LibraryDependency, PythonPackageDistributionResourceFlavor},
+            resource::{
+                decompress_data, zstd_dictionary_id, LibraryDependency,
+                PythonPackageDistributionResourceFlavor,
+            },
             testutil::FakeBytecodeCompiler,
         },
         simple_file_manifest::FileEntry,
@@ -1882,6 +2923,41 @@ mod tests {
 
     const DEFAULT_CACHE_TAG: &str = "cpython-39";
 
+    #[test]
+    fn test_resource_conversion_bytecode_stripping() -> Result<()> {
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+
+        let pre = PrePackagedResource {
+            is_module: true,
+            name: "module".to_string(),
+            in_memory_bytecode: Some(PythonModuleBytecodeProvider::FromSource(FileData::Memory(
+                b"source".to_vec(),
+            ))),
+            in_memory_bytecode_opt1: Some(PythonModuleBytecodeProvider::Provided(
+                FileData::Memory(b"bytecode".to_vec()),
+            )),
+            bytecode_stripping: BytecodeStripping {
+                docstrings: true,
+                annotations: false,
+            },
+            ..PrePackagedResource::default()
+        };
+
+        let (resource, _) = pre.to_resource(&mut compiler)?;
+
+        assert_eq!(
+            resource.in_memory_bytecode,
+            Some(Cow::Owned(b"strip-docstrings:bc0source".to_vec()))
+        );
+        // Provided bytecode is used as-is.
+        assert_eq!(
+            resource.in_memory_bytecode_opt1,
+            Some(Cow::Owned(b"bytecode".to_vec()))
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_resource_conversion_basic() -> Result<()> {
         let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
@@ -2176,6 +3252,140 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_resource_conversion_in_memory_compression() -> Result<()> {
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+
+        let mut resources = BTreeMap::new();
+        resources.insert("foo".to_string(), FileData::Memory(b"value".to_vec()));
+
+        let pre = PrePackagedResource {
+            is_module: true,
+            name: "module".to_string(),
+            in_memory_bytecode: Some(PythonModuleBytecodeProvider::Provided(FileData::Memory(
+                b"bytecode".to_vec(),
+            ))),
+            in_memory_resources: Some(resources),
+            in_memory_bytecode_compression: ResourceCompression::Zstd(3),
+            in_memory_resources_compression: ResourceCompression::Zlib(6),
+            ..PrePackagedResource::default()
+        };
+
+        let (resource, installs) = pre.to_resource(&mut compiler)?;
+
+        assert_eq!(
+            resource.in_memory_bytecode_compression,
+            Some(DataCompression::Zstd)
+        );
+        assert_eq!(
+            decompress_data(
+                DataCompression::Zstd,
+                resource.in_memory_bytecode.as_ref().unwrap()
+            )?,
+            b"bytecode"
+        );
+        assert_eq!(
+            resource.in_memory_package_resources_compression,
+            Some(DataCompression::Zlib)
+        );
+        assert_eq!(
+            decompress_data(
+                DataCompression::Zlib,
+                resource
+                    .in_memory_package_resources
+                    .as_ref()
+                    .unwrap()
+                    .get("foo")
+                    .unwrap()
+            )?,
+            b"value"
+        );
+        assert!(installs.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zstd_dictionary_compression() -> Result<()> {
+        let mut resources = BTreeMap::new();
+
+        for i in 0..500 {
+            let name = format!("module{}", i);
+            let bytecode =
+                format!("def function_{i}(value):\n    return value * {i} + helper_{i}(value)\n")
+                    .repeat(4);
+
+            resources.insert(
+                name.clone(),
+                Resource {
+                    name: Cow::Owned(name),
+                    is_python_module: true,
+                    in_memory_bytecode: Some(Cow::Owned(bytecode.into_bytes())),
+                    in_memory_bytecode_compression: Some(DataCompression::ZstdDictionary),
+                    ..Resource::default()
+                },
+            );
+        }
+
+        let original = resources.clone();
+        let dictionary = apply_zstd_dictionary_compression(&mut resources, 3)?.unwrap();
+        let dictionary = &dictionary;
+        assert_eq!(resources.len(), original.len());
+
+        for (name, resource) in &original {
+            let compressed = resources.get(name).unwrap();
+            let data = compressed.in_memory_bytecode.as_ref().unwrap();
+
+            assert_eq!(
+                compressed.in_memory_bytecode_compression,
+                Some(DataCompression::ZstdDictionary)
+            );
+            assert_eq!(
+                crate::resource::zstd_frame_dictionary_id(data),
+                zstd_dictionary_id(dictionary)
+            );
+            assert_eq!(
+                crate::resource::decompress_data_with_dictionary(data, dictionary)?,
+                resource.in_memory_bytecode.as_ref().unwrap().as_ref()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zstd_dictionary_compression_fallback() -> Result<()> {
+        let mut resources = BTreeMap::new();
+        resources.insert(
+            "module".to_string(),
+            Resource {
+                name: Cow::Owned("module".to_string()),
+                is_python_module: true,
+                in_memory_bytecode: Some(Cow::Owned(b"bytecode".to_vec())),
+                in_memory_bytecode_compression: Some(DataCompression::ZstdDictionary),
+                ..Resource::default()
+            },
+        );
+
+        // Too little data to train a dictionary so regular zstd is used.
+        assert!(apply_zstd_dictionary_compression(&mut resources, 3)?.is_none());
+        assert_eq!(resources.len(), 1);
+        let resource = resources.get("module").unwrap();
+        assert_eq!(
+            resource.in_memory_bytecode_compression,
+            Some(DataCompression::Zstd)
+        );
+        assert_eq!(
+            decompress_data(
+                DataCompression::Zstd,
+                resource.in_memory_bytecode.as_ref().unwrap()
+            )?,
+            b"bytecode"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_resource_conversion_in_memory_distribution_resources() -> Result<()> {
         let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
@@ -2734,6 +3944,17 @@ mod tests {
     #[test]
     fn test_populate_parent_packages_in_memory_source() -> Result<()> {
         let mut h = BTreeMap::new();
+        for name in ["root", "root.parent"] {
+            h.insert(
+                name.to_string(),
+                PrePackagedResource {
+                    is_module: true,
+                    name: name.to_string(),
+                    is_package: true,
+                    ..PrePackagedResource::default()
+                },
+            );
+        }
         h.insert(
             "root.parent.child".to_string(),
             PrePackagedResource {
@@ -2775,6 +3996,17 @@ mod tests {
     #[test]
     fn test_populate_parent_packages_relative_path_source() -> Result<()> {
         let mut h = BTreeMap::new();
+        for name in ["root", "root.parent"] {
+            h.insert(
+                name.to_string(),
+                PrePackagedResource {
+                    is_module: true,
+                    name: name.to_string(),
+                    is_package: true,
+                    ..PrePackagedResource::default()
+                },
+            );
+        }
         h.insert(
             "root.parent.child".to_string(),
             PrePackagedResource {
@@ -2819,6 +4051,17 @@ mod tests {
     #[test]
     fn test_populate_parent_packages_in_memory_bytecode() -> Result<()> {
         let mut h = BTreeMap::new();
+        for name in ["root", "root.parent"] {
+            h.insert(
+                name.to_string(),
+                PrePackagedResource {
+                    is_module: true,
+                    name: name.to_string(),
+                    is_package: true,
+                    ..PrePackagedResource::default()
+                },
+            );
+        }
         h.insert(
             "root.parent.child".to_string(),
             PrePackagedResource {
@@ -2863,6 +4106,63 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_populate_parent_packages_namespace() -> Result<()> {
+        let mut h = BTreeMap::new();
+        h.insert(
+            "root.parent.child".to_string(),
+            PrePackagedResource {
+                is_module: true,
+                name: "root.parent.child".to_string(),
+                in_memory_source: Some(FileData::Memory(vec![42])),
+                relative_path_module_source: Some((
+                    "prefix".to_string(),
+                    FileData::Memory(vec![42]),
+                )),
+                ..PrePackagedResource::default()
+            },
+        );
+        h.insert(
+            "root".to_string(),
+            PrePackagedResource {
+                is_module: true,
+                name: "root".to_string(),
+                is_package: true,
+                in_memory_source: Some(FileData::Memory(vec![1])),
+                ..PrePackagedResource::default()
+            },
+        );
+
+        populate_parent_packages(&mut h)?;
+
+        assert_eq!(h.len(), 3);
+        // Missing parents are namespace packages without code.
+        assert_eq!(
+            h.get("root.parent"),
+            Some(&PrePackagedResource {
+                is_module: true,
+                name: "root.parent".to_string(),
+                is_package: true,
+                is_namespace_package: true,
+                ..PrePackagedResource::default()
+            })
+        );
+        // Existing packages are regular packages.
+        assert_eq!(
+            h.get("root"),
+            Some(&PrePackagedResource {
+                is_module: true,
+                name: "root".to_string(),
+                is_package: true,
+                in_memory_source: Some(FileData::Memory(vec![1])),
+                relative_path_module_source: Some(("prefix".to_string(), FileData::Memory(vec![]))),
+                ..PrePackagedResource::default()
+            })
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_populate_parent_packages_distribution_extension_module() -> Result<()> {
         let mut h = BTreeMap::new();
@@ -2887,6 +4187,7 @@ mod tests {
                 is_module: true,
                 name: "foo".to_string(),
                 is_package: true,
+                is_namespace_package: true,
                 ..PrePackagedResource::default()
             })
         );
@@ -2920,6 +4221,7 @@ mod tests {
                 is_module: true,
                 name: "foo".to_string(),
                 is_package: true,
+                is_namespace_package: true,
                 ..PrePackagedResource::default()
             })
         );
@@ -2978,6 +4280,107 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_source_map_path() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("python-packaging-test")
+            .tempdir()?;
+        let root = td.path().join("repo");
+        std::fs::create_dir_all(root.join("src").join("app"))?;
+        std::fs::write(root.join("src").join("app").join("main.py"), "pass\n")?;
+        std::fs::write(td.path().join("outside.py"), "pass\n")?;
+
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+        );
+        r.set_source_map_root(Some(root.clone()));
+
+        r.add_python_module_bytecode_from_source(
+            &PythonModuleBytecodeFromSource {
+                name: "app.main".to_string(),
+                source: FileData::Path(root.join("src").join("app").join("main.py")),
+                optimize_level: BytecodeOptimizationLevel::Zero,
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "outside".to_string(),
+                source: FileData::Path(td.path().join("outside.py")),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+        let resources = r.compile_resources(&mut compiler)?;
+
+        assert_eq!(
+            resources.resources.get("app.main").unwrap().source_map_path,
+            Some(Cow::Borrowed("src/app/main.py"))
+        );
+        assert_eq!(
+            resources.resources.get("outside").unwrap().source_map_path,
+            None
+        );
+        assert_eq!(
+            resources.resources.get("app").unwrap().source_map_path,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_packages() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+        );
+
+        for name in ["app", "app.main", "application", "other"] {
+            r.add_python_module_source(
+                &PythonModuleSource {
+                    name: name.to_string(),
+                    source: FileData::Memory(vec![42]),
+                    is_package: name == "app",
+                    cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+                &ConcreteResourceLocation::InMemory,
+            )?;
+        }
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+        let mut resources = r.compile_resources(&mut compiler)?;
+
+        let layer = resources.split_packages(&["app".to_string()]);
+
+        assert_eq!(
+            layer.resources.keys().collect::<Vec<_>>(),
+            vec!["app", "app.main"]
+        );
+        assert_eq!(
+            resources.resources.keys().collect::<Vec<_>>(),
+            vec!["application", "other"]
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_in_memory_source_module_parents() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -3021,7 +4424,7 @@ mod tests {
                 is_python_module: true,
                 name: Cow::Owned("root".to_string()),
                 is_python_package: true,
-                in_memory_source: Some(Cow::Owned(vec![])),
+                is_python_namespace_package: true,
                 ..Resource::default()
             })
         );
@@ -3031,7 +4434,7 @@ mod tests {
                 is_python_module: true,
                 name: Cow::Owned("root.parent".to_string()),
                 is_python_package: true,
-                in_memory_source: Some(Cow::Owned(vec![])),
+                is_python_namespace_package: true,
                 ..Resource::default()
             })
         );
@@ -3097,9 +4500,7 @@ mod tests {
                 is_python_module: true,
                 name: Cow::Owned("foo".to_string()),
                 is_python_package: true,
-                relative_path_module_source: Some(Cow::Owned(PathBuf::from(
-                    "prefix/foo/__init__.py"
-                ))),
+                is_python_namespace_package: true,
                 ..Resource::default()
             })
         );
@@ -3108,24 +4509,17 @@ mod tests {
             Some(&Resource {
                 is_python_module: true,
                 name: Cow::Owned("foo.bar".to_string()),
-                relative_path_module_source: Some(Cow::Owned(PathBuf::from("prefix/foo/bar.py"))),
-                ..Resource::default()
-            })
-        );
-        assert_eq!(
-            resources.extra_files,
-            vec![
-                (
-                    PathBuf::from("prefix/foo/__init__.py"),
-                    FileData::Memory(vec![]),
-                    false
-                ),
-                (
-                    PathBuf::from("prefix/foo/bar.py"),
-                    FileData::Memory(vec![42]),
-                    false
-                )
-            ]
+                relative_path_module_source: Some(Cow::Owned(PathBuf::from("prefix/foo/bar.py"))),
+                ..Resource::default()
+            })
+        );
+        assert_eq!(
+            resources.extra_files,
+            vec![(
+                PathBuf::from("prefix/foo/bar.py"),
+                FileData::Memory(vec![42]),
+                false
+            )]
         );
 
         Ok(())
@@ -3157,6 +4551,8 @@ mod tests {
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
+            compression: ResourceCompression::None,
+            bytecode_stripping: BytecodeStripping::default(),
         };
 
         // include=false is a noop.
@@ -3382,6 +4778,8 @@ mod tests {
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
+            compression: ResourceCompression::None,
+            bytecode_stripping: BytecodeStripping::default(),
         };
 
         // include=false is a noop.
@@ -3522,7 +4920,7 @@ mod tests {
                 is_python_module: true,
                 name: Cow::Owned("root".to_string()),
                 is_python_package: true,
-                in_memory_bytecode_opt1: Some(Cow::Owned(b"bc1".to_vec())),
+                is_python_namespace_package: true,
                 ..Resource::default()
             })
         );
@@ -3532,7 +4930,7 @@ mod tests {
                 is_python_module: true,
                 name: Cow::Owned("root.parent".to_string()),
                 is_python_package: true,
-                in_memory_bytecode_opt1: Some(Cow::Owned(b"bc1".to_vec())),
+                is_python_namespace_package: true,
                 ..Resource::default()
             })
         );
@@ -3578,6 +4976,8 @@ mod tests {
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
+            compression: ResourceCompression::None,
+            bytecode_stripping: BytecodeStripping::default(),
         };
 
         // include=false is a noop.
@@ -3687,6 +5087,7 @@ mod tests {
                 data: FileData::Memory(vec![42]),
                 is_stdlib: false,
                 is_test: false,
+                locale: None,
             },
             &ConcreteResourceLocation::InMemory,
         )?;
@@ -3733,6 +5134,60 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_add_in_memory_localized_package_resource() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![
+                AbstractResourceLocation::InMemory,
+                AbstractResourceLocation::RelativePath,
+            ],
+            vec![],
+            false,
+            false,
+        );
+        let resource = PythonPackageResource {
+            leaf_package: "foo".to_string(),
+            relative_name: "messages.txt".to_string(),
+            data: FileData::Memory(vec![42]),
+            is_stdlib: false,
+            is_test: false,
+            locale: Some("de".to_string()),
+        };
+
+        assert!(r
+            .add_python_package_resource(
+                &resource,
+                &ConcreteResourceLocation::RelativePath("prefix".to_string()),
+            )
+            .is_err());
+
+        r.add_python_package_resource(&resource, &ConcreteResourceLocation::InMemory)?;
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+
+        let resources = r.compile_resources(&mut compiler)?;
+
+        let entry = resources.resources.get("foo").unwrap();
+        assert!(entry.in_memory_package_resources.is_none());
+        assert_eq!(
+            entry.in_memory_localized_package_resources,
+            Some(
+                [(
+                    Cow::Owned("de".to_string()),
+                    [(Cow::Owned("messages.txt".to_string()), Cow::Owned(vec![42]))]
+                        .iter()
+                        .cloned()
+                        .collect()
+                )]
+                .iter()
+                .cloned()
+                .collect()
+            )
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_relative_path_package_resource() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -3748,6 +5203,7 @@ mod tests {
                 data: FileData::Memory(vec![42]),
                 is_stdlib: false,
                 is_test: false,
+                locale: None,
             },
             &ConcreteResourceLocation::RelativePath("prefix".to_string()),
         )?;
@@ -3825,6 +5281,7 @@ mod tests {
             data: FileData::Memory(vec![42]),
             is_stdlib: false,
             is_test: false,
+            locale: None,
         };
 
         let mut add_context = PythonResourceAddCollectionContext {
@@ -3835,6 +5292,8 @@ mod tests {
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
+            compression: ResourceCompression::None,
+            bytecode_stripping: BytecodeStripping::default(),
         };
 
         // include=false is a noop.
@@ -4060,6 +5519,8 @@ mod tests {
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
+            compression: ResourceCompression::None,
+            bytecode_stripping: BytecodeStripping::default(),
         };
 
         // include=false is a noop.
@@ -4231,6 +5692,7 @@ mod tests {
                 is_shared_library: true,
                 name: "foo".to_string(),
                 in_memory_shared_library: Some(FileData::Memory(vec![40])),
+                in_memory_shared_library_filename: Some(PathBuf::from("libfoo.so")),
                 ..PrePackagedResource::default()
             })
         );
@@ -4354,6 +5816,7 @@ mod tests {
                 is_python_module: true,
                 name: Cow::Owned("foo".to_string()),
                 is_python_package: true,
+                is_python_namespace_package: true,
                 ..Resource::default()
             })
         );
@@ -4586,6 +6049,8 @@ mod tests {
             optimize_level_zero: false,
             optimize_level_one: false,
             optimize_level_two: false,
+            compression: ResourceCompression::None,
+            bytecode_stripping: BytecodeStripping::default(),
         };
 
         // include=false is a noop.
@@ -4684,4 +6149,371 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_find_dynamic_imports() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+        );
+
+        for (name, source) in [
+            ("app", "import importlib\nimportlib.import_module('json')\n"),
+            ("plain", "import os\n"),
+        ] {
+            r.add_python_module_source(
+                &PythonModuleSource {
+                    name: name.to_string(),
+                    source: FileData::Memory(source.as_bytes().to_vec()),
+                    is_package: false,
+                    cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+                &ConcreteResourceLocation::InMemory,
+            )?;
+        }
+
+        r.add_python_module_bytecode_from_source(
+            &PythonModuleBytecodeFromSource {
+                name: "pkg.loader".to_string(),
+                source: FileData::Memory(Vec::from(
+                    "importlib.import_module('.plugin', __package__)\n__import__(name)\n",
+                )),
+                optimize_level: BytecodeOptimizationLevel::Zero,
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        let imports = r.find_dynamic_imports()?;
+        assert_eq!(
+            imports.keys().collect::<Vec<_>>(),
+            vec!["app", "pkg.loader"]
+        );
+        assert_eq!(
+            imports["app"],
+            vec![DynamicImport::Module("json".to_string())]
+        );
+        assert_eq!(
+            imports["pkg.loader"],
+            vec![
+                DynamicImport::Module("pkg.plugin".to_string()),
+                DynamicImport::Computed("__import__".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_imports() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+        );
+
+        for (name, is_package, source) in [
+            ("app", false, "import json\nfrom mylib import helper\n"),
+            ("json", true, "from .decoder import JSONDecoder\n"),
+            ("json.decoder", false, "import re\n"),
+            ("json.tool", false, "import argparse\n"),
+            ("mylib.helper", false, "import pickle\n"),
+            ("pickle", false, "from _pickle import Pickler\n"),
+            ("copyreg", false, ""),
+            ("tkinter", true, "import _tkinter\n"),
+        ] {
+            r.add_python_module_source(
+                &PythonModuleSource {
+                    name: name.to_string(),
+                    source: FileData::Memory(source.as_bytes().to_vec()),
+                    is_package,
+                    cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+                &ConcreteResourceLocation::InMemory,
+            )?;
+        }
+
+        let analysis = r.analyze_imports(&BTreeSet::from(["app".to_string()]))?;
+        assert_eq!(
+            analysis.reachable,
+            [
+                "app",
+                "copyreg",
+                "json",
+                "json.decoder",
+                "mylib.helper",
+                "pickle"
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+        );
+        assert_eq!(
+            analysis.unreachable,
+            BTreeSet::from(["json.tool".to_string(), "tkinter".to_string()])
+        );
+        assert!(analysis.opaque.is_empty());
+
+        // Package roots make all package members reachable.
+        let analysis =
+            r.analyze_imports(&BTreeSet::from(["app".to_string(), "json".to_string()]))?;
+        assert_eq!(
+            analysis.unreachable,
+            BTreeSet::from(["tkinter".to_string()])
+        );
+
+        r.remove_unreachable_modules(&analysis);
+        assert!(!r.resources.contains_key("tkinter"));
+        assert!(r.resources.contains_key("json.tool"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_override_resource() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![
+                AbstractResourceLocation::InMemory,
+                AbstractResourceLocation::RelativePath,
+            ],
+            vec![],
+            false,
+            false,
+        );
+
+        r.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "pkg".to_string(),
+                relative_name: "data/config.json".to_string(),
+                data: FileData::Memory(vec![1]),
+                is_stdlib: false,
+                is_test: false,
+                locale: None,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        // Overrides apply to resources already collected.
+        r.override_resource("pkg/data/config.json", FileData::Memory(vec![2]))?;
+        assert!(r
+            .override_resource("pkg/data/config.json", FileData::Memory(vec![3]))
+            .is_err());
+        assert!(r
+            .override_resource("pkg/../etc", FileData::Memory(vec![3]))
+            .is_err());
+
+        assert_eq!(
+            r.resources.get("pkg").unwrap().in_memory_resources,
+            Some(BTreeMap::from_iter([(
+                "data/config.json".to_string(),
+                FileData::Memory(vec![2])
+            )]))
+        );
+
+        // And to resources collected later.
+        r.override_resource("pkg/mod.py", FileData::Memory(vec![4]))?;
+        assert!(!r.resource_overrides()[1].applied);
+        assert!(r.check_resource_overrides().is_err());
+
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "pkg.mod".to_string(),
+                source: FileData::Memory(vec![5]),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::RelativePath("lib".to_string()),
+        )?;
+        r.add_python_module_bytecode(
+            &PythonModuleBytecode::new(
+                "pkg.mod",
+                BytecodeOptimizationLevel::Zero,
+                false,
+                DEFAULT_CACHE_TAG,
+                &[6],
+            ),
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        assert!(r.resource_overrides().iter().all(|o| o.applied));
+        r.check_resource_overrides()?;
+
+        let entry = r.resources.get("pkg.mod").unwrap();
+        assert_eq!(
+            entry.relative_path_module_source,
+            Some(("lib".to_string(), FileData::Memory(vec![4])))
+        );
+        assert_eq!(
+            entry.in_memory_bytecode,
+            Some(PythonModuleBytecodeProvider::FromSource(FileData::Memory(
+                vec![4]
+            )))
+        );
+
+        r.override_resource("pkg/missing.txt", FileData::Memory(vec![7]))?;
+        assert!(r.check_resource_overrides().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_relative_path() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![AbstractResourceLocation::InMemory],
+            false,
+            false,
+        );
+
+        r.add_python_module_bytecode_from_source(
+            &PythonModuleBytecodeFromSource {
+                name: "foo.bar".to_string(),
+                source: FileData::Memory(vec![42]),
+                optimize_level: BytecodeOptimizationLevel::Zero,
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "foo".to_string(),
+                relative_name: "data/resource.txt".to_string(),
+                data: FileData::Memory(vec![42]),
+                is_stdlib: false,
+                is_test: false,
+                locale: None,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_package_distribution_resource(
+            &PythonPackageDistributionResource {
+                location: PythonPackageDistributionResourceFlavor::DistInfo,
+                package: "foo".to_string(),
+                version: "1.0".to_string(),
+                name: "METADATA".to_string(),
+                data: FileData::Memory(b"Name: foo\nVersion: 1.0\n".to_vec()),
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_extension_module(
+            &PythonExtensionModule {
+                name: "foo.ext".to_string(),
+                init_fn: Some("PyInit_ext".to_string()),
+                extension_file_suffix: ".so".to_string(),
+                shared_library: Some(FileData::Memory(vec![42])),
+                object_file_data: vec![],
+                is_package: false,
+                link_libraries: vec![LibraryDependency {
+                    name: "mylib".to_string(),
+                    static_library: None,
+                    static_filename: None,
+                    dynamic_library: Some(FileData::Memory(vec![40])),
+                    dynamic_filename: Some(PathBuf::from("libmylib.so")),
+                    framework: false,
+                    system: false,
+                }],
+                is_stdlib: false,
+                builtin_default: false,
+                required: false,
+                variant: None,
+                license: None,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        let r = r.to_relative_path(
+            "lib/python3.9",
+            DEFAULT_CACHE_TAG,
+            ".cpython-39-x86_64-linux-gnu.so",
+        )?;
+
+        assert!(r
+            .iter_resources()
+            .all(|(_, resource)| resource.in_memory_bytecode.is_none()
+                && resource.in_memory_resources.is_none()
+                && resource.in_memory_distribution_resources.is_none()
+                && resource.in_memory_extension_module_shared_library.is_none()
+                && resource.in_memory_shared_library.is_none()));
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+        let resources = r.compile_resources(&mut compiler)?;
+
+        assert_eq!(
+            resources
+                .extra_files
+                .iter()
+                .map(|(path, _, executable)| (path.clone(), *executable))
+                .collect::<BTreeSet<_>>(),
+            BTreeSet::from([
+                (
+                    PathBuf::from("lib/python3.9/foo/__pycache__/__init__.cpython-39.pyc"),
+                    false
+                ),
+                (
+                    PathBuf::from("lib/python3.9/foo/__pycache__/bar.cpython-39.pyc"),
+                    false
+                ),
+                (PathBuf::from("lib/python3.9/foo/data/resource.txt"), false),
+                (
+                    PathBuf::from("lib/python3.9/foo-1.0.dist-info/METADATA"),
+                    false
+                ),
+                (
+                    PathBuf::from("lib/python3.9/foo/ext.cpython-39-x86_64-linux-gnu.so"),
+                    true
+                ),
+                (PathBuf::from("lib/python3.9/foo/libmylib.so"), true),
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_relative_path_localized_resources() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+        );
+
+        r.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "foo".to_string(),
+                relative_name: "resource.txt".to_string(),
+                data: FileData::Memory(vec![42]),
+                is_stdlib: false,
+                is_test: false,
+                locale: Some("de".to_string()),
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        assert_eq!(
+            format!(
+                "{:#}",
+                r.to_relative_path("lib", DEFAULT_CACHE_TAG, ".so")
+                    .unwrap_err()
+            ),
+            "converting foo to relative path: foo has localized resources, which can only be loaded from memory"
+        );
+
+        Ok(())
+    }
 }
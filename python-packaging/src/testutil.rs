@@ -8,7 +8,7 @@
 
 use {
     crate::{
-        bytecode::{CompileMode, PythonBytecodeCompiler},
+        bytecode::{BytecodeStripping, CompileMode, PythonBytecodeCompiler},
         resource::BytecodeOptimizationLevel,
     },
     anyhow::Result,
@@ -42,4 +42,23 @@ impl PythonBytecodeCompiler for FakeBytecodeCompiler {
 
         Ok(res)
     }
+
+    fn compile_stripped(
+        &mut self,
+        source: &[u8],
+        filename: &str,
+        optimize: BytecodeOptimizationLevel,
+        strip: BytecodeStripping,
+        output_mode: CompileMode,
+    ) -> Result<Vec<u8>> {
+        let mut res = Vec::new();
+
+        if !strip.is_none() {
+            res.extend(format!("strip-{}:", strip).as_bytes());
+        }
+
+        res.extend(self.compile(source, filename, optimize, output_mode)?);
+
+        Ok(res)
+    }
 }
@@ -55,6 +55,46 @@ impl WheelArchive {
             .as_str()
             .to_string();
 
+        let files = Self::read_files(reader)?;
+
+        Ok(Self {
+            files,
+            name_version,
+        })
+    }
+
+    /// Construct an instance from a generic reader without knowing the wheel's filename.
+    ///
+    /// The info directory is located by looking for a top-level
+    /// `*.dist-info/WHEEL` file in the archive.
+    pub fn from_reader_unnamed<R>(reader: R) -> Result<Self>
+    where
+        R: std::io::Read + std::io::Seek,
+    {
+        let files = Self::read_files(reader)?;
+
+        let name_version = files
+            .iter_files()
+            .find_map(|f| {
+                f.path()
+                    .to_str()
+                    .and_then(|p| p.strip_suffix(".dist-info/WHEEL"))
+                    .filter(|p| !p.contains('/'))
+                    .map(|p| p.to_string())
+            })
+            .ok_or_else(|| anyhow!("could not find .dist-info/WHEEL in wheel"))?;
+
+        Ok(Self {
+            files,
+            name_version,
+        })
+    }
+
+    /// Read files from a zip archive into a [FileManifest].
+    fn read_files<R>(reader: R) -> Result<FileManifest>
+    where
+        R: std::io::Read + std::io::Seek,
+    {
         let mut archive = ZipArchive::new(reader)?;
 
         let mut files = FileManifest::default();
@@ -76,10 +116,7 @@ impl WheelArchive {
             )?;
         }
 
-        Ok(Self {
-            files,
-            name_version,
-        })
+        Ok(files)
     }
 
     /// Construct an instance from a filesystem path.
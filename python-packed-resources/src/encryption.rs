@@ -0,0 +1,171 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Encryption of packed resources data.
+
+Encrypted data consists of the [ENCRYPTED_HEADER], a 12 byte nonce, and
+a complete resources payload encrypted with AES-256-GCM followed by its
+16 byte authentication tag. The header is authenticated as associated data.
+
+The nonce is derived from the key and the plaintext so encrypting the same
+data with the same key is deterministic. This only reveals whether two
+payloads are identical.
+*/
+
+use {
+    crate::serialization::{ENCRYPTED_HEADER, ENCRYPTION_KEY_LENGTH},
+    ring::{aead, hmac},
+    sha2::{Digest, Sha256},
+};
+
+/// Length in bytes of the nonce stored in encrypted data.
+const NONCE_LENGTH: usize = 12;
+
+fn new_key(key: &[u8; ENCRYPTION_KEY_LENGTH]) -> Result<aead::LessSafeKey, &'static str> {
+    let key = aead::UnboundKey::new(&aead::AES_256_GCM, key)
+        .map_err(|_| "invalid resources encryption key")?;
+
+    Ok(aead::LessSafeKey::new(key))
+}
+
+/// Encrypt resources data with a key.
+pub fn encrypt_packed_resources(
+    data: &[u8],
+    key: &[u8; ENCRYPTION_KEY_LENGTH],
+) -> Result<Vec<u8>, &'static str> {
+    // The nonce is keyed with a key derived from, but distinct from, the
+    // encryption key.
+    let nonce_key = Sha256::new()
+        .chain_update(b"pyembed-nonce")
+        .chain_update(key)
+        .finalize();
+    let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &nonce_key), data);
+
+    let mut nonce = [0u8; NONCE_LENGTH];
+    nonce.copy_from_slice(&tag.as_ref()[0..NONCE_LENGTH]);
+
+    let mut in_out = data.to_vec();
+    new_key(key)?
+        .seal_in_place_append_tag(
+            aead::Nonce::assume_unique_for_key(nonce),
+            aead::Aad::from(ENCRYPTED_HEADER),
+            &mut in_out,
+        )
+        .map_err(|_| "error encrypting resources data")?;
+
+    let mut res = Vec::with_capacity(ENCRYPTED_HEADER.len() + NONCE_LENGTH + in_out.len());
+    res.extend_from_slice(ENCRYPTED_HEADER);
+    res.extend_from_slice(&nonce);
+    res.extend_from_slice(&in_out);
+
+    Ok(res)
+}
+
+/// Decrypt encrypted resources data with a key.
+///
+/// Errors if the data isn't encrypted, the key is wrong, or the data was modified.
+pub fn decrypt_packed_resources(
+    data: &[u8],
+    key: &[u8; ENCRYPTION_KEY_LENGTH],
+) -> Result<Vec<u8>, &'static str> {
+    let data = data
+        .strip_prefix(ENCRYPTED_HEADER)
+        .ok_or("resources data is not encrypted")?;
+
+    if data.len() < NONCE_LENGTH + aead::AES_256_GCM.tag_len() {
+        return Err("encrypted resources data is truncated");
+    }
+
+    let mut nonce = [0u8; NONCE_LENGTH];
+    nonce.copy_from_slice(&data[0..NONCE_LENGTH]);
+
+    let mut in_out = data[NONCE_LENGTH..].to_vec();
+    let plaintext_len = new_key(key)?
+        .open_in_place(
+            aead::Nonce::assume_unique_for_key(nonce),
+            aead::Aad::from(ENCRYPTED_HEADER),
+            &mut in_out,
+        )
+        .map_err(|_| "unable to decrypt resources data; wrong key or modified data")?
+        .len();
+    in_out.truncate(plaintext_len);
+
+    Ok(in_out)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{load_resources, write_packed_resources_v3, Resource},
+    };
+
+    #[test]
+    fn test_encrypt_roundtrip() -> Result<(), &'static str> {
+        let resource = Resource {
+            name: "foo".into(),
+            is_python_module: true,
+            in_memory_source: Some(b"import sys".to_vec().into()),
+            ..Resource::default()
+        };
+
+        let mut data = vec![];
+        write_packed_resources_v3(std::slice::from_ref(&resource), &mut data, None)
+            .map_err(|_| "error writing resources")?;
+
+        let key = [42u8; ENCRYPTION_KEY_LENGTH];
+        let encrypted = encrypt_packed_resources(&data, &key)?;
+
+        assert!(crate::is_encrypted(&encrypted));
+        assert!(!encrypted
+            .windows(b"import sys".len())
+            .any(|w| w == b"import sys"));
+        assert_eq!(encrypt_packed_resources(&data, &key)?, encrypted);
+        assert_eq!(
+            load_resources(&encrypted).err(),
+            Some("resources data is encrypted and must be decrypted before parsing")
+        );
+
+        let decrypted = decrypt_packed_resources(&encrypted, &key)?;
+        assert_eq!(decrypted, data);
+
+        let resources = load_resources(&decrypted)?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(resources, vec![resource]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decrypt_errors() -> Result<(), &'static str> {
+        let key = [42u8; ENCRYPTION_KEY_LENGTH];
+        let encrypted = encrypt_packed_resources(b"pyembed\x03", &key)?;
+
+        assert_eq!(
+            decrypt_packed_resources(b"pyembed\x03", &key),
+            Err("resources data is not encrypted")
+        );
+        assert_eq!(
+            decrypt_packed_resources(&encrypted[0..ENCRYPTED_HEADER.len() + 4], &key),
+            Err("encrypted resources data is truncated")
+        );
+        assert_eq!(
+            decrypt_packed_resources(&encrypted, &[0u8; ENCRYPTION_KEY_LENGTH]),
+            Err("unable to decrypt resources data; wrong key or modified data")
+        );
+
+        let mut modified = encrypted;
+        let last = modified.len() - 1;
+        modified[last] ^= 0x01;
+        assert_eq!(
+            decrypt_packed_resources(&modified, &key),
+            Err("unable to decrypt resources data; wrong key or modified data")
+        );
+
+        Ok(())
+    }
+}
@@ -29,14 +29,29 @@ it can be used outside the PyOxidizer project. See the aforementioned docs
 for the canonical specification of this format.
 */
 
+#[cfg(feature = "encryption")]
+mod encryption;
 mod parser;
 mod resource;
 mod serialization;
 mod writer;
 
 pub use crate::{
-    parser::{load_resources, ResourceParserIterator},
+    parser::{
+        find_executable_overlay, find_resource, is_encrypted, load_resources,
+        ResourceParserIterator,
+    },
     resource::Resource,
-    serialization::HEADER_V3,
-    writer::write_packed_resources_v3,
+    serialization::{
+        DataCompression, ENCRYPTED_HEADER, ENCRYPTION_KEY_LENGTH, HEADER_V3, HEADER_V4,
+        OVERLAY_TRAILER_LENGTH, OVERLAY_TRAILER_MAGIC, PAYLOAD_ALIGNMENT,
+    },
+    writer::{
+        write_executable_overlay, write_packed_resources_v3,
+        write_packed_resources_v3_with_dictionaries, write_packed_resources_v4,
+        write_packed_resources_v4_with_dictionaries,
+    },
 };
+
+#[cfg(feature = "encryption")]
+pub use crate::encryption::{decrypt_packed_resources, encrypt_packed_resources};
@@ -11,7 +11,11 @@
 use {
     crate::{
         resource::Resource,
-        serialization::{BlobInteriorPadding, BlobSectionField, ResourceField, HEADER_V3},
+        serialization::{
+            BlobInteriorPadding, BlobSectionField, DataCompression, ResourceField,
+            CONTENT_DIGEST_LENGTH, ENCRYPTED_HEADER, HEADER_V3, HEADER_V4, OVERLAY_TRAILER_LENGTH,
+            OVERLAY_TRAILER_MAGIC,
+        },
     },
     byteorder::{LittleEndian, ReadBytesExt},
     std::{borrow::Cow, collections::HashMap, io::Cursor, path::Path},
@@ -48,6 +52,14 @@ pub struct ResourceParserIterator<'a> {
     blob_sections: [Option<BlobSectionReadState>; 256],
     claimed_resources_count: usize,
     read_resources_count: usize,
+    /// Whether index entries record the offset of each blob (version 4).
+    blob_offsets: bool,
+    /// Digest of the blobs of the current resource, if verifying digests.
+    hasher: Option<blake3::Hasher>,
+    /// Offset of the lookup table of index entries sorted by name (version 4).
+    lookup_table_offset: usize,
+    /// Payload of the compression dictionaries blob section.
+    compression_dictionaries: &'a [u8],
 }
 
 impl<'a> ResourceParserIterator<'a> {
@@ -56,44 +68,117 @@ impl<'a> ResourceParserIterator<'a> {
         self.claimed_resources_count
     }
 
+    /// Obtain the zstd compression dictionaries stored in the data.
+    ///
+    /// Dictionaries are referenced by the dictionary ID in the zstd frame
+    /// header of data compressed with [DataCompression::ZstdDictionary].
+    pub fn compression_dictionaries(&self) -> Result<Vec<&'a [u8]>, &'static str> {
+        let mut dictionaries = vec![];
+        let mut reader = Cursor::new(self.compression_dictionaries);
+
+        while (reader.position() as usize) < self.compression_dictionaries.len() {
+            let start = reader.position() as usize + 4;
+            let length = reader
+                .read_u32::<LittleEndian>()
+                .map_err(|_| "failed reading compression dictionary length")?
+                as usize;
+
+            dictionaries.push(
+                self.compression_dictionaries
+                    .get(start..start + length)
+                    .ok_or("compression dictionary out of bounds")?,
+            );
+            reader.set_position((start + length) as u64);
+        }
+
+        Ok(dictionaries)
+    }
+
+    /// Set whether to verify the content digest of each resource.
+    ///
+    /// When enabled, all data referenced by a resource is hashed and the
+    /// iterator emits an error if it doesn't match the recorded digest.
+    /// Only version 4 data has digests.
+    pub fn verify_content_digests(&mut self, verify: bool) -> Result<(), &'static str> {
+        if verify && !self.blob_offsets {
+            return Err("data format does not support content digests");
+        }
+
+        self.hasher = if verify {
+            Some(blake3::Hasher::new())
+        } else {
+            None
+        };
+
+        Ok(())
+    }
+
     /// Resolve a slice to an individual blob's data.
     ///
-    /// This accepts a reference to the original blobs payload, an array of
-    /// current blob section offsets, the resource field being accessed, and the
-    /// length of the blob and returns a slice to that blob.
-    fn resolve_blob_data(&mut self, resource_field: ResourceField, length: usize) -> &'a [u8] {
-        let mut state = self.blob_sections[resource_field as usize]
-            .as_mut()
-            .expect("blob state not found");
-
-        let blob = &self.data[state.offset..state.offset + length];
-
-        let increment = match &state.interior_padding {
-            BlobInteriorPadding::None => length,
-            BlobInteriorPadding::Null => length + 1,
+    /// This accepts the resource field being accessed and the length of the
+    /// blob and returns a slice to that blob. Version 4 data records the
+    /// blob's offset in the index. Otherwise the offset is tracked per blob
+    /// section.
+    fn resolve_blob_data(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<&'a [u8], &'static str> {
+        let offset = if self.blob_offsets {
+            self.reader
+                .read_u64::<LittleEndian>()
+                .map_err(|_| "failed reading blob offset")? as usize
+        } else {
+            let state = self.blob_sections[resource_field as usize]
+                .as_mut()
+                .ok_or("blob section not found")?;
+
+            let offset = state.offset;
+
+            state.offset += match &state.interior_padding {
+                BlobInteriorPadding::None => length,
+                BlobInteriorPadding::Null => length + 1,
+            };
+
+            offset
         };
 
-        state.offset += increment;
+        let blob = offset
+            .checked_add(length)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or("blob data out of bounds")?;
+
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(blob);
+        }
 
-        blob
+        Ok(blob)
     }
 
     #[cfg(unix)]
-    fn resolve_path(&mut self, resource_field: ResourceField, length: usize) -> Cow<'a, Path> {
-        let path_str = OsStr::from_bytes(self.resolve_blob_data(resource_field, length));
-        Cow::Borrowed(Path::new(path_str))
+    fn resolve_path(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<Cow<'a, Path>, &'static str> {
+        let path_str = OsStr::from_bytes(self.resolve_blob_data(resource_field, length)?);
+        Ok(Cow::Borrowed(Path::new(path_str)))
     }
 
     #[cfg(windows)]
-    fn resolve_path(&mut self, resource_field: ResourceField, length: usize) -> Cow<'a, Path> {
-        let raw = self.resolve_blob_data(resource_field, length);
+    fn resolve_path(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<Cow<'a, Path>, &'static str> {
+        let raw = self.resolve_blob_data(resource_field, length)?;
         let raw = unsafe { std::slice::from_raw_parts(raw.as_ptr() as *const u16, raw.len() / 2) };
 
         // There isn't an API that lets us get a OsStr from &[u16]. So we need to use
         // owned types.
         let path_string = OsString::from_wide(raw);
 
-        Cow::Owned(PathBuf::from(path_string))
+        Ok(Cow::Owned(PathBuf::from(path_string)))
     }
 
     fn parse_next(&mut self) -> Result<Option<Resource<'a, u8>>, &'static str> {
@@ -122,8 +207,16 @@ impl<'a> ResourceParserIterator<'a> {
                     self.read_resources_count += 1;
                     current_resource = Resource::default();
                     current_resource_name = None;
+
+                    if let Some(hasher) = &mut self.hasher {
+                        hasher.reset();
+                    }
                 }
                 ResourceField::EndOfEntry => {
+                    if self.hasher.is_some() && current_resource.content_digest.is_none() {
+                        return Err("resource content digest is missing");
+                    }
+
                     let res = if current_resource_name.is_some() {
                         Ok(Some(current_resource))
                     } else {
@@ -140,7 +233,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     let name = unsafe {
-                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l))
+                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l)?)
                     };
 
                     current_resource_name = Some(name);
@@ -160,7 +253,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_source =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
                 ResourceField::InMemoryBytecode => {
                     let l = self
@@ -170,7 +263,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_bytecode =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
                 ResourceField::InMemoryBytecodeOpt1 => {
                     let l = self
@@ -180,7 +273,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_bytecode_opt1 =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
                 ResourceField::InMemoryBytecodeOpt2 => {
                     let l = self
@@ -190,7 +283,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_bytecode_opt2 =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
                 ResourceField::InMemoryExtensionModuleSharedLibrary => {
                     let l = self
@@ -200,7 +293,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_extension_module_shared_library =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
 
                 ResourceField::InMemoryResourcesData => {
@@ -221,7 +314,7 @@ impl<'a> ResourceParserIterator<'a> {
 
                         let resource_name = unsafe {
                             std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, resource_name_length),
+                                self.resolve_blob_data(field_type, resource_name_length)?,
                             )
                         };
 
@@ -231,7 +324,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading resource length")?
                             as usize;
 
-                        let resource_data = self.resolve_blob_data(field_type, resource_length);
+                        let resource_data = self.resolve_blob_data(field_type, resource_length)?;
 
                         resources
                             .insert(Cow::Borrowed(resource_name), Cow::Borrowed(resource_data));
@@ -240,6 +333,68 @@ impl<'a> ResourceParserIterator<'a> {
                     current_resource.in_memory_package_resources = Some(resources);
                 }
 
+                ResourceField::InMemoryLocalizedResourcesData => {
+                    let locale_count = self
+                        .reader
+                        .read_u32::<LittleEndian>()
+                        .map_err(|_| "failed reading localized resources length")?
+                        as usize;
+
+                    let mut locales = HashMap::with_capacity(locale_count);
+
+                    for _ in 0..locale_count {
+                        let locale_length = self
+                            .reader
+                            .read_u16::<LittleEndian>()
+                            .map_err(|_| "failed reading locale name length")?
+                            as usize;
+
+                        let locale = unsafe {
+                            std::str::from_utf8_unchecked(
+                                self.resolve_blob_data(field_type, locale_length)?,
+                            )
+                        };
+
+                        let resource_count = self
+                            .reader
+                            .read_u32::<LittleEndian>()
+                            .map_err(|_| "failed reading localized resources count")?
+                            as usize;
+
+                        let mut resources = HashMap::with_capacity(resource_count);
+
+                        for _ in 0..resource_count {
+                            let resource_name_length = self
+                                .reader
+                                .read_u16::<LittleEndian>()
+                                .map_err(|_| "failed reading localized resource name")?
+                                as usize;
+
+                            let resource_name = unsafe {
+                                std::str::from_utf8_unchecked(
+                                    self.resolve_blob_data(field_type, resource_name_length)?,
+                                )
+                            };
+
+                            let resource_length = self
+                                .reader
+                                .read_u64::<LittleEndian>()
+                                .map_err(|_| "failed reading localized resource length")?
+                                as usize;
+
+                            let resource_data =
+                                self.resolve_blob_data(field_type, resource_length)?;
+
+                            resources
+                                .insert(Cow::Borrowed(resource_name), Cow::Borrowed(resource_data));
+                        }
+
+                        locales.insert(Cow::Borrowed(locale), resources);
+                    }
+
+                    current_resource.in_memory_localized_package_resources = Some(locales);
+                }
+
                 ResourceField::InMemoryDistributionResource => {
                     let resource_count = self
                         .reader
@@ -258,7 +413,7 @@ impl<'a> ResourceParserIterator<'a> {
 
                         let name = unsafe {
                             std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
+                                self.resolve_blob_data(field_type, name_length)?,
                             )
                         };
 
@@ -267,7 +422,7 @@ impl<'a> ResourceParserIterator<'a> {
                                 "failed reading package distribution resource length"
                             })? as usize;
 
-                        let resource_data = self.resolve_blob_data(field_type, resource_length);
+                        let resource_data = self.resolve_blob_data(field_type, resource_length)?;
 
                         resources.insert(Cow::Borrowed(name), Cow::Borrowed(resource_data));
                     }
@@ -283,7 +438,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_shared_library =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
 
                 ResourceField::SharedLibraryDependencyNames => {
@@ -303,7 +458,7 @@ impl<'a> ResourceParserIterator<'a> {
 
                         let name = unsafe {
                             std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
+                                self.resolve_blob_data(field_type, name_length)?,
                             )
                         };
 
@@ -320,7 +475,7 @@ impl<'a> ResourceParserIterator<'a> {
                         .map_err(|_| "failed reading Python module relative path length")?
                         as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_source = Some(path);
                 }
@@ -331,7 +486,7 @@ impl<'a> ResourceParserIterator<'a> {
                             "failed reading Python module bytecode relative path length"
                         })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode = Some(path);
                 }
@@ -341,7 +496,7 @@ impl<'a> ResourceParserIterator<'a> {
                         "failed reading Python module bytecode opt 1 relative path length"
                     })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode_opt1 = Some(path);
                 }
@@ -351,7 +506,7 @@ impl<'a> ResourceParserIterator<'a> {
                         "failed reading Python module bytecode opt 2 relative path length"
                     })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode_opt2 = Some(path);
                 }
@@ -361,7 +516,7 @@ impl<'a> ResourceParserIterator<'a> {
                         "failed reading Python extension module shared library relative path length"
                     })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_extension_module_shared_library = Some(path);
                 }
@@ -383,7 +538,7 @@ impl<'a> ResourceParserIterator<'a> {
 
                         let resource_name = unsafe {
                             std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, resource_name_length),
+                                self.resolve_blob_data(field_type, resource_name_length)?,
                             )
                         };
 
@@ -393,7 +548,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading resource path length")?
                             as usize;
 
-                        let path = self.resolve_path(field_type, path_length);
+                        let path = self.resolve_path(field_type, path_length)?;
 
                         resources.insert(Cow::Borrowed(resource_name), path);
                     }
@@ -417,7 +572,7 @@ impl<'a> ResourceParserIterator<'a> {
 
                         let name = unsafe {
                             std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
+                                self.resolve_blob_data(field_type, name_length)?,
                             )
                         };
 
@@ -427,7 +582,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading package distribution path length")?
                             as usize;
 
-                        let path = self.resolve_path(field_type, path_length);
+                        let path = self.resolve_path(field_type, path_length)?;
 
                         resources.insert(Cow::Borrowed(name), path);
                     }
@@ -471,7 +626,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.file_data_embedded =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
 
                 ResourceField::FileDataUtf8RelativePath => {
@@ -482,7 +637,60 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.file_data_utf8_relative_path = Some(Cow::Borrowed(unsafe {
-                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l))
+                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l)?)
+                    }));
+                }
+
+                ResourceField::InMemoryBytecodeCompression => {
+                    let value = self
+                        .reader
+                        .read_u8()
+                        .map_err(|_| "failed reading bytecode compression value")?;
+
+                    current_resource.in_memory_bytecode_compression =
+                        Some(DataCompression::try_from(value)?);
+                }
+
+                ResourceField::InMemoryResourcesCompression => {
+                    let value = self
+                        .reader
+                        .read_u8()
+                        .map_err(|_| "failed reading package resources compression value")?;
+
+                    current_resource.in_memory_package_resources_compression =
+                        Some(DataCompression::try_from(value)?);
+                }
+
+                ResourceField::CompressionDictionary => {
+                    return Err("compression dictionaries are not resource fields");
+                }
+
+                ResourceField::ContentDigest => {
+                    let mut digest = [0u8; CONTENT_DIGEST_LENGTH];
+                    std::io::Read::read_exact(&mut self.reader, &mut digest)
+                        .map_err(|_| "failed reading content digest")?;
+
+                    if let Some(hasher) = &mut self.hasher {
+                        let matches = hasher.finalize().as_bytes() == &digest;
+                        hasher.reset();
+
+                        if !matches {
+                            return Err("resource content digest mismatch");
+                        }
+                    }
+
+                    current_resource.content_digest = Some(digest);
+                }
+
+                ResourceField::SourceMapPath => {
+                    let l = self
+                        .reader
+                        .read_u32::<LittleEndian>()
+                        .map_err(|_| "failed reading source map path length")?
+                        as usize;
+
+                    current_resource.source_map_path = Some(Cow::Borrowed(unsafe {
+                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l)?)
                     }));
                 }
             }
@@ -522,11 +730,87 @@ pub fn load_resources<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>,
 
     if header == HEADER_V3 {
         load_resources_v3(&data[8..])
+    } else if header == HEADER_V4 {
+        load_resources_v4(data)
+    } else if is_encrypted(data) {
+        Err("resources data is encrypted and must be decrypted before parsing")
     } else {
         Err("unrecognized file format")
     }
 }
 
+/// Find resources data appended to an executable.
+///
+/// `data` is the content of the executable. See [crate::write_executable_overlay()].
+pub fn find_executable_overlay(data: &[u8]) -> Result<&[u8], &'static str> {
+    if !data.ends_with(OVERLAY_TRAILER_MAGIC) || data.len() < OVERLAY_TRAILER_LENGTH {
+        return Err("no resources data appended to executable");
+    }
+
+    let mut reader = Cursor::new(&data[data.len() - OVERLAY_TRAILER_LENGTH..]);
+    let offset = reader
+        .read_u64::<LittleEndian>()
+        .map_err(|_| "failed reading appended resources offset")? as usize;
+    let length = reader
+        .read_u64::<LittleEndian>()
+        .map_err(|_| "failed reading appended resources length")? as usize;
+
+    offset
+        .checked_add(length)
+        .filter(|end| *end <= data.len() - OVERLAY_TRAILER_LENGTH)
+        .map(|end| &data[offset..end])
+        .ok_or("appended resources data out of bounds")
+}
+
+/// Whether data is encrypted resources data.
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(ENCRYPTED_HEADER)
+}
+
+/// Find a resource by name in a packed resources data structure.
+///
+/// Version 4 data is binary searched via its lookup table, so only the index
+/// entries compared against are parsed. Older versions are parsed until the
+/// resource is found.
+pub fn find_resource<'a>(
+    data: &'a [u8],
+    name: &str,
+) -> Result<Option<Resource<'a, u8>>, &'static str> {
+    if data.get(0..8) != Some(HEADER_V4) {
+        for resource in load_resources(data)? {
+            let resource = resource?;
+
+            if resource.name == name {
+                return Ok(Some(resource));
+            }
+        }
+
+        return Ok(None);
+    }
+
+    let mut parser = load_resources_v4(data)?;
+
+    let mut low = 0;
+    let mut high = parser.claimed_resources_count;
+
+    while low < high {
+        let middle = low + (high - low) / 2;
+        let entry_offset = parser.lookup_table_entry(middle)?;
+
+        match parser.entry_name(entry_offset)?.cmp(name.as_bytes()) {
+            std::cmp::Ordering::Less => low = middle + 1,
+            std::cmp::Ordering::Greater => high = middle,
+            std::cmp::Ordering::Equal => {
+                parser.reader.set_position(entry_offset as u64);
+
+                return parser.parse_next();
+            }
+        }
+    }
+
+    Ok(None)
+}
+
 fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &'static str> {
     let mut reader = Cursor::new(data);
 
@@ -596,6 +880,9 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
                         .map_err(|_| "failed reading raw payload length")?;
                     current_blob_raw_payload_length = Some(l as usize);
                 }
+                BlobSectionField::Offset => {
+                    return Err("blob section offsets require version 4");
+                }
                 BlobSectionField::InteriorPadding => {
                     let padding = reader
                         .read_u8()
@@ -627,9 +914,15 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
         ;
     // Current offset from start of blobs data.
     let mut current_blob_offset = 0;
+    let mut compression_dictionaries: &[u8] = &[];
 
     for section in &blob_sections {
         let section_start_offset = blob_start_offset + current_blob_offset;
+        if section.resource_field == u8::from(ResourceField::CompressionDictionary) {
+            compression_dictionaries = data
+                .get(section_start_offset..section_start_offset + section.raw_payload_length)
+                .ok_or("compression dictionaries blob section out of bounds")?;
+        }
         blob_offsets[section.resource_field as usize] = Some(BlobSectionReadState {
             offset: section_start_offset,
             interior_padding: match section.interior_padding {
@@ -647,16 +940,189 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
         blob_sections: blob_offsets,
         claimed_resources_count: resources_count,
         read_resources_count: 0,
+        blob_offsets: false,
+        hasher: None,
+        lookup_table_offset: 0,
+        compression_dictionaries,
+    })
+}
+
+/// Length of the version 4 global header, including the magic.
+const HEADER_V4_LENGTH: usize = 8 + 1 + 4 + 4 + 4;
+
+/// Find the payload of the compression dictionaries blob section of version 4 data.
+///
+/// `index` is the blob index. Sections record their offset, so other entries
+/// are skipped.
+fn find_compression_dictionaries_v4<'a>(
+    data: &'a [u8],
+    index: &[u8],
+) -> Result<&'a [u8], &'static str> {
+    let mut reader = Cursor::new(index);
+    let mut field = None;
+    let mut length = None;
+    let mut offset = None;
+
+    loop {
+        match BlobSectionField::try_from(
+            reader
+                .read_u8()
+                .map_err(|_| "failed reading blob section field type")?,
+        )? {
+            BlobSectionField::EndOfIndex => return Ok(&[]),
+            BlobSectionField::StartOfEntry => {
+                field = None;
+                length = None;
+                offset = None;
+            }
+            BlobSectionField::EndOfEntry => {
+                if field == Some(u8::from(ResourceField::CompressionDictionary)) {
+                    let offset = offset.ok_or("blob section offset is required")?;
+                    let length = length.ok_or("blob raw payload length is required")?;
+
+                    return data
+                        .get(offset..offset + length)
+                        .ok_or("compression dictionaries blob section out of bounds");
+                }
+            }
+            BlobSectionField::ResourceFieldType => {
+                field = Some(
+                    reader
+                        .read_u8()
+                        .map_err(|_| "failed reading blob resource field value")?,
+                );
+            }
+            BlobSectionField::RawPayloadLength => {
+                length = Some(
+                    reader
+                        .read_u64::<LittleEndian>()
+                        .map_err(|_| "failed reading raw payload length")?
+                        as usize,
+                );
+            }
+            BlobSectionField::Offset => {
+                offset = Some(
+                    reader
+                        .read_u64::<LittleEndian>()
+                        .map_err(|_| "failed reading blob section offset")?
+                        as usize,
+                );
+            }
+            BlobSectionField::InteriorPadding => {
+                reader
+                    .read_u8()
+                    .map_err(|_| "failed reading interior padding field value")?;
+            }
+        }
+    }
+}
+
+/// Parse version 4 data.
+///
+/// Unlike earlier versions, offsets are relative to the start of the data,
+/// including the magic header. Index entries record the offset of every blob,
+/// so the blob index is only consulted for the compression dictionaries
+/// section.
+fn load_resources_v4<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &'static str> {
+    let mut reader = Cursor::new(data);
+    reader.set_position(8);
+
+    // Blob section count.
+    reader
+        .read_u8()
+        .map_err(|_| "failed reading blob section count")?;
+    let blob_index_length = reader
+        .read_u32::<LittleEndian>()
+        .map_err(|_| "failed reading blob index length")? as usize;
+    let resources_count = reader
+        .read_u32::<LittleEndian>()
+        .map_err(|_| "failed reading resources count")? as usize;
+    let resources_index_length = reader
+        .read_u32::<LittleEndian>()
+        .map_err(|_| "failed reading resources index length")?
+        as usize;
+
+    let resources_index_start = HEADER_V4_LENGTH + blob_index_length;
+    let lookup_table_offset = resources_index_start + resources_index_length;
+
+    if data.len() < lookup_table_offset + 4 * resources_count {
+        return Err("data too short for resources index");
+    }
+
+    let compression_dictionaries =
+        find_compression_dictionaries_v4(data, &data[HEADER_V4_LENGTH..resources_index_start])?;
+
+    reader.set_position(resources_index_start as u64);
+
+    Ok(ResourceParserIterator {
+        done: resources_index_length == 0 || resources_count == 0,
+        data,
+        reader,
+        blob_sections: [None; 256],
+        claimed_resources_count: resources_count,
+        read_resources_count: 0,
+        blob_offsets: true,
+        hasher: None,
+        lookup_table_offset,
+        compression_dictionaries,
     })
 }
 
+impl<'a> ResourceParserIterator<'a> {
+    /// Obtain the offset of the index entry at a position in the version 4 lookup table.
+    fn lookup_table_entry(&self, index: usize) -> Result<usize, &'static str> {
+        let offset = self.lookup_table_offset + 4 * index;
+        let mut entry = self
+            .data
+            .get(offset..offset + 4)
+            .ok_or("lookup table entry out of bounds")?;
+
+        Ok(entry
+            .read_u32::<LittleEndian>()
+            .map_err(|_| "failed reading lookup table entry")? as usize)
+    }
+
+    /// Obtain the resource name of the version 4 index entry at an offset.
+    ///
+    /// Writers always emit the name as the first field of an entry.
+    fn entry_name(&self, offset: usize) -> Result<&'a [u8], &'static str> {
+        let mut entry = self
+            .data
+            .get(offset..offset + 12)
+            .ok_or("index entry out of bounds")?;
+
+        if entry.read_u8().ok() != Some(ResourceField::StartOfEntry.into())
+            || entry.read_u8().ok() != Some(ResourceField::Name.into())
+        {
+            return Err("index entry does not start with resource name");
+        }
+
+        let length = entry
+            .read_u16::<LittleEndian>()
+            .map_err(|_| "failed reading resource name length")? as usize;
+        let name_offset = entry
+            .read_u64::<LittleEndian>()
+            .map_err(|_| "failed reading resource name offset")? as usize;
+
+        name_offset
+            .checked_add(length)
+            .and_then(|end| self.data.get(name_offset..end))
+            .ok_or("blob data out of bounds")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use {
         super::*,
         crate::{
-            resource::Resource, serialization::BlobInteriorPadding,
-            writer::write_packed_resources_v3,
+            resource::Resource,
+            serialization::{BlobInteriorPadding, PAYLOAD_ALIGNMENT},
+            writer::{
+                write_executable_overlay, write_packed_resources_v3,
+                write_packed_resources_v3_with_dictionaries, write_packed_resources_v4,
+                write_packed_resources_v4_with_dictionaries,
+            },
         },
     };
 
@@ -674,7 +1140,7 @@ mod tests {
         let res = load_resources(data);
         assert_eq!(res.err(), Some("unrecognized file format"));
 
-        let data = b"pyembed\x04";
+        let data = b"pyembed\x05";
         let res = load_resources(data);
         assert_eq!(res.err(), Some("unrecognized file format"));
     }
@@ -1038,6 +1504,49 @@ mod tests {
         assert_eq!(resources.get("another").unwrap().as_ref(), b"value2");
     }
 
+    #[test]
+    fn test_in_memory_localized_package_resources() {
+        let mut de = HashMap::new();
+        de.insert(Cow::from("messages.txt"), Cow::from(b"hallo".to_vec()));
+        de.insert(Cow::from("other.txt"), Cow::from(b"andere".to_vec()));
+        let mut fr = HashMap::new();
+        fr.insert(Cow::from("messages.txt"), Cow::from(b"bonjour".to_vec()));
+
+        let mut locales = HashMap::new();
+        locales.insert(Cow::from("de_DE"), de);
+        locales.insert(Cow::from("fr"), fr);
+
+        let resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_localized_package_resources: Some(locales),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[resource], &mut data, None).unwrap();
+        let resources = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(resources.len(), 1);
+
+        let entry = &resources[0];
+
+        let locales = entry
+            .in_memory_localized_package_resources
+            .as_ref()
+            .unwrap();
+        assert_eq!(locales.len(), 2);
+        let de = locales.get("de_DE").unwrap();
+        assert_eq!(de.len(), 2);
+        assert_eq!(de.get("messages.txt").unwrap().as_ref(), b"hallo");
+        assert_eq!(de.get("other.txt").unwrap().as_ref(), b"andere");
+        let fr = locales.get("fr").unwrap();
+        assert_eq!(fr.len(), 1);
+        assert_eq!(fr.get("messages.txt").unwrap().as_ref(), b"bonjour");
+    }
+
     #[test]
     fn test_in_memory_package_distribution() {
         let mut resources = HashMap::new();
@@ -1157,6 +1666,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_source_map_path() {
+        let resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_bytecode: Some(Cow::from(b"bytecode".to_vec())),
+            source_map_path: Some(Cow::from("src/foo.py")),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[resource], &mut data, None).unwrap();
+        let resources = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(resources.len(), 1);
+
+        let entry = &resources[0];
+
+        assert_eq!(
+            entry,
+            &Resource {
+                name: Cow::Borrowed("foo"),
+                in_memory_bytecode: Some(Cow::Borrowed(b"bytecode")),
+                source_map_path: Some(Cow::Borrowed("src/foo.py")),
+                ..Resource::default()
+            }
+        );
+    }
+
     #[test]
     fn test_relative_path_module_bytecode() {
         let resource = Resource {
@@ -1346,8 +1886,7 @@ mod tests {
     }
 
     #[allow(clippy::cognitive_complexity)]
-    #[test]
-    fn test_all_fields() {
+    fn all_fields_resource() -> Resource<'static, u8> {
         let mut in_memory_resources = HashMap::new();
         in_memory_resources.insert(
             Cow::from("foo".to_string()),
@@ -1359,6 +1898,11 @@ mod tests {
         in_memory_distribution.insert(Cow::from("dist"), Cow::from(b"distvalue".to_vec()));
         in_memory_distribution.insert(Cow::from("dist2"), Cow::from(b"dist2value".to_vec()));
 
+        let mut localized_resources = HashMap::new();
+        localized_resources.insert(Cow::from("foo"), Cow::from(b"localized".to_vec()));
+        let mut in_memory_localized = HashMap::new();
+        in_memory_localized.insert(Cow::from("de"), localized_resources);
+
         let mut relative_path_resources = HashMap::new();
         relative_path_resources.insert(
             Cow::from("resource.txt"),
@@ -1376,7 +1920,7 @@ mod tests {
             Cow::from(Path::new("package/resource.txt")),
         );
 
-        let resource = Resource {
+        Resource {
             name: Cow::from("module"),
             is_python_package: true,
             is_python_namespace_package: true,
@@ -1386,6 +1930,7 @@ mod tests {
             in_memory_bytecode_opt2: Some(Cow::from(b"bytecodeopt2".to_vec())),
             in_memory_extension_module_shared_library: Some(Cow::from(b"library".to_vec())),
             in_memory_package_resources: Some(in_memory_resources),
+            in_memory_localized_package_resources: Some(in_memory_localized),
             in_memory_distribution_resources: Some(in_memory_distribution),
             in_memory_shared_library: Some(Cow::from(b"library".to_vec())),
             shared_library_dependency_names: Some(vec![Cow::from("libfoo"), Cow::from("depends")]),
@@ -1405,19 +1950,14 @@ mod tests {
             file_executable: true,
             file_data_embedded: Some(Cow::from(b"file_data_embedded".to_vec())),
             file_data_utf8_relative_path: Some(Cow::from("file_data_utf8_relative_path")),
-        };
-
-        let mut data = Vec::new();
-        write_packed_resources_v3(&[resource], &mut data, None).unwrap();
-        let resources = load_resources(&data)
-            .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
-            .unwrap();
-
-        assert_eq!(resources.len(), 1);
-
-        let entry = &resources[0];
+            in_memory_bytecode_compression: Some(DataCompression::Zstd),
+            in_memory_package_resources_compression: Some(DataCompression::Zlib),
+            content_digest: None,
+            source_map_path: Some(Cow::from("src/module/__init__.py")),
+        }
+    }
 
+    fn assert_all_fields(entry: &Resource<u8>) {
         assert!(entry.is_python_package);
         assert!(entry.is_python_namespace_package);
         assert_eq!(entry.in_memory_source.as_ref().unwrap().as_ref(), b"source");
@@ -1447,6 +1987,16 @@ mod tests {
         assert_eq!(resources.get("foo").unwrap().as_ref(), b"foovalue");
         assert_eq!(resources.get("resource2").unwrap().as_ref(), b"value2");
 
+        let locales = entry
+            .in_memory_localized_package_resources
+            .as_ref()
+            .unwrap();
+        assert_eq!(locales.len(), 1);
+        assert_eq!(
+            locales.get("de").unwrap().get("foo").unwrap().as_ref(),
+            b"localized"
+        );
+
         let resources = entry.in_memory_distribution_resources.as_ref().unwrap();
         assert_eq!(resources.len(), 2);
         assert_eq!(resources.get("dist").unwrap().as_ref(), b"distvalue");
@@ -1519,6 +2069,31 @@ mod tests {
             entry.file_data_utf8_relative_path.as_ref().unwrap(),
             "file_data_utf8_relative_path"
         );
+        assert_eq!(
+            entry.source_map_path.as_ref().unwrap(),
+            "src/module/__init__.py"
+        );
+        assert_eq!(
+            entry.in_memory_bytecode_compression,
+            Some(DataCompression::Zstd)
+        );
+        assert_eq!(
+            entry.in_memory_package_resources_compression,
+            Some(DataCompression::Zlib)
+        );
+    }
+
+    #[test]
+    fn test_all_fields() {
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[all_fields_resource()], &mut data, None).unwrap();
+        let resources = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(resources.len(), 1);
+        assert_all_fields(&resources[0]);
     }
 
     #[test]
@@ -1547,4 +2122,207 @@ mod tests {
 
         assert_eq!(resources, loaded);
     }
+
+    #[test]
+    fn test_v4_all_fields() {
+        let mut data = Vec::new();
+        write_packed_resources_v4(&[all_fields_resource()], &mut data).unwrap();
+
+        let mut parser = load_resources(&data).unwrap();
+        parser.verify_content_digests(true).unwrap();
+        let resources = parser
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(resources.len(), 1);
+
+        let entry = &resources[0];
+        assert_all_fields(entry);
+        assert!(entry.content_digest.is_some());
+
+        // Digests can be recomputed from both the original and parsed resource.
+        assert_eq!(
+            Some(all_fields_resource().compute_content_digest().unwrap()),
+            entry.content_digest
+        );
+        assert_eq!(
+            Some(entry.compute_content_digest().unwrap()),
+            entry.content_digest
+        );
+
+        // Payloads are aligned relative to the start of the data.
+        let base = data.as_ptr() as usize;
+        for payload in [
+            entry.in_memory_source.as_ref().unwrap(),
+            entry.in_memory_bytecode.as_ref().unwrap(),
+            entry.in_memory_shared_library.as_ref().unwrap(),
+            entry.file_data_embedded.as_ref().unwrap(),
+            entry
+                .in_memory_package_resources
+                .as_ref()
+                .unwrap()
+                .get("foo")
+                .unwrap(),
+        ] {
+            assert_eq!((payload.as_ptr() as usize - base) % PAYLOAD_ALIGNMENT, 0);
+        }
+    }
+
+    #[test]
+    fn test_v4_fields_mix() {
+        let resources: Vec<Resource<u8>> = vec![
+            Resource {
+                name: Cow::from("foo"),
+                is_python_module: true,
+                in_memory_source: Some(Cow::from(b"import io".to_vec())),
+                ..Resource::default()
+            },
+            Resource {
+                name: Cow::from("bar"),
+                is_python_module: true,
+                in_memory_bytecode: Some(Cow::from(b"fake bytecode".to_vec())),
+                ..Resource::default()
+            },
+        ];
+
+        let mut data = Vec::new();
+        write_packed_resources_v4(&resources, &mut data).unwrap();
+        let loaded = load_resources(&data)
+            .unwrap()
+            .map(|r| {
+                r.map(|mut r| {
+                    r.content_digest = None;
+                    r
+                })
+            })
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(resources, loaded);
+    }
+
+    #[test]
+    fn test_compression_dictionaries() {
+        let resources = vec![all_fields_resource()];
+        let dictionaries: [&[u8]; 2] = [b"dictionary 1", b"dictionary 2"];
+
+        let mut v3 = Vec::new();
+        write_packed_resources_v3_with_dictionaries(&resources, &dictionaries, &mut v3, None)
+            .unwrap();
+        let mut v4 = Vec::new();
+        write_packed_resources_v4_with_dictionaries(&resources, &dictionaries, &mut v4).unwrap();
+
+        for data in [&v3, &v4] {
+            let parser = load_resources(data).unwrap();
+            assert_eq!(parser.compression_dictionaries().unwrap(), dictionaries);
+
+            // Dictionaries aren't exposed as resources.
+            let loaded = parser
+                .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+                .unwrap();
+            assert_eq!(loaded.len(), 1);
+            assert_all_fields(&loaded[0]);
+        }
+
+        let mut data = Vec::new();
+        write_packed_resources_v4(&resources, &mut data).unwrap();
+        assert!(load_resources(&data)
+            .unwrap()
+            .compression_dictionaries()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_v4_content_digest_mismatch() {
+        let resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"import io".to_vec())),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v4(&[resource], &mut data).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0xff;
+
+        // Digests aren't verified by default.
+        load_resources(&data).unwrap().next().unwrap().unwrap();
+
+        let mut parser = load_resources(&data).unwrap();
+        parser.verify_content_digests(true).unwrap();
+        assert_eq!(parser.next(), Some(Err("resource content digest mismatch")));
+    }
+
+    #[test]
+    fn test_verify_content_digests_v3() {
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[all_fields_resource()], &mut data, None).unwrap();
+
+        let mut parser = load_resources(&data).unwrap();
+        assert_eq!(
+            parser.verify_content_digests(true),
+            Err("data format does not support content digests")
+        );
+    }
+
+    #[test]
+    fn test_find_resource() {
+        let resources = (0..100)
+            .map(|i| Resource {
+                name: Cow::Owned(format!("module{}", i)),
+                in_memory_source: Some(Cow::Owned(format!("source{}", i).into_bytes())),
+                ..Resource::default()
+            })
+            .collect::<Vec<Resource<u8>>>();
+
+        let mut v3 = Vec::new();
+        write_packed_resources_v3(&resources, &mut v3, None).unwrap();
+        let mut v4 = Vec::new();
+        write_packed_resources_v4(&resources, &mut v4).unwrap();
+
+        for data in [&v3, &v4] {
+            for i in [0, 1, 42, 99] {
+                let resource = find_resource(data, &format!("module{}", i))
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(resource.name, format!("module{}", i));
+                assert_eq!(
+                    resource.in_memory_source.unwrap().as_ref(),
+                    format!("source{}", i).as_bytes()
+                );
+            }
+
+            assert_eq!(find_resource(data, "missing").unwrap(), None);
+            assert_eq!(find_resource(data, "module100").unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_find_executable_overlay() {
+        assert!(find_executable_overlay(b"executable").is_err());
+
+        let resources = vec![Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"source".to_vec())),
+            ..Resource::default()
+        }];
+        let mut data = Vec::new();
+        write_packed_resources_v4(&resources, &mut data).unwrap();
+
+        let mut exe = b"executable".to_vec();
+        write_executable_overlay(exe.len(), &data, &mut exe).unwrap();
+
+        let overlay = find_executable_overlay(&exe).unwrap();
+        assert_eq!(overlay, data.as_slice());
+        assert_eq!(
+            (overlay.as_ptr() as usize - exe.as_ptr() as usize) % PAYLOAD_ALIGNMENT,
+            0
+        );
+        assert!(find_resource(overlay, "foo").unwrap().is_some());
+
+        let end = exe.len() - OVERLAY_TRAILER_LENGTH;
+        exe[end..end + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(find_executable_overlay(&exe).is_err());
+    }
 }
@@ -6,7 +6,10 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::{borrow::Cow, collections::HashMap, path::Path};
+use {
+    crate::serialization::DataCompression,
+    std::{borrow::Cow, collections::HashMap, path::Path},
+};
 
 /// Represents an indexed resource.
 ///
@@ -74,6 +77,15 @@ where
     /// `importlib.resources` API via in-memory data access.
     pub in_memory_package_resources: Option<HashMap<Cow<'a, str>, Cow<'a, [X]>>>,
 
+    /// Mapping of locale name to a mapping of virtual filename to data for
+    /// resources to expose to Python's `importlib.resources` API when that
+    /// locale is selected.
+    ///
+    /// Values are compressed the same way as `in_memory_package_resources`.
+    #[allow(clippy::type_complexity)]
+    pub in_memory_localized_package_resources:
+        Option<HashMap<Cow<'a, str>, HashMap<Cow<'a, str>, Cow<'a, [X]>>>>,
+
     /// Mapping of virtual filename to data for package distribution metadata
     /// to expose to Python's `importlib.metadata` API via in-memory data access.
     pub in_memory_distribution_resources: Option<HashMap<Cow<'a, str>, Cow<'a, [X]>>>,
@@ -115,6 +127,25 @@ where
 
     /// Holds arbitrary file data in a relative path encoded in UTF-8.
     pub file_data_utf8_relative_path: Option<Cow<'a, str>>,
+
+    /// Compression applied to the `in_memory_bytecode*` fields.
+    pub in_memory_bytecode_compression: Option<DataCompression>,
+
+    /// Compression applied to the values of `in_memory_package_resources`.
+    pub in_memory_package_resources_compression: Option<DataCompression>,
+
+    /// BLAKE3 digest of the data referenced by the serialized resource.
+    ///
+    /// Only populated when parsing data formats having digests. Writers
+    /// compute digests themselves and ignore this field.
+    pub content_digest: Option<[u8; 32]>,
+
+    /// Path of the source file this module was packaged from.
+    ///
+    /// The path is relative to a root chosen at packaging time, typically the
+    /// root of a source repository, and uses `/` as separator. It allows
+    /// rendering tracebacks with paths of the original source files.
+    pub source_map_path: Option<Cow<'a, str>>,
 }
 
 impl<'a, X> Default for Resource<'a, X>
@@ -138,6 +169,7 @@ where
             in_memory_bytecode_opt2: None,
             in_memory_extension_module_shared_library: None,
             in_memory_package_resources: None,
+            in_memory_localized_package_resources: None,
             in_memory_distribution_resources: None,
             in_memory_shared_library: None,
             shared_library_dependency_names: None,
@@ -151,6 +183,10 @@ where
             file_executable: false,
             file_data_embedded: None,
             file_data_utf8_relative_path: None,
+            in_memory_bytecode_compression: None,
+            in_memory_package_resources_compression: None,
+            content_digest: None,
+            source_map_path: None,
         }
     }
 }
@@ -203,6 +239,9 @@ where
         if let Some(value) = other.in_memory_package_resources {
             self.in_memory_package_resources.replace(value);
         }
+        if let Some(value) = other.in_memory_localized_package_resources {
+            self.in_memory_localized_package_resources.replace(value);
+        }
         if let Some(value) = other.in_memory_distribution_resources {
             self.in_memory_distribution_resources.replace(value);
         }
@@ -243,6 +282,17 @@ where
         if let Some(value) = other.file_data_utf8_relative_path {
             self.file_data_utf8_relative_path.replace(value);
         }
+        if let Some(value) = other.in_memory_bytecode_compression {
+            self.in_memory_bytecode_compression.replace(value);
+        }
+        if let Some(value) = other.in_memory_package_resources_compression {
+            self.in_memory_package_resources_compression.replace(value);
+        }
+        if let Some(value) = other.source_map_path {
+            self.source_map_path.replace(value);
+        }
+        // The merged resource no longer corresponds to a serialized entry.
+        self.content_digest = None;
 
         Ok(())
     }
@@ -289,6 +339,28 @@ where
                     })
                     .collect()
             }),
+            in_memory_localized_package_resources: self
+                .in_memory_localized_package_resources
+                .as_ref()
+                .map(|value| {
+                    value
+                        .iter()
+                        .map(|(locale, resources)| {
+                            (
+                                Cow::Owned(locale.clone().into_owned()),
+                                resources
+                                    .iter()
+                                    .map(|(k, v)| {
+                                        (
+                                            Cow::Owned(k.clone().into_owned()),
+                                            Cow::Owned(v.clone().into_owned()),
+                                        )
+                                    })
+                                    .collect(),
+                            )
+                        })
+                        .collect()
+                }),
             in_memory_distribution_resources: self.in_memory_distribution_resources.as_ref().map(
                 |value| {
                     value
@@ -370,6 +442,13 @@ where
                 .file_data_utf8_relative_path
                 .as_ref()
                 .map(|value| Cow::Owned(value.clone().into_owned())),
+            in_memory_bytecode_compression: self.in_memory_bytecode_compression,
+            in_memory_package_resources_compression: self.in_memory_package_resources_compression,
+            content_digest: self.content_digest,
+            source_map_path: self
+                .source_map_path
+                .as_ref()
+                .map(|value| Cow::Owned(value.clone().into_owned())),
         }
     }
 }
@@ -8,9 +8,36 @@
 
 /*! Declares the foundational data primitives inside packed resources data. */
 
-/// Header value for version 2 of resources payload.
+/// Header value for version 3 of resources payload.
 pub const HEADER_V3: &[u8] = b"pyembed\x03";
 
+/// Header value for version 4 of resources payload.
+pub const HEADER_V4: &[u8] = b"pyembed\x04";
+
+/// Header value of encrypted resources data.
+///
+/// Encrypted data wraps a complete resources payload. See the `encryption`
+/// crate feature.
+pub const ENCRYPTED_HEADER: &[u8] = b"pyembed-encrypted\x01";
+
+/// Length in bytes of keys used to encrypt resources data.
+pub const ENCRYPTION_KEY_LENGTH: usize = 32;
+
+/// Alignment in bytes of payload data in version 4 of resources payload.
+pub const PAYLOAD_ALIGNMENT: usize = 64;
+
+/// Magic value ending resources data appended to an executable.
+///
+/// Appended data is followed by a trailer holding the little-endian u64
+/// offset and length of the data within the file, then this value.
+pub const OVERLAY_TRAILER_MAGIC: &[u8] = b"pyembed-overlay\x01";
+
+/// Length in bytes of the trailer following resources data appended to an executable.
+pub const OVERLAY_TRAILER_LENGTH: usize = 16 + OVERLAY_TRAILER_MAGIC.len();
+
+/// Length in bytes of resource content digests.
+pub const CONTENT_DIGEST_LENGTH: usize = 32;
+
 /// Defines interior padding mechanism between entries in blob sections.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BlobInteriorPadding {
@@ -34,6 +61,46 @@ impl From<&BlobInteriorPadding> for u8 {
     }
 }
 
+/// Compression algorithm applied to in-memory resource data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataCompression {
+    /// zlib (DEFLATE) compression.
+    Zlib = 0x01,
+
+    /// Zstandard compression.
+    Zstd = 0x02,
+
+    /// Zstandard compression using a shared dictionary.
+    ///
+    /// The dictionary is identified by the dictionary ID recorded in the
+    /// zstd frame header and is stored in the compression dictionaries blob
+    /// section.
+    ZstdDictionary = 0x03,
+}
+
+impl From<DataCompression> for u8 {
+    fn from(source: DataCompression) -> Self {
+        match source {
+            DataCompression::Zlib => 0x01,
+            DataCompression::Zstd => 0x02,
+            DataCompression::ZstdDictionary => 0x03,
+        }
+    }
+}
+
+impl TryFrom<u8> for DataCompression {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(DataCompression::Zlib),
+            0x02 => Ok(DataCompression::Zstd),
+            0x03 => Ok(DataCompression::ZstdDictionary),
+            _ => Err("invalid data compression value"),
+        }
+    }
+}
+
 /// Describes a blob section field type in the blob index.
 #[derive(Debug, PartialEq, Eq, PartialOrd)]
 pub enum BlobSectionField {
@@ -43,6 +110,7 @@ pub enum BlobSectionField {
     ResourceFieldType = 0x03,
     RawPayloadLength = 0x04,
     InteriorPadding = 0x05,
+    Offset = 0x06,
 }
 
 impl From<BlobSectionField> for u8 {
@@ -53,6 +121,7 @@ impl From<BlobSectionField> for u8 {
             BlobSectionField::ResourceFieldType => 0x02,
             BlobSectionField::RawPayloadLength => 0x03,
             BlobSectionField::InteriorPadding => 0x04,
+            BlobSectionField::Offset => 0x05,
             BlobSectionField::EndOfEntry => 0xff,
         }
     }
@@ -68,6 +137,7 @@ impl TryFrom<u8> for BlobSectionField {
             0x02 => Ok(BlobSectionField::ResourceFieldType),
             0x03 => Ok(BlobSectionField::RawPayloadLength),
             0x04 => Ok(BlobSectionField::InteriorPadding),
+            0x05 => Ok(BlobSectionField::Offset),
             0xff => Ok(BlobSectionField::EndOfEntry),
             _ => Err("invalid blob index field type"),
         }
@@ -109,6 +179,12 @@ pub enum ResourceField {
     FileExecutable = 0x1c,
     FileDataEmbedded = 0x1d,
     FileDataUtf8RelativePath = 0x1e,
+    InMemoryBytecodeCompression = 0x1f,
+    InMemoryResourcesCompression = 0x20,
+    CompressionDictionary = 0x21,
+    InMemoryLocalizedResourcesData = 0x22,
+    ContentDigest = 0x23,
+    SourceMapPath = 0x24,
 }
 
 impl From<ResourceField> for u8 {
@@ -144,6 +220,12 @@ impl From<ResourceField> for u8 {
             ResourceField::FileExecutable => 0x1c,
             ResourceField::FileDataEmbedded => 0x1d,
             ResourceField::FileDataUtf8RelativePath => 0x1e,
+            ResourceField::InMemoryBytecodeCompression => 0x1f,
+            ResourceField::InMemoryResourcesCompression => 0x20,
+            ResourceField::CompressionDictionary => 0x21,
+            ResourceField::InMemoryLocalizedResourcesData => 0x22,
+            ResourceField::ContentDigest => 0x23,
+            ResourceField::SourceMapPath => 0x24,
             ResourceField::EndOfEntry => 0xff,
         }
     }
@@ -184,6 +266,12 @@ impl TryFrom<u8> for ResourceField {
             0x1c => Ok(ResourceField::FileExecutable),
             0x1d => Ok(ResourceField::FileDataEmbedded),
             0x1e => Ok(ResourceField::FileDataUtf8RelativePath),
+            0x1f => Ok(ResourceField::InMemoryBytecodeCompression),
+            0x20 => Ok(ResourceField::InMemoryResourcesCompression),
+            0x21 => Ok(ResourceField::CompressionDictionary),
+            0x22 => Ok(ResourceField::InMemoryLocalizedResourcesData),
+            0x23 => Ok(ResourceField::ContentDigest),
+            0x24 => Ok(ResourceField::SourceMapPath),
             0xff => Ok(ResourceField::EndOfEntry),
             _ => Err("invalid field type"),
         }
@@ -11,11 +11,19 @@
 use {
     crate::{
         resource::Resource,
-        serialization::{BlobInteriorPadding, BlobSectionField, ResourceField, HEADER_V3},
+        serialization::{
+            BlobInteriorPadding, BlobSectionField, ResourceField, CONTENT_DIGEST_LENGTH, HEADER_V3,
+            HEADER_V4, OVERLAY_TRAILER_MAGIC, PAYLOAD_ALIGNMENT,
+        },
     },
     anyhow::{anyhow, Context, Result},
     byteorder::{LittleEndian, WriteBytesExt},
-    std::{collections::BTreeMap, io::Write, path::Path},
+    std::{
+        borrow::Cow,
+        collections::{BTreeMap, HashMap},
+        io::Write,
+        path::Path,
+    },
 };
 
 #[cfg(unix)]
@@ -51,6 +59,121 @@ fn path_to_bytes(p: &Path) -> Vec<u8> {
     }
 }
 
+/// Resource fields whose data is stored in blob sections.
+const BLOB_FIELDS: [ResourceField; 21] = [
+    ResourceField::Name,
+    ResourceField::InMemorySource,
+    ResourceField::InMemoryBytecode,
+    ResourceField::InMemoryBytecodeOpt1,
+    ResourceField::InMemoryBytecodeOpt2,
+    ResourceField::InMemoryExtensionModuleSharedLibrary,
+    ResourceField::InMemoryResourcesData,
+    ResourceField::InMemoryDistributionResource,
+    ResourceField::InMemorySharedLibrary,
+    ResourceField::SharedLibraryDependencyNames,
+    ResourceField::RelativeFilesystemModuleSource,
+    ResourceField::RelativeFilesystemModuleBytecode,
+    ResourceField::RelativeFilesystemModuleBytecodeOpt1,
+    ResourceField::RelativeFilesystemModuleBytecodeOpt2,
+    ResourceField::RelativeFilesystemExtensionModuleSharedLibrary,
+    ResourceField::RelativeFilesystemPackageResources,
+    ResourceField::RelativeFilesystemDistributionResource,
+    ResourceField::FileDataEmbedded,
+    ResourceField::FileDataUtf8RelativePath,
+    ResourceField::InMemoryLocalizedResourcesData,
+    ResourceField::SourceMapPath,
+];
+
+/// Obtain the entries of a map sorted by key.
+///
+/// Map entries are written in key order so output is deterministic and
+/// content digests can be recomputed from parsed resources.
+fn sorted_entries<K: Ord, V>(map: &HashMap<K, V>) -> Vec<(&K, &V)> {
+    let mut entries = map.iter().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    entries
+}
+
+/// Round an offset up to the payload alignment.
+fn align_offset(offset: usize) -> usize {
+    offset + (PAYLOAD_ALIGNMENT - offset % PAYLOAD_ALIGNMENT) % PAYLOAD_ALIGNMENT
+}
+
+/// Places blobs referenced by version 4 index entries.
+struct BlobLayout<'b> {
+    /// Offset of the next blob in each blob section, indexed by resource field.
+    offsets: [usize; 256],
+    /// Blobs and their offsets, in the order they are referenced.
+    blobs: Vec<(usize, Cow<'b, [u8]>)>,
+    /// Digest of the blobs referenced by the resource being written.
+    hasher: blake3::Hasher,
+}
+
+impl<'b> BlobLayout<'b> {
+    fn new(section_offsets: [usize; 256]) -> Self {
+        Self {
+            offsets: section_offsets,
+            blobs: vec![],
+            hasher: blake3::Hasher::new(),
+        }
+    }
+
+    /// Place a blob, returning its offset.
+    ///
+    /// Aligned blobs start on a [PAYLOAD_ALIGNMENT] boundary.
+    fn add(&mut self, field: ResourceField, data: Cow<'b, [u8]>, aligned: bool) -> usize {
+        let next = &mut self.offsets[field as usize];
+
+        if aligned {
+            *next = align_offset(*next);
+        }
+
+        let offset = *next;
+        *next += data.len();
+
+        self.hasher.update(&data);
+        self.blobs.push((offset, data));
+
+        offset
+    }
+}
+
+/// Place a blob referenced by an index entry and write its offset.
+///
+/// Does nothing without a layout, as version 1 entries don't record offsets.
+fn write_blob_offset<'b, W: Write>(
+    dest: &mut W,
+    layout: Option<&mut BlobLayout<'b>>,
+    field: ResourceField,
+    data: &'b [u8],
+    aligned: bool,
+) -> Result<()> {
+    if let Some(layout) = layout {
+        let offset = layout.add(field, Cow::Borrowed(data), aligned);
+        dest.write_u64::<LittleEndian>(offset as u64)
+            .context("writing blob offset")?;
+    }
+
+    Ok(())
+}
+
+/// Like [write_blob_offset] but for platform-native paths.
+fn write_path_offset<W: Write>(
+    dest: &mut W,
+    layout: Option<&mut BlobLayout>,
+    field: ResourceField,
+    path: &Path,
+) -> Result<()> {
+    if let Some(layout) = layout {
+        let offset = layout.add(field, Cow::Owned(path_to_bytes(path)), false);
+        dest.write_u64::<LittleEndian>(offset as u64)
+            .context("writing blob offset")?;
+    }
+
+    Ok(())
+}
+
 #[derive(Debug)]
 struct BlobSection {
     resource_field: ResourceField,
@@ -125,6 +248,7 @@ where
             || self.in_memory_bytecode_opt2.is_some()
             || self.in_memory_extension_module_shared_library.is_some()
             || self.in_memory_package_resources.is_some()
+            || self.in_memory_localized_package_resources.is_some()
             || self.in_memory_distribution_resources.is_some()
             || self.in_memory_shared_library.is_some()
             || self.relative_path_module_source.is_some()
@@ -181,6 +305,16 @@ where
             index += 10 * resources.len();
         }
 
+        if let Some(locales) = &self.in_memory_localized_package_resources {
+            index += 5;
+
+            for resources in locales.values() {
+                // u16 + u32 for locale name and resource count.
+                index += 6;
+                index += 10 * resources.len();
+            }
+        }
+
         if let Some(metadata) = &self.in_memory_distribution_resources {
             index += 5;
             // Same as resources.
@@ -264,6 +398,18 @@ where
             index += 5;
         }
 
+        if self.in_memory_bytecode_compression.is_some() {
+            index += 2;
+        }
+
+        if self.in_memory_package_resources_compression.is_some() {
+            index += 2;
+        }
+
+        if self.source_map_path.is_some() {
+            index += 5;
+        }
+
         // End of index entry.
         index += 1;
 
@@ -326,6 +472,22 @@ where
                     0
                 }
             }
+            ResourceField::InMemoryLocalizedResourcesData => {
+                if let Some(locales) = &self.in_memory_localized_package_resources {
+                    locales
+                        .iter()
+                        .map(|(locale, resources)| {
+                            locale.len()
+                                + resources
+                                    .iter()
+                                    .map(|(key, value)| key.len() + value.len())
+                                    .sum::<usize>()
+                        })
+                        .sum()
+                } else {
+                    0
+                }
+            }
             ResourceField::InMemoryDistributionResource => {
                 if let Some(metadata) = &self.in_memory_distribution_resources {
                     metadata
@@ -426,16 +588,23 @@ where
                     0
                 }
             }
+            ResourceField::InMemoryBytecodeCompression => 0,
+            ResourceField::InMemoryResourcesCompression => 0,
+            ResourceField::CompressionDictionary => 0,
+            ResourceField::ContentDigest => 0,
+            ResourceField::SourceMapPath => {
+                if let Some(path) = &self.source_map_path {
+                    path.len()
+                } else {
+                    0
+                }
+            }
         }
     }
 
-    /// Compute the size of interior padding for a specific field.
-    pub fn field_blob_interior_padding_length(
-        &self,
-        field: ResourceField,
-        padding: BlobInteriorPadding,
-    ) -> usize {
-        let elements_count = match field {
+    /// Compute the number of blobs a field references.
+    pub fn field_blob_elements_count(&self, field: ResourceField) -> usize {
+        match field {
             ResourceField::EndOfIndex => 0,
             ResourceField::StartOfEntry => 0,
             ResourceField::EndOfEntry => 0,
@@ -484,6 +653,16 @@ where
                     0
                 }
             }
+            ResourceField::InMemoryLocalizedResourcesData => {
+                if let Some(locales) = &self.in_memory_localized_package_resources {
+                    locales
+                        .values()
+                        .map(|resources| 1 + resources.len() * 2)
+                        .sum()
+                } else {
+                    0
+                }
+            }
             ResourceField::InMemoryDistributionResource => {
                 if let Some(metadata) = &self.in_memory_distribution_resources {
                     metadata.len() * 2
@@ -575,18 +754,78 @@ where
                     0
                 }
             }
-        };
+            ResourceField::InMemoryBytecodeCompression => 0,
+            ResourceField::InMemoryResourcesCompression => 0,
+            ResourceField::CompressionDictionary => 0,
+            ResourceField::ContentDigest => 0,
+            ResourceField::SourceMapPath => {
+                if self.source_map_path.is_some() {
+                    1
+                } else {
+                    0
+                }
+            }
+        }
+    }
 
+    /// Compute the size of interior padding for a specific field.
+    pub fn field_blob_interior_padding_length(
+        &self,
+        field: ResourceField,
+        padding: BlobInteriorPadding,
+    ) -> usize {
         let overhead = match padding {
             BlobInteriorPadding::None => 0,
             BlobInteriorPadding::Null => 1,
         };
 
-        elements_count * overhead
+        self.field_blob_elements_count(field) * overhead
     }
 
+    /// Compute length of index entry for version 4 payload format.
+    ///
+    /// Version 4 entries are version 1 entries with an offset following
+    /// every blob length and a trailing content digest.
+    pub fn index_v4_length(&self) -> usize {
+        let blobs_count = BLOB_FIELDS
+            .iter()
+            .map(|field| self.field_blob_elements_count(*field))
+            .sum::<usize>();
+
+        self.index_v1_length() + 8 * blobs_count + 1 + CONTENT_DIGEST_LENGTH
+    }
+}
+
+impl<'a> Resource<'a, u8> {
     /// Write the version 1 index entry for a resource instance.
     pub fn write_index_v1<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.write_index(dest, None)
+    }
+
+    /// Compute the digest of the data referenced by this resource.
+    ///
+    /// This is the value of the content digest written to version 4 index entries.
+    pub fn compute_content_digest(&self) -> Result<[u8; CONTENT_DIGEST_LENGTH]> {
+        let mut entry = vec![];
+        self.write_index(&mut entry, Some(&mut BlobLayout::new([0; 256])))?;
+
+        // The entry ends with the digest followed by the end of entry marker.
+        let end = entry.len() - 1;
+        let mut digest = [0u8; CONTENT_DIGEST_LENGTH];
+        digest.copy_from_slice(&entry[end - CONTENT_DIGEST_LENGTH..end]);
+
+        Ok(digest)
+    }
+
+    /// Write an index entry for a resource instance.
+    ///
+    /// With a [BlobLayout], a version 4 entry is written: blobs are placed in
+    /// the layout and their offsets written after their lengths.
+    fn write_index<'b, W: Write>(
+        &'b self,
+        dest: &mut W,
+        mut layout: Option<&mut BlobLayout<'b>>,
+    ) -> Result<()> {
         let name_len =
             u16::try_from(self.name.as_bytes().len()).context("converting name to u16")?;
 
@@ -598,6 +837,13 @@ where
 
         dest.write_u16::<LittleEndian>(name_len)
             .context("writing resource name length")?;
+        write_blob_offset(
+            dest,
+            layout.as_deref_mut(),
+            ResourceField::Name,
+            self.name.as_bytes(),
+            false,
+        )?;
 
         if self.is_python_package {
             dest.write_u8(ResourceField::IsPythonPackage.into())
@@ -616,6 +862,13 @@ where
                 .context("writing in-memory source length field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory source length")?;
+            write_blob_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::InMemorySource,
+                source,
+                true,
+            )?;
         }
 
         if let Some(bytecode) = &self.in_memory_bytecode {
@@ -625,6 +878,13 @@ where
                 .context("writing in-memory bytecode length field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory bytecode length")?;
+            write_blob_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::InMemoryBytecode,
+                bytecode,
+                true,
+            )?;
         }
 
         if let Some(bytecode) = &self.in_memory_bytecode_opt1 {
@@ -634,6 +894,13 @@ where
                 .context("writing in-memory bytecode opt 1 length field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory bytecode opt 1 length")?;
+            write_blob_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::InMemoryBytecodeOpt1,
+                bytecode,
+                true,
+            )?;
         }
 
         if let Some(bytecode) = &self.in_memory_bytecode_opt2 {
@@ -643,6 +910,13 @@ where
                 .context("writing in-memory bytecode opt 2 field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory bytecode opt 2 length")?;
+            write_blob_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::InMemoryBytecodeOpt2,
+                bytecode,
+                true,
+            )?;
         }
 
         if let Some(library) = &self.in_memory_extension_module_shared_library {
@@ -652,6 +926,13 @@ where
                 .context("writing in-memory extension module shared library field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory extension module shared library length")?;
+            write_blob_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::InMemoryExtensionModuleSharedLibrary,
+                library,
+                true,
+            )?;
         }
 
         if let Some(resources) = &self.in_memory_package_resources {
@@ -662,13 +943,77 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory resources data length")?;
 
-            for (name, value) in resources.iter() {
+            for (name, value) in sorted_entries(resources) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting resource name length to u16")?;
                 dest.write_u16::<LittleEndian>(name_length)
                     .context("writing resource name length")?;
+                write_blob_offset(
+                    dest,
+                    layout.as_deref_mut(),
+                    ResourceField::InMemoryResourcesData,
+                    name.as_bytes(),
+                    false,
+                )?;
                 dest.write_u64::<LittleEndian>(value.len() as u64)
                     .context("writing resource data length")?;
+                write_blob_offset(
+                    dest,
+                    layout.as_deref_mut(),
+                    ResourceField::InMemoryResourcesData,
+                    value,
+                    true,
+                )?;
+            }
+        }
+
+        if let Some(locales) = &self.in_memory_localized_package_resources {
+            let l = u32::try_from(locales.len())
+                .context("converting in-memory localized resources length to u32")?;
+            dest.write_u8(ResourceField::InMemoryLocalizedResourcesData.into())
+                .context("writing in-memory localized resources field")?;
+            dest.write_u32::<LittleEndian>(l)
+                .context("writing in-memory localized resources length")?;
+
+            for (locale, resources) in sorted_entries(locales) {
+                let locale_length =
+                    u16::try_from(locale.len()).context("converting locale name length to u16")?;
+                dest.write_u16::<LittleEndian>(locale_length)
+                    .context("writing locale name length")?;
+                write_blob_offset(
+                    dest,
+                    layout.as_deref_mut(),
+                    ResourceField::InMemoryLocalizedResourcesData,
+                    locale.as_bytes(),
+                    false,
+                )?;
+                let count = u32::try_from(resources.len())
+                    .context("converting localized resources count to u32")?;
+                dest.write_u32::<LittleEndian>(count)
+                    .context("writing localized resources count")?;
+
+                for (name, value) in sorted_entries(resources) {
+                    let name_length = u16::try_from(name.len())
+                        .context("converting resource name length to u16")?;
+                    dest.write_u16::<LittleEndian>(name_length)
+                        .context("writing resource name length")?;
+                    write_blob_offset(
+                        dest,
+                        layout.as_deref_mut(),
+                        ResourceField::InMemoryLocalizedResourcesData,
+                        name.as_bytes(),
+                        false,
+                    )?;
+                    dest.write_u64::<LittleEndian>(value.len() as u64)
+                        .context("writing resource data length")?;
+                    write_blob_offset(
+                        dest,
+                        layout.as_deref_mut(),
+                        ResourceField::InMemoryLocalizedResourcesData,
+                        value,
+                        true,
+                    )?;
+                }
             }
         }
 
@@ -680,13 +1025,27 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory package distribution length")?;
 
-            for (name, value) in metadata {
+            for (name, value) in sorted_entries(metadata) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting distribution name length to u16")?;
                 dest.write_u16::<LittleEndian>(name_length)
                     .context("writing distribution name length")?;
+                write_blob_offset(
+                    dest,
+                    layout.as_deref_mut(),
+                    ResourceField::InMemoryDistributionResource,
+                    name.as_bytes(),
+                    false,
+                )?;
                 dest.write_u64::<LittleEndian>(value.len() as u64)
                     .context("writing distribution data length")?;
+                write_blob_offset(
+                    dest,
+                    layout.as_deref_mut(),
+                    ResourceField::InMemoryDistributionResource,
+                    value,
+                    false,
+                )?;
             }
         }
 
@@ -697,6 +1056,13 @@ where
                 .context("writing in-memory shared library field")?;
             dest.write_u64::<LittleEndian>(l)
                 .context("writing in-memory shared library length")?;
+            write_blob_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::InMemorySharedLibrary,
+                library,
+                true,
+            )?;
         }
 
         if let Some(names) = &self.shared_library_dependency_names {
@@ -712,6 +1078,13 @@ where
                     .context("converting shared library dependency name length to u16")?;
                 dest.write_u16::<LittleEndian>(name_length)
                     .context("writing shared library dependency name length")?;
+                write_blob_offset(
+                    dest,
+                    layout.as_deref_mut(),
+                    ResourceField::SharedLibraryDependencyNames,
+                    name.as_bytes(),
+                    false,
+                )?;
             }
         }
 
@@ -722,6 +1095,12 @@ where
                 .context("writing relative path module source field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path module source length")?;
+            write_path_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::RelativeFilesystemModuleSource,
+                path,
+            )?;
         }
 
         if let Some(path) = &self.relative_path_module_bytecode {
@@ -731,6 +1110,12 @@ where
                 .context("writing relative path module bytecode field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path module bytecode length")?;
+            write_path_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::RelativeFilesystemModuleBytecode,
+                path,
+            )?;
         }
 
         if let Some(path) = &self.relative_path_module_bytecode_opt1 {
@@ -740,6 +1125,12 @@ where
                 .context("writing relative path module bytecode opt1 field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path module bytecode opt1 length")?;
+            write_path_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::RelativeFilesystemModuleBytecodeOpt1,
+                path,
+            )?;
         }
 
         if let Some(path) = &self.relative_path_module_bytecode_opt2 {
@@ -749,6 +1140,12 @@ where
                 .context("writing relative path module bytecode opt2 field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path module bytecode opt2 length")?;
+            write_path_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::RelativeFilesystemModuleBytecodeOpt2,
+                path,
+            )?;
         }
 
         if let Some(path) = &self.relative_path_extension_module_shared_library {
@@ -758,6 +1155,12 @@ where
                 .context("writing relative path extension module shared library field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path extension module shared library length")?;
+            write_path_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::RelativeFilesystemExtensionModuleSharedLibrary,
+                path,
+            )?;
         }
 
         if let Some(resources) = &self.relative_path_package_resources {
@@ -768,15 +1171,28 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path resources resources data length")?;
 
-            for (name, path) in resources.iter() {
+            for (name, path) in sorted_entries(resources) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting resource name length to u16")?;
                 let path_length = u32::try_from(path_bytes_length(path))
                     .context("converting resource path length to u32")?;
                 dest.write_u16::<LittleEndian>(name_length)
                     .context("writing resource name length")?;
+                write_blob_offset(
+                    dest,
+                    layout.as_deref_mut(),
+                    ResourceField::RelativeFilesystemPackageResources,
+                    name.as_bytes(),
+                    false,
+                )?;
                 dest.write_u32::<LittleEndian>(path_length)
                     .context("writing resource path length")?;
+                write_path_offset(
+                    dest,
+                    layout.as_deref_mut(),
+                    ResourceField::RelativeFilesystemPackageResources,
+                    path,
+                )?;
             }
         }
 
@@ -788,15 +1204,28 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path distribution data length")?;
 
-            for (name, path) in metadata.iter() {
+            for (name, path) in sorted_entries(metadata) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting resource name length to u16")?;
                 let path_length = u32::try_from(path_bytes_length(path))
                     .context("converting resource path length to u32")?;
                 dest.write_u16::<LittleEndian>(name_length)
                     .context("writing resource name length")?;
+                write_blob_offset(
+                    dest,
+                    layout.as_deref_mut(),
+                    ResourceField::RelativeFilesystemDistributionResource,
+                    name.as_bytes(),
+                    false,
+                )?;
                 dest.write_u32::<LittleEndian>(path_length)
                     .context("writing resource path length")?;
+                write_path_offset(
+                    dest,
+                    layout.as_deref_mut(),
+                    ResourceField::RelativeFilesystemDistributionResource,
+                    path,
+                )?;
             }
         }
 
@@ -842,6 +1271,13 @@ where
                 .context("writing file_data_embedded field")?;
             dest.write_u64::<LittleEndian>(l)
                 .context("writing file_data_embedded length")?;
+            write_blob_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::FileDataEmbedded,
+                data,
+                true,
+            )?;
         }
 
         if let Some(path) = &self.file_data_utf8_relative_path {
@@ -851,6 +1287,51 @@ where
                 .context("writing file_data_utf8_relative_path field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing file_data_utf_relative_path field")?;
+            write_blob_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::FileDataUtf8RelativePath,
+                path.as_bytes(),
+                false,
+            )?;
+        }
+
+        if let Some(compression) = self.in_memory_bytecode_compression {
+            dest.write_u8(ResourceField::InMemoryBytecodeCompression.into())
+                .context("writing in_memory_bytecode_compression field")?;
+            dest.write_u8(compression.into())
+                .context("writing in_memory_bytecode_compression value")?;
+        }
+
+        if let Some(compression) = self.in_memory_package_resources_compression {
+            dest.write_u8(ResourceField::InMemoryResourcesCompression.into())
+                .context("writing in_memory_package_resources_compression field")?;
+            dest.write_u8(compression.into())
+                .context("writing in_memory_package_resources_compression value")?;
+        }
+
+        if let Some(path) = &self.source_map_path {
+            let l =
+                u32::try_from(path.len()).context("converting source map path length to u32")?;
+            dest.write_u8(ResourceField::SourceMapPath.into())
+                .context("writing source_map_path field")?;
+            dest.write_u32::<LittleEndian>(l)
+                .context("writing source_map_path length")?;
+            write_blob_offset(
+                dest,
+                layout.as_deref_mut(),
+                ResourceField::SourceMapPath,
+                path.as_bytes(),
+                false,
+            )?;
+        }
+
+        if let Some(layout) = layout {
+            dest.write_u8(ResourceField::ContentDigest.into())
+                .context("writing content digest field")?;
+            dest.write_all(layout.hasher.finalize().as_bytes())
+                .context("writing content digest")?;
+            layout.hasher.reset();
         }
 
         dest.write_u8(ResourceField::EndOfEntry.into())
@@ -860,12 +1341,42 @@ where
     }
 }
 
+/// Serialize zstd compression dictionaries to the payload of their blob section.
+///
+/// Each dictionary is stored as its `u32` length followed by its data.
+fn compression_dictionaries_payload(dictionaries: &[&[u8]]) -> Result<Vec<u8>> {
+    let mut payload = vec![];
+
+    for dictionary in dictionaries {
+        payload.write_u32::<LittleEndian>(
+            u32::try_from(dictionary.len())
+                .context("converting compression dictionary length to u32")?,
+        )?;
+        payload.write_all(dictionary)?;
+    }
+
+    Ok(payload)
+}
+
 /// Write packed resources data, version 3.
-#[allow(clippy::cognitive_complexity)]
 pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
     resources: &[T],
     dest: &mut W,
     interior_padding: Option<BlobInteriorPadding>,
+) -> Result<()> {
+    write_packed_resources_v3_with_dictionaries(resources, &[], dest, interior_padding)
+}
+
+/// Write packed resources data, version 3, including zstd compression dictionaries.
+///
+/// Dictionaries are stored in their own blob section instead of being
+/// attached to a resource.
+#[allow(clippy::cognitive_complexity)]
+pub fn write_packed_resources_v3_with_dictionaries<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
+    resources: &[T],
+    compression_dictionaries: &[&[u8]],
+    dest: &mut W,
+    interior_padding: Option<BlobInteriorPadding>,
 ) -> Result<()> {
     let mut blob_sections = BTreeMap::new();
 
@@ -997,6 +1508,24 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
             resource,
             ResourceField::FileDataUtf8RelativePath,
         );
+        process_field(
+            &mut blob_sections,
+            resource,
+            ResourceField::InMemoryLocalizedResourcesData,
+        );
+        process_field(&mut blob_sections, resource, ResourceField::SourceMapPath);
+    }
+
+    let dictionaries_payload = compression_dictionaries_payload(compression_dictionaries)?;
+    if !dictionaries_payload.is_empty() {
+        blob_sections.insert(
+            ResourceField::CompressionDictionary,
+            BlobSection {
+                resource_field: ResourceField::CompressionDictionary,
+                raw_payload_length: dictionaries_payload.len(),
+                interior_padding: None,
+            },
+        );
     }
 
     for section in blob_sections.values() {
@@ -1066,7 +1595,7 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
 
     for resource in resources {
         if let Some(resources) = &resource.as_ref().in_memory_package_resources {
-            for (key, value) in resources.iter() {
+            for (key, value) in sorted_entries(resources) {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
                 dest.write_all(value)?;
@@ -1077,7 +1606,7 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
 
     for resource in resources {
         if let Some(resources) = &resource.as_ref().in_memory_distribution_resources {
-            for (key, value) in resources {
+            for (key, value) in sorted_entries(resources) {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
                 dest.write_all(value)?;
@@ -1142,7 +1671,7 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
 
     for resource in resources {
         if let Some(resources) = &resource.as_ref().relative_path_package_resources {
-            for (key, path) in resources.iter() {
+            for (key, path) in sorted_entries(resources) {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
                 dest.write_all(&path_to_bytes(path))?;
@@ -1153,7 +1682,7 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
 
     for resource in resources {
         if let Some(resources) = &resource.as_ref().relative_path_distribution_resources {
-            for (key, path) in resources {
+            for (key, path) in sorted_entries(resources) {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
                 dest.write_all(&path_to_bytes(path))?;
@@ -1176,6 +1705,193 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
         }
     }
 
+    dest.write_all(&dictionaries_payload)?;
+
+    for resource in resources {
+        if let Some(locales) = &resource.as_ref().in_memory_localized_package_resources {
+            for (locale, resources) in sorted_entries(locales) {
+                dest.write_all(locale.as_bytes())?;
+                add_interior_padding(dest)?;
+
+                for (key, value) in sorted_entries(resources) {
+                    dest.write_all(key.as_bytes())?;
+                    add_interior_padding(dest)?;
+                    dest.write_all(value)?;
+                    add_interior_padding(dest)?;
+                }
+            }
+        }
+    }
+
+    for resource in resources {
+        if let Some(path) = &resource.as_ref().source_map_path {
+            dest.write_all(path.as_bytes())?;
+            add_interior_padding(dest)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write packed resources data, version 4.
+///
+/// Unlike version 3, every blob is referenced by its offset, payloads are
+/// aligned to [PAYLOAD_ALIGNMENT] bytes, every index entry carries a digest of
+/// the data it references, and a lookup table allows finding resources by name
+/// without parsing other index entries.
+pub fn write_packed_resources_v4<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
+    resources: &[T],
+    dest: &mut W,
+) -> Result<()> {
+    write_packed_resources_v4_with_dictionaries(resources, &[], dest)
+}
+
+/// Write packed resources data, version 4, including zstd compression dictionaries.
+///
+/// Dictionaries are stored in their own blob section instead of being
+/// attached to a resource.
+pub fn write_packed_resources_v4_with_dictionaries<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
+    resources: &[T],
+    compression_dictionaries: &[&[u8]],
+    dest: &mut W,
+) -> Result<()> {
+    let dictionaries_payload = compression_dictionaries_payload(compression_dictionaries)?;
+
+    // Lay out blobs relative to the start of their section to learn section sizes.
+    let mut measure = BlobLayout::new([0; 256]);
+    // 1 for end of index field.
+    let mut resources_index_length = 1;
+
+    for resource in resources {
+        let resource = resource.as_ref();
+        resource.write_index(&mut std::io::sink(), Some(&mut measure))?;
+        resources_index_length += resource.index_v4_length();
+    }
+
+    if !dictionaries_payload.is_empty() {
+        measure.add(
+            ResourceField::CompressionDictionary,
+            Cow::Borrowed(&dictionaries_payload),
+            false,
+        );
+    }
+
+    let section_lengths = measure.offsets;
+    drop(measure);
+
+    let section_fields = (0..256)
+        .filter(|field| section_lengths[*field] > 0)
+        .collect::<Vec<_>>();
+
+    // Start of entry, field type + value, length field + value, offset field + value,
+    // end of entry. 1 for end of index field.
+    let blob_index_length = 22 * section_fields.len() + 1;
+    // Magic, blob section count, blob index length, resources count, resources index length.
+    let resources_index_start = HEADER_V4.len() + 1 + 4 + 4 + 4 + blob_index_length;
+    let lookup_table_start = resources_index_start + resources_index_length;
+    let blobs_start = lookup_table_start + 4 * resources.len();
+
+    let mut section_offsets = [0; 256];
+    let mut offset = blobs_start;
+    for field in &section_fields {
+        offset = align_offset(offset);
+        section_offsets[*field] = offset;
+        offset += section_lengths[*field];
+    }
+
+    dest.write_all(HEADER_V4)?;
+    dest.write_u8(
+        u8::try_from(section_fields.len()).context("converting blob section count to u8")?,
+    )?;
+    dest.write_u32::<LittleEndian>(
+        u32::try_from(blob_index_length).context("converting blob index length to u32")?,
+    )?;
+    dest.write_u32::<LittleEndian>(
+        u32::try_from(resources.len()).context("converting resources count to u32")?,
+    )?;
+    dest.write_u32::<LittleEndian>(
+        u32::try_from(resources_index_length)
+            .context("converting resources index length to u32")?,
+    )?;
+
+    // Write the blob index.
+    for field in &section_fields {
+        dest.write_u8(BlobSectionField::StartOfEntry.into())?;
+        dest.write_u8(BlobSectionField::ResourceFieldType.into())?;
+        dest.write_u8(*field as u8)?;
+        dest.write_u8(BlobSectionField::RawPayloadLength.into())?;
+        dest.write_u64::<LittleEndian>(section_lengths[*field] as u64)?;
+        dest.write_u8(BlobSectionField::Offset.into())?;
+        dest.write_u64::<LittleEndian>(section_offsets[*field] as u64)?;
+        dest.write_u8(BlobSectionField::EndOfEntry.into())?;
+    }
+    dest.write_u8(BlobSectionField::EndOfIndex.into())?;
+
+    // Write the resources index, placing blobs at their final offsets.
+    let mut layout = BlobLayout::new(section_offsets);
+    let mut entries = Vec::with_capacity(resources.len());
+    let mut entry_offset = resources_index_start;
+
+    for resource in resources {
+        let resource = resource.as_ref();
+        resource.write_index(dest, Some(&mut layout))?;
+
+        entries.push((
+            resource.name.as_bytes(),
+            u32::try_from(entry_offset).context("converting index entry offset to u32")?,
+        ));
+        entry_offset += resource.index_v4_length();
+    }
+    dest.write_u8(ResourceField::EndOfIndex.into())?;
+
+    if !dictionaries_payload.is_empty() {
+        layout.add(
+            ResourceField::CompressionDictionary,
+            Cow::Borrowed(&dictionaries_payload),
+            false,
+        );
+    }
+
+    // Write the lookup table of index entries sorted by resource name.
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    for (_, offset) in entries {
+        dest.write_u32::<LittleEndian>(offset)?;
+    }
+
+    // Write blob data, zero padding gaps between blobs.
+    layout.blobs.sort_by_key(|(offset, _)| *offset);
+
+    let mut position = blobs_start;
+    for (offset, data) in &layout.blobs {
+        if data.is_empty() {
+            continue;
+        }
+        if *offset < position {
+            return Err(anyhow!("blob at offset {} overlaps previous blob", offset));
+        }
+
+        dest.write_all(&vec![0; offset - position])?;
+        dest.write_all(data)?;
+        position = offset + data.len();
+    }
+
+    Ok(())
+}
+
+/// Write resources data to be appended to an executable.
+///
+/// `offset` is the length of the executable the data is appended to. The data
+/// is padded to start on a [PAYLOAD_ALIGNMENT] boundary and followed by a
+/// trailer allowing [crate::find_executable_overlay()] to locate it.
+pub fn write_executable_overlay<W: Write>(offset: usize, data: &[u8], dest: &mut W) -> Result<()> {
+    let data_offset = align_offset(offset);
+
+    dest.write_all(&vec![0; data_offset - offset])?;
+    dest.write_all(data)?;
+    dest.write_u64::<LittleEndian>(data_offset as u64)?;
+    dest.write_u64::<LittleEndian>(data.len() as u64)?;
+    dest.write_all(OVERLAY_TRAILER_MAGIC)?;
+
     Ok(())
 }
 
@@ -1207,6 +1923,82 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_write_empty_v4() -> Result<()> {
+        let mut data = Vec::new();
+        let resources: Vec<Resource<u8>> = Vec::new();
+        write_packed_resources_v4(&resources, &mut data)?;
+
+        let mut expected: Vec<u8> = b"pyembed\x04".to_vec();
+        // Number of blob sections.
+        expected.write_u8(0)?;
+        // Length of blob index (end of index marker).
+        expected.write_u32::<LittleEndian>(1)?;
+        // Number of resources.
+        expected.write_u32::<LittleEndian>(0)?;
+        // Length of index (end of index marker).
+        expected.write_u32::<LittleEndian>(1)?;
+        // End of index for blob and modules. The lookup table is empty.
+        expected.write_u8(0)?;
+        expected.write_u8(0)?;
+
+        assert_eq!(data, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_resource_name_v4() -> Result<()> {
+        let mut data = Vec::new();
+        let resource = Resource {
+            name: Cow::Owned("foo".to_string()),
+            ..Resource::default()
+        };
+
+        write_packed_resources_v4(&[resource], &mut data)?;
+
+        let mut expected: Vec<u8> = b"pyembed\x04".to_vec();
+        // Number of blob sections.
+        expected.write_u8(1)?;
+        // Length of blob index. Start of entry, field type + value, length field + value,
+        // offset field + value, end of entry, end of index.
+        expected.write_u32::<LittleEndian>(1 + 2 + 9 + 9 + 1 + 1)?;
+        // Number of resources.
+        expected.write_u32::<LittleEndian>(1)?;
+        // Length of index. Start of entry, name field, name length, name offset,
+        // digest field, digest, end of entry, end of index.
+        expected.write_u32::<LittleEndian>(1 + 1 + 2 + 8 + 1 + 32 + 1 + 1)?;
+        // Blobs index.
+        expected.write_u8(BlobSectionField::StartOfEntry.into())?;
+        expected.write_u8(BlobSectionField::ResourceFieldType.into())?;
+        expected.write_u8(ResourceField::Name.into())?;
+        expected.write_u8(BlobSectionField::RawPayloadLength.into())?;
+        expected.write_u64::<LittleEndian>(b"foo".len() as u64)?;
+        expected.write_u8(BlobSectionField::Offset.into())?;
+        expected.write_u64::<LittleEndian>(128)?;
+        expected.write_u8(BlobSectionField::EndOfEntry.into())?;
+        expected.write_u8(BlobSectionField::EndOfIndex.into())?;
+        // Resource index.
+        let entry_offset = expected.len() as u32;
+        expected.write_u8(ResourceField::StartOfEntry.into())?;
+        expected.write_u8(ResourceField::Name.into())?;
+        expected.write_u16::<LittleEndian>(b"foo".len() as u16)?;
+        expected.write_u64::<LittleEndian>(128)?;
+        expected.write_u8(ResourceField::ContentDigest.into())?;
+        expected.write_all(blake3::hash(b"foo").as_bytes())?;
+        expected.write_u8(ResourceField::EndOfEntry.into())?;
+        expected.write_u8(ResourceField::EndOfIndex.into())?;
+        // Lookup table.
+        expected.write_u32::<LittleEndian>(entry_offset)?;
+        // Blobs start at the first aligned offset.
+        expected.resize(128, 0);
+        expected.write_all(b"foo")?;
+
+        assert_eq!(data, expected);
+
+        Ok(())
+    }
+
     #[test]
     fn test_write_resource_name() -> Result<()> {
         let mut data = Vec::new();
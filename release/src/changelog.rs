@@ -0,0 +1,236 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Changelog generation from conventional commits.
+
+use {
+    anyhow::Result,
+    std::{fmt::Write, path::Path},
+    tugger_common::changelog::ConventionalCommit,
+};
+
+/// Commit types recognized as conventional commits.
+const CONVENTIONAL_TYPES: &[&str] = &[
+    "build", "chore", "ci", "docs", "feat", "fix", "perf", "refactor", "revert", "style", "test",
+];
+
+/// How much a version needs to be incremented.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BumpLevel {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl BumpLevel {
+    /// Apply this bump to a version.
+    ///
+    /// Versions before 1.0 follow Cargo's semantics, where the minor component
+    /// denotes breaking changes.
+    pub fn apply(&self, version: &semver::Version) -> semver::Version {
+        let (major, minor, patch) = match (self, version.major) {
+            (Self::Major, 0) => (0, version.minor + 1, 0),
+            (Self::Major, _) => (version.major + 1, 0, 0),
+            (Self::Minor, 0) => (0, version.minor, version.patch + 1),
+            (Self::Minor, _) => (version.major, version.minor + 1, 0),
+            (Self::Patch, _) => (version.major, version.minor, version.patch + 1),
+        };
+
+        semver::Version::new(major, minor, patch)
+    }
+}
+
+/// A commit contributing to a release.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commit {
+    /// Abbreviated commit ID.
+    pub id: String,
+    /// Conventional commit type, if the commit follows the convention.
+    pub kind: Option<String>,
+    /// Conventional commit scope.
+    pub scope: Option<String>,
+    /// Whether the commit denotes a breaking change.
+    pub breaking: bool,
+    /// Commit description, without the conventional commit prefix.
+    pub description: String,
+}
+
+impl Commit {
+    /// Construct an instance from a commit ID and message.
+    pub fn from_message(id: &str, message: &str) -> Self {
+        let subject = message.lines().next().unwrap_or_default().trim();
+
+        match ConventionalCommit::parse(message) {
+            Some(commit) if CONVENTIONAL_TYPES.contains(&commit.kind.as_str()) => Self {
+                id: id.to_string(),
+                kind: Some(commit.kind),
+                scope: commit.scope,
+                breaking: commit.breaking,
+                description: commit.description,
+            },
+            _ => Self {
+                id: id.to_string(),
+                kind: None,
+                scope: None,
+                breaking: message.lines().skip(1).any(|line| {
+                    line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+                }),
+                description: subject.to_string(),
+            },
+        }
+    }
+
+    /// The version bump this commit requires.
+    pub fn bump_level(&self) -> BumpLevel {
+        if self.breaking {
+            BumpLevel::Major
+        } else if self.kind.as_deref() == Some("feat") {
+            BumpLevel::Minor
+        } else {
+            BumpLevel::Patch
+        }
+    }
+
+    /// Name of the changelog section this commit is listed in.
+    fn section(&self) -> &'static str {
+        if self.breaking {
+            return "Breaking Changes";
+        }
+
+        match self.kind.as_deref() {
+            Some("feat") => "Features",
+            Some("fix") => "Bug Fixes",
+            Some("perf") => "Performance",
+            _ => "Other Changes",
+        }
+    }
+}
+
+/// Resolve the version bump required by a set of commits.
+///
+/// Returns `None` if there are no commits.
+pub fn bump_level(commits: &[Commit]) -> Option<BumpLevel> {
+    commits.iter().map(|c| c.bump_level()).max()
+}
+
+/// Render the changelog section for a release in Markdown.
+pub fn render_section(version: &semver::Version, date: &str, commits: &[Commit]) -> String {
+    let mut res = format!("## {} ({})\n\n", version, date);
+
+    if commits.is_empty() {
+        res.push_str("No changes recorded.\n\n");
+        return res;
+    }
+
+    for section in [
+        "Breaking Changes",
+        "Features",
+        "Bug Fixes",
+        "Performance",
+        "Other Changes",
+    ] {
+        let entries = commits
+            .iter()
+            .filter(|c| c.section() == section)
+            .collect::<Vec<_>>();
+
+        if entries.is_empty() {
+            continue;
+        }
+
+        writeln!(res, "### {}\n", section).unwrap();
+
+        for commit in entries {
+            if let Some(scope) = &commit.scope {
+                writeln!(
+                    res,
+                    "* **{}:** {} ({})",
+                    scope, commit.description, commit.id
+                )
+                .unwrap();
+            } else {
+                writeln!(res, "* {} ({})", commit.description, commit.id).unwrap();
+            }
+        }
+
+        res.push('\n');
+    }
+
+    res
+}
+
+/// Insert a rendered section at the top of a changelog file.
+///
+/// The file is created if it doesn't exist.
+pub fn prepend_section(path: &Path, section: &str) -> Result<()> {
+    const HEADER: &str = "# Changelog\n\n";
+
+    let existing = if path.exists() {
+        std::fs::read_to_string(path)?
+    } else {
+        String::new()
+    };
+
+    let body = existing.strip_prefix(HEADER).unwrap_or(&existing);
+
+    std::fs::write(path, format!("{}{}{}", HEADER, section, body))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commit() {
+        let c = Commit::from_message("abc", "feat(pyembed): add thing\n\nbody");
+        assert_eq!(c.kind.as_deref(), Some("feat"));
+        assert_eq!(c.scope.as_deref(), Some("pyembed"));
+        assert_eq!(c.description, "add thing");
+        assert_eq!(c.bump_level(), BumpLevel::Minor);
+
+        let c = Commit::from_message("abc", "fix!: change thing");
+        assert!(c.breaking);
+        assert_eq!(c.bump_level(), BumpLevel::Major);
+
+        let c = Commit::from_message("abc", "fix: thing\n\nBREAKING CHANGE: it broke");
+        assert!(c.breaking);
+
+        // Repository style commit messages aren't conventional commits.
+        let c = Commit::from_message("abc", "pyembed: add thing");
+        assert_eq!(c.kind, None);
+        assert_eq!(c.description, "pyembed: add thing");
+        assert_eq!(c.bump_level(), BumpLevel::Patch);
+    }
+
+    #[test]
+    fn test_bump() {
+        let v = |s: &str| semver::Version::parse(s).unwrap();
+
+        assert_eq!(BumpLevel::Major.apply(&v("0.24.1")), v("0.25.0"));
+        assert_eq!(BumpLevel::Minor.apply(&v("0.24.1")), v("0.24.2"));
+        assert_eq!(BumpLevel::Patch.apply(&v("0.24.1")), v("0.24.2"));
+        assert_eq!(BumpLevel::Major.apply(&v("1.2.3")), v("2.0.0"));
+        assert_eq!(BumpLevel::Minor.apply(&v("1.2.3")), v("1.3.0"));
+        assert_eq!(BumpLevel::Patch.apply(&v("1.2.3")), v("1.2.4"));
+    }
+
+    #[test]
+    fn test_render_section() {
+        let commits = vec![
+            Commit::from_message("a", "feat: new"),
+            Commit::from_message("b", "fix(scope): broken"),
+            Commit::from_message("c", "pyembed: other"),
+        ];
+
+        assert_eq!(
+            render_section(&semver::Version::new(0, 2, 0), "2023-01-01", &commits),
+            "## 0.2.0 (2023-01-01)\n\n\
+            ### Features\n\n* new (a)\n\n\
+            ### Bug Fixes\n\n* **scope:** broken (b)\n\n\
+            ### Other Changes\n\n* pyembed: other (c)\n\n"
+        );
+    }
+}
@@ -326,10 +326,24 @@ pub fn generate_sphinx_files(repo_root: &Path) -> Result<()> {
             name: "OxidizedPythonInterpreterConfig".into(),
         }];
 
-        let python_packaging_structs = vec![TypeReference {
-            filename: "python-packaging/src/interpreter.rs".into(),
-            name: "PythonInterpreterConfig".into(),
-        }];
+        let python_packaging_structs = vec![
+            TypeReference {
+                filename: "python-packaging/src/interpreter.rs".into(),
+                name: "PythonInterpreterConfig".into(),
+            },
+            TypeReference {
+                filename: "python-packaging/src/interpreter.rs".into(),
+                name: "HostRequirements".into(),
+            },
+            TypeReference {
+                filename: "python-packaging/src/interpreter.rs".into(),
+                name: "SandboxPolicy".into(),
+            },
+            TypeReference {
+                filename: "python-packaging/src/interpreter.rs".into(),
+                name: "BuildInfo".into(),
+            },
+        ];
 
         let python_packaging_enums = vec![
             TypeReference {
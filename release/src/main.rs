@@ -4,7 +4,7 @@
 
 use {
     anyhow::{anyhow, Context, Result},
-    clap::{ArgMatches, Command},
+    clap::{Arg, ArgAction, ArgMatches, Command},
     duct::cmd,
     git2::Repository,
     std::{
@@ -14,7 +14,10 @@ use {
     },
 };
 
+pub mod changelog;
 pub mod documentation;
+pub mod publish;
+pub mod verify;
 
 /// Obtain the package version string from a Cargo.toml file.
 fn cargo_toml_package_version(path: &Path) -> Result<String> {
@@ -173,9 +176,61 @@ fn command_synchronize_generated_files(repo_root: &Path) -> Result<()> {
     println!("writing {}", lock_path.display());
     std::fs::write(&lock_path, cargo_lock.as_bytes())?;
 
+    generate_pyembed_header(repo_root)?;
+
+    Ok(())
+}
+
+/// Generate the C header declaring the pyembed C API.
+fn generate_pyembed_header(repo_root: &Path) -> Result<()> {
+    let pyembed_path = repo_root.join("pyembed");
+    let header_path = pyembed_path.join("include").join("pyembed.h");
+
+    let config = cbindgen::Config::from_file(pyembed_path.join("cbindgen.toml"))
+        .map_err(|e| anyhow!("error reading cbindgen config: {}", e))?;
+
+    println!("writing {}", header_path.display());
+    cbindgen::Builder::new()
+        .with_src(pyembed_path.join("src").join("capi.rs"))
+        .with_config(config)
+        .generate()
+        .context("generating pyembed.h")?
+        .write_to_file(&header_path);
+
     Ok(())
 }
 
+fn package_args(args: &ArgMatches) -> Vec<String> {
+    args.get_many::<String>("package")
+        .unwrap_or_default()
+        .cloned()
+        .collect()
+}
+
+fn command_plan(repo_root: &Path, args: &ArgMatches) -> Result<()> {
+    publish::command_plan(repo_root, &package_args(args), args.get_flag("changelog"))
+}
+
+fn command_release(repo_root: &Path, args: &ArgMatches) -> Result<()> {
+    publish::command_release(
+        repo_root,
+        &publish::ReleaseOptions {
+            packages: package_args(args),
+            dry_run: args.get_flag("dry-run"),
+            resume: args.get_flag("resume"),
+            push: args.get_flag("push"),
+        },
+    )
+}
+
+fn command_verify_published(repo_root: &Path, args: &ArgMatches) -> Result<()> {
+    verify::command_verify_published(
+        repo_root,
+        &package_args(args),
+        args.get_flag("all-versions"),
+    )
+}
+
 fn main_impl() -> Result<()> {
     let cwd = std::env::current_dir()?;
 
@@ -211,14 +266,81 @@ fn main_impl() -> Result<()> {
             Command::new("generate-new-project-cargo-lock")
                 .about("Emit a Cargo.lock file for the pyembed crate"),
         )
+        .subcommand(
+            Command::new("plan")
+                .about("Print the publish order and the versions crates would be released at")
+                .arg(
+                    Arg::new("package")
+                        .long("package")
+                        .short('p')
+                        .action(ArgAction::Append)
+                        .help("Only consider the named crate"),
+                )
+                .arg(
+                    Arg::new("changelog")
+                        .long("changelog")
+                        .action(ArgAction::SetTrue)
+                        .help("Print generated changelogs"),
+                ),
+        )
+        .subcommand(
+            Command::new("release")
+                .about("Bump versions, write changelogs, and publish and tag crates")
+                .arg(
+                    Arg::new("package")
+                        .long("package")
+                        .short('p')
+                        .action(ArgAction::Append)
+                        .help("Only release the named crate"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("push")
+                        .help("Print the plan and run dry-run publishes without changing anything"),
+                )
+                .arg(
+                    Arg::new("resume")
+                        .long("resume")
+                        .action(ArgAction::SetTrue)
+                        .help("Resume a previously interrupted release"),
+                )
+                .arg(
+                    Arg::new("push")
+                        .long("push")
+                        .action(ArgAction::SetTrue)
+                        .help("Push the release commit and tags when done"),
+                ),
+        )
         .subcommand(Command::new("synchronize-generated-files").about("Write out generated files"))
+        .subcommand(
+            Command::new("verify-published")
+                .about("Verify crates published to crates.io match their tagged sources")
+                .arg(
+                    Arg::new("package")
+                        .long("package")
+                        .short('p')
+                        .action(ArgAction::Append)
+                        .help("Only verify the named crate"),
+                )
+                .arg(
+                    Arg::new("all-versions")
+                        .long("all-versions")
+                        .action(ArgAction::SetTrue)
+                        .help("Verify every tagged version instead of only the latest"),
+                ),
+        )
         .get_matches();
 
     match matches.subcommand() {
         Some(("generate-new-project-cargo-lock", args)) => {
             command_generate_new_project_cargo_lock(&repo_root, args)
         }
+        Some(("plan", args)) => command_plan(&repo_root, args),
+        Some(("release", args)) => command_release(&repo_root, args),
         Some(("synchronize-generated-files", _)) => command_synchronize_generated_files(&repo_root),
+        Some(("verify-published", args)) => command_verify_published(&repo_root, args),
         _ => Err(anyhow!("invalid sub-command")),
     }
 }
@@ -0,0 +1,994 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Multi-crate release orchestration.
+
+use {
+    crate::{
+        changelog::{self, Commit},
+        run_cmd,
+    },
+    anyhow::{anyhow, Context, Result},
+    duct::cmd,
+    git2::{DiffOptions, Repository, Sort, StatusOptions},
+    once_cell::sync::Lazy,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        path::{Path, PathBuf},
+    },
+};
+
+/// Regex matching a `version = "..."` key in a manifest.
+static RE_VERSION_KEY: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(r#"version\s*=\s*"[^"]*""#).unwrap());
+
+/// Output from `cargo publish` indicating the version was previously published.
+const ALREADY_PUBLISHED: &[&str] = &["already uploaded", "already exists"];
+
+/// Workspace-wide release settings.
+///
+/// These are read from `[workspace.metadata.release]`, which is shared with
+/// `cargo-release`.
+#[derive(Clone, Debug)]
+pub struct ReleaseSettings {
+    pub commit_message: String,
+    pub tag_name: String,
+    pub tag_message: String,
+    pub push_remote: String,
+}
+
+impl ReleaseSettings {
+    fn from_manifest(manifest: &toml::Value) -> Self {
+        let metadata = manifest
+            .get("workspace")
+            .and_then(|v| v.get("metadata"))
+            .and_then(|v| v.get("release"));
+
+        let get = |key: &str, default: &str| -> String {
+            metadata
+                .and_then(|v| v.get(key))
+                .and_then(|v| v.as_str())
+                .unwrap_or(default)
+                .to_string()
+        };
+
+        Self {
+            commit_message: get("pre-release-commit-message", "workspace: perform releases"),
+            tag_name: get("tag-name", "{{crate_name}}/{{version}}"),
+            tag_message: get("tag-message", "{{crate_name}}: version {{version}}"),
+            push_remote: get("push-remote", "origin"),
+        }
+    }
+
+    /// Resolve the name of the tag for a crate version.
+    pub fn tag_name(&self, crate_name: &str, version: &str) -> String {
+        expand_template(&self.tag_name, crate_name, version, "", "")
+    }
+
+    /// Resolve the message of the tag for a crate version.
+    pub fn tag_message(&self, crate_name: &str, version: &str) -> String {
+        expand_template(&self.tag_message, crate_name, version, "", "")
+    }
+}
+
+/// Expand `{{...}}` placeholders as used by release metadata.
+fn expand_template(
+    template: &str,
+    crate_name: &str,
+    version: &str,
+    prev_version: &str,
+    date: &str,
+) -> String {
+    template
+        .replace("{{crate_name}}", crate_name)
+        .replace("{{version}}", version)
+        .replace("{{prev_version}}", prev_version)
+        .replace("{{date}}", date)
+}
+
+/// A text replacement performed when a crate is released.
+#[derive(Clone, Debug)]
+pub struct Replacement {
+    pub file: PathBuf,
+    pub search: String,
+    pub replace: String,
+    pub exactly: Option<usize>,
+}
+
+/// A crate in the workspace.
+#[derive(Clone, Debug)]
+pub struct WorkspaceCrate {
+    pub name: String,
+    pub version: semver::Version,
+    /// Directory of the crate, relative to the workspace root.
+    pub path: PathBuf,
+    /// Whether the crate is published to a registry.
+    pub releasable: bool,
+    /// Names of workspace crates that must be published before this one.
+    pub dependencies: BTreeSet<String>,
+    pub replacements: Vec<Replacement>,
+    pub pre_release_hook: Vec<String>,
+}
+
+impl WorkspaceCrate {
+    fn load(root: &Path, path: &Path) -> Result<Self> {
+        let manifest_path = root.join(path).join("Cargo.toml");
+        let manifest = std::fs::read_to_string(&manifest_path)
+            .with_context(|| format!("reading {}", manifest_path.display()))?
+            .parse::<toml::Value>()
+            .with_context(|| format!("parsing {}", manifest_path.display()))?;
+
+        let package = manifest
+            .get("package")
+            .ok_or_else(|| anyhow!("{} has no [package]", manifest_path.display()))?;
+
+        let name = package
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("{} has no package name", manifest_path.display()))?
+            .to_string();
+        let version = semver::Version::parse(
+            package
+                .get("version")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("{} has no package version", manifest_path.display()))?,
+        )?;
+
+        let metadata = package.get("metadata").and_then(|v| v.get("release"));
+
+        let releasable = package.get("publish").and_then(|v| v.as_bool()) != Some(false)
+            && metadata
+                .and_then(|v| v.get("release"))
+                .and_then(|v| v.as_bool())
+                != Some(false);
+
+        let mut dependencies = BTreeSet::new();
+
+        let mut dependency_tables = vec![
+            (manifest.get("dependencies"), false),
+            (manifest.get("build-dependencies"), false),
+            (manifest.get("dev-dependencies"), true),
+        ];
+        if let Some(targets) = manifest.get("target").and_then(|v| v.as_table()) {
+            for target in targets.values() {
+                dependency_tables.push((target.get("dependencies"), false));
+                dependency_tables.push((target.get("build-dependencies"), false));
+                dependency_tables.push((target.get("dev-dependencies"), true));
+            }
+        }
+
+        for (table, dev) in dependency_tables {
+            for (key, value) in table.and_then(|v| v.as_table()).into_iter().flatten() {
+                if value.get("path").is_none() {
+                    continue;
+                }
+
+                // Path dev-dependencies without a version are stripped when
+                // publishing, so they impose no ordering.
+                if dev && value.get("version").is_none() {
+                    continue;
+                }
+
+                let name = value.get("package").and_then(|v| v.as_str()).unwrap_or(key);
+
+                dependencies.insert(name.to_string());
+            }
+        }
+
+        let replacements = metadata
+            .and_then(|v| v.get("pre-release-replacements"))
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .map(|entry| {
+                let get = |key: &str| -> Result<String> {
+                    Ok(entry
+                        .get(key)
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| {
+                            anyhow!("{}: replacement missing {}", manifest_path.display(), key)
+                        })?
+                        .to_string())
+                };
+
+                Ok(Replacement {
+                    file: path.join(get("file")?),
+                    search: get("search")?,
+                    replace: get("replace")?,
+                    exactly: entry
+                        .get("exactly")
+                        .and_then(|v| v.as_integer())
+                        .map(|v| v as usize),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let pre_release_hook = metadata
+            .and_then(|v| v.get("pre-release-hook"))
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            name,
+            version,
+            path: path.to_path_buf(),
+            releasable,
+            dependencies,
+            replacements,
+            pre_release_hook,
+        })
+    }
+}
+
+/// Crates in a Cargo workspace.
+#[derive(Clone, Debug)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub settings: ReleaseSettings,
+    pub crates: BTreeMap<String, WorkspaceCrate>,
+}
+
+impl Workspace {
+    /// Load the workspace rooted at a directory.
+    pub fn load(root: &Path) -> Result<Self> {
+        let manifest = std::fs::read_to_string(root.join("Cargo.toml"))?.parse::<toml::Value>()?;
+
+        let members = manifest
+            .get("workspace")
+            .and_then(|v| v.get("members"))
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| anyhow!("Cargo.toml does not define workspace members"))?;
+
+        let mut crates = BTreeMap::new();
+
+        for member in members {
+            let member = member
+                .as_str()
+                .ok_or_else(|| anyhow!("workspace member is not a string"))?;
+
+            let krate = WorkspaceCrate::load(root, Path::new(member))?;
+            crates.insert(krate.name.clone(), krate);
+        }
+
+        // Only dependencies on workspace crates matter.
+        let names = crates.keys().cloned().collect::<BTreeSet<_>>();
+        for krate in crates.values_mut() {
+            krate.dependencies.retain(|name| names.contains(name));
+        }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            settings: ReleaseSettings::from_manifest(&manifest),
+            crates,
+        })
+    }
+
+    /// Resolve the order crates need to be published in.
+    ///
+    /// Crates are ordered so every crate comes after the workspace crates it
+    /// depends on. Ties are broken by name so the order is stable.
+    pub fn publish_order(&self) -> Result<Vec<&WorkspaceCrate>> {
+        let mut remaining = self
+            .crates
+            .values()
+            .map(|c| (c.name.as_str(), c.dependencies.clone()))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut res = vec![];
+
+        while !remaining.is_empty() {
+            let ready = remaining
+                .iter()
+                .filter(|(_, deps)| deps.is_empty())
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>();
+
+            if ready.is_empty() {
+                return Err(anyhow!(
+                    "dependency cycle between crates: {}",
+                    remaining.keys().cloned().collect::<Vec<_>>().join(", ")
+                ));
+            }
+
+            for name in ready {
+                remaining.remove(name);
+                for deps in remaining.values_mut() {
+                    deps.remove(name);
+                }
+
+                res.push(&self.crates[name]);
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+/// A planned release of a crate.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlannedRelease {
+    pub name: String,
+    pub path: PathBuf,
+    /// The most recently released version, if any.
+    pub previous_version: Option<String>,
+    pub version: String,
+    /// Rendered changelog section.
+    pub changelog: String,
+    pub published: bool,
+    pub tagged: bool,
+}
+
+/// Persisted state of a release, allowing it to be resumed on failure.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ReleaseState {
+    pub releases: Vec<PlannedRelease>,
+    /// Whether version changes have been written to the working directory.
+    #[serde(default)]
+    pub applied: bool,
+    /// Whether version changes have been committed.
+    pub committed: bool,
+}
+
+impl ReleaseState {
+    fn path(root: &Path) -> PathBuf {
+        root.join("target").join("release-state.json")
+    }
+
+    fn load(root: &Path) -> Result<Self> {
+        let path = Self::path(root);
+        let data = std::fs::read(&path)
+            .with_context(|| format!("reading {}; is a release in progress?", path.display()))?;
+
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    fn save(&self, root: &Path) -> Result<()> {
+        let path = Self::path(root);
+        std::fs::create_dir_all(path.parent().expect("state path should have parent"))?;
+        std::fs::write(&path, serde_json::to_vec_pretty(self)?)?;
+
+        Ok(())
+    }
+}
+
+/// Resolve versions of a crate having release tags.
+pub(crate) fn tagged_versions(
+    repo: &Repository,
+    settings: &ReleaseSettings,
+    krate: &WorkspaceCrate,
+) -> Result<BTreeMap<semver::Version, String>> {
+    let prefix = settings.tag_name(&krate.name, "");
+
+    let mut res = BTreeMap::new();
+
+    for tag in repo
+        .tag_names(Some(&format!("{}*", prefix)))?
+        .iter()
+        .flatten()
+    {
+        if let Ok(version) = semver::Version::parse(&tag[prefix.len()..]) {
+            res.insert(version, tag.to_string());
+        }
+    }
+
+    Ok(res)
+}
+
+/// Resolve commits touching a crate since a tag.
+fn commits_since(repo: &Repository, tag: &str, path: &Path) -> Result<Vec<Commit>> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL)?;
+    walk.simplify_first_parent()?;
+    walk.push_head()?;
+    walk.hide(
+        repo.revparse_single(&format!("refs/tags/{}", tag))?
+            .peel_to_commit()?
+            .id(),
+    )?;
+
+    let mut res = vec![];
+
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        let parent_tree = match commit.parents().next() {
+            Some(parent) => Some(parent.tree()?),
+            None => None,
+        };
+
+        let mut options = DiffOptions::new();
+        options.pathspec(path);
+
+        let diff = repo.diff_tree_to_tree(
+            parent_tree.as_ref(),
+            Some(&commit.tree()?),
+            Some(&mut options),
+        )?;
+
+        if diff.deltas().len() > 0 {
+            let id = commit.as_object().short_id()?;
+
+            res.push(Commit::from_message(
+                id.as_str().unwrap_or_default(),
+                commit.message().unwrap_or_default(),
+            ));
+        }
+    }
+
+    Ok(res)
+}
+
+/// Compute the release of a crate, if it needs one.
+///
+/// A crate whose current version isn't tagged is released at that version,
+/// without any pre-release suffix. A crate whose current version is tagged
+/// is released if it changed since, with a version bump derived from the
+/// conventional commits since. In either case, the version is raised if
+/// the conventional commits require a larger bump than it represents.
+fn plan_release(
+    repo: &Repository,
+    workspace: &Workspace,
+    krate: &WorkspaceCrate,
+    date: &str,
+) -> Result<Option<PlannedRelease>> {
+    let mut base = krate.version.clone();
+    base.pre = semver::Prerelease::EMPTY;
+    base.build = semver::BuildMetadata::EMPTY;
+
+    let tags = tagged_versions(repo, &workspace.settings, krate)?;
+    let previous = tags.range(..=&base).next_back();
+
+    let commits = match previous {
+        Some((_, tag)) => commits_since(repo, tag, &krate.path)?,
+        None => vec![],
+    };
+
+    let version = match previous {
+        Some((version, _)) if *version == base => {
+            match changelog::bump_level(&commits) {
+                Some(level) => level.apply(version),
+                // Nothing changed since the last release.
+                None => return Ok(None),
+            }
+        }
+        Some((version, _)) => match changelog::bump_level(&commits) {
+            Some(level) => base.clone().max(level.apply(version)),
+            None => base,
+        },
+        None => base,
+    };
+
+    Ok(Some(PlannedRelease {
+        name: krate.name.clone(),
+        path: krate.path.clone(),
+        previous_version: previous.map(|(v, _)| v.to_string()),
+        changelog: changelog::render_section(&version, date, &commits),
+        version: version.to_string(),
+        published: false,
+        tagged: false,
+    }))
+}
+
+/// Compute releases of workspace crates, in publish order.
+///
+/// If `packages` is non-empty, only the named crates are considered.
+pub fn plan_releases(
+    repo: &Repository,
+    workspace: &Workspace,
+    packages: &[String],
+    date: &str,
+) -> Result<Vec<PlannedRelease>> {
+    for name in packages {
+        if !workspace.crates.contains_key(name) {
+            return Err(anyhow!("{} is not a workspace crate", name));
+        }
+    }
+
+    let mut res = vec![];
+
+    for krate in workspace.publish_order()? {
+        if !krate.releasable || (!packages.is_empty() && !packages.contains(&krate.name)) {
+            continue;
+        }
+
+        if let Some(release) = plan_release(repo, workspace, krate, date)? {
+            res.push(release);
+        }
+    }
+
+    Ok(res)
+}
+
+/// Set the version in the `[package]` section of a manifest.
+pub fn set_package_version(manifest: &str, version: &str) -> Result<String> {
+    let mut in_package = false;
+    let mut found = false;
+
+    let lines = manifest
+        .split_inclusive('\n')
+        .map(|line| {
+            let trimmed = line.trim();
+
+            if trimmed.starts_with('[') {
+                in_package = trimmed == "[package]";
+            } else if in_package && !found && trimmed.starts_with("version") {
+                if let Some(m) = RE_VERSION_KEY.find(line) {
+                    found = true;
+                    return format!(
+                        "{}version = \"{}\"{}",
+                        &line[..m.start()],
+                        version,
+                        &line[m.end()..]
+                    );
+                }
+            }
+
+            line.to_string()
+        })
+        .collect::<String>();
+
+    if found {
+        Ok(lines)
+    } else {
+        Err(anyhow!("unable to find package version in manifest"))
+    }
+}
+
+/// Set the version requirement of a path dependency in a manifest.
+///
+/// Both `[dependencies.<name>]` tables and inline tables are updated, across
+/// all dependency kinds and targets.
+pub fn set_dependency_version(manifest: &str, dependency: &str, version: &str) -> String {
+    let table_suffix = format!("dependencies.{}]", dependency);
+    let inline_prefix = format!("{} = {{", dependency);
+
+    let mut in_dependency_table = false;
+    let mut in_dependencies = false;
+
+    manifest
+        .split_inclusive('\n')
+        .map(|line| {
+            let trimmed = line.trim();
+
+            if trimmed.starts_with('[') {
+                in_dependency_table = trimmed.ends_with(&table_suffix);
+                in_dependencies = trimmed.ends_with("dependencies]");
+            } else if (in_dependency_table && trimmed.starts_with("version"))
+                || (in_dependencies
+                    && trimmed.starts_with(&inline_prefix)
+                    && trimmed.contains("path"))
+            {
+                return RE_VERSION_KEY
+                    .replace(line, format!("version = \"{}\"", version).as_str())
+                    .to_string();
+            }
+
+            line.to_string()
+        })
+        .collect()
+}
+
+/// Apply a crate's pre-release replacements.
+fn apply_replacements(
+    root: &Path,
+    krate: &WorkspaceCrate,
+    release: &PlannedRelease,
+    date: &str,
+) -> Result<()> {
+    for replacement in &krate.replacements {
+        let path = root.join(&replacement.file);
+        let data = std::fs::read_to_string(&path)
+            .with_context(|| format!("reading {}", path.display()))?;
+
+        let search = regex::Regex::new(&replacement.search)?;
+
+        let count = search.find_iter(&data).count();
+        if let Some(exactly) = replacement.exactly {
+            if count != exactly {
+                return Err(anyhow!(
+                    "{}: expected {} matches of {:?}; found {}",
+                    path.display(),
+                    exactly,
+                    replacement.search,
+                    count
+                ));
+            }
+        }
+
+        let replace = expand_template(
+            &replacement.replace,
+            &krate.name,
+            &release.version,
+            release.previous_version.as_deref().unwrap_or_default(),
+            date,
+        );
+
+        std::fs::write(
+            &path,
+            search.replace_all(&data, replace.as_str()).as_bytes(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Write new versions, replacements, and changelogs to the working directory.
+fn apply_releases(workspace: &Workspace, releases: &[PlannedRelease], date: &str) -> Result<()> {
+    let root = &workspace.root;
+
+    for release in releases {
+        let krate = &workspace.crates[&release.name];
+
+        println!("{}: setting version to {}", release.name, release.version);
+        let manifest_path = root.join(&krate.path).join("Cargo.toml");
+        let manifest = std::fs::read_to_string(&manifest_path)?;
+        std::fs::write(
+            &manifest_path,
+            set_package_version(&manifest, &release.version)
+                .with_context(|| format!("updating {}", manifest_path.display()))?,
+        )?;
+
+        for other in workspace.crates.values() {
+            if other.dependencies.contains(&release.name) {
+                let path = root.join(&other.path).join("Cargo.toml");
+                let manifest = std::fs::read_to_string(&path)?;
+                std::fs::write(
+                    &path,
+                    set_dependency_version(&manifest, &release.name, &release.version),
+                )?;
+            }
+        }
+
+        apply_replacements(root, krate, release, date)?;
+
+        changelog::prepend_section(
+            &root.join(&krate.path).join("CHANGELOG.md"),
+            &release.changelog,
+        )?;
+    }
+
+    // Hooks run once all versions are updated, as they may depend on them.
+    for release in releases {
+        let krate = &workspace.crates[&release.name];
+
+        if let Some((program, args)) = krate.pre_release_hook.split_first() {
+            println!("{}: running pre-release hook", release.name);
+            cmd(program, args)
+                .dir(root.join(&krate.path))
+                .env("WORKSPACE_ROOT", root)
+                .env("CRATE_ROOT", root.join(&krate.path))
+                .env("CRATE_NAME", &release.name)
+                .env(
+                    "PREV_VERSION",
+                    release.previous_version.as_deref().unwrap_or_default(),
+                )
+                .env("NEW_VERSION", &release.version)
+                .run()
+                .with_context(|| format!("running pre-release hook for {}", release.name))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Ensure the working directory has no uncommitted changes.
+fn ensure_clean(repo: &Repository) -> Result<()> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(false);
+
+    if repo.statuses(Some(&mut options))?.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("working directory has uncommitted changes"))
+    }
+}
+
+fn print_plan(releases: &[PlannedRelease]) {
+    if releases.is_empty() {
+        println!("nothing to release");
+    }
+
+    for release in releases {
+        println!(
+            "{} {} -> {}",
+            release.name,
+            release
+                .previous_version
+                .as_deref()
+                .unwrap_or("(unreleased)"),
+            release.version
+        );
+    }
+}
+
+/// Options controlling a release.
+#[derive(Clone, Debug, Default)]
+pub struct ReleaseOptions {
+    /// Limit the release to these crates.
+    pub packages: Vec<String>,
+    /// Only print the plan and perform dry-run publishes.
+    pub dry_run: bool,
+    /// Continue a previously interrupted release.
+    pub resume: bool,
+    /// Push the release commit and tags when done.
+    pub push: bool,
+}
+
+fn today() -> String {
+    time::OffsetDateTime::now_utc().date().to_string()
+}
+
+/// Print the releases that would be performed.
+pub fn command_plan(repo_root: &Path, packages: &[String], changelogs: bool) -> Result<()> {
+    let repo = Repository::discover(repo_root)?;
+    let workspace = Workspace::load(repo_root)?;
+
+    println!(
+        "publish order: {}",
+        workspace
+            .publish_order()?
+            .into_iter()
+            .filter(|c| c.releasable)
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let releases = plan_releases(&repo, &workspace, packages, &today())?;
+    print_plan(&releases);
+
+    if changelogs {
+        for release in &releases {
+            println!("\n# {}\n\n{}", release.name, release.changelog.trim_end());
+        }
+    }
+
+    Ok(())
+}
+
+/// Perform a release of workspace crates.
+///
+/// Versions are bumped, changelogs are written, and the result is committed.
+/// Then each crate is published and tagged in dependency order. Progress is
+/// recorded in `target/release-state.json` so an interrupted release can be
+/// resumed.
+pub fn command_release(repo_root: &Path, options: &ReleaseOptions) -> Result<()> {
+    let repo = Repository::discover(repo_root)?;
+    let workspace = Workspace::load(repo_root)?;
+    let date = today();
+
+    let mut state = if options.resume {
+        let state = ReleaseState::load(repo_root)?;
+        println!("resuming release");
+        print_plan(&state.releases);
+        state
+    } else {
+        if ReleaseState::path(repo_root).exists() {
+            return Err(anyhow!(
+                "a release is in progress; resume it with --resume or delete {}",
+                ReleaseState::path(repo_root).display()
+            ));
+        }
+
+        let releases = plan_releases(&repo, &workspace, &options.packages, &date)?;
+        print_plan(&releases);
+
+        ReleaseState {
+            releases,
+            applied: false,
+            committed: false,
+        }
+    };
+
+    if state.releases.is_empty() {
+        return Ok(());
+    }
+
+    if options.dry_run {
+        let mut failed = vec![];
+
+        for release in &state.releases {
+            println!("\n{}", release.changelog.trim_end());
+
+            if let Err(e) = run_cmd(
+                &release.name,
+                repo_root,
+                "cargo",
+                vec!["publish", "--dry-run", "--allow-dirty", "-p", &release.name],
+                vec![],
+            ) {
+                println!("{}: dry-run publish failed: {}", release.name, e);
+                failed.push(release.name.clone());
+            }
+        }
+
+        return if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("dry-run publish failed for {}", failed.join(", ")))
+        };
+    }
+
+    if !state.committed {
+        // A resumed release has already modified the working directory, so
+        // only check for a clean state before applying changes.
+        if !state.applied {
+            ensure_clean(&repo)?;
+            apply_releases(&workspace, &state.releases, &date)?;
+
+            state.applied = true;
+            state.save(repo_root)?;
+        }
+
+        cmd!("cargo", "update", "--workspace", "--offline")
+            .dir(repo_root)
+            .run()
+            .context("updating Cargo.lock")?;
+        cmd!(
+            "git",
+            "commit",
+            "-a",
+            "-m",
+            &workspace.settings.commit_message
+        )
+        .dir(repo_root)
+        .run()
+        .context("committing release")?;
+
+        state.committed = true;
+        state.save(repo_root)?;
+    }
+
+    for i in 0..state.releases.len() {
+        if state.releases[i].published {
+            continue;
+        }
+
+        let name = state.releases[i].name.clone();
+        run_cmd(
+            &name,
+            repo_root,
+            "cargo",
+            vec!["publish", "-p", &name],
+            ALREADY_PUBLISHED.iter().map(|s| s.to_string()).collect(),
+        )
+        .with_context(|| format!("publishing {}; fix and rerun with --resume", name))?;
+
+        state.releases[i].published = true;
+        state.save(repo_root)?;
+    }
+
+    for i in 0..state.releases.len() {
+        if state.releases[i].tagged {
+            continue;
+        }
+
+        let release = &state.releases[i];
+        let tag = workspace.settings.tag_name(&release.name, &release.version);
+
+        if repo.revparse_single(&format!("refs/tags/{}", tag)).is_err() {
+            cmd!(
+                "git",
+                "tag",
+                "-a",
+                &tag,
+                "-m",
+                workspace
+                    .settings
+                    .tag_message(&release.name, &release.version)
+            )
+            .dir(repo_root)
+            .run()
+            .with_context(|| format!("creating tag {}", tag))?;
+        }
+
+        state.releases[i].tagged = true;
+        state.save(repo_root)?;
+    }
+
+    if options.push {
+        cmd!("git", "push", &workspace.settings.push_remote, "HEAD")
+            .dir(repo_root)
+            .run()?;
+        cmd!("git", "push", &workspace.settings.push_remote, "--tags")
+            .dir(repo_root)
+            .run()?;
+    } else {
+        println!(
+            "release complete; push with `git push {0} HEAD && git push {0} --tags`",
+            workspace.settings.push_remote
+        );
+    }
+
+    std::fs::remove_file(ReleaseState::path(repo_root))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_package_version() -> Result<()> {
+        let manifest = "[package]\nname = \"foo\"\nversion = \"0.1.0-pre\"\n\n[dependencies.bar]\nversion = \"0.2.0\"\n";
+
+        assert_eq!(
+            set_package_version(manifest, "0.1.0")?,
+            "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n\n[dependencies.bar]\nversion = \"0.2.0\"\n"
+        );
+        assert!(set_package_version("[dependencies.bar]\nversion = \"0.2.0\"\n", "1.0.0").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_dependency_version() {
+        let manifest = "[package]\nversion = \"0.1.0\"\n\n\
+            [dependencies]\nbar = { version = \"0.2.0\", path = \"../bar\" }\nbarbaz = \"1.0\"\n\n\
+            [dependencies.baz]\nversion = \"0.3.0\"\npath = \"../baz\"\n\n\
+            [target.'cfg(unix)'.dev-dependencies.baz]\nversion = \"0.3.0\"\npath = \"../baz\"\n";
+
+        assert_eq!(
+            set_dependency_version(manifest, "baz", "0.4.0"),
+            "[package]\nversion = \"0.1.0\"\n\n\
+            [dependencies]\nbar = { version = \"0.2.0\", path = \"../bar\" }\nbarbaz = \"1.0\"\n\n\
+            [dependencies.baz]\nversion = \"0.4.0\"\npath = \"../baz\"\n\n\
+            [target.'cfg(unix)'.dev-dependencies.baz]\nversion = \"0.4.0\"\npath = \"../baz\"\n"
+        );
+        assert_eq!(
+            set_dependency_version(manifest, "bar", "0.3.0"),
+            manifest.replace("version = \"0.2.0\"", "version = \"0.3.0\"")
+        );
+    }
+
+    #[test]
+    fn test_publish_order() -> Result<()> {
+        let krate = |name: &str, deps: &[&str]| WorkspaceCrate {
+            name: name.to_string(),
+            version: semver::Version::new(0, 1, 0),
+            path: PathBuf::from(name),
+            releasable: true,
+            dependencies: deps.iter().map(|s| s.to_string()).collect(),
+            replacements: vec![],
+            pre_release_hook: vec![],
+        };
+
+        let mut workspace = Workspace {
+            root: PathBuf::new(),
+            settings: ReleaseSettings::from_manifest(&toml::Value::Table(Default::default())),
+            crates: [
+                krate("app", &["lib-b", "lib-a"]),
+                krate("lib-a", &[]),
+                krate("lib-b", &["lib-a"]),
+                krate("other", &[]),
+            ]
+            .into_iter()
+            .map(|c| (c.name.clone(), c))
+            .collect(),
+        };
+
+        assert_eq!(
+            workspace
+                .publish_order()?
+                .into_iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["lib-a", "other", "lib-b", "app"]
+        );
+
+        workspace
+            .crates
+            .get_mut("lib-a")
+            .unwrap()
+            .dependencies
+            .insert("app".to_string());
+        assert!(workspace.publish_order().is_err());
+
+        Ok(())
+    }
+}
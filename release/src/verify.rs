@@ -0,0 +1,356 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Verification of published crates against the repository.
+
+use {
+    crate::publish::{tagged_versions, Workspace},
+    anyhow::{anyhow, Context, Result},
+    duct::cmd,
+    git2::Repository,
+    std::{
+        collections::BTreeMap,
+        fmt::{Display, Formatter},
+        io::Read,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Files in `.crate` archives generated by Cargo.
+///
+/// Their content doesn't exist in the repository, so it isn't compared.
+const GENERATED_FILES: &[&str] = &["Cargo.toml", "Cargo.lock", ".cargo_vcs_info.json"];
+
+/// A difference between a published crate and the repository.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Discrepancy {
+    /// The version isn't available from the registry.
+    NotPublished,
+    /// A file that should be packaged is missing from the published crate.
+    Missing(String),
+    /// The published crate has a file that wouldn't be packaged.
+    Unexpected(String),
+    /// A published file differs from the repository.
+    Modified(String),
+    /// The published crate was packaged from a different commit than the tag.
+    Revision { expected: String, actual: String },
+}
+
+impl Display for Discrepancy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotPublished => write!(f, "not published"),
+            Self::Missing(path) => write!(f, "missing file: {}", path),
+            Self::Unexpected(path) => write!(f, "unexpected file: {}", path),
+            Self::Modified(path) => write!(f, "modified file: {}", path),
+            Self::Revision { expected, actual } => write!(
+                f,
+                "packaged from commit {}; tag refers to {}",
+                actual, expected
+            ),
+        }
+    }
+}
+
+/// Compare files in a published crate against those that should be packaged.
+///
+/// `expected` holds the files `cargo package` would include, with `None`
+/// content for files generated by Cargo.
+pub fn compare_files(
+    expected: &BTreeMap<String, Option<Vec<u8>>>,
+    published: &BTreeMap<String, Vec<u8>>,
+    commit: &str,
+) -> Vec<Discrepancy> {
+    let mut res = vec![];
+
+    for (path, data) in expected {
+        match (published.get(path), data) {
+            (None, _) => res.push(Discrepancy::Missing(path.clone())),
+            (Some(actual), Some(data)) if actual != data => {
+                res.push(Discrepancy::Modified(path.clone()))
+            }
+            _ => {}
+        }
+    }
+
+    for path in published.keys() {
+        if !expected.contains_key(path) {
+            res.push(Discrepancy::Unexpected(path.clone()));
+        }
+    }
+
+    if let Some(data) = published.get(".cargo_vcs_info.json") {
+        let actual = serde_json::from_slice::<serde_json::Value>(data)
+            .ok()
+            .and_then(|v| v["git"]["sha1"].as_str().map(|s| s.to_string()))
+            .unwrap_or_default();
+
+        if actual != commit {
+            res.push(Discrepancy::Revision {
+                expected: commit.to_string(),
+                actual,
+            });
+        }
+    }
+
+    res
+}
+
+/// Download a crate version from crates.io.
+///
+/// Returns `None` if the version doesn't exist.
+fn download_crate(
+    client: &reqwest::blocking::Client,
+    name: &str,
+    version: &str,
+) -> Result<Option<Vec<u8>>> {
+    let url = format!(
+        "https://static.crates.io/crates/{0}/{0}-{1}.crate",
+        name, version
+    );
+
+    let response = client.get(&url).send()?;
+
+    // Missing objects are reported as 403 by the underlying storage.
+    if matches!(
+        response.status(),
+        reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::FORBIDDEN
+    ) {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        response
+            .error_for_status()
+            .with_context(|| format!("downloading {}", url))?
+            .bytes()?
+            .to_vec(),
+    ))
+}
+
+/// Extract the files in a `.crate` archive.
+///
+/// Paths are relative to the crate root.
+fn crate_files(data: &[u8], name: &str, version: &str) -> Result<BTreeMap<String, Vec<u8>>> {
+    let prefix = format!("{}-{}/", name, version);
+
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(data));
+    let mut res = BTreeMap::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?.to_string_lossy().replace('\\', "/");
+        let path = path
+            .strip_prefix(&prefix)
+            .ok_or_else(|| anyhow!("unexpected path in crate archive: {}", path))?
+            .to_string();
+
+        let mut data = vec![];
+        entry.read_to_end(&mut data)?;
+
+        res.insert(path, data);
+    }
+
+    Ok(res)
+}
+
+/// A git worktree that is removed when dropped.
+struct Worktree<'a> {
+    repo_root: &'a Path,
+    path: PathBuf,
+}
+
+impl<'a> Worktree<'a> {
+    fn new(repo_root: &'a Path, path: PathBuf, commit: &str) -> Result<Self> {
+        cmd!("git", "worktree", "add", "--detach", &path, commit)
+            .dir(repo_root)
+            .stdout_to_stderr()
+            .run()
+            .context("creating git worktree")?;
+
+        Ok(Self { repo_root, path })
+    }
+}
+
+impl<'a> Drop for Worktree<'a> {
+    fn drop(&mut self) {
+        let _ = cmd!("git", "worktree", "remove", "--force", &self.path)
+            .dir(self.repo_root)
+            .stdout_to_stderr()
+            .run();
+    }
+}
+
+/// Resolve the files `cargo package` includes for a crate in a source tree.
+fn expected_files(crate_dir: &Path) -> Result<BTreeMap<String, Option<Vec<u8>>>> {
+    let output = cmd!("cargo", "package", "--list", "--offline", "--allow-dirty")
+        .dir(crate_dir)
+        .read()
+        .with_context(|| format!("listing package files in {}", crate_dir.display()))?;
+
+    let mut res = BTreeMap::new();
+
+    for path in output.lines().filter(|l| !l.is_empty()) {
+        let path = path.replace('\\', "/");
+
+        let data = if GENERATED_FILES.contains(&path.as_str()) {
+            None
+        } else if path == "Cargo.toml.orig" {
+            Some(std::fs::read(crate_dir.join("Cargo.toml"))?)
+        } else {
+            Some(
+                std::fs::read(crate_dir.join(&path))
+                    .with_context(|| format!("reading {}", path))?,
+            )
+        };
+
+        res.insert(path, data);
+    }
+
+    Ok(res)
+}
+
+/// Verify published versions of workspace crates match their tagged sources.
+///
+/// By default, only the most recently tagged version of each crate is
+/// verified.
+pub fn command_verify_published(
+    repo_root: &Path,
+    packages: &[String],
+    all_versions: bool,
+) -> Result<()> {
+    let repo = Repository::discover(repo_root)?;
+    let workspace = Workspace::load(repo_root)?;
+
+    let client = reqwest::blocking::Client::builder()
+        .user_agent("PyOxidizer release verification")
+        .build()?;
+    let temp_dir = tempfile::TempDir::new()?;
+
+    let mut failures = 0;
+
+    for krate in workspace.crates.values() {
+        if !krate.releasable || (!packages.is_empty() && !packages.contains(&krate.name)) {
+            continue;
+        }
+
+        let tags = tagged_versions(&repo, &workspace.settings, krate)?;
+        let tags = if all_versions {
+            tags.into_iter().collect::<Vec<_>>()
+        } else {
+            tags.into_iter().next_back().into_iter().collect()
+        };
+
+        if tags.is_empty() {
+            println!("{}: no release tags; skipping", krate.name);
+        }
+
+        for (version, tag) in tags {
+            let version = version.to_string();
+            let commit = repo
+                .revparse_single(&format!("refs/tags/{}", tag))?
+                .peel_to_commit()?
+                .id()
+                .to_string();
+
+            let discrepancies = match download_crate(&client, &krate.name, &version)? {
+                Some(data) => {
+                    let published = crate_files(&data, &krate.name, &version)?;
+
+                    let worktree = Worktree::new(
+                        repo_root,
+                        temp_dir.path().join(format!("{}-{}", krate.name, version)),
+                        &commit,
+                    )?;
+                    let expected = expected_files(&worktree.path.join(&krate.path))?;
+
+                    compare_files(&expected, &published, &commit)
+                }
+                None => vec![Discrepancy::NotPublished],
+            };
+
+            if discrepancies.is_empty() {
+                println!("{} {}: OK", krate.name, version);
+            } else {
+                failures += 1;
+
+                for discrepancy in discrepancies {
+                    println!("{} {}: {}", krate.name, version, discrepancy);
+                }
+            }
+        }
+    }
+
+    if failures > 0 {
+        Err(anyhow!(
+            "{} published crate versions differ from the repository",
+            failures
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_files() {
+        let expected = [
+            ("Cargo.toml", None),
+            ("Cargo.toml.orig", Some(b"[package]".to_vec())),
+            ("src/lib.rs", Some(b"fn foo() {}".to_vec())),
+            ("README.md", Some(b"readme".to_vec())),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect::<BTreeMap<_, _>>();
+
+        let mut published = [
+            ("Cargo.toml", b"[package] # normalized".to_vec()),
+            ("Cargo.toml.orig", b"[package]".to_vec()),
+            ("src/lib.rs", b"fn foo() {}".to_vec()),
+            ("README.md", b"readme".to_vec()),
+            (
+                ".cargo_vcs_info.json",
+                br#"{"git": {"sha1": "abc"}, "path_in_vcs": "foo"}"#.to_vec(),
+            ),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect::<BTreeMap<_, _>>();
+
+        assert_eq!(
+            compare_files(&expected, &published, "abc"),
+            vec![Discrepancy::Unexpected(".cargo_vcs_info.json".to_string())]
+        );
+
+        let mut expected = expected;
+        expected.insert(".cargo_vcs_info.json".to_string(), None);
+        assert!(compare_files(&expected, &published, "abc").is_empty());
+
+        published.insert("src/lib.rs".to_string(), b"fn evil() {}".to_vec());
+        published.insert("build.rs".to_string(), b"fn main() {}".to_vec());
+        published.remove("README.md");
+
+        assert_eq!(
+            compare_files(&expected, &published, "def"),
+            vec![
+                Discrepancy::Missing("README.md".to_string()),
+                Discrepancy::Modified("src/lib.rs".to_string()),
+                Discrepancy::Unexpected("build.rs".to_string()),
+                Discrepancy::Revision {
+                    expected: "def".to_string(),
+                    actual: "abc".to_string()
+                },
+            ]
+        );
+    }
+}
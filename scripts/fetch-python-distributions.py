@@ -91,6 +91,11 @@ def main():
             target_triple != "x86_64-unknown-linux-musl" and flavor != "static-noopt"
         )
 
+        # pyembed doesn't support free-threaded builds, which have flavors like
+        # freethreaded+pgo+lto.
+        if "freethreaded" in flavor:
+            continue
+
         key = "%s-%s-%s" % (major_minor, target_triple, flavor)
 
         records[key] = {
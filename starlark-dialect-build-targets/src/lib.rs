@@ -24,7 +24,7 @@ use {
     },
     std::{
         borrow::Cow,
-        collections::{BTreeMap, HashMap},
+        collections::{BTreeMap, BTreeSet, HashMap},
         os::raw::c_ulong,
         path::{Path, PathBuf},
     },
@@ -96,6 +96,9 @@ pub struct Target {
     /// Other targets this one depends on.
     pub depends: Vec<String>,
 
+    /// The variant of a target registered with variants this target builds.
+    pub variant: Option<String>,
+
     /// What calling callable returned, if it has been called.
     pub resolved_value: Option<Value>,
 
@@ -125,6 +128,12 @@ pub struct EnvironmentContext {
     /// Order targets are registered in.
     targets_order: Vec<String>,
 
+    /// Variants of targets registered with variants.
+    ///
+    /// Keys are the names targets were registered under. Each variant is
+    /// registered as its own target, named by [variant_target_name()].
+    target_variants: BTreeMap<String, Vec<String>>,
+
     /// Name of the default target.
     default_target: Option<String>,
 
@@ -151,6 +160,7 @@ impl EnvironmentContext {
             target_build_path_prefix: None,
             targets: BTreeMap::new(),
             targets_order: vec![],
+            target_variants: BTreeMap::new(),
             default_target: None,
             resolve_targets: None,
             default_build_script_target: None,
@@ -262,6 +272,7 @@ impl EnvironmentContext {
             Target {
                 callable,
                 depends,
+                variant: None,
                 resolved_value: None,
                 built_target: None,
             },
@@ -276,20 +287,183 @@ impl EnvironmentContext {
         }
     }
 
+    /// Register a named target having multiple variants.
+    ///
+    /// A target is registered for each variant, named by [variant_target_name()].
+    /// The callable receives the variant name via its `variant` keyword argument.
+    /// `target` itself refers to all variants.
+    pub fn register_target_variants(
+        &mut self,
+        target: String,
+        callable: Value,
+        depends: Vec<String>,
+        variants: Vec<String>,
+        default: bool,
+        default_build_script: bool,
+    ) {
+        for variant in &variants {
+            let name = variant_target_name(&target, variant);
+
+            if !self.targets.contains_key(&name) {
+                self.targets_order.push(name.clone());
+            }
+
+            self.targets.insert(
+                name,
+                Target {
+                    callable: callable.clone(),
+                    depends: depends.clone(),
+                    variant: Some(variant.clone()),
+                    resolved_value: None,
+                    built_target: None,
+                },
+            );
+        }
+
+        self.target_variants.insert(target.clone(), variants);
+
+        if default || self.default_target.is_none() {
+            self.default_target = Some(target.clone());
+        }
+
+        if default_build_script || self.default_build_script_target.is_none() {
+            self.default_build_script_target = Some(target);
+        }
+    }
+
+    /// Obtain the variants of a target registered with variants.
+    pub fn target_variants(&self, target: &str) -> Option<&Vec<String>> {
+        self.target_variants.get(target)
+    }
+
+    /// Expand a target name to the names of the targets it refers to.
+    ///
+    /// Targets registered with variants expand to a target per variant. Other
+    /// names are returned as is.
+    pub fn expand_target(&self, target: &str) -> Vec<String> {
+        if let Some(variants) = self.target_variants.get(target) {
+            variants
+                .iter()
+                .map(|variant| variant_target_name(target, variant))
+                .collect()
+        } else {
+            vec![target.to_string()]
+        }
+    }
+
+    /// Resolve the name of the target satisfying a dependency of a target.
+    ///
+    /// A variant target depending on a target registered with the same variant
+    /// depends on that variant. Otherwise the dependency is used as is.
+    pub fn dependency_target(&self, target: &Target, depend: &str) -> String {
+        match (&target.variant, self.target_variants.get(depend)) {
+            (Some(variant), Some(variants)) if variants.contains(variant) => {
+                variant_target_name(depend, variant)
+            }
+            _ => depend.to_string(),
+        }
+    }
+
     /// Determine what targets should be resolved.
     ///
     /// This isn't the full list of targets that will be resolved, only the main
-    /// targets that we will instruct the resolver to resolve.
+    /// targets that we will instruct the resolver to resolve. Targets registered
+    /// with variants are expanded to their variants.
     pub fn targets_to_resolve(&self) -> Vec<String> {
-        if let Some(targets) = &self.resolve_targets {
-            targets.clone()
+        self.requested_targets_to_resolve(self.resolve_targets.as_deref())
+    }
+
+    /// Determine what targets should be resolved for explicitly requested targets.
+    ///
+    /// Like [Self::targets_to_resolve()] but for `targets` instead of the
+    /// targets set by [Self::set_resolve_targets()]. `None` resolves the
+    /// default target.
+    pub fn requested_targets_to_resolve(&self, targets: Option<&[String]>) -> Vec<String> {
+        let targets = if let Some(targets) = targets {
+            targets.to_vec()
         } else if self.build_script_mode && self.default_build_script_target.is_some() {
             vec![self.default_build_script_target.clone().unwrap()]
         } else if let Some(target) = &self.default_target {
             vec![target.to_string()]
         } else {
             Vec::new()
+        };
+
+        targets
+            .iter()
+            .flat_map(|target| self.expand_target(target))
+            .collect()
+    }
+
+    /// Obtain the names of all targets a target transitively depends on.
+    ///
+    /// The returned set includes the target itself. Unknown targets and
+    /// dependency cycles are tolerated: they are simply not descended into.
+    pub fn target_dependency_closure(&self, target: &str) -> BTreeSet<String> {
+        let mut closure = BTreeSet::new();
+        let mut stack = vec![target.to_string()];
+
+        while let Some(name) = stack.pop() {
+            if !closure.insert(name.clone()) {
+                continue;
+            }
+
+            if let Some(t) = self.targets.get(&name) {
+                stack.extend(
+                    t.depends
+                        .iter()
+                        .map(|d| self.dependency_target(t, d))
+                        .filter(|d| !closure.contains(d)),
+                );
+            } else if self.target_variants.contains_key(&name) {
+                stack.extend(self.expand_target(&name));
+            }
+        }
+
+        closure
+    }
+
+    /// Partition targets into groups that can be built independently of each other.
+    ///
+    /// Targets sharing any transitive dependency are placed in the same group,
+    /// so no target is ever built by more than one group. Groups are ordered by
+    /// the first appearance of one of their members in `targets` and targets
+    /// within a group retain their relative order.
+    pub fn independent_target_groups(&self, targets: &[String]) -> Vec<Vec<String>> {
+        let mut groups: Vec<(Vec<String>, BTreeSet<String>)> = vec![];
+
+        for target in targets {
+            let mut closure = self.target_dependency_closure(target);
+            let mut members = vec![target.clone()];
+
+            // Absorb every existing group overlapping this target's closure. The
+            // merged group takes the position of the earliest overlapping group.
+            let mut insert_index = None;
+            let mut index = 0;
+            while index < groups.len() {
+                if groups[index].1.is_disjoint(&closure) {
+                    index += 1;
+                    continue;
+                }
+
+                let (mut existing_members, existing_closure) = groups.remove(index);
+                closure.extend(existing_closure);
+                existing_members.append(&mut members);
+                members = existing_members;
+
+                if insert_index.is_none() {
+                    insert_index = Some(index);
+                }
+            }
+
+            let mut seen = BTreeSet::new();
+            members.retain(|t| seen.insert(t.clone()));
+            members.sort_by_key(|t| targets.iter().position(|x| x == t));
+
+            groups.insert(insert_index.unwrap_or(groups.len()), (members, closure));
         }
+
+        groups.into_iter().map(|(members, _)| members).collect()
     }
 }
 
@@ -726,6 +900,11 @@ impl TryToOptional<Vec<StringHashMap>> for Value {
 
 const ENVIRONMENT_CONTEXT_SYMBOL: &str = "BUILD_CONTEXT";
 
+/// Obtain the name of the target building a variant of a target.
+pub fn variant_target_name(target: &str, variant: &str) -> String {
+    format!("{}-{}", target, variant)
+}
+
 /// Obtain the `Value` holding the `EnvironmentContext` for a Starlark environment.
 ///
 /// This is a helper function. The returned `Value` needs to be casted
@@ -762,7 +941,7 @@ fn starlark_print(args: &[Value]) -> ValueResult {
     Ok(Value::new(NoneType::None))
 }
 
-/// register_target(target, callable, depends=None, default=false)
+/// register_target(target, callable, depends=None, default=false, default_build_script=false, variants=None)
 fn starlark_register_target(
     type_values: &TypeValues,
     target: String,
@@ -770,21 +949,53 @@ fn starlark_register_target(
     depends: Value,
     default: bool,
     default_build_script: bool,
+    variants: Value,
 ) -> ValueResult {
     required_type_arg("callable", "function", &callable)?;
     optional_list_arg("depends", "string", &depends)?;
+    optional_list_arg("variants", "string", &variants)?;
 
     let depends = match depends.get_type() {
         "list" => depends.iter()?.iter().map(|x| x.to_string()).collect(),
         _ => Vec::new(),
     };
 
+    let variants: Option<Vec<String>> = variants.try_to_optional()?;
+
+    if let Some(variants) = &variants {
+        let mut seen = BTreeSet::new();
+
+        for variant in variants {
+            if variant.is_empty() || !seen.insert(variant) {
+                return Err(ValueError::from(RuntimeError {
+                    code: "BUILD_TARGETS",
+                    message: format!(
+                        "variants of target {} must be unique non-empty strings",
+                        target
+                    ),
+                    label: "register_target()".to_string(),
+                }));
+            }
+        }
+    }
+
     let raw_context = get_context_value(type_values)?;
     let mut context = raw_context
         .downcast_mut::<EnvironmentContext>()?
         .ok_or(ValueError::IncorrectParameterType)?;
 
-    context.register_target(target, callable, depends, default, default_build_script);
+    if let Some(variants) = variants {
+        context.register_target_variants(
+            target,
+            callable,
+            depends,
+            variants,
+            default,
+            default_build_script,
+        );
+    } else {
+        context.register_target(target, callable, depends, default, default_build_script);
+    }
 
     Ok(Value::new(NoneType::None))
 }
@@ -798,7 +1009,10 @@ fn starlark_register_target(
 ///
 /// If the target depends on other targets, those targets will be resolved
 /// recursively before calling the target's function.
-fn starlark_resolve_target(
+///
+/// Resolving a target registered with variants resolves every variant and
+/// returns a list of their values.
+pub fn starlark_resolve_target(
     type_values: &TypeValues,
     call_stack: &mut CallStack,
     target: String,
@@ -806,12 +1020,28 @@ fn starlark_resolve_target(
     // The block is here so the borrowed `EnvironmentContext` goes out of
     // scope before we call into another Starlark function. Without this, we
     // could get a double borrow.
-    let target_entry = {
+    let (target_entry, depends) = {
         let raw_context = get_context_value(type_values)?;
         let context = raw_context
             .downcast_ref::<EnvironmentContext>()
             .ok_or(ValueError::IncorrectParameterType)?;
 
+        if context.target_variants(&target).is_some() {
+            let variant_targets = context.expand_target(&target);
+            drop(context);
+
+            let mut values = Vec::new();
+            for variant_target in variant_targets {
+                values.push(starlark_resolve_target(
+                    type_values,
+                    call_stack,
+                    variant_target,
+                )?);
+            }
+
+            return Ok(Value::from(values));
+        }
+
         // If we have a resolved value for this target, return it.
         if let Some(v) = if let Some(t) = context.get_target(&target) {
             t.resolved_value.as_ref().cloned()
@@ -823,20 +1053,28 @@ fn starlark_resolve_target(
 
         warn!("resolving target {}", target);
 
-        match context.get_target(&target) {
+        let target_entry = match context.get_target(&target) {
             Some(v) => Ok((*v).clone()),
             None => Err(ValueError::from(RuntimeError {
                 code: "BUILD_TARGETS",
                 message: format!("target {} does not exist", target),
                 label: "resolve_target()".to_string(),
             })),
-        }?
+        }?;
+
+        let depends = target_entry
+            .depends
+            .iter()
+            .map(|d| context.dependency_target(&target_entry, d))
+            .collect::<Vec<_>>();
+
+        (target_entry, depends)
     };
 
     // Resolve target dependencies.
     let mut args = Vec::new();
 
-    for depend_target in target_entry.depends {
+    for depend_target in depends {
         args.push(starlark_resolve_target(
             type_values,
             call_stack,
@@ -844,14 +1082,14 @@ fn starlark_resolve_target(
         )?);
     }
 
-    let res = target_entry.callable.call(
-        call_stack,
-        type_values,
-        args,
-        LinkedHashMap::new(),
-        None,
-        None,
-    )?;
+    let mut kwargs = LinkedHashMap::new();
+    if let Some(variant) = &target_entry.variant {
+        kwargs.insert("variant".into(), Value::from(variant.as_str()));
+    }
+
+    let res = target_entry
+        .callable
+        .call(call_stack, type_values, args, kwargs, None, None)?;
 
     // TODO consider replacing the target's callable with a new function that returns the
     // resolved value. This will ensure a target function is only ever called once.
@@ -940,9 +1178,18 @@ starlark_module! { build_targets_module =>
         callable,
         depends = NoneType::None,
         default: bool = false,
-        default_build_script: bool = false
+        default_build_script: bool = false,
+        variants = NoneType::None
     ) {
-        starlark_register_target(env, target, callable, depends, default, default_build_script)
+        starlark_register_target(
+            env,
+            target,
+            callable,
+            depends,
+            default,
+            default_build_script,
+            variants,
+        )
     }
 
     resolve_target(env env, call_stack cs, target: String) {
@@ -1073,13 +1320,23 @@ pub fn run_target(
             .map_err(|e| anyhow!("unable to resolve context value: {:?}", e))?;
         let context = context_value.downcast_ref::<EnvironmentContext>().unwrap();
 
-        if let Some(t) = target {
+        let target = if let Some(t) = target {
             t.to_string()
         } else if let Some(t) = context.default_target() {
             t.to_string()
         } else {
             return Err(anyhow!("unable to determine target to run"));
+        };
+
+        if context.target_variants(&target).is_some() {
+            return Err(anyhow!(
+                "target {} has multiple variants; specify one of {} to run",
+                target,
+                context.expand_target(&target).join(", ")
+            ));
         }
+
+        target
     };
 
     let resolved_target = build_target(env, type_values, call_stack, &target)?;
@@ -1139,4 +1396,110 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_register_target_variants() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("def exe(variant): return 'exe-' + variant")?;
+        env.eval("def install(exe, variant): return 'install-' + exe")?;
+        env.eval("def docs(): return 'docs'")?;
+        env.eval("register_target('exe', exe, variants=['cpu', 'cuda'])")?;
+        env.eval("register_target('install', install, depends=['exe'], variants=['cpu', 'cuda'], default=True)")?;
+        env.eval("register_target('docs', docs)")?;
+
+        {
+            let context_value = get_context_value(&env.type_values).unwrap();
+            let context = context_value
+                .downcast_ref::<EnvironmentContext>()
+                .ok_or(ValueError::IncorrectParameterType)
+                .unwrap();
+
+            assert_eq!(context.targets().len(), 5);
+            assert!(context.get_target("exe").is_none());
+            assert_eq!(
+                context.get_target("exe-cuda").unwrap().variant,
+                Some("cuda".to_string())
+            );
+            assert_eq!(context.default_target(), Some("install"));
+            assert_eq!(
+                context.targets_to_resolve(),
+                vec!["install-cpu".to_string(), "install-cuda".to_string()]
+            );
+            assert_eq!(
+                context.requested_targets_to_resolve(Some(&["exe".to_string()])),
+                vec!["exe-cpu".to_string(), "exe-cuda".to_string()]
+            );
+            assert_eq!(
+                context.requested_targets_to_resolve(None),
+                context.targets_to_resolve()
+            );
+            assert_eq!(
+                context.target_dependency_closure("install"),
+                [
+                    "install",
+                    "install-cpu",
+                    "install-cuda",
+                    "exe-cpu",
+                    "exe-cuda"
+                ]
+                .iter()
+                .map(|x| x.to_string())
+                .collect()
+            );
+        }
+
+        assert_eq!(
+            env.eval("resolve_target('install-cuda')")?.to_string(),
+            "install-exe-cuda"
+        );
+        assert_eq!(
+            env.eval("resolve_target('exe')")?.to_string(),
+            "[\"exe-cpu\", \"exe-cuda\"]"
+        );
+
+        assert!(env
+            .eval("register_target('bad', exe, variants=['cpu', 'cpu'])")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_independent_target_groups() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("def f(*args): pass")?;
+        env.eval("register_target('base', f)")?;
+        env.eval("register_target('a', f, depends=['base'])")?;
+        env.eval("register_target('b', f, depends=['base'])")?;
+        env.eval("register_target('c', f)")?;
+        env.eval("register_target('d', f, depends=['c'])")?;
+        env.eval("register_target('e', f)")?;
+
+        let context_value = get_context_value(&env.type_values).unwrap();
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)
+            .unwrap();
+
+        assert_eq!(
+            context.target_dependency_closure("a"),
+            ["a", "base"].iter().map(|x| x.to_string()).collect()
+        );
+
+        let targets = ["e", "a", "d", "b", "c"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            context.independent_target_groups(&targets),
+            vec![
+                vec!["e".to_string()],
+                vec!["a".to_string(), "b".to_string()],
+                vec!["d".to_string(), "c".to_string()],
+            ]
+        );
+
+        Ok(())
+    }
 }
@@ -160,3 +160,56 @@ pub fn find_undefined_elf_symbols(buffer: &[u8], elf: &goblin::elf::Elf) -> Vec<
 
     res
 }
+
+/// Find the newest symbol version of a namespace required by an ELF binary.
+///
+/// `namespace` is the prefix of symbol version names. e.g. `GLIBC` for
+/// versions like `GLIBC_2.17`.
+///
+/// Returns `None` if the data isn't a dynamically linked ELF binary or
+/// doesn't require a symbol version in the namespace.
+pub fn find_required_symbol_version(buffer: &[u8], namespace: &str) -> Option<String> {
+    let elf = match goblin::Object::parse(buffer) {
+        Ok(goblin::Object::Elf(elf)) => elf,
+        _ => return None,
+    };
+
+    elf.dynamic.as_ref()?;
+
+    let prefix = format!("{}_", namespace);
+
+    find_undefined_elf_symbols(buffer, &elf)
+        .into_iter()
+        .filter_map(|symbol| {
+            symbol
+                .version?
+                .strip_prefix(&prefix)
+                .map(|version| version.to_string())
+        })
+        .max_by(|a, b| match version_compare::compare(a, b) {
+            Ok(version_compare::Cmp::Lt) => std::cmp::Ordering::Less,
+            Ok(version_compare::Cmp::Gt) => std::cmp::Ordering::Greater,
+            _ => std::cmp::Ordering::Equal,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_required_symbol_version() {
+        assert_eq!(
+            find_required_symbol_version(b"not an ELF binary", "GLIBC"),
+            None
+        );
+
+        let exe = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+
+        if cfg!(all(target_os = "linux", target_env = "gnu")) {
+            let version = find_required_symbol_version(&exe, "GLIBC").unwrap();
+            assert!(version.starts_with("2."));
+            assert_eq!(find_required_symbol_version(&exe, "NOT_A_NAMESPACE"), None);
+        }
+    }
+}
@@ -0,0 +1,411 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Detect binary traits commonly triggering anti-virus heuristics.
+//!
+//! Anti-virus products (like Windows Defender) and operating system
+//! gatekeepers (like macOS Gatekeeper) are suspicious of binaries that
+//! look like they were produced by malware tooling. Self-contained
+//! applications tend to have some of these traits by accident. The
+//! functionality in this module identifies them so they can be remediated.
+
+use {
+    anyhow::{anyhow, Result},
+    byteorder::{LittleEndian, ReadBytesExt},
+    std::{fmt::Formatter, io::Cursor},
+};
+
+/// Size of blocks to compute entropy over.
+const ENTROPY_BLOCK_SIZE: usize = 65536;
+
+/// Shannon entropy in bits per byte at which data is considered compressed or encrypted.
+pub const HIGH_ENTROPY_THRESHOLD: f64 = 7.2;
+
+/// Section names used by common executable packers.
+const PACKER_SECTION_NAMES: &[&str] = &[".aspack", ".MPRESS1", ".MPRESS2", "UPX0", "UPX1", "UPX2"];
+
+/// Resource type ID of `VS_VERSIONINFO` resources in PE binaries.
+const RT_VERSION: u32 = 16;
+
+/// A binary trait commonly triggering anti-virus heuristics.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum AvHeuristic {
+    /// A section or segment is both writable and executable.
+    ///
+    /// This is characteristic of self-modifying code and runtime unpackers.
+    WritableExecutable,
+
+    /// The binary was processed by an executable packer (like UPX).
+    ExecutablePacker,
+
+    /// A large region of data has entropy indicative of compression or encryption.
+    HighEntropyData,
+
+    /// A PE binary lacks a `VS_VERSIONINFO` resource.
+    MissingVersionInfo,
+
+    /// The binary does not have a code signature.
+    MissingSignature,
+}
+
+impl std::fmt::Display for AvHeuristic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::WritableExecutable => "writable-executable",
+            Self::ExecutablePacker => "executable-packer",
+            Self::HighEntropyData => "high-entropy-data",
+            Self::MissingVersionInfo => "missing-version-info",
+            Self::MissingSignature => "missing-signature",
+        })
+    }
+}
+
+/// An instance of an [AvHeuristic] found in a binary.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AvHeuristicFinding {
+    /// The heuristic that was matched.
+    pub heuristic: AvHeuristic,
+
+    /// Human readable description of what matched.
+    pub detail: String,
+}
+
+impl AvHeuristicFinding {
+    fn new(heuristic: AvHeuristic, detail: impl ToString) -> Self {
+        Self {
+            heuristic,
+            detail: detail.to_string(),
+        }
+    }
+}
+
+/// Compute the Shannon entropy of data, in bits per byte.
+pub fn shannon_entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts = [0usize; 256];
+    for b in data {
+        counts[*b as usize] += 1;
+    }
+
+    let len = data.len() as f64;
+
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Find high entropy regions in data.
+///
+/// Data is split into fixed size blocks and adjacent blocks whose entropy
+/// exceeds [HIGH_ENTROPY_THRESHOLD] are coalesced. Returns the
+/// `(offset, length, entropy)` of each region. Trailing data smaller than a
+/// block is ignored, as entropy of small inputs is not meaningful.
+pub fn find_high_entropy_regions(data: &[u8]) -> Vec<(usize, usize, f64)> {
+    let mut regions: Vec<(usize, usize, f64)> = vec![];
+
+    for (i, block) in data.chunks_exact(ENTROPY_BLOCK_SIZE).enumerate() {
+        let entropy = shannon_entropy(block);
+        if entropy < HIGH_ENTROPY_THRESHOLD {
+            continue;
+        }
+
+        let offset = i * ENTROPY_BLOCK_SIZE;
+
+        match regions.last_mut() {
+            Some((start, length, region_entropy)) if *start + *length == offset => {
+                let blocks = (*length / ENTROPY_BLOCK_SIZE) as f64;
+                *region_entropy = (*region_entropy * blocks + entropy) / (blocks + 1.0);
+                *length += ENTROPY_BLOCK_SIZE;
+            }
+            _ => {
+                regions.push((offset, ENTROPY_BLOCK_SIZE, entropy));
+            }
+        }
+    }
+
+    regions
+}
+
+fn high_entropy_findings(name: &str, data: &[u8]) -> Vec<AvHeuristicFinding> {
+    find_high_entropy_regions(data)
+        .into_iter()
+        .map(|(offset, length, entropy)| {
+            AvHeuristicFinding::new(
+                AvHeuristic::HighEntropyData,
+                format!(
+                    "{} bytes at offset {:#x} of {} have entropy {:.2} bits/byte",
+                    length, offset, name, entropy
+                ),
+            )
+        })
+        .collect()
+}
+
+/// Whether a PE binary's resources contain a `VS_VERSIONINFO` resource.
+fn pe_has_version_info(data: &[u8], pe: &goblin::pe::PE) -> Result<bool> {
+    let optional_header = pe
+        .header
+        .optional_header
+        .ok_or_else(|| anyhow!("PE binary has no optional header"))?;
+
+    let resource_table = match optional_header.data_directories.get_resource_table() {
+        Some(table) if table.size > 0 => table,
+        _ => return Ok(false),
+    };
+
+    let offset = goblin::pe::utils::find_offset(
+        resource_table.virtual_address as usize,
+        &pe.sections,
+        optional_header.windows_fields.file_alignment,
+        &goblin::pe::options::ParseOptions::default(),
+    )
+    .ok_or_else(|| anyhow!("unable to resolve offset of PE resources"))?;
+
+    // IMAGE_RESOURCE_DIRECTORY is 16 bytes, ending with counts of named and ID
+    // entries. 8 byte IMAGE_RESOURCE_DIRECTORY_ENTRY records follow, named
+    // entries first.
+    let directory = data
+        .get(offset..offset + 16)
+        .ok_or_else(|| anyhow!("PE resource directory out of bounds"))?;
+    let mut reader = Cursor::new(&directory[12..]);
+    let named_count = reader.read_u16::<LittleEndian>()? as usize;
+    let id_count = reader.read_u16::<LittleEndian>()? as usize;
+
+    let entries_offset = offset + 16 + named_count * 8;
+
+    for i in 0..id_count {
+        let entry_offset = entries_offset + i * 8;
+        let entry = data
+            .get(entry_offset..entry_offset + 8)
+            .ok_or_else(|| anyhow!("PE resource directory entry out of bounds"))?;
+
+        if Cursor::new(entry).read_u32::<LittleEndian>()? == RT_VERSION {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn analyze_pe(data: &[u8], pe: &goblin::pe::PE) -> Result<Vec<AvHeuristicFinding>> {
+    let mut findings = vec![];
+
+    for section in &pe.sections {
+        let name = section.name().unwrap_or("<unknown>");
+
+        if PACKER_SECTION_NAMES.contains(&name) {
+            findings.push(AvHeuristicFinding::new(
+                AvHeuristic::ExecutablePacker,
+                format!("section {} is produced by an executable packer", name),
+            ));
+        }
+
+        let wx = goblin::pe::section_table::IMAGE_SCN_MEM_WRITE
+            | goblin::pe::section_table::IMAGE_SCN_MEM_EXECUTE;
+        if section.characteristics & wx == wx {
+            findings.push(AvHeuristicFinding::new(
+                AvHeuristic::WritableExecutable,
+                format!("section {} is writable and executable", name),
+            ));
+        }
+
+        let start = section.pointer_to_raw_data as usize;
+        let end = start + section.size_of_raw_data as usize;
+        if let Some(section_data) = data.get(start..end) {
+            findings.extend(high_entropy_findings(
+                &format!("section {}", name),
+                section_data,
+            ));
+        }
+    }
+
+    if !pe_has_version_info(data, pe)? {
+        findings.push(AvHeuristicFinding::new(
+            AvHeuristic::MissingVersionInfo,
+            "no VS_VERSIONINFO resource is present",
+        ));
+    }
+
+    let signed = pe
+        .header
+        .optional_header
+        .and_then(|h| *h.data_directories.get_certificate_table())
+        .map(|table| table.size > 0)
+        .unwrap_or(false);
+    if !signed {
+        findings.push(AvHeuristicFinding::new(
+            AvHeuristic::MissingSignature,
+            "no Authenticode signature is present",
+        ));
+    }
+
+    Ok(findings)
+}
+
+fn analyze_elf(data: &[u8], elf: &goblin::elf::Elf) -> Vec<AvHeuristicFinding> {
+    let mut findings = vec![];
+
+    for header in &elf.program_headers {
+        let wx = goblin::elf::program_header::PF_W | goblin::elf::program_header::PF_X;
+        if header.p_type == goblin::elf::program_header::PT_LOAD && header.p_flags & wx == wx {
+            findings.push(AvHeuristicFinding::new(
+                AvHeuristic::WritableExecutable,
+                format!(
+                    "loadable segment at offset {:#x} is writable and executable",
+                    header.p_offset
+                ),
+            ));
+        }
+    }
+
+    for section in &elf.section_headers {
+        let name = elf
+            .shdr_strtab
+            .get_at(section.sh_name)
+            .unwrap_or("<unknown>");
+
+        if PACKER_SECTION_NAMES.contains(&name) {
+            findings.push(AvHeuristicFinding::new(
+                AvHeuristic::ExecutablePacker,
+                format!("section {} is produced by an executable packer", name),
+            ));
+        }
+
+        if let Some(section_data) = section.file_range().and_then(|range| data.get(range)) {
+            findings.extend(high_entropy_findings(
+                &format!("section {}", name),
+                section_data,
+            ));
+        }
+    }
+
+    findings
+}
+
+fn analyze_macho(macho: &goblin::mach::MachO) -> Vec<AvHeuristicFinding> {
+    let mut findings = vec![];
+
+    for segment in macho.segments.iter() {
+        let name = segment.name().unwrap_or("<unknown>");
+
+        let wx = goblin::mach::constants::VM_PROT_WRITE | goblin::mach::constants::VM_PROT_EXECUTE;
+        if segment.initprot & wx == wx {
+            findings.push(AvHeuristicFinding::new(
+                AvHeuristic::WritableExecutable,
+                format!("segment {} is writable and executable", name),
+            ));
+        }
+
+        findings.extend(high_entropy_findings(
+            &format!("segment {}", name),
+            segment.data,
+        ));
+    }
+
+    if !macho.load_commands.iter().any(|command| {
+        matches!(
+            command.command,
+            goblin::mach::load_command::CommandVariant::CodeSignature(_)
+        )
+    }) {
+        findings.push(AvHeuristicFinding::new(
+            AvHeuristic::MissingSignature,
+            "no LC_CODE_SIGNATURE load command is present",
+        ));
+    }
+
+    findings
+}
+
+/// Find traits in a binary that commonly trigger anti-virus heuristics.
+///
+/// Supports PE, ELF, and Mach-O binaries, including fat Mach-O binaries.
+pub fn find_av_heuristics(data: &[u8]) -> Result<Vec<AvHeuristicFinding>> {
+    let mut findings = match goblin::Object::parse(data)? {
+        goblin::Object::PE(pe) => analyze_pe(data, &pe)?,
+        goblin::Object::Elf(elf) => analyze_elf(data, &elf),
+        goblin::Object::Mach(goblin::mach::Mach::Binary(macho)) => analyze_macho(&macho),
+        goblin::Object::Mach(goblin::mach::Mach::Fat(multi)) => {
+            let mut findings = vec![];
+
+            for i in 0..multi.narches {
+                if let goblin::mach::SingleArch::MachO(macho) = multi.get(i)? {
+                    findings.extend(analyze_macho(&macho));
+                }
+            }
+
+            findings
+        }
+        goblin::Object::Archive(_) => {
+            return Err(anyhow!("archives are not supported"));
+        }
+        goblin::Object::Unknown(magic) => {
+            return Err(anyhow!("unknown binary magic: {:#x}", magic));
+        }
+    };
+
+    findings.dedup();
+
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shannon_entropy() {
+        assert_eq!(shannon_entropy(b""), 0.0);
+        assert_eq!(shannon_entropy(&[0u8; 1024]), 0.0);
+        assert_eq!(shannon_entropy(b"abab"), 1.0);
+
+        let all_bytes = (0..=255u8).collect::<Vec<_>>();
+        assert_eq!(shannon_entropy(&all_bytes), 8.0);
+    }
+
+    #[test]
+    fn test_find_high_entropy_regions() {
+        let random = (0..=255u8)
+            .cycle()
+            .take(ENTROPY_BLOCK_SIZE * 2)
+            .collect::<Vec<_>>();
+
+        let mut data = vec![0u8; ENTROPY_BLOCK_SIZE];
+        data.extend(&random);
+        data.extend(vec![0u8; ENTROPY_BLOCK_SIZE]);
+        data.extend(&random[0..ENTROPY_BLOCK_SIZE]);
+
+        assert_eq!(
+            find_high_entropy_regions(&data),
+            vec![
+                (ENTROPY_BLOCK_SIZE, ENTROPY_BLOCK_SIZE * 2, 8.0),
+                (ENTROPY_BLOCK_SIZE * 4, ENTROPY_BLOCK_SIZE, 8.0)
+            ]
+        );
+
+        assert!(find_high_entropy_regions(&random[0..1024]).is_empty());
+    }
+
+    #[test]
+    fn test_find_av_heuristics_current_exe() -> Result<()> {
+        let data = std::fs::read(std::env::current_exe()?)?;
+        let findings = find_av_heuristics(&data)?;
+
+        assert!(!findings
+            .iter()
+            .any(|f| f.heuristic == AvHeuristic::WritableExecutable));
+
+        assert!(find_av_heuristics(b"not a binary").is_err());
+
+        Ok(())
+    }
+}
@@ -61,6 +61,17 @@
 //! to different signing *backends* and gives you a relatively clean interface
 //! to attempt code signing. If signing was successful, you'll get a
 //! [SignedOutput] describing where the signed content lives.
+//!
+//! # Notarization
+//!
+//! Apple content distributed outside the App Store should also be notarized
+//! by Apple. Register an [AppleNotarizer] via [Signer::apple_notarizer] to
+//! have signed bundles and Mach-O executables notarized as part of
+//! [SignableSigner::sign]. [AppleNotarizer] can also be used directly to
+//! notarize DMGs, flat packages and zip files.
+
+mod notarization;
+pub use notarization::*;
 
 use {
     apple_codesign::{cryptography::InMemoryPrivateKey, AppleCodesignError, MachOSigner},
@@ -126,16 +137,16 @@ pub enum SigningError {
     CertificateNotUsable(String),
 
     #[error("error resolving certificate chain: {0}")]
-    MacOsCertificateChainResolveFailure(AppleCodesignError),
+    MacOsCertificateChainResolveFailure(Box<AppleCodesignError>),
 
     #[error("path {0} is not signable")]
     PathNotSignable(PathBuf),
 
     #[error("error signing mach-o binary: {0}")]
-    MachOSigningError(AppleCodesignError),
+    MachOSigningError(Box<AppleCodesignError>),
 
     #[error("error signing Apple bundle: {0}")]
-    AppleBundleSigningError(AppleCodesignError),
+    AppleBundleSigningError(Box<AppleCodesignError>),
 
     #[error("error running settings callback: {0}")]
     SettingsCallback(anyhow::Error),
@@ -151,6 +162,21 @@ pub enum SigningError {
 
     #[error("X.509 certificate handling error: {0}")]
     X509Certificate(#[from] X509CertificateError),
+
+    #[error("error notarizing: {0}")]
+    NotarizationError(Box<AppleCodesignError>),
+
+    #[error("notarizing {0} is not supported; only bundles, DMGs, flat packages, zip files, and Mach-O executables can be notarized")]
+    NotarizationUnsupported(PathBuf),
+
+    #[error("no Apple notarizer is registered")]
+    NoAppleNotarizer,
+
+    #[error("error stapling notarization ticket: {0}")]
+    StaplingError(Box<AppleCodesignError>),
+
+    #[error("zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
 }
 
 /// Represents a location where signed data should be written.
@@ -724,6 +750,24 @@ pub type AppleSigningSettingsFn =
 pub type WindowsSignerFn =
     fn(&Signable, &mut tugger_windows_codesign::SigntoolSign) -> Result<(), anyhow::Error>;
 
+/// Options for signing Windows signables.
+///
+/// See the corresponding methods of [tugger_windows_codesign::SigntoolSign].
+#[derive(Clone, Debug, Default)]
+pub struct WindowsSigningOptions {
+    /// Sign with a SHA-1 digest and append a SHA-256 signature.
+    pub dual_sign: bool,
+
+    /// Append signatures to existing signatures instead of replacing them.
+    pub append_signature: bool,
+
+    /// Whether to generate page hashes. [None] uses signtool's default.
+    pub page_hashes: Option<bool>,
+
+    /// Verify all signatures and their certificate chains after signing.
+    pub verify: bool,
+}
+
 /// An entity for performing code signing.
 ///
 /// This contains the [SigningCertificate] as well as other global signing
@@ -745,9 +789,15 @@ pub struct Signer {
     /// used for signing Apple signables.
     apple_signing_settings_fn: Option<Arc<AppleSigningSettingsFn>>,
 
+    /// Options for signing Windows signables.
+    windows_signing_options: WindowsSigningOptions,
+
     /// Optional function to influence creation of [tugger_windows_codesign::SigntoolSign]
     /// used for signing Windows signables.
     windows_signer_fn: Option<Arc<WindowsSignerFn>>,
+
+    /// Optional notarizer for signed Apple signables.
+    apple_notarizer: Option<Arc<AppleNotarizer>>,
 }
 
 impl From<SigningCertificate> for Signer {
@@ -763,8 +813,10 @@ impl Signer {
             signing_certificate,
             certificate_chain: vec![],
             time_stamp_url: None,
+            windows_signing_options: WindowsSigningOptions::default(),
             apple_signing_settings_fn: None,
             windows_signer_fn: None,
+            apple_notarizer: None,
         }
     }
 
@@ -848,7 +900,7 @@ impl Signer {
         let domain = apple_codesign::KeychainDomain::User;
 
         let certs = apple_codesign::macos_keychain_find_certificate_chain(domain, None, &user_id)
-            .map_err(SigningError::MacOsCertificateChainResolveFailure)?;
+            .map_err(|e| SigningError::MacOsCertificateChainResolveFailure(Box::new(e)))?;
 
         if certs.is_empty() {
             return Err(SigningError::CertificateResolutionFailure(
@@ -899,11 +951,35 @@ impl Signer {
         self.apple_signing_settings_fn = Some(Arc::new(cb));
     }
 
+    /// Set options for signing Windows signables.
+    pub fn windows_signing_options(&mut self, options: WindowsSigningOptions) {
+        self.windows_signing_options = options;
+    }
+
     /// Set a callback function to be called to influence settings for signing individual Windows signables.
     pub fn windows_settings_callback(&mut self, cb: WindowsSignerFn) {
         self.windows_signer_fn = Some(Arc::new(cb));
     }
 
+    /// Notarize Apple signables after signing them.
+    ///
+    /// Signed Apple bundles and Mach-O executables will be submitted to Apple
+    /// for notarization. Mach-O libraries aren't notarized on their own.
+    pub fn apple_notarizer(&mut self, notarizer: AppleNotarizer) {
+        self.apple_notarizer = Some(Arc::new(notarizer));
+    }
+
+    /// Notarize content at a filesystem path with the registered [AppleNotarizer].
+    ///
+    /// This is used to notarize content that isn't signed by us, such as zip
+    /// files and DMGs. See [AppleNotarizer::notarize_path].
+    pub fn notarize_path(&self, path: impl AsRef<Path>) -> Result<Notarization, SigningError> {
+        self.apple_notarizer
+            .as_ref()
+            .ok_or(SigningError::NoAppleNotarizer)?
+            .notarize_path(path)
+    }
+
     /// Determine the *signability* of a potentially signable entity.
     pub fn resolve_signability(
         &self,
@@ -976,9 +1052,15 @@ pub struct SignableSigner<'a> {
     /// used for signing Apple signables.
     apple_signing_settings_fn: Option<Arc<AppleSigningSettingsFn>>,
 
+    /// Options for signing Windows signables.
+    windows_signing_options: WindowsSigningOptions,
+
     /// Optional function to influence creation of [tugger_windows_codesign::SigntoolSign]
     /// used for signing Windows signables.
     windows_signer_fn: Option<Arc<WindowsSignerFn>>,
+
+    /// Optional notarizer for signed Apple signables.
+    apple_notarizer: Option<Arc<AppleNotarizer>>,
 }
 
 impl<'a> SignableSigner<'a> {
@@ -992,8 +1074,10 @@ impl<'a> SignableSigner<'a> {
             signable,
             certificate_chain,
             time_stamp_url,
+            windows_signing_options: signer.windows_signing_options.clone(),
             apple_signing_settings_fn: signer.apple_signing_settings_fn.clone(),
             windows_signer_fn: signer.windows_signer_fn.clone(),
+            apple_notarizer: signer.apple_notarizer.clone(),
         }
     }
 
@@ -1073,6 +1157,20 @@ impl<'a> SignableSigner<'a> {
 
         signer.file_digest_algorithm("SHA256");
 
+        let options = &self.windows_signing_options;
+        if options.dual_sign {
+            signer.dual_sign();
+        }
+        if options.append_signature {
+            signer.append_signature();
+        }
+        if let Some(enabled) = options.page_hashes {
+            signer.page_hashes(enabled);
+        }
+        if options.verify {
+            signer.verify();
+        }
+
         if let Some(cb) = &self.windows_signer_fn {
             cb(&self.signable, &mut signer).map_err(SigningError::SettingsCallback)?;
         }
@@ -1100,9 +1198,26 @@ impl<'a> SignableSigner<'a> {
     /// files can be created, as needed. If not provided, a new temporary
     /// directory will be managed. In all cases, we attempt to remove temporary
     /// files as part of execution.
+    ///
+    /// If the [Signer] has an [AppleNotarizer], signed Apple bundles and Mach-O
+    /// executables are notarized before returning.
     pub fn sign(
         &self,
 
+        temp_dir: Option<&Path>,
+        destination: &SigningDestination,
+    ) -> Result<SignedOutput, SigningError> {
+        let output = self.sign_signable(temp_dir, destination)?;
+
+        if let Some(notarizer) = &self.apple_notarizer {
+            self.notarize(notarizer, &output)?;
+        }
+
+        Ok(output)
+    }
+
+    fn sign_signable(
+        &self,
         temp_dir: Option<&Path>,
         destination: &SigningDestination,
     ) -> Result<SignedOutput, SigningError> {
@@ -1229,12 +1344,12 @@ impl<'a> SignableSigner<'a> {
                 let settings = self.as_apple_signing_settings()?;
 
                 let signer = apple_codesign::MachOSigner::new(macho_data)
-                    .map_err(SigningError::MachOSigningError)?;
+                    .map_err(|e| SigningError::MachOSigningError(Box::new(e)))?;
 
                 let mut dest = Vec::<u8>::with_capacity(macho_data.len() + 2_usize.pow(17));
                 signer
                     .write_signed_binary(&settings, &mut dest)
-                    .map_err(SigningError::MachOSigningError)?;
+                    .map_err(|e| SigningError::MachOSigningError(Box::new(e)))?;
 
                 match destination {
                     SigningDestination::Memory => {
@@ -1257,12 +1372,12 @@ impl<'a> SignableSigner<'a> {
                 warn!("signing {}", source_file.display());
 
                 let signer = apple_codesign::MachOSigner::new(macho_data)
-                    .map_err(SigningError::MachOSigningError)?;
+                    .map_err(|e| SigningError::MachOSigningError(Box::new(e)))?;
 
                 let mut dest = Vec::<u8>::with_capacity(macho_data.len() + 2_usize.pow(17));
                 signer
                     .write_signed_binary(&settings, &mut dest)
-                    .map_err(SigningError::MachOSigningError)?;
+                    .map_err(|e| SigningError::MachOSigningError(Box::new(e)))?;
 
                 match destination {
                     SigningDestination::Memory => {
@@ -1295,17 +1410,51 @@ impl<'a> SignableSigner<'a> {
                 );
 
                 let signer = apple_codesign::BundleSigner::new_from_path(source_dir)
-                    .map_err(SigningError::AppleBundleSigningError)?;
+                    .map_err(|e| SigningError::AppleBundleSigningError(Box::new(e)))?;
 
                 signer
                     .write_signed_bundle(dest_dir, &settings)
-                    .map_err(SigningError::AppleBundleSigningError)?;
+                    .map_err(|e| SigningError::AppleBundleSigningError(Box::new(e)))?;
 
                 Ok(SignedOutput::Directory(dest_dir.clone()))
             }
         }
     }
 
+    /// Notarize signed output of an Apple signable.
+    fn notarize(
+        &self,
+        notarizer: &AppleNotarizer,
+        output: &SignedOutput,
+    ) -> Result<(), SigningError> {
+        match (&self.signable, output) {
+            (Signable::AppleBundle(_), SignedOutput::Directory(path)) => {
+                notarizer.notarize_path(path)?;
+            }
+            (Signable::MachOFile(_, _) | Signable::MachOData(_), SignedOutput::File(path))
+                if macho_is_executable(&std::fs::read(path)?) =>
+            {
+                notarizer.notarize_path(path)?;
+            }
+            (Signable::MachOFile(source_file, _), SignedOutput::Memory(data))
+                if macho_is_executable(data) =>
+            {
+                let filename = source_file
+                    .file_name()
+                    .map(|x| x.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "executable".to_string());
+
+                notarizer.notarize_macho_data(&filename, data)?;
+            }
+            (Signable::MachOData(_), SignedOutput::Memory(data)) if macho_is_executable(data) => {
+                notarizer.notarize_macho_data("executable", data)?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
     /// Whether signing to the specified [SigningDestination] will require temporary files.
     ///
     /// Temporary files are used when:
@@ -0,0 +1,265 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Apple notarization of signed content.
+//!
+//! Notarization uploads signed content to Apple, waits for Apple to scan it,
+//! then *staples* the resulting ticket to the content so Gatekeeper can
+//! verify it offline.
+//!
+//! Apple only accepts bundles, DMGs, flat packages (`.pkg`) and zip files.
+//! Standalone Mach-O executables are wrapped in a zip file for submission.
+//! Tickets can't be stapled to Mach-O executables or zip files, so Gatekeeper
+//! looks up their ticket online the first time they are run.
+
+use {
+    crate::SigningError,
+    apple_bundles::DirectoryBundle,
+    apple_codesign::{
+        notarization::{NotarizationUpload, Notarizer},
+        stapling::Stapler,
+        AppleCodesignError, PathType,
+    },
+    goblin::mach::{header::MH_EXECUTE, Mach, SingleArch},
+    log::warn,
+    std::{io::Write, path::Path, time::Duration},
+};
+
+/// Default amount of time to wait for Apple to process a submission.
+pub const DEFAULT_NOTARIZATION_WAIT_LIMIT: Duration = Duration::from_secs(600);
+
+/// Describes a successful notarization.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Notarization {
+    /// ID of the submission to Apple's Notary API.
+    pub submission_id: String,
+
+    /// Whether the notarization ticket was stapled to the content.
+    pub stapled: bool,
+}
+
+/// An entity for notarizing signed Apple content.
+///
+/// Instances authenticate against the App Store Connect API with an API key.
+#[derive(Clone)]
+pub struct AppleNotarizer {
+    notarizer: Notarizer,
+
+    /// How long to wait for Apple to process a submission.
+    wait_limit: Duration,
+}
+
+impl AppleNotarizer {
+    /// Construct an instance from a file containing a JSON encoded API key.
+    ///
+    /// These files are produced by `rcodesign encode-app-store-connect-api-key`.
+    pub fn from_api_key(path: impl AsRef<Path>) -> Result<Self, SigningError> {
+        Ok(Self {
+            notarizer: Notarizer::from_api_key(path.as_ref())
+                .map_err(|e| SigningError::NotarizationError(Box::new(e)))?,
+            wait_limit: DEFAULT_NOTARIZATION_WAIT_LIMIT,
+        })
+    }
+
+    /// Construct an instance from an API issuer ID and API key ID.
+    ///
+    /// The private key is looked for in the locations `altool` uses, e.g.
+    /// `~/.appstoreconnect/private_keys/AuthKey_<key_id>.p8`.
+    pub fn from_api_key_id(issuer_id: &str, key_id: &str) -> Result<Self, SigningError> {
+        Ok(Self {
+            notarizer: Notarizer::from_api_key_id(issuer_id, key_id)
+                .map_err(|e| SigningError::NotarizationError(Box::new(e)))?,
+            wait_limit: DEFAULT_NOTARIZATION_WAIT_LIMIT,
+        })
+    }
+
+    /// Set how long to wait for Apple to process a submission.
+    ///
+    /// Notarization fails if Apple hasn't finished processing by then.
+    pub fn wait_limit(&mut self, wait_limit: Duration) {
+        self.wait_limit = wait_limit;
+    }
+
+    /// Notarize content at a filesystem path.
+    ///
+    /// The path can be a bundle, DMG, flat package, zip file or Mach-O
+    /// executable. The ticket is stapled to bundles, DMGs and flat packages.
+    pub fn notarize_path(&self, path: impl AsRef<Path>) -> Result<Notarization, SigningError> {
+        let path = path.as_ref();
+
+        match PathType::from_path(path).map_err(|e| SigningError::NotarizationError(Box::new(e)))? {
+            PathType::MachO => {
+                let filename = path
+                    .file_name()
+                    .ok_or_else(|| SigningError::NotarizationUnsupported(path.to_path_buf()))?
+                    .to_string_lossy();
+
+                self.notarize_macho_data(&filename, &std::fs::read(path)?)
+            }
+            PathType::Zip => {
+                warn!("notarizing zip file {}", path.display());
+                let upload = self
+                    .notarizer
+                    .notarize_flat_package(path, Some(self.wait_limit))
+                    .map_err(|e| SigningError::NotarizationError(Box::new(e)))?;
+                warn!("notarization tickets can't be stapled to zip files");
+
+                Ok(Notarization {
+                    submission_id: submission_id(upload),
+                    stapled: false,
+                })
+            }
+            PathType::Xar => {
+                warn!("notarizing flat package {}", path.display());
+                let upload = self
+                    .notarizer
+                    .notarize_flat_package(path, Some(self.wait_limit))
+                    .map_err(|e| SigningError::NotarizationError(Box::new(e)))?;
+                self.stapler()?
+                    .staple_path(path)
+                    .map_err(|e| SigningError::StaplingError(Box::new(e)))?;
+
+                Ok(Notarization {
+                    submission_id: submission_id(upload),
+                    stapled: true,
+                })
+            }
+            PathType::Dmg => {
+                warn!("notarizing DMG {}", path.display());
+                let upload = self
+                    .notarizer
+                    .notarize_dmg(path, Some(self.wait_limit))
+                    .map_err(|e| SigningError::NotarizationError(Box::new(e)))?;
+                self.stapler()?
+                    .staple_dmg(path)
+                    .map_err(|e| SigningError::StaplingError(Box::new(e)))?;
+
+                Ok(Notarization {
+                    submission_id: submission_id(upload),
+                    stapled: true,
+                })
+            }
+            PathType::Bundle => {
+                warn!("notarizing bundle {}", path.display());
+                let bundle = DirectoryBundle::new_from_path(path).map_err(|e| {
+                    SigningError::NotarizationError(Box::new(AppleCodesignError::DirectoryBundle(
+                        e,
+                    )))
+                })?;
+                let upload = self
+                    .notarizer
+                    .notarize_bundle(&bundle, Some(self.wait_limit))
+                    .map_err(|e| SigningError::NotarizationError(Box::new(e)))?;
+                self.stapler()?
+                    .staple_bundle(&bundle)
+                    .map_err(|e| SigningError::StaplingError(Box::new(e)))?;
+
+                Ok(Notarization {
+                    submission_id: submission_id(upload),
+                    stapled: true,
+                })
+            }
+            PathType::Other => Err(SigningError::NotarizationUnsupported(path.to_path_buf())),
+        }
+    }
+
+    /// Notarize a Mach-O executable held in memory.
+    ///
+    /// The executable is wrapped in a zip file as a file named `filename`.
+    /// Tickets can't be stapled to Mach-O executables, so nothing is modified.
+    pub fn notarize_macho_data(
+        &self,
+        filename: &str,
+        data: &[u8],
+    ) -> Result<Notarization, SigningError> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("tugger-notarize-")
+            .tempdir()?;
+        let zip_path = temp_dir.path().join(format!("{}.zip", filename));
+
+        warn!("notarizing Mach-O executable {} as a zip file", filename);
+        std::fs::write(&zip_path, macho_to_zip(filename, data)?)?;
+
+        let upload = self
+            .notarizer
+            .notarize_flat_package(&zip_path, Some(self.wait_limit))
+            .map_err(|e| SigningError::NotarizationError(Box::new(e)))?;
+        warn!(
+            "notarization tickets can't be stapled to Mach-O executables; Gatekeeper will look it up online"
+        );
+
+        Ok(Notarization {
+            submission_id: submission_id(upload),
+            stapled: false,
+        })
+    }
+
+    fn stapler(&self) -> Result<Stapler, SigningError> {
+        Stapler::new().map_err(|e| SigningError::StaplingError(Box::new(e)))
+    }
+}
+
+fn submission_id(upload: NotarizationUpload) -> String {
+    match upload {
+        NotarizationUpload::UploadId(id) => id,
+        NotarizationUpload::NotaryResponse(response) => response.data.id,
+    }
+}
+
+/// Whether Mach-O data is an executable, as opposed to a library or extension module.
+///
+/// Libraries don't need to be notarized on their own: they are covered by the
+/// notarization of the executable or bundle loading them.
+pub fn macho_is_executable(data: &[u8]) -> bool {
+    match Mach::parse(data) {
+        Ok(Mach::Binary(macho)) => macho.header.filetype == MH_EXECUTE,
+        Ok(Mach::Fat(multi)) => multi.into_iter().all(|arch| {
+            matches!(arch, Ok(SingleArch::MachO(macho)) if macho.header.filetype == MH_EXECUTE)
+        }),
+        Err(_) => false,
+    }
+}
+
+/// Produce zip file data holding a single executable file.
+///
+/// This is the form Apple accepts standalone Mach-O executables in.
+pub fn macho_to_zip(filename: &str, data: &[u8]) -> Result<Vec<u8>, SigningError> {
+    let mut zf = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+
+    zf.start_file(
+        filename,
+        zip::write::FileOptions::default().unix_permissions(0o755),
+    )?;
+    zf.write_all(data)?;
+
+    Ok(zf.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::io::Read};
+
+    #[test]
+    fn macho_zip() -> Result<(), SigningError> {
+        let data = macho_to_zip("myapp", b"executable")?;
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data))?;
+        assert_eq!(archive.len(), 1);
+
+        let mut file = archive.by_index(0)?;
+        assert_eq!(file.name(), "myapp");
+        assert_eq!(file.unix_mode(), Some(0o100755));
+
+        let mut content = vec![];
+        file.read_to_end(&mut content)?;
+        assert_eq!(content, b"executable");
+
+        Ok(())
+    }
+
+    #[test]
+    fn macho_executable() {
+        assert!(!macho_is_executable(b"not a Mach-O"));
+    }
+}
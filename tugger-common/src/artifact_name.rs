@@ -0,0 +1,498 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Consistent file names for release artifacts.
+
+An [ArtifactNamingPolicy] renders file names of release artifacts from a
+template. The version is validated as a PEP 440 version and adapted to the
+conventions of each [ArtifactKind]: Debian and RPM packages sort pre-releases
+before releases with `~`, so `1.0rc1` becomes `1.0~rc1` there.
+
+Templates contain `{name}`, `{version}`, `{channel}`, `{platform}`, `{abi}`
+and `{ext}` placeholders. A placeholder may start with a separator character
+(`-`, `_` or `.`), e.g. `{-channel}`. The separator is only rendered if the
+value isn't empty, so optional values don't leave dangling separators.
+*/
+
+use {
+    anyhow::{anyhow, Result},
+    once_cell::sync::Lazy,
+    regex::Regex,
+    std::fmt::{Display, Formatter},
+};
+
+/// Regular expression matching PEP 440 versions, from the PEP's appendix.
+static PEP440_VERSION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?ix)^\s*v?
+        (?:(?P<epoch>[0-9]+)!)?
+        (?P<release>[0-9]+(?:\.[0-9]+)*)
+        (?:[-_.]?(?P<pre_l>alpha|a|beta|b|preview|pre|c|rc)[-_.]?(?P<pre_n>[0-9]+)?)?
+        (?:-(?P<post_n1>[0-9]+)|[-_.]?(?P<post_l>post|rev|r)[-_.]?(?P<post_n2>[0-9]+)?)?
+        (?:[-_.]?(?P<dev_l>dev)[-_.]?(?P<dev_n>[0-9]+)?)?
+        (?:\+(?P<local>[a-z0-9]+(?:[-_.][a-z0-9]+)*))?
+        \s*$",
+    )
+    .unwrap()
+});
+
+/// Regular expression matching values allowed in file names.
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9][A-Za-z0-9_.+-]*$").unwrap());
+
+/// A validated PEP 440 version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pep440Version {
+    epoch: u64,
+    release: Vec<u64>,
+    pre: Option<(&'static str, u64)>,
+    post: Option<u64>,
+    dev: Option<u64>,
+    local: Option<String>,
+}
+
+impl Pep440Version {
+    /// Parse a version string, normalizing its spelling.
+    pub fn parse(s: &str) -> Result<Self> {
+        let captures = PEP440_VERSION
+            .captures(s)
+            .ok_or_else(|| anyhow!("{} is not a valid PEP 440 version", s))?;
+
+        let number = |name: &str| -> Result<Option<u64>> {
+            captures
+                .name(name)
+                .map(|m| m.as_str().parse::<u64>())
+                .transpose()
+                .map_err(|e| anyhow!("invalid number in version {}: {}", s, e))
+        };
+
+        let release = captures["release"]
+            .split('.')
+            .map(|x| x.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow!("invalid number in version {}: {}", s, e))?;
+
+        let pre = if let Some(label) = captures.name("pre_l") {
+            let label = match label.as_str().to_lowercase().as_str() {
+                "alpha" | "a" => "a",
+                "beta" | "b" => "b",
+                _ => "rc",
+            };
+
+            Some((label, number("pre_n")?.unwrap_or(0)))
+        } else {
+            None
+        };
+
+        let post = if captures.name("post_n1").is_some() {
+            number("post_n1")?
+        } else if captures.name("post_l").is_some() {
+            Some(number("post_n2")?.unwrap_or(0))
+        } else {
+            None
+        };
+
+        let dev = if captures.name("dev_l").is_some() {
+            Some(number("dev_n")?.unwrap_or(0))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            epoch: number("epoch")?.unwrap_or(0),
+            release,
+            pre,
+            post,
+            dev,
+            local: captures
+                .name("local")
+                .map(|m| m.as_str().to_lowercase().replace(['-', '_'], ".")),
+        })
+    }
+
+    /// Whether this is a pre-release or development release.
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some() || self.dev.is_some()
+    }
+
+    /// Render the version for Debian and RPM packages.
+    ///
+    /// Pre-releases and development releases are separated with `~`, which
+    /// these package managers sort before the corresponding release. The
+    /// epoch is omitted, as it isn't part of package file names.
+    pub fn to_package_version(&self) -> String {
+        let mut s = self.release_string();
+
+        if let Some((label, n)) = &self.pre {
+            s.push_str(&format!("~{}{}", label, n));
+        }
+        if let Some(n) = self.post {
+            s.push_str(&format!(".post{}", n));
+        }
+        if let Some(n) = self.dev {
+            s.push_str(&format!("~dev{}", n));
+        }
+        if let Some(local) = &self.local {
+            s.push_str(&format!("+{}", local));
+        }
+
+        s
+    }
+
+    fn release_string(&self) -> String {
+        self.release
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+impl Display for Pep440Version {
+    /// Render the normalized form of the version.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+
+        f.write_str(&self.release_string())?;
+
+        if let Some((label, n)) = &self.pre {
+            write!(f, "{}{}", label, n)?;
+        }
+        if let Some(n) = self.post {
+            write!(f, ".post{}", n)?;
+        }
+        if let Some(n) = self.dev {
+            write!(f, ".dev{}", n)?;
+        }
+        if let Some(local) = &self.local {
+            write!(f, "+{}", local)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A type of release artifact.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArtifactKind {
+    /// A standalone executable.
+    ///
+    /// `{ext}` is empty, as builders of executables add the extension
+    /// required by the target platform.
+    Executable,
+    /// A Windows installer.
+    Msi,
+    /// An Apple disk image.
+    Dmg,
+    /// A Debian package.
+    Deb,
+    /// An RPM package.
+    Rpm,
+    /// A zip archive.
+    Zip,
+    /// A gzip compressed tar archive.
+    TarGz,
+}
+
+impl ArtifactKind {
+    /// The file name extension of the artifact, including the leading `.`.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Executable => "",
+            Self::Msi => ".msi",
+            Self::Dmg => ".dmg",
+            Self::Deb => ".deb",
+            Self::Rpm => ".rpm",
+            Self::Zip => ".zip",
+            Self::TarGz => ".tar.gz",
+        }
+    }
+}
+
+impl TryFrom<&str> for ArtifactKind {
+    type Error = anyhow::Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "exe" => Ok(Self::Executable),
+            "msi" => Ok(Self::Msi),
+            "dmg" => Ok(Self::Dmg),
+            "deb" => Ok(Self::Deb),
+            "rpm" => Ok(Self::Rpm),
+            "zip" => Ok(Self::Zip),
+            "tar.gz" => Ok(Self::TarGz),
+            _ => Err(anyhow!(
+                "unknown artifact kind {}; expected one of exe, msi, dmg, deb, rpm, zip, tar.gz",
+                s
+            )),
+        }
+    }
+}
+
+/// A part of a parsed template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Option<char>, Placeholder),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Placeholder {
+    Name,
+    Version,
+    Channel,
+    Platform,
+    Abi,
+    Ext,
+}
+
+fn parse_template(template: &str) -> Result<Vec<Segment>> {
+    let mut segments = vec![];
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        if rest[0..start].contains('}') {
+            return Err(anyhow!("unmatched }} in template {}", template));
+        }
+
+        if start > 0 {
+            segments.push(Segment::Literal(rest[0..start].to_string()));
+        }
+
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("unterminated placeholder in template {}", template))?
+            + start;
+        let mut field = &rest[start + 1..end];
+
+        let separator = match field.chars().next() {
+            Some(c @ ('-' | '_' | '.')) => {
+                field = &field[1..];
+                Some(c)
+            }
+            _ => None,
+        };
+
+        let placeholder = match field {
+            "name" => Placeholder::Name,
+            "version" => Placeholder::Version,
+            "channel" => Placeholder::Channel,
+            "platform" => Placeholder::Platform,
+            "abi" => Placeholder::Abi,
+            "ext" => Placeholder::Ext,
+            _ => {
+                return Err(anyhow!(
+                    "unknown placeholder {{{}}} in template {}",
+                    field,
+                    template
+                ))
+            }
+        };
+
+        segments.push(Segment::Placeholder(separator, placeholder));
+        rest = &rest[end + 1..];
+    }
+
+    if rest.contains('}') {
+        return Err(anyhow!("unmatched }} in template {}", template));
+    }
+
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest.to_string()));
+    }
+
+    Ok(segments)
+}
+
+/// Renders file names of release artifacts from a template.
+#[derive(Clone, Debug)]
+pub struct ArtifactNamingPolicy {
+    template: Vec<Segment>,
+    name: String,
+    version: Pep440Version,
+    platform: String,
+    channel: Option<String>,
+    abi: Option<String>,
+}
+
+impl ArtifactNamingPolicy {
+    /// The template used when none is given.
+    pub const DEFAULT_TEMPLATE: &'static str = "{name}-{version}{-channel}-{platform}{-abi}{ext}";
+
+    /// Construct an instance from a template, application name, version and platform tag.
+    ///
+    /// Errors if the template has unknown placeholders, the version isn't a
+    /// valid PEP 440 version or a value contains characters not suitable
+    /// for file names.
+    pub fn new(template: &str, name: &str, version: &str, platform: &str) -> Result<Self> {
+        Ok(Self {
+            template: parse_template(template)?,
+            name: validate_tag("name", name)?,
+            version: Pep440Version::parse(version)?,
+            platform: validate_tag("platform", platform)?,
+            channel: None,
+            abi: None,
+        })
+    }
+
+    /// Set the release channel, e.g. `nightly` or `beta`.
+    pub fn set_channel(&mut self, channel: &str) -> Result<()> {
+        self.channel = Some(validate_tag("channel", channel)?);
+        Ok(())
+    }
+
+    /// Set the ABI tag, e.g. `cp310`.
+    pub fn set_abi(&mut self, abi: &str) -> Result<()> {
+        self.abi = Some(validate_tag("abi", abi)?);
+        Ok(())
+    }
+
+    /// The validated version.
+    pub fn version(&self) -> &Pep440Version {
+        &self.version
+    }
+
+    /// Render the file name of an artifact.
+    pub fn render(&self, kind: ArtifactKind) -> String {
+        let version = match kind {
+            ArtifactKind::Deb | ArtifactKind::Rpm => self.version.to_package_version(),
+            _ => self.version.to_string(),
+        };
+
+        let mut s = String::new();
+
+        for segment in &self.template {
+            let (separator, value) = match segment {
+                Segment::Literal(literal) => {
+                    s.push_str(literal);
+                    continue;
+                }
+                Segment::Placeholder(separator, placeholder) => (
+                    separator,
+                    match placeholder {
+                        Placeholder::Name => self.name.as_str(),
+                        Placeholder::Version => version.as_str(),
+                        Placeholder::Channel => self.channel.as_deref().unwrap_or(""),
+                        Placeholder::Platform => self.platform.as_str(),
+                        Placeholder::Abi => self.abi.as_deref().unwrap_or(""),
+                        Placeholder::Ext => kind.extension(),
+                    },
+                ),
+            };
+
+            if !value.is_empty() {
+                if let Some(separator) = separator {
+                    s.push(*separator);
+                }
+                s.push_str(value);
+            }
+        }
+
+        s
+    }
+}
+
+fn validate_tag(field: &str, value: &str) -> Result<String> {
+    if TAG.is_match(value) {
+        Ok(value.to_string())
+    } else {
+        Err(anyhow!(
+            "{} {:?} is not suitable for file names; use letters, digits, and _.+-",
+            field,
+            value
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pep440_normalization() -> Result<()> {
+        for (version, normalized) in [
+            ("1.0", "1.0"),
+            ("v1.0", "1.0"),
+            ("1!2.0", "1!2.0"),
+            ("1.0-alpha.1", "1.0a1"),
+            ("1.0.BETA2", "1.0b2"),
+            ("1.0c1", "1.0rc1"),
+            ("1.0rc", "1.0rc0"),
+            ("1.0-1", "1.0.post1"),
+            ("1.0.post", "1.0.post0"),
+            ("1.0.dev", "1.0.dev0"),
+            ("1.0rc1.post2.dev3", "1.0rc1.post2.dev3"),
+            ("1.0+Ubuntu-1", "1.0+ubuntu.1"),
+        ] {
+            assert_eq!(Pep440Version::parse(version)?.to_string(), normalized);
+        }
+
+        assert!(Pep440Version::parse("1.0-foo").is_err());
+        assert!(Pep440Version::parse("latest").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_version() -> Result<()> {
+        assert_eq!(
+            Pep440Version::parse("1.0rc1")?.to_package_version(),
+            "1.0~rc1"
+        );
+        assert_eq!(
+            Pep440Version::parse("2.1.dev4")?.to_package_version(),
+            "2.1~dev4"
+        );
+        assert_eq!(Pep440Version::parse("1!3.0")?.to_package_version(), "3.0");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render() -> Result<()> {
+        let mut policy = ArtifactNamingPolicy::new(
+            ArtifactNamingPolicy::DEFAULT_TEMPLATE,
+            "myapp",
+            "1.0-rc.1",
+            "x86_64-unknown-linux-gnu",
+        )?;
+
+        assert_eq!(
+            policy.render(ArtifactKind::TarGz),
+            "myapp-1.0rc1-x86_64-unknown-linux-gnu.tar.gz"
+        );
+        assert_eq!(
+            policy.render(ArtifactKind::Deb),
+            "myapp-1.0~rc1-x86_64-unknown-linux-gnu.deb"
+        );
+        assert_eq!(
+            policy.render(ArtifactKind::Executable),
+            "myapp-1.0rc1-x86_64-unknown-linux-gnu"
+        );
+
+        policy.set_channel("nightly")?;
+        policy.set_abi("cp310")?;
+        assert_eq!(
+            policy.render(ArtifactKind::Zip),
+            "myapp-1.0rc1-nightly-x86_64-unknown-linux-gnu-cp310.zip"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(ArtifactNamingPolicy::new("{name}-{arch}", "myapp", "1.0", "linux").is_err());
+        assert!(ArtifactNamingPolicy::new("{name", "myapp", "1.0", "linux").is_err());
+        assert!(ArtifactNamingPolicy::new("{name}}", "myapp", "1.0", "linux").is_err());
+        assert!(ArtifactNamingPolicy::new("}{name}", "myapp", "1.0", "linux").is_err());
+        assert!(ArtifactNamingPolicy::new("{name}", "my/app", "1.0", "linux").is_err());
+        assert!(ArtifactNamingPolicy::new("{name}", "myapp", "one", "linux").is_err());
+
+        let mut policy = ArtifactNamingPolicy::new("{name}", "myapp", "1.0", "linux").unwrap();
+        assert!(policy.set_channel("a b").is_err());
+        assert!(ArtifactKind::try_from("pkg").is_err());
+    }
+}
@@ -0,0 +1,690 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Package changelog generation.
+
+This module converts a [Keep a Changelog](https://keepachangelog.com/) file
+or a git history following [Conventional Commits](https://www.conventionalcommits.org/)
+into `debian/changelog` and RPM `%changelog` content.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    git2::{Oid, Repository, Sort},
+    std::collections::HashMap,
+    time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset},
+};
+
+/// Maximum length of lines in emitted changelogs.
+///
+/// lintian complains about `debian/changelog` lines longer than 80 characters.
+const MAX_LINE_LENGTH: usize = 80;
+
+/// Description used for releases without changes.
+const EMPTY_RELEASE_DESCRIPTION: &str = "New upstream release.";
+
+/// A change in a release.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChangelogChange {
+    /// Category of the change, e.g. `Added` or `Fixed`.
+    pub category: Option<String>,
+
+    /// Description of the change.
+    pub description: String,
+}
+
+impl ChangelogChange {
+    fn text(&self) -> String {
+        if let Some(category) = &self.category {
+            format!("{}: {}", category, self.description)
+        } else {
+            self.description.clone()
+        }
+    }
+}
+
+/// A released version and its changes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChangelogRelease {
+    /// Upstream version of the release.
+    pub version: String,
+
+    /// When the release was made.
+    pub date: OffsetDateTime,
+
+    /// Changes in the release.
+    pub changes: Vec<ChangelogChange>,
+}
+
+/// A series of releases, newest first.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Changelog {
+    pub releases: Vec<ChangelogRelease>,
+}
+
+impl Changelog {
+    /// Parse a changelog in the Keep a Changelog format.
+    ///
+    /// Releases are `## [<version>] - <YYYY-MM-DD>` headings. `###` headings
+    /// define the category of the list items that follow. The `Unreleased`
+    /// section is ignored.
+    pub fn from_keep_a_changelog(data: &str) -> Result<Self> {
+        let mut releases = vec![];
+        // Whether list items are collected for the last release.
+        let mut in_release = false;
+        let mut category = None;
+        // Whether the previous line continues a list item.
+        let mut in_item = false;
+
+        for line in data.lines() {
+            let trimmed = line.trim();
+
+            if let Some(heading) = line.strip_prefix("## ") {
+                in_item = false;
+                category = None;
+                in_release = match parse_release_heading(heading)? {
+                    Some(release) => {
+                        releases.push(release);
+                        true
+                    }
+                    None => false,
+                };
+            } else if let Some(heading) = line.strip_prefix("### ") {
+                in_item = false;
+                category = Some(heading.trim().to_string());
+            } else if line.starts_with('#') {
+                in_item = false;
+                in_release = false;
+            } else if trimmed.is_empty() {
+                in_item = false;
+            } else if !in_release {
+                continue;
+            } else if let Some(description) = ["- ", "* "]
+                .iter()
+                .find_map(|prefix| line.strip_prefix(prefix))
+            {
+                in_item = true;
+                releases
+                    .last_mut()
+                    .expect("in_release implies a release")
+                    .changes
+                    .push(ChangelogChange {
+                        category: category.clone(),
+                        description: description.trim().to_string(),
+                    });
+            } else if in_item {
+                let change = releases
+                    .last_mut()
+                    .and_then(|release| release.changes.last_mut())
+                    .expect("in_item implies a change");
+
+                change.description.push(' ');
+                change.description.push_str(trimmed);
+            }
+        }
+
+        Ok(Self::from_releases(releases))
+    }
+
+    /// Derive a changelog from the git history of `HEAD`.
+    ///
+    /// Tags named `<tag_prefix><version>` mark releases. Each release consists of
+    /// the Conventional Commits since the previous release tag on the first-parent
+    /// history. Only features, fixes, performance improvements, and breaking
+    /// changes are recorded. Commits after the most recent release tag are ignored.
+    pub fn from_git_history(repo: &Repository, tag_prefix: &str) -> Result<Self> {
+        let mut tags = HashMap::<Oid, String>::new();
+
+        for name in repo.tag_names(None)?.iter().flatten() {
+            let version = match name.strip_prefix(tag_prefix) {
+                Some(version) if version.starts_with(|c: char| c.is_ascii_digit()) => version,
+                _ => continue,
+            };
+
+            let commit = repo
+                .revparse_single(&format!("refs/tags/{}", name))?
+                .peel_to_commit()
+                .with_context(|| format!("resolving commit of tag {}", name))?;
+
+            tags.insert(commit.id(), version.to_string());
+        }
+
+        let mut walk = repo.revwalk()?;
+        walk.set_sorting(Sort::TOPOLOGICAL)?;
+        walk.simplify_first_parent()?;
+        walk.push_head()?;
+
+        let mut releases = vec![];
+
+        for oid in walk {
+            let oid = oid?;
+            let commit = repo.find_commit(oid)?;
+
+            if let Some(version) = tags.get(&oid) {
+                let time = commit.time();
+
+                releases.push(ChangelogRelease {
+                    version: version.clone(),
+                    date: OffsetDateTime::from_unix_timestamp(time.seconds())?
+                        .to_offset(UtcOffset::from_whole_seconds(time.offset_minutes() * 60)?),
+                    changes: vec![],
+                });
+            }
+
+            if let (Some(release), Some(message)) = (releases.last_mut(), commit.message()) {
+                if let Some(change) = parse_conventional_commit(message) {
+                    release.changes.push(change);
+                }
+            }
+        }
+
+        Ok(Self::from_releases(releases))
+    }
+
+    fn from_releases(mut releases: Vec<ChangelogRelease>) -> Self {
+        // Packaging tools require entries in reverse chronological order.
+        releases.sort_by_key(|release| std::cmp::Reverse(release.date));
+
+        Self { releases }
+    }
+
+    /// Render the changelog in the `debian/changelog` format.
+    ///
+    /// `revision` is the Debian revision appended to upstream versions and
+    /// `maintainer` is of the form `Name <email>`.
+    pub fn to_debian_changelog(
+        &self,
+        package: &str,
+        revision: &str,
+        distribution: &str,
+        urgency: &str,
+        maintainer: &str,
+    ) -> String {
+        let mut lines = vec![];
+
+        for release in &self.releases {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+
+            lines.push(format!(
+                "{} ({}) {}; urgency={}",
+                package,
+                debian_version(&release.version, revision),
+                distribution,
+                urgency
+            ));
+            lines.push(String::new());
+
+            for text in release_texts(release) {
+                lines.extend(wrap(&text, "  * ", "    "));
+            }
+
+            lines.push(String::new());
+            lines.push(format!(
+                " -- {}  {}",
+                maintainer,
+                format_rfc2822(&release.date)
+            ));
+        }
+
+        lines.into_iter().map(|line| line + "\n").collect()
+    }
+
+    /// Render the changelog as the body of an RPM `%changelog` section.
+    ///
+    /// `release` is the RPM release appended to upstream versions and `packager`
+    /// is of the form `Name <email>`.
+    pub fn to_rpm_changelog(&self, packager: &str, release: &str) -> String {
+        let mut lines = vec![];
+
+        for entry in &self.releases {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+
+            let date = entry.date.date();
+
+            lines.push(format!(
+                "* {} {} {:02} {} {} - {}-{}",
+                abbreviation(&date.weekday().to_string()),
+                abbreviation(&date.month().to_string()),
+                date.day(),
+                date.year(),
+                packager,
+                rpm_version(&entry.version),
+                release
+            ));
+
+            for text in release_texts(entry) {
+                // `%` would otherwise start a macro.
+                lines.extend(wrap(&text.replace('%', "%%"), "- ", "  "));
+            }
+        }
+
+        lines.into_iter().map(|line| line + "\n").collect()
+    }
+}
+
+/// Convert an upstream version to a Debian version.
+///
+/// Pre-release separators are converted to `~` so pre-releases sort before
+/// the final release.
+pub fn debian_version(upstream: &str, revision: &str) -> String {
+    format!("{}-{}", upstream.replace('-', "~"), revision)
+}
+
+/// Convert an upstream version to an RPM version.
+///
+/// RPM versions can't contain `-`. Pre-release separators are converted to `~`
+/// so pre-releases sort before the final release.
+pub fn rpm_version(upstream: &str) -> String {
+    upstream.replace('-', "~")
+}
+
+/// Parse a Keep a Changelog release heading.
+///
+/// Returns `None` for the `Unreleased` section.
+fn parse_release_heading(heading: &str) -> Result<Option<ChangelogRelease>> {
+    let (version, date) = match heading.split_once(" - ") {
+        Some((version, date)) => (version, Some(date)),
+        None => (heading, None),
+    };
+
+    let version = version
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+
+    if version.eq_ignore_ascii_case("unreleased") {
+        return Ok(None);
+    }
+
+    let date = date
+        .and_then(|date| date.split_whitespace().next())
+        .ok_or_else(|| anyhow!("release {} does not have a date", version))?;
+
+    Ok(Some(ChangelogRelease {
+        date: parse_date(date).with_context(|| format!("parsing date of release {}", version))?,
+        version,
+        changes: vec![],
+    }))
+}
+
+/// Parse a `YYYY-MM-DD` date.
+fn parse_date(s: &str) -> Result<OffsetDateTime> {
+    let parts = s
+        .split('-')
+        .map(|part| part.parse::<u16>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| anyhow!("invalid date: {}", s))?;
+
+    if parts.len() != 3 {
+        return Err(anyhow!("invalid date: {}", s));
+    }
+
+    let date = Date::from_calendar_date(
+        parts[0] as i32,
+        Month::try_from(parts[1] as u8)?,
+        parts[2] as u8,
+    )?;
+
+    Ok(PrimitiveDateTime::new(date, Time::MIDNIGHT).assume_utc())
+}
+
+/// A commit message following [Conventional Commits](https://www.conventionalcommits.org/).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConventionalCommit {
+    /// Commit type, e.g. `feat` or `fix`. Always lowercase.
+    pub kind: String,
+
+    /// Scope of the change, if any.
+    pub scope: Option<String>,
+
+    /// Whether the commit denotes a breaking change.
+    pub breaking: bool,
+
+    /// Description of the change, without the type and scope prefix.
+    pub description: String,
+}
+
+impl ConventionalCommit {
+    /// Parse a commit message.
+    ///
+    /// Returns `None` if the summary line doesn't follow the convention.
+    pub fn parse(message: &str) -> Option<Self> {
+        let summary = message.lines().next()?.trim();
+        let (prefix, description) = summary.split_once(": ")?;
+
+        let (prefix, breaking) = match prefix.strip_suffix('!') {
+            Some(prefix) => (prefix, true),
+            None => (prefix, false),
+        };
+        let breaking = breaking
+            || message.lines().skip(1).any(|line| {
+                line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+            });
+
+        let (kind, scope) = match prefix.split_once('(') {
+            Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?)),
+            None => (prefix, None),
+        };
+
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+
+        Some(Self {
+            kind: kind.to_ascii_lowercase(),
+            scope: scope.map(|s| s.to_string()),
+            breaking,
+            description: description.trim().to_string(),
+        })
+    }
+
+    /// Convert to a changelog change.
+    ///
+    /// Returns `None` for commits not worth recording.
+    pub fn to_change(&self) -> Option<ChangelogChange> {
+        let category = match self.kind.as_str() {
+            "feat" => "Added",
+            "fix" => "Fixed",
+            "perf" => "Changed",
+            _ if self.breaking => "Changed",
+            _ => return None,
+        };
+
+        let mut description = self.description.clone();
+        if let Some(scope) = &self.scope {
+            description = format!("{}: {}", scope, description);
+        }
+        if self.breaking {
+            description = format!("BREAKING: {}", description);
+        }
+
+        Some(ChangelogChange {
+            category: Some(category.to_string()),
+            description,
+        })
+    }
+}
+
+/// Parse a Conventional Commits message into a change.
+///
+/// Returns `None` for commits not worth recording.
+fn parse_conventional_commit(message: &str) -> Option<ChangelogChange> {
+    ConventionalCommit::parse(message)?.to_change()
+}
+
+/// Obtain the texts of the changes in a release.
+fn release_texts(release: &ChangelogRelease) -> Vec<String> {
+    if release.changes.is_empty() {
+        vec![EMPTY_RELEASE_DESCRIPTION.to_string()]
+    } else {
+        release.changes.iter().map(|change| change.text()).collect()
+    }
+}
+
+/// Word wrap text to [MAX_LINE_LENGTH].
+fn wrap(text: &str, first_prefix: &str, prefix: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = first_prefix.to_string();
+    // Whether the current line has words.
+    let mut has_words = false;
+
+    for word in text.split_whitespace() {
+        if has_words && line.len() + 1 + word.len() > MAX_LINE_LENGTH {
+            lines.push(line);
+            line = prefix.to_string();
+            has_words = false;
+        }
+
+        if has_words {
+            line.push(' ');
+        }
+        line.push_str(word);
+        has_words = true;
+    }
+
+    lines.push(line);
+
+    lines
+}
+
+fn abbreviation(s: &str) -> &str {
+    &s[0..3]
+}
+
+/// Format a date as defined by RFC 2822, as used by `debian/changelog`.
+fn format_rfc2822(date: &OffsetDateTime) -> String {
+    let offset = date.offset().whole_seconds();
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} {}{:02}{:02}",
+        abbreviation(&date.weekday().to_string()),
+        date.day(),
+        abbreviation(&date.month().to_string()),
+        date.year(),
+        date.hour(),
+        date.minute(),
+        date.second(),
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 3600,
+        offset.abs() % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEEP_A_CHANGELOG: &str = "# Changelog
+
+All notable changes to this project will be documented in this file.
+
+## [Unreleased]
+
+### Added
+- Not released yet.
+
+## [1.0.0] - 2017-06-20
+
+### Added
+- New visual identity by a designer whose name is long enough to require
+  wrapping the line.
+
+### Fixed
+- 100% of typos.
+
+## [1.0.0-rc.1] - 2017-06-01
+
+## [0.3.0] - 2015-12-03 [YANKED]
+### Removed
+* Section about \"changelog\" vs \"CHANGELOG\".
+
+[unreleased]: https://github.com/olivierlacan/keep-a-changelog/compare/v1.0.0...HEAD
+[1.0.0]: https://github.com/olivierlacan/keep-a-changelog/compare/v0.3.0...v1.0.0
+";
+
+    #[test]
+    fn keep_a_changelog() -> Result<()> {
+        let changelog = Changelog::from_keep_a_changelog(KEEP_A_CHANGELOG)?;
+
+        assert_eq!(
+            changelog
+                .releases
+                .iter()
+                .map(|r| r.version.as_str())
+                .collect::<Vec<_>>(),
+            vec!["1.0.0", "1.0.0-rc.1", "0.3.0"]
+        );
+        assert_eq!(
+            changelog.releases[0].changes,
+            vec![
+                ChangelogChange {
+                    category: Some("Added".into()),
+                    description: "New visual identity by a designer whose name is long enough to require wrapping the line.".into(),
+                },
+                ChangelogChange {
+                    category: Some("Fixed".into()),
+                    description: "100% of typos.".into(),
+                }
+            ]
+        );
+        assert!(changelog.releases[1].changes.is_empty());
+        assert_eq!(changelog.releases[2].changes.len(), 1);
+
+        assert!(Changelog::from_keep_a_changelog("## [1.0.0]\n").is_err());
+        assert!(Changelog::from_keep_a_changelog("## [1.0.0] - 2017-13-01\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn debian_changelog() -> Result<()> {
+        let changelog = Changelog::from_keep_a_changelog(KEEP_A_CHANGELOG)?;
+
+        assert_eq!(
+            changelog.to_debian_changelog(
+                "foo",
+                "1",
+                "unstable",
+                "medium",
+                "Jane Doe <jane@example.com>"
+            ),
+            "foo (1.0.0-1) unstable; urgency=medium
+
+  * Added: New visual identity by a designer whose name is long enough to
+    require wrapping the line.
+  * Fixed: 100% of typos.
+
+ -- Jane Doe <jane@example.com>  Tue, 20 Jun 2017 00:00:00 +0000
+
+foo (1.0.0~rc.1-1) unstable; urgency=medium
+
+  * New upstream release.
+
+ -- Jane Doe <jane@example.com>  Thu, 01 Jun 2017 00:00:00 +0000
+
+foo (0.3.0-1) unstable; urgency=medium
+
+  * Removed: Section about \"changelog\" vs \"CHANGELOG\".
+
+ -- Jane Doe <jane@example.com>  Thu, 03 Dec 2015 00:00:00 +0000
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rpm_changelog() -> Result<()> {
+        let changelog = Changelog::from_keep_a_changelog(KEEP_A_CHANGELOG)?;
+
+        assert_eq!(
+            changelog.to_rpm_changelog("Jane Doe <jane@example.com>", "1"),
+            "* Tue Jun 20 2017 Jane Doe <jane@example.com> - 1.0.0-1
+- Added: New visual identity by a designer whose name is long enough to require
+  wrapping the line.
+- Fixed: 100%% of typos.
+
+* Thu Jun 01 2017 Jane Doe <jane@example.com> - 1.0.0~rc.1-1
+- New upstream release.
+
+* Thu Dec 03 2015 Jane Doe <jane@example.com> - 0.3.0-1
+- Removed: Section about \"changelog\" vs \"CHANGELOG\".
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn conventional_commits() {
+        assert_eq!(
+            ConventionalCommit::parse("Fix(pyembed)!: drop thing"),
+            Some(ConventionalCommit {
+                kind: "fix".into(),
+                scope: Some("pyembed".into()),
+                breaking: true,
+                description: "drop thing".into(),
+            })
+        );
+        assert_eq!(
+            parse_conventional_commit("feat(parser): support arrays\n\nDetails."),
+            Some(ChangelogChange {
+                category: Some("Added".into()),
+                description: "parser: support arrays".into()
+            })
+        );
+        assert_eq!(
+            parse_conventional_commit("refactor!: drop Python 3.7"),
+            Some(ChangelogChange {
+                category: Some("Changed".into()),
+                description: "BREAKING: drop Python 3.7".into()
+            })
+        );
+        assert_eq!(
+            parse_conventional_commit("fix: handle empty input\n\nBREAKING CHANGE: errors"),
+            Some(ChangelogChange {
+                category: Some("Fixed".into()),
+                description: "BREAKING: handle empty input".into()
+            })
+        );
+        assert_eq!(parse_conventional_commit("docs: typo"), None);
+        assert_eq!(parse_conventional_commit("Merge branch 'main'"), None);
+        assert_eq!(parse_conventional_commit("see: https://example.com"), None);
+    }
+
+    #[test]
+    fn git_history() -> Result<()> {
+        let td = tempfile::TempDir::new()?;
+        let repo = Repository::init(td.path())?;
+
+        let mut parent = None;
+        for (message, seconds, tag) in [
+            ("feat: initial", 1_500_000_000, None),
+            ("chore: release", 1_500_000_100, Some("v0.1.0")),
+            ("fix(cli): crash on start", 1_500_100_000, None),
+            ("docs: readme", 1_500_100_100, Some("v0.2.0")),
+            ("feat: unreleased", 1_500_200_000, None),
+        ] {
+            let sig = git2::Signature::new(
+                "Jane Doe",
+                "jane@example.com",
+                &git2::Time::new(seconds, 60),
+            )?;
+            let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+            let parents = parent.iter().collect::<Vec<_>>();
+            let oid = repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)?;
+            let commit = repo.find_commit(oid)?;
+
+            if let Some(tag) = tag {
+                repo.tag_lightweight(tag, commit.as_object(), false)?;
+            }
+
+            parent = Some(commit);
+        }
+
+        let changelog = Changelog::from_git_history(&repo, "v")?;
+        assert_eq!(changelog.releases.len(), 2);
+        assert_eq!(changelog.releases[0].version, "0.2.0");
+        assert_eq!(
+            changelog.releases[0].changes,
+            vec![ChangelogChange {
+                category: Some("Fixed".into()),
+                description: "cli: crash on start".into()
+            }]
+        );
+        assert_eq!(changelog.releases[1].version, "0.1.0");
+        assert_eq!(changelog.releases[1].changes.len(), 1);
+
+        assert!(changelog
+            .to_debian_changelog("foo", "1", "unstable", "low", "Jane Doe <jane@example.com>")
+            .contains(" -- Jane Doe <jane@example.com>  Fri, 14 Jul 2017 03:41:40 +0100\n"));
+
+        Ok(())
+    }
+}
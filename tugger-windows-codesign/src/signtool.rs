@@ -62,6 +62,10 @@ pub struct SigntoolSign {
     description: Option<String>,
     file_digest_algorithm: String,
     timestamp_server: Option<TimestampServer>,
+    dual_sign: bool,
+    append_signature: bool,
+    page_hashes: Option<bool>,
+    verify: bool,
     extra_args: Vec<String>,
     sign_files: Vec<PathBuf>,
 }
@@ -76,6 +80,10 @@ impl SigntoolSign {
             description: None,
             file_digest_algorithm: "SHA256".to_string(),
             timestamp_server: None,
+            dual_sign: false,
+            append_signature: false,
+            page_hashes: None,
+            verify: false,
             extra_args: vec![],
             sign_files: vec![],
         }
@@ -91,6 +99,10 @@ impl SigntoolSign {
             description: self.description.clone(),
             file_digest_algorithm: self.file_digest_algorithm.clone(),
             timestamp_server: self.timestamp_server.clone(),
+            dual_sign: self.dual_sign,
+            append_signature: self.append_signature,
+            page_hashes: self.page_hashes,
+            verify: self.verify,
             extra_args: self.extra_args.clone(),
             sign_files: vec![],
         }
@@ -134,6 +146,42 @@ impl SigntoolSign {
         self
     }
 
+    /// Sign with a SHA-1 digest and append a SHA-256 signature.
+    ///
+    /// Versions of Windows before Windows 7 SP1 don't understand SHA-256
+    /// signatures. Dual signing keeps files verifiable there while newer
+    /// versions use the SHA-256 signature. This overrides the file digest
+    /// algorithm. RFC 3161 timestamps use the digest algorithm of each signature.
+    pub fn dual_sign(&mut self) -> &mut Self {
+        self.dual_sign = true;
+        self
+    }
+
+    /// Append the signature to existing signatures instead of replacing them.
+    ///
+    /// Activates the `/as` flag.
+    pub fn append_signature(&mut self) -> &mut Self {
+        self.append_signature = true;
+        self
+    }
+
+    /// Set whether to generate page hashes for executable files.
+    ///
+    /// Activates the `/ph` or `/nph` flag. If not set, signtool's default is used.
+    pub fn page_hashes(&mut self, enabled: bool) -> &mut Self {
+        self.page_hashes = Some(enabled);
+        self
+    }
+
+    /// Verify the signatures of signed files after signing.
+    ///
+    /// Runs `signtool verify /pa /all`, which verifies all signatures of each
+    /// file and their certificate chains using the Authenticode policy.
+    pub fn verify(&mut self) -> &mut Self {
+        self.verify = true;
+        self
+    }
+
     /// Set extra arguments to pass to signtool.
     ///
     /// Ideally this would not be used. Consider adding a separate API for use cases
@@ -149,10 +197,8 @@ impl SigntoolSign {
         self
     }
 
-    /// Run `signtool sign` with requested options.
-    pub fn run(&self) -> Result<()> {
-        let signtool = find_signtool().context("locating signtool.exe")?;
-
+    /// Resolve the arguments to `signtool sign` for one signature.
+    fn sign_args(&self, digest_algorithm: &str, append: bool) -> Vec<String> {
         let mut args = vec!["sign".to_string()];
 
         if self.verbose {
@@ -195,7 +241,7 @@ impl SigntoolSign {
         }
 
         args.push("/fd".to_string());
-        args.push(self.file_digest_algorithm.clone());
+        args.push(digest_algorithm.to_string());
 
         if let Some(server) = &self.timestamp_server {
             match server {
@@ -207,34 +253,93 @@ impl SigntoolSign {
                     args.push("/tr".to_string());
                     args.push(url.to_string());
                     args.push("/td".to_string());
-                    args.push(algorithm.to_string());
+                    if self.dual_sign {
+                        args.push(digest_algorithm.to_string());
+                    } else {
+                        args.push(algorithm.to_string());
+                    }
                 }
             }
         }
 
+        if append {
+            args.push("/as".to_string());
+        }
+
+        match self.page_hashes {
+            Some(true) => args.push("/ph".to_string()),
+            Some(false) => args.push("/nph".to_string()),
+            None => {}
+        }
+
         args.extend(self.extra_args.iter().cloned());
 
         args.extend(self.sign_files.iter().map(|p| p.display().to_string()));
 
-        let command = duct::cmd(signtool, args)
-            .stderr_to_stdout()
-            .reader()
-            .context("running signtool")?;
-        {
-            let reader = BufReader::new(&command);
-            for line in reader.lines() {
-                warn!("{}", line?);
-            }
+        args
+    }
+
+    /// Resolve the arguments to `signtool verify` for verifying signed files.
+    fn verify_args(&self) -> Vec<String> {
+        let mut args = vec!["verify".to_string(), "/pa".to_string(), "/all".to_string()];
+
+        if self.verbose {
+            args.push("/v".to_string());
         }
 
-        let output = command
-            .try_wait()?
-            .ok_or_else(|| anyhow!("unable to wait on command"))?;
-        if output.status.success() {
-            Ok(())
+        args.extend(self.sign_files.iter().map(|p| p.display().to_string()));
+
+        args
+    }
+
+    /// Resolve the arguments of each signtool invocation, in order.
+    fn invocations(&self) -> Vec<Vec<String>> {
+        let mut res = if self.dual_sign {
+            vec![
+                self.sign_args("SHA1", self.append_signature),
+                self.sign_args("SHA256", true),
+            ]
         } else {
-            Err(anyhow!("error running signtool"))
+            vec![self.sign_args(&self.file_digest_algorithm, self.append_signature)]
+        };
+
+        if self.verify {
+            res.push(self.verify_args());
         }
+
+        res
+    }
+
+    /// Run `signtool sign` with requested options.
+    ///
+    /// With dual signing, signtool is invoked once per signature. With
+    /// verification, `signtool verify` is invoked afterwards.
+    pub fn run(&self) -> Result<()> {
+        let signtool = find_signtool().context("locating signtool.exe")?;
+
+        for args in self.invocations() {
+            let action = args[0].clone();
+
+            let command = duct::cmd(&signtool, args)
+                .stderr_to_stdout()
+                .reader()
+                .context("running signtool")?;
+            {
+                let reader = BufReader::new(&command);
+                for line in reader.lines() {
+                    warn!("{}", line?);
+                }
+            }
+
+            let output = command
+                .try_wait()?
+                .ok_or_else(|| anyhow!("unable to wait on command"))?;
+            if !output.status.success() {
+                return Err(anyhow!("error running signtool {}", action));
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -263,6 +368,81 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_invocations() {
+        let mut c = FileBasedCodeSigningCertificate::new("cert.pfx");
+        c.set_password("pw");
+
+        let mut sign = SigntoolSign::new(c.into());
+        sign.timestamp_server(TimestampServer::Rfc3161(
+            "http://timestamp.example.com".to_string(),
+            "SHA256".to_string(),
+        ))
+        .sign_file("app.exe");
+
+        assert_eq!(
+            sign.invocations(),
+            vec![vec![
+                "sign",
+                "/f",
+                "cert.pfx",
+                "/p",
+                "pw",
+                "/fd",
+                "SHA256",
+                "/tr",
+                "http://timestamp.example.com",
+                "/td",
+                "SHA256",
+                "app.exe"
+            ]]
+        );
+
+        sign.dual_sign().page_hashes(true).verify();
+
+        assert_eq!(
+            sign.invocations(),
+            vec![
+                vec![
+                    "sign",
+                    "/f",
+                    "cert.pfx",
+                    "/p",
+                    "pw",
+                    "/fd",
+                    "SHA1",
+                    "/tr",
+                    "http://timestamp.example.com",
+                    "/td",
+                    "SHA1",
+                    "/ph",
+                    "app.exe"
+                ],
+                vec![
+                    "sign",
+                    "/f",
+                    "cert.pfx",
+                    "/p",
+                    "pw",
+                    "/fd",
+                    "SHA256",
+                    "/tr",
+                    "http://timestamp.example.com",
+                    "/td",
+                    "SHA256",
+                    "/as",
+                    "/ph",
+                    "app.exe"
+                ],
+                vec!["verify", "/pa", "/all", "app.exe"],
+            ]
+        );
+
+        let mut sign = sign.clone_settings();
+        sign.append_signature().sign_file("other.exe");
+        assert!(sign.invocations()[0].contains(&"/as".to_string()));
+    }
+
     #[test]
     fn test_sign_executable() -> Result<()> {
         if cfg!(target_family = "unix") {
@@ -0,0 +1,188 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    starlark::{
+        values::{
+            error::{RuntimeError, UnsupportedOperation, ValueError},
+            none::NoneType,
+            Immutable, TypedValue, Value, ValueResult,
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::optional_str_arg,
+    tugger_common::artifact_name::{ArtifactKind, ArtifactNamingPolicy},
+};
+
+fn error_context<F, T>(label: &str, f: F) -> Result<T, ValueError>
+where
+    F: FnOnce() -> anyhow::Result<T>,
+{
+    f().map_err(|e| {
+        ValueError::Runtime(RuntimeError {
+            code: "TUGGER_ARTIFACT_NAMING",
+            message: format!("{:?}", e),
+            label: label.to_string(),
+        })
+    })
+}
+
+pub struct ArtifactNamingPolicyValue {
+    pub inner: ArtifactNamingPolicy,
+}
+
+impl TypedValue for ArtifactNamingPolicyValue {
+    type Holder = Immutable<ArtifactNamingPolicyValue>;
+    const TYPE: &'static str = "ArtifactNamingPolicy";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        Ok(match attribute {
+            "version" => Value::from(self.inner.version().to_string()),
+            "package_version" => Value::from(self.inner.version().to_package_version()),
+            "is_prerelease" => Value::from(self.inner.version().is_prerelease()),
+            _ => {
+                return Err(ValueError::OperationNotSupported {
+                    op: UnsupportedOperation::GetAttr(attribute.to_string()),
+                    left: Self::TYPE.to_string(),
+                    right: None,
+                })
+            }
+        })
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(matches!(
+            attribute,
+            "version" | "package_version" | "is_prerelease"
+        ))
+    }
+}
+
+impl ArtifactNamingPolicyValue {
+    fn new_from_args(
+        name: String,
+        version: String,
+        platform: String,
+        template: Value,
+        channel: Value,
+        abi: Value,
+    ) -> ValueResult {
+        const LABEL: &str = "ArtifactNamingPolicy()";
+
+        let template = optional_str_arg("template", &template)?;
+        let channel = optional_str_arg("channel", &channel)?;
+        let abi = optional_str_arg("abi", &abi)?;
+
+        let inner = error_context(LABEL, || {
+            let mut policy = ArtifactNamingPolicy::new(
+                template
+                    .as_deref()
+                    .unwrap_or(ArtifactNamingPolicy::DEFAULT_TEMPLATE),
+                &name,
+                &version,
+                &platform,
+            )?;
+
+            if let Some(channel) = &channel {
+                policy.set_channel(channel)?;
+            }
+            if let Some(abi) = &abi {
+                policy.set_abi(abi)?;
+            }
+
+            Ok(policy)
+        })?;
+
+        Ok(Value::new(Self { inner }))
+    }
+
+    fn filename(&self, kind: String) -> ValueResult {
+        const LABEL: &str = "ArtifactNamingPolicy.filename()";
+
+        let kind = error_context(LABEL, || ArtifactKind::try_from(kind.as_str()))?;
+
+        Ok(Value::from(self.inner.render(kind)))
+    }
+}
+
+starlark_module! { artifact_naming_module =>
+    #[allow(non_snake_case)]
+    ArtifactNamingPolicy(
+        name: String,
+        version: String,
+        platform: String,
+        template = NoneType::None,
+        channel = NoneType::None,
+        abi = NoneType::None
+    ) {
+        ArtifactNamingPolicyValue::new_from_args(name, version, platform, template, channel, abi)
+    }
+
+    ArtifactNamingPolicy.filename(this, kind: String) {
+        let this = this.downcast_ref::<ArtifactNamingPolicyValue>().unwrap();
+        this.filename(kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {crate::starlark::testutil::*, anyhow::Result};
+
+    #[test]
+    fn new() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval(
+            "policy = ArtifactNamingPolicy('myapp', '1.0-RC1', 'x86_64-pc-windows-msvc', channel = 'beta')",
+        )?;
+
+        assert_eq!(env.eval("policy.version")?.to_string(), "1.0rc1");
+        assert_eq!(env.eval("policy.package_version")?.to_string(), "1.0~rc1");
+        assert!(env.eval("policy.is_prerelease")?.to_bool());
+        assert_eq!(
+            env.eval("policy.filename('msi')")?.to_string(),
+            "myapp-1.0rc1-beta-x86_64-pc-windows-msvc.msi"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn template() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval(
+            "policy = ArtifactNamingPolicy('myapp', '2.0', 'amd64', template = '{name}_{version}_{platform}{ext}')",
+        )?;
+
+        assert_eq!(
+            env.eval("policy.filename('deb')")?.to_string(),
+            "myapp_2.0_amd64.deb"
+        );
+        assert!(env.eval("policy.filename('apk')").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        assert!(env
+            .eval("ArtifactNamingPolicy('myapp', 'latest', 'linux')")
+            .is_err());
+        assert!(env
+            .eval("ArtifactNamingPolicy('myapp', '1.0', 'linux', template = '{name}-{arch}')")
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -21,15 +21,16 @@ use {
             starlark_signature_extraction, starlark_signatures,
         },
     },
-    starlark_dialect_build_targets::required_type_arg,
+    starlark_dialect_build_targets::{optional_bool_arg, required_type_arg},
     std::{
         fmt::{Display, Formatter},
         path::{Path, PathBuf},
         sync::{Arc, Mutex},
+        time::Duration,
     },
     tugger_code_signing::{
-        SignableCandidate, SignedOutput, Signer, SigningCertificate, SigningDestination,
-        SigningError,
+        AppleNotarizer, SignableCandidate, SignedOutput, Signer, SigningCertificate,
+        SigningDestination, SigningError, WindowsSigningOptions,
     },
 };
 
@@ -210,6 +211,56 @@ impl CodeSignerValue {
         })
     }
 
+    fn set_windows_signing_options(
+        &self,
+        dual_sign: bool,
+        append_signature: bool,
+        page_hashes: Value,
+        verify: bool,
+    ) -> ValueResult {
+        let page_hashes = optional_bool_arg("page_hashes", &page_hashes)?;
+
+        let mut signer = self.signer("set_windows_signing_options()")?;
+
+        signer.windows_signing_options(WindowsSigningOptions {
+            dual_sign,
+            append_signature,
+            page_hashes,
+            verify,
+        });
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    fn set_apple_notarization_api_key(&self, path: String, wait_limit_seconds: i64) -> ValueResult {
+        let label = "set_apple_notarization_api_key()";
+
+        let mut signer = self.signer(label)?;
+
+        error_context(label, || {
+            let wait_limit = u64::try_from(wait_limit_seconds)
+                .map_err(|_| anyhow!("wait_limit_seconds must not be negative"))?;
+
+            let mut notarizer = AppleNotarizer::from_api_key(&path)?;
+            notarizer.wait_limit(Duration::from_secs(wait_limit));
+            signer.apple_notarizer(notarizer);
+
+            Ok(Value::new(NoneType::None))
+        })
+    }
+
+    fn notarize(&self, path: String) -> ValueResult {
+        let label = "notarize()";
+
+        let signer = self.signer(label)?;
+
+        error_context(label, || {
+            let notarization = signer.notarize_path(&path)?;
+
+            Ok(Value::from(notarization.stapled))
+        })
+    }
+
     fn set_signing_callback(&mut self, func: Value) -> ValueResult {
         required_type_arg("func", "function", &func)?;
 
@@ -621,6 +672,27 @@ starlark_module! { code_signing_module =>
         this.set_time_stamp_server(url)
     }
 
+    CodeSigner.set_windows_signing_options(
+        this,
+        dual_sign: bool = false,
+        append_signature: bool = false,
+        page_hashes = NoneType::None,
+        verify: bool = false
+    ) {
+        let this = this.downcast_ref::<CodeSignerValue>().unwrap();
+        this.set_windows_signing_options(dual_sign, append_signature, page_hashes, verify)
+    }
+
+    CodeSigner.set_apple_notarization_api_key(this, path: String, wait_limit_seconds: i64 = 600) {
+        let this = this.downcast_ref::<CodeSignerValue>().unwrap();
+        this.set_apple_notarization_api_key(path, wait_limit_seconds)
+    }
+
+    CodeSigner.notarize(this, path: String) {
+        let this = this.downcast_ref::<CodeSignerValue>().unwrap();
+        this.notarize(path)
+    }
+
     CodeSigner.set_signing_callback(this, func) {
         let mut this = this.downcast_mut::<CodeSignerValue>().unwrap().unwrap();
         this.set_signing_callback(func)
@@ -826,6 +898,31 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn set_windows_signing_options() -> Result<()> {
+        let mut env = env_with_pfx_signer()?;
+
+        env.eval("signer.set_windows_signing_options(dual_sign = True, page_hashes = False, verify = True)")?;
+        env.eval("signer.set_windows_signing_options()")?;
+        assert!(env
+            .eval("signer.set_windows_signing_options(page_hashes = 'yes')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn notarize_without_api_key() -> Result<()> {
+        let mut env = env_with_pfx_signer()?;
+
+        assert!(env.eval("signer.notarize('app.zip')").is_err());
+        assert!(env
+            .eval("signer.set_apple_notarization_api_key('missing.json')")
+            .is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn set_signing_callback() -> Result<()> {
         let mut env = env_with_pfx_signer()?;
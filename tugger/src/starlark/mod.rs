@@ -9,6 +9,7 @@ Tugger.
 */
 
 pub mod apple_universal_binary;
+pub mod artifact_naming;
 pub mod code_signing;
 pub mod file_content;
 pub mod file_manifest;
@@ -126,6 +127,7 @@ pub fn register_starlark_dialect(
     type_values: &mut TypeValues,
 ) -> Result<(), EnvironmentError> {
     apple_universal_binary::apple_universal_binary_module(env, type_values);
+    artifact_naming::artifact_naming_module(env, type_values);
     code_signing::code_signing_module(env, type_values);
     file_content::file_content_module(env, type_values);
     file_manifest::file_manifest_module(env, type_values);